@@ -8,6 +8,12 @@ const envSchema = z.object({
   APP_BASE_URL: z.string().url(),
   API_BASE_URL: z.string().url(),
   DAEMON_HMAC_SKEW_SECONDS: z.coerce.number().int().positive().default(60),
+  /**
+   * Longest a server transfer may run. Sent to the source daemon with
+   * the push; a transfer still `running` well past it is failed by the
+   * transfer janitor.
+   */
+  TRANSFER_TIMEOUT_MINUTES: z.coerce.number().int().positive().default(30),
   LOG_LEVEL: z.enum(["debug", "info", "warn", "error"]).default("info"),
 })
 
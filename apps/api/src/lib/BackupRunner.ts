@@ -1,4 +1,4 @@
-import { eq } from "drizzle-orm"
+import { and, eq, inArray } from "drizzle-orm"
 
 import type { Db } from "@workspace/db/client.types"
 import { backupsTable } from "@workspace/db/schema/backups"
@@ -6,6 +6,61 @@ import { nodesTable } from "@workspace/db/schema/nodes"
 import { serversTable } from "@workspace/db/schema/servers"
 
 import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSupports } from "@/lib/NodeCapabilities"
+import { mintActingToken } from "@/lib/ServerAccess"
+
+type ServerRow = typeof serversTable.$inferSelect
+
+/** Mirrors the daemon's backup.Quota. */
+export type BackupQuota = {
+  bytes: number
+  rotate: boolean
+  protected: string[]
+}
+
+/**
+ * The daemon-side quota for a server's next backup, or undefined when
+ * the server has none. Locked backups are passed as protected so a
+ * "rotate" policy never deletes them.
+ */
+export const backupQuota = async (
+  db: Db,
+  server: ServerRow
+): Promise<BackupQuota | undefined> => {
+  if (server.backupQuotaMb === null) return undefined
+  const locked = await db
+    .select({ name: backupsTable.name })
+    .from(backupsTable)
+    .where(
+      and(eq(backupsTable.serverId, server.id), eq(backupsTable.locked, true))
+    )
+  return {
+    bytes: server.backupQuotaMb * 1024 * 1024,
+    rotate: server.backupQuotaPolicy === "rotate",
+    protected: locked.map((b) => b.name),
+  }
+}
+
+/**
+ * Drop the rows for backups the daemon rotated out to stay under the
+ * server's quota.
+ */
+export const removeRotatedBackups = async (
+  db: Db,
+  serverId: string,
+  rotated: string[] | undefined
+): Promise<void> => {
+  if (rotated === undefined || rotated.length === 0) return
+  await db
+    .delete(backupsTable)
+    .where(
+      and(
+        eq(backupsTable.serverId, serverId),
+        eq(backupsTable.storage, "local"),
+        inArray(backupsTable.name, rotated)
+      )
+    )
+}
 
 /**
  * Insert a `pending` backup row and kick off the daemon call in the
@@ -22,8 +77,33 @@ export const runBackup = async (params: {
   db: Db
   serverId: string
   name: string
+  /** Also have the daemon record per-file checksums in a manifest. */
+  manifest?: boolean
+  /** Who asked for the backup; recorded in the daemon's metadata. */
+  source?: "manual" | "schedule" | "plugin"
+  /** Paths to leave out; see the daemon's backup.CreateOptions. */
+  ignore?: string[]
+  /**
+   * Name of an earlier backup to make this one incremental on; only
+   * files changed since it are archived. Needs `backup.incremental`.
+   */
+  base?: string
+  /**
+   * The user a manual backup is made for; the daemon checks their
+   * scopes. Without one the call is the panel's own (schedules).
+   */
+  user?: { id: string; isAdmin?: boolean | null }
 }): Promise<string | null> => {
-  const { db, serverId, name } = params
+  const {
+    db,
+    serverId,
+    name,
+    manifest = false,
+    source = "manual",
+    ignore = [],
+    base,
+    user,
+  } = params
   const row = (
     await db
       .select({ server: serversTable, node: nodesTable })
@@ -34,16 +114,29 @@ export const runBackup = async (params: {
   )[0]
   if (row === undefined) return null
   if (row.node.daemonPublicKey === null) return null
+  const userToken =
+    user === undefined
+      ? undefined
+      : await mintActingToken(db, user, serverId, {
+          id: row.node.id,
+          daemonPublicKey: row.node.daemonPublicKey,
+        })
 
   const [created] = await db
     .insert(backupsTable)
-    .values({ serverId, name, storage: "local", state: "pending" })
+    .values({ serverId, name, storage: "local", state: "pending", base })
     .returning({ id: backupsTable.id })
   if (created === undefined) return null
 
   const baseUrl = `${row.node.scheme}://${row.node.fqdn}:${row.node.daemonPort}`
   const nodeId = row.node.id
   const signingKeyHex = row.node.daemonPublicKey
+  // Older daemons ignore unknown body fields, so the backup still runs;
+  // it just has no manifest to show.
+  const withManifest = manifest && nodeSupports(row.node, "backup.manifest")
+  // Likewise an older daemon can't enforce a quota; the backup runs
+  // unchecked rather than not at all.
+  const quota = await backupQuota(db, row.server)
   const backupId = created.id
   void (async () => {
     try {
@@ -53,12 +146,22 @@ export const runBackup = async (params: {
         signingKeyHex,
         method: "POST",
         path: `/api/servers/${serverId}/backups?op=create`,
-        body: { name },
+        body: { name, manifest: withManifest, source, ignore, quota, base },
+        userToken,
+        asPanel: userToken === undefined,
       })
       if (!resp.ok) {
         await db
           .update(backupsTable)
-          .set({ state: "failed", failureCode: "backups.create_failed" })
+          .set({
+            state: "failed",
+            failureCode:
+              resp.status === 507
+                ? "backups.quota_exceeded"
+                : resp.status === 409
+                  ? "backups.base_missing"
+                  : "backups.create_failed",
+          })
           .where(eq(backupsTable.id, backupId))
         return
       }
@@ -66,13 +169,19 @@ export const runBackup = async (params: {
         name: string
         bytes: number
         sha256: string
+        base?: string
+        rotated?: string[]
       }
+      await removeRotatedBackups(db, serverId, result.rotated)
+      // A node on the dedup engine makes every backup whole, whatever
+      // base was asked for.
       await db
         .update(backupsTable)
         .set({
           state: "ready",
           bytes: result.bytes,
           sha256: result.sha256,
+          base: result.base ?? null,
           completedAt: new Date(),
         })
         .where(eq(backupsTable.id, backupId))
@@ -5,6 +5,11 @@ import { createHmac } from "node:crypto"
  * The daemon verifies the request with the same per-node signing key
  * stored in `nodes.daemon_public_key`, so a leaked daemon URL can't be
  * called from anywhere but a process that holds the key.
+ *
+ * `userToken` (see `mintUserContextToken`) names the user the request
+ * acts for; the daemon then also checks that user's scopes. Routes a
+ * user can reach refuse a call without one unless `asPanel` marks it as
+ * the panel's own work, e.g. a schedule.
  */
 export const callDaemon = async (params: {
   baseUrl: string
@@ -13,7 +18,11 @@ export const callDaemon = async (params: {
   method: "GET" | "POST" | "PUT" | "DELETE"
   path: string
   body?: unknown
+  /** Sent as-is instead of `body`, e.g. an upload passed straight through. */
+  stream?: { body: ReadableStream<Uint8Array>; contentType: string }
   signal?: AbortSignal
+  userToken?: string
+  asPanel?: boolean
 }): Promise<Response> => {
   const ts = Math.floor(Date.now() / 1000).toString()
   const sig = createHmac("sha256", Buffer.from(params.signingKeyHex, "hex"))
@@ -24,6 +33,12 @@ export const callDaemon = async (params: {
     "X-Stellar-Timestamp": ts,
     Authorization: `Bearer ${sig}`,
   }
+  if (params.userToken !== undefined) {
+    headers["X-Stellar-User-Token"] = params.userToken
+  }
+  if (params.asPanel === true) {
+    headers["X-Stellar-Acting"] = "panel"
+  }
   const init: RequestInit = {
     method: params.method,
     headers,
@@ -33,5 +48,11 @@ export const callDaemon = async (params: {
     headers["Content-Type"] = "application/json"
     init.body = JSON.stringify(params.body)
   }
+  if (params.stream !== undefined) {
+    headers["Content-Type"] = params.stream.contentType
+    init.body = params.stream.body
+    // Node's fetch refuses a streamed body without it.
+    Object.assign(init, { duplex: "half" })
+  }
   return fetch(`${params.baseUrl.replace(/\/$/, "")}${params.path}`, init)
 }
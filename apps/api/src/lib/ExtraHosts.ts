@@ -0,0 +1,90 @@
+import { isIP } from "node:net"
+
+import { eq, inArray } from "drizzle-orm"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import {
+  nodeAllocationsTable,
+  nodesTable,
+} from "@workspace/db/schema/nodes"
+import {
+  serversTable,
+  type ServerExtraHost,
+} from "@workspace/db/schema/servers"
+
+const hostnameSchema = z
+  .string()
+  .max(253)
+  .regex(
+    /^[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?(\.[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?)*$/
+  )
+
+export const extraHostsSchema = z
+  .array(
+    z.union([
+      z.object({
+        hostname: hostnameSchema,
+        ip: z.union([z.ipv4(), z.ipv6(), z.literal("host-gateway")]),
+      }),
+      z.object({ hostname: hostnameSchema, serverId: z.string().uuid() }),
+    ])
+  )
+  .max(32)
+
+/**
+ * Turns a server's extra hosts into the `{hostname, ip}` pairs the
+ * daemon writes to /etc/hosts. An entry naming another server resolves
+ * to where that server listens as seen from `nodeId`: Docker's
+ * "host-gateway" when both run on the same node, otherwise the other
+ * server's primary allocation IP, or its node's address when the
+ * allocation binds every interface. Entries that can't be resolved (the
+ * server is gone, its node only has a hostname) are dropped.
+ */
+export const resolveExtraHosts = async (
+  db: Db,
+  nodeId: string,
+  hosts: ServerExtraHost[]
+): Promise<{ hostname: string; ip: string }[]> => {
+  const serverIds = hosts.flatMap((h) => ("serverId" in h ? [h.serverId] : []))
+  const targets =
+    serverIds.length === 0
+      ? []
+      : await db
+          .select({
+            id: serversTable.id,
+            nodeId: serversTable.nodeId,
+            fqdn: nodesTable.fqdn,
+            ip: nodeAllocationsTable.ip,
+          })
+          .from(serversTable)
+          .innerJoin(nodesTable, eq(nodesTable.id, serversTable.nodeId))
+          .leftJoin(
+            nodeAllocationsTable,
+            eq(nodeAllocationsTable.id, serversTable.primaryAllocationId)
+          )
+          .where(inArray(serversTable.id, serverIds))
+  const byId = new Map(targets.map((t) => [t.id, t]))
+  const resolved: { hostname: string; ip: string }[] = []
+  for (const h of hosts) {
+    if (!("serverId" in h)) {
+      resolved.push({ hostname: h.hostname, ip: h.ip })
+      continue
+    }
+    const target = byId.get(h.serverId)
+    if (target === undefined) continue
+    if (target.nodeId === nodeId) {
+      resolved.push({ hostname: h.hostname, ip: "host-gateway" })
+    } else if (
+      target.ip !== null &&
+      isIP(target.ip) !== 0 &&
+      target.ip !== "0.0.0.0" &&
+      target.ip !== "::"
+    ) {
+      resolved.push({ hostname: h.hostname, ip: target.ip })
+    } else if (isIP(target.fqdn) !== 0) {
+      resolved.push({ hostname: h.hostname, ip: target.fqdn })
+    }
+  }
+  return resolved
+}
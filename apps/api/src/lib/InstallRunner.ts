@@ -1,4 +1,7 @@
+import { randomUUID } from "node:crypto"
+
 import { asc, eq } from "drizzle-orm"
+import { z } from "zod"
 
 import type { Db } from "@workspace/db/client.types"
 import { installLogsTable } from "@workspace/db/schema/install"
@@ -8,9 +11,13 @@ import {
   serversTable,
 } from "@workspace/db/schema/servers"
 import { blueprintsTable } from "@workspace/db/schema/blueprints"
-import type { Blueprint } from "@workspace/shared/blueprint.types"
+import type {
+  Blueprint,
+  BlueprintInstallInterpreter,
+} from "@workspace/shared/blueprint.types"
 
 import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSupports } from "@/lib/NodeCapabilities"
 
 type JobState = "pending" | "running" | "succeeded" | "failed"
 
@@ -25,6 +32,47 @@ export type InstallJob = {
 
 const MAX_LOG_LINES = 5_000
 
+/** Finished rollouts kept for late readers of their progress. */
+const MAX_FINISHED_ROLLOUTS = 20
+
+export type RolloutServer = {
+  serverId: string
+  state: "queued" | JobState
+  exitCode: number | null
+}
+
+type RolloutListener = (s: RolloutServer) => void
+
+/**
+ * A bulk reinstall: `servers` are reinstalled in order, at most
+ * `concurrency` at a time. In memory like the jobs it starts.
+ */
+export type Rollout = {
+  id: string
+  startedBy: string
+  concurrency: number
+  startedAt: Date
+  finishedAt: Date | null
+  servers: RolloutServer[]
+}
+
+/**
+ * A template archive to install from instead of the blueprint's script.
+ * The daemon tells the format from the URL's file name, so only names
+ * it can extract are accepted; the checksum is what makes a template
+ * safe to reuse.
+ */
+const ARCHIVE_URL = /^https?:\/\/[^/]+\/.*\.(tar\.gz|tgz|tar|zip)$/i
+
+export const installArchiveSchema = z.object({
+  url: z
+    .string()
+    .url()
+    .refine((u) => ARCHIVE_URL.test(u.split(/[?#]/)[0] ?? "")),
+  checksum: z.string().regex(/^(sha256:[0-9a-f]{64}|sha1:[0-9a-f]{40})$/i),
+  mirrors: z.array(z.string().url()).max(5).optional(),
+})
+
 /**
  * In-memory tracker for install jobs. Persisting to a `install_jobs`
  * table is a phase-2 enhancement; for now if the API process dies mid-
@@ -34,6 +82,10 @@ const MAX_LOG_LINES = 5_000
  */
 export class InstallRunner {
   private readonly jobs = new Map<string, InstallJob>()
+  /** Settles when the server's current install has finished. */
+  private readonly finished = new Map<string, Promise<void>>()
+  private readonly rollouts = new Map<string, Rollout>()
+  private readonly listeners = new Map<string, Set<RolloutListener>>()
 
   public constructor(private readonly db: Db) {}
 
@@ -79,7 +131,7 @@ export class InstallRunner {
       log: [],
     }
     this.jobs.set(serverId, job)
-    void this.run(job).catch((err: unknown) => {
+    const done = this.run(job).catch((err: unknown) => {
       job.state = "failed"
       job.finishedAt = new Date()
       job.log.push({
@@ -87,9 +139,107 @@ export class InstallRunner {
         line: `install runner: ${err instanceof Error ? err.message : String(err)}`,
       })
     })
+    this.finished.set(serverId, done)
     return job
   }
 
+  /**
+   * Reinstall `serverIds` rolling-fashion: `concurrency` at a time, the
+   * next starting as soon as one finishes. Returns at once; the rollout
+   * carries on without whoever started it, and `watchRollout` follows
+   * its progress. A failed install doesn't stop the rest.
+   */
+  public startRollout(params: {
+    serverIds: string[]
+    concurrency: number
+    startedBy: string
+  }): Rollout {
+    const rollout: Rollout = {
+      id: randomUUID(),
+      startedBy: params.startedBy,
+      concurrency: params.concurrency,
+      startedAt: new Date(),
+      finishedAt: null,
+      servers: params.serverIds.map((serverId) => ({
+        serverId,
+        state: "queued",
+        exitCode: null,
+      })),
+    }
+    this.rollouts.set(rollout.id, rollout)
+    let next = 0
+    const worker = async (): Promise<void> => {
+      while (next < rollout.servers.length) {
+        const entry = rollout.servers[next++]
+        if (entry === undefined) return
+        await this.reinstall(rollout, entry)
+      }
+    }
+    const workers = Array.from(
+      { length: Math.min(params.concurrency, rollout.servers.length) },
+      () => worker()
+    )
+    void Promise.all(workers).finally(() => {
+      rollout.finishedAt = new Date()
+      this.listeners.delete(rollout.id)
+      this.pruneRollouts()
+    })
+    return rollout
+  }
+
+  public getRollout(id: string): Rollout | undefined {
+    return this.rollouts.get(id)
+  }
+
+  /**
+   * Calls `fn` with each server whose state changes until the rollout
+   * finishes. Returns the unsubscribe.
+   */
+  public watchRollout(id: string, fn: RolloutListener): () => void {
+    let set = this.listeners.get(id)
+    if (set === undefined) {
+      set = new Set()
+      this.listeners.set(id, set)
+    }
+    set.add(fn)
+    return () => {
+      this.listeners.get(id)?.delete(fn)
+    }
+  }
+
+  private async reinstall(
+    rollout: Rollout,
+    entry: RolloutServer
+  ): Promise<void> {
+    const notify = (): void => {
+      for (const fn of this.listeners.get(rollout.id) ?? []) fn({ ...entry })
+    }
+    entry.state = "running"
+    notify()
+    try {
+      await this.db
+        .update(serversTable)
+        .set({ installState: "pending", updatedAt: new Date() })
+        .where(eq(serversTable.id, entry.serverId))
+      const job = await this.enqueue(entry.serverId)
+      await this.finished.get(entry.serverId)
+      entry.state = job.state === "succeeded" ? "succeeded" : "failed"
+      entry.exitCode = job.exitCode
+    } catch {
+      entry.state = "failed"
+    }
+    notify()
+  }
+
+  private pruneRollouts(): void {
+    const done = [...this.rollouts.values()].filter(
+      (r) => r.finishedAt !== null
+    )
+    for (const r of done.slice(0, -MAX_FINISHED_ROLLOUTS)) {
+      this.rollouts.delete(r.id)
+    }
+  }
+
   private async run(job: InstallJob): Promise<void> {
     job.state = "running"
     // Wipe any stale log rows from a prior install so the live read
@@ -129,6 +279,7 @@ export class InstallRunner {
       installImage: string
       installEntrypoint: string
       installScript: string
+      installInterpreter: BlueprintInstallInterpreter | null
     }
 
     // Resolve install env: blueprint variable defaults overlaid with the
@@ -148,6 +299,14 @@ export class InstallRunner {
     }
     env["SERVER_MEMORY"] = String(server.memoryLimitMb)
 
+    // A template install skips the script; checked again here in case
+    // the node's daemon was downgraded since the server was created.
+    if (
+      server.installArchive !== null &&
+      !nodeSupports(node, "install.archive")
+    ) {
+      throw new Error("node's daemon can't install from an archive")
+    }
     const baseUrl = `${node.scheme}://${node.fqdn}:${node.daemonPort}`
     const resp = await callDaemon({
       baseUrl,
@@ -160,14 +319,23 @@ export class InstallRunner {
         entrypoint: blueprintData.installEntrypoint,
         script: blueprintData.installScript,
         environment: env,
+        ...(blueprintData.installInterpreter !== null
+          ? { interpreter: blueprintData.installInterpreter }
+          : {}),
+        ...(server.installArchive !== null
+          ? { archive: server.installArchive }
+          : {}),
       },
     })
     if (!resp.ok || resp.body === null) {
+      // The daemon's error says why, e.g. an interpreter the node
+      // doesn't allow.
+      const detail = resp.ok ? resp.statusText : await resp.text()
       job.state = "failed"
       job.finishedAt = new Date()
       job.log.push({
         stream: "stderr",
-        line: `daemon install: ${resp.status} ${resp.statusText}`,
+        line: `daemon install: ${resp.status} ${detail}`,
       })
       return
     }
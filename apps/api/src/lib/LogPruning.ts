@@ -0,0 +1,77 @@
+import { eq } from "drizzle-orm"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import { blueprintsTable } from "@workspace/db/schema/blueprints"
+import {
+  serversTable,
+  type ServerLogPruning,
+} from "@workspace/db/schema/servers"
+import { logPruningSchema } from "@workspace/shared/blueprint"
+import type { BlueprintLogPruning } from "@workspace/shared/blueprint.types"
+
+export const serverLogPruningSchema = logPruningSchema.extend({
+  enabled: z.boolean().optional(),
+})
+
+/**
+ * The body of the daemon's `POST /logs/prune`. Limits the policy leaves
+ * out go as 0, which the daemon reads as "no limit".
+ */
+export type LogPruneRequest = {
+  globs?: string[]
+  maxAgeDays: number
+  maxTotalMb: number
+  dryRun: boolean
+}
+
+/**
+ * A server's effective log pruning policy: the blueprint's with the
+ * server's fields on top, or null when neither sets one or the server
+ * turns it off.
+ */
+export const effectiveLogPruning = (
+  blueprint: BlueprintLogPruning | null,
+  server: ServerLogPruning | null
+): BlueprintLogPruning | null => {
+  if (server?.enabled === false) return null
+  if (blueprint === null && server === null) return null
+  return {
+    globs: server?.globs ?? blueprint?.globs,
+    maxAgeDays: server?.maxAgeDays ?? blueprint?.maxAgeDays,
+    maxTotalMb: server?.maxTotalMb ?? blueprint?.maxTotalMb,
+  }
+}
+
+/** Loads the server's effective policy; see `effectiveLogPruning`. */
+export const loadLogPruning = async (
+  db: Db,
+  serverId: string
+): Promise<BlueprintLogPruning | null> => {
+  const row = (
+    await db
+      .select({
+        blueprint: blueprintsTable.logPruning,
+        server: serversTable.logPruning,
+      })
+      .from(serversTable)
+      .innerJoin(
+        blueprintsTable,
+        eq(blueprintsTable.id, serversTable.blueprintId)
+      )
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  if (row === undefined) return null
+  return effectiveLogPruning(row.blueprint, row.server)
+}
+
+export const logPruneRequest = (
+  policy: BlueprintLogPruning,
+  dryRun: boolean
+): LogPruneRequest => ({
+  globs: policy.globs,
+  maxAgeDays: policy.maxAgeDays ?? 0,
+  maxTotalMb: policy.maxTotalMb ?? 0,
+  dryRun,
+})
@@ -0,0 +1,94 @@
+import type { NodeCapabilities } from "@workspace/db/schema/nodes"
+import { ApiException } from "@workspace/shared/errors"
+
+/**
+ * Feature strings the daemon reports in its heartbeat. Mirrors the
+ * constants in the daemon's `capability` package; only features the
+ * API checks before calling a daemon endpoint are listed.
+ */
+export type NodeFeature =
+  | "allocations.sync"
+  | "backup.import"
+  | "backup.incremental"
+  | "backup.manifest"
+  | "backup.metadata"
+  | "backup.quota"
+  | "command.capture"
+  | "config.apply"
+  | "config.effective"
+  | "console.sse"
+  | "environment"
+  | "files.activity"
+  | "files.ndjson"
+  | "files.pull.verify"
+  | "files.scan"
+  | "files.structured"
+  | "idle.shutdown"
+  | "images.pull"
+  | "install.archive"
+  | "log.levels"
+  | "log.tail"
+  | "logs.prune"
+  | "macros"
+  | "network.hosts"
+  | "players"
+  | "power.countdown"
+  | "power.pause"
+  | "power.queue"
+  | "power.validate"
+  | "proxy"
+  | "self.update"
+  | "servers.states"
+  | "servers.states.changes"
+  | "sftp.paths"
+  | "sftp.sessions"
+  | "system.queues"
+  | "transfer.ignore"
+  | "wake.connect"
+
+/**
+ * Whether the node's daemon advertised `feature`. A node with no
+ * capability report (never heartbeated, or a daemon that predates
+ * reporting) only supports the baseline, so this is false.
+ */
+export const nodeSupports = (
+  node: { capabilities: NodeCapabilities | null },
+  feature: NodeFeature
+): boolean => node.capabilities?.features.includes(feature) ?? false
+
+/**
+ * Throws `nodes.unsupported_feature` (409) instead of letting the call
+ * reach a daemon that would 404 or misread it. The fix on the operator
+ * side is upgrading the node's daemon.
+ */
+export const assertNodeSupports = (
+  node: { capabilities: NodeCapabilities | null },
+  feature: NodeFeature
+): void => {
+  if (!nodeSupports(node, feature)) {
+    throw new ApiException("nodes.unsupported_feature", { status: 409 })
+  }
+}
+
+/**
+ * Whether the node's daemon speaks transfer mode `mode` (an archive
+ * format or verification scheme). Unreported nodes speak none beyond
+ * the baseline tar.gz push.
+ */
+export const nodeSpeaksTransferMode = (
+  node: { capabilities: NodeCapabilities | null },
+  mode: string
+): boolean => node.capabilities?.transferModes.includes(mode) ?? false
+
+/** Schedule task actions every daemon build has been able to run. */
+const baselineScheduleTasks = ["power", "command", "backup"]
+
+/**
+ * Whether the node's daemon can carry out a schedule task `action`.
+ * Unreported nodes get the baseline set.
+ */
+export const nodeRunsTask = (
+  node: { capabilities: NodeCapabilities | null },
+  action: string
+): boolean =>
+  (node.capabilities?.scheduleTasks ?? baselineScheduleTasks).includes(action)
@@ -0,0 +1,196 @@
+import { and, eq, inArray } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { nodesTable } from "@workspace/db/schema/nodes"
+import { serversTable } from "@workspace/db/schema/servers"
+import {
+  serverReplicationsTable,
+  serverTransfersTable,
+  type ServerReplicationRow,
+} from "@workspace/db/schema/transfers"
+
+import type { Env } from "@/env"
+import {
+  mintTransferToken,
+  pushServer,
+  transferWindowOpen,
+} from "@/lib/TransferRunner"
+
+const TICK_MS = 60_000
+
+/** Servers with a sync in flight, so ticks and manual syncs don't overlap. */
+const syncing = new Set<string>()
+
+/**
+ * Runs `fn` with the server's sync slot held, so no sync starts while
+ * it does; resolves to "replication.busy" without running it when a
+ * sync is already in flight.
+ */
+export const withoutSync = async <T>(
+  serverId: string,
+  fn: () => Promise<T>
+): Promise<T | "replication.busy"> => {
+  if (syncing.has(serverId)) return "replication.busy"
+  syncing.add(serverId)
+  try {
+    return await fn()
+  } finally {
+    syncing.delete(serverId)
+  }
+}
+
+/**
+ * Push the server's files to its standby node once and record the
+ * outcome on the replication row. The standby's copy is the server's
+ * live tree there, so each sync is a delta push against the last one.
+ * Resolves to null on success or the error code; a sync already in
+ * flight resolves to "replication.busy" without starting another.
+ */
+export const syncReplication = async (params: {
+  db: Db
+  env: Env
+  replication: ServerReplicationRow
+}): Promise<string | null> => {
+  const { db, env, replication } = params
+  const { serverId } = replication
+  if (syncing.has(serverId)) return "replication.busy"
+  syncing.add(serverId)
+  try {
+    await db
+      .update(serverReplicationsTable)
+      .set({ lastAttemptAt: new Date() })
+      .where(eq(serverReplicationsTable.serverId, serverId))
+    const server = (
+      await db
+        .select({ nodeId: serversTable.nodeId })
+        .from(serversTable)
+        .where(eq(serversTable.id, serverId))
+        .limit(1)
+    )[0]
+    const nodes = await db
+      .select()
+      .from(nodesTable)
+      .where(
+        inArray(
+          nodesTable.id,
+          [server?.nodeId, replication.standbyNodeId].filter(
+            (id): id is string => id !== undefined
+          )
+        )
+      )
+    const sourceNode = nodes.find((n) => n.id === server?.nodeId)
+    const standbyNode = nodes.find((n) => n.id === replication.standbyNodeId)
+    let code: string | null = "nodes.unreachable"
+    if (
+      sourceNode !== undefined &&
+      standbyNode !== undefined &&
+      standbyNode.daemonPublicKey !== null
+    ) {
+      code = await pushServer({
+        env,
+        serverId,
+        sourceNode,
+        targetNode: standbyNode,
+        token: mintTransferToken(standbyNode.daemonPublicKey, serverId),
+        urgent: false,
+      }).catch((err: unknown) =>
+        err instanceof Error ? err.message : "unknown"
+      )
+    }
+    await db
+      .update(serverReplicationsTable)
+      .set(
+        code === null
+          ? { lastSyncedAt: new Date(), lastError: null }
+          : { lastError: code }
+      )
+      .where(eq(serverReplicationsTable.serverId, serverId))
+    return code
+  } finally {
+    syncing.delete(serverId)
+  }
+}
+
+/**
+ * Keeps standby copies fresh: every replication whose last attempt is
+ * older than its interval is synced, as long as both nodes' transfer
+ * windows are open and the server isn't in the middle of a transfer.
+ * Syncs run one after another so a tick never floods a node.
+ */
+export class ReplicationQueue {
+  private timer: ReturnType<typeof setTimeout> | null = null
+
+  public constructor(
+    private readonly db: Db,
+    private readonly env: Env
+  ) {}
+
+  public start(): void {
+    if (this.timer !== null) return
+    this.tick()
+  }
+
+  public stop(): void {
+    if (this.timer !== null) {
+      clearTimeout(this.timer)
+      this.timer = null
+    }
+  }
+
+  private tick(): void {
+    void this.runDue()
+      .catch((err: unknown) => {
+        console.error("replication queue tick failed:", err)
+      })
+      .finally(() => {
+        this.timer = setTimeout(() => this.tick(), TICK_MS)
+      })
+  }
+
+  private async runDue(): Promise<void> {
+    const now = Date.now()
+    const due = (
+      await this.db
+        .select({
+          replication: serverReplicationsTable,
+          sourceNodeId: serversTable.nodeId,
+        })
+        .from(serverReplicationsTable)
+        .innerJoin(
+          serversTable,
+          eq(serversTable.id, serverReplicationsTable.serverId)
+        )
+    ).filter(
+      ({ replication: r }) =>
+        r.lastAttemptAt === null ||
+        now - r.lastAttemptAt.getTime() >= r.intervalMinutes * 60_000
+    )
+    if (due.length === 0) return
+    const nodes = new Map(
+      (await this.db.select().from(nodesTable)).map((n) => [n.id, n])
+    )
+    for (const { replication, sourceNodeId } of due) {
+      const source = nodes.get(sourceNodeId)
+      const standby = nodes.get(replication.standbyNodeId)
+      if (source === undefined || standby === undefined) continue
+      if (!transferWindowOpen(source) || !transferWindowOpen(standby)) continue
+      // A transfer moves the server's files itself; sync after it.
+      const transferring = await this.db
+        .select({ id: serverTransfersTable.id })
+        .from(serverTransfersTable)
+        .where(
+          and(
+            eq(serverTransfersTable.serverId, replication.serverId),
+            inArray(serverTransfersTable.status, [
+              "pending",
+              "queued",
+              "running",
+            ])
+          )
+        )
+        .limit(1)
+      if (transferring.length > 0) continue
+      await syncReplication({ db: this.db, env: this.env, replication })
+    }
+  }
+}
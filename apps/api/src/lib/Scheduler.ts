@@ -10,6 +10,8 @@ import { serversTable } from "@workspace/db/schema/servers"
 
 import { runBackup } from "@/lib/BackupRunner"
 import { callDaemon } from "@/lib/DaemonHttp"
+import { loadLogPruning, logPruneRequest } from "@/lib/LogPruning"
+import { nodeRunsTask, nodeSupports } from "@/lib/NodeCapabilities"
 import type { StatusCache } from "@/lib/StatusCache"
 
 type ScheduleTaskRow = (typeof scheduleTasksTable)["$inferSelect"]
@@ -125,7 +127,7 @@ const nextFiring = (raw: string, after: Date): Date | null => {
  * process; the tick is short and cheap (a single SELECT) so there's no
  * coordination needed between API replicas — at most you'll get a
  * duplicate task in a multi-replica deploy, which is acceptable for the
- * power/command/backup actions we support.
+ * power/command/backup/prune actions we support.
  */
 export class Scheduler {
   private timer: ReturnType<typeof setTimeout> | null = null
@@ -237,6 +239,24 @@ export class Scheduler {
           setTimeout(resolve, task.delaySeconds * 1000)
         )
       }
+      if (!nodeRunsTask(server.node, task.action)) {
+        console.warn(
+          `schedule task ${task.id} skipped: node can't run "${task.action}"`
+        )
+        continue
+      }
+      // Restarting without the warning players were promised is worse
+      // than not restarting.
+      if (
+        task.action === "power" &&
+        task.payload?.["countdownSeconds"] !== undefined &&
+        !nodeSupports(server.node, "power.countdown")
+      ) {
+        console.warn(
+          `schedule task ${task.id} skipped: node can't run restart countdowns`
+        )
+        continue
+      }
       try {
         await this.runTask(
           baseUrl,
@@ -271,13 +291,27 @@ export class Scheduler {
         ) {
           return
         }
+        // `restart` with countdownSeconds (and optionally
+        // countdownMessage) warns players in the console first.
+        const seconds = Number(payload["countdownSeconds"] ?? 0)
+        const countdown =
+          action === "restart" && seconds > 0
+            ? {
+                seconds,
+                message:
+                  typeof payload["countdownMessage"] === "string"
+                    ? payload["countdownMessage"]
+                    : undefined,
+              }
+            : undefined
         await callDaemon({
           baseUrl,
           nodeId,
           signingKeyHex,
+          asPanel: true,
           method: "POST",
           path: `/api/servers/${serverId}/power`,
-          body: { action },
+          body: { action, countdown },
         })
         return
       }
@@ -288,12 +322,26 @@ export class Scheduler {
           baseUrl,
           nodeId,
           signingKeyHex,
+          asPanel: true,
           method: "POST",
           path: `/api/servers/${serverId}/command`,
           body: { line },
         })
         return
       }
+      case "macro": {
+        const name = String(payload["name"] ?? "")
+        if (name === "") return
+        await callDaemon({
+          baseUrl,
+          nodeId,
+          signingKeyHex,
+          asPanel: true,
+          method: "POST",
+          path: `/api/servers/${serverId}/commands/macro/${encodeURIComponent(name)}`,
+        })
+        return
+      }
       case "backup": {
         const explicit = typeof payload["name"] === "string" ? payload["name"] : ""
         const stamp = new Date()
@@ -301,7 +349,63 @@ export class Scheduler {
           .replace(/:/g, "-")
           .replace(/\./g, "-")
         const name = explicit !== "" ? explicit : "scheduled-" + stamp
-        await runBackup({ db: this.db, serverId, name })
+        await runBackup({ db: this.db, serverId, name, source: "schedule" })
+        return
+      }
+      case "update": {
+        // Pull-and-extract: `url` (required), `checksum`, `mirrors` and
+        // `clean` (comma-separated), `directory`, and `restart`, which
+        // defaults to true so players aren't left on half-swapped files.
+        const url = String(payload["url"] ?? "")
+        if (url === "") return
+        const list = (key: string): string[] =>
+          String(payload[key] ?? "")
+            .split(",")
+            .map((s) => s.trim())
+            .filter((s) => s !== "")
+        const res = await callDaemon({
+          baseUrl,
+          nodeId,
+          signingKeyHex,
+          asPanel: true,
+          method: "POST",
+          path: `/api/servers/${serverId}/update`,
+          body: {
+            url,
+            checksum: String(payload["checksum"] ?? ""),
+            mirrors: list("mirrors"),
+            clean: list("clean"),
+            directory: String(payload["directory"] ?? ""),
+            restart: payload["restart"] !== false,
+          },
+        })
+        if (!res.ok) {
+          throw new Error(`daemon answered ${res.status}: ${await res.text()}`)
+        }
+        return
+      }
+      case "prune_logs": {
+        // The policy lives on the blueprint and the server, not the
+        // task, so editing it doesn't mean editing every schedule.
+        const policy = await loadLogPruning(this.db, serverId)
+        if (policy === null) {
+          console.warn(
+            `schedule task ${task.id} skipped: log pruning is off for server ${serverId}`
+          )
+          return
+        }
+        const res = await callDaemon({
+          baseUrl,
+          nodeId,
+          signingKeyHex,
+          asPanel: true,
+          method: "POST",
+          path: `/api/servers/${serverId}/logs/prune`,
+          body: logPruneRequest(policy, false),
+        })
+        if (!res.ok) {
+          throw new Error(`daemon answered ${res.status}: ${await res.text()}`)
+        }
         return
       }
       default:
@@ -0,0 +1,230 @@
+import { and, eq } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { nodesTable } from "@workspace/db/schema/nodes"
+import { serverSubusersTable, serversTable } from "@workspace/db/schema/servers"
+import type { ErrorCode } from "@workspace/shared/error-codes"
+import { ApiException } from "@workspace/shared/errors"
+import type { DaemonJwtScope } from "@workspace/shared/jwt.types"
+
+import { callDaemon } from "@/lib/DaemonHttp"
+import { assertNodeSupports, type NodeFeature } from "@/lib/NodeCapabilities"
+import { mintUserContextToken } from "@/lib/Tokens"
+
+export type ServerAccess = {
+  server: typeof serversTable.$inferSelect
+  role: "owner" | "subuser" | "admin"
+  permissions: string[]
+  /** Directories a subuser's SFTP access is limited to; null = all. */
+  sftpPaths: string[] | null
+}
+
+type AccessUser = { id: string; isAdmin?: boolean | null }
+
+/**
+ * How `user` may reach the server: as a panel admin, its owner, or a
+ * subuser with their stored permissions. Throws `servers.not_found` or
+ * `permissions.denied` otherwise.
+ */
+export const loadServerAccess = async (
+  db: Db,
+  user: AccessUser,
+  serverId: string
+): Promise<ServerAccess> => {
+  const server = (
+    await db
+      .select()
+      .from(serversTable)
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  if (server === undefined) {
+    throw new ApiException("servers.not_found", { status: 404 })
+  }
+  if (user.isAdmin === true) {
+    return { server, role: "admin", permissions: [], sftpPaths: null }
+  }
+  if (server.ownerId === user.id) {
+    return { server, role: "owner", permissions: [], sftpPaths: null }
+  }
+  const sub = (
+    await db
+      .select()
+      .from(serverSubusersTable)
+      .where(
+        and(
+          eq(serverSubusersTable.serverId, serverId),
+          eq(serverSubusersTable.userId, user.id)
+        )
+      )
+      .limit(1)
+  )[0]
+  if (sub === undefined) {
+    throw new ApiException("permissions.denied", { status: 403 })
+  }
+  return {
+    server,
+    role: "subuser",
+    permissions: sub.permissions,
+    sftpPaths: sub.sftpPaths,
+  }
+}
+
+/**
+ * Throws unless `user` is a panel admin or owns the server; subusers
+ * are refused. Returns the server.
+ */
+export const assertOwnerOrAdmin = async (
+  db: Db,
+  user: AccessUser,
+  serverId: string
+) => {
+  const server = (
+    await db
+      .select()
+      .from(serversTable)
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  if (server === undefined) {
+    throw new ApiException("servers.not_found", { status: 404 })
+  }
+  if (user.isAdmin !== true && server.ownerId !== user.id) {
+    throw new ApiException("permissions.denied", { status: 403 })
+  }
+  return server
+}
+
+// Scopes a user can hold on a server. The transfer scopes are the
+// daemons' own and never go to a user.
+const userScopes: DaemonJwtScope[] = [
+  "console.read",
+  "console.write",
+  "stats.read",
+  "control.start",
+  "control.stop",
+  "control.restart",
+  "files.read",
+  "files.write",
+  "files.delete",
+  "sftp",
+  "backup.read",
+  "backup.write",
+  "admin.exec",
+]
+
+/**
+ * Everything `access` lets its user do on the server, as daemon
+ * scopes: all of them for an admin, all but `admin.exec` for the
+ * owner, and a subuser's stored permissions.
+ */
+export const actingScopes = (access: ServerAccess): DaemonJwtScope[] => {
+  if (access.role === "admin") return userScopes
+  const held = new Set(access.permissions)
+  return userScopes.filter(
+    (s) => s !== "admin.exec" && (access.role === "owner" || held.has(s))
+  )
+}
+
+/**
+ * The user context for a signed call made on `user`'s behalf, minted
+ * with their real permissions on the server so the daemon's own scope
+ * check decides.
+ */
+export const mintActingToken = async (
+  db: Db,
+  user: AccessUser,
+  serverId: string,
+  node: { id: string; daemonPublicKey: string }
+): Promise<string> => {
+  const access = await loadServerAccess(db, user, serverId)
+  return mintUserContextToken({
+    signingKeyHex: node.daemonPublicKey,
+    userId: user.id,
+    serverId,
+    nodeId: node.id,
+    scope: actingScopes(access),
+  })
+}
+
+export const loadServerNode = async (db: Db, serverId: string) => {
+  const row = (
+    await db
+      .select({ server: serversTable, node: nodesTable })
+      .from(serversTable)
+      .innerJoin(nodesTable, eq(nodesTable.id, serversTable.nodeId))
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  if (row === undefined) {
+    throw new ApiException("servers.not_found", { status: 404 })
+  }
+  return row
+}
+
+type ServerDaemonCall = {
+  db: Db
+  serverId: string
+  actor: AccessUser | "panel"
+  feature?: NodeFeature
+  method: "GET" | "POST" | "PUT" | "DELETE"
+  path: string
+  body?: unknown
+  signal?: AbortSignal
+}
+
+/**
+ * Signs `/api/servers/:id<path>` to the server's node. `actor` is the
+ * user the call is made for, whose token the daemon checks, or "panel"
+ * for the panel's own work (schedules). Throws `nodes.unreachable` for
+ * an unpaired node and `nodes.unsupported_feature` when `feature` is
+ * missing; the daemon's response is returned as-is.
+ */
+export const callServerDaemon = async (
+  params: ServerDaemonCall
+): Promise<Response> => {
+  const { node } = await loadServerNode(params.db, params.serverId)
+  if (node.daemonPublicKey === null) {
+    throw new ApiException("nodes.unreachable", { status: 503 })
+  }
+  if (params.feature !== undefined) assertNodeSupports(node, params.feature)
+  return callDaemon({
+    baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+    nodeId: node.id,
+    signingKeyHex: node.daemonPublicKey,
+    method: params.method,
+    path: `/api/servers/${params.serverId}${params.path}`,
+    body: params.body,
+    signal: params.signal,
+    asPanel: params.actor === "panel",
+    userToken:
+      params.actor === "panel"
+        ? undefined
+        : await mintActingToken(params.db, params.actor, params.serverId, {
+            id: node.id,
+            daemonPublicKey: node.daemonPublicKey,
+          }),
+  })
+}
+
+/**
+ * callServerDaemon for routes that relay the daemon's answer: a non-2xx
+ * response throws. Daemon error codes listed in `errors` map to the
+ * panel's, keeping the status; otherwise a 404 or 409 keeps its status
+ * and anything else is a 502.
+ */
+export const forwardServerDaemon = async (
+  params: ServerDaemonCall & { errors?: Record<string, ErrorCode> }
+): Promise<Response> => {
+  const resp = await callServerDaemon(params)
+  if (resp.ok) return resp
+  const err = (await resp.json().catch(() => null)) as {
+    error?: { code?: string }
+  } | null
+  const code = params.errors?.[err?.error?.code ?? ""]
+  if (code !== undefined) throw new ApiException(code, { status: resp.status })
+  if (resp.status === 404 || resp.status === 409) {
+    throw new ApiException("internal.unexpected", { status: resp.status })
+  }
+  throw new ApiException("internal.unexpected", { status: 502 })
+}
@@ -0,0 +1,205 @@
+import { inArray } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { nodesTable } from "@workspace/db/schema/nodes"
+import type { serversTable } from "@workspace/db/schema/servers"
+
+import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSupports, type NodeFeature } from "@/lib/NodeCapabilities"
+
+type ServerRow = typeof serversTable.$inferSelect
+type NodeRow = typeof nodesTable.$inferSelect
+
+/** One server's entry in the daemon's state reports. */
+export type ServerState = {
+  process_state: string
+  flags: string[]
+  uptime_ms: number
+  cpu_absolute: number
+  memory_bytes: number
+  memory_limit_bytes: number
+  /** Absent on daemons that predate drop counting. */
+  console_dropped?: number
+}
+
+/** Daemon answer to `GET /api/servers/states/changes`. */
+type StateChanges = {
+  cursor: string
+  reset: boolean
+  servers: Record<string, ServerState>
+}
+
+/** How long a state poll is held open, on the daemon and here. */
+const POLL_WAIT_SECONDS = 25
+
+type HostingNode = {
+  node: NodeRow & { daemonPublicKey: string }
+  rows: ServerRow[]
+}
+
+/**
+ * The nodes hosting `rows` whose daemon speaks `feature`, each with the
+ * rows it hosts.
+ */
+const nodesFor = async (
+  db: Db,
+  rows: readonly ServerRow[],
+  feature: NodeFeature
+): Promise<HostingNode[]> => {
+  const byNode = new Map<string, ServerRow[]>()
+  for (const row of rows) {
+    byNode.set(row.nodeId, [...(byNode.get(row.nodeId) ?? []), row])
+  }
+  if (byNode.size === 0) return []
+  const nodes = await db
+    .select()
+    .from(nodesTable)
+    .where(inArray(nodesTable.id, [...byNode.keys()]))
+  return nodes.flatMap((node) =>
+    node.daemonPublicKey !== null && nodeSupports(node, feature)
+      ? [
+          {
+            node: { ...node, daemonPublicKey: node.daemonPublicKey },
+            rows: byNode.get(node.id) ?? [],
+          },
+        ]
+      : []
+  )
+}
+
+const nodeBaseUrl = (node: NodeRow) =>
+  `${node.scheme}://${node.fqdn}:${node.daemonPort}`
+
+/**
+ * Copy the reported states of `rows` into `out`. Suspension lives in
+ * the panel, so the daemon can't flag it; it's added here.
+ */
+const collect = (
+  out: Record<string, ServerState>,
+  rows: readonly ServerRow[],
+  reported: Record<string, ServerState>
+) => {
+  for (const row of rows) {
+    const state = reported[row.id]
+    if (state === undefined) continue
+    const flags = row.suspended ? [...state.flags, "suspended"] : state.flags
+    out[row.id] = { ...state, flags }
+  }
+}
+
+/**
+ * Live state of `rows`, one daemon call per node. Servers on nodes that
+ * can't report (unreachable, or a daemon without `servers.states`) are
+ * left out.
+ */
+export const fetchServerStates = async (
+  db: Db,
+  rows: readonly ServerRow[]
+): Promise<Record<string, ServerState>> => {
+  const states: Record<string, ServerState> = {}
+  const nodes = await nodesFor(db, rows, "servers.states")
+  await Promise.all(
+    nodes.map(async ({ node, rows: hosted }) => {
+      const resp = await callDaemon({
+        baseUrl: nodeBaseUrl(node),
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: "/api/servers/states",
+        signal: AbortSignal.timeout(5_000),
+      }).catch(() => null)
+      if (resp === null || !resp.ok) return
+      const reported: Record<string, ServerState> | null = await resp
+        .json()
+        .catch(() => null)
+      if (reported !== null) collect(states, hosted, reported)
+    })
+  )
+  return states
+}
+
+/**
+ * Opaque cursor for {@link pollServerStates}: each node's own feed
+ * cursor, keyed by node id.
+ */
+const decodeCursor = (cursor: string | undefined): Record<string, string> => {
+  if (cursor === undefined || cursor === "") return {}
+  try {
+    const parsed: unknown = JSON.parse(
+      Buffer.from(cursor, "base64url").toString("utf8")
+    )
+    if (typeof parsed !== "object" || parsed === null) return {}
+    return Object.fromEntries(
+      Object.entries(parsed).filter(
+        (e): e is [string, string] => typeof e[1] === "string"
+      )
+    )
+  } catch {
+    return {}
+  }
+}
+
+const encodeCursor = (cursors: Record<string, string>): string =>
+  Buffer.from(JSON.stringify(cursors), "utf8").toString("base64url")
+
+/**
+ * Long-poll the nodes hosting `rows` for state changes. Resolves once
+ * any node reports a change (the other polls are dropped and pick up
+ * from their old cursor next time), or when the wait runs out with
+ * nothing to report. Without a cursor every server's state comes back
+ * at once. The returned cursor goes into the next call.
+ */
+export const pollServerStates = async (params: {
+  db: Db
+  rows: readonly ServerRow[]
+  cursor: string | undefined
+  signal: AbortSignal
+}): Promise<{ cursor: string; states: Record<string, ServerState> }> => {
+  const { db, rows, signal } = params
+  const cursors = decodeCursor(params.cursor)
+  const states: Record<string, ServerState> = {}
+  const nodes = await nodesFor(db, rows, "servers.states.changes")
+  const done = new AbortController()
+  const abort = () => done.abort()
+  signal.addEventListener("abort", abort)
+  const timer = setTimeout(abort, (POLL_WAIT_SECONDS + 10) * 1000)
+  try {
+    if (nodes.length === 0) {
+      // Nothing to watch; hold the poll anyway so callers don't spin.
+      await new Promise<void>((resolve) => {
+        const wait = setTimeout(resolve, POLL_WAIT_SECONDS * 1000)
+        done.signal.addEventListener("abort", () => {
+          clearTimeout(wait)
+          resolve()
+        })
+      })
+    }
+    await Promise.all(
+      nodes.map(async ({ node, rows: hosted }) => {
+        const since = encodeURIComponent(cursors[node.id] ?? "")
+        const resp = await callDaemon({
+          baseUrl: nodeBaseUrl(node),
+          nodeId: node.id,
+          signingKeyHex: node.daemonPublicKey,
+          method: "GET",
+          path: `/api/servers/states/changes?since=${since}&wait=${POLL_WAIT_SECONDS}`,
+          signal: done.signal,
+        }).catch(() => null)
+        if (resp === null || !resp.ok) return
+        const changes: StateChanges | null = await resp
+          .json()
+          .catch(() => null)
+        if (changes === null) return
+        cursors[node.id] = changes.cursor
+        collect(states, hosted, changes.servers)
+        if (changes.reset || Object.keys(changes.servers).length > 0) {
+          done.abort()
+        }
+      })
+    )
+  } finally {
+    clearTimeout(timer)
+    signal.removeEventListener("abort", abort)
+  }
+  return { cursor: encodeCursor(cursors), states }
+}
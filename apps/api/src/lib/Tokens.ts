@@ -22,6 +22,7 @@ export const mintDaemonToken = (params: {
   nodeId: string
   scope: DaemonJwtScope[]
   ttlSeconds: number
+  paths?: string[]
 }): { token: string; expiresAt: Date } => {
   const now = Math.floor(Date.now() / 1000)
   const claims: DaemonJwtClaims = {
@@ -33,6 +34,9 @@ export const mintDaemonToken = (params: {
     exp: now + params.ttlSeconds,
     jti: randomBytes(8).toString("hex"),
   }
+  if (params.paths !== undefined && params.paths.length > 0) {
+    claims.paths = params.paths
+  }
   const header = base64url(Buffer.from(JSON.stringify({ alg: "HS256", typ: "JWT" })))
   const payload = base64url(Buffer.from(JSON.stringify(claims)))
   const signingInput = `${header}.${payload}`
@@ -46,3 +50,17 @@ export const mintDaemonToken = (params: {
     expiresAt: new Date(claims.exp * 1000),
   }
 }
+
+/**
+ * Mint the user context for a signed daemon call made on a user's
+ * behalf (`callDaemon({ userToken })`). `scope` is what the user may do
+ * on the server; the daemon refuses the call if the route's scope isn't
+ * in it, even though the request itself carries the node signature.
+ */
+export const mintUserContextToken = (params: {
+  signingKeyHex: string
+  userId: string
+  serverId: string
+  nodeId: string
+  scope: DaemonJwtScope[]
+}): string => mintDaemonToken({ ...params, ttlSeconds: 60 }).token
@@ -0,0 +1,74 @@
+import { and, eq, lt, or } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { serverTransfersTable } from "@workspace/db/schema/transfers"
+
+const TICK_MS = 60_000
+
+/**
+ * Slack on top of the transfer timeout before a row is given up on: the
+ * source daemon reports its own timeout, and that report deserves the
+ * chance to land first.
+ */
+const GRACE_MS = 2 * 60_000
+
+/**
+ * Releases the per-server transfer lock when a transfer's partners stop
+ * reporting. A row `running` for longer than the timeout means the
+ * source daemon (or the API process that was waiting on it) died
+ * mid-transfer, and one still `pending` that long was never started;
+ * either is marked failed so a new transfer can start. Queued rows wait
+ * for their window however long it takes. The daemons time out and
+ * clean up their own side.
+ */
+export class TransferJanitor {
+  private timer: ReturnType<typeof setTimeout> | null = null
+
+  public constructor(
+    private readonly db: Db,
+    private readonly timeoutMinutes: number
+  ) {}
+
+  public start(): void {
+    if (this.timer !== null) return
+    this.tick()
+  }
+
+  public stop(): void {
+    if (this.timer !== null) {
+      clearTimeout(this.timer)
+      this.timer = null
+    }
+  }
+
+  private tick(): void {
+    void this.sweep()
+      .catch((err: unknown) => {
+        console.error("transfer janitor tick failed:", err)
+      })
+      .finally(() => {
+        this.timer = setTimeout(() => this.tick(), TICK_MS)
+      })
+  }
+
+  private async sweep(): Promise<void> {
+    const cutoff = new Date(
+      Date.now() - this.timeoutMinutes * 60_000 - GRACE_MS
+    )
+    await this.db
+      .update(serverTransfersTable)
+      .set({ status: "failed", error: "timeout", completedAt: new Date() })
+      .where(
+        or(
+          and(
+            eq(serverTransfersTable.status, "running"),
+            lt(serverTransfersTable.startedAt, cutoff)
+          ),
+          and(
+            eq(serverTransfersTable.status, "pending"),
+            lt(serverTransfersTable.createdAt, cutoff)
+          )
+        )
+      )
+  }
+}
@@ -0,0 +1,65 @@
+import { asc, eq } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { nodesTable } from "@workspace/db/schema/nodes"
+import { serverTransfersTable } from "@workspace/db/schema/transfers"
+
+import type { Env } from "@/env"
+import { runTransfer, transferWindowOpen } from "@/lib/TransferRunner"
+
+const TICK_MS = 30_000
+
+/**
+ * Starts queued transfers once both nodes' transfer windows are open,
+ * going by each node's last heartbeat. Oldest first; a node that turns
+ * the push away anyway sends the row back to the queue.
+ */
+export class TransferQueue {
+  private timer: ReturnType<typeof setTimeout> | null = null
+
+  public constructor(
+    private readonly db: Db,
+    private readonly env: Env
+  ) {}
+
+  public start(): void {
+    if (this.timer !== null) return
+    this.tick()
+  }
+
+  public stop(): void {
+    if (this.timer !== null) {
+      clearTimeout(this.timer)
+      this.timer = null
+    }
+  }
+
+  private tick(): void {
+    void this.runDue()
+      .catch((err: unknown) => {
+        console.error("transfer queue tick failed:", err)
+      })
+      .finally(() => {
+        this.timer = setTimeout(() => this.tick(), TICK_MS)
+      })
+  }
+
+  private async runDue(): Promise<void> {
+    const queued = await this.db
+      .select()
+      .from(serverTransfersTable)
+      .where(eq(serverTransfersTable.status, "queued"))
+      .orderBy(asc(serverTransfersTable.createdAt))
+    if (queued.length === 0) return
+    const nodes = new Map(
+      (await this.db.select().from(nodesTable)).map((n) => [n.id, n])
+    )
+    for (const transfer of queued) {
+      const source = nodes.get(transfer.sourceNodeId)
+      const target = nodes.get(transfer.targetNodeId)
+      if (source === undefined || target === undefined) continue
+      if (!transferWindowOpen(source) || !transferWindowOpen(target)) continue
+      await runTransfer({ db: this.db, env: this.env, transfer })
+    }
+  }
+}
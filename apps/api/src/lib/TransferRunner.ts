@@ -0,0 +1,330 @@
+import { createHmac } from "node:crypto"
+
+import { and, eq, inArray } from "drizzle-orm"
+
+import type { Db } from "@workspace/db/client.types"
+import { nodeAllocationsTable, nodesTable } from "@workspace/db/schema/nodes"
+import {
+  serverAllocationsTable,
+  serversTable,
+} from "@workspace/db/schema/servers"
+import {
+  serverReplicationsTable,
+  serverTransfersTable,
+  type ServerTransferRow,
+} from "@workspace/db/schema/transfers"
+
+import type { Env } from "@/env"
+import { writeAudit } from "@/lib/Audit"
+import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSpeaksTransferMode } from "@/lib/NodeCapabilities"
+
+type NodeRow = typeof nodesTable.$inferSelect
+
+/** Error codes a daemon answers with when its transfer window is shut. */
+const WINDOW_CLOSED = [
+  "transfer.outside_window",
+  "transfer.target_outside_window",
+]
+
+/**
+ * Whether the node's last heartbeat put it inside its transfer window.
+ * Nodes that don't report one are always open.
+ */
+export const transferWindowOpen = (node: NodeRow): boolean =>
+  node.transferPolicy?.windowOpen !== false
+
+/**
+ * Whether the archive can go from `source` to `target` protected: both
+ * daemons encrypt transfers, or the target is served over HTTPS.
+ */
+export const transferSecure = (source: NodeRow, target: NodeRow): boolean =>
+  encrypted(source, target) || target.scheme === "https"
+
+const encrypted = (source: NodeRow, target: NodeRow): boolean =>
+  nodeSpeaksTransferMode(source, "aes-256-gcm") &&
+  nodeSpeaksTransferMode(target, "aes-256-gcm")
+
+type Tx = Parameters<Parameters<Db["transaction"]>[0]>[0]
+
+/**
+ * Moves the server to `nodeId` inside `tx`, the switchover shared by a
+ * finished transfer and a standby promotion: its allocations are freed,
+ * `allocationId` becomes its primary and only one, and its replication
+ * goes — the standby's allocation was freed with the rest, and a standby
+ * for the new node is set up again if wanted.
+ */
+export const switchServerNode = async (
+  tx: Tx,
+  params: { serverId: string; nodeId: string; allocationId: string }
+): Promise<void> => {
+  const { serverId, nodeId, allocationId } = params
+  await tx
+    .update(nodeAllocationsTable)
+    .set({ serverId: null })
+    .where(eq(nodeAllocationsTable.serverId, serverId))
+  await tx
+    .delete(serverAllocationsTable)
+    .where(eq(serverAllocationsTable.serverId, serverId))
+  await tx
+    .update(nodeAllocationsTable)
+    .set({ serverId })
+    .where(eq(nodeAllocationsTable.id, allocationId))
+  await tx.insert(serverAllocationsTable).values({ serverId, allocationId })
+  await tx
+    .update(serversTable)
+    .set({
+      nodeId,
+      primaryAllocationId: allocationId,
+      status: "offline",
+      updatedAt: new Date(),
+    })
+    .where(eq(serversTable.id, serverId))
+  await tx
+    .delete(serverReplicationsTable)
+    .where(eq(serverReplicationsTable.serverId, serverId))
+}
+
+const hmac = (keyHex: string, message: string): Buffer =>
+  createHmac("sha256", Buffer.from(keyHex, "hex")).update(message).digest()
+
+/**
+ * The per-transfer archive key, sealed for the source. The key is
+ * derived from the target's signing key, so the target can re-derive
+ * it from the token alone; the source gets it XORed with a pad only its
+ * own signing key produces, so it never crosses the wire in the clear.
+ */
+const sealedTransferKey = (
+  sourceKeyHex: string,
+  targetKeyHex: string,
+  token: string
+): string => {
+  const key = hmac(targetKeyHex, `transfer-key|${token}`)
+  const pad = hmac(sourceKeyHex, `transfer-key-seal|${token}`)
+  return Buffer.from(key.map((b, i) => b ^ (pad[i] ?? 0))).toString("hex")
+}
+
+type DaemonError = { error?: { code?: string } }
+
+/**
+ * Mint the one-time HMAC token the source daemon presents to the
+ * target. Signed with the *target* node's key — only the target can
+ * verify, only the source can present.
+ */
+export const mintTransferToken = (
+  targetKeyHex: string,
+  serverId: string
+): { ts: number; token: string } => {
+  const ts = Math.floor(Date.now() / 1000)
+  return { ts, token: hmac(targetKeyHex, `${serverId}|${ts}`).toString("hex") }
+}
+
+/**
+ * Have the source daemon push the server's tree to the target's ingest
+ * endpoint and wait for it to finish. Resolves to null once the target
+ * has the files, or to the daemon's error code (the HTTP status when it
+ * sent none).
+ */
+export const pushServer = async (params: {
+  env: Env
+  serverId: string
+  sourceNode: NodeRow
+  targetNode: NodeRow
+  token: { ts: number; token: string }
+  urgent: boolean
+  ignore?: string[] | null
+}): Promise<string | null> => {
+  const { env, serverId, sourceNode, targetNode, token } = params
+  const sourceKey = sourceNode.daemonPublicKey
+  const targetKey = targetNode.daemonPublicKey
+  if (sourceKey === null || targetKey === null) return "nodes.unreachable"
+  const resp = await callDaemon({
+    baseUrl: `${sourceNode.scheme}://${sourceNode.fqdn}:${sourceNode.daemonPort}`,
+    nodeId: sourceNode.id,
+    signingKeyHex: sourceKey,
+    method: "POST",
+    path: `/api/servers/${serverId}/transfer/push`,
+    body: {
+      targetUrl: `${targetNode.scheme}://${targetNode.fqdn}:${targetNode.daemonPort}/api/servers/${serverId}/transfer/ingest`,
+      token: token.token,
+      timestamp: token.ts,
+      timeoutSeconds: env.TRANSFER_TIMEOUT_MINUTES * 60,
+      urgent: params.urgent,
+      ignore: params.ignore ?? undefined,
+      // Only files the target doesn't already hold go over the wire.
+      delta:
+        nodeSpeaksTransferMode(sourceNode, "delta") &&
+        nodeSpeaksTransferMode(targetNode, "delta"),
+      sealedKey: encrypted(sourceNode, targetNode)
+        ? sealedTransferKey(sourceKey, targetKey, token.token)
+        : undefined,
+    },
+  })
+  if (resp.ok) return null
+  const rejection: DaemonError | null = await resp.json().catch(() => null)
+  return rejection?.error?.code ?? String(resp.status)
+}
+
+const loadNode = async (db: Db, id: string): Promise<NodeRow | undefined> =>
+  (
+    await db
+      .select()
+      .from(nodesTable)
+      .where(eq(nodesTable.id, id))
+      .limit(1)
+  )[0]
+
+/**
+ * Start a pending or queued transfer: claim the row, mint a fresh
+ * one-time token (a queued transfer may start hours after it was asked
+ * for, long past the token's freshness window) and kick the source
+ * daemon to push to the target in the background. Once the push
+ * succeeds the server is switched over to the target. A node that turns
+ * the push away because its window is shut puts the row back in the
+ * queue for the TransferQueue to retry. The archive is encrypted when
+ * both daemons can; otherwise the target must be HTTPS.
+ */
+export const runTransfer = async (params: {
+  db: Db
+  env: Env
+  transfer: ServerTransferRow
+}): Promise<void> => {
+  const { db, env, transfer } = params
+  const { serverId } = transfer
+  const server = (
+    await db
+      .select()
+      .from(serversTable)
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  const sourceNode = await loadNode(db, transfer.sourceNodeId)
+  const targetNode = await loadNode(db, transfer.targetNodeId)
+  if (
+    server === undefined ||
+    sourceNode === undefined ||
+    sourceNode.daemonPublicKey === null ||
+    targetNode === undefined ||
+    targetNode.daemonPublicKey === null
+  ) {
+    await db
+      .update(serverTransfersTable)
+      .set({
+        status: "failed",
+        error: "nodes.unreachable",
+        completedAt: new Date(),
+      })
+      .where(eq(serverTransfersTable.id, transfer.id))
+    return
+  }
+  // A node may have been rolled back to a daemon that can't encrypt
+  // since the transfer was queued.
+  if (!transferSecure(sourceNode, targetNode)) {
+    await db
+      .update(serverTransfersTable)
+      .set({
+        status: "failed",
+        error: "transfers.insecure",
+        completedAt: new Date(),
+      })
+      .where(eq(serverTransfersTable.id, transfer.id))
+    return
+  }
+  const { ts, token: tokenHex } = mintTransferToken(
+    targetNode.daemonPublicKey,
+    serverId
+  )
+  const [claimed] = await db
+    .update(serverTransfersTable)
+    .set({
+      status: "running",
+      token: tokenHex,
+      error: null,
+      startedAt: new Date(),
+    })
+    .where(
+      and(
+        eq(serverTransfersTable.id, transfer.id),
+        inArray(serverTransfersTable.status, ["pending", "queued"])
+      )
+    )
+    .returning({ id: serverTransfersTable.id })
+  if (claimed === undefined) return
+
+  void (async () => {
+    try {
+      const code = await pushServer({
+        env,
+        serverId,
+        sourceNode,
+        targetNode,
+        token: { ts, token: tokenHex },
+        urgent: transfer.urgent,
+        ignore: transfer.ignore,
+      })
+      if (code !== null) {
+        if (WINDOW_CLOSED.includes(code)) {
+          // The heartbeat said open but the node disagrees (clock
+          // drift, a config change); wait for the next window.
+          await db
+            .update(serverTransfersTable)
+            .set({ status: "queued", error: code, startedAt: null })
+            .where(eq(serverTransfersTable.id, transfer.id))
+          return
+        }
+        await db
+          .update(serverTransfersTable)
+          .set({
+            status: "failed",
+            error: `source push: ${code}`,
+            completedAt: new Date(),
+          })
+          .where(eq(serverTransfersTable.id, transfer.id))
+        return
+      }
+      // Atomic switchover: free the old primary allocation, link
+      // the new one, retarget the server's nodeId. Skipped if the
+      // janitor already gave up on the transfer.
+      const switched = await db.transaction(async (tx) => {
+        const [completed] = await tx
+          .update(serverTransfersTable)
+          .set({ status: "completed", completedAt: new Date() })
+          .where(
+            and(
+              eq(serverTransfersTable.id, transfer.id),
+              eq(serverTransfersTable.status, "running")
+            )
+          )
+          .returning({ id: serverTransfersTable.id })
+        if (completed === undefined) return false
+        await switchServerNode(tx, {
+          serverId,
+          nodeId: transfer.targetNodeId,
+          allocationId: transfer.targetAllocationId,
+        })
+        return true
+      })
+      if (!switched) return
+      void writeAudit({
+        db,
+        actorId: null,
+        action: "servers.transferred",
+        targetType: "server",
+        targetId: serverId,
+        metadata: {
+          sourceNode: sourceNode.id,
+          targetNode: transfer.targetNodeId,
+        },
+      })
+    } catch (err) {
+      await db
+        .update(serverTransfersTable)
+        .set({
+          status: "failed",
+          error: err instanceof Error ? err.message : "unknown",
+          completedAt: new Date(),
+        })
+        .where(eq(serverTransfersTable.id, transfer.id))
+    }
+  })()
+}
@@ -0,0 +1,107 @@
+/**
+ * Checks a blueprint variable value against its pipe-separated `rules`
+ * string (the egg format: `required|string|max:20`, `nullable|integer`,
+ * `regex:/^.+\.jar$/`). Covers the rules the shipped blueprints use;
+ * unknown rules are ignored rather than failing a start. Returns a
+ * human-readable problem, or null when the value passes.
+ */
+export const checkVariableRules = (
+  rules: string,
+  value: string
+): string | null => {
+  const parsed = parseRules(rules)
+  const names = new Set(parsed.map((r) => r.name))
+  if (value === "") {
+    if (names.has("required")) return "is required"
+    return null
+  }
+  const numeric =
+    names.has("integer") || names.has("numeric") || names.has("number")
+  const size = numeric ? Number(value) : value.length
+  for (const { name, arg } of parsed) {
+    switch (name) {
+      case "integer":
+        if (!/^-?\d+$/.test(value)) return "must be a whole number"
+        break
+      case "numeric":
+      case "number":
+        if (!Number.isFinite(Number(value))) return "must be a number"
+        break
+      case "boolean":
+        if (!["true", "false", "1", "0"].includes(value)) {
+          return "must be true or false"
+        }
+        break
+      case "alpha_num":
+        if (!/^[A-Za-z0-9]+$/.test(value)) return "must be letters and digits"
+        break
+      case "max":
+        if (size > Number(arg)) return `must be at most ${arg}`
+        break
+      case "min":
+        if (size < Number(arg)) return `must be at least ${arg}`
+        break
+      case "between": {
+        const [lo, hi] = arg.split(",").map(Number)
+        if (lo !== undefined && hi !== undefined && (size < lo || size > hi)) {
+          return `must be between ${lo} and ${hi}`
+        }
+        break
+      }
+      case "in":
+        if (!arg.split(",").includes(value)) return `must be one of ${arg}`
+        break
+      case "regex": {
+        const re = toRegExp(arg)
+        if (re !== null && !re.test(value)) return `must match ${arg}`
+        break
+      }
+    }
+  }
+  return null
+}
+
+/**
+ * Splits on `|`, except inside a `regex:/.../flags` argument, whose
+ * pattern may itself contain pipes.
+ */
+const parseRules = (rules: string): { name: string; arg: string }[] => {
+  const out: { name: string; arg: string }[] = []
+  let rest = rules
+  while (rest !== "") {
+    let token: string
+    if (rest.startsWith("regex:/")) {
+      const end = findRegexEnd(rest, "regex:/".length)
+      token = rest.slice(0, end)
+      rest = rest.slice(end + 1)
+    } else {
+      const bar = rest.indexOf("|")
+      token = bar === -1 ? rest : rest.slice(0, bar)
+      rest = bar === -1 ? "" : rest.slice(bar + 1)
+    }
+    const colon = token.indexOf(":")
+    if (colon === -1) out.push({ name: token, arg: "" })
+    else out.push({ name: token.slice(0, colon), arg: token.slice(colon + 1) })
+  }
+  return out
+}
+
+/** Index just past `/flags` of the regex starting at `from`. */
+const findRegexEnd = (s: string, from: number): number => {
+  for (let i = s.indexOf("/", from); i !== -1; i = s.indexOf("/", i + 1)) {
+    if (s[i - 1] === "\\") continue
+    const m = /^[a-z]*(\||$)/.exec(s.slice(i + 1))
+    if (m !== null) return i + 1 + m[0].length - (m[1] === "|" ? 1 : 0)
+  }
+  return s.length
+}
+
+const toRegExp = (arg: string): RegExp | null => {
+  const m = /^\/(.*)\/([a-z]*)$/s.exec(arg)
+  if (m === null) return null
+  try {
+    return new RegExp(m[1] ?? "", (m[2] ?? "").replace(/[^gimsuy]/g, ""))
+  } catch {
+    return null
+  }
+}
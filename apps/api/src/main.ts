@@ -11,8 +11,11 @@ import { buildAuth } from "@/auth"
 import { loadEnv } from "@/env"
 import { errorToResponse } from "@/lib/Errors"
 import { InstallRunner } from "@/lib/InstallRunner"
+import { ReplicationQueue } from "@/lib/ReplicationQueue"
 import { Scheduler } from "@/lib/Scheduler"
 import { StatusCache } from "@/lib/StatusCache"
+import { TransferJanitor } from "@/lib/TransferJanitor"
+import { TransferQueue } from "@/lib/TransferQueue"
 import { requestIdMiddleware, type ApiVariables } from "@/middleware/RequestId"
 import { buildActivityRoute } from "@/routes/Activity"
 import { buildAdminAuditRoute } from "@/routes/AdminAudit"
@@ -21,8 +24,15 @@ import { buildAdminUsersRoute } from "@/routes/AdminUsers"
 import { buildServerAllocationsRoute } from "@/routes/Allocations"
 import { buildBackupsRoute } from "@/routes/Backups"
 import { buildBlueprintsRoute } from "@/routes/Blueprints"
+import { buildCommandsRoute } from "@/routes/Commands"
+import { buildEnvironmentRoute } from "@/routes/Environment"
 import { buildInstancesRoute } from "@/routes/Instances"
+import { buildMacrosRoute } from "@/routes/Macros"
+import { buildPlayersRoute } from "@/routes/Players"
+import { buildPowerRoute } from "@/routes/Power"
+import { buildReplicationRoute } from "@/routes/Replication"
 import { buildSchedulesRoute } from "@/routes/Schedules"
+import { buildServerConfigRoute } from "@/routes/ServerConfig"
 import { buildSubusersRoute } from "@/routes/Subusers"
 import { buildTransfersRoute } from "@/routes/Transfers"
 import { buildMeRoute } from "@/routes/Me"
@@ -49,6 +59,12 @@ const statusCache = new StatusCache(redis)
 const installRunner = new InstallRunner(db)
 const scheduler = new Scheduler(db, statusCache)
 scheduler.start()
+const transferJanitor = new TransferJanitor(db, env.TRANSFER_TIMEOUT_MINUTES)
+transferJanitor.start()
+const transferQueue = new TransferQueue(db, env)
+transferQueue.start()
+const replicationQueue = new ReplicationQueue(db, env)
+replicationQueue.start()
 
 const app = new Hono<{ Variables: ApiVariables }>()
 
@@ -89,7 +105,14 @@ app.route("/api/servers", buildServerAllocationsRoute({ auth, db }))
 app.route("/api/servers", buildSubusersRoute({ auth, db }))
 app.route("/api/servers", buildActivityRoute({ auth, db }))
 app.route("/api/servers", buildSchedulesRoute({ auth, db }))
-app.route("/api/servers", buildTransfersRoute({ auth, db }))
+app.route("/api/servers", buildMacrosRoute({ auth, db }))
+app.route("/api/servers", buildPlayersRoute({ auth, db }))
+app.route("/api/servers", buildCommandsRoute({ auth, db }))
+app.route("/api/servers", buildEnvironmentRoute({ auth, db }))
+app.route("/api/servers", buildPowerRoute({ auth, db }))
+app.route("/api/servers", buildServerConfigRoute({ auth, db }))
+app.route("/api/servers", buildTransfersRoute({ auth, db, env }))
+app.route("/api/servers", buildReplicationRoute({ auth, db, env }))
 app.route("/api/servers", buildInstancesRoute({ auth, db, installRunner }))
 app.route("/api/remote", buildRemoteRoute({ db, env, statusCache }))
 app.route("/api/nodes/pair", buildPairingExchangeRoute({ db }))
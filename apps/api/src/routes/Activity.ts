@@ -3,10 +3,9 @@ import { Hono } from "hono"
 
 import type { Db } from "@workspace/db/client.types"
 import { auditLogTable } from "@workspace/db/schema/audit"
-import { serversTable } from "@workspace/db/schema/servers"
-import { ApiException } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin } from "@/lib/ServerAccess"
 import {
   buildRequireSession,
   type AuthVariables,
@@ -24,7 +23,7 @@ export const buildActivityRoute = (params: { auth: Auth; db: Db }) => {
     .use("*", requireSession)
     .get("/:serverId/activity", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const limit = Math.min(
         100,
         Math.max(1, Number(c.req.query("limit") ?? 25))
@@ -56,23 +55,3 @@ export const buildActivityRoute = (params: { auth: Auth; db: Db }) => {
       })
     })
 }
-
-const assertAccess = async (
-  db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-): Promise<void> => {
-  const server = (
-    await db
-      .select({ ownerId: serversTable.ownerId })
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin === true) return
-  if (server.ownerId === user.id) return
-  throw new ApiException("permissions.denied", { status: 403 })
-}
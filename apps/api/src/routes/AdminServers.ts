@@ -14,13 +14,20 @@ import {
   serverVariablesTable,
   serversTable,
 } from "@workspace/db/schema/servers"
+import { filePatternSchema } from "@workspace/shared/blueprint"
 import {
   ApiException,
   apiValidationError,
 } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
-import type { InstallRunner } from "@/lib/InstallRunner"
+import { extraHostsSchema } from "@/lib/ExtraHosts"
+import {
+  installArchiveSchema,
+  type InstallRunner,
+} from "@/lib/InstallRunner"
+import { serverLogPruningSchema } from "@/lib/LogPruning"
+import { assertNodeSupports } from "@/lib/NodeCapabilities"
 import type { StatusCache } from "@/lib/StatusCache"
 import { buildRequireAdmin } from "@/middleware/RequireAdmin"
 import type { AuthVariables } from "@/middleware/RequireSession"
@@ -32,6 +39,25 @@ const updateServerSchema = z.object({
   blueprintId: z.string().uuid().optional(),
   dockerImage: z.string().min(1).optional(),
   ownerId: z.string().uuid().optional(),
+  hostNetwork: z.boolean().optional(),
+  /** Null lifts the quota. */
+  backupQuotaMb: z.number().int().positive().nullable().optional(),
+  backupQuotaPolicy: z.enum(["reject", "rotate"]).optional(),
+  /** Null uses the node's console defaults. */
+  consoleHistoryLines: z
+    .number()
+    .int()
+    .min(10)
+    .max(20000)
+    .nullable()
+    .optional(),
+  consoleBuffer: z.number().int().min(16).max(4096).nullable().optional(),
+  /** Null clears them; either way they apply on the next restart. */
+  extraHosts: extraHostsSchema.nullable().optional(),
+  /** Added to the blueprint's denylist; picked up at the next start. */
+  fileDenylist: z.array(filePatternSchema).max(128).nullable().optional(),
+  /** Overrides the blueprint's log pruning; null falls back to it. */
+  logPruning: serverLogPruningSchema.nullable().optional(),
 })
 
 const variablesSchema = z.object({
@@ -57,6 +83,7 @@ const adminCreateSchema = z.object({
   cpuLimitPercent: z.number().int().positive(),
   diskLimitMb: z.number().int().positive(),
   variables: z.record(z.string(), z.string()).default({}),
+  installArchive: installArchiveSchema.optional(),
 })
 
 export const buildAdminServersRoute = (params: {
@@ -82,6 +109,7 @@ export const buildAdminServersRoute = (params: {
           diskLimitMb: serversTable.diskLimitMb,
           dockerImage: serversTable.dockerImage,
           blueprintId: serversTable.blueprintId,
+          hostNetwork: serversTable.hostNetwork,
           createdAt: serversTable.createdAt,
           ownerId: serversTable.ownerId,
           nodeId: serversTable.nodeId,
@@ -181,6 +209,9 @@ export const buildAdminServersRoute = (params: {
           status: 409,
         })
       }
+      if (input.installArchive !== undefined) {
+        assertNodeSupports(node, "install.archive")
+      }
       if (!Object.values(blueprint.dockerImages).includes(input.dockerImage)) {
         throw new ApiException("servers.startup.invalid_docker_image", {
           status: 422,
@@ -227,6 +258,7 @@ export const buildAdminServersRoute = (params: {
             startupExtra: null,
             status: "offline",
             installState: "pending",
+            installArchive: input.installArchive ?? null,
           })
           .returning()
         if (row === undefined) throw new Error("insert failed")
@@ -252,6 +284,21 @@ export const buildAdminServersRoute = (params: {
       const id = c.req.param("id")
       const parsed = updateServerSchema.safeParse(await c.req.json())
       if (!parsed.success) throw apiValidationError(parsed.error)
+      if ((parsed.data.extraHosts?.length ?? 0) > 0) {
+        // An older daemon would drop them without a word.
+        const node = (
+          await db
+            .select({ node: nodesTable })
+            .from(serversTable)
+            .innerJoin(nodesTable, eq(nodesTable.id, serversTable.nodeId))
+            .where(eq(serversTable.id, id))
+            .limit(1)
+        )[0]?.node
+        if (node === undefined) {
+          throw new ApiException("servers.not_found", { status: 404 })
+        }
+        assertNodeSupports(node, "network.hosts")
+      }
       await db
         .update(serversTable)
         .set({ ...parsed.data, updatedAt: new Date() })
@@ -1,5 +1,6 @@
 import { and, eq, isNull } from "drizzle-orm"
 import { Hono } from "hono"
+import { z } from "zod"
 
 import type { Db } from "@workspace/db/client.types"
 import { nodeAllocationsTable } from "@workspace/db/schema/nodes"
@@ -7,18 +8,34 @@ import {
   serverAllocationsTable,
   serversTable,
 } from "@workspace/db/schema/servers"
-import { ApiException } from "@workspace/shared/errors"
+import { ApiException, apiValidationError } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
+import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSupports } from "@/lib/NodeCapabilities"
+import {
+  assertOwnerOrAdmin,
+  loadServerNode,
+  mintActingToken,
+} from "@/lib/ServerAccess"
 import {
   buildRequireSession,
   type AuthVariables,
 } from "@/middleware/RequireSession"
 
+const syncBodySchema = z.object({ restart: z.boolean().default(false) })
+
 /**
  * Per-server allocation management. The owner / admin can list, request
  * a random allocation from the pool, set a different one as primary, or
  * unassign one (provided it isn't the primary).
+ *
+ * Adding or removing an allocation tells the node, which answers with
+ * `sync`: the ports a running container would gain and lose, and
+ * whether it needs a restart to bind them (Docker can't rebind a
+ * running container). `allocations/sync` re-sends that, optionally
+ * restarting the server onto the new ports. `sync` is null when the
+ * node couldn't be told; the next start picks the ports up anyway.
  */
 export const buildServerAllocationsRoute = (params: {
   auth: Auth
@@ -31,7 +48,7 @@ export const buildServerAllocationsRoute = (params: {
     .use("*", requireSession)
     .get("/:serverId/allocations", async (c) => {
       const serverId = c.req.param("serverId")
-      const server = await assertOwner(db, c.get("user"), serverId)
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const allocations = await db
         .select({
           id: nodeAllocationsTable.id,
@@ -56,7 +73,7 @@ export const buildServerAllocationsRoute = (params: {
     })
     .post("/:serverId/allocations/random", async (c) => {
       const serverId = c.req.param("serverId")
-      const server = await assertOwner(db, c.get("user"), serverId)
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const free = (
         await db
           .select()
@@ -83,12 +100,13 @@ export const buildServerAllocationsRoute = (params: {
           .insert(serverAllocationsTable)
           .values({ serverId, allocationId: free.id })
       })
-      return c.json({ allocation: { ...free, serverId } })
+      const sync = await syncAllocations(db, c.get("user"), serverId, false)
+      return c.json({ allocation: { ...free, serverId }, sync })
     })
     .patch("/:serverId/allocations/:allocId/primary", async (c) => {
       const serverId = c.req.param("serverId")
       const allocId = c.req.param("allocId")
-      await assertOwner(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const link = (
         await db
           .select()
@@ -113,7 +131,7 @@ export const buildServerAllocationsRoute = (params: {
     .delete("/:serverId/allocations/:allocId", async (c) => {
       const serverId = c.req.param("serverId")
       const allocId = c.req.param("allocId")
-      const server = await assertOwner(db, c.get("user"), serverId)
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
       if (server.primaryAllocationId === allocId) {
         throw new ApiException("servers.cannot_remove_primary_allocation", {
           status: 409,
@@ -133,27 +151,68 @@ export const buildServerAllocationsRoute = (params: {
           .set({ serverId: null })
           .where(eq(nodeAllocationsTable.id, allocId))
       })
-      return c.json({ ok: true })
+      const sync = await syncAllocations(db, c.get("user"), serverId, false)
+      return c.json({ ok: true, sync })
+    })
+    .post("/:serverId/allocations/sync", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const parsed = syncBodySchema.safeParse(
+        await c.req.json().catch(() => ({}))
+      )
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const sync = await syncAllocations(
+        db,
+        c.get("user"),
+        serverId,
+        parsed.data.restart
+      )
+      if (sync === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      return c.json({ sync })
     })
 }
 
-const assertOwner = async (
+/**
+ * Asks the server's node to pick up its current allocations. Returns
+ * the daemon's answer, or null when the node is unpaired, predates the
+ * endpoint or didn't answer; allocation changes don't fail over that.
+ */
+const syncAllocations = async (
   db: Db,
   user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-) => {
-  const server = (
-    await db
-      .select()
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
+  serverId: string,
+  restart: boolean
+): Promise<unknown> => {
+  const { node } = await loadServerNode(db, serverId)
+  if (
+    node.daemonPublicKey === null ||
+    !nodeSupports(node, "allocations.sync")
+  ) {
+    return null
   }
-  if (user.isAdmin !== true && server.ownerId !== user.id) {
-    throw new ApiException("permissions.denied", { status: 403 })
+  const userToken = await mintActingToken(db, user, serverId, {
+    id: node.id,
+    daemonPublicKey: node.daemonPublicKey,
+  })
+  try {
+    const resp = await callDaemon({
+      baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+      nodeId: node.id,
+      signingKeyHex: node.daemonPublicKey,
+      method: "POST",
+      path: `/api/servers/${serverId}/allocations`,
+      body: { restart },
+      userToken,
+    })
+    if (resp.status === 429) {
+      throw new ApiException("servers.power.queue_full", { status: 429 })
+    }
+    if (!resp.ok) return null
+    return await resp.json()
+  } catch (err) {
+    if (err instanceof ApiException) throw err
+    return null
   }
-  return server
 }
@@ -1,4 +1,4 @@
-import { and, eq } from "drizzle-orm"
+import { and, desc, eq } from "drizzle-orm"
 import { Hono } from "hono"
 import { z } from "zod"
 
@@ -7,28 +7,68 @@ import {
   backupDestinationsTable,
   backupsTable,
 } from "@workspace/db/schema/backups"
-import { nodesTable } from "@workspace/db/schema/nodes"
 import { serversTable } from "@workspace/db/schema/servers"
 import { ApiException, apiValidationError } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
-import { runBackup } from "@/lib/BackupRunner"
+import {
+  backupQuota,
+  removeRotatedBackups,
+  runBackup,
+} from "@/lib/BackupRunner"
 import { callDaemon } from "@/lib/DaemonHttp"
+import { assertNodeSupports, nodeSupports } from "@/lib/NodeCapabilities"
+import {
+  assertOwnerOrAdmin,
+  loadServerNode,
+  mintActingToken,
+} from "@/lib/ServerAccess"
 import {
   buildRequireSession,
   type AuthVariables,
 } from "@/middleware/RequireSession"
 
+const backupNameSchema = z
+  .string()
+  .min(1)
+  .max(64)
+  .regex(/^[A-Za-z0-9._-]+$/)
+
 const createBackupSchema = z.object({
-  name: z
-    .string()
-    .min(1)
-    .max(64)
-    .regex(/^[A-Za-z0-9._-]+$/),
+  name: backupNameSchema,
   /** Optional S3 destination id; ignored in v1 (local-only backups). */
   destinationId: z.string().uuid().optional(),
+  /** Record per-file sha256s alongside the archive checksum. */
+  manifest: z.boolean().optional(),
+  /** Paths to leave out of the archive ("logs/", "*.jar", "cache/*"). */
+  ignore: z.array(z.string().min(1).max(256)).max(64).optional(),
+  /**
+   * Only archive what changed since the server's latest ready backup.
+   * Falls back to a full backup when there's none to build on.
+   */
+  incremental: z.boolean().optional(),
+})
+
+const importBackupSchema = z.object({
+  name: backupNameSchema,
+  url: z.string().url().regex(/^https?:\/\//),
 })
 
+/** One archive as the daemon's `op=list` reports it. */
+type NodeBackup = {
+  name: string
+  source?: string
+  compression: string
+  bytes: number
+  sha256?: string
+  ignore?: string[]
+  manifest: boolean
+  base?: string
+  createdAt: string
+  daemonVersion?: string
+  sidecar: boolean
+}
+
 export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
   const { auth, db } = params
   const requireSession = buildRequireSession(auth)
@@ -37,7 +77,7 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     .use("*", requireSession)
     .get("/:serverId/backups", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const rows = await db
         .select()
         .from(backupsTable)
@@ -46,30 +86,45 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     })
     .post("/:serverId/backups", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const parsed = createBackupSchema.safeParse(await c.req.json())
       if (!parsed.success) throw apiValidationError(parsed.error)
-      // Per-server backup limit: refuse new creates once we're at or
-      // over it. Counts every non-failed row so locked + ready + pending
-      // all consume a slot.
-      const server = (
-        await db
-          .select({ limit: serversTable.backupLimit })
-          .from(serversTable)
-          .where(eq(serversTable.id, serverId))
-          .limit(1)
-      )[0]
-      const existing = await db
-        .select({ id: backupsTable.id })
-        .from(backupsTable)
-        .where(eq(backupsTable.serverId, serverId))
-      if (server !== undefined && existing.length >= server.limit) {
-        throw new ApiException("internal.unexpected", {
-          status: 409,
-          params: { limit: server.limit },
-        })
+      await assertBackupSlot(db, serverId)
+      const ignore = parsed.data.ignore ?? []
+      if (ignore.length > 0) {
+        // An older daemon would drop the patterns and back up everything.
+        const { node } = await loadServerNode(db, serverId)
+        assertNodeSupports(node, "backup.metadata")
       }
-      const id = await runBackup({ db, serverId, name: parsed.data.name })
+      let base: string | undefined
+      if (parsed.data.incremental === true) {
+        const { node } = await loadServerNode(db, serverId)
+        assertNodeSupports(node, "backup.incremental")
+        base = (
+          await db
+            .select({ name: backupsTable.name })
+            .from(backupsTable)
+            .where(
+              and(
+                eq(backupsTable.serverId, serverId),
+                eq(backupsTable.storage, "local"),
+                eq(backupsTable.state, "ready")
+              )
+            )
+            .orderBy(desc(backupsTable.completedAt))
+            .limit(1)
+        )[0]?.name
+      }
+      const id = await runBackup({
+        db,
+        serverId,
+        name: parsed.data.name,
+        manifest: parsed.data.manifest,
+        source: "manual",
+        ignore,
+        base,
+        user: c.get("user"),
+      })
       if (id === null) {
         throw new ApiException("internal.unexpected", { status: 502 })
       }
@@ -80,10 +135,196 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
         .limit(1)
       return c.json({ backup: row })
     })
+    // Registers an archive made elsewhere as a backup. Either the raw
+    // .tar.gz as the body (name in the query string) or JSON
+    // {name, url} for the node to pull; the daemon validates it and
+    // computes the checksum.
+    .post("/:serverId/backups/import", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const isJson = (c.req.header("content-type") ?? "").startsWith(
+        "application/json"
+      )
+      let name: string
+      let url: string | undefined
+      if (isJson) {
+        const parsed = importBackupSchema.safeParse(await c.req.json())
+        if (!parsed.success) throw apiValidationError(parsed.error)
+        name = parsed.data.name
+        url = parsed.data.url
+      } else {
+        const parsed = backupNameSchema.safeParse(c.req.query("name"))
+        if (!parsed.success) throw apiValidationError(parsed.error)
+        name = parsed.data
+      }
+      const upload = c.req.raw.body
+      if (!isJson && upload === null) {
+        throw new ApiException("validation.failed", { status: 422 })
+      }
+      await assertBackupSlot(db, serverId)
+      const { node, server } = await loadServerNode(db, serverId)
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "backup.import")
+      const [created] = await db
+        .insert(backupsTable)
+        .values({ serverId, name, storage: "local", state: "pending" })
+        .returning({ id: backupsTable.id })
+      if (created === undefined) {
+        throw new ApiException("internal.unexpected", { status: 500 })
+      }
+      const query = new URLSearchParams({ op: "import" })
+      if (!isJson) query.set("name", name)
+      const userToken = await mintActingToken(db, c.get("user"), serverId, {
+        id: node.id,
+        daemonPublicKey: node.daemonPublicKey,
+      })
+      const quota = await backupQuota(db, server)
+      if (quota !== undefined) {
+        query.set("quotaBytes", String(quota.bytes))
+        if (quota.rotate) query.set("quotaRotate", "1")
+        for (const locked of quota.protected) query.append("protected", locked)
+      }
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "POST",
+        path: `/api/servers/${server.id}/backups?${query}`,
+        userToken,
+        ...(isJson
+          ? { body: { name, url } }
+          : {
+              stream: {
+                body: upload as ReadableStream<Uint8Array>,
+                contentType: "application/gzip",
+              },
+            }),
+      }).catch(() => null)
+      if (resp === null || !resp.ok) {
+        // Nothing was registered on the node; drop the placeholder row.
+        await db.delete(backupsTable).where(eq(backupsTable.id, created.id))
+        if (resp?.status === 409) {
+          throw new ApiException("backups.exists", { status: 409 })
+        }
+        if (resp?.status === 422) {
+          throw new ApiException("backups.invalid_archive", { status: 422 })
+        }
+        if (resp?.status === 507) {
+          throw new ApiException("backups.quota_exceeded", { status: 507 })
+        }
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      const result = (await resp.json()) as {
+        bytes: number
+        sha256: string
+        rotated?: string[]
+      }
+      await removeRotatedBackups(db, serverId, result.rotated)
+      const [row] = await db
+        .update(backupsTable)
+        .set({
+          state: "ready",
+          bytes: result.bytes,
+          sha256: result.sha256,
+          completedAt: new Date(),
+        })
+        .where(eq(backupsTable.id, created.id))
+        .returning()
+      return c.json({ backup: row })
+    })
+    // What's actually on the node, reconciled against the panel's rows:
+    // `orphaned` archives have no row, `missing` rows have no archive.
+    .get("/:serverId/backups/node", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const { node, server } = await loadServerNode(db, serverId)
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "backup.metadata")
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: `/api/servers/${server.id}/backups?op=list`,
+        userToken: await mintActingToken(db, c.get("user"), serverId, {
+          id: node.id,
+          daemonPublicKey: node.daemonPublicKey,
+        }),
+      })
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      const { backups } = (await resp.json()) as { backups: NodeBackup[] }
+      const rows = await db
+        .select({
+          id: backupsTable.id,
+          name: backupsTable.name,
+          state: backupsTable.state,
+          storage: backupsTable.storage,
+        })
+        .from(backupsTable)
+        .where(eq(backupsTable.serverId, serverId))
+      const onNode = new Set(backups.map((b) => b.name))
+      const known = new Set(rows.map((r) => r.name))
+      return c.json({
+        backups,
+        orphaned: backups.filter((b) => !known.has(b.name)).map((b) => b.name),
+        // Pending rows haven't written their archive yet, and S3 ones
+        // never live on the node.
+        missing: rows
+          .filter(
+            (r) =>
+              r.state === "ready" &&
+              r.storage === "local" &&
+              !onNode.has(r.name)
+          )
+          .map((r) => r.id),
+      })
+    })
+    // Backup disk use against the server's quota. `bytes` is measured
+    // on the node; null when its daemon predates quota support.
+    .get("/:serverId/backups/usage", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const { node, server } = await loadServerNode(db, serverId)
+      const quota = {
+        quotaBytes:
+          server.backupQuotaMb === null
+            ? null
+            : server.backupQuotaMb * 1024 * 1024,
+        policy: server.backupQuotaPolicy,
+      }
+      if (
+        node.daemonPublicKey === null ||
+        !nodeSupports(node, "backup.quota")
+      ) {
+        return c.json({ bytes: null, ...quota })
+      }
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: `/api/servers/${server.id}/backups?op=usage`,
+        userToken: await mintActingToken(db, c.get("user"), serverId, {
+          id: node.id,
+          daemonPublicKey: node.daemonPublicKey,
+        }),
+      })
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      const { bytes } = (await resp.json()) as { bytes: number }
+      return c.json({ bytes, ...quota })
+    })
     .post("/:serverId/backups/:backupId/restore", async (c) => {
       const serverId = c.req.param("serverId")
       const backupId = c.req.param("backupId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const backup = (
         await db
           .select()
@@ -111,15 +352,62 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
         method: "POST",
         path: `/api/servers/${server.id}/backups?op=restore`,
         body: { name: backup.name },
+        userToken: await mintActingToken(db, c.get("user"), serverId, {
+          id: node.id,
+          daemonPublicKey: node.daemonPublicKey,
+        }),
       })
       if (!resp.ok) {
         throw new ApiException("internal.unexpected", { status: 502 })
       }
       return c.json({ ok: true })
     })
+    .get("/:serverId/backups/:backupId/manifest", async (c) => {
+      const serverId = c.req.param("serverId")
+      const backupId = c.req.param("backupId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const backup = (
+        await db
+          .select()
+          .from(backupsTable)
+          .where(
+            and(
+              eq(backupsTable.id, backupId),
+              eq(backupsTable.serverId, serverId)
+            )
+          )
+          .limit(1)
+      )[0]
+      if (backup === undefined) {
+        throw new ApiException("backups.not_found", { status: 404 })
+      }
+      const { node, server } = await loadServerNode(db, serverId)
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "backup.manifest")
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: `/api/servers/${server.id}/backups?op=manifest&name=${encodeURIComponent(backup.name)}`,
+        userToken: await mintActingToken(db, c.get("user"), serverId, {
+          id: node.id,
+          daemonPublicKey: node.daemonPublicKey,
+        }),
+      })
+      if (resp.status === 404) {
+        throw new ApiException("backups.no_manifest", { status: 404 })
+      }
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      return c.json({ manifest: await resp.json() })
+    })
     .get("/:serverId/destination", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const dest = (
         await db
           .select({
@@ -141,7 +429,7 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     })
     .put("/:serverId/destination", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       // Partial-update support: secretAccessKey is optional on update so
       // operators can rotate the public credential without retyping the
       // secret. The first PUT must include it; subsequent PUTs without
@@ -192,7 +480,7 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     })
     .delete("/:serverId/destination", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       await db
         .delete(backupDestinationsTable)
         .where(eq(backupDestinationsTable.serverId, serverId))
@@ -201,7 +489,7 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     .delete("/:serverId/backups/:backupId", async (c) => {
       const serverId = c.req.param("serverId")
       const backupId = c.req.param("backupId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const backup = (
         await db
           .select()
@@ -217,9 +505,30 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
       if (backup === undefined) {
         throw new ApiException("internal.unexpected", { status: 404 })
       }
+      // Incremental backups built on this one can't be restored
+      // without it.
+      const dependents = await db
+        .select({ name: backupsTable.name })
+        .from(backupsTable)
+        .where(
+          and(
+            eq(backupsTable.serverId, serverId),
+            eq(backupsTable.base, backup.name)
+          )
+        )
+      if (dependents.length > 0) {
+        throw new ApiException("backups.has_dependents", {
+          status: 409,
+          params: { backups: dependents.map((d) => d.name).join(", ") },
+        })
+      }
       const { node, server } = await loadServerNode(db, serverId)
       if (node.daemonPublicKey !== null) {
         const baseUrl = `${node.scheme}://${node.fqdn}:${node.daemonPort}`
+        const userToken = await mintActingToken(db, c.get("user"), serverId, {
+          id: node.id,
+          daemonPublicKey: node.daemonPublicKey,
+        })
         await callDaemon({
           baseUrl,
           nodeId: node.id,
@@ -227,6 +536,7 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
           method: "POST",
           path: `/api/servers/${server.id}/backups?op=delete`,
           body: { name: backup.name },
+          userToken,
         }).catch(() => {
           // Daemon-side delete is idempotent; if it fails we still
           // remove the DB row so the UI doesn't show a phantom backup.
@@ -237,37 +547,26 @@ export const buildBackupsRoute = (params: { auth: Auth; db: Db }) => {
     })
 }
 
-const assertAccess = async (
-  db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-): Promise<void> => {
+/**
+ * Per-server backup limit: refuse new backups once we're at or over it.
+ * Counts every row so locked + ready + pending all consume a slot.
+ */
+const assertBackupSlot = async (db: Db, serverId: string): Promise<void> => {
   const server = (
     await db
-      .select({ ownerId: serversTable.ownerId })
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin === true) return
-  if (server.ownerId === user.id) return
-  throw new ApiException("permissions.denied", { status: 403 })
-}
-
-const loadServerNode = async (db: Db, serverId: string) => {
-  const row = (
-    await db
-      .select({ server: serversTable, node: nodesTable })
+      .select({ limit: serversTable.backupLimit })
       .from(serversTable)
-      .innerJoin(nodesTable, eq(nodesTable.id, serversTable.nodeId))
       .where(eq(serversTable.id, serverId))
       .limit(1)
   )[0]
-  if (row === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
+  const existing = await db
+    .select({ id: backupsTable.id })
+    .from(backupsTable)
+    .where(eq(backupsTable.serverId, serverId))
+  if (server !== undefined && existing.length >= server.limit) {
+    throw new ApiException("internal.unexpected", {
+      status: 409,
+      params: { limit: server.limit },
+    })
   }
-  return row
 }
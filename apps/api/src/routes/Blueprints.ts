@@ -60,7 +60,14 @@ export const buildBlueprintsRoute = (params: { auth: Auth; db: Db }) => {
           installImage: data.install.image,
           installEntrypoint: data.install.entrypoint,
           installScript: data.install.script,
+          installInterpreter: data.install.interpreter ?? null,
           lifecycle: data.lifecycle,
+          memoryOverhead: data.memoryOverhead ?? null,
+          sidecars: data.sidecars ?? null,
+          services: data.services ?? null,
+          playerCommands: data.playerCommands ?? null,
+          files: data.files ?? null,
+          logPruning: data.logPruning ?? null,
           features: data.features ?? null,
         })
         .returning()
@@ -89,7 +96,14 @@ export const buildBlueprintsRoute = (params: { auth: Auth; db: Db }) => {
           installImage: data.install.image,
           installEntrypoint: data.install.entrypoint,
           installScript: data.install.script,
+          installInterpreter: data.install.interpreter ?? null,
           lifecycle: data.lifecycle,
+          memoryOverhead: data.memoryOverhead ?? null,
+          sidecars: data.sidecars ?? null,
+          services: data.services ?? null,
+          playerCommands: data.playerCommands ?? null,
+          files: data.files ?? null,
+          logPruning: data.logPruning ?? null,
           features: data.features ?? null,
           updatedAt: new Date(),
         })
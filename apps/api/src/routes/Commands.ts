@@ -0,0 +1,74 @@
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import { ApiException, apiValidationError } from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin, callServerDaemon } from "@/lib/ServerAccess"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+const commandBodySchema = z.object({
+  line: z
+    .string()
+    .min(1)
+    .max(1024)
+    .regex(/^[^\r\n]*$/),
+  // How long to collect console output after sending, and optionally a
+  // regexp (Go syntax) that ends collection early on the first match.
+  windowMs: z.number().int().min(100).max(30_000).default(2_000),
+  until: z.string().min(1).max(256).optional(),
+})
+
+/**
+ * Run a console command and get its output back: the daemon sends the
+ * line, collects console output for `windowMs` (or until a line
+ * matches `until`) and answers `{id, lines, matched, truncated}`. Plain
+ * fire-and-forget commands stay on the console socket.
+ */
+export const buildCommandsRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .post("/:serverId/command", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const parsed = commandBodySchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const { line, windowMs, until } = parsed.data
+      const resp = await callServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "command.capture",
+        method: "POST",
+        path: "/command",
+        body: { line, capture: { windowMs, until } },
+      })
+      if (resp.status === 400) {
+        // send_failed, or a capture the daemon refused: `until` isn't a
+        // regexp Go's engine accepts.
+        const body = (await resp.json().catch(() => null)) as {
+          error?: { code?: string }
+        } | null
+        if (body?.error?.code === "command.bad_capture") {
+          throw new ApiException("servers.command.bad_pattern", {
+            status: 422,
+          })
+        }
+        throw new ApiException("servers.command.send_failed", { status: 409 })
+      }
+      if (resp.status === 403) {
+        throw new ApiException("permissions.denied", { status: 403 })
+      }
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      return c.json(await resp.json())
+    })
+}
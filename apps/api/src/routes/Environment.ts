@@ -0,0 +1,198 @@
+import { eq } from "drizzle-orm"
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import { blueprintsTable } from "@workspace/db/schema/blueprints"
+import {
+  serverVariablesTable,
+  serversTable,
+} from "@workspace/db/schema/servers"
+import { ApiException, apiValidationError } from "@workspace/shared/errors"
+import type { ApiFieldError } from "@workspace/shared/errors.types"
+
+import type { Auth } from "@/auth"
+import { callDaemon } from "@/lib/DaemonHttp"
+import { nodeSupports } from "@/lib/NodeCapabilities"
+import {
+  assertOwnerOrAdmin,
+  loadServerNode,
+  mintActingToken,
+} from "@/lib/ServerAccess"
+import { checkVariableRules } from "@/lib/VariableRules"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+const environmentBodySchema = z.object({
+  variables: z.record(z.string(), z.string().max(4096)),
+})
+
+type DaemonEnvironment = {
+  state: string
+  synced: boolean
+  environment: Record<string, string>
+  overrides: Record<string, string>
+  pendingRestart: string[]
+}
+
+/**
+ * A server's blueprint variables with the values the node is running.
+ * GET lists each variable's saved value and, when the node answers, the
+ * ones a running server only picks up on restart. PATCH checks the new
+ * values against the blueprint's rules (owners can only change
+ * `userEditable` variables), saves them, and hands them straight to the
+ * node, which keeps them for the next start even if its config pull
+ * still sees the old ones. The answer's `pendingRestart` says which
+ * need a restart; `daemon` is null when the node couldn't be reached,
+ * in which case the values land at the next start's config pull.
+ */
+export const buildEnvironmentRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .get("/:serverId/environment", async (c) => {
+      const serverId = c.req.param("serverId")
+      const user = c.get("user")
+      await assertOwnerOrAdmin(db, user, serverId)
+      const variables = await loadVariables(db, serverId)
+      const daemon = await daemonEnvironment(db, user, serverId, "GET")
+      return c.json({
+        variables: variables.filter(
+          (v) => user.isAdmin === true || v.userViewable
+        ),
+        pendingRestart: daemon?.pendingRestart ?? [],
+        daemon,
+      })
+    })
+    .patch("/:serverId/environment", async (c) => {
+      const serverId = c.req.param("serverId")
+      const user = c.get("user")
+      await assertOwnerOrAdmin(db, user, serverId)
+      const parsed = environmentBodySchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const variables = await loadVariables(db, serverId)
+      const byKey = new Map(variables.map((v) => [v.key, v]))
+      const fields: ApiFieldError[] = []
+      for (const [key, value] of Object.entries(parsed.data.variables)) {
+        const path = `variables.${key}`
+        const variable = byKey.get(key)
+        if (variable === undefined) {
+          fields.push({ path, code: "validation.variable.unknown" })
+          continue
+        }
+        if (user.isAdmin !== true && !variable.userEditable) {
+          fields.push({ path, code: "validation.variable.not_editable" })
+          continue
+        }
+        const problem = checkVariableRules(variable.rules, value)
+        if (problem !== null) {
+          fields.push({
+            path,
+            code: "validation.variable.rule",
+            params: { problem },
+          })
+        }
+      }
+      if (fields.length > 0) {
+        throw new ApiException("validation.failed", { status: 422, fields })
+      }
+      await db.transaction(async (tx) => {
+        for (const [key, value] of Object.entries(parsed.data.variables)) {
+          await tx
+            .insert(serverVariablesTable)
+            .values({ serverId, variableKey: key, value })
+            .onConflictDoUpdate({
+              target: [
+                serverVariablesTable.serverId,
+                serverVariablesTable.variableKey,
+              ],
+              set: { value },
+            })
+        }
+      })
+      const daemon = await daemonEnvironment(
+        db,
+        user,
+        serverId,
+        "PUT",
+        parsed.data.variables
+      )
+      return c.json({
+        ok: true,
+        pendingRestart: daemon?.pendingRestart ?? [],
+        daemon,
+      })
+    })
+}
+
+/**
+ * The blueprint's variables merged with the server's saved values.
+ */
+const loadVariables = async (db: Db, serverId: string) => {
+  const row = (
+    await db
+      .select({ variables: blueprintsTable.variables })
+      .from(serversTable)
+      .innerJoin(
+        blueprintsTable,
+        eq(blueprintsTable.id, serversTable.blueprintId)
+      )
+      .where(eq(serversTable.id, serverId))
+      .limit(1)
+  )[0]
+  const saved = await db
+    .select()
+    .from(serverVariablesTable)
+    .where(eq(serverVariablesTable.serverId, serverId))
+  const valueByKey = new Map(saved.map((r) => [r.variableKey, r.value]))
+  return (row?.variables ?? []).map((v) => ({
+    key: v.key,
+    name: v.name,
+    description: v.description,
+    userViewable: v.userViewable,
+    userEditable: v.userEditable,
+    rules: v.rules,
+    value: valueByKey.get(v.key) ?? v.default,
+  }))
+}
+
+/**
+ * Reads (GET) or sets (PUT) the node's copy of the environment. Null
+ * when the node is unpaired, predates the endpoint or didn't answer;
+ * the saved values still reach it at the next start.
+ */
+const daemonEnvironment = async (
+  db: Db,
+  user: { id: string; isAdmin?: boolean | null },
+  serverId: string,
+  method: "GET" | "PUT",
+  environment?: Record<string, string>
+): Promise<DaemonEnvironment | null> => {
+  const { node } = await loadServerNode(db, serverId)
+  if (node.daemonPublicKey === null || !nodeSupports(node, "environment")) {
+    return null
+  }
+  const userToken = await mintActingToken(db, user, serverId, {
+    id: node.id,
+    daemonPublicKey: node.daemonPublicKey,
+  })
+  try {
+    const resp = await callDaemon({
+      baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+      nodeId: node.id,
+      signingKeyHex: node.daemonPublicKey,
+      method,
+      path: `/api/servers/${serverId}/environment`,
+      body: environment === undefined ? undefined : { environment },
+      userToken,
+    })
+    if (!resp.ok) return null
+    return (await resp.json()) as DaemonEnvironment
+  } catch {
+    return null
+  }
+}
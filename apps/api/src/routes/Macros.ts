@@ -0,0 +1,100 @@
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import { apiValidationError } from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin, forwardServerDaemon } from "@/lib/ServerAccess"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+const macroNameSchema = z.string().regex(/^[a-z0-9][a-z0-9_-]{0,63}$/)
+
+const macroBodySchema = z.object({
+  steps: z
+    .array(
+      z.object({
+        command: z.string().min(1).max(1024),
+        delayMs: z.number().int().min(0).max(600_000),
+      })
+    )
+    .min(1)
+    .max(50),
+})
+
+/**
+ * Command macros are stored by the daemon, not the DB: the API only
+ * checks access and forwards. Invocation returns the daemon's job
+ * snapshot so the panel can follow progress over the console socket.
+ */
+export const buildMacrosRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .get("/:serverId/macros", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "macros",
+        method: "GET",
+        path: "/commands/macros",
+      })
+      return c.json(await resp.json())
+    })
+    .put("/:serverId/macros/:name", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const name = macroNameSchema.safeParse(c.req.param("name"))
+      if (!name.success) throw apiValidationError(name.error)
+      const parsed = macroBodySchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "macros",
+        method: "PUT",
+        path: `/commands/macros/${name.data}`,
+        body: parsed.data,
+      })
+      return c.json(await resp.json())
+    })
+    .delete("/:serverId/macros/:name", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const name = macroNameSchema.safeParse(c.req.param("name"))
+      if (!name.success) throw apiValidationError(name.error)
+      await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "macros",
+        method: "DELETE",
+        path: `/commands/macros/${name.data}`,
+      })
+      return c.json({ ok: true })
+    })
+    .post("/:serverId/macros/:name/run", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const name = macroNameSchema.safeParse(c.req.param("name"))
+      if (!name.success) throw apiValidationError(name.error)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "macros",
+        method: "POST",
+        path: `/commands/macro/${name.data}`,
+      })
+      return c.json(await resp.json())
+    })
+}
@@ -1,6 +1,6 @@
 import { randomBytes, randomUUID, createHash } from "node:crypto"
 
-import { eq } from "drizzle-orm"
+import { and, eq, or } from "drizzle-orm"
 import { Hono } from "hono"
 import { z } from "zod"
 
@@ -10,12 +10,16 @@ import {
   nodePairingTokensTable,
   nodesTable,
 } from "@workspace/db/schema/nodes"
+import { serverTransfersTable } from "@workspace/db/schema/transfers"
+import type { ErrorCode } from "@workspace/shared/error-codes"
 import {
   ApiException,
   apiValidationError,
 } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
+import { callDaemon } from "@/lib/DaemonHttp"
+import { assertNodeSupports } from "@/lib/NodeCapabilities"
 import type { AuthVariables } from "@/middleware/RequireSession"
 import { buildRequireAdmin } from "@/middleware/RequireAdmin"
 
@@ -40,11 +44,60 @@ const allocationsSchema = z
       })
       .optional(),
     alias: z.string().max(120).optional(),
+    protocol: z.enum(["tcp", "udp", "both"]).optional(),
+    // Reserve `portRange` as one allocation instead of one per port —
+    // for games that need a contiguous (usually UDP) block.
+    block: z.boolean().optional(),
   })
   .refine(
     (v) => v.ports !== undefined || v.portRange !== undefined,
     "ports or portRange required"
   )
+  .refine(
+    (v) => v.block !== true || v.portRange !== undefined,
+    "block requires portRange"
+  )
+
+const imagePullSchema = z.object({
+  image: z.string().min(1).max(512),
+  force: z.boolean().default(false),
+})
+
+const proxyRulesSchema = z.object({
+  rules: z
+    .array(
+      z.object({
+        host: z.string().min(1).max(253),
+        backend: z.string().regex(/^[^\s:]+:\d{1,5}$/),
+        serverId: z.string().uuid().optional(),
+      })
+    )
+    .max(1000)
+    .refine(
+      (rules) =>
+        new Set(rules.map((r) => r.host.toLowerCase())).size === rules.length,
+      { message: "Proxy hosts must be unique" }
+    ),
+})
+
+const logLevel = z.enum(["debug", "info", "warn", "error"])
+
+const logLevelsSchema = z.object({
+  level: logLevel,
+  modules: z.record(z.string().regex(/^[a-z]+$/), logLevel),
+})
+
+const updateSchema = z.object({
+  version: z.string().regex(/^v?[0-9A-Za-z][0-9A-Za-z.+-]{0,63}$/),
+})
+
+/** Daemon self-update error codes → the panel's. */
+const updateErrors: Record<string, ErrorCode> = {
+  "update.bad_version": "nodes.update.bad_version",
+  "update.in_progress": "nodes.update.in_progress",
+  "update.bad_signature": "nodes.update.bad_signature",
+  "update.no_previous": "nodes.update.no_previous",
+}
 
 const PAIRING_TTL_SECONDS = 600
 
@@ -150,11 +203,39 @@ export const buildNodesRoute = (params: { auth: Auth; db: Db }) => {
               for (let p = lo; p <= hi; p++) out.push(p)
               return out
             })()
-      const rows = ports.map((port) => ({
+      const protocol = parsed.data.protocol ?? "tcp"
+      const block = parsed.data.block === true && ports.length > 1
+      const lastPort = ports[ports.length - 1] ?? null
+      if (block && lastPort !== null) {
+        // onConflictDoNothing only catches an identical start port; a
+        // block must not straddle any existing allocation on the IP.
+        const existing = await db
+          .select({
+            port: nodeAllocationsTable.port,
+            portEnd: nodeAllocationsTable.portEnd,
+          })
+          .from(nodeAllocationsTable)
+          .where(
+            and(
+              eq(nodeAllocationsTable.nodeId, id),
+              eq(nodeAllocationsTable.ip, parsed.data.ip)
+            )
+          )
+        const first = ports[0] ?? lastPort
+        const overlaps = existing.some(
+          (r) => r.port <= lastPort && (r.portEnd ?? r.port) >= first
+        )
+        if (overlaps) {
+          throw new ApiException("allocations.overlap", { status: 409 })
+        }
+      }
+      const rows = (block ? ports.slice(0, 1) : ports).map((port) => ({
         id: randomUUID(),
         nodeId: id,
         ip: parsed.data.ip,
         port,
+        portEnd: block ? lastPort : null,
+        protocol,
         alias: parsed.data.alias ?? null,
         serverId: null,
       }))
@@ -182,6 +263,195 @@ export const buildNodesRoute = (params: { auth: Auth; db: Db }) => {
         .where(eq(nodeAllocationsTable.id, allocId))
       return c.json({ ok: true })
     })
+    .get("/:id/proxy", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      // Stats are live from the daemon; a disabled proxy or an
+      // unreachable node still returns the stored rules.
+      let stats: unknown = null
+      if (node.daemonPublicKey !== null) {
+        try {
+          const resp = await callDaemon({
+            baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+            nodeId: node.id,
+            signingKeyHex: node.daemonPublicKey,
+            method: "GET",
+            path: "/api/proxy/stats",
+          })
+          if (resp.ok) stats = await resp.json()
+        } catch {
+          stats = null
+        }
+      }
+      return c.json({ rules: node.proxyRules ?? [], stats })
+    })
+    .put("/:id/proxy/rules", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      const parsed = proxyRulesSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      await db
+        .update(nodesTable)
+        .set({ proxyRules: parsed.data.rules })
+        .where(eq(nodesTable.id, node.id))
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "PUT",
+        path: "/api/proxy/rules",
+        body: { rules: parsed.data.rules },
+      })
+      if (!resp.ok) {
+        throw new ApiException("nodes.unreachable", { status: 502 })
+      }
+      return c.json({ rules: parsed.data.rules })
+    })
+    // Self-update: the daemon downloads the signed release, swaps its
+    // binary and re-execs; its game servers keep running. The next
+    // heartbeat carries the new build's capabilities.
+    .post("/:id/update", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      const parsed = updateSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      return c.json(
+        await callUpdate(node, "/api/system/update", {
+          version: parsed.data.version,
+        })
+      )
+    })
+    .post("/:id/rollback", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      return c.json(await callUpdate(node, "/api/system/rollback"))
+    })
+    // Runtime log levels, e.g. `{level: "info", modules: {transfer:
+    // "debug"}}` to debug one subsystem. Reset by a daemon restart.
+    .get("/:id/log-levels", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      return c.json(await callLogLevels(node, "GET"))
+    })
+    .put("/:id/log-levels", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      const parsed = logLevelsSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      return c.json(await callLogLevels(node, "PUT", parsed.data))
+    })
+    // The daemon's own log, when it writes one (`[log] directory`).
+    .get("/:id/logs", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      const lines = Math.min(
+        5000,
+        Math.max(1, Math.floor(Number(c.req.query("lines") ?? 200)) || 200)
+      )
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "log.tail")
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: `/api/system/logs?lines=${lines}`,
+      })
+      if (!resp.ok) {
+        throw new ApiException("nodes.unreachable", { status: 502 })
+      }
+      return c.json(await resp.json())
+    })
+    // What the node is busy with: the daemon's installs, backups,
+    // transfers and jobs in flight with its limits, plus the transfers
+    // the panel is holding for the node's transfer window.
+    .get("/:id/queues", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "system.queues")
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "GET",
+        path: "/api/system/queues",
+      })
+      if (!resp.ok) {
+        throw new ApiException("nodes.unreachable", { status: 502 })
+      }
+      const queued = await db
+        .select({ id: serverTransfersTable.id })
+        .from(serverTransfersTable)
+        .where(
+          and(
+            eq(serverTransfersTable.status, "queued"),
+            or(
+              eq(serverTransfersTable.sourceNodeId, node.id),
+              eq(serverTransfersTable.targetNodeId, node.id)
+            )
+          )
+        )
+      return c.json({
+        ...((await resp.json()) as object),
+        transfersQueued: queued.length,
+      })
+    })
+    // Prewarm an image on the node ahead of a migration or blueprint
+    // rollout. The daemon's JSON-Lines progress is passed through as it
+    // comes; aborting the request cancels the pull.
+    .post("/:id/images/pull", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      if (node.daemonPublicKey === null) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      assertNodeSupports(node, "images.pull")
+      const parsed = imagePullSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const resp = await callDaemon({
+        baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+        nodeId: node.id,
+        signingKeyHex: node.daemonPublicKey,
+        method: "POST",
+        path: "/api/system/images/pull",
+        body: parsed.data,
+        signal: c.req.raw.signal,
+      })
+      if (!resp.ok || resp.body === null) {
+        throw new ApiException("nodes.unreachable", { status: 502 })
+      }
+      return new Response(resp.body, {
+        headers: {
+          "Content-Type": "application/x-ndjson",
+          "Cache-Control": "no-cache",
+        },
+      })
+    })
+    // Live SFTP sessions and login-failure bans, for kicking a client or
+    // unbanning an address that mistyped its token too often.
+    .get("/:id/sftp/sessions", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      return c.json(await callSftpAdmin(node, "GET", "sessions"))
+    })
+    .delete("/:id/sftp/sessions/:sessionId", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      return c.json(
+        await callSftpAdmin(
+          node,
+          "DELETE",
+          `sessions/${encodeURIComponent(c.req.param("sessionId"))}`
+        )
+      )
+    })
+    .delete("/:id/sftp/bans/:ip", async (c) => {
+      const node = await loadNode(db, c.req.param("id"))
+      return c.json(
+        await callSftpAdmin(
+          node,
+          "DELETE",
+          `bans/${encodeURIComponent(c.req.param("ip"))}`
+        )
+      )
+    })
     .post("/:id/pair", async (c) => {
       const id = c.req.param("id")
       const node = (
@@ -253,3 +523,95 @@ export const buildPairingExchangeRoute = (params: { db: Db }) => {
     return c.json({ nodeId, signingKey })
   })
 }
+
+const callUpdate = async (
+  node: Awaited<ReturnType<typeof loadNode>>,
+  path: string,
+  body?: unknown
+): Promise<unknown> => {
+  if (node.daemonPublicKey === null) {
+    throw new ApiException("nodes.unreachable", { status: 503 })
+  }
+  assertNodeSupports(node, "self.update")
+  const resp = await callDaemon({
+    baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+    nodeId: node.id,
+    signingKeyHex: node.daemonPublicKey,
+    method: "POST",
+    path,
+    body,
+  })
+  if (!resp.ok) {
+    const err = (await resp.json().catch(() => null)) as {
+      error?: { code?: string }
+    } | null
+    const code = updateErrors[err?.error?.code ?? ""] ?? "nodes.update.failed"
+    throw new ApiException(code, { status: resp.status })
+  }
+  return resp.json()
+}
+
+const callLogLevels = async (
+  node: Awaited<ReturnType<typeof loadNode>>,
+  method: "GET" | "PUT",
+  body?: unknown
+): Promise<unknown> => {
+  if (node.daemonPublicKey === null) {
+    throw new ApiException("nodes.unreachable", { status: 503 })
+  }
+  assertNodeSupports(node, "log.levels")
+  const resp = await callDaemon({
+    baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+    nodeId: node.id,
+    signingKeyHex: node.daemonPublicKey,
+    method,
+    path: "/api/system/log-levels",
+    body,
+  })
+  if (!resp.ok) {
+    throw new ApiException("nodes.unreachable", { status: 502 })
+  }
+  return resp.json()
+}
+
+const sftpErrors: Record<string, ErrorCode> = {
+  "sftp.session_not_found": "nodes.sftp.session_not_found",
+  "sftp.ban_not_found": "nodes.sftp.ban_not_found",
+}
+
+const callSftpAdmin = async (
+  node: Awaited<ReturnType<typeof loadNode>>,
+  method: "GET" | "DELETE",
+  path: string
+): Promise<unknown> => {
+  if (node.daemonPublicKey === null) {
+    throw new ApiException("nodes.unreachable", { status: 503 })
+  }
+  assertNodeSupports(node, "sftp.sessions")
+  const resp = await callDaemon({
+    baseUrl: `${node.scheme}://${node.fqdn}:${node.daemonPort}`,
+    nodeId: node.id,
+    signingKeyHex: node.daemonPublicKey,
+    method,
+    path: `/api/system/sftp/${path}`,
+  })
+  if (!resp.ok) {
+    const err = (await resp.json().catch(() => null)) as {
+      error?: { code?: string }
+    } | null
+    const code = sftpErrors[err?.error?.code ?? ""]
+    if (code !== undefined) throw new ApiException(code, { status: 404 })
+    throw new ApiException("nodes.unreachable", { status: 502 })
+  }
+  return resp.json()
+}
+
+const loadNode = async (db: Db, id: string) => {
+  const node = (
+    await db.select().from(nodesTable).where(eq(nodesTable.id, id)).limit(1)
+  )[0]
+  if (node === undefined) {
+    throw new ApiException("nodes.not_found", { status: 404 })
+  }
+  return node
+}
@@ -0,0 +1,49 @@
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import { playerActionSchema } from "@workspace/shared/blueprint"
+import { apiValidationError } from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin, forwardServerDaemon } from "@/lib/ServerAccess"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+const playerBodySchema = z.object({
+  player: z.string().regex(/^[A-Za-z0-9_.-]{1,32}$/),
+  reason: z.string().max(256).optional(),
+})
+
+/**
+ * Structured player administration. The daemon renders the action
+ * through the blueprint's `playerCommands` templates, so the panel and
+ * plugins send `{player, reason}` without knowing the game's syntax.
+ */
+export const buildPlayersRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .post("/:serverId/players/:action", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const action = playerActionSchema.safeParse(c.req.param("action"))
+      if (!action.success) throw apiValidationError(action.error)
+      const parsed = playerBodySchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "players",
+        method: "POST",
+        path: `/players/${action.data}`,
+        body: parsed.data,
+      })
+      return c.json(await resp.json())
+    })
+}
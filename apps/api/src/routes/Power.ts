@@ -0,0 +1,121 @@
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import type { ErrorCode } from "@workspace/shared/error-codes"
+import { apiValidationError } from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin, forwardServerDaemon } from "@/lib/ServerAccess"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+export const countdownSchema = z.object({
+  seconds: z.number().int().min(1).max(3600),
+  // Console command sent at each warning; {{time}} becomes "5 minutes".
+  message: z
+    .string()
+    .max(256)
+    .regex(/^[^\r\n]*$/)
+    .optional(),
+  // Seconds before the restart to warn at; the daemon's defaults
+  // otherwise.
+  warnings: z.array(z.number().int().min(1).max(3600)).max(32).optional(),
+})
+
+const daemonErrors: Record<string, ErrorCode> = {
+  "power.in_progress": "servers.power.in_progress",
+  "power.queue_full": "servers.power.queue_full",
+  "power.no_countdown": "servers.power.no_countdown",
+}
+
+/**
+ * Power actions that need more than the console socket's `set state`:
+ * a restart with a player-facing countdown, and a view of the node's
+ * power queue for the server. Pause and unpause freeze a running
+ * server in place (memory kept) and thaw it; they're on the socket too.
+ */
+export const buildPowerRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .get("/:serverId/power/queue", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "power.queue",
+        method: "GET",
+        path: "/power",
+        errors: daemonErrors,
+      })
+      return c.json(await resp.json())
+    })
+    .post("/:serverId/power/restart-countdown", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const parsed = countdownSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "power.countdown",
+        method: "POST",
+        path: "/power",
+        body: { action: "restart", countdown: parsed.data },
+        errors: daemonErrors,
+      })
+      return c.json(await resp.json())
+    })
+    .delete("/:serverId/power/restart-countdown", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "power.countdown",
+        method: "DELETE",
+        path: "/power",
+        errors: daemonErrors,
+      })
+      return c.json({ ok: true })
+    })
+    .post("/:serverId/power/pause", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "power.pause",
+        method: "POST",
+        path: "/power",
+        body: { action: "pause" },
+        errors: daemonErrors,
+      })
+      return c.json(await resp.json())
+    })
+    .post("/:serverId/power/unpause", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await forwardServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "power.pause",
+        method: "POST",
+        path: "/power",
+        body: { action: "unpause" },
+        errors: daemonErrors,
+      })
+      return c.json(await resp.json())
+    })
+}
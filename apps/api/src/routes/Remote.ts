@@ -21,6 +21,7 @@ import { ApiException } from "@workspace/shared/errors"
 
 import { writeAudit } from "@/lib/Audit"
 import type { Env } from "@/env"
+import { resolveExtraHosts } from "@/lib/ExtraHosts"
 import type { StatusCache } from "@/lib/StatusCache"
 
 const statusCallbackSchema = z.object({
@@ -37,6 +38,67 @@ const auditCallbackSchema = z.object({
     .optional(),
 })
 
+const nodeAuditCallbackSchema = z.object({
+  action: z.string().min(1).max(120),
+  metadata: z
+    .record(z.string(), z.union([z.string(), z.number(), z.boolean()]))
+    .optional(),
+})
+
+const heartbeatSchema = z.object({
+  version: z.string().max(64).optional(),
+  capabilities: z
+    .object({
+      messages: z.object({
+        current: z.number().int(),
+        supported: z.array(z.number().int()),
+      }),
+      backupDrivers: z.array(z.string()),
+      transferModes: z.array(z.string()),
+      scheduleTasks: z.array(z.string()),
+      features: z.array(z.string()),
+    })
+    .optional(),
+  backupUsage: z
+    .object({
+      totalBytes: z.number().int().nonnegative(),
+      servers: z.record(z.string(), z.number().int().nonnegative()),
+    })
+    .optional(),
+  transfers: z
+    .object({
+      bandwidthMbps: z.number().int().nonnegative(),
+      window: z.string().optional(),
+      windowOpen: z.boolean(),
+      opensAt: z.string().optional(),
+    })
+    .optional(),
+  resources: z
+    .object({
+      cpus: z.number().int().nonnegative(),
+      memoryMb: z.number().int().nonnegative(),
+      diskMb: z.number().int().nonnegative(),
+      diskFreeMb: z.number().int().nonnegative(),
+    })
+    .optional(),
+  health: z
+    .object({
+      docker: z.boolean(),
+    })
+    .optional(),
+})
+
+const allocationUsageSchema = z.object({
+  running: z.array(z.string().uuid()),
+  bindings: z.array(
+    z.object({
+      serverId: z.string().uuid(),
+      ip: z.string(),
+      port: z.number().int().min(1).max(65535),
+    })
+  ),
+})
+
 /**
  * Daemon → API callback surface. Today: container status only. Mounted
  * unauthenticated at the route level — each handler verifies the per-
@@ -118,6 +180,8 @@ export const buildRemoteRoute = (params: {
           id: nodeAllocationsTable.id,
           ip: nodeAllocationsTable.ip,
           port: nodeAllocationsTable.port,
+          portEnd: nodeAllocationsTable.portEnd,
+          protocol: nodeAllocationsTable.protocol,
         })
         .from(nodeAllocationsTable)
         .innerJoin(
@@ -162,7 +226,9 @@ export const buildRemoteRoute = (params: {
         : blueprint.startupCommand
       // Translate the blueprint stop signal into the daemon's StopConfig
       // shape: leading `^` means "write rest to stdin", anything else is
-      // a kill signal name (SIGTERM, SIGINT, …).
+      // a kill signal name (SIGTERM, SIGINT, …). `timeoutMs` is the
+      // blueprint's stopping grace period before the daemon escalates
+      // to SIGKILL; 0 leaves the daemon default.
       const sig = blueprint.stopSignal ?? ""
       const stop = sig.startsWith("^")
         ? { type: "command" as const, value: sig.slice(1) }
@@ -173,16 +239,128 @@ export const buildRemoteRoute = (params: {
         dockerImage: row.server.dockerImage,
         startupCommand,
         environment: env_,
-        stop,
+        stop: {
+          ...stop,
+          timeoutMs: blueprint.lifecycle?.stopping?.graceTimeoutMs ?? 0,
+        },
         memoryLimitMb: row.server.memoryLimitMb,
+        memoryOverhead: blueprint.memoryOverhead ?? null,
         cpuLimitPercent: row.server.cpuLimitPercent,
+        diskLimitMb: row.server.diskLimitMb,
+        hostNetwork: row.server.hostNetwork,
+        // Blocks travel as one entry; the daemon expands them into
+        // per-port Docker bindings only at container create.
         ports: allocations.map((a) => ({
           hostIp: a.ip,
           hostPort: a.port,
+          hostPortEnd: a.portEnd ?? 0,
           containerPort: a.port,
+          protocol: a.protocol,
         })),
         startupDone,
         configFiles: blueprint.configFiles ?? [],
+        sidecars: (blueprint.sidecars ?? []).map((s) => ({
+          name: s.name,
+          image: s.image,
+          command: s.command ?? [],
+          environment: s.environment ?? {},
+          memoryLimitMb: s.memoryLimitMb ?? 0,
+        })),
+        services: (blueprint.services ?? []).map((s) => ({
+          name: s.name,
+          image: s.image,
+          command: s.command ?? [],
+          environment: s.environment ?? {},
+          memoryLimitMb: s.memoryLimitMb ?? 0,
+          dependsOn: s.dependsOn ?? [],
+          volumes: s.volumes ?? [],
+          console: s.console ?? false,
+        })),
+        playerCommands: blueprint.playerCommands ?? {},
+        idle: row.server.idlePolicy,
+        // Zero leaves the node's history_lines / console_buffer.
+        console: {
+          historyLines: row.server.consoleHistoryLines ?? 0,
+          buffer: row.server.consoleBuffer ?? 0,
+        },
+        extraHosts: await resolveExtraHosts(
+          db,
+          row.server.nodeId,
+          row.server.extraHosts ?? []
+        ),
+        // Merged with the node's defaults on the daemon.
+        files: {
+          denylist: [
+            ...(blueprint.files?.denylist ?? []),
+            ...(row.server.fileDenylist ?? []),
+          ],
+          hidden: blueprint.files?.hidden ?? [],
+        },
+      })
+    })
+    .post("/allocations/usage", async (c) => {
+      const ok = await verifyDaemonSignature({
+        db,
+        env,
+        headers: c.req.raw.headers,
+      })
+      if (!ok) {
+        throw new ApiException("auth.session.invalid", { status: 401 })
+      }
+      const nodeId = c.req.raw.headers.get("x-stellar-node-id") ?? ""
+      const parsed = allocationUsageSchema.safeParse(await c.req.json())
+      if (!parsed.success) {
+        throw new ApiException("validation.failed", { status: 422 })
+      }
+      const { running, bindings } = parsed.data
+      const runningSet = new Set(running)
+      const rows = await db
+        .select({
+          id: nodeAllocationsTable.id,
+          ip: nodeAllocationsTable.ip,
+          port: nodeAllocationsTable.port,
+          portEnd: nodeAllocationsTable.portEnd,
+          serverId: nodeAllocationsTable.serverId,
+        })
+        .from(nodeAllocationsTable)
+        .where(eq(nodeAllocationsTable.nodeId, nodeId))
+      // Docker reports wildcard binds as 0.0.0.0, which covers any
+      // allocation IP on the same port.
+      const matches = (ip: string, allocIp: string) =>
+        ip === allocIp || ip === "0.0.0.0" || allocIp === "0.0.0.0"
+      const stale: string[] = []
+      const matched = new Set<(typeof bindings)[number]>()
+      const now = new Date()
+      await db.transaction(async (tx) => {
+        for (const row of rows) {
+          const last = row.portEnd ?? row.port
+          const inRow = bindings.filter(
+            (b) => b.port >= row.port && b.port <= last && matches(b.ip, row.ip)
+          )
+          for (const b of inRow) matched.add(b)
+          const binding = inRow[0]
+          const boundServerId = binding?.serverId ?? null
+          // Stale: a running server that doesn't bind its allocation,
+          // or a port bound by a server it isn't assigned to.
+          const unbound =
+            row.serverId !== null &&
+            runningSet.has(row.serverId) &&
+            boundServerId !== row.serverId
+          const misbound =
+            boundServerId !== null && boundServerId !== row.serverId
+          if (unbound || misbound) {
+            stale.push(row.id)
+          }
+          await tx
+            .update(nodeAllocationsTable)
+            .set({ boundServerId, usageReportedAt: now })
+            .where(eq(nodeAllocationsTable.id, row.id))
+        }
+      })
+      return c.json({
+        ok: true,
+        stale,
+        unmanaged: bindings.filter((b) => !matched.has(b)),
       })
     })
     .post("/heartbeat", async (c) => {
@@ -195,9 +373,34 @@ export const buildRemoteRoute = (params: {
         throw new ApiException("auth.session.invalid", { status: 401 })
       }
       const nodeId = c.req.raw.headers.get("x-stellar-node-id") ?? ""
+      // Daemons that predate capability reporting send no body. Clear
+      // the columns for them so a downgraded node doesn't keep the newer
+      // build's feature list, backup usage, transfer window or health.
+      const parsed = heartbeatSchema.safeParse(
+        await c.req.json().catch(() => ({}))
+      )
+      if (!parsed.success) {
+        throw new ApiException("validation.failed", { status: 422 })
+      }
+      // Measured here too: the signature check already bounds the
+      // drift, but a node well inside it is worth warning about before
+      // its users' tokens start failing.
+      const health = parsed.data.health
+      const skew =
+        Math.floor(Date.now() / 1000) -
+        Number.parseInt(c.req.raw.headers.get("x-stellar-timestamp") ?? "", 10)
       await db
         .update(nodesTable)
-        .set({ connectedAt: new Date() })
+        .set({
+          connectedAt: new Date(),
+          capabilities: parsed.data.capabilities ?? null,
+          backupUsage: parsed.data.backupUsage ?? null,
+          transferPolicy: parsed.data.transfers ?? null,
+          daemonVersion: parsed.data.version ?? null,
+          resources: parsed.data.resources ?? null,
+          health:
+            health === undefined ? null : { ...health, clockSkewSeconds: skew },
+        })
         .where(eq(nodesTable.id, nodeId))
       return c.json({ ok: true })
     })
@@ -225,6 +428,30 @@ export const buildRemoteRoute = (params: {
       })
       return c.json({ ok: true })
     })
+    .post("/audit", async (c) => {
+      const ok = await verifyDaemonSignature({
+        db,
+        env,
+        headers: c.req.raw.headers,
+      })
+      if (!ok) {
+        throw new ApiException("auth.session.invalid", { status: 401 })
+      }
+      const nodeId = c.req.raw.headers.get("x-stellar-node-id") ?? ""
+      const parsed = nodeAuditCallbackSchema.safeParse(await c.req.json())
+      if (!parsed.success) {
+        throw new ApiException("validation.failed", { status: 422 })
+      }
+      await writeAudit({
+        db,
+        actorId: null,
+        action: parsed.data.action,
+        targetType: "node",
+        targetId: nodeId,
+        metadata: parsed.data.metadata,
+      })
+      return c.json({ ok: true })
+    })
 }
 
 /**
@@ -0,0 +1,272 @@
+import { and, eq, inArray } from "drizzle-orm"
+import { Hono } from "hono"
+import { z } from "zod"
+
+import type { Db } from "@workspace/db/client.types"
+import {
+  nodeAllocationsTable,
+  nodesTable,
+} from "@workspace/db/schema/nodes"
+import {
+  serverReplicationsTable,
+  serverTransfersTable,
+} from "@workspace/db/schema/transfers"
+import {
+  ApiException,
+  apiValidationError,
+} from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import type { Env } from "@/env"
+import { writeAudit } from "@/lib/Audit"
+import { nodeSpeaksTransferMode } from "@/lib/NodeCapabilities"
+import { syncReplication, withoutSync } from "@/lib/ReplicationQueue"
+import { assertOwnerOrAdmin, callServerDaemon } from "@/lib/ServerAccess"
+import { switchServerNode, transferSecure } from "@/lib/TransferRunner"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+const configureSchema = z.object({
+  standbyNodeId: z.string().uuid(),
+  standbyAllocationId: z.string().uuid(),
+  intervalMinutes: z.number().int().min(5).max(7 * 24 * 60).default(60),
+})
+
+/**
+ * Warm standbys. A server's files are pushed to its standby node by the
+ * ReplicationQueue (or on demand) with the transfer machinery, and
+ * promoting the standby switches the server over to it the way a
+ * finished transfer does — without a push, so it works with the
+ * server's node gone. Whatever changed since the last sync is lost.
+ */
+export const buildReplicationRoute = (params: {
+  auth: Auth
+  db: Db
+  env: Env
+}) => {
+  const { auth, db, env } = params
+  const requireSession = buildRequireSession(auth)
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .get("/:serverId/replication", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const replication = await loadReplication(db, serverId)
+      return c.json({ replication: replication ?? null })
+    })
+    .put("/:serverId/replication", async (c) => {
+      const serverId = c.req.param("serverId")
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const parsed = configureSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const { standbyNodeId, standbyAllocationId, intervalMinutes } =
+        parsed.data
+      if (standbyNodeId === server.nodeId) {
+        throw new ApiException("replication.same_node", { status: 409 })
+      }
+      const nodes = await db
+        .select()
+        .from(nodesTable)
+        .where(inArray(nodesTable.id, [server.nodeId, standbyNodeId]))
+      const sourceNode = nodes.find((n) => n.id === server.nodeId)
+      const standbyNode = nodes.find((n) => n.id === standbyNodeId)
+      if (standbyNode === undefined) {
+        throw new ApiException("nodes.not_found", { status: 404 })
+      }
+      if (
+        sourceNode === undefined ||
+        sourceNode.daemonPublicKey === null ||
+        standbyNode.daemonPublicKey === null
+      ) {
+        throw new ApiException("nodes.unreachable", { status: 503 })
+      }
+      // Without delta pushes every sync would resend the whole server.
+      if (
+        !nodeSpeaksTransferMode(sourceNode, "delta") ||
+        !nodeSpeaksTransferMode(standbyNode, "delta")
+      ) {
+        throw new ApiException("nodes.unsupported_feature", { status: 409 })
+      }
+      if (!transferSecure(sourceNode, standbyNode)) {
+        throw new ApiException("transfers.insecure", { status: 409 })
+      }
+      const existing = await loadReplication(db, serverId)
+      const alloc = (
+        await db
+          .select()
+          .from(nodeAllocationsTable)
+          .where(eq(nodeAllocationsTable.id, standbyAllocationId))
+          .limit(1)
+      )[0]
+      const ownStandby =
+        existing?.standbyAllocationId === standbyAllocationId &&
+        alloc?.serverId === serverId
+      if (
+        alloc === undefined ||
+        alloc.nodeId !== standbyNodeId ||
+        (alloc.serverId !== null && !ownStandby)
+      ) {
+        throw new ApiException("transfers.allocation_unavailable", {
+          status: 409,
+        })
+      }
+      const row = await db.transaction(async (tx) => {
+        // The standby allocation is held for the server, not linked to
+        // it; promotion links it.
+        if (
+          existing !== undefined &&
+          existing.standbyAllocationId !== standbyAllocationId
+        ) {
+          await tx
+            .update(nodeAllocationsTable)
+            .set({ serverId: null })
+            .where(eq(nodeAllocationsTable.id, existing.standbyAllocationId))
+        }
+        await tx
+          .update(nodeAllocationsTable)
+          .set({ serverId })
+          .where(eq(nodeAllocationsTable.id, standbyAllocationId))
+        const standbyMoved = existing?.standbyNodeId !== standbyNodeId
+        const [saved] = await tx
+          .insert(serverReplicationsTable)
+          .values({
+            serverId,
+            standbyNodeId,
+            standbyAllocationId,
+            intervalMinutes,
+          })
+          .onConflictDoUpdate({
+            target: serverReplicationsTable.serverId,
+            set: {
+              standbyNodeId,
+              standbyAllocationId,
+              intervalMinutes,
+              // A new standby starts without a copy.
+              ...(standbyMoved
+                ? { lastSyncedAt: null, lastAttemptAt: null, lastError: null }
+                : {}),
+            },
+          })
+          .returning()
+        return saved
+      })
+      return c.json({ replication: row })
+    })
+    .delete("/:serverId/replication", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const replication = await requireReplication(db, serverId)
+      await db.transaction(async (tx) => {
+        await tx
+          .update(nodeAllocationsTable)
+          .set({ serverId: null })
+          .where(eq(nodeAllocationsTable.id, replication.standbyAllocationId))
+        await tx
+          .delete(serverReplicationsTable)
+          .where(eq(serverReplicationsTable.serverId, serverId))
+      })
+      return c.json({ ok: true })
+    })
+    .post("/:serverId/replication/sync", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const replication = await requireReplication(db, serverId)
+      if (await transferring(db, serverId)) {
+        throw new ApiException("transfers.in_progress", { status: 409 })
+      }
+      const code = await syncReplication({ db, env, replication })
+      if (code === "replication.busy") {
+        throw new ApiException("replication.busy", { status: 409 })
+      }
+      return c.json({ replication: await loadReplication(db, serverId) })
+    })
+    .post("/:serverId/replication/promote", async (c) => {
+      const serverId = c.req.param("serverId")
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const replication = await requireReplication(db, serverId)
+      if (replication.lastSyncedAt === null) {
+        throw new ApiException("replication.not_synced", { status: 409 })
+      }
+      if (await transferring(db, serverId)) {
+        throw new ApiException("transfers.in_progress", { status: 409 })
+      }
+      // A sync still writing to the standby would race the server
+      // starting there; hold the slot so none starts either.
+      const promoted = await withoutSync(serverId, async () => {
+        // Best effort: the point of promoting is usually that the node
+        // is gone, but if it isn't, the old copy shouldn't keep
+        // running alongside the new one.
+        await callServerDaemon({
+          db,
+          serverId,
+          actor: c.get("user"),
+          method: "POST",
+          path: "/power",
+          body: { action: "stop" },
+          signal: AbortSignal.timeout(5_000),
+        }).catch(() => null)
+        // The old node keeps its copy; it's no longer the server's once
+        // nodeId moves.
+        await db.transaction((tx) =>
+          switchServerNode(tx, {
+            serverId,
+            nodeId: replication.standbyNodeId,
+            allocationId: replication.standbyAllocationId,
+          })
+        )
+      })
+      if (promoted === "replication.busy") {
+        throw new ApiException("replication.busy", { status: 409 })
+      }
+      void writeAudit({
+        db,
+        actorId: c.get("user").id,
+        action: "servers.replication.promoted",
+        targetType: "server",
+        targetId: serverId,
+        metadata: {
+          sourceNode: server.nodeId,
+          targetNode: replication.standbyNodeId,
+          lastSyncedAt: replication.lastSyncedAt.toISOString(),
+        },
+      })
+      return c.json({ ok: true })
+    })
+}
+
+const loadReplication = async (db: Db, serverId: string) =>
+  (
+    await db
+      .select()
+      .from(serverReplicationsTable)
+      .where(eq(serverReplicationsTable.serverId, serverId))
+      .limit(1)
+  )[0]
+
+const requireReplication = async (db: Db, serverId: string) => {
+  const replication = await loadReplication(db, serverId)
+  if (replication === undefined) {
+    throw new ApiException("replication.not_configured", { status: 404 })
+  }
+  return replication
+}
+
+const transferring = async (db: Db, serverId: string): Promise<boolean> =>
+  (
+    await db
+      .select({ id: serverTransfersTable.id })
+      .from(serverTransfersTable)
+      .where(
+        and(
+          eq(serverTransfersTable.serverId, serverId),
+          inArray(serverTransfersTable.status, [
+            "pending",
+            "queued",
+            "running",
+          ])
+        )
+      )
+      .limit(1)
+  ).length > 0
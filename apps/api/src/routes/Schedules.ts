@@ -7,13 +7,13 @@ import {
   scheduleTasksTable,
   schedulesTable,
 } from "@workspace/db/schema/schedules"
-import { serversTable } from "@workspace/db/schema/servers"
 import {
   ApiException,
   apiValidationError,
 } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin } from "@/lib/ServerAccess"
 import {
   buildRequireSession,
   type AuthVariables,
@@ -21,7 +21,14 @@ import {
 
 const taskSchema = z.object({
   sortOrder: z.number().int().nonnegative(),
-  action: z.enum(["power", "command", "backup"]),
+  action: z.enum([
+    "power",
+    "command",
+    "macro",
+    "backup",
+    "update",
+    "prune_logs",
+  ]),
   delaySeconds: z.number().int().nonnegative(),
   payload: z
     .record(z.string(), z.union([z.string(), z.number(), z.boolean()]))
@@ -42,7 +49,7 @@ export const buildSchedulesRoute = (params: { auth: Auth; db: Db }) => {
     .use("*", requireSession)
     .get("/:serverId/schedules", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const schedules = await db
         .select()
         .from(schedulesTable)
@@ -74,7 +81,7 @@ export const buildSchedulesRoute = (params: { auth: Auth; db: Db }) => {
     })
     .post("/:serverId/schedules", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const parsed = scheduleInputSchema.safeParse(await c.req.json())
       if (!parsed.success) throw apiValidationError(parsed.error)
       const out = await db.transaction(async (tx) => {
@@ -106,7 +113,7 @@ export const buildSchedulesRoute = (params: { auth: Auth; db: Db }) => {
     .patch("/:serverId/schedules/:scheduleId", async (c) => {
       const serverId = c.req.param("serverId")
       const scheduleId = c.req.param("scheduleId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const parsed = scheduleInputSchema.safeParse(await c.req.json())
       if (!parsed.success) throw apiValidationError(parsed.error)
       const out = await db.transaction(async (tx) => {
@@ -148,7 +155,7 @@ export const buildSchedulesRoute = (params: { auth: Auth; db: Db }) => {
     .delete("/:serverId/schedules/:scheduleId", async (c) => {
       const serverId = c.req.param("serverId")
       const scheduleId = c.req.param("scheduleId")
-      await assertAccess(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       await db
         .delete(schedulesTable)
         .where(
@@ -160,23 +167,3 @@ export const buildSchedulesRoute = (params: { auth: Auth; db: Db }) => {
       return c.json({ ok: true })
     })
 }
-
-const assertAccess = async (
-  db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-): Promise<void> => {
-  const server = (
-    await db
-      .select({ ownerId: serversTable.ownerId })
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin === true) return
-  if (server.ownerId === user.id) return
-  throw new ApiException("permissions.denied", { status: 403 })
-}
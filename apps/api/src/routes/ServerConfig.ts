@@ -0,0 +1,197 @@
+import { eq } from "drizzle-orm"
+import { Hono } from "hono"
+
+import type { Db } from "@workspace/db/client.types"
+import { blueprintsTable } from "@workspace/db/schema/blueprints"
+import { serverVariablesTable } from "@workspace/db/schema/servers"
+import { logPruningSchema } from "@workspace/shared/blueprint"
+import {
+  ApiException,
+  apiValidationError,
+} from "@workspace/shared/errors"
+
+import type { Auth } from "@/auth"
+import { loadLogPruning, logPruneRequest } from "@/lib/LogPruning"
+import { nodeSupports } from "@/lib/NodeCapabilities"
+import {
+  assertOwnerOrAdmin,
+  callServerDaemon,
+  loadServerNode,
+} from "@/lib/ServerAccess"
+import { checkVariableRules } from "@/lib/VariableRules"
+import {
+  buildRequireSession,
+  type AuthVariables,
+} from "@/middleware/RequireSession"
+
+interface PreflightCheck {
+  id: string
+  status: "pass" | "warn" | "fail" | "skip"
+  message: string
+}
+
+/**
+ * Debugging view of a server's configuration as the daemon resolves it
+ * for the next start: limits with overhead, flattened and substituted
+ * environment, network mode, port bindings, mounts and stop sequence.
+ * The daemon pulls the panel config first, so a mismatch between this
+ * and the panel's own view points at the daemon side.
+ *
+ * `power/validate` is a dry-run start: the panel checks variables
+ * against their blueprint rules, the daemon runs its pre-start checks
+ * (image, ports, disk, admission, ...) and the combined checklist comes
+ * back without anything being started or pulled.
+ *
+ * `config/apply` writes the current variables into the blueprint's
+ * config files now rather than at the next start, and returns the keys
+ * that changed in each file so the user can reload the game's config.
+ *
+ * `logs/prune/preview` is a dry run of the server's log pruning: the
+ * files a `prune_logs` task would delete now, and what would be left.
+ * A policy in the body is previewed instead of the saved one, so an
+ * edit can be checked before it's saved.
+ */
+export const buildServerConfigRoute = (params: { auth: Auth; db: Db }) => {
+  const { auth, db } = params
+  const requireSession = buildRequireSession(auth)
+
+  return new Hono<{ Variables: AuthVariables }>()
+    .use("*", requireSession)
+    .get("/:serverId/config/effective", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await callServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "config.effective",
+        method: "GET",
+        path: "/config/effective",
+      })
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      return c.json(await resp.json())
+    })
+    .post("/:serverId/config/apply", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const resp = await callServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "config.apply",
+        method: "POST",
+        path: "/config/apply",
+      })
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      return c.json(await resp.json())
+    })
+    .post("/:serverId/logs/prune/preview", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      // No body previews the saved policy.
+      const body: unknown = await c.req.json().catch(() => null)
+      const parsed = logPruningSchema.nullable().safeParse(body)
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const policy = parsed.data ?? (await loadLogPruning(db, serverId))
+      if (policy === null) {
+        throw new ApiException("servers.log_pruning_off", { status: 409 })
+      }
+      const resp = await callServerDaemon({
+        db,
+        serverId,
+        actor: c.get("user"),
+        feature: "logs.prune",
+        method: "POST",
+        path: "/logs/prune",
+        body: logPruneRequest(policy, true),
+      })
+      if (!resp.ok) {
+        throw new ApiException("internal.unexpected", { status: 502 })
+      }
+      return c.json(await resp.json())
+    })
+    .post("/:serverId/power/validate", async (c) => {
+      const serverId = c.req.param("serverId")
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
+      const { node, server } = await loadServerNode(db, serverId)
+      const checks = await checkVariables(db, server.id, server.blueprintId)
+      if (node.daemonPublicKey === null) {
+        checks.push({
+          id: "node",
+          status: "fail",
+          message: "the node has not been paired",
+        })
+      } else if (!nodeSupports(node, "power.validate")) {
+        checks.push({
+          id: "node",
+          status: "skip",
+          message: "the node's daemon predates pre-flight checks; upgrade it",
+        })
+      } else {
+        const resp = await callServerDaemon({
+          db,
+          serverId,
+          actor: c.get("user"),
+          method: "POST",
+          path: "/power/validate",
+        }).catch(() => null)
+        if (resp === null || !resp.ok) {
+          checks.push({
+            id: "node",
+            status: "fail",
+            message: "the node's daemon is unreachable",
+          })
+        } else {
+          const body = (await resp.json()) as { checks: PreflightCheck[] }
+          checks.push(...body.checks)
+        }
+      }
+      return c.json({
+        ok: checks.every((check) => check.status !== "fail"),
+        checks,
+      })
+    })
+}
+
+/**
+ * One check covering every blueprint variable: fails listing each value
+ * that breaks its rules. Unset variables are checked at their default.
+ */
+const checkVariables = async (
+  db: Db,
+  serverId: string,
+  blueprintId: string
+): Promise<PreflightCheck[]> => {
+  const [values, blueprint] = await Promise.all([
+    db
+      .select()
+      .from(serverVariablesTable)
+      .where(eq(serverVariablesTable.serverId, serverId)),
+    db
+      .select({ variables: blueprintsTable.variables })
+      .from(blueprintsTable)
+      .where(eq(blueprintsTable.id, blueprintId))
+      .limit(1)
+      .then((rows) => rows[0] ?? null),
+  ])
+  const valueByKey = new Map<string, string>()
+  for (const row of values) valueByKey.set(row.variableKey, row.value)
+  const problems: string[] = []
+  for (const v of blueprint?.variables ?? []) {
+    const problem = checkVariableRules(
+      v.rules,
+      valueByKey.get(v.key) ?? v.default
+    )
+    if (problem !== null) problems.push(`${v.name} (${v.key}) ${problem}`)
+  }
+  if (problems.length > 0) {
+    return [{ id: "variables", status: "fail", message: problems.join("; ") }]
+  }
+  return [
+    { id: "variables", status: "pass", message: "all variables are valid" },
+  ]
+}
@@ -17,13 +17,21 @@ import type { DaemonJwtScope } from "@workspace/shared/jwt.types"
 import type { Auth } from "@/auth"
 import type { Env } from "@/env"
 import { writeAudit } from "@/lib/Audit"
-import type { InstallRunner } from "@/lib/InstallRunner"
+import {
+  installArchiveSchema,
+  type InstallRunner,
+  type Rollout,
+  type RolloutServer,
+} from "@/lib/InstallRunner"
+import { assertNodeSupports } from "@/lib/NodeCapabilities"
+import { loadServerAccess } from "@/lib/ServerAccess"
+import { fetchServerStates, pollServerStates } from "@/lib/ServerStates"
 import type { StatusCache } from "@/lib/StatusCache"
 import { mintDaemonToken } from "@/lib/Tokens"
 import { buildRequireSession, type AuthVariables } from "@/middleware/RequireSession"
 
 const credentialsBodySchema = z.object({
-  purpose: z.enum(["console", "files", "sftp"]).default("console"),
+  purpose: z.enum(["console", "files", "sftp", "exec"]).default("console"),
 })
 
 const purposeScopes: Record<
@@ -49,8 +57,16 @@ const purposeScopes: Record<
     scopes: ["sftp"],
     ttlSeconds: 86_400,
   },
+  // Container exec for support staff. Panel admins only — not granted
+  // to owners or subusers even though they hold every other scope.
+  exec: {
+    scopes: ["admin.exec"],
+    ttlSeconds: 300,
+  },
 }
 
+const adminOnlyScopes = new Set<DaemonJwtScope>(["admin.exec"])
+
 const createServerSchema = z.object({
   name: z.string().min(1).max(120),
   description: z.string().max(500).optional(),
@@ -63,8 +79,35 @@ const createServerSchema = z.object({
   diskLimitMb: z.number().int().positive(),
   startupExtra: z.string().optional(),
   variables: z.record(z.string(), z.string()).default({}),
+  installArchive: installArchiveSchema.optional(),
+})
+
+const bulkReinstallSchema = z.object({
+  serverIds: z
+    .array(z.string().uuid())
+    .min(1)
+    .max(1_000)
+    .transform((ids) => [...new Set(ids)]),
+  concurrency: z.number().int().min(1).max(50).default(5),
 })
 
+// Null turns idle shutdown off.
+const idlePolicySchema = z
+  .object({
+    minutes: z.number().int().min(5).max(10_080),
+    signals: z
+      .array(z.enum(["players", "console", "cpu"]))
+      .min(1)
+      .transform((s) => [...new Set(s)]),
+    cpuPercent: z.number().min(0).max(100_000).default(0),
+    startOnDemand: z.boolean().default(false),
+    wakeMessage: z.string().min(1).max(256).nullable().default(null),
+  })
+  .refine((p) => !p.signals.includes("cpu") || p.cpuPercent > 0, {
+    path: ["cpuPercent"],
+  })
+  .nullable()
+
 /**
  * Server CRUD + the credentials endpoint that mints the per-node JWT
  * the browser uses to dial the daemon directly.
@@ -82,33 +125,7 @@ export const buildServersRoute = (params: {
   return new Hono<{ Variables: AuthVariables }>()
     .use("*", requireSession)
     .get("/", async (c) => {
-      const user = c.get("user")
-      const ownedRows = user.isAdmin === true
-        ? await db.select().from(serversTable)
-        : await db
-            .select({ s: serversTable })
-            .from(serversTable)
-            .leftJoin(
-              serverSubusersTable,
-              eq(serverSubusersTable.serverId, serversTable.id)
-            )
-            .where(
-              or(
-                eq(serversTable.ownerId, user.id),
-                eq(serverSubusersTable.userId, user.id)
-              )
-            )
-            .then((rows) => {
-              const seen = new Set<string>()
-              const out = []
-              for (const r of rows) {
-                const row = "s" in r ? r.s : r
-                if (seen.has(row.id)) continue
-                seen.add(row.id)
-                out.push(row)
-              }
-              return out
-            })
+      const ownedRows = await listAccessibleServers(db, c.get("user"))
       // Overlay live status from Redis cache (daemon's HTTP callback
       // populates it). Rows without a cache entry use the DB value.
       const cached = await statusCache.getMany(ownedRows.map((r) => r.id))
@@ -118,6 +135,68 @@ export const buildServersRoute = (params: {
       }))
       return c.json({ servers: merged })
     })
+    // Live state of every server the user can see: process state,
+    // busy flags, uptime, CPU and memory. Servers on nodes that can't
+    // report are left out; the list's cached status covers them.
+    .get("/states", async (c) => {
+      const rows = await listAccessibleServers(db, c.get("user"))
+      return c.json({ states: await fetchServerStates(db, rows) })
+    })
+    // Long poll for state changes: answers as soon as any of the
+    // user's servers changes process state or flags, with the states
+    // that changed and the cursor for the next poll. Without a cursor
+    // every server comes back at once.
+    .get("/states/changes", async (c) => {
+      const rows = await listAccessibleServers(db, c.get("user"))
+      const result = await pollServerStates({
+        db,
+        rows,
+        cursor: c.req.query("cursor"),
+        signal: c.req.raw.signal,
+      })
+      return c.json(result)
+    })
+    .post("/bulk/reinstall", async (c) => {
+      const user = c.get("user")
+      const parsed = bulkReinstallSchema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      const { serverIds, concurrency } = parsed.data
+      // Same rule as reinstalling each one by hand, checked up front so
+      // a rollout never stops halfway on a server it can't touch.
+      for (const id of serverIds) {
+        const access = await loadServerAccess(db, user, id)
+        if (access.role !== "owner" && access.role !== "admin") {
+          throw new ApiException("permissions.denied", { status: 403 })
+        }
+      }
+      const rollout = installRunner.startRollout({
+        serverIds,
+        concurrency,
+        startedBy: user.id,
+      })
+      void writeAudit({
+        db,
+        actorId: user.id,
+        action: "servers.bulk_reinstall",
+        metadata: {
+          rolloutId: rollout.id,
+          servers: serverIds.length,
+          concurrency,
+        },
+      })
+      return rolloutStream(installRunner, rollout)
+    })
+    .get("/bulk/reinstall/:rolloutId", async (c) => {
+      const user = c.get("user")
+      const rollout = installRunner.getRollout(c.req.param("rolloutId"))
+      if (
+        rollout === undefined ||
+        (rollout.startedBy !== user.id && user.isAdmin !== true)
+      ) {
+        throw new ApiException("servers.rollout_not_found", { status: 404 })
+      }
+      return c.json({ rollout })
+    })
     .get("/:id", async (c) => {
       const id = c.req.param("id")
       const user = c.get("user")
@@ -251,6 +330,46 @@ export const buildServersRoute = (params: {
         .where(eq(serversTable.id, id))
       return c.json({ ok: true })
     })
+    .get("/:id/idle", async (c) => {
+      const id = c.req.param("id")
+      const access = await loadServerAccess(db, c.get("user"), id)
+      return c.json({ idlePolicy: access.server.idlePolicy })
+    })
+    // Takes effect from the server's next start, when the daemon pulls
+    // its config.
+    .patch("/:id/idle", async (c) => {
+      const id = c.req.param("id")
+      const user = c.get("user")
+      const access = await loadServerAccess(db, user, id)
+      if (access.role !== "owner" && access.role !== "admin") {
+        throw new ApiException("permissions.denied", { status: 403 })
+      }
+      const schema = z.object({ idlePolicy: idlePolicySchema })
+      const parsed = schema.safeParse(await c.req.json())
+      if (!parsed.success) throw apiValidationError(parsed.error)
+      if (parsed.data.idlePolicy !== null) {
+        const node = (
+          await db
+            .select()
+            .from(nodesTable)
+            .where(eq(nodesTable.id, access.server.nodeId))
+            .limit(1)
+        )[0]
+        if (node === undefined) {
+          throw new ApiException("nodes.not_found", { status: 404 })
+        }
+        // An older daemon would ignore the policy and never stop it.
+        assertNodeSupports(node, "idle.shutdown")
+        if (parsed.data.idlePolicy.startOnDemand) {
+          assertNodeSupports(node, "wake.connect")
+        }
+      }
+      await db
+        .update(serversTable)
+        .set({ idlePolicy: parsed.data.idlePolicy, updatedAt: new Date() })
+        .where(eq(serversTable.id, id))
+      return c.json({ idlePolicy: parsed.data.idlePolicy })
+    })
     .patch("/:id/blueprint", async (c) => {
       const id = c.req.param("id")
       const user = c.get("user")
@@ -391,6 +510,9 @@ export const buildServersRoute = (params: {
           status: 409,
         })
       }
+      if (input.installArchive !== undefined) {
+        assertNodeSupports(node, "install.archive")
+      }
       if (!Object.values(blueprint.dockerImages).includes(input.dockerImage)) {
         throw new ApiException("servers.startup.invalid_docker_image", {
           status: 422,
@@ -437,6 +559,7 @@ export const buildServersRoute = (params: {
             startupExtra: input.startupExtra ?? null,
             status: "offline",
             installState: "pending",
+            installArchive: input.installArchive ?? null,
           })
           .returning()
         if (row === undefined) throw new Error("insert failed")
@@ -611,6 +734,9 @@ export const buildServersRoute = (params: {
       ) {
         throw new ApiException("permissions.denied", { status: 403 })
       }
+      // A daemon that doesn't know the paths claim would ignore it and
+      // hand out the whole server.
+      if (access.sftpPaths !== null) assertNodeSupports(node, "sftp.paths")
       const minted = mintDaemonToken({
         signingKeyHex: node.daemonPublicKey,
         userId: user.id,
@@ -618,6 +744,7 @@ export const buildServersRoute = (params: {
         nodeId: node.id,
         scope: ["sftp"],
         ttlSeconds: 86_400,
+        paths: access.sftpPaths ?? undefined,
       })
       return c.json({
         host: node.fqdn,
@@ -641,9 +768,12 @@ export const buildServersRoute = (params: {
       const allowedSet = new Set<DaemonJwtScope>(
         access.permissions as DaemonJwtScope[]
       )
-      // Owner / admin gets every scope the purpose declares.
-      const granted: DaemonJwtScope[] = config.scopes.filter(
-        (s) => access.role === "owner" || access.role === "admin" || allowedSet.has(s)
+      // Owner / admin gets every scope the purpose declares, except the
+      // admin-only ones.
+      const granted: DaemonJwtScope[] = config.scopes.filter((s) =>
+        adminOnlyScopes.has(s)
+          ? access.role === "admin"
+          : access.role === "owner" || access.role === "admin" || allowedSet.has(s)
       )
       if (granted.length === 0) {
         throw new ApiException("permissions.denied", {
@@ -667,6 +797,9 @@ export const buildServersRoute = (params: {
           params: { node: node.name },
         })
       }
+      if (granted.includes("sftp") && access.sftpPaths !== null) {
+        assertNodeSupports(node, "sftp.paths")
+      }
       const minted = mintDaemonToken({
         signingKeyHex: node.daemonPublicKey,
         userId: user.id,
@@ -674,6 +807,9 @@ export const buildServersRoute = (params: {
         nodeId: node.id,
         scope: granted,
         ttlSeconds: config.ttlSeconds,
+        paths: granted.includes("sftp")
+          ? (access.sftpPaths ?? undefined)
+          : undefined,
       })
       const browserBase = env.APP_BASE_URL.replace(/\/$/, "")
       const wsBase = browserBase.replace(/^http/, "ws")
@@ -696,42 +832,86 @@ export const buildServersRoute = (params: {
     })
 }
 
-type ServerAccess = {
-  server: typeof serversTable.$inferSelect
-  role: "owner" | "subuser" | "admin"
-  permissions: string[]
-}
-
-const loadServerAccess = async (
+/**
+ * The servers `user` can see: every server for admins, otherwise the
+ * ones they own or are a subuser of.
+ */
+const listAccessibleServers = async (
   db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-): Promise<ServerAccess> => {
-  const server = (
-    await db
-      .select()
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin === true) {
-    return { server, role: "admin", permissions: [] }
-  }
-  if (server.ownerId === user.id) {
-    return { server, role: "owner", permissions: [] }
-  }
-  const sub = (
-    await db
-      .select()
-      .from(serverSubusersTable)
-      .where(eq(serverSubusersTable.serverId, serverId))
-      .limit(1)
-  )[0]
-  if (sub === undefined || sub.userId !== user.id) {
-    throw new ApiException("permissions.denied", { status: 403 })
+  user: { id: string; isAdmin?: boolean | null }
+): Promise<(typeof serversTable.$inferSelect)[]> => {
+  if (user.isAdmin === true) return db.select().from(serversTable)
+  const rows = await db
+    .select({ s: serversTable })
+    .from(serversTable)
+    .leftJoin(
+      serverSubusersTable,
+      eq(serverSubusersTable.serverId, serversTable.id)
+    )
+    .where(
+      or(
+        eq(serversTable.ownerId, user.id),
+        eq(serverSubusersTable.userId, user.id)
+      )
+    )
+  const seen = new Set<string>()
+  const out = []
+  for (const { s: row } of rows) {
+    if (seen.has(row.id)) continue
+    seen.add(row.id)
+    out.push(row)
   }
-  return { server, role: "subuser", permissions: sub.permissions }
+  return out
+}
+
+/**
+ * NDJSON progress of a bulk reinstall: first the rollout with every
+ * server's state so far, then a `{serverId, state, exitCode}` line as
+ * each starts and finishes, then `{done, succeeded, failed}`. Closing
+ * the stream leaves the rollout running; GET it by id to catch up.
+ */
+const rolloutStream = (
+  installRunner: InstallRunner,
+  rollout: Rollout
+): Response => {
+  const encoder = new TextEncoder()
+  let unwatch = (): void => {}
+  const body = new ReadableStream<Uint8Array>({
+    start(controller) {
+      const send = (frame: unknown): void => {
+        controller.enqueue(encoder.encode(JSON.stringify(frame) + "\n"))
+      }
+      const count = (state: RolloutServer["state"]): number =>
+        rollout.servers.filter((s) => s.state === state).length
+      const finish = (): void => {
+        unwatch()
+        send({
+          done: true,
+          succeeded: count("succeeded"),
+          failed: count("failed"),
+        })
+        controller.close()
+      }
+      send({ rollout })
+      const settled = (s: RolloutServer): boolean =>
+        s.state === "succeeded" || s.state === "failed"
+      if (rollout.servers.every(settled)) {
+        finish()
+        return
+      }
+      unwatch = installRunner.watchRollout(rollout.id, (s) => {
+        send(s)
+        if (rollout.servers.every(settled)) finish()
+      })
+    },
+    cancel() {
+      unwatch()
+    },
+  })
+  return new Response(body, {
+    headers: {
+      "Content-Type": "application/x-ndjson",
+      "Cache-Control": "no-cache",
+    },
+  })
 }
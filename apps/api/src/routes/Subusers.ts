@@ -4,28 +4,40 @@ import { z } from "zod"
 
 import type { Db } from "@workspace/db/client.types"
 import { usersTable } from "@workspace/db/schema/auth"
-import {
-  serverSubusersTable,
-  serversTable,
-} from "@workspace/db/schema/servers"
+import { serverSubusersTable } from "@workspace/db/schema/servers"
 import {
   ApiException,
   apiValidationError,
 } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
+import { assertOwnerOrAdmin } from "@/lib/ServerAccess"
 import {
   buildRequireSession,
   type AuthVariables,
 } from "@/middleware/RequireSession"
 
+// An absolute directory inside the server, e.g. "/plugins/MyPlugin".
+// Normalised to no trailing slash; ".." segments are rejected.
+const sftpPathSchema = z
+  .string()
+  .max(512)
+  .regex(/^\//)
+  .refine((p) => !p.split("/").includes(".."))
+  .transform((p) => p.replace(/\/+$/, "") || "/")
+
+// Empty or null means the whole server.
+const sftpPathsSchema = z.array(sftpPathSchema).max(32).nullable().optional()
+
 const inviteSchema = z.object({
   email: z.string().email(),
   permissions: z.array(z.string()).min(1),
+  sftpPaths: sftpPathsSchema,
 })
 
 const updateSchema = z.object({
   permissions: z.array(z.string()).min(1),
+  sftpPaths: sftpPathsSchema,
 })
 
 export const buildSubusersRoute = (params: { auth: Auth; db: Db }) => {
@@ -43,6 +55,7 @@ export const buildSubusersRoute = (params: { auth: Auth; db: Db }) => {
           serverId: serverSubusersTable.serverId,
           userId: serverSubusersTable.userId,
           permissions: serverSubusersTable.permissions,
+          sftpPaths: serverSubusersTable.sftpPaths,
           createdAt: serverSubusersTable.createdAt,
           email: usersTable.email,
           name: usersTable.name,
@@ -73,6 +86,7 @@ export const buildSubusersRoute = (params: { auth: Auth; db: Db }) => {
           serverId,
           userId: target.id,
           permissions: parsed.data.permissions,
+          sftpPaths: normaliseSftpPaths(parsed.data.sftpPaths),
         })
         .returning()
       if (row === undefined) {
@@ -94,7 +108,13 @@ export const buildSubusersRoute = (params: { auth: Auth; db: Db }) => {
       if (!parsed.success) throw apiValidationError(parsed.error)
       const [row] = await db
         .update(serverSubusersTable)
-        .set({ permissions: parsed.data.permissions })
+        .set({
+          permissions: parsed.data.permissions,
+          // Omitted leaves the current restriction; null clears it.
+          ...(parsed.data.sftpPaths !== undefined && {
+            sftpPaths: normaliseSftpPaths(parsed.data.sftpPaths),
+          }),
+        })
         .where(
           and(
             eq(serverSubusersTable.id, subuserId),
@@ -136,22 +156,9 @@ export const buildSubusersRoute = (params: { auth: Auth; db: Db }) => {
     })
 }
 
-const assertOwnerOrAdmin = async (
-  db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-): Promise<void> => {
-  const server = (
-    await db
-      .select({ ownerId: serversTable.ownerId })
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin === true) return
-  if (server.ownerId === user.id) return
-  throw new ApiException("permissions.denied", { status: 403 })
-}
+const normaliseSftpPaths = (
+  paths: string[] | null | undefined
+): string[] | null =>
+  paths === null || paths === undefined || paths.length === 0
+    ? null
+    : [...new Set(paths)]
@@ -1,6 +1,4 @@
-import { createHmac } from "node:crypto"
-
-import { desc, eq } from "drizzle-orm"
+import { and, desc, eq, inArray } from "drizzle-orm"
 import { Hono } from "hono"
 import { z } from "zod"
 
@@ -9,10 +7,6 @@ import {
   nodeAllocationsTable,
   nodesTable,
 } from "@workspace/db/schema/nodes"
-import {
-  serverAllocationsTable,
-  serversTable,
-} from "@workspace/db/schema/servers"
 import { serverTransfersTable } from "@workspace/db/schema/transfers"
 import {
   ApiException,
@@ -20,8 +14,14 @@ import {
 } from "@workspace/shared/errors"
 
 import type { Auth } from "@/auth"
-import { writeAudit } from "@/lib/Audit"
-import { callDaemon } from "@/lib/DaemonHttp"
+import type { Env } from "@/env"
+import { assertNodeSupports } from "@/lib/NodeCapabilities"
+import { assertOwnerOrAdmin } from "@/lib/ServerAccess"
+import {
+  runTransfer,
+  transferSecure,
+  transferWindowOpen,
+} from "@/lib/TransferRunner"
 import {
   buildRequireSession,
   type AuthVariables,
@@ -30,23 +30,29 @@ import {
 const startSchema = z.object({
   targetNodeId: z.string().uuid(),
   targetAllocationId: z.string().uuid(),
+  /** Run now even if a node's transfer window is shut. */
+  urgent: z.boolean().default(false),
+  /** Paths to leave behind, e.g. `backups/` or `*.log`. */
+  ignore: z.array(z.string().min(1).max(256)).max(64).optional(),
 })
 
 /**
- * Transfer-record bookkeeping. The actual byte-pushing daemon-to-daemon
- * transfer is a phase-3 feature; this endpoint persists the request and
- * marks it `failed` with `error="not_implemented"` so the UI can
- * surface a clear message. The schema is preserved so flipping the
- * implementation switch later doesn't churn migrations.
+ * Transfer requests. The push and the switchover run in runTransfer;
+ * a non-urgent transfer asked for while either node's transfer window
+ * is shut is recorded as `queued` and started by the TransferQueue.
  */
-export const buildTransfersRoute = (params: { auth: Auth; db: Db }) => {
-  const { auth, db } = params
+export const buildTransfersRoute = (params: {
+  auth: Auth
+  db: Db
+  env: Env
+}) => {
+  const { auth, db, env } = params
   const requireSession = buildRequireSession(auth)
   return new Hono<{ Variables: AuthVariables }>()
     .use("*", requireSession)
     .get("/:serverId/transfers", async (c) => {
       const serverId = c.req.param("serverId")
-      await assertOwner(db, c.get("user"), serverId)
+      await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const rows = await db
         .select()
         .from(serverTransfersTable)
@@ -56,9 +62,29 @@ export const buildTransfersRoute = (params: { auth: Auth; db: Db }) => {
     })
     .post("/:serverId/transfer", async (c) => {
       const serverId = c.req.param("serverId")
-      const server = await assertOwner(db, c.get("user"), serverId)
+      const server = await assertOwnerOrAdmin(db, c.get("user"), serverId)
       const parsed = startSchema.safeParse(await c.req.json())
       if (!parsed.success) throw apiValidationError(parsed.error)
+      // One transfer per server at a time, queued ones included. A
+      // transfer whose daemons died is failed by the TransferJanitor,
+      // which frees the slot.
+      const running = await db
+        .select({ id: serverTransfersTable.id })
+        .from(serverTransfersTable)
+        .where(
+          and(
+            eq(serverTransfersTable.serverId, serverId),
+            inArray(serverTransfersTable.status, [
+              "pending",
+              "queued",
+              "running",
+            ])
+          )
+        )
+        .limit(1)
+      if (running.length > 0) {
+        throw new ApiException("transfers.in_progress", { status: 409 })
+      }
       const targetNode = (
         await db
           .select()
@@ -85,9 +111,6 @@ export const buildTransfersRoute = (params: { auth: Auth; db: Db }) => {
           status: 409,
         })
       }
-      // Mint the one-time HMAC token the source daemon will present to
-      // the target. Signed with the *target* node's key — only the
-      // target can verify, only the source can present.
       if (targetNode.daemonPublicKey === null) {
         throw new ApiException("nodes.unreachable", { status: 503 })
       }
@@ -101,13 +124,20 @@ export const buildTransfersRoute = (params: { auth: Auth; db: Db }) => {
       if (sourceNode === undefined || sourceNode.daemonPublicKey === null) {
         throw new ApiException("nodes.unreachable", { status: 503 })
       }
-      const ts = Math.floor(Date.now() / 1000)
-      const token = createHmac(
-        "sha256",
-        Buffer.from(targetNode.daemonPublicKey, "hex")
-      )
-        .update(`${serverId}|${ts}`)
-        .digest("hex")
+      if (!transferSecure(sourceNode, targetNode)) {
+        throw new ApiException("transfers.insecure", { status: 409 })
+      }
+      const ignore = parsed.data.ignore ?? []
+      if (ignore.length > 0) {
+        // An older daemon would drop the patterns and send everything.
+        assertNodeSupports(sourceNode, "transfer.ignore")
+      }
+      // Non-urgent transfers wait for both nodes' transfer windows; the
+      // TransferQueue starts them once the windows open.
+      const urgent = parsed.data.urgent
+      const queued =
+        !urgent &&
+        (!transferWindowOpen(sourceNode) || !transferWindowOpen(targetNode))
 
       const [row] = await db
         .insert(serverTransfersTable)
@@ -116,122 +146,17 @@ export const buildTransfersRoute = (params: { auth: Auth; db: Db }) => {
           sourceNodeId: server.nodeId,
           targetNodeId: parsed.data.targetNodeId,
           targetAllocationId: parsed.data.targetAllocationId,
-          token,
-          status: "running",
+          // Minted by runTransfer when the push starts.
+          token: "",
+          urgent,
+          ignore: ignore.length > 0 ? ignore : null,
+          status: queued ? "queued" : "pending",
         })
         .returning()
       if (row === undefined) {
         throw new ApiException("internal.unexpected", { status: 500 })
       }
-
-      // Kick the source daemon to push to the target. Async so the
-      // response returns quickly; the row's `status` flips to completed
-      // / failed inline below once the source's call returns.
-      const targetUrl = `${targetNode.scheme}://${targetNode.fqdn}:${targetNode.daemonPort}/api/servers/${serverId}/transfer/ingest`
-      const pushBody = { targetUrl, token, timestamp: ts }
-      void (async () => {
-        try {
-          const resp = await callDaemon({
-            baseUrl: `${sourceNode.scheme}://${sourceNode.fqdn}:${sourceNode.daemonPort}`,
-            nodeId: sourceNode.id,
-            signingKeyHex: sourceNode.daemonPublicKey ?? "",
-            method: "POST",
-            path: `/api/servers/${serverId}/transfer/push`,
-            body: pushBody,
-          })
-          if (!resp.ok) {
-            await db
-              .update(serverTransfersTable)
-              .set({
-                status: "failed",
-                error: `source push: ${resp.status}`,
-                completedAt: new Date(),
-              })
-              .where(eq(serverTransfersTable.id, row.id))
-            return
-          }
-          // Atomic switchover: free the old primary allocation, link
-          // the new one, retarget the server's nodeId.
-          await db.transaction(async (tx) => {
-            const oldAllocId = server.primaryAllocationId
-            await tx
-              .update(nodeAllocationsTable)
-              .set({ serverId: null })
-              .where(eq(nodeAllocationsTable.serverId, serverId))
-            await tx
-              .delete(serverAllocationsTable)
-              .where(eq(serverAllocationsTable.serverId, serverId))
-            await tx
-              .update(nodeAllocationsTable)
-              .set({ serverId })
-              .where(
-                eq(
-                  nodeAllocationsTable.id,
-                  parsed.data.targetAllocationId
-                )
-              )
-            await tx.insert(serverAllocationsTable).values({
-              serverId,
-              allocationId: parsed.data.targetAllocationId,
-            })
-            await tx
-              .update(serversTable)
-              .set({
-                nodeId: parsed.data.targetNodeId,
-                primaryAllocationId: parsed.data.targetAllocationId,
-                status: "offline",
-                updatedAt: new Date(),
-              })
-              .where(eq(serversTable.id, serverId))
-            await tx
-              .update(serverTransfersTable)
-              .set({ status: "completed", completedAt: new Date() })
-              .where(eq(serverTransfersTable.id, row.id))
-            void oldAllocId // explicit unused binding for clarity
-          })
-          void writeAudit({
-            db,
-            actorId: null,
-            action: "servers.transferred",
-            targetType: "server",
-            targetId: serverId,
-            metadata: {
-              sourceNode: sourceNode.id,
-              targetNode: parsed.data.targetNodeId,
-            },
-          })
-        } catch (err) {
-          await db
-            .update(serverTransfersTable)
-            .set({
-              status: "failed",
-              error: err instanceof Error ? err.message : "unknown",
-              completedAt: new Date(),
-            })
-            .where(eq(serverTransfersTable.id, row.id))
-        }
-      })()
+      if (!queued) await runTransfer({ db, env, transfer: row })
       return c.json({ transfer: row })
     })
 }
-
-const assertOwner = async (
-  db: Db,
-  user: { id: string; isAdmin?: boolean | null },
-  serverId: string
-) => {
-  const server = (
-    await db
-      .select()
-      .from(serversTable)
-      .where(eq(serversTable.id, serverId))
-      .limit(1)
-  )[0]
-  if (server === undefined) {
-    throw new ApiException("servers.not_found", { status: 404 })
-  }
-  if (user.isAdmin !== true && server.ownerId !== user.id) {
-    throw new ApiException("permissions.denied", { status: 403 })
-  }
-  return server
-}
@@ -0,0 +1,276 @@
+// Package apitypes holds the JSON bodies the daemon's HTTP API accepts
+// and answers with, so the panel's tooling and third-party clients can
+// build requests from the exact types the router decodes instead of
+// hand-rolled JSON. It sits outside internal/ to be importable from
+// other modules and depends on nothing but the standard library; a
+// field added here is an API change for every client.
+//
+// Wire names match what the panel sends today. Requests are
+// HMAC-signed (or carry a user token on the file endpoints) as
+// described on each router handler; this package doesn't sign.
+package apitypes
+
+// Error is the body of every non-2xx JSON answer. Message is only set
+// for errors the panel shows verbatim.
+type Error struct {
+	Error struct {
+		Code    string `json:"code"`
+		Message string `json:"message,omitempty"`
+	} `json:"error"`
+}
+
+// OK is the body of a request that succeeded with nothing to report.
+type OK struct {
+	OK bool `json:"ok"`
+}
+
+// File operations: POST /api/servers/{id}/files?op=<op>.
+
+// MoveRequest is the body of op=move.
+type MoveRequest struct {
+	From string `json:"from"`
+	To   string `json:"to"`
+}
+
+// CopyRequest is the body of op=copy: copy (or with Move, move) each
+// of Paths into Destination. OnConflict is "rename" (the default),
+// "overwrite", "skip" or "fail"; see files.Manager.Copy.
+type CopyRequest struct {
+	Paths       []string `json:"paths"`
+	Destination string   `json:"destination"`
+	Move        bool     `json:"move"`
+	OnConflict  string   `json:"onConflict"`
+}
+
+// DecompressRequest is the body of op=decompress.
+type DecompressRequest struct {
+	Path        string `json:"path"`
+	Destination string `json:"destination"`
+}
+
+// CompressRequest is the body of op=compress.
+type CompressRequest struct {
+	Paths       []string `json:"paths"`
+	Destination string   `json:"destination"`
+}
+
+// PullRequest is the body of op=pull: download URL into Directory,
+// named Filename or after the URL. Mirrors are tried in order when URL
+// fails or its file doesn't match Checksum ("sha256:<hex>" or
+// "sha1:<hex>", optional); the job's result names the URL that served
+// the file.
+type PullRequest struct {
+	URL       string   `json:"url"`
+	Directory string   `json:"directory"`
+	Filename  string   `json:"filename"`
+	Mirrors   []string `json:"mirrors,omitempty"`
+	Checksum  string   `json:"checksum,omitempty"`
+}
+
+// UploadResponse answers POST /files/upload (multipart "file" parts,
+// ?path= the directory, ?overwrite= overwrite, rename or fail). Count
+// is how many files were stored; Files has a line for every part.
+type UploadResponse struct {
+	OK    bool           `json:"ok"`
+	Count int            `json:"count"`
+	Files []UploadedFile `json:"files"`
+}
+
+// UploadedFile is one uploaded file: Status "written", "renamed" (Path
+// is the new name), "exists" (skipped under overwrite=fail), "blocked"
+// (refused by the node's file policy) or "quarantined" (flagged by the
+// node's malware scan, not stored).
+type UploadedFile struct {
+	Name   string `json:"name"`
+	Path   string `json:"path"`
+	Bytes  int64  `json:"bytes"`
+	Status string `json:"status"`
+}
+
+// UpdateFromURLRequest is the body of POST /api/servers/{id}/update:
+// download the archive at URL (or a mirror) and verify it against
+// Checksum like a pull, delete whatever matches Clean (globs relative to
+// the server root, e.g. "mods/*"), and extract it into Directory ("/"
+// when empty). With Restart a running server is stopped for the swap
+// and started again afterwards; without it the files change under the
+// running server. Schedule tasks of type "update" send it.
+type UpdateFromURLRequest struct {
+	URL       string   `json:"url"`
+	Mirrors   []string `json:"mirrors,omitempty"`
+	Checksum  string   `json:"checksum,omitempty"`
+	Clean     []string `json:"clean,omitempty"`
+	Directory string   `json:"directory,omitempty"`
+	Restart   bool     `json:"restart"`
+}
+
+// UpdateFromURLResult is the update job's result: the URL that served
+// the archive and the paths Clean removed.
+type UpdateFromURLResult struct {
+	URL       string   `json:"url"`
+	Removed   []string `json:"removed"`
+	Restarted bool     `json:"restarted"`
+}
+
+// LogPruneRequest is the body of POST /api/servers/{id}/logs/prune:
+// delete the files matching Globs (default logs/** and crash-reports/**)
+// older than MaxAgeDays, then the oldest until they fit in MaxTotalMb.
+// Zero disables a limit. With DryRun the result lists what would go and
+// nothing is deleted. Schedule tasks of type "prune_logs" send it, and
+// the panel's preview sends it dry.
+type LogPruneRequest struct {
+	Globs      []string `json:"globs,omitempty"`
+	MaxAgeDays int      `json:"maxAgeDays"`
+	MaxTotalMb int64    `json:"maxTotalMb"`
+	DryRun     bool     `json:"dryRun"`
+}
+
+// Power: POST /api/servers/{id}/power and /command.
+
+// PowerRequest is the body of a power action. Countdown is only
+// accepted with "restart".
+type PowerRequest struct {
+	Action    string     `json:"action"`
+	Countdown *Countdown `json:"countdown,omitempty"`
+}
+
+// Countdown delays a restart, warning players in the console at each
+// of Warnings (seconds left). Message may use {{time}}.
+type Countdown struct {
+	Seconds  int    `json:"seconds"`
+	Message  string `json:"message"`
+	Warnings []int  `json:"warnings"`
+}
+
+// CommandRequest is the body of /command: one line for the server's
+// stdin. Schedule tasks of type "command" send it. With Capture the
+// answer is a CommandResult instead of OK.
+type CommandRequest struct {
+	Line    string          `json:"line"`
+	Capture *CommandCapture `json:"capture,omitempty"`
+}
+
+// CommandCapture collects the console output that follows a command:
+// every line for WindowMs milliseconds after it is sent, or until a line
+// matches the regexp Until.
+type CommandCapture struct {
+	WindowMs int    `json:"windowMs"`
+	Until    string `json:"until,omitempty"`
+}
+
+// CommandResult answers a captured command. ID tags the command in the
+// daemon's log; Matched says Until matched (the last line is the match)
+// and Truncated that the window produced more lines than were kept.
+type CommandResult struct {
+	OK        bool     `json:"ok"`
+	ID        string   `json:"id"`
+	Lines     []string `json:"lines"`
+	Matched   bool     `json:"matched"`
+	Truncated bool     `json:"truncated"`
+}
+
+// Allocations: POST /api/servers/{id}/allocations.
+
+// AllocationSyncRequest tells the daemon the server's allocations
+// changed. A running container can't be rebound in place; with Restart
+// a server whose ports differ is restarted onto the new ones, otherwise
+// the answer only reports the difference.
+type AllocationSyncRequest struct {
+	Restart bool `json:"restart"`
+}
+
+// AllocationSyncResult lists the host ports the running container
+// gains (Added) and gives up (Removed) once rebound. RestartRequired
+// says they differ and the server still runs on the old ones; Ticket is
+// the queued restart's power ticket when one was asked for.
+type AllocationSyncResult struct {
+	State           string        `json:"state"`
+	Added           []PortBinding `json:"added"`
+	Removed         []PortBinding `json:"removed"`
+	RestartRequired bool          `json:"restartRequired"`
+	Ticket          uint64        `json:"ticket,omitempty"`
+}
+
+// PortBinding is one published host port.
+type PortBinding struct {
+	IP       string `json:"ip"`
+	Port     int    `json:"port"`
+	Protocol string `json:"protocol"`
+}
+
+// Environment: GET and PUT /api/servers/{id}/environment.
+
+// EnvironmentRequest is the body of a PUT: variable values to use from
+// the next start, on top of the panel's config until it agrees.
+type EnvironmentRequest struct {
+	Environment map[string]string `json:"environment"`
+}
+
+// EnvironmentResult is the server's installed environment, the
+// overrides in it that the panel's config doesn't carry yet, and the
+// variables the running container still has an older value of
+// (PendingRestart, empty when it's offline). Synced is false when the
+// panel config couldn't be pulled and Environment may be stale.
+type EnvironmentResult struct {
+	State          string            `json:"state"`
+	Synced         bool              `json:"synced"`
+	Environment    map[string]string `json:"environment"`
+	Overrides      map[string]string `json:"overrides"`
+	PendingRestart []string          `json:"pendingRestart"`
+}
+
+// Backups: POST /api/servers/{id}/backups?op=<op>.
+
+// CreateBackupRequest is the body of op=create. Source and Ignore
+// narrow what is archived; Quota, when set, is enforced before the
+// archive is written. Base names an earlier backup to make this one
+// incremental on.
+type CreateBackupRequest struct {
+	Name     string      `json:"name"`
+	Manifest bool        `json:"manifest"`
+	Source   string      `json:"source"`
+	Ignore   []string    `json:"ignore"`
+	Quota    BackupQuota `json:"quota"`
+	Base     string      `json:"base,omitempty"`
+}
+
+// BackupQuota caps a server's backup storage. With Rotate the oldest
+// backups not in Protected are removed to make room.
+type BackupQuota struct {
+	Bytes     int64    `json:"bytes"`
+	Rotate    bool     `json:"rotate"`
+	Protected []string `json:"protected"`
+}
+
+// RestoreBackupRequest is the body of op=restore.
+type RestoreBackupRequest struct {
+	Name string `json:"name"`
+}
+
+// DeleteBackupRequest is the body of op=delete.
+type DeleteBackupRequest struct {
+	Name string `json:"name"`
+}
+
+// Transfers: POST /api/servers/{id}/transfer/push on the source node.
+
+// TransferPushRequest tells the source to push the server to TargetURL
+// (the target's ingest endpoint). Token and Timestamp are the one-time
+// credentials the panel minted for the target. SealedKey, when set, is
+// the per-transfer archive key sealed for the source; without it the
+// target must be HTTPS. TimeoutSeconds may shorten, never lengthen, the
+// node's own transfer timeout. Ignore lists patterns for paths to leave
+// behind (e.g. "backups/", "logs/"), matched like a backup's and on top
+// of the server's .stellarignore files. With Delta the source first asks
+// the target which files it already has (from an earlier or failed
+// transfer) and only sends the ones that changed; a target that can't
+// answer gets everything.
+type TransferPushRequest struct {
+	TargetURL      string   `json:"targetUrl"`
+	Token          string   `json:"token"`
+	Timestamp      int64    `json:"timestamp"`
+	TimeoutSeconds int      `json:"timeoutSeconds"`
+	Urgent         bool     `json:"urgent"`
+	SealedKey      string   `json:"sealedKey,omitempty"`
+	Ignore         []string `json:"ignore,omitempty"`
+	Delta          bool     `json:"delta,omitempty"`
+}
@@ -0,0 +1,276 @@
+package main
+
+import (
+	"bufio"
+	"bytes"
+	"context"
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"flag"
+	"fmt"
+	"io"
+	"net"
+	"net/http"
+	"os"
+	"os/signal"
+	"slices"
+	"sort"
+	"strconv"
+	"strings"
+	"text/tabwriter"
+	"time"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/events"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+const ctlUsage = `usage: stellar-daemon ctl [--config PATH | --socket PATH] <command>
+
+  servers                      list servers and their state
+  console <server> [--no-follow]
+                               print recent console output and follow it
+  power <server> <action>      start, stop, restart or kill a server
+  backup <server> [name]       create a backup and wait for it
+  stats                        node version, capacity and commitments`
+
+// runCtl administers the running daemon from the node itself, for when
+// the panel is down or unreachable. It talks to the local API socket
+// when one is configured (or given with --socket, which needs no access
+// to the config file) and otherwise signs requests to http_listen on
+// loopback with the node key, like the panel does.
+//
+// Backups made here are on disk like any other, but the panel only
+// lists the ones it started.
+func runCtl(args []string) error {
+	fs := flag.NewFlagSet("ctl", flag.ContinueOnError)
+	cfgPath := fs.String("config", defaultConfigPath(), "path to config.toml")
+	socket := fs.String("socket", "", "path to the daemon's API socket")
+	fs.Usage = func() { fmt.Fprintln(os.Stderr, ctlUsage) }
+	if err := fs.Parse(args); err != nil {
+		return err
+	}
+	if fs.NArg() == 0 {
+		return errors.New(ctlUsage)
+	}
+	c, err := newCtlClient(*cfgPath, *socket)
+	if err != nil {
+		return err
+	}
+	ctx, stop := signal.NotifyContext(context.Background(), os.Interrupt)
+	defer stop()
+	cmd, rest := fs.Arg(0), fs.Args()[1:]
+	switch {
+	case cmd == "servers" && len(rest) == 0:
+		return c.servers(ctx)
+	case cmd == "console" && len(rest) >= 1:
+		follow := !slices.Contains(rest[1:], "--no-follow")
+		return c.console(ctx, rest[0], follow)
+	case cmd == "power" && len(rest) == 2:
+		return c.power(ctx, rest[0], rest[1])
+	case cmd == "backup" && (len(rest) == 1 || len(rest) == 2):
+		name := "ctl-" + time.Now().UTC().Format("20060102-150405")
+		if len(rest) == 2 {
+			name = rest[1]
+		}
+		return c.backup(ctx, rest[0], name)
+	case cmd == "stats" && len(rest) == 0:
+		return c.stats(ctx)
+	}
+	return errors.New(ctlUsage)
+}
+
+// ctlClient reaches the local daemon over its socket, or over loopback
+// TCP with a signature when key is set.
+type ctlClient struct {
+	http   *http.Client
+	base   string
+	nodeID string
+	key    []byte
+}
+
+func newCtlClient(cfgPath, socket string) (*ctlClient, error) {
+	if socket == "" {
+		cfg, err := config.Load(cfgPath)
+		if err != nil {
+			return nil, err
+		}
+		if cfg.Socket.Path == "" {
+			return loopbackClient(cfg)
+		}
+		socket = cfg.Socket.Path
+	}
+	return &ctlClient{
+		http: &http.Client{Transport: &http.Transport{
+			DialContext: func(ctx context.Context, _, _ string) (net.Conn, error) {
+				return (&net.Dialer{}).DialContext(ctx, "unix", socket)
+			},
+		}},
+		base: "http://stellar-daemon",
+	}, nil
+}
+
+func loopbackClient(cfg *config.Config) (*ctlClient, error) {
+	key, err := hex.DecodeString(cfg.SigningKeyHex)
+	if err != nil {
+		return nil, fmt.Errorf("signing_key: %w", err)
+	}
+	host, port, err := net.SplitHostPort(cfg.HTTPListen)
+	if err != nil {
+		return nil, fmt.Errorf("http_listen: %w", err)
+	}
+	if host == "" || host == "0.0.0.0" || host == "::" {
+		host = "127.0.0.1"
+	}
+	return &ctlClient{
+		http:   &http.Client{},
+		base:   "http://" + net.JoinHostPort(host, port),
+		nodeID: cfg.NodeID,
+		key:    key,
+	}, nil
+}
+
+// do sends the request and returns the response if it is 2xx; anything
+// else becomes an error carrying the daemon's error code.
+func (c *ctlClient) do(ctx context.Context, method, path string, body any) (*http.Response, error) {
+	var rdr io.Reader
+	if body != nil {
+		raw, err := json.Marshal(body)
+		if err != nil {
+			return nil, err
+		}
+		rdr = bytes.NewReader(raw)
+	}
+	req, err := http.NewRequestWithContext(ctx, method, c.base+path, rdr)
+	if err != nil {
+		return nil, err
+	}
+	req.Header.Set("Content-Type", "application/json")
+	if c.key != nil {
+		ts := strconv.FormatInt(time.Now().Unix(), 10)
+		mac := hmac.New(sha256.New, c.key)
+		mac.Write([]byte(c.nodeID + "|" + ts))
+		req.Header.Set("X-Stellar-Node-Id", c.nodeID)
+		req.Header.Set("X-Stellar-Timestamp", ts)
+		req.Header.Set("Authorization", "Bearer "+hex.EncodeToString(mac.Sum(nil)))
+	}
+	resp, err := c.http.Do(req)
+	if err != nil {
+		return nil, fmt.Errorf("contact daemon: %w", err)
+	}
+	if resp.StatusCode/100 == 2 {
+		return resp, nil
+	}
+	defer resp.Body.Close()
+	raw, _ := io.ReadAll(io.LimitReader(resp.Body, 4096))
+	var e apitypes.Error
+	if json.Unmarshal(raw, &e) == nil && e.Error.Code != "" {
+		if e.Error.Message != "" {
+			return nil, fmt.Errorf("%s: %s (%s)", resp.Status, e.Error.Code, e.Error.Message)
+		}
+		return nil, fmt.Errorf("%s: %s", resp.Status, e.Error.Code)
+	}
+	return nil, fmt.Errorf("%s: %s", resp.Status, strings.TrimSpace(string(raw)))
+}
+
+func (c *ctlClient) servers(ctx context.Context) error {
+	resp, err := c.do(ctx, http.MethodGet, "/api/servers/states", nil)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	var states map[string]server.State
+	if err := json.NewDecoder(resp.Body).Decode(&states); err != nil {
+		return err
+	}
+	ids := make([]string, 0, len(states))
+	for id := range states {
+		ids = append(ids, id)
+	}
+	sort.Strings(ids)
+	tw := tabwriter.NewWriter(os.Stdout, 0, 0, 2, ' ', 0)
+	fmt.Fprintln(tw, "SERVER\tSTATE\tFLAGS\tCPU\tMEMORY\tUPTIME")
+	for _, id := range ids {
+		st := states[id]
+		fmt.Fprintf(tw, "%s\t%s\t%s\t%.1f%%\t%d / %d MiB\t%s\n",
+			id, st.ProcessState, strings.Join(st.Flags, ","), st.CPUAbsolute,
+			st.MemoryBytes>>20, st.MemoryLimitBytes>>20,
+			(time.Duration(st.UptimeMs) * time.Millisecond).Truncate(time.Second))
+	}
+	return tw.Flush()
+}
+
+func (c *ctlClient) console(ctx context.Context, serverID string, follow bool) error {
+	path := "/api/servers/" + serverID + "/console"
+	if follow {
+		path += "?follow=1"
+	}
+	resp, err := c.do(ctx, http.MethodGet, path, nil)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	sc := bufio.NewScanner(resp.Body)
+	sc.Buffer(make([]byte, 64*1024), 1<<20)
+	for sc.Scan() {
+		var env events.Envelope
+		if json.Unmarshal(sc.Bytes(), &env) != nil || len(env.Args) == 0 {
+			continue
+		}
+		switch env.Event {
+		case "console output":
+			fmt.Println(env.Args[0])
+		case "status":
+			fmt.Printf("[status] %v\n", env.Args[0])
+		}
+	}
+	if ctx.Err() != nil {
+		return nil
+	}
+	return sc.Err()
+}
+
+func (c *ctlClient) power(ctx context.Context, serverID, action string) error {
+	resp, err := c.do(ctx, http.MethodPost, "/api/servers/"+serverID+"/power", apitypes.PowerRequest{Action: action})
+	if err != nil {
+		return err
+	}
+	resp.Body.Close()
+	fmt.Printf("%s queued for %s\n", action, serverID)
+	return nil
+}
+
+func (c *ctlClient) backup(ctx context.Context, serverID, name string) error {
+	fmt.Printf("creating backup %q of %s...\n", name, serverID)
+	resp, err := c.do(ctx, http.MethodPost, "/api/servers/"+serverID+"/backups?op=create", apitypes.CreateBackupRequest{Name: name})
+	if err != nil {
+		return err
+	}
+	resp.Body.Close()
+	fmt.Println("backup complete")
+	return nil
+}
+
+func (c *ctlClient) stats(ctx context.Context) error {
+	resp, err := c.do(ctx, http.MethodGet, "/api/system", nil)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	var report map[string]any
+	if err := json.NewDecoder(resp.Body).Decode(&report); err != nil {
+		return err
+	}
+	// Capabilities are for the panel; an admin wants the rest.
+	delete(report, "capabilities")
+	out, err := json.MarshalIndent(report, "", "  ")
+	if err != nil {
+		return err
+	}
+	fmt.Println(string(out))
+	return nil
+}
@@ -8,6 +8,7 @@ import (
 	"bytes"
 	"context"
 	"encoding/json"
+	"errors"
 	"flag"
 	"fmt"
 	"io"
@@ -16,17 +17,31 @@ import (
 	"os"
 	"os/signal"
 	"path/filepath"
+	"runtime"
+	"slices"
 	"strings"
 	"syscall"
 	"time"
 
 	"github.com/stellarstack/daemon/internal/backup"
+	"github.com/stellarstack/daemon/internal/capability"
+	"github.com/stellarstack/daemon/internal/clock"
 	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/diskio"
 	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/filepolicy"
 	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/jobs"
 	stellarjwt "github.com/stellarstack/daemon/internal/jwt"
+	"github.com/stellarstack/daemon/internal/logging"
+	"github.com/stellarstack/daemon/internal/macros"
 	"github.com/stellarstack/daemon/internal/panel"
+	"github.com/stellarstack/daemon/internal/pgzip"
+	"github.com/stellarstack/daemon/internal/proxy"
 	"github.com/stellarstack/daemon/internal/router"
+	"github.com/stellarstack/daemon/internal/scan"
+	"github.com/stellarstack/daemon/internal/selfupdate"
 	"github.com/stellarstack/daemon/internal/server"
 	"github.com/stellarstack/daemon/internal/sftp"
 )
@@ -39,6 +54,24 @@ func main() {
 		}
 		return
 	}
+	if len(os.Args) > 1 && os.Args[1] == "version" {
+		fmt.Println(config.Version)
+		return
+	}
+	if len(os.Args) > 1 && os.Args[1] == "ctl" {
+		if err := runCtl(os.Args[2:]); err != nil {
+			fmt.Fprintln(os.Stderr, "ctl:", err)
+			os.Exit(1)
+		}
+		return
+	}
+	if len(os.Args) > 1 && (os.Args[1] == "update" || os.Args[1] == "rollback") {
+		if err := runUpdate(os.Args[1], os.Args[2:]); err != nil {
+			fmt.Fprintln(os.Stderr, os.Args[1]+":", err)
+			os.Exit(1)
+		}
+		return
+	}
 
 	cfgPath := flag.String("config", defaultConfigPath(), "path to config.toml")
 	flag.Parse()
@@ -47,65 +80,241 @@ func main() {
 	if err != nil {
 		log.Fatalf("config: %v", err)
 	}
+	var logOut io.Writer = os.Stderr
+	var logFile *logging.File
+	if cfg.Log.Directory != "" {
+		logFile, err = logging.OpenFile(cfg.Log.Directory, logging.FileOptions{
+			MaxBytes: int64(cfg.Log.MaxSizeMb) << 20,
+			MaxAge:   time.Duration(cfg.Log.RotateHours) * time.Hour,
+			Keep:     cfg.Log.MaxFiles,
+			KeepFor:  time.Duration(cfg.Log.MaxAgeDays) * 24 * time.Hour,
+		})
+		if err != nil {
+			log.Fatalf("log directory: %v", err)
+		}
+		logOut = logFile
+	}
+	if err := logging.Setup(logOut, cfg.Log.Format, cfg.Log.Level, cfg.Log.Modules); err != nil {
+		log.Fatalf("config: %v", err)
+	}
 
 	dc := docker.New(cfg.DockerSocket)
+	// Start-time port probes see the daemon's own network namespace.
+	// Under compose's default bridge network that isn't the host's, so
+	// they'd pass whatever holds the port; find out which it is.
+	hostNetCtx, cancelHostNet := context.WithTimeout(context.Background(), 5*time.Second)
+	onHostNet, err := dc.HostNetwork(hostNetCtx)
+	cancelHostNet()
+	switch {
+	case err != nil:
+		log.Printf("daemon: can't tell whether the daemon is on the host network, assuming not: %v", err)
+	case !onHostNet:
+		log.Printf("daemon: not on the host network; start port checks only see other containers' ports and wake-on-connect is off. Run the daemon with network_mode: host for both")
+	}
 	verifier, err := stellarjwt.New(cfg.SigningKeyHex)
 	if err != nil {
 		log.Fatalf("jwt verifier: %v", err)
 	}
+	verifier.SetLeeway(time.Duration(cfg.Clock.LeewaySeconds) * time.Second)
+	checkClock(cfg.Clock.NTPServer)
 	panelClient, err := panel.New(cfg.APIBaseURL, cfg.NodeID, cfg.SigningKeyHex)
 	if err != nil {
 		log.Fatalf("panel client: %v", err)
 	}
-	// Tell the API the node is alive. Best-effort on boot; the ticker
-	// below keeps it fresh so the admin nodes page reflects reality.
-	go func() {
-		hbCtx, hbCancel := context.WithTimeout(context.Background(), 5*time.Second)
-		_ = panelClient.Heartbeat(hbCtx)
-		hbCancel()
-		ticker := time.NewTicker(30 * time.Second)
-		defer ticker.Stop()
-		for range ticker.C {
-			c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
-			_ = panelClient.Heartbeat(c)
-			cancel()
-		}
-	}()
+	updater, err := selfupdate.New(cfg.Update.ReleaseURL, cfg.Update.PublicKey)
+	if err != nil && !errors.Is(err, selfupdate.ErrDisabled) {
+		log.Printf("daemon: self-update disabled: %v", err)
+	}
+	bm := backup.New(cfg.DataDir)
+	bm.SetEngine(cfg.Backup.Engine)
+	// beat asks for a heartbeat now, so a Docker outage reaches the
+	// panel's node health without waiting out the ticker.
+	beat := make(chan struct{}, 1)
 
-	mgr := server.NewManager(dc, panelClient, cfg.HistoryLines)
+	mgr := server.NewManager(dc, panelClient, server.ConsoleDefaults{
+		HistoryLines: cfg.HistoryLines,
+		Buffer:       cfg.ConsoleBuffer,
+	})
+	mgr.SetStatsPolicy(server.StatsPolicy{
+		MemoryDeltaBytes:  cfg.Stats.MemoryDeltaBytes,
+		CPUDelta:          cfg.Stats.CPUDelta,
+		NetworkDeltaBytes: cfg.Stats.NetworkDeltaBytes,
+		DiskDeltaBytes:    cfg.Stats.DiskDeltaBytes,
+		FullInterval:      time.Duration(cfg.Stats.FullIntervalSeconds) * time.Second,
+	})
+	if a := cfg.Admission; a.Enabled {
+		capacity := server.DetectCapacity(server.Capacity{
+			MemoryMb:         a.MemoryMb,
+			CPUPercent:       a.CPUPercent,
+			MemoryOvercommit: a.MemoryOvercommit,
+			CPUOvercommit:    a.CPUOvercommit,
+		})
+		log.Printf("daemon: admission control on (%d MB x%.2f, %d%% cpu x%.2f)",
+			capacity.MemoryMb, capacity.MemoryOvercommit, capacity.CPUPercent, capacity.CPUOvercommit)
+		mgr.SetAdmission(&capacity)
+	}
+	mgr.SetHostNetworkAllowlist(cfg.HostNetworkAllowlist)
+	mgr.SetDaemonHostNetwork(onHostNet)
+	mgr.SetEnvOverrideDir(filepath.Join(cfg.DataDir, "environment"))
+	fsio.SetEnabled(!cfg.Files.PlainCopy)
+	pgzip.SetThreads(cfg.CompressionThreads)
+	diskio.Configure(int64(cfg.IO.MBPerSecond)<<20, int64(cfg.IO.BurstMB)<<20)
 	fm := files.New(cfg.DataDir)
-	bm := backup.New(cfg.DataDir)
+	fm.SetActivityThresholds(files.ActivityThresholds{
+		Window:     time.Duration(cfg.Files.ActivityWindowMinutes) * time.Minute,
+		ReadBytes:  cfg.Files.ActivityReadMb << 20,
+		WriteBytes: cfg.Files.ActivityWriteMb << 20,
+		Deletes:    cfg.Files.ActivityDeletes,
+		ArchiveOps: cfg.Files.ActivityArchiveOps,
+	}, func(serverID string, w files.ActivityWarning) {
+		log.Printf("files: server %s: unusual activity: %s %d in %s (threshold %d)",
+			serverID, w.Metric, w.Value, w.Window, w.Threshold)
+		c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		defer cancel()
+		_ = panelClient.PushAudit(c, serverID, "", "servers.files.activity_warning", map[string]any{
+			"metric":        w.Metric,
+			"value":         w.Value,
+			"threshold":     w.Threshold,
+			"windowSeconds": int64(w.Window.Seconds()),
+		})
+	})
+	var scanWritten func(serverID, abs, rel, source string) error
+	if sc, err := scan.New(cfg.Scan.Clamd, cfg.Scan.Command, time.Duration(cfg.Scan.TimeoutSeconds)*time.Second); err == nil {
+		log.Printf("daemon: malware scanning on, quarantine at %s", cfg.Scan.QuarantineDir)
+		fm.SetScanner(sc, files.ScanOptions{
+			QuarantineDir: cfg.Scan.QuarantineDir,
+			FailClosed:    cfg.Scan.FailClosed,
+			OnQuarantine: func(serverID string, q files.Quarantined) {
+				c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+				defer cancel()
+				_ = panelClient.PushAudit(c, serverID, "", "servers.files.quarantined", map[string]any{
+					"path":      q.Path,
+					"source":    q.Source,
+					"signature": q.Signature,
+				})
+				mgr.Get(serverID).PublishDaemon(q.Path + " was flagged by the malware scan (" + q.Signature + ") and quarantined")
+			},
+		})
+		scanWritten = func(serverID, abs, rel, source string) error {
+			return fm.ScanWritten(context.Background(), serverID, abs, rel, source)
+		}
+	}
+	policy := filepolicy.New(cfg.Files.BlockedExtensions, cfg.Files.BlockedTypes, cfg.Files.BlockedAllowDirs, cfg.Files.BlockSetuid)
+	onBlocked := func(serverID, path, source, reason string) {
+		log.Printf("files: server %s: %s of %s blocked: %s", serverID, source, path, reason)
+		c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		defer cancel()
+		_ = panelClient.PushAudit(c, serverID, "", "servers.files.blocked", map[string]any{
+			"path":   path,
+			"source": source,
+			"reason": reason,
+		})
+	}
+	fm.SetPolicy(policy, func(serverID string, b files.Blocked) {
+		onBlocked(serverID, b.Path, b.Source, b.Reason)
+	})
+	// A server's denylist and hidden list are the node's defaults plus
+	// what its last pulled config adds (blueprint and server lists).
+	pathRules := func(serverID string) *filepolicy.PathRules {
+		c := mgr.Get(serverID).Config()
+		return filepolicy.NewPathRules(
+			append(slices.Clone(cfg.Files.Denylist), c.FileDenylist...),
+			append(slices.Clone(cfg.Files.Hidden), c.FileHidden...),
+		)
+	}
+	fm.SetPathRules(pathRules)
+	jm := jobs.New(cfg.DataDir)
+	mcs := macros.New(cfg.DataDir)
 
 	ctx, cancel := context.WithCancel(context.Background())
 	defer cancel()
 	mgr.Reconcile(ctx)
+	go mgr.WatchDocker(ctx, func(up bool) {
+		action := "nodes.docker.reconnected"
+		if !up {
+			action = "nodes.docker.disconnected"
+		}
+		select {
+		case beat <- struct{}{}:
+		default:
+		}
+		go func() {
+			c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+			defer cancel()
+			if err := panelClient.PushNodeAudit(c, action, nil); err != nil {
+				log.Printf("daemon: push node audit: %v", err)
+			}
+		}()
+	})
 
-	r := router.New(cfg, verifier, mgr, fm, bm)
-	srv := &http.Server{
-		Addr:              cfg.HTTPListen,
-		Handler:           r.Handler(),
-		ReadHeaderTimeout: 15 * time.Second,
-	}
-
+	// Report which allocation ports are really bound so the panel's
+	// pool survives crashes and hand-edited containers. Runs after
+	// Reconcile so every stellar-* container is registered.
 	go func() {
-		log.Printf("daemon: listening on %s", cfg.HTTPListen)
-		if err := srv.ListenAndServe(); err != nil && err != http.ErrServerClosed {
-			log.Fatalf("listen: %v", err)
+		ticker := time.NewTicker(time.Duration(cfg.AllocationReportSeconds) * time.Second)
+		defer ticker.Stop()
+		for {
+			c, cancel := context.WithTimeout(ctx, 15*time.Second)
+			if err := mgr.ReportAllocations(c); err != nil {
+				log.Printf("daemon: allocation report: %v", err)
+			}
+			cancel()
+			select {
+			case <-ctx.Done():
+				return
+			case <-ticker.C:
+			}
 		}
 	}()
 
+	var px *proxy.Proxy
+	if cfg.Proxy.Enabled {
+		px = proxy.New(cfg.DataDir)
+		go func() {
+			if err := px.ListenAndServe(cfg.Proxy.Listen); err != nil {
+				log.Printf("proxy: listener exited: %v", err)
+			}
+		}()
+	}
+
 	sftpServer, err := sftp.New(struct {
 		Listen      string
 		HostKeyPath string
 		Verifier    *stellarjwt.Verifier
 		DataDir     string
 		NodeID      string
+		Writes      sftp.WriteOptions
+		DiskLimit   func(serverID string) int64
+		Limits      sftp.Limits
+		Policy      *filepolicy.Policy
+		OnBlocked   func(serverID, path, reason string)
+		PathRules   func(serverID string) *filepolicy.PathRules
+		Scan        func(serverID, abs, rel, source string) error
 	}{
 		Listen:      cfg.SFTPListen,
 		HostKeyPath: cfg.SFTPHostKey,
 		Verifier:    verifier,
 		DataDir:     cfg.DataDir,
 		NodeID:      cfg.NodeID,
+		Writes: sftp.WriteOptions{
+			BufferBytes: max(cfg.SFTP.WriteBufferKb, 0) << 10,
+			WriteBehind: cfg.SFTP.WriteBehind,
+		},
+		DiskLimit: func(serverID string) int64 {
+			return mgr.Get(serverID).Config().Disk << 20
+		},
+		Limits: sftp.Limits{
+			MaxConnsPerIP: max(cfg.SFTP.MaxConnsPerIP, 0),
+			MaxFailures:   max(cfg.SFTP.MaxAuthFailures, 0),
+			FailWindow:    time.Duration(max(cfg.SFTP.AuthFailWindowSeconds, 0)) * time.Second,
+			BanFor:        time.Duration(max(cfg.SFTP.BanMinutes, 0)) * time.Minute,
+		},
+		Policy: policy,
+		OnBlocked: func(serverID, path, reason string) {
+			onBlocked(serverID, path, "sftp", reason)
+		},
+		PathRules: pathRules,
+		Scan:      scanWritten,
 	})
 	if err != nil {
 		log.Printf("sftp: skipped (%v)", err)
@@ -117,13 +326,184 @@ func main() {
 		}()
 	}
 
+	caps := capability.Current(capability.Options{
+		ProxyEnabled:      cfg.Proxy.Enabled,
+		SelfUpdateEnabled: updater != nil,
+		LogFileEnabled:    logFile != nil,
+		SFTPEnabled:       sftpServer != nil,
+		ScanEnabled:       cfg.Scan.Enabled(),
+		HostNetwork:       onHostNet,
+	})
+	// Backup usage, the transfer window, resources and health are
+	// recomputed on every beat so the panel's node metrics, per-server
+	// quotas and transfer queue track the node as it is now.
+	var skew time.Duration
+	heartbeat := func() {
+		hb := panel.HeartbeatReport{Version: config.Version, Capabilities: caps}
+		if u, err := bm.Usage(); err == nil {
+			hb.BackupUsage = &u
+		} else {
+			log.Printf("daemon: backup usage: %v", err)
+		}
+		hb.Transfers = panel.TransferPolicy{BandwidthMbps: cfg.Transfer.BandwidthMbps, Window: cfg.Transfer.Window, WindowOpen: true}
+		if open, opens := cfg.Transfer.WindowOpen(time.Now()); !open {
+			hb.Transfers.WindowOpen = false
+			hb.Transfers.OpensAt = &opens
+		}
+		hb.Resources = hostResources(cfg.DataDir)
+		c, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		hb.Health = panel.Health{Docker: dc.Ping(c) == nil}
+		was := skew
+		var hbErr error
+		skew, hbErr = panelClient.Heartbeat(c, hb)
+		cancel()
+		if hbErr == nil || skew != 0 {
+			clock.Record("panel", skew)
+		}
+		// Signed requests both ways and users' tokens are checked
+		// against the clock, so a drifting one breaks them. Said once
+		// when the drift appears and once when it's gone.
+		switch off := skew.Abs() >= clockSkewWarn; {
+		case off && was.Abs() < clockSkewWarn:
+			log.Printf("daemon: clock is %s off the panel's (panel minus node); signed requests and tokens will fail, check time sync", skew)
+		case !off && was.Abs() >= clockSkewWarn:
+			log.Printf("daemon: clock back in step with the panel")
+		}
+	}
+	// Tell the API the node is alive. Best-effort on boot; the ticker
+	// below keeps it fresh so the admin nodes page reflects reality.
+	go func() {
+		heartbeat()
+		ticker := time.NewTicker(30 * time.Second)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-ticker.C:
+			case <-beat:
+			}
+			heartbeat()
+		}
+	}()
+
+	r := router.New(cfg, verifier, mgr, fm, bm, jm, mcs, px)
+	// Needs the router's config sync, installed by router.New.
+	go mgr.ArmWake(ctx)
+	go r.RunTransferJanitor(ctx)
+	go r.RunStateFeed(ctx)
+	r.SetDocker(dc)
+	if logFile != nil {
+		r.SetLogFile(logFile)
+	}
+	if sftpServer != nil {
+		r.SetSFTP(sftpServer)
+	}
+	restart := make(chan struct{}, 1)
+	if updater != nil {
+		r.SetUpdater(updater, func() {
+			select {
+			case restart <- struct{}{}:
+			default:
+			}
+		})
+	}
+	srv := &http.Server{
+		Addr:              cfg.HTTPListen,
+		Handler:           r.Handler(),
+		ReadHeaderTimeout: 15 * time.Second,
+	}
+
+	go func() {
+		log.Printf("daemon: listening on %s", cfg.HTTPListen)
+		if err := srv.ListenAndServe(); err != nil && err != http.ErrServerClosed {
+			log.Fatalf("listen: %v", err)
+		}
+	}()
+	var sock *http.Server
+	if cfg.Socket.Path != "" {
+		s, ln, err := r.SocketServer()
+		if err != nil {
+			log.Fatalf("socket: %v", err)
+		}
+		sock = s
+		go func() {
+			log.Printf("daemon: listening on unix:%s", cfg.Socket.Path)
+			if err := sock.Serve(ln); err != nil && err != http.ErrServerClosed {
+				log.Printf("socket: %v", err)
+			}
+		}()
+	}
+	shutdown := func(ctx context.Context) {
+		_ = srv.Shutdown(ctx)
+		if sock != nil {
+			_ = sock.Shutdown(ctx)
+		}
+	}
+
 	sig := make(chan os.Signal, 1)
 	signal.Notify(sig, os.Interrupt, syscall.SIGTERM)
-	<-sig
-	log.Println("daemon: shutting down")
-	shutdownCtx, cancelShutdown := context.WithTimeout(context.Background(), 10*time.Second)
-	defer cancelShutdown()
-	_ = srv.Shutdown(shutdownCtx)
+	select {
+	case <-sig:
+		log.Println("daemon: shutting down")
+		shutdownCtx, cancelShutdown := context.WithTimeout(context.Background(), 10*time.Second)
+		defer cancelShutdown()
+		shutdown(shutdownCtx)
+	case <-restart:
+		// Drain in-flight requests, then become the new binary. Every
+		// other listener is close-on-exec, so the new process can bind
+		// them again; containers keep running throughout.
+		log.Println("daemon: restarting into updated binary")
+		shutdownCtx, cancelShutdown := context.WithTimeout(context.Background(), 10*time.Second)
+		shutdown(shutdownCtx)
+		cancelShutdown()
+		if err := updater.Exec(); err != nil {
+			log.Fatalf("daemon: exec updated binary: %v", err)
+		}
+	}
+}
+
+// clockSkewWarn is how far the node's clock may drift from the panel's
+// before the heartbeat warns.
+const clockSkewWarn = 5 * time.Second
+
+// checkClock logs a system clock that can't be right, and asks the NTP
+// server how far off it is; in the background, so boot doesn't wait on
+// the network.
+func checkClock(ntpServer string) {
+	if err := clock.Sane(time.Now()); err != nil {
+		log.Printf("daemon: %v; users' tokens will be refused until it is", err)
+	}
+	if ntpServer == "none" {
+		return
+	}
+	go func() {
+		ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		defer cancel()
+		offset, err := clock.QueryNTP(ctx, ntpServer)
+		if err != nil {
+			clock.RecordError(err)
+			log.Printf("daemon: clock check against %s: %v", ntpServer, err)
+			return
+		}
+		clock.Record("ntp", offset)
+		if offset.Abs() >= clockSkewWarn {
+			log.Printf("daemon: clock is %s off %s's; check time sync", offset.Round(time.Millisecond), ntpServer)
+		}
+	}()
+}
+
+// hostResources measures the host for the heartbeat: CPUs, memory, and
+// the filesystem holding dataDir.
+func hostResources(dataDir string) panel.Resources {
+	r := panel.Resources{
+		CPUs:     runtime.NumCPU(),
+		MemoryMb: server.DetectCapacity(server.Capacity{}).MemoryMb,
+	}
+	var st syscall.Statfs_t
+	if err := syscall.Statfs(dataDir, &st); err == nil {
+		r.DiskMb = int64(st.Blocks) * int64(st.Bsize) >> 20
+		r.DiskFreeMb = int64(st.Bavail) * int64(st.Bsize) >> 20
+	}
+	return r
 }
 
 // defaultConfigPath returns ~/.stellar-daemon/config.toml on dev hosts
@@ -142,6 +522,46 @@ func defaultConfigPath() string {
 	return "/etc/stellar-daemon/config.toml"
 }
 
+// runUpdate installs a release (or restores the previous binary) from
+// the command line, for nodes whose panel can't reach them or that
+// predate panel-triggered updates.
+//
+// Usage: stellar-daemon update <version> [--config PATH]
+//
+//	stellar-daemon rollback [--config PATH]
+//
+// Only the binary on disk changes; restart the service to run it.
+func runUpdate(cmd string, args []string) error {
+	fs := flag.NewFlagSet(cmd, flag.ContinueOnError)
+	cfgPath := fs.String("config", defaultConfigPath(), "path to config.toml")
+	if err := fs.Parse(args); err != nil {
+		return err
+	}
+	if cmd == "update" && fs.NArg() != 1 {
+		return fmt.Errorf("usage: stellar-daemon update <version> [--config PATH]")
+	}
+	cfg, err := config.Load(*cfgPath)
+	if err != nil {
+		return err
+	}
+	u, err := selfupdate.New(cfg.Update.ReleaseURL, cfg.Update.PublicKey)
+	if err != nil {
+		return err
+	}
+	if cmd == "rollback" {
+		if err := u.Rollback(); err != nil {
+			return err
+		}
+		fmt.Println("restored the previous binary; restart the daemon to run it")
+		return nil
+	}
+	if err := u.Install(context.Background(), fs.Arg(0)); err != nil {
+		return err
+	}
+	fmt.Printf("installed %s; restart the daemon to run it\n", fs.Arg(0))
+	return nil
+}
+
 // runConfigure exchanges a one-time pairing token for the per-node
 // signing key and writes a fresh config.toml to disk.
 //
@@ -8,42 +8,122 @@ package backup
 import (
 	"archive/tar"
 	"compress/gzip"
+	"context"
 	"crypto/sha256"
 	"encoding/hex"
+	"encoding/json"
 	"errors"
 	"fmt"
 	"io"
 	"os"
 	"path/filepath"
 	"strings"
+	"sync"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/ignore"
+	"github.com/stellarstack/daemon/internal/pgzip"
 )
 
 // Manager holds the daemon's backup configuration. The data dir is
 // shared with the docker bind mount and the backup output path.
 type Manager struct {
 	dataDir string
+	engine  string
+	// repoMu serialises writes to the dedup repositories.
+	repoMu sync.Mutex
 }
 
 func New(dataDir string) *Manager { return &Manager{dataDir: dataDir} }
 
 // Result is what the daemon returns to the API after a successful
-// create. Bytes is the on-disk size of the gzipped tarball.
+// create. Bytes is the on-disk size of the gzipped tarball, or for the
+// dedup engine the bytes of chunks the backup added.
 type Result struct {
-	Name   string `json:"name"`
-	Bytes  int64  `json:"bytes"`
+	Name     string `json:"name"`
+	Bytes    int64  `json:"bytes"`
+	SHA256   string `json:"sha256"`
+	Manifest bool   `json:"manifest,omitempty"`
+	// Base is the backup this one is incremental on; empty for a full
+	// backup.
+	Base string `json:"base,omitempty"`
+	// Rotated names the older backups deleted to fit this one under
+	// the server's quota.
+	Rotated []string `json:"rotated,omitempty"`
+}
+
+// Manifest lists the archive checksum plus one checksum per regular
+// file, stored next to the tarball as `<name>.manifest.json`. Lets an
+// operator verify a restore, or a single file, without trusting the
+// tarball blindly.
+type Manifest struct {
+	Name   string         `json:"name"`
+	Bytes  int64          `json:"bytes"`
+	SHA256 string         `json:"sha256"`
+	Files  []ManifestFile `json:"files"`
+}
+
+// ManifestFile is one regular file in the archive.
+type ManifestFile struct {
+	Path   string `json:"path"`
+	Size   int64  `json:"size"`
 	SHA256 string `json:"sha256"`
 }
 
+// CreateOptions shape one backup. Source is who asked for it
+// ("manual", "schedule", "plugin"); it's only recorded. Ignore lists
+// patterns for paths to leave out, on top of any .stellarignore files
+// in the server's tree; see package ignore. Quota, when set, is
+// enforced once the archive is written; see Quota. Base, when set,
+// names an earlier backup of the server to make this one incremental
+// on; see Index. The dedup engine ignores Base: every snapshot is
+// complete and shares chunks with the others anyway. Progress, when
+// set, is called about once a second while the backup runs.
+type CreateOptions struct {
+	Source   string
+	Ignore   []string
+	Manifest bool
+	Quota    Quota
+	Base     string
+	Progress func(Progress)
+}
+
 // Create snapshots the server's bind-mount tree to a gzipped tarball.
-// Returns the resulting size + sha256 so the API can persist them.
-func (m *Manager) Create(serverID, name string) (Result, error) {
+// Returns the resulting size + sha256 so the API can persist them. Both
+// are computed on the bytes as they're written, so the archive is never
+// read back. With opts.Manifest, each file's sha256 is computed the same
+// way from the read that feeds the tarball and saved as a manifest
+// (of an incremental backup, only the files it archives). Every backup
+// also gets a metadata sidecar and a snapshot index; see Metadata and
+// Index. With the dedup engine the backup goes into the server's
+// chunk repository instead; see Snapshot.
+func (m *Manager) Create(serverID, name string, opts CreateOptions) (Result, error) {
+	withManifest := opts.Manifest
 	if !validName(name) {
 		return Result{}, errors.New("invalid backup name")
 	}
+	if m.engine == EngineDedup {
+		return m.createDedup(serverID, name, opts)
+	}
+	base := map[string]IndexEntry{}
+	if opts.Base != "" {
+		idx, err := m.loadIndex(serverID, opts.Base)
+		if err != nil {
+			return Result{}, fmt.Errorf("%w: %s", ErrBaseMissing, opts.Base)
+		}
+		base = idx.Files
+	}
 	src := filepath.Join(m.dataDir, "servers", serverID)
 	if _, err := os.Stat(src); err != nil {
 		return Result{}, fmt.Errorf("server root: %w", err)
 	}
+	if err := m.checkQuota(serverID, opts.Quota); err != nil {
+		return Result{}, err
+	}
+	track := newTracker(name, src, opts.Ignore, opts.Progress)
 	dstDir := filepath.Join(m.dataDir, "backups", serverID)
 	if err := os.MkdirAll(dstDir, 0o755); err != nil {
 		return Result{}, fmt.Errorf("mkdir backup dir: %w", err)
@@ -57,20 +137,22 @@ func (m *Manager) Create(serverID, name string) (Result, error) {
 
 	hasher := sha256.New()
 	mw := io.MultiWriter(out, hasher)
-	gz := gzip.NewWriter(mw)
+	gz := pgzip.NewWriter(mw)
 	tw := tar.NewWriter(gz)
+	var files []ManifestFile
+	index := map[string]IndexEntry{}
 
-	walkErr := filepath.Walk(src, func(path string, info os.FileInfo, err error) error {
-		if err != nil {
-			return err
-		}
-		rel, err := filepath.Rel(src, path)
-		if err != nil {
-			return err
-		}
-		if rel == "." {
-			return nil
+	walkErr := ignore.Walk(src, opts.Ignore, func(path, rel string, info os.FileInfo) error {
+		if info.Mode().IsRegular() {
+			if prev, ok := base[rel]; ok {
+				if cur, same := unchanged(path, info, prev); same {
+					index[rel] = cur
+					track.add(info.Size())
+					return nil
+				}
+			}
 		}
+		index[rel] = IndexEntry{Size: info.Size(), ModTime: info.ModTime().UnixNano(), Dir: info.IsDir()}
 		hdr, err := tar.FileInfoHeader(info, "")
 		if err != nil {
 			return err
@@ -87,7 +169,14 @@ func (m *Manager) Create(serverID, name string) (Result, error) {
 			return err
 		}
 		defer f.Close()
-		_, err = io.Copy(tw, f)
+		r := track.reader(diskio.Reader(context.Background(), diskio.Background, f))
+		fh := sha256.New()
+		n, err := fsio.Copy(tw, io.TeeReader(r, fh))
+		sum := hex.EncodeToString(fh.Sum(nil))
+		index[rel] = IndexEntry{Size: n, ModTime: info.ModTime().UnixNano(), SHA256: sum}
+		if withManifest {
+			files = append(files, ManifestFile{Path: rel, Size: n, SHA256: sum})
+		}
 		return err
 	})
 	if walkErr != nil {
@@ -104,6 +193,7 @@ func (m *Manager) Create(serverID, name string) (Result, error) {
 		os.Remove(dst)
 		return Result{}, err
 	}
+	track.finish()
 	if err := out.Sync(); err != nil {
 		return Result{}, err
 	}
@@ -111,22 +201,127 @@ func (m *Manager) Create(serverID, name string) (Result, error) {
 	if err != nil {
 		return Result{}, err
 	}
-	return Result{
+	res := Result{
 		Name:   name,
 		Bytes:  st.Size(),
 		SHA256: hex.EncodeToString(hasher.Sum(nil)),
-	}, nil
+		Base:   opts.Base,
+	}
+	if err := writeSidecar(indexPath(dstDir, name), Index{Name: name, Base: opts.Base, Files: index}); err != nil {
+		return Result{}, fmt.Errorf("write index: %w", err)
+	}
+	if withManifest {
+		if files == nil {
+			files = []ManifestFile{}
+		}
+		if err := writeSidecar(manifestPath(dstDir, name), Manifest{Name: name, Bytes: res.Bytes, SHA256: res.SHA256, Files: files}); err != nil {
+			return Result{}, fmt.Errorf("write manifest: %w", err)
+		}
+		res.Manifest = true
+	}
+	meta := Metadata{
+		Name:          name,
+		Source:        opts.Source,
+		Compression:   "gzip",
+		Bytes:         res.Bytes,
+		SHA256:        res.SHA256,
+		Ignore:        opts.Ignore,
+		Manifest:      res.Manifest,
+		Base:          opts.Base,
+		CreatedAt:     time.Now().UTC(),
+		DaemonVersion: config.Version,
+	}
+	if err := writeSidecar(metadataPath(dstDir, name), meta); err != nil {
+		return Result{}, fmt.Errorf("write metadata: %w", err)
+	}
+	rotated, err := m.enforceQuota(serverID, name, opts.Quota)
+	if err != nil {
+		return Result{}, err
+	}
+	res.Rotated = rotated
+	return res, nil
 }
 
-// Restore extracts the named tarball back into the server's bind mount.
-// Wipes the existing tree first; caller is expected to have stopped the
-// container.
+func manifestPath(dir, name string) string {
+	return filepath.Join(dir, name+".manifest.json")
+}
+
+// writeSidecar writes v as JSON next to a backup, atomically.
+func writeSidecar(path string, v any) error {
+	raw, err := json.Marshal(v)
+	if err != nil {
+		return err
+	}
+	tmp := path + ".tmp"
+	if err := os.WriteFile(tmp, raw, 0o644); err != nil {
+		return err
+	}
+	return os.Rename(tmp, path)
+}
+
+// Manifest loads the checksum manifest written alongside a backup.
+// Returns an os.ErrNotExist error for backups created without one.
+func (m *Manager) Manifest(serverID, name string) (*Manifest, error) {
+	if !validName(name) {
+		return nil, errors.New("invalid backup name")
+	}
+	raw, err := os.ReadFile(manifestPath(filepath.Join(m.dataDir, "backups", serverID), name))
+	if err != nil {
+		return nil, err
+	}
+	var mf Manifest
+	if err := json.Unmarshal(raw, &mf); err != nil {
+		return nil, err
+	}
+	return &mf, nil
+}
+
+// Restore extracts the named backup back into the server's bind
+// mount. Wipes the existing tree first; caller is expected to have
+// stopped the container. An incremental backup is rebuilt from its
+// chain: each archive from the full backup forward is extracted over
+// the last, then the files deleted since are removed. A dedup backup
+// is rebuilt from its snapshot.
 func (m *Manager) Restore(serverID, name string) error {
 	if !validName(name) {
 		return errors.New("invalid backup name")
 	}
-	src := filepath.Join(m.dataDir, "backups", serverID, name+".tar.gz")
+	if m.isSnapshot(serverID, name) {
+		return m.restoreDedup(serverID, name)
+	}
+	chain, err := m.chain(serverID, name)
+	if err != nil {
+		return err
+	}
+	for _, b := range chain {
+		if _, err := os.Stat(filepath.Join(m.dataDir, "backups", serverID, b+".tar.gz")); err != nil {
+			return fmt.Errorf("%w: %s", ErrBaseMissing, b)
+		}
+	}
 	dst := filepath.Join(m.dataDir, "servers", serverID)
+	if err := os.RemoveAll(dst); err != nil {
+		return err
+	}
+	if err := os.MkdirAll(dst, 0o755); err != nil {
+		return err
+	}
+	for _, b := range chain {
+		if err := extract(filepath.Join(m.dataDir, "backups", serverID, b+".tar.gz"), dst); err != nil {
+			return fmt.Errorf("extract %s: %w", b, err)
+		}
+	}
+	if len(chain) == 1 {
+		return nil
+	}
+	idx, err := m.loadIndex(serverID, name)
+	if err != nil {
+		return err
+	}
+	return prune(dst, idx)
+}
+
+// extract unpacks the gzipped tarball at src over dst.
+func extract(src, dst string) error {
 	in, err := os.Open(src)
 	if err != nil {
 		return fmt.Errorf("open backup: %w", err)
@@ -137,12 +332,6 @@ func (m *Manager) Restore(serverID, name string) error {
 		return fmt.Errorf("gzip: %w", err)
 	}
 	defer gz.Close()
-	if err := os.RemoveAll(dst); err != nil {
-		return err
-	}
-	if err := os.MkdirAll(dst, 0o755); err != nil {
-		return err
-	}
 	tr := tar.NewReader(gz)
 	for {
 		hdr, err := tr.Next()
@@ -170,7 +359,7 @@ func (m *Manager) Restore(serverID, name string) error {
 			if err != nil {
 				return err
 			}
-			if _, err := io.Copy(f, tr); err != nil {
+			if _, err := fsio.Copy(diskio.Writer(context.Background(), diskio.Background, f), tr); err != nil {
 				f.Close()
 				return err
 			}
@@ -180,15 +369,33 @@ func (m *Manager) Restore(serverID, name string) error {
 	return nil
 }
 
-// Delete removes the named backup from disk.
+// Delete removes the named backup from disk. A backup others are
+// incremental on is refused with ErrHasDependents; delete those first.
+// Deleting a dedup backup also frees the chunks only it used.
 func (m *Manager) Delete(serverID, name string) error {
 	if !validName(name) {
 		return errors.New("invalid backup name")
 	}
-	path := filepath.Join(m.dataDir, "backups", serverID, name+".tar.gz")
-	if err := os.Remove(path); err != nil && !os.IsNotExist(err) {
+	deps, err := m.dependents(serverID, name)
+	if err != nil {
+		return err
+	}
+	if len(deps) > 0 {
+		return fmt.Errorf("%w: %s", ErrHasDependents, strings.Join(deps, ", "))
+	}
+	dir := filepath.Join(m.dataDir, "backups", serverID)
+	if m.isSnapshot(serverID, name) {
+		if err := m.deleteSnapshot(serverID, name); err != nil {
+			return err
+		}
+	} else if err := os.Remove(filepath.Join(dir, name+".tar.gz")); err != nil && !os.IsNotExist(err) {
 		return err
 	}
+	for _, sidecar := range []string{manifestPath(dir, name), metadataPath(dir, name), indexPath(dir, name)} {
+		if err := os.Remove(sidecar); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+	}
 	return nil
 }
 
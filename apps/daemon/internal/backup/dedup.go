@@ -0,0 +1,573 @@
+package backup
+
+import (
+	"bufio"
+	"compress/gzip"
+	"context"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"io/fs"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/ignore"
+)
+
+// The dedup engine stores backups in a content-addressed repository
+// per server instead of one tarball each, so daily backups of a
+// mostly unchanged world only cost the chunks that changed:
+//
+//	<dataDir>/backups/<server>/repo/
+//	  chunks/<ab>/<sha256>     gzipped chunk, named by its raw sha256
+//	  index.json               chunk id → raw and stored size
+//	  snapshots/<name>.json    one per backup: every entry and its chunks
+//
+// Files are cut with a content-defined chunker (a gear rolling hash),
+// so an insert early in a region file shifts only the chunks around it.
+// Deleting a backup removes its snapshot and then collects the chunks
+// no snapshot references any more. Backups keep their metadata and
+// manifest sidecars next to the repository as tarball backups do, with
+// Compression "dedup".
+
+// Engines accepted by SetEngine.
+const (
+	EngineTar   = "tar"
+	EngineDedup = "dedup"
+)
+
+// Chunk size bounds. The cut point is where the rolling hash has its
+// low 20 bits clear, about every 1 MiB.
+const (
+	chunkMin  = 512 << 10
+	chunkMax  = 8 << 20
+	chunkMask = 1<<20 - 1
+)
+
+// gear is the rolling hash's per-byte table, fixed so that chunk
+// boundaries (and so deduplication) hold across daemon builds.
+var gear = func() (t [256]uint64) {
+	x := uint64(0x5374656c6c6172) // splitmix64
+	for i := range t {
+		x += 0x9e3779b97f4a7c15
+		z := x
+		z = (z ^ z>>30) * 0xbf58476d1ce4e5b9
+		z = (z ^ z>>27) * 0x94d049bb133111eb
+		t[i] = z ^ z>>31
+	}
+	return t
+}()
+
+// Snapshot is one dedup backup: every entry of the server's tree.
+type Snapshot struct {
+	Name      string         `json:"name"`
+	CreatedAt time.Time      `json:"createdAt"`
+	Files     []SnapshotFile `json:"files"`
+}
+
+// SnapshotFile is one entry. A regular file lists its chunks in order.
+type SnapshotFile struct {
+	Path    string      `json:"path"`
+	Mode    fs.FileMode `json:"mode"`
+	ModTime time.Time   `json:"modTime"`
+	Size    int64       `json:"size"`
+	Dir     bool        `json:"dir,omitempty"`
+	Chunks  []string    `json:"chunks,omitempty"`
+}
+
+// ChunkInfo is a chunk's index entry.
+type ChunkInfo struct {
+	Size   int64 `json:"size"`
+	Stored int64 `json:"stored"`
+}
+
+// PruneResult is what a garbage collection of the repository freed.
+type PruneResult struct {
+	Chunks int   `json:"chunks"`
+	Bytes  int64 `json:"bytes"`
+}
+
+// SetEngine picks how new backups are stored: EngineTar (the default)
+// or EngineDedup. Existing backups of either kind stay restorable.
+func (m *Manager) SetEngine(engine string) {
+	m.engine = engine
+}
+
+func (m *Manager) repoDir(serverID string) string {
+	return filepath.Join(m.dataDir, "backups", serverID, "repo")
+}
+
+func (m *Manager) snapshotPath(serverID, name string) string {
+	return filepath.Join(m.repoDir(serverID), "snapshots", name+".json")
+}
+
+func (m *Manager) chunkPath(serverID, id string) string {
+	return filepath.Join(m.repoDir(serverID), "chunks", id[:2], id)
+}
+
+func (m *Manager) isSnapshot(serverID, name string) bool {
+	_, err := os.Stat(m.snapshotPath(serverID, name))
+	return err == nil
+}
+
+func (m *Manager) loadChunkIndex(serverID string) (map[string]ChunkInfo, error) {
+	idx := map[string]ChunkInfo{}
+	raw, err := os.ReadFile(filepath.Join(m.repoDir(serverID), "index.json"))
+	if os.IsNotExist(err) {
+		return idx, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	if err := json.Unmarshal(raw, &idx); err != nil {
+		return nil, fmt.Errorf("chunk index: %w", err)
+	}
+	return idx, nil
+}
+
+func (m *Manager) loadSnapshot(serverID, name string) (*Snapshot, error) {
+	raw, err := os.ReadFile(m.snapshotPath(serverID, name))
+	if err != nil {
+		return nil, err
+	}
+	var snap Snapshot
+	if err := json.Unmarshal(raw, &snap); err != nil {
+		return nil, err
+	}
+	return &snap, nil
+}
+
+// createDedup is Create for the dedup engine. Bytes in the result is
+// what the backup added to the repository, not the size of the tree.
+func (m *Manager) createDedup(serverID, name string, opts CreateOptions) (Result, error) {
+	src := filepath.Join(m.dataDir, "servers", serverID)
+	if _, err := os.Stat(src); err != nil {
+		return Result{}, fmt.Errorf("server root: %w", err)
+	}
+	if err := m.checkQuota(serverID, opts.Quota); err != nil {
+		return Result{}, err
+	}
+	track := newTracker(name, src, opts.Ignore, opts.Progress)
+	snap, files, added, err := m.snapshot(serverID, src, name, opts, track)
+	if err != nil {
+		return Result{}, err
+	}
+	track.finish()
+	raw, err := json.Marshal(snap)
+	if err != nil {
+		return Result{}, err
+	}
+	sum := sha256.Sum256(raw)
+	res := Result{Name: name, Bytes: added, SHA256: hex.EncodeToString(sum[:])}
+	dir := filepath.Join(m.dataDir, "backups", serverID)
+	if opts.Manifest {
+		if files == nil {
+			files = []ManifestFile{}
+		}
+		if err := writeSidecar(manifestPath(dir, name), Manifest{Name: name, Bytes: res.Bytes, SHA256: res.SHA256, Files: files}); err != nil {
+			return Result{}, fmt.Errorf("write manifest: %w", err)
+		}
+		res.Manifest = true
+	}
+	meta := Metadata{
+		Name:          name,
+		Source:        opts.Source,
+		Compression:   EngineDedup,
+		Bytes:         res.Bytes,
+		SHA256:        res.SHA256,
+		Ignore:        opts.Ignore,
+		Manifest:      res.Manifest,
+		CreatedAt:     snap.CreatedAt,
+		DaemonVersion: config.Version,
+	}
+	if err := writeSidecar(metadataPath(dir, name), meta); err != nil {
+		return Result{}, fmt.Errorf("write metadata: %w", err)
+	}
+	rotated, err := m.enforceQuota(serverID, name, opts.Quota)
+	if err != nil {
+		return Result{}, err
+	}
+	res.Rotated = rotated
+	return res, nil
+}
+
+// snapshot chunks the tree at src into the repository and saves the
+// snapshot, returning it with the manifest entries (when asked for) and
+// the bytes of new chunks.
+func (m *Manager) snapshot(serverID, src, name string, opts CreateOptions, track *tracker) (*Snapshot, []ManifestFile, int64, error) {
+	m.repoMu.Lock()
+	defer m.repoMu.Unlock()
+	index, err := m.loadChunkIndex(serverID)
+	if err != nil {
+		return nil, nil, 0, err
+	}
+	if err := os.MkdirAll(filepath.Join(m.repoDir(serverID), "snapshots"), 0o755); err != nil {
+		return nil, nil, 0, fmt.Errorf("mkdir repo: %w", err)
+	}
+	snap := Snapshot{Name: name, CreatedAt: time.Now().UTC(), Files: []SnapshotFile{}}
+	var files []ManifestFile
+	var added int64
+	buf := make([]byte, 0, chunkMax)
+	walkErr := ignore.Walk(src, opts.Ignore, func(path, rel string, info os.FileInfo) error {
+		if !info.IsDir() && !info.Mode().IsRegular() {
+			// Restore only recreates directories and regular files.
+			return nil
+		}
+		entry := SnapshotFile{Path: rel, Mode: info.Mode().Perm(), ModTime: info.ModTime().UTC(), Size: info.Size(), Dir: info.IsDir()}
+		if info.IsDir() {
+			snap.Files = append(snap.Files, entry)
+			return nil
+		}
+		f, err := os.Open(path)
+		if err != nil {
+			return err
+		}
+		defer f.Close()
+		fh := sha256.New()
+		br := bufio.NewReaderSize(io.TeeReader(track.reader(diskio.Reader(context.Background(), diskio.Background, f)), fh), 1<<20)
+		entry.Size = 0
+		for {
+			chunk, err := nextChunk(br, buf)
+			if len(chunk) > 0 {
+				id, stored, werr := m.putChunk(serverID, index, chunk)
+				if werr != nil {
+					return werr
+				}
+				entry.Chunks = append(entry.Chunks, id)
+				entry.Size += int64(len(chunk))
+				added += stored
+			}
+			if errors.Is(err, io.EOF) {
+				break
+			}
+			if err != nil {
+				return err
+			}
+		}
+		snap.Files = append(snap.Files, entry)
+		if opts.Manifest {
+			files = append(files, ManifestFile{Path: rel, Size: entry.Size, SHA256: hex.EncodeToString(fh.Sum(nil))})
+		}
+		return nil
+	})
+	// Chunks written before a failure stay indexed; the next backup
+	// reuses them or a prune collects them.
+	if err := writeSidecar(filepath.Join(m.repoDir(serverID), "index.json"), index); err != nil {
+		return nil, nil, 0, fmt.Errorf("write chunk index: %w", err)
+	}
+	if walkErr != nil {
+		return nil, nil, 0, walkErr
+	}
+	if err := writeSidecar(m.snapshotPath(serverID, name), snap); err != nil {
+		return nil, nil, 0, fmt.Errorf("write snapshot: %w", err)
+	}
+	return &snap, files, added, nil
+}
+
+// nextChunk reads the next content-defined chunk of r into buf. It
+// returns io.EOF with the last (possibly empty) chunk.
+func nextChunk(r *bufio.Reader, buf []byte) ([]byte, error) {
+	buf = buf[:0]
+	var h uint64
+	for {
+		b, err := r.ReadByte()
+		if err != nil {
+			return buf, err
+		}
+		buf = append(buf, b)
+		h = h<<1 + gear[b]
+		if len(buf) >= chunkMax || (len(buf) >= chunkMin && h&chunkMask == 0) {
+			return buf, nil
+		}
+	}
+}
+
+// putChunk stores chunk unless the repository has it, returning its id
+// and the bytes it added to disk.
+func (m *Manager) putChunk(serverID string, index map[string]ChunkInfo, chunk []byte) (string, int64, error) {
+	sum := sha256.Sum256(chunk)
+	id := hex.EncodeToString(sum[:])
+	if _, ok := index[id]; ok {
+		return id, 0, nil
+	}
+	path := m.chunkPath(serverID, id)
+	if err := os.MkdirAll(filepath.Dir(path), 0o755); err != nil {
+		return "", 0, err
+	}
+	tmp := path + ".tmp"
+	out, err := os.Create(tmp)
+	if err != nil {
+		return "", 0, err
+	}
+	gz := gzip.NewWriter(out)
+	_, err = gz.Write(chunk)
+	if cerr := gz.Close(); err == nil {
+		err = cerr
+	}
+	if cerr := out.Close(); err == nil {
+		err = cerr
+	}
+	if err == nil {
+		err = os.Rename(tmp, path)
+	}
+	if err != nil {
+		os.Remove(tmp)
+		return "", 0, err
+	}
+	st, err := os.Stat(path)
+	if err != nil {
+		return "", 0, err
+	}
+	index[id] = ChunkInfo{Size: int64(len(chunk)), Stored: st.Size()}
+	return id, st.Size(), nil
+}
+
+// restoreDedup rebuilds the server's tree from a snapshot. The repo
+// lock is held throughout so a prune can't collect chunks under it,
+// every chunk the snapshot names must be present before anything is
+// touched, and the tree is built in a staging directory beside the
+// server's that only replaces it once complete: a failed restore
+// leaves the current files as they were. Each chunk is checked against
+// its id as it's read back.
+func (m *Manager) restoreDedup(serverID, name string) error {
+	m.repoMu.Lock()
+	defer m.repoMu.Unlock()
+	snap, err := m.loadSnapshot(serverID, name)
+	if err != nil {
+		return fmt.Errorf("open snapshot: %w", err)
+	}
+	for _, e := range snap.Files {
+		for _, id := range e.Chunks {
+			if len(id) != sha256.Size*2 {
+				return fmt.Errorf("bad chunk id %q", id)
+			}
+			if _, err := os.Stat(m.chunkPath(serverID, id)); err != nil {
+				return fmt.Errorf("%s: chunk %s: %w", e.Path, id, err)
+			}
+		}
+	}
+	servers := filepath.Join(m.dataDir, "servers")
+	dst := filepath.Join(servers, serverID)
+	stage := filepath.Join(servers, ".restore-"+serverID)
+	old := filepath.Join(servers, ".replaced-"+serverID)
+	// Leftovers of a restore the daemon died in the middle of.
+	if err := os.RemoveAll(stage); err != nil {
+		return err
+	}
+	if err := os.RemoveAll(old); err != nil {
+		return err
+	}
+	if err := os.MkdirAll(stage, 0o755); err != nil {
+		return err
+	}
+	if err := m.restoreTree(serverID, stage, snap); err != nil {
+		os.RemoveAll(stage)
+		return err
+	}
+	if err := os.Rename(dst, old); err != nil && !os.IsNotExist(err) {
+		os.RemoveAll(stage)
+		return err
+	}
+	if err := os.Rename(stage, dst); err != nil {
+		// Put the old tree back rather than leave the server without one.
+		_ = os.Rename(old, dst)
+		os.RemoveAll(stage)
+		return err
+	}
+	return os.RemoveAll(old)
+}
+
+// restoreTree writes snap's entries under root.
+func (m *Manager) restoreTree(serverID, root string, snap *Snapshot) error {
+	for _, e := range snap.Files {
+		target := filepath.Join(root, filepath.Clean("/"+e.Path))
+		if !strings.HasPrefix(target, root) {
+			return errors.New("snapshot entry escapes server root")
+		}
+		if e.Dir {
+			if err := os.MkdirAll(target, e.Mode|0o700); err != nil {
+				return err
+			}
+			continue
+		}
+		if err := m.restoreFile(serverID, target, e); err != nil {
+			return fmt.Errorf("%s: %w", e.Path, err)
+		}
+	}
+	return nil
+}
+
+func (m *Manager) restoreFile(serverID, target string, e SnapshotFile) error {
+	if err := os.MkdirAll(filepath.Dir(target), 0o755); err != nil {
+		return err
+	}
+	f, err := os.OpenFile(target, os.O_CREATE|os.O_WRONLY|os.O_TRUNC, e.Mode)
+	if err != nil {
+		return err
+	}
+	defer f.Close()
+	w := diskio.Writer(context.Background(), diskio.Background, f)
+	for _, id := range e.Chunks {
+		if err := m.copyChunk(w, serverID, id); err != nil {
+			return err
+		}
+	}
+	if err := f.Close(); err != nil {
+		return err
+	}
+	return os.Chtimes(target, e.ModTime, e.ModTime)
+}
+
+func (m *Manager) copyChunk(w io.Writer, serverID, id string) error {
+	if len(id) != sha256.Size*2 {
+		return fmt.Errorf("bad chunk id %q", id)
+	}
+	in, err := os.Open(m.chunkPath(serverID, id))
+	if err != nil {
+		return fmt.Errorf("chunk %s: %w", id, err)
+	}
+	defer in.Close()
+	gz, err := gzip.NewReader(in)
+	if err != nil {
+		return fmt.Errorf("chunk %s: %w", id, err)
+	}
+	h := sha256.New()
+	if _, err := fsio.Copy(io.MultiWriter(w, h), gz); err != nil {
+		return err
+	}
+	if hex.EncodeToString(h.Sum(nil)) != id {
+		return fmt.Errorf("chunk %s is corrupt", id)
+	}
+	return nil
+}
+
+// deleteSnapshot removes a dedup backup and collects its chunks.
+func (m *Manager) deleteSnapshot(serverID, name string) error {
+	m.repoMu.Lock()
+	defer m.repoMu.Unlock()
+	if err := os.Remove(m.snapshotPath(serverID, name)); err != nil && !os.IsNotExist(err) {
+		return err
+	}
+	_, err := m.gc(serverID)
+	return err
+}
+
+// Prune deletes the repository chunks no snapshot references, such as
+// those left by a backup that failed halfway.
+func (m *Manager) Prune(serverID string) (PruneResult, error) {
+	m.repoMu.Lock()
+	defer m.repoMu.Unlock()
+	return m.gc(serverID)
+}
+
+func (m *Manager) gc(serverID string) (PruneResult, error) {
+	var res PruneResult
+	repo := m.repoDir(serverID)
+	snaps, err := os.ReadDir(filepath.Join(repo, "snapshots"))
+	if os.IsNotExist(err) {
+		return res, nil
+	}
+	if err != nil {
+		return res, err
+	}
+	used := map[string]bool{}
+	for _, e := range snaps {
+		name, ok := strings.CutSuffix(e.Name(), ".json")
+		if !ok {
+			continue
+		}
+		snap, err := m.loadSnapshot(serverID, name)
+		if err != nil {
+			// An unreadable snapshot's chunks can't be told apart
+			// from garbage; keep everything.
+			return res, fmt.Errorf("snapshot %s: %w", name, err)
+		}
+		for _, f := range snap.Files {
+			for _, id := range f.Chunks {
+				used[id] = true
+			}
+		}
+	}
+	index, err := m.loadChunkIndex(serverID)
+	if err != nil {
+		return res, err
+	}
+	err = filepath.WalkDir(filepath.Join(repo, "chunks"), func(p string, d fs.DirEntry, err error) error {
+		if err != nil || d.IsDir() || used[d.Name()] {
+			return err
+		}
+		info, err := d.Info()
+		if err != nil {
+			return err
+		}
+		if err := os.Remove(p); err != nil {
+			return err
+		}
+		delete(index, d.Name())
+		res.Chunks++
+		res.Bytes += info.Size()
+		return nil
+	})
+	if err != nil && !os.IsNotExist(err) {
+		return res, err
+	}
+	for id := range index {
+		if !used[id] {
+			delete(index, id)
+		}
+	}
+	return res, writeSidecar(filepath.Join(repo, "index.json"), index)
+}
+
+// listSnapshots adds the dedup backups to a List. Their size is what
+// each added to the repository, from the metadata sidecar.
+func (m *Manager) listSnapshots(serverID, dir string) []Metadata {
+	entries, err := os.ReadDir(filepath.Join(m.repoDir(serverID), "snapshots"))
+	if err != nil {
+		return nil
+	}
+	var out []Metadata
+	for _, e := range entries {
+		name, ok := strings.CutSuffix(e.Name(), ".json")
+		if !ok || !validName(name) {
+			continue
+		}
+		info, err := e.Info()
+		if err != nil {
+			continue
+		}
+		meta := Metadata{Name: name, Compression: EngineDedup, CreatedAt: info.ModTime().UTC()}
+		if raw, err := os.ReadFile(metadataPath(dir, name)); err == nil && json.Unmarshal(raw, &meta) == nil {
+			meta.Sidecar = true
+		}
+		meta.Name = name
+		out = append(out, meta)
+	}
+	return out
+}
+
+// repoUsage is the bytes the server's repository holds.
+func (m *Manager) repoUsage(serverID string) (int64, error) {
+	var total int64
+	err := filepath.WalkDir(m.repoDir(serverID), func(_ string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return err
+		}
+		if info, err := d.Info(); err == nil && info.Mode().IsRegular() {
+			total += info.Size()
+		}
+		return nil
+	})
+	if os.IsNotExist(err) {
+		return 0, nil
+	}
+	return total, err
+}
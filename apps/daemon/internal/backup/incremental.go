@@ -0,0 +1,177 @@
+package backup
+
+import (
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"path/filepath"
+)
+
+// Incremental backups. Every backup made here also writes
+// `<name>.index.json`: every entry in the snapshot with its size,
+// mtime and, for regular files, sha256. A backup created with a Base
+// only archives the regular files that differ from the base's index
+// (plus every directory and symlink, which cost a header each), and
+// records the full snapshot in its own index. Restoring it extracts
+// the chain from the full backup at its root forward, then removes
+// whatever the target's index doesn't list, i.e. the files deleted
+// since the base.
+
+// maxChain caps how many backups a restore walks back through, so a
+// corrupt sidecar can't loop it.
+const maxChain = 256
+
+// ErrBaseMissing: the requested base backup isn't on disk, or was
+// made before indexes existed (imported, or by an older daemon).
+var ErrBaseMissing = errors.New("base backup missing or has no index")
+
+// ErrHasDependents: other backups are incremental on this one, so
+// deleting it would leave them unrestorable.
+var ErrHasDependents = errors.New("backup has incremental backups based on it")
+
+// Index is the snapshot a backup represents, keyed by slash-separated
+// path relative to the server root.
+type Index struct {
+	Name  string                `json:"name"`
+	Base  string                `json:"base,omitempty"`
+	Files map[string]IndexEntry `json:"files"`
+}
+
+// IndexEntry is one path in a snapshot. ModTime is Unix nanoseconds.
+// SHA256 is set for regular files only.
+type IndexEntry struct {
+	Size    int64  `json:"size"`
+	ModTime int64  `json:"modTime"`
+	Dir     bool   `json:"dir,omitempty"`
+	SHA256  string `json:"sha256,omitempty"`
+}
+
+func indexPath(dir, name string) string {
+	return filepath.Join(dir, name+".index.json")
+}
+
+func (m *Manager) loadIndex(serverID, name string) (*Index, error) {
+	if !validName(name) {
+		return nil, errors.New("invalid backup name")
+	}
+	raw, err := os.ReadFile(indexPath(filepath.Join(m.dataDir, "backups", serverID), name))
+	if err != nil {
+		return nil, err
+	}
+	var idx Index
+	if err := json.Unmarshal(raw, &idx); err != nil {
+		return nil, err
+	}
+	if idx.Files == nil {
+		idx.Files = map[string]IndexEntry{}
+	}
+	return &idx, nil
+}
+
+// unchanged reports whether the file at path still matches prev. Size
+// and mtime matching is enough; when only the mtime moved (a touch, or
+// a plugin rewriting identical bytes) the file is hashed and compared
+// with prev's checksum, and the fresh mtime is returned for the new
+// index so the next backup skips the read.
+func unchanged(path string, info os.FileInfo, prev IndexEntry) (IndexEntry, bool) {
+	cur := IndexEntry{Size: info.Size(), ModTime: info.ModTime().UnixNano(), SHA256: prev.SHA256}
+	if prev.Dir || prev.Size != cur.Size {
+		return cur, false
+	}
+	if prev.ModTime == cur.ModTime {
+		return cur, true
+	}
+	if prev.SHA256 == "" {
+		return cur, false
+	}
+	sum, err := hashFile(path)
+	return cur, err == nil && sum == prev.SHA256
+}
+
+func hashFile(path string) (string, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+	h := sha256.New()
+	if _, err := io.Copy(h, f); err != nil {
+		return "", err
+	}
+	return hex.EncodeToString(h.Sum(nil)), nil
+}
+
+// chain returns the backups a restore of name extracts, the full
+// backup at the root first and name last.
+func (m *Manager) chain(serverID, name string) ([]string, error) {
+	out := []string{name}
+	for cur := name; ; {
+		idx, err := m.loadIndex(serverID, cur)
+		if errors.Is(err, os.ErrNotExist) && cur == name {
+			// A backup from before indexes; it's full by definition.
+			return out, nil
+		}
+		if err != nil {
+			return nil, fmt.Errorf("%w: %s", ErrBaseMissing, cur)
+		}
+		if idx.Base == "" {
+			break
+		}
+		if len(out) >= maxChain {
+			return nil, fmt.Errorf("backup chain of %s is longer than %d", name, maxChain)
+		}
+		out = append([]string{idx.Base}, out...)
+		cur = idx.Base
+	}
+	return out, nil
+}
+
+// dependents lists the backups whose metadata names base as their base.
+func (m *Manager) dependents(serverID, base string) ([]string, error) {
+	list, err := m.List(serverID)
+	if err != nil {
+		return nil, err
+	}
+	var out []string
+	for _, b := range list {
+		if b.Base == base {
+			out = append(out, b.Name)
+		}
+	}
+	return out, nil
+}
+
+// prune removes everything under root that idx doesn't list.
+func prune(root string, idx *Index) error {
+	var stale []string
+	err := filepath.Walk(root, func(p string, info os.FileInfo, err error) error {
+		if err != nil {
+			return err
+		}
+		rel, err := filepath.Rel(root, p)
+		if err != nil || rel == "." {
+			return err
+		}
+		if _, ok := idx.Files[filepath.ToSlash(rel)]; ok {
+			return nil
+		}
+		stale = append(stale, p)
+		if info.IsDir() {
+			return filepath.SkipDir
+		}
+		return nil
+	})
+	if err != nil {
+		return err
+	}
+	for _, p := range stale {
+		if err := os.RemoveAll(p); err != nil {
+			return err
+		}
+	}
+	return nil
+}
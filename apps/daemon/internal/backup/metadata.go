@@ -0,0 +1,195 @@
+package backup
+
+import (
+	"archive/tar"
+	"compress/gzip"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"path"
+	"path/filepath"
+	"slices"
+	"sort"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/config"
+)
+
+// Metadata is the sidecar stored next to every backup as
+// `<name>.meta.json`. It makes the backup directory self-describing, so
+// the panel can reconcile its rows against what's actually on the node
+// (a row whose archive is gone, an archive with no row) without
+// trusting file names.
+type Metadata struct {
+	Name          string    `json:"name"`
+	Source        string    `json:"source,omitempty"`
+	Compression   string    `json:"compression"`
+	Bytes         int64     `json:"bytes"`
+	SHA256        string    `json:"sha256,omitempty"`
+	Ignore        []string  `json:"ignore,omitempty"`
+	Manifest      bool      `json:"manifest"`
+	Base          string    `json:"base,omitempty"`
+	CreatedAt     time.Time `json:"createdAt"`
+	DaemonVersion string    `json:"daemonVersion,omitempty"`
+	// Sidecar is false for archives from before sidecars existed (or
+	// whose sidecar is unreadable): only name, size and mtime are
+	// known.
+	Sidecar bool `json:"sidecar"`
+}
+
+func metadataPath(dir, name string) string {
+	return filepath.Join(dir, name+".meta.json")
+}
+
+// List returns every backup archive on disk for the server, oldest
+// first. The size always comes from the archive itself, so a truncated
+// file shows up as a mismatch against the sidecar's checksum rather than
+// being hidden by it. Dedup backups follow, sized by their sidecar.
+func (m *Manager) List(serverID string) ([]Metadata, error) {
+	dir := filepath.Join(m.dataDir, "backups", serverID)
+	entries, err := os.ReadDir(dir)
+	if os.IsNotExist(err) {
+		return []Metadata{}, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	out := []Metadata{}
+	for _, e := range entries {
+		name, ok := strings.CutSuffix(e.Name(), ".tar.gz")
+		if !ok || e.IsDir() || !validName(name) {
+			continue
+		}
+		info, err := e.Info()
+		if err != nil {
+			continue
+		}
+		meta := Metadata{Name: name, Compression: "gzip", CreatedAt: info.ModTime().UTC()}
+		if raw, err := os.ReadFile(metadataPath(dir, name)); err == nil && json.Unmarshal(raw, &meta) == nil {
+			meta.Sidecar = true
+		} else if _, err := os.Stat(manifestPath(dir, name)); err == nil {
+			meta.Manifest = true
+		}
+		meta.Name = name
+		meta.Bytes = info.Size()
+		out = append(out, meta)
+	}
+	out = append(out, m.listSnapshots(serverID, dir)...)
+	sort.Slice(out, func(i, j int) bool { return out[i].CreatedAt.Before(out[j].CreatedAt) })
+	return out, nil
+}
+
+// ErrExists: a backup with the requested name is already on disk.
+var ErrExists = errors.New("backup already exists")
+
+// ErrInvalidArchive: an imported file isn't a gzipped tarball, or has
+// an entry that would land outside the server root on restore.
+var ErrInvalidArchive = errors.New("not a valid backup archive")
+
+// Import registers an archive from elsewhere (another panel, a local
+// copy) as a backup of the server. The stream is validated as it's
+// written — every tar entry is read through and checked the same way
+// Restore would place it — and hashed on the way, so a bad upload is
+// rejected without a second pass and a good one gets the same Result
+// and metadata as a backup made here. The quota applies as it does to
+// Create.
+func (m *Manager) Import(serverID, name, source string, q Quota, src io.Reader) (Result, error) {
+	if !validName(name) {
+		return Result{}, errors.New("invalid backup name")
+	}
+	dir := filepath.Join(m.dataDir, "backups", serverID)
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return Result{}, fmt.Errorf("mkdir backup dir: %w", err)
+	}
+	dst := filepath.Join(dir, name+".tar.gz")
+	if _, err := os.Stat(dst); err == nil {
+		return Result{}, ErrExists
+	}
+	if err := m.checkQuota(serverID, q); err != nil {
+		return Result{}, err
+	}
+	part := dst + ".part"
+	out, err := os.OpenFile(part, os.O_CREATE|os.O_EXCL|os.O_WRONLY, 0o644)
+	if err != nil {
+		if os.IsExist(err) {
+			return Result{}, ErrExists
+		}
+		return Result{}, err
+	}
+	defer os.Remove(part)
+	defer out.Close()
+
+	hasher := sha256.New()
+	tee := io.TeeReader(src, io.MultiWriter(out, hasher))
+	if err := validateArchive(tee); err != nil {
+		return Result{}, err
+	}
+	// Trailing bytes after the tar end marker still belong to the file.
+	if _, err := io.Copy(io.Discard, tee); err != nil {
+		return Result{}, err
+	}
+	if err := out.Sync(); err != nil {
+		return Result{}, err
+	}
+	if err := out.Close(); err != nil {
+		return Result{}, err
+	}
+	st, err := os.Stat(part)
+	if err != nil {
+		return Result{}, err
+	}
+	if err := os.Rename(part, dst); err != nil {
+		return Result{}, err
+	}
+	res := Result{Name: name, Bytes: st.Size(), SHA256: hex.EncodeToString(hasher.Sum(nil))}
+	meta := Metadata{
+		Name:          name,
+		Source:        source,
+		Compression:   "gzip",
+		Bytes:         res.Bytes,
+		SHA256:        res.SHA256,
+		CreatedAt:     time.Now().UTC(),
+		DaemonVersion: config.Version,
+	}
+	if err := writeSidecar(metadataPath(dir, name), meta); err != nil {
+		os.Remove(dst)
+		return Result{}, fmt.Errorf("write metadata: %w", err)
+	}
+	rotated, err := m.enforceQuota(serverID, name, q)
+	if err != nil {
+		return Result{}, err
+	}
+	res.Rotated = rotated
+	return res, nil
+}
+
+// validateArchive reads a gzipped tarball to the end, failing with
+// ErrInvalidArchive on anything Restore couldn't extract safely.
+func validateArchive(r io.Reader) error {
+	gz, err := gzip.NewReader(r)
+	if err != nil {
+		return fmt.Errorf("%w: %v", ErrInvalidArchive, err)
+	}
+	defer gz.Close()
+	tr := tar.NewReader(gz)
+	for {
+		hdr, err := tr.Next()
+		if err == io.EOF {
+			return nil
+		}
+		if err != nil {
+			return fmt.Errorf("%w: %v", ErrInvalidArchive, err)
+		}
+		if path.IsAbs(hdr.Name) || slices.Contains(strings.Split(hdr.Name, "/"), "..") {
+			return fmt.Errorf("%w: entry %q escapes the server root", ErrInvalidArchive, hdr.Name)
+		}
+		if _, err := io.Copy(io.Discard, tr); err != nil {
+			return fmt.Errorf("%w: %v", ErrInvalidArchive, err)
+		}
+	}
+}
@@ -0,0 +1,108 @@
+package backup
+
+import (
+	"io"
+	"os"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/ignore"
+)
+
+// progressInterval spaces out Progress reports.
+const progressInterval = time.Second
+
+// Progress is how far a running backup has got, reported through
+// CreateOptions.Progress. Percent and BytesProcessed count the regular
+// files read (or, for an incremental backup, skipped as unchanged) out
+// of TotalBytes, which a stat-only walk measures before the backup
+// starts. ETASeconds extrapolates the rate so far; -1 until there's a
+// rate to go on.
+type Progress struct {
+	Name           string  `json:"name"`
+	Percent        float64 `json:"progress"`
+	BytesProcessed int64   `json:"bytesProcessed"`
+	TotalBytes     int64   `json:"totalBytes"`
+	ETASeconds     int64   `json:"etaSeconds"`
+}
+
+// tracker feeds one backup's Progress reports. A nil *tracker (no
+// callback) ignores everything.
+type tracker struct {
+	fn    func(Progress)
+	name  string
+	total int64
+	done  int64
+	start time.Time
+	last  time.Time
+}
+
+func newTracker(name, src string, patterns []string, fn func(Progress)) *tracker {
+	if fn == nil {
+		return nil
+	}
+	t := &tracker{fn: fn, name: name, start: time.Now()}
+	_ = ignore.Walk(src, patterns, func(_, _ string, info os.FileInfo) error {
+		if info.Mode().IsRegular() {
+			t.total += info.Size()
+		}
+		return nil
+	})
+	t.report()
+	return t
+}
+
+// add counts n more bytes processed.
+func (t *tracker) add(n int64) {
+	if t == nil {
+		return
+	}
+	t.done += n
+	if time.Since(t.last) >= progressInterval {
+		t.report()
+	}
+}
+
+// finish reports the backup fully processed.
+func (t *tracker) finish() {
+	if t == nil {
+		return
+	}
+	// Files can grow between the size walk and the read.
+	t.total = max(t.total, t.done)
+	t.done = t.total
+	t.report()
+}
+
+func (t *tracker) report() {
+	t.last = time.Now()
+	p := Progress{Name: t.name, BytesProcessed: t.done, TotalBytes: t.total, ETASeconds: -1}
+	if t.total > 0 {
+		p.Percent = min(100, float64(t.done)*100/float64(t.total))
+	} else {
+		p.Percent = 100
+	}
+	if elapsed := time.Since(t.start).Seconds(); t.done > 0 && elapsed > 0 {
+		rate := float64(t.done) / elapsed
+		p.ETASeconds = int64(float64(max(0, t.total-t.done)) / rate)
+	}
+	t.fn(p)
+}
+
+// reader counts what's read through r.
+func (t *tracker) reader(r io.Reader) io.Reader {
+	if t == nil {
+		return r
+	}
+	return &trackedReader{r: r, t: t}
+}
+
+type trackedReader struct {
+	r io.Reader
+	t *tracker
+}
+
+func (r *trackedReader) Read(p []byte) (int, error) {
+	n, err := r.r.Read(p)
+	r.t.add(int64(n))
+	return n, err
+}
@@ -0,0 +1,156 @@
+package backup
+
+import (
+	"errors"
+	"os"
+	"path/filepath"
+	"slices"
+)
+
+// Quota caps the disk a server's backups may use, sidecars included.
+// Bytes <= 0 is unlimited. Over the cap a new backup fails, or with
+// Rotate the oldest backups are deleted to make room; Protected names
+// (the panel's locked backups) and the bases of incremental backups
+// are never rotated away.
+type Quota struct {
+	Bytes     int64    `json:"bytes"`
+	Rotate    bool     `json:"rotate"`
+	Protected []string `json:"protected"`
+}
+
+// ErrQuotaExceeded: the backup doesn't fit in the server's quota, even
+// after rotating every backup that may be rotated.
+var ErrQuotaExceeded = errors.New("backup quota exceeded")
+
+// Usage is the backup storage on the node, reported to the panel with
+// every heartbeat.
+type Usage struct {
+	TotalBytes int64            `json:"totalBytes"`
+	Servers    map[string]int64 `json:"servers"`
+}
+
+// Usage sums the backup storage of every server on the node.
+func (m *Manager) Usage() (Usage, error) {
+	u := Usage{Servers: map[string]int64{}}
+	entries, err := os.ReadDir(filepath.Join(m.dataDir, "backups"))
+	if os.IsNotExist(err) {
+		return u, nil
+	}
+	if err != nil {
+		return u, err
+	}
+	for _, e := range entries {
+		if !e.IsDir() {
+			continue
+		}
+		n, err := m.ServerUsage(e.Name())
+		if err != nil {
+			continue
+		}
+		u.Servers[e.Name()] = n
+		u.TotalBytes += n
+	}
+	return u, nil
+}
+
+// ServerUsage is the bytes the server's backup directory holds:
+// archives, sidecars, the dedup repository and any import still being
+// written.
+func (m *Manager) ServerUsage(serverID string) (int64, error) {
+	entries, err := os.ReadDir(filepath.Join(m.dataDir, "backups", serverID))
+	if os.IsNotExist(err) {
+		return 0, nil
+	}
+	if err != nil {
+		return 0, err
+	}
+	total, err := m.repoUsage(serverID)
+	if err != nil {
+		return 0, err
+	}
+	for _, e := range entries {
+		if info, err := e.Info(); err == nil && info.Mode().IsRegular() {
+			total += info.Size()
+		}
+	}
+	return total, nil
+}
+
+// checkQuota is the pre-flight for a new backup: with no room left and
+// nothing to rotate, fail before spending minutes on the archive.
+func (m *Manager) checkQuota(serverID string, q Quota) error {
+	if q.Bytes <= 0 || q.Rotate {
+		return nil
+	}
+	used, err := m.ServerUsage(serverID)
+	if err != nil {
+		return err
+	}
+	if used >= q.Bytes {
+		return ErrQuotaExceeded
+	}
+	return nil
+}
+
+// enforceQuota runs once the backup `fresh` is on disk. If the server
+// is over q it either rotates the oldest backups out (returning their
+// names) or, when rotating can't free enough, deletes fresh and returns
+// ErrQuotaExceeded. Nothing is rotated unless that makes fresh fit.
+func (m *Manager) enforceQuota(serverID, fresh string, q Quota) ([]string, error) {
+	if q.Bytes <= 0 {
+		return nil, nil
+	}
+	used, err := m.ServerUsage(serverID)
+	if err != nil {
+		return nil, err
+	}
+	if used <= q.Bytes {
+		return nil, nil
+	}
+	var rotate []string
+	if q.Rotate {
+		list, err := m.List(serverID)
+		if err != nil {
+			return nil, err
+		}
+		dir := filepath.Join(m.dataDir, "backups", serverID)
+		bases := map[string]bool{}
+		for _, b := range list {
+			if b.Base != "" {
+				bases[b.Base] = true
+			}
+		}
+		for _, b := range list {
+			if used <= q.Bytes {
+				break
+			}
+			if b.Name == fresh || bases[b.Name] || slices.Contains(q.Protected, b.Name) {
+				continue
+			}
+			rotate = append(rotate, b.Name)
+			used -= b.Bytes + sidecarBytes(dir, b.Name)
+		}
+	}
+	if used > q.Bytes {
+		if err := m.Delete(serverID, fresh); err != nil {
+			return nil, err
+		}
+		return nil, ErrQuotaExceeded
+	}
+	for _, name := range rotate {
+		if err := m.Delete(serverID, name); err != nil {
+			return nil, err
+		}
+	}
+	return rotate, nil
+}
+
+func sidecarBytes(dir, name string) int64 {
+	var n int64
+	for _, p := range []string{manifestPath(dir, name), metadataPath(dir, name), indexPath(dir, name)} {
+		if st, err := os.Stat(p); err == nil {
+			n += st.Size()
+		}
+	}
+	return n
+}
@@ -0,0 +1,150 @@
+// Package capability describes what this daemon build can do, so the
+// panel can check before sending a request an older daemon would 404 or
+// misread. The report is sent with every heartbeat (the panel stores it
+// on the node row) and served on /api/system.
+//
+// Adding a feature string is the whole contract: the panel treats any
+// feature it doesn't find as unsupported and degrades (hides the
+// button, skips the step, returns nodes.unsupported_feature) instead of
+// calling the endpoint. Never rename or drop a string a shipped panel
+// checks for.
+package capability
+
+import "github.com/stellarstack/daemon/internal/events"
+
+// Feature strings for endpoints and behaviours added after the baseline
+// (power, console, files, backups create/restore/delete, transfers).
+const (
+	AllocationsSync   = "allocations.sync"
+	BackupImport      = "backup.import"
+	BackupIncremental = "backup.incremental"
+	BackupManifest    = "backup.manifest"
+	BackupMetadata    = "backup.metadata"
+	BackupQuota       = "backup.quota"
+	CommandCapture    = "command.capture"
+	ConfigApply       = "config.apply"
+	ConfigEffective   = "config.effective"
+	ConsoleSSE        = "console.sse"
+	Environment       = "environment"
+	FilesActivity     = "files.activity"
+	FilesNDJSON       = "files.ndjson"
+	FilesPullVerify   = "files.pull.verify"
+	FilesScan         = "files.scan"
+	FilesStructured   = "files.structured"
+	IdleShutdown      = "idle.shutdown"
+	ImagesPull        = "images.pull"
+	InstallArchive    = "install.archive"
+	LogLevels         = "log.levels"
+	LogTail           = "log.tail"
+	LogsPrune         = "logs.prune"
+	Macros            = "macros"
+	NetworkHosts      = "network.hosts"
+	Players           = "players"
+	PowerCountdown    = "power.countdown"
+	PowerPause        = "power.pause"
+	PowerQueue        = "power.queue"
+	PowerValidate     = "power.validate"
+	Proxy             = "proxy"
+	SelfUpdate        = "self.update"
+	ServerStates      = "servers.states"
+	StateChanges      = "servers.states.changes"
+	SFTPPaths         = "sftp.paths"
+	SFTPSessions      = "sftp.sessions"
+	SystemQueues      = "system.queues"
+	TransferIgnore    = "transfer.ignore"
+	WakeConnect       = "wake.connect"
+)
+
+// Report is the capability payload. Field names are part of the panel
+// contract.
+type Report struct {
+	Messages struct {
+		Current   int   `json:"current"`
+		Supported []int `json:"supported"`
+	} `json:"messages"`
+	// BackupDrivers are the storage backends `backups?op=create` accepts.
+	BackupDrivers []string `json:"backupDrivers"`
+	// TransferModes are the archive formats/verification schemes the
+	// transfer push and ingest endpoints speak.
+	TransferModes []string `json:"transferModes"`
+	// ScheduleTasks are the schedule task actions this daemon can carry
+	// out (the panel runs the schedule; the daemon runs the task).
+	ScheduleTasks []string `json:"scheduleTasks"`
+	Features      []string `json:"features"`
+}
+
+// Options are the config-dependent parts of the report.
+type Options struct {
+	ProxyEnabled      bool
+	SelfUpdateEnabled bool
+	LogFileEnabled    bool
+	SFTPEnabled       bool
+	ScanEnabled       bool
+	// HostNetwork: the daemon shares the host's network namespace, so
+	// it can hold a stopped server's ports for wake-on-connect.
+	HostNetwork bool
+}
+
+// Current builds the report for this build and config.
+func Current(opts Options) Report {
+	r := Report{
+		BackupDrivers: []string{"local"},
+		TransferModes: []string{"tar.gz", "sha256-trailer", "aes-256-gcm", "delta"},
+		ScheduleTasks: []string{"power", "command", "macro", "backup", "update", "prune_logs"},
+		Features: []string{
+			AllocationsSync,
+			BackupImport,
+			BackupIncremental,
+			BackupManifest,
+			BackupMetadata,
+			BackupQuota,
+			CommandCapture,
+			ConfigApply,
+			ConfigEffective,
+			ConsoleSSE,
+			Environment,
+			FilesActivity,
+			FilesNDJSON,
+			FilesPullVerify,
+			FilesStructured,
+			IdleShutdown,
+			ImagesPull,
+			InstallArchive,
+			LogLevels,
+			LogsPrune,
+			Macros,
+			NetworkHosts,
+			Players,
+			PowerCountdown,
+			PowerPause,
+			PowerQueue,
+			PowerValidate,
+			ServerStates,
+			StateChanges,
+			SFTPPaths,
+			SystemQueues,
+			TransferIgnore,
+		},
+	}
+	r.Messages.Current = events.SchemaVersion
+	r.Messages.Supported = events.SupportedVersions
+	if opts.ProxyEnabled {
+		r.Features = append(r.Features, Proxy)
+	}
+	if opts.SelfUpdateEnabled {
+		r.Features = append(r.Features, SelfUpdate)
+	}
+	if opts.LogFileEnabled {
+		r.Features = append(r.Features, LogTail)
+	}
+	if opts.SFTPEnabled {
+		r.Features = append(r.Features, SFTPSessions)
+	}
+	if opts.ScanEnabled {
+		r.Features = append(r.Features, FilesScan)
+	}
+	if opts.HostNetwork {
+		r.Features = append(r.Features, WakeConnect)
+	}
+	return r
+}
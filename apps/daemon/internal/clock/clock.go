@@ -0,0 +1,120 @@
+// Package clock keeps track of how far this node's clock is off. The
+// tokens the panel mints carry issue and expiry times that the daemon
+// checks against its own clock, so a drifting clock shows up as users
+// being refused for no visible reason; knowing the offset lets the
+// daemon say what is wrong instead. Two sources feed it: an NTP server
+// asked at startup, and the panel's Date header on every heartbeat,
+// which keeps the figure current.
+package clock
+
+import (
+	"context"
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"net"
+	"sync"
+	"time"
+)
+
+// Report is the last measurement. OffsetSeconds is the reference
+// clock minus this node's: positive means the node is behind.
+type Report struct {
+	OffsetSeconds float64   `json:"offsetSeconds"`
+	Source        string    `json:"source,omitempty"`
+	CheckedAt     time.Time `json:"checkedAt"`
+	Error         string    `json:"error,omitempty"`
+}
+
+var (
+	mu   sync.Mutex
+	last Report
+)
+
+// Record stores an offset measured against source ("ntp", "panel").
+func Record(source string, offset time.Duration) {
+	mu.Lock()
+	defer mu.Unlock()
+	last = Report{OffsetSeconds: offset.Seconds(), Source: source, CheckedAt: time.Now().UTC()}
+}
+
+// RecordError notes a failed measurement; the last offset stands.
+func RecordError(err error) {
+	mu.Lock()
+	defer mu.Unlock()
+	last.Error = err.Error()
+}
+
+// Current returns the last measurement; the zero Report when there has
+// been none.
+func Current() Report {
+	mu.Lock()
+	defer mu.Unlock()
+	return last
+}
+
+// Offset is Current's offset as a duration.
+func Offset() time.Duration {
+	return time.Duration(Current().OffsetSeconds * float64(time.Second))
+}
+
+// earliest is before any build of the daemon: a clock reading earlier
+// was never set (a board without an RTC booting at the epoch).
+var earliest = time.Date(2024, time.January, 1, 0, 0, 0, 0, time.UTC)
+
+// Sane reports a system clock that can't be right whatever NTP says.
+func Sane(now time.Time) error {
+	if now.Before(earliest) {
+		return fmt.Errorf("system clock reads %s; it was never set", now.UTC().Format(time.RFC3339))
+	}
+	return nil
+}
+
+// ntpEpoch is the NTP era's start, 1900-01-01, in Unix seconds.
+const ntpEpoch = -2208988800
+
+// QueryNTP asks server (host or host:port) for the time with one SNTP
+// request and returns its clock minus this node's.
+func QueryNTP(ctx context.Context, server string) (time.Duration, error) {
+	if _, _, err := net.SplitHostPort(server); err != nil {
+		server = net.JoinHostPort(server, "123")
+	}
+	var d net.Dialer
+	conn, err := d.DialContext(ctx, "udp", server)
+	if err != nil {
+		return 0, err
+	}
+	defer conn.Close()
+	if deadline, ok := ctx.Deadline(); ok {
+		_ = conn.SetDeadline(deadline)
+	}
+	req := make([]byte, 48)
+	req[0] = 0x1b // leap indicator 0, version 3, mode 3 (client)
+	sent := time.Now()
+	if _, err := conn.Write(req); err != nil {
+		return 0, err
+	}
+	resp := make([]byte, 48)
+	n, err := conn.Read(resp)
+	received := time.Now()
+	if err != nil {
+		return 0, err
+	}
+	if n < 48 || resp[0]&0x07 != 4 {
+		return 0, errors.New("ntp: malformed answer")
+	}
+	if resp[1] == 0 {
+		return 0, errors.New("ntp: server is unsynchronised")
+	}
+	serverReceived := ntpTime(resp[32:40])
+	serverSent := ntpTime(resp[40:48])
+	return (serverReceived.Sub(sent) + serverSent.Sub(received)) / 2, nil
+}
+
+// ntpTime decodes a 64-bit NTP timestamp: seconds since 1900 and a
+// binary fraction.
+func ntpTime(b []byte) time.Time {
+	secs := int64(binary.BigEndian.Uint32(b[:4]))
+	frac := int64(binary.BigEndian.Uint32(b[4:]))
+	return time.Unix(secs+ntpEpoch, frac*int64(time.Second)>>32)
+}
@@ -8,6 +8,9 @@ import (
 	"errors"
 	"fmt"
 	"os"
+	"path/filepath"
+	"strings"
+	"time"
 
 	"github.com/pelletier/go-toml/v2"
 )
@@ -27,6 +30,361 @@ type Config struct {
 	DataDir       string `toml:"data_dir"`
 	DockerSocket  string `toml:"docker_socket"`
 	HistoryLines  int    `toml:"history_lines"`
+
+	// ConsoleBuffer is how many frames a console subscriber may fall
+	// behind before frames are dropped for it. Default 64. Together with
+	// history_lines (the console replay depth, default 150) it is the
+	// node default; the panel can set either per server, e.g. deeper
+	// history for a modded server with verbose logs.
+	ConsoleBuffer int `toml:"console_buffer"`
+
+	// ExecAllowlist is the set of binaries (argv[0]) the admin exec
+	// endpoint will run inside a server container. Empty → the
+	// read-only inspection defaults in DefaultExecAllowlist.
+	ExecAllowlist []string `toml:"exec_allowlist"`
+
+	// AllocationReportSeconds is how often the daemon reports which
+	// allocation ports running containers actually publish, so the
+	// panel can flag stale assignments. Default 300.
+	AllocationReportSeconds int `toml:"allocation_report_seconds"`
+
+	// HostNetworkAllowlist lists the server UUIDs that may run with
+	// host networking when the panel asks for it; "*" allows every
+	// server. Host-mode containers skip Docker port bindings, so the
+	// daemon can't observe their ports (allocation reports echo the
+	// configured ones) and Docker reports no per-container network
+	// counters. Empty → host networking is refused.
+	HostNetworkAllowlist []string `toml:"host_network_allowlist"`
+
+	// CompressionThreads is the thread budget shared by every gzip
+	// stream the daemon writes (backups, transfers, compress jobs).
+	// Default one per CPU; lower it to leave cores for game servers
+	// while a backup runs.
+	CompressionThreads int `toml:"compression_threads"`
+
+	// ImagePullConcurrency caps the image pulls the panel may have
+	// this node prewarm at once; more wait their turn. Pulls for a
+	// server's own start or install aren't counted. Default 2.
+	ImagePullConcurrency int `toml:"image_pull_concurrency"`
+
+	Admission AdmissionConfig `toml:"admission"`
+	Stats     StatsConfig     `toml:"stats"`
+	Proxy     ProxyConfig     `toml:"proxy"`
+	Files     FilesConfig     `toml:"files"`
+	Update    UpdateConfig    `toml:"update"`
+	Log       LogConfig       `toml:"log"`
+	SFTP      SFTPConfig      `toml:"sftp"`
+	Transfer  TransferConfig  `toml:"transfer"`
+	Socket    SocketConfig    `toml:"socket"`
+	Scan      ScanConfig      `toml:"scan"`
+	Clock     ClockConfig     `toml:"clock"`
+	IO        IOConfig        `toml:"io"`
+	Install   InstallConfig   `toml:"install"`
+	Backup    BackupConfig    `toml:"backup"`
+}
+
+// TransferConfig bounds server transfers so a partner that dies
+// mid-transfer can't hold one open. timeout_minutes caps the whole
+// transfer (default 30; the panel may ask for less) and stall_seconds
+// how long either side waits with no bytes moving, or the source with
+// no answer to its heartbeat to the target (default 60). Inbound trees
+// are extracted under tmp_directory (default <data_dir>/tmp/transfers)
+// and moved into place only once verified, so keep it on the data
+// dir's filesystem; whatever a dead transfer leaves there is purged by
+// a janitor.
+//
+// bandwidth_mbps caps the node's transfer traffic, pushes and ingests
+// together (0, the default, is unlimited). window, e.g. "02:00-06:00"
+// in the node's local time, limits transfers the panel doesn't mark
+// urgent to those hours and may wrap past midnight; empty allows any
+// time. Outside it such transfers are refused and the panel queues them.
+//
+// max_ingest_mb caps the archive an inbound transfer may send, as it
+// arrives on the wire (413 beyond it). 0, the default, accepts any
+// size: a server's data legitimately runs to hundreds of gigabytes.
+type TransferConfig struct {
+	TmpDirectory   string `toml:"tmp_directory"`
+	TimeoutMinutes int    `toml:"timeout_minutes"`
+	StallSeconds   int    `toml:"stall_seconds"`
+	BandwidthMbps  int    `toml:"bandwidth_mbps"`
+	Window         string `toml:"window"`
+	MaxIngestMb    int    `toml:"max_ingest_mb"`
+}
+
+// WindowMinutes parses Window into minutes after local midnight. ok is
+// false when no window is set.
+func (t TransferConfig) WindowMinutes() (start, end int, ok bool, err error) {
+	if t.Window == "" {
+		return 0, 0, false, nil
+	}
+	from, to, found := strings.Cut(t.Window, "-")
+	if found {
+		start, err = clockMinutes(strings.TrimSpace(from))
+	}
+	if found && err == nil {
+		end, err = clockMinutes(strings.TrimSpace(to))
+	}
+	if !found || err != nil || start == end {
+		return 0, 0, false, fmt.Errorf("config: transfer.window %q: want HH:MM-HH:MM", t.Window)
+	}
+	return start, end, true, nil
+}
+
+// WindowOpen reports whether non-urgent transfers may run at now and,
+// if not, when the window next opens. Always open without a window.
+func (t TransferConfig) WindowOpen(now time.Time) (bool, time.Time) {
+	start, end, ok, err := t.WindowMinutes()
+	if !ok || err != nil {
+		return true, time.Time{}
+	}
+	m := now.Hour()*60 + now.Minute()
+	inside := m >= start && m < end
+	if start > end {
+		inside = m >= start || m < end
+	}
+	if inside {
+		return true, time.Time{}
+	}
+	y, mo, d := now.Date()
+	opens := time.Date(y, mo, d, 0, start, 0, 0, now.Location())
+	if !opens.After(now) {
+		opens = opens.AddDate(0, 0, 1)
+	}
+	return false, opens
+}
+
+func clockMinutes(s string) (int, error) {
+	t, err := time.Parse("15:04", s)
+	if err != nil {
+		return 0, err
+	}
+	return t.Hour()*60 + t.Minute(), nil
+}
+
+// SFTPConfig tunes the SFTP subsystem. write_buffer_kb coalesces each
+// upload's small WRITE packets into writes of that size (default 1024;
+// negative writes every packet straight to disk). write_behind flushes
+// full buffers on a background goroutine so the client's next packet
+// isn't held up by the disk; errors still surface before the client's
+// close of the file succeeds.
+//
+// The rest protect a public listener: max_conns_per_ip caps concurrent
+// connections from one address (default 10), and max_auth_failures
+// failed logins within auth_fail_window_seconds (default 5 in 300) ban
+// the address for ban_minutes (default 15). Negative disables each.
+type SFTPConfig struct {
+	WriteBufferKb         int  `toml:"write_buffer_kb"`
+	WriteBehind           bool `toml:"write_behind"`
+	MaxConnsPerIP         int  `toml:"max_conns_per_ip"`
+	MaxAuthFailures       int  `toml:"max_auth_failures"`
+	AuthFailWindowSeconds int  `toml:"auth_fail_window_seconds"`
+	BanMinutes            int  `toml:"ban_minutes"`
+}
+
+// LogConfig shapes the daemon's own log output. format is "pretty"
+// (default, the classic `date time message` lines) or "json" (one
+// object per line for log shippers). level is the default minimum
+// level (debug, info, warn, error; default info) and modules overrides
+// it per module — the word a line starts with, e.g. `transfer =
+// "debug"` or `server = "warn"`. Adjustable at runtime through
+// /api/system/log-levels.
+//
+// directory sends the log to <directory>/daemon.log instead of stderr.
+// The file rotates at max_size_mb or after rotate_hours, whichever
+// comes first; rotated files are gzipped and the newest max_files
+// younger than max_age_days kept. Defaults 50, 24, 10 and 14.
+type LogConfig struct {
+	Format      string            `toml:"format"`
+	Level       string            `toml:"level"`
+	Modules     map[string]string `toml:"modules"`
+	Directory   string            `toml:"directory"`
+	MaxSizeMb   int               `toml:"max_size_mb"`
+	RotateHours int               `toml:"rotate_hours"`
+	MaxFiles    int               `toml:"max_files"`
+	MaxAgeDays  int               `toml:"max_age_days"`
+}
+
+// UpdateConfig enables self-update from the panel or `stellar-daemon
+// update`. Releases are fetched from release_url and must verify
+// against public_key (hex Ed25519); see package selfupdate for the
+// layout. Either unset → self-update is off.
+type UpdateConfig struct {
+	ReleaseURL string `toml:"release_url"`
+	PublicKey  string `toml:"public_key"`
+}
+
+// FilesConfig bounds the file manager so one user hammering it can't
+// stall every server on the node. max_concurrent_jobs caps in-flight
+// compress/decompress/pull jobs per server and max_concurrent_lists
+// caps simultaneous directory listings per server (429 beyond either);
+// request_timeout_seconds bounds synchronous calls such as listings,
+// stats and moves (503 when exceeded). Defaults 2, 4 and 30.
+// plain_copy turns off the kernel-copy / large-buffer path in package
+// fsio and falls back to io.Copy everywhere. max_write_mb caps the body
+// of a file write (the editor's saves) and max_upload_mb a multipart
+// upload, streamed to disk, of however many files (413 beyond either;
+// defaults 50 and 100).
+//
+// The activity_* keys flag a server whose file manager use within
+// activity_window_minutes (default 10) reaches activity_read_mb read,
+// activity_write_mb written, activity_deletes entries deleted or
+// activity_archive_ops compresses and extracts: the daemon logs it and
+// files a warning in the server's activity log. 0 (the default)
+// disables a threshold.
+//
+// blocked_extensions (e.g. [".sh", ".so", ".qcow2"]) and blocked_types
+// (sniffed MIME types such as "application/x-executable") keep files
+// off every server however they arrive: writes, uploads, pulls,
+// extraction, SFTP and scp. blocked_allow_dirs are server-relative
+// directories exempt from both, e.g. ["/scripts"]. block_setuid refuses
+// setuid and setgid files. All empty (the default) → no policy.
+//
+// denylist and hidden are every server's default path lists, merged
+// with the blueprint's and the server's own (package ignore patterns,
+// e.g. [".git/", "*.db"]). Denied paths are refused by the file manager
+// and SFTP; hidden ones are left out of their listings.
+//
+// Writes, deletes, moves, extracts, compresses, backups and transfers
+// lock the paths they touch; one that conflicts with another in flight
+// waits up to lock_wait_seconds (default 5) for it, then gets 423.
+type FilesConfig struct {
+	MaxConcurrentJobs     int  `toml:"max_concurrent_jobs"`
+	MaxConcurrentLists    int  `toml:"max_concurrent_lists"`
+	RequestTimeoutSeconds int  `toml:"request_timeout_seconds"`
+	PlainCopy             bool `toml:"plain_copy"`
+	MaxWriteMb            int  `toml:"max_write_mb"`
+	MaxUploadMb           int  `toml:"max_upload_mb"`
+
+	ActivityWindowMinutes int   `toml:"activity_window_minutes"`
+	ActivityReadMb        int64 `toml:"activity_read_mb"`
+	ActivityWriteMb       int64 `toml:"activity_write_mb"`
+	ActivityDeletes       int64 `toml:"activity_deletes"`
+	ActivityArchiveOps    int64 `toml:"activity_archive_ops"`
+
+	BlockedExtensions []string `toml:"blocked_extensions"`
+	BlockedTypes      []string `toml:"blocked_types"`
+	BlockedAllowDirs  []string `toml:"blocked_allow_dirs"`
+	BlockSetuid       bool     `toml:"block_setuid"`
+
+	Denylist []string `toml:"denylist"`
+	Hidden   []string `toml:"hidden"`
+
+	LockWaitSeconds int `toml:"lock_wait_seconds"`
+}
+
+// ProxyConfig enables the built-in shared-port TCP proxy, which routes
+// connections to servers by TLS SNI or Minecraft handshake hostname
+// using rules pushed from the panel. Off unless `enabled = true`.
+type ProxyConfig struct {
+	Enabled bool   `toml:"enabled"`
+	Listen  string `toml:"listen"`
+}
+
+// SocketConfig additionally serves the REST API on a Unix socket at
+// path for node-local tooling (CLI utilities, the desktop app,
+// scripts). Requests on it need no HMAC signature: the daemon asks the
+// kernel who connected (SO_PEERCRED) and admits root, its own user and
+// the uids in allow_uids or, by primary group, allow_gids. mode is the
+// socket file's permissions, default 0o660. Empty path → off.
+type SocketConfig struct {
+	Path      string `toml:"path"`
+	Mode      uint32 `toml:"mode"`
+	AllowUIDs []int  `toml:"allow_uids"`
+	AllowGIDs []int  `toml:"allow_gids"`
+}
+
+// ScanConfig checks uploads and pulls for malware before they reach a
+// server; see package scan. clamd is clamd's socket path or host:port;
+// command an external scanner following clamscan's exit codes, e.g.
+// ["clamdscan", "--no-summary"]. Both unset → off.
+// Flagged files go to quarantine_dir (default <data_dir>/quarantine)
+// and the server's activity log. A scan that fails (scanner down,
+// timeout_seconds passed, default 60) lets the file through unless
+// fail_closed is set.
+type ScanConfig struct {
+	Clamd          string   `toml:"clamd"`
+	Command        []string `toml:"command"`
+	QuarantineDir  string   `toml:"quarantine_dir"`
+	FailClosed     bool     `toml:"fail_closed"`
+	TimeoutSeconds int      `toml:"timeout_seconds"`
+}
+
+// Enabled reports whether a scanner is configured.
+func (s ScanConfig) Enabled() bool { return s.Clamd != "" || len(s.Command) > 0 }
+
+// ClockConfig tolerates and checks clock drift between this node and
+// the panel, whose tokens carry issue and expiry times. leeway_seconds
+// (default 30) is how far past its expiry, or before its issue time, a
+// token is still accepted. ntp_server (default pool.ntp.org) is asked
+// for the time at startup, and the offset reported on /healthz and in
+// the log; "none" skips the check, e.g. on hosts without outbound UDP.
+type ClockConfig struct {
+	LeewaySeconds int    `toml:"leeway_seconds"`
+	NTPServer     string `toml:"ntp_server"`
+}
+
+// IOConfig caps the disk bandwidth the daemon's own work may take from
+// the game servers. mb_per_second (0, the default, is unlimited) is
+// shared by backups, restores, transfers, archive jobs and directory
+// size scans; a server's own file operations are counted against it
+// but never held back, so they push the rest aside. Backups and
+// transfers go before size scans when the budget is short. burst_mb
+// (default one second's worth) is how much may go at once after a lull.
+type IOConfig struct {
+	MBPerSecond int `toml:"mb_per_second"`
+	BurstMB     int `toml:"burst_mb"`
+}
+
+// InstallConfig limits install scripts. interpreters are what a
+// blueprint may name to run its script with (default bash, sh, ash and
+// python); a blueprint naming another is refused on this node. Scripts
+// that name none run as before, through the blueprint's entrypoint.
+type InstallConfig struct {
+	Interpreters []string `toml:"interpreters"`
+}
+
+// BackupConfig picks how local backups are stored. engine "tar" (the
+// default) writes one gzipped tarball per backup. "dedup" keeps each
+// server's backups in one content-addressed repository of chunks, so a
+// backup only stores what changed since any other; deleting a backup
+// frees the chunks nothing else uses. Backups made under either engine
+// stay restorable after switching.
+type BackupConfig struct {
+	Engine string `toml:"engine"`
+}
+
+// StatsConfig suppresses per-second stats frames that barely changed.
+// A frame is published only when some value moved by at least its
+// delta since the last published frame, or full_interval_seconds have
+// passed. All deltas 0 (the default) publishes every sample.
+type StatsConfig struct {
+	MemoryDeltaBytes    int64   `toml:"memory_delta_bytes"`
+	CPUDelta            float64 `toml:"cpu_delta"`
+	NetworkDeltaBytes   int64   `toml:"network_delta_bytes"`
+	DiskDeltaBytes      int64   `toml:"disk_delta_bytes"`
+	FullIntervalSeconds int     `toml:"full_interval_seconds"`
+}
+
+// DefaultExecAllowlist covers the commands support staff reach for when
+// inspecting a container: process, disk, network and file listings.
+// Only argv[0] is checked, so nothing here may run another program or
+// write through its arguments: find (-exec, -delete), env (env sh) and
+// java (any jar) are left out for that reason.
+var DefaultExecAllowlist = []string{
+	"cat", "df", "du", "free", "head", "id", "ls", "netstat",
+	"ps", "ss", "stat", "tail", "top", "uptime", "whoami",
+}
+
+// AdmissionConfig gates server starts and inbound transfers on the sum
+// of configured limits vs node capacity. Off unless `enabled = true`.
+// Capacity fields left at 0 are detected from the host at boot; the
+// overcommit ratios scale capacity (1.5 = allow 150% of physical).
+type AdmissionConfig struct {
+	Enabled          bool    `toml:"enabled"`
+	MemoryMb         int64   `toml:"memory_mb"`
+	CPUPercent       int64   `toml:"cpu_percent"`
+	MemoryOvercommit float64 `toml:"memory_overcommit"`
+	CPUOvercommit    float64 `toml:"cpu_overcommit"`
 }
 
 // Load reads the TOML at `path` and validates the required fields. The
@@ -69,5 +427,114 @@ func Load(path string) (*Config, error) {
 	if c.HistoryLines <= 0 {
 		c.HistoryLines = 150
 	}
+	if c.ConsoleBuffer <= 0 {
+		c.ConsoleBuffer = 64
+	}
+	if len(c.ExecAllowlist) == 0 {
+		c.ExecAllowlist = DefaultExecAllowlist
+	}
+	if c.AllocationReportSeconds <= 0 {
+		c.AllocationReportSeconds = 300
+	}
+	if c.Proxy.Listen == "" {
+		c.Proxy.Listen = ":25565"
+	}
+	if c.Files.MaxConcurrentJobs <= 0 {
+		c.Files.MaxConcurrentJobs = 2
+	}
+	if c.Files.MaxConcurrentLists <= 0 {
+		c.Files.MaxConcurrentLists = 4
+	}
+	if c.Files.RequestTimeoutSeconds <= 0 {
+		c.Files.RequestTimeoutSeconds = 30
+	}
+	if c.Files.MaxWriteMb <= 0 {
+		c.Files.MaxWriteMb = 50
+	}
+	if c.Files.MaxUploadMb <= 0 {
+		c.Files.MaxUploadMb = 100
+	}
+	if c.Files.LockWaitSeconds <= 0 {
+		c.Files.LockWaitSeconds = 5
+	}
+	if c.Files.ActivityWindowMinutes <= 0 {
+		c.Files.ActivityWindowMinutes = 10
+	}
+	if c.SFTP.WriteBufferKb == 0 {
+		c.SFTP.WriteBufferKb = 1024
+	}
+	if c.SFTP.MaxConnsPerIP == 0 {
+		c.SFTP.MaxConnsPerIP = 10
+	}
+	if c.SFTP.MaxAuthFailures == 0 {
+		c.SFTP.MaxAuthFailures = 5
+	}
+	if c.SFTP.AuthFailWindowSeconds == 0 {
+		c.SFTP.AuthFailWindowSeconds = 300
+	}
+	if c.SFTP.BanMinutes == 0 {
+		c.SFTP.BanMinutes = 15
+	}
+	if c.Log.MaxSizeMb <= 0 {
+		c.Log.MaxSizeMb = 50
+	}
+	if c.Log.RotateHours <= 0 {
+		c.Log.RotateHours = 24
+	}
+	if c.Log.MaxFiles <= 0 {
+		c.Log.MaxFiles = 10
+	}
+	if c.Log.MaxAgeDays <= 0 {
+		c.Log.MaxAgeDays = 14
+	}
+	if c.Stats.FullIntervalSeconds <= 0 {
+		c.Stats.FullIntervalSeconds = 10
+	}
+	if c.Admission.MemoryOvercommit <= 0 {
+		c.Admission.MemoryOvercommit = 1
+	}
+	if c.Admission.CPUOvercommit <= 0 {
+		c.Admission.CPUOvercommit = 1
+	}
+	if c.Transfer.TmpDirectory == "" {
+		c.Transfer.TmpDirectory = filepath.Join(c.DataDir, "tmp", "transfers")
+	}
+	if c.Transfer.TimeoutMinutes <= 0 {
+		c.Transfer.TimeoutMinutes = 30
+	}
+	if c.Transfer.StallSeconds <= 0 {
+		c.Transfer.StallSeconds = 60
+	}
+	if c.Socket.Mode == 0 {
+		c.Socket.Mode = 0o660
+	}
+	if c.Scan.QuarantineDir == "" {
+		c.Scan.QuarantineDir = filepath.Join(c.DataDir, "quarantine")
+	}
+	if c.Scan.TimeoutSeconds <= 0 {
+		c.Scan.TimeoutSeconds = 60
+	}
+	if c.Clock.LeewaySeconds <= 0 {
+		c.Clock.LeewaySeconds = 30
+	}
+	if c.Clock.NTPServer == "" {
+		c.Clock.NTPServer = "pool.ntp.org"
+	}
+	if c.ImagePullConcurrency <= 0 {
+		c.ImagePullConcurrency = 2
+	}
+	if c.Install.Interpreters == nil {
+		c.Install.Interpreters = []string{"bash", "sh", "ash", "python"}
+	}
+	switch c.Backup.Engine {
+	case "":
+		c.Backup.Engine = "tar"
+	case "tar", "dedup":
+	default:
+		return nil, fmt.Errorf("config: backup.engine %q: want tar or dedup", c.Backup.Engine)
+	}
+	if _, _, _, err := c.Transfer.WindowMinutes(); err != nil {
+		return nil, err
+	}
 	return &c, nil
 }
@@ -0,0 +1,235 @@
+// Package configfile reads and patches game config files by key, for
+// the boot-time config patcher and the files API's structured editor.
+// Format names match the blueprint configFiles parser names.
+//
+// Properties files are patched line by line, keeping comments and
+// order; YAML node by node, keeping them too. JSON and TOML are
+// decoded, changed and re-encoded: the values survive, but comments
+// (TOML) and key order don't.
+//
+// Keys in JSON, TOML and YAML are dotted paths to a leaf
+// ("server.port"); a key with a dot in it can't be addressed.
+package configfile
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"path/filepath"
+	"sort"
+	"strings"
+
+	"github.com/pelletier/go-toml/v2"
+)
+
+// Formats.
+const (
+	Properties = "properties"
+	JSON       = "json"
+	TOML       = "toml"
+	YAML       = "yaml"
+)
+
+var (
+	// ErrUnsupported is returned for a format this package can't parse.
+	ErrUnsupported = errors.New("config format not supported")
+	// ErrBadValue is a value the format can't hold at that key, e.g. an
+	// object in a properties file or a key under a scalar.
+	ErrBadValue = errors.New("value doesn't fit the file")
+)
+
+// Entry is one key and its value. Properties values are strings; JSON,
+// TOML and YAML ones keep their type, arrays included.
+type Entry struct {
+	Key   string `json:"key"`
+	Value any    `json:"value"`
+}
+
+// Detect names the format of path from its extension.
+func Detect(path string) (string, bool) {
+	switch strings.ToLower(filepath.Ext(path)) {
+	case ".properties":
+		return Properties, true
+	case ".json":
+		return JSON, true
+	case ".toml":
+		return TOML, true
+	case ".yml", ".yaml":
+		return YAML, true
+	}
+	return "", false
+}
+
+// Read lists body's keys: properties and YAML leaves in file order,
+// JSON and TOML ones sorted by path.
+func Read(format string, body []byte) ([]Entry, error) {
+	switch format {
+	case Properties:
+		return parseProperties(body), nil
+	case YAML:
+		return readYAML(body)
+	case JSON, TOML:
+		doc, err := decode(format, body)
+		if err != nil {
+			return nil, err
+		}
+		entries := []Entry{}
+		flatten("", doc, &entries)
+		return entries, nil
+	}
+	return nil, ErrUnsupported
+}
+
+// Patch sets each key in set and returns the new file, after parsing it
+// back to make sure it reads as intended. Numbers may be json.Number.
+func Patch(format string, body []byte, set map[string]any) ([]byte, error) {
+	switch format {
+	case Properties:
+		patches := make(map[string]string, len(set))
+		for k, v := range set {
+			switch v.(type) {
+			case map[string]any, []any, nil:
+				return nil, fmt.Errorf("%w: %s", ErrBadValue, k)
+			}
+			patches[k] = fmt.Sprint(v)
+		}
+		out := PatchProperties(body, patches)
+		if err := CheckProperties(out, patches); err != nil {
+			return nil, err
+		}
+		return out, nil
+	case YAML:
+		return patchYAML(body, set)
+	case JSON, TOML:
+		doc, err := decode(format, body)
+		if err != nil {
+			return nil, err
+		}
+		for k, v := range set {
+			if err := setPath(doc, strings.Split(k, "."), normalize(format, v)); err != nil {
+				return nil, fmt.Errorf("%w: %s", err, k)
+			}
+		}
+		out, err := encode(format, doc)
+		if err != nil {
+			return nil, err
+		}
+		if _, err := decode(format, out); err != nil {
+			return nil, err
+		}
+		return out, nil
+	}
+	return nil, ErrUnsupported
+}
+
+// Check reads body as format and checks that every key in want comes
+// back as exactly that value, as Read prints it: the read-back after a
+// patch, for writers that validate what actually reached the disk.
+func Check(format string, body []byte, want map[string]string) error {
+	if format == Properties {
+		return CheckProperties(body, want)
+	}
+	entries, err := Read(format, body)
+	if err != nil {
+		return err
+	}
+	got := make(map[string]string, len(entries))
+	for _, e := range entries {
+		got[e.Key] = fmt.Sprint(e.Value)
+	}
+	for k, v := range want {
+		if got[k] != v {
+			return fmt.Errorf("%s would read as %q, not %q", k, got[k], v)
+		}
+	}
+	return nil
+}
+
+func decode(format string, body []byte) (map[string]any, error) {
+	doc := map[string]any{}
+	if len(bytes.TrimSpace(body)) == 0 {
+		return doc, nil
+	}
+	var err error
+	if format == JSON {
+		dec := json.NewDecoder(bytes.NewReader(body))
+		dec.UseNumber()
+		err = dec.Decode(&doc)
+	} else {
+		err = toml.Unmarshal(body, &doc)
+	}
+	if err != nil {
+		return nil, fmt.Errorf("parse %s: %w", format, err)
+	}
+	return doc, nil
+}
+
+func encode(format string, doc map[string]any) ([]byte, error) {
+	if format == JSON {
+		out, err := json.MarshalIndent(doc, "", "  ")
+		return append(out, '\n'), err
+	}
+	return toml.Marshal(doc)
+}
+
+// normalize turns JSON numbers into the integer or float TOML and YAML
+// should write; JSON keeps them verbatim.
+func normalize(format string, v any) any {
+	if format == JSON {
+		return v
+	}
+	switch t := v.(type) {
+	case json.Number:
+		if i, err := t.Int64(); err == nil {
+			return i
+		}
+		f, _ := t.Float64()
+		return f
+	case []any:
+		for i := range t {
+			t[i] = normalize(format, t[i])
+		}
+	case map[string]any:
+		for k := range t {
+			t[k] = normalize(format, t[k])
+		}
+	}
+	return v
+}
+
+func flatten(prefix string, node map[string]any, out *[]Entry) {
+	keys := make([]string, 0, len(node))
+	for k := range node {
+		keys = append(keys, k)
+	}
+	sort.Strings(keys)
+	for _, k := range keys {
+		path := k
+		if prefix != "" {
+			path = prefix + "." + k
+		}
+		if child, ok := node[k].(map[string]any); ok && len(child) > 0 {
+			flatten(path, child, out)
+			continue
+		}
+		*out = append(*out, Entry{Key: path, Value: node[k]})
+	}
+}
+
+func setPath(node map[string]any, path []string, v any) error {
+	for _, k := range path[:len(path)-1] {
+		switch child := node[k].(type) {
+		case map[string]any:
+			node = child
+		case nil:
+			next := map[string]any{}
+			node[k] = next
+			node = next
+		default:
+			return ErrBadValue
+		}
+	}
+	node[path[len(path)-1]] = v
+	return nil
+}
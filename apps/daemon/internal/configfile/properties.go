@@ -0,0 +1,113 @@
+package configfile
+
+import (
+	"fmt"
+	"strings"
+)
+
+// PatchProperties sets each key in patches, in place where the key
+// already has a line and appended otherwise. Comments, order and every
+// other line are kept.
+func PatchProperties(body []byte, patches map[string]string) []byte {
+	lines := strings.Split(string(body), "\n")
+	seen := map[string]bool{}
+	for i, raw := range lines {
+		trimmed := strings.TrimSpace(raw)
+		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
+			continue
+		}
+		eq := strings.IndexAny(raw, "=:")
+		if eq < 0 {
+			continue
+		}
+		key := strings.TrimSpace(raw[:eq])
+		if val, ok := patches[key]; ok {
+			lines[i] = key + "=" + val
+			seen[key] = true
+		}
+	}
+	for k, v := range patches {
+		if seen[k] {
+			continue
+		}
+		lines = append(lines, k+"="+v)
+	}
+	out := strings.Join(lines, "\n")
+	if !strings.HasSuffix(out, "\n") {
+		out += "\n"
+	}
+	return []byte(out)
+}
+
+// CheckProperties reads body the way Java loads a .properties file and
+// checks that every patched key comes back as exactly its value. A
+// substituted value with a newline, a trailing backslash (which joins
+// the next line on) or leading blanks, or a key with a separator in it,
+// would otherwise leave a file the server reads differently.
+func CheckProperties(body []byte, patches map[string]string) error {
+	got := map[string]string{}
+	for _, e := range parseProperties(body) {
+		got[e.Key] = e.Value.(string)
+	}
+	for k, v := range patches {
+		if got[k] != v {
+			return fmt.Errorf("%s would read as %q, not %q", k, got[k], v)
+		}
+	}
+	return nil
+}
+
+// parseProperties lists body's keys in file order. A key set twice is
+// listed once, where it first appears, with its last value (the one
+// Java keeps).
+func parseProperties(body []byte) []Entry {
+	entries := []Entry{}
+	index := map[string]int{}
+	lines := strings.Split(string(body), "\n")
+	for i := 0; i < len(lines); i++ {
+		line := strings.TrimLeft(strings.TrimSuffix(lines[i], "\r"), " \t\f")
+		if line == "" || line[0] == '#' || line[0] == '!' {
+			continue
+		}
+		for continuesLine(line) && i+1 < len(lines) {
+			i++
+			line = line[:len(line)-1] + strings.TrimLeft(strings.TrimSuffix(lines[i], "\r"), " \t\f")
+		}
+		key, val := splitProperty(line)
+		if at, ok := index[key]; ok {
+			entries[at].Value = val
+			continue
+		}
+		index[key] = len(entries)
+		entries = append(entries, Entry{Key: key, Value: val})
+	}
+	return entries
+}
+
+// continuesLine reports whether line ends in an odd number of
+// backslashes, i.e. runs on to the next line.
+func continuesLine(line string) bool {
+	n := len(line) - len(strings.TrimRight(line, "\\"))
+	return n%2 == 1
+}
+
+// splitProperty splits a logical .properties line at its first
+// unescaped '=', ':' or blank.
+func splitProperty(line string) (key, val string) {
+	end := len(line)
+	for i := 0; i < len(line); i++ {
+		if line[i] == '\\' {
+			i++
+			continue
+		}
+		if strings.IndexByte("=: \t\f", line[i]) >= 0 {
+			end = i
+			break
+		}
+	}
+	key, rest := line[:end], strings.TrimLeft(line[end:], " \t\f")
+	if rest != "" && (rest[0] == '=' || rest[0] == ':') {
+		rest = strings.TrimLeft(rest[1:], " \t\f")
+	}
+	return key, rest
+}
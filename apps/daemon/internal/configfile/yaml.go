@@ -0,0 +1,156 @@
+package configfile
+
+import (
+	"bytes"
+	"fmt"
+	"strings"
+
+	"gopkg.in/yaml.v3"
+)
+
+// YAML goes through yaml.v3's node tree rather than being decoded into
+// maps like JSON and TOML: the nodes keep the comments and key order
+// hand-edited server configs (bukkit.yml, paper's) depend on. Only the
+// indentation changes, to two spaces, when a patched file is written.
+
+// parseYAML is body's document node. An empty body, or one with only
+// comments, is an empty mapping; any other top level is refused.
+func parseYAML(body []byte) (*yaml.Node, error) {
+	var doc yaml.Node
+	if err := yaml.Unmarshal(body, &doc); err != nil {
+		return nil, fmt.Errorf("parse yaml: %w", err)
+	}
+	if len(doc.Content) == 0 {
+		return &yaml.Node{Kind: yaml.DocumentNode, Content: []*yaml.Node{{Kind: yaml.MappingNode, Tag: "!!map"}}}, nil
+	}
+	if doc.Content[0].Kind != yaml.MappingNode {
+		return nil, fmt.Errorf("parse yaml: %w: the document isn't a mapping", ErrBadValue)
+	}
+	return &doc, nil
+}
+
+func readYAML(body []byte) ([]Entry, error) {
+	doc, err := parseYAML(body)
+	if err != nil {
+		return nil, err
+	}
+	entries := []Entry{}
+	if err := flattenYAML("", doc.Content[0], &entries); err != nil {
+		return nil, err
+	}
+	return entries, nil
+}
+
+// flattenYAML lists node's leaves in file order, as flatten does for a
+// decoded map.
+func flattenYAML(prefix string, node *yaml.Node, out *[]Entry) error {
+	for i := 0; i+1 < len(node.Content); i += 2 {
+		path := node.Content[i].Value
+		if prefix != "" {
+			path = prefix + "." + path
+		}
+		val := node.Content[i+1]
+		if val.Kind == yaml.MappingNode && len(val.Content) > 0 {
+			if err := flattenYAML(path, val, out); err != nil {
+				return err
+			}
+			continue
+		}
+		var v any
+		if err := val.Decode(&v); err != nil {
+			return fmt.Errorf("parse yaml: %s: %w", path, err)
+		}
+		*out = append(*out, Entry{Key: path, Value: stringKeys(v)})
+	}
+	return nil
+}
+
+func patchYAML(body []byte, set map[string]any) ([]byte, error) {
+	doc, err := parseYAML(body)
+	if err != nil {
+		return nil, err
+	}
+	for k, v := range set {
+		if err := setYAMLPath(doc.Content[0], strings.Split(k, "."), normalize(YAML, v)); err != nil {
+			return nil, fmt.Errorf("%w: %s", err, k)
+		}
+	}
+	var buf bytes.Buffer
+	enc := yaml.NewEncoder(&buf)
+	enc.SetIndent(2)
+	if err := enc.Encode(doc); err != nil {
+		return nil, err
+	}
+	if err := enc.Close(); err != nil {
+		return nil, err
+	}
+	if _, err := readYAML(buf.Bytes()); err != nil {
+		return nil, err
+	}
+	return buf.Bytes(), nil
+}
+
+// setYAMLPath is setPath over a mapping node. A replaced value keeps
+// the comments around it; missing mappings on the way (or null ones)
+// are created.
+func setYAMLPath(node *yaml.Node, path []string, v any) error {
+	for i, k := range path {
+		var val *yaml.Node
+		for j := 0; j+1 < len(node.Content); j += 2 {
+			if node.Content[j].Value == k {
+				val = node.Content[j+1]
+				break
+			}
+		}
+		if i == len(path)-1 {
+			var leaf yaml.Node
+			if err := leaf.Encode(v); err != nil {
+				return err
+			}
+			if val == nil {
+				node.Content = append(node.Content, yamlKey(k), &leaf)
+				return nil
+			}
+			leaf.HeadComment, leaf.LineComment, leaf.FootComment = val.HeadComment, val.LineComment, val.FootComment
+			*val = leaf
+			return nil
+		}
+		switch {
+		case val == nil:
+			val = &yaml.Node{Kind: yaml.MappingNode, Tag: "!!map"}
+			node.Content = append(node.Content, yamlKey(k), val)
+		case val.Kind == yaml.ScalarNode && val.Tag == "!!null":
+			*val = yaml.Node{Kind: yaml.MappingNode, Tag: "!!map", HeadComment: val.HeadComment, LineComment: val.LineComment}
+		case val.Kind != yaml.MappingNode:
+			return ErrBadValue
+		}
+		node = val
+	}
+	return nil
+}
+
+func yamlKey(k string) *yaml.Node {
+	return &yaml.Node{Kind: yaml.ScalarNode, Tag: "!!str", Value: k}
+}
+
+// stringKeys turns the map[any]any yaml.v3 decodes a mapping with
+// non-string keys into, which JSON can't encode, into map[string]any.
+func stringKeys(v any) any {
+	switch t := v.(type) {
+	case map[any]any:
+		m := make(map[string]any, len(t))
+		for k, e := range t {
+			m[fmt.Sprint(k)] = stringKeys(e)
+		}
+		return m
+	case map[string]any:
+		for k, e := range t {
+			t[k] = stringKeys(e)
+		}
+	case []any:
+		for i, e := range t {
+			t[i] = stringKeys(e)
+		}
+	}
+	return v
+}
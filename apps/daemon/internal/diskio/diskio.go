@@ -0,0 +1,163 @@
+// Package diskio shares the node's disk bandwidth between the game
+// servers and the daemon's own background work. Everything the daemon
+// reads or writes in bulk draws bytes from one token bucket, by class:
+// Live (a server's own file operations) is charged but never held,
+// Background (backups, transfers, archive jobs) waits for the bucket to
+// have tokens, and Maintenance (size scans, hashing for manifests)
+// waits for it to be half full. Live traffic therefore pushes the
+// others back, and backups run ahead of maintenance.
+//
+// Unconfigured, nothing is throttled and Reader and Writer return what
+// they're given, so file-to-file copies keep their kernel fast path.
+package diskio
+
+import (
+	"context"
+	"io"
+	"sync"
+	"time"
+)
+
+// Class is a caller's priority.
+type Class int
+
+const (
+	// Live is I/O a game server or its user is waiting on.
+	Live Class = iota
+	// Background is work someone asked for but isn't watching byte by
+	// byte: backups, restores, transfers, compress and extract jobs.
+	Background
+	// Maintenance is the daemon's own upkeep: directory size scans,
+	// hashing for transfer manifests.
+	Maintenance
+)
+
+// EntryCost is what visiting one directory entry is charged as: scans
+// move few bytes but cost a seek each.
+const EntryCost = 4 << 10
+
+// bucket is a token bucket in bytes. tokens may go negative: Live
+// charges run it into debt that the other classes wait out.
+type bucket struct {
+	mu     sync.Mutex
+	rate   float64 // bytes per second
+	burst  float64
+	tokens float64
+	last   time.Time
+}
+
+var (
+	mu      sync.RWMutex
+	current *bucket
+)
+
+// Configure limits the bytes per second all classes share, with bursts
+// up to burst bytes (one second's worth when 0). bytesPerSec 0 turns
+// throttling off.
+func Configure(bytesPerSec, burst int64) {
+	mu.Lock()
+	defer mu.Unlock()
+	if bytesPerSec <= 0 {
+		current = nil
+		return
+	}
+	if burst <= 0 {
+		burst = bytesPerSec
+	}
+	current = &bucket{rate: float64(bytesPerSec), burst: float64(burst), tokens: float64(burst), last: time.Now()}
+}
+
+func active() *bucket {
+	mu.RLock()
+	defer mu.RUnlock()
+	return current
+}
+
+// Wait takes n bytes from the bucket for class, blocking until the
+// class may have them. Live never blocks.
+func Wait(ctx context.Context, class Class, n int) error {
+	b := active()
+	if b == nil || n <= 0 {
+		return nil
+	}
+	for {
+		delay := b.take(class, float64(n))
+		if delay <= 0 {
+			return nil
+		}
+		timer := time.NewTimer(delay)
+		select {
+		case <-ctx.Done():
+			timer.Stop()
+			return ctx.Err()
+		case <-timer.C:
+		}
+	}
+}
+
+// take grants n to class and returns 0, or returns how long to wait
+// before asking again.
+func (b *bucket) take(class Class, n float64) time.Duration {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	now := time.Now()
+	b.tokens = min(b.burst, b.tokens+now.Sub(b.last).Seconds()*b.rate)
+	b.last = now
+	var floor float64
+	switch class {
+	case Live:
+		b.tokens -= n
+		return 0
+	case Maintenance:
+		floor = b.burst / 2
+	}
+	if b.tokens > floor {
+		// A grant may overdraw: reads don't come in bucket-sized pieces.
+		b.tokens -= n
+		return 0
+	}
+	return max(time.Millisecond, time.Duration((floor-b.tokens)/b.rate*float64(time.Second)))
+}
+
+// Reader charges class for every byte read from r.
+func Reader(ctx context.Context, class Class, r io.Reader) io.Reader {
+	if active() == nil {
+		return r
+	}
+	return &reader{ctx: ctx, class: class, r: r}
+}
+
+type reader struct {
+	ctx   context.Context
+	class Class
+	r     io.Reader
+}
+
+func (r *reader) Read(p []byte) (int, error) {
+	n, err := r.r.Read(p)
+	if werr := Wait(r.ctx, r.class, n); werr != nil && err == nil {
+		err = werr
+	}
+	return n, err
+}
+
+// Writer charges class for every byte written to w, before writing it.
+func Writer(ctx context.Context, class Class, w io.Writer) io.Writer {
+	if active() == nil {
+		return w
+	}
+	return &writer{ctx: ctx, class: class, w: w}
+}
+
+type writer struct {
+	ctx   context.Context
+	class Class
+	w     io.Writer
+}
+
+func (w *writer) Write(p []byte) (int, error) {
+	if err := Wait(w.ctx, w.class, len(p)); err != nil {
+		return 0, err
+	}
+	return w.w.Write(p)
+}
@@ -13,13 +13,15 @@ import (
 	"errors"
 	"fmt"
 	"io"
-	"log"
 	"net"
 	"net/http"
 	"net/url"
+	"sort"
 	"strings"
 	"sync"
 	"time"
+
+	"github.com/stellarstack/daemon/internal/logging"
 )
 
 // API version matches what's stable on Docker Engine 25+ (Colima ships
@@ -96,9 +98,36 @@ func (e *ContainerNotFoundError) Error() string { return "container not found: "
 
 // PortMapping describes a host:container port pair.
 type PortMapping struct {
-	HostIP        string
-	HostPort      int
-	ContainerPort int
+	HostIP        string `json:"hostIp"`
+	HostPort      int    `json:"hostPort"`
+	HostPortEnd   int    `json:"hostPortEnd,omitempty"` // inclusive end of a contiguous block; 0 = single port
+	ContainerPort int    `json:"containerPort"`
+	Protocol      string `json:"protocol,omitempty"` // "tcp" (default), "udp" or "both"
+}
+
+// MaxPortSpan caps a single block. The Engine API has no range syntax,
+// so every port becomes its own binding (and, with the userland proxy,
+// its own docker-proxy process); host networking suits anything larger.
+const MaxPortSpan = 1024
+
+// Span is the number of ports the mapping covers.
+func (p PortMapping) Span() int {
+	if p.HostPortEnd <= p.HostPort {
+		return 1
+	}
+	return p.HostPortEnd - p.HostPort + 1
+}
+
+// Protocols returns the Docker protocol names the mapping binds.
+func (p PortMapping) Protocols() []string {
+	switch p.Protocol {
+	case "udp":
+		return []string{"udp"}
+	case "both":
+		return []string{"tcp", "udp"}
+	default:
+		return []string{"tcp"}
+	}
 }
 
 // CreateContainerOptions is the (partial) Docker create payload we use.
@@ -119,6 +148,9 @@ type CreateContainerOptions struct {
 	OpenStdin        bool
 	Tty              bool
 	NetworkMode      string
+	NetworkAliases   []string // DNS names on NetworkMode (user-defined networks only)
+	ExtraHosts       []string // "hostname:ip" lines for the container's /etc/hosts
+	Volumes          []string // extra "source:target" binds / named volumes
 	AutoRemove       bool
 	User             string
 }
@@ -134,16 +166,21 @@ func (c *Client) CreateContainer(ctx context.Context, opts CreateContainerOption
 	exposed := map[string]struct{}{}
 	bindings := map[string][]map[string]string{}
 	for _, p := range opts.Ports {
-		key := fmt.Sprintf("%d/tcp", p.ContainerPort)
-		exposed[key] = struct{}{}
 		host := p.HostIP
 		if host == "" {
 			host = "0.0.0.0"
 		}
-		bindings[key] = append(bindings[key], map[string]string{
-			"HostIp":   host,
-			"HostPort": fmt.Sprintf("%d", p.HostPort),
-		})
+		// Blocks map host port N+i to container port ContainerPort+i.
+		for _, proto := range p.Protocols() {
+			for i := 0; i < p.Span(); i++ {
+				key := fmt.Sprintf("%d/%s", p.ContainerPort+i, proto)
+				exposed[key] = struct{}{}
+				bindings[key] = append(bindings[key], map[string]string{
+					"HostIp":   host,
+					"HostPort": fmt.Sprintf("%d", p.HostPort+i),
+				})
+			}
+		}
 	}
 	hostConfig := map[string]any{
 		"PortBindings": bindings,
@@ -157,12 +194,19 @@ func (c *Client) CreateContainer(ctx context.Context, opts CreateContainerOption
 		hostConfig["CpuPeriod"] = 100_000
 		hostConfig["CpuQuota"] = opts.CPULimitPercent * 1000
 	}
+	binds := append([]string{}, opts.Volumes...)
 	if opts.BindMount != "" {
-		hostConfig["Binds"] = []string{opts.BindMount + ":/home/container"}
+		binds = append(binds, opts.BindMount+":/home/container")
+	}
+	if len(binds) > 0 {
+		hostConfig["Binds"] = binds
 	}
 	if opts.NetworkMode != "" {
 		hostConfig["NetworkMode"] = opts.NetworkMode
 	}
+	if len(opts.ExtraHosts) > 0 {
+		hostConfig["ExtraHosts"] = opts.ExtraHosts
+	}
 
 	body := map[string]any{
 		"Image":        opts.Image,
@@ -191,6 +235,13 @@ func (c *Client) CreateContainer(ctx context.Context, opts CreateContainerOption
 	if opts.User != "" {
 		body["User"] = opts.User
 	}
+	if opts.NetworkMode != "" && len(opts.NetworkAliases) > 0 {
+		body["NetworkingConfig"] = map[string]any{
+			"EndpointsConfig": map[string]any{
+				opts.NetworkMode: map[string]any{"Aliases": opts.NetworkAliases},
+			},
+		}
+	}
 
 	q := url.Values{}
 	q.Set("name", opts.Name)
@@ -272,6 +323,33 @@ func (c *Client) KillContainer(ctx context.Context, name, signal string) error {
 	return nil
 }
 
+// PauseContainer freezes every process in the container (cgroup
+// freezer); memory stays as it is.
+func (c *Client) PauseContainer(ctx context.Context, name string) error {
+	return c.freezeOp(ctx, name, "pause")
+}
+
+// UnpauseContainer thaws a paused container.
+func (c *Client) UnpauseContainer(ctx context.Context, name string) error {
+	return c.freezeOp(ctx, name, "unpause")
+}
+
+func (c *Client) freezeOp(ctx context.Context, name, op string) error {
+	resp, err := c.do(ctx, http.MethodPost, "/containers/"+name+"/"+op, nil)
+	if err != nil {
+		return err
+	}
+	if resp.StatusCode == http.StatusNotFound {
+		resp.Body.Close()
+		return &ContainerNotFoundError{Name: name}
+	}
+	if resp.StatusCode/100 != 2 {
+		return errorFromResponse(resp, op)
+	}
+	resp.Body.Close()
+	return nil
+}
+
 // RemoveContainer deletes the container. Pass force=true to remove a
 // running one.
 func (c *Client) RemoveContainer(ctx context.Context, name string, force bool) error {
@@ -295,16 +373,21 @@ func (c *Client) RemoveContainer(ctx context.Context, name string, force bool) e
 }
 
 // InspectState reports the container's running flag, exit code, started-at
-// timestamp, and OOM flag. Returns ContainerNotFoundError when missing.
+// timestamp, OOM flag and init process PID (0 when not running).
+// Returns ContainerNotFoundError when missing.
 type State struct {
 	Running    bool
 	ExitCode   int
 	OOMKilled  bool
 	StartedAt  string
+	Pid        int
 	StopSignal string
+	// Env is the environment the container was created with.
+	Env map[string]string
 }
 
-// Inspect returns container state plus the configured StopSignal.
+// Inspect returns container state plus the configured StopSignal and
+// environment.
 func (c *Client) Inspect(ctx context.Context, name string) (*State, error) {
 	resp, err := c.do(ctx, http.MethodGet, "/containers/"+name+"/json", nil)
 	if err != nil {
@@ -323,20 +406,29 @@ func (c *Client) Inspect(ctx context.Context, name string) (*State, error) {
 			ExitCode  int
 			OOMKilled bool
 			StartedAt string
+			Pid       int
 		}
 		Config struct {
 			StopSignal string
+			Env        []string
 		}
 	}
 	if err := json.NewDecoder(resp.Body).Decode(&raw); err != nil {
 		return nil, err
 	}
+	env := make(map[string]string, len(raw.Config.Env))
+	for _, kv := range raw.Config.Env {
+		k, v, _ := strings.Cut(kv, "=")
+		env[k] = v
+	}
 	return &State{
 		Running:    raw.State.Running,
 		ExitCode:   raw.State.ExitCode,
 		OOMKilled:  raw.State.OOMKilled,
 		StartedAt:  raw.State.StartedAt,
+		Pid:        raw.State.Pid,
 		StopSignal: raw.Config.StopSignal,
+		Env:        env,
 	}, nil
 }
 
@@ -350,6 +442,19 @@ func (c *Client) IsRunning(ctx context.Context, name string) bool {
 	return st.Running
 }
 
+// Ping reports whether the Docker engine answers.
+func (c *Client) Ping(ctx context.Context) error {
+	resp, err := c.do(ctx, http.MethodGet, "/_ping", nil)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return fmt.Errorf("docker ping: %s", resp.Status)
+	}
+	return nil
+}
+
 // WaitNotRunning matches the *current* state — fires immediately for a
 // created or exited container. Used by stop/wait paths that want to
 // know "is the container down yet, possibly already".
@@ -381,6 +486,31 @@ func (c *Client) containerWait(ctx context.Context, name, condition string) bool
 	return true
 }
 
+// ImagePresent reports whether image is already in the local store.
+func (c *Client) ImagePresent(ctx context.Context, image string) bool {
+	resp, err := c.do(ctx, http.MethodGet, "/images/"+url.PathEscape(image)+"/json", nil)
+	if err != nil {
+		return false
+	}
+	resp.Body.Close()
+	return resp.StatusCode == http.StatusOK
+}
+
+// ImagePullable asks the registry for image's manifest via the Engine
+// (/distribution/:name/json) without pulling any layers, so reference
+// typos and missing credentials surface before a start.
+func (c *Client) ImagePullable(ctx context.Context, image string) error {
+	resp, err := c.do(ctx, http.MethodGet, "/distribution/"+url.PathEscape(image)+"/json", nil)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		return errorFromResponse(resp, "distribution inspect")
+	}
+	return nil
+}
+
 // EnsureImage pulls the image if it's not already present locally.
 func (c *Client) EnsureImage(ctx context.Context, image string) error {
 	// Check first via /images/:name/json — cheap.
@@ -392,20 +522,57 @@ func (c *Client) EnsureImage(ctx context.Context, image string) error {
 	if resp != nil {
 		resp.Body.Close()
 	}
-	// Pull.
+	return c.PullImage(ctx, image, nil)
+}
+
+// PullProgress is one message of an image pull: a layer's status
+// ("Downloading", "Pull complete") and, while it transfers, its bytes
+// so far out of total.
+type PullProgress struct {
+	Status  string `json:"status"`
+	ID      string `json:"id,omitempty"`
+	Current int64  `json:"current,omitempty"`
+	Total   int64  `json:"total,omitempty"`
+}
+
+// PullImage pulls image, passing each progress message the Engine
+// streams to progress when it's set. A pull that fails partway still
+// answers 200, with the error as the stream's last message, so the
+// stream is read to the end either way.
+func (c *Client) PullImage(ctx context.Context, image string, progress func(PullProgress)) error {
 	q := url.Values{}
 	q.Set("fromImage", image)
-	pull, err := c.do(ctx, http.MethodPost, "/images/create?"+q.Encode(), nil)
+	resp, err := c.do(ctx, http.MethodPost, "/images/create?"+q.Encode(), nil)
 	if err != nil {
 		return err
 	}
-	defer pull.Body.Close()
-	if pull.StatusCode/100 != 2 {
-		return errorFromResponse(pull, "pull")
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		return errorFromResponse(resp, "pull")
+	}
+	dec := json.NewDecoder(resp.Body)
+	for {
+		var msg struct {
+			Status         string `json:"status"`
+			ID             string `json:"id"`
+			Error          string `json:"error"`
+			ProgressDetail struct {
+				Current int64 `json:"current"`
+				Total   int64 `json:"total"`
+			} `json:"progressDetail"`
+		}
+		if err := dec.Decode(&msg); err == io.EOF {
+			return nil
+		} else if err != nil {
+			return err
+		}
+		if msg.Error != "" {
+			return fmt.Errorf("docker pull: %s", msg.Error)
+		}
+		if progress != nil {
+			progress(PullProgress{Status: msg.Status, ID: msg.ID, Current: msg.ProgressDetail.Current, Total: msg.ProgressDetail.Total})
+		}
 	}
-	// Drain the streamed pull progress so the docker daemon completes.
-	_, _ = io.Copy(io.Discard, pull.Body)
-	return nil
 }
 
 // AttachOptions configures an attach session.
@@ -438,11 +605,21 @@ func (c *Client) Attach(ctx context.Context, name string, opts AttachOptions) (n
 		q.Set("logs", "1")
 	}
 
+	return c.hijack(ctx, "/containers/"+name+"/attach?"+q.Encode(), nil, "attach")
+}
+
+// hijack POSTs to path over a raw socket connection and upgrades it to
+// a bidirectional stream (attach, exec start). body is optional JSON.
+func (c *Client) hijack(ctx context.Context, path string, body []byte, op string) (net.Conn, *bufio.Reader, error) {
 	conn, err := net.Dial("unix", c.socketPath)
 	if err != nil {
 		return nil, nil, fmt.Errorf("dial docker: %w", err)
 	}
-	req, err := http.NewRequestWithContext(ctx, http.MethodPost, "http://docker/"+apiVersion+"/containers/"+name+"/attach?"+q.Encode(), nil)
+	var reqBody io.Reader
+	if body != nil {
+		reqBody = bytes.NewReader(body)
+	}
+	req, err := http.NewRequestWithContext(ctx, http.MethodPost, "http://docker/"+apiVersion+path, reqBody)
 	if err != nil {
 		conn.Close()
 		return nil, nil, err
@@ -450,19 +627,22 @@ func (c *Client) Attach(ctx context.Context, name string, opts AttachOptions) (n
 	req.Host = "docker"
 	req.Header.Set("Connection", "Upgrade")
 	req.Header.Set("Upgrade", "tcp")
+	if body != nil {
+		req.Header.Set("Content-Type", "application/json")
+	}
 	if err := req.Write(conn); err != nil {
 		conn.Close()
-		return nil, nil, fmt.Errorf("attach request: %w", err)
+		return nil, nil, fmt.Errorf("%s request: %w", op, err)
 	}
 	reader := bufio.NewReader(conn)
 	resp, err := http.ReadResponse(reader, req)
 	if err != nil {
 		conn.Close()
-		return nil, nil, fmt.Errorf("attach response: %w", err)
+		return nil, nil, fmt.Errorf("%s response: %w", op, err)
 	}
 	if resp.StatusCode != http.StatusSwitchingProtocols && resp.StatusCode/100 != 2 {
 		conn.Close()
-		return nil, nil, fmt.Errorf("attach: %s", resp.Status)
+		return nil, nil, fmt.Errorf("%s: %s", op, resp.Status)
 	}
 	return conn, reader, nil
 }
@@ -623,7 +803,7 @@ func streamRawLines(ctx context.Context, r io.Reader, out chan<- LogLine) {
 				if len(preview) > 80 {
 					preview = preview[:80]
 				}
-				log.Printf("docker.streamRawLines: read=%d total=%d first=%q", n, totalBytes, preview)
+				logging.Debugf("docker", "docker.streamRawLines: read=%d total=%d first=%q", n, totalBytes, preview)
 			}
 			data := leftover + string(buf[:n])
 			lastNL := strings.LastIndex(data, "\n")
@@ -645,7 +825,7 @@ func streamRawLines(ctx context.Context, r io.Reader, out chan<- LogLine) {
 			}
 		}
 		if err != nil {
-			log.Printf("docker.streamRawLines: read err=%v total=%d", err, totalBytes)
+			logging.Debugf("docker", "docker.streamRawLines: read err=%v total=%d", err, totalBytes)
 			return
 		}
 	}
@@ -834,6 +1014,170 @@ func (c *Client) ListContainersFiltered(ctx context.Context, namePrefix string)
 	return out, nil
 }
 
+// Event is one entry from Docker's event stream.
+type Event struct {
+	Type   string
+	Action string
+	// Name is the actor's name attribute: the container name for
+	// container events, without the leading slash.
+	Name string
+}
+
+// Events follows Docker's event stream from now on. The channel closes
+// when ctx is cancelled or the stream breaks, which is how a Docker
+// daemon restart shows up.
+func (c *Client) Events(ctx context.Context) (<-chan Event, error) {
+	resp, err := c.do(ctx, http.MethodGet, "/events", nil)
+	if err != nil {
+		return nil, err
+	}
+	if resp.StatusCode/100 != 2 {
+		return nil, errorFromResponse(resp, "events")
+	}
+	out := make(chan Event, 16)
+	go func() {
+		defer close(out)
+		defer resp.Body.Close()
+		dec := json.NewDecoder(resp.Body)
+		for {
+			var raw struct {
+				Type   string
+				Action string
+				Actor  struct {
+					Attributes map[string]string
+				}
+			}
+			if err := dec.Decode(&raw); err != nil {
+				return
+			}
+			select {
+			case <-ctx.Done():
+				return
+			case out <- Event{Type: raw.Type, Action: raw.Action, Name: raw.Actor.Attributes["name"]}:
+			}
+		}
+	}()
+	return out, nil
+}
+
+// PublishedPort is one host port a running container publishes.
+type PublishedPort struct {
+	Container string
+	HostIP    string
+	HostPort  int
+	Proto     string
+}
+
+// PublishedPorts lists the host ports published by every running
+// container. Used to name the holder when a start would collide with
+// another container's binding.
+func (c *Client) PublishedPorts(ctx context.Context) ([]PublishedPort, error) {
+	resp, err := c.do(ctx, http.MethodGet, "/containers/json", nil)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		return nil, errorFromResponse(resp, "list")
+	}
+	var raw []struct {
+		Names []string
+		Ports []struct {
+			IP         string
+			PublicPort int
+			Type       string
+		}
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&raw); err != nil {
+		return nil, err
+	}
+	var out []PublishedPort
+	for _, r := range raw {
+		name := ""
+		if len(r.Names) > 0 {
+			name = strings.TrimPrefix(r.Names[0], "/")
+		}
+		for _, p := range r.Ports {
+			if p.PublicPort == 0 {
+				continue
+			}
+			out = append(out, PublishedPort{
+				Container: name,
+				HostIP:    p.IP,
+				HostPort:  p.PublicPort,
+				Proto:     p.Type,
+			})
+		}
+	}
+	return out, nil
+}
+
+// ContainerPorts lists the host ports name publishes; empty when it
+// isn't running.
+func (c *Client) ContainerPorts(ctx context.Context, name string) ([]PublishedPort, error) {
+	all, err := c.PublishedPorts(ctx)
+	if err != nil {
+		return nil, err
+	}
+	var out []PublishedPort
+	for _, p := range all {
+		if p.Container == name {
+			out = append(out, p)
+		}
+	}
+	return out, nil
+}
+
+// DiffPorts compares the host ports a container publishes with the ones
+// want would publish: added are wanted but not bound, removed bound but
+// no longer wanted. Wildcard bindings compare as 0.0.0.0 whichever
+// address family Docker reports them under. Both come back sorted.
+func DiffPorts(current []PublishedPort, want []PortMapping) (added, removed []PublishedPort) {
+	norm := func(ip string) string {
+		if ip == "" || ip == "::" {
+			return "0.0.0.0"
+		}
+		return ip
+	}
+	have := map[PublishedPort]bool{}
+	for _, p := range current {
+		have[PublishedPort{HostIP: norm(p.HostIP), HostPort: p.HostPort, Proto: p.Proto}] = true
+	}
+	wanted := map[PublishedPort]bool{}
+	for _, pm := range want {
+		for i := 0; i < pm.Span(); i++ {
+			for _, proto := range pm.Protocols() {
+				wanted[PublishedPort{HostIP: norm(pm.HostIP), HostPort: pm.HostPort + i, Proto: proto}] = true
+			}
+		}
+	}
+	for p := range wanted {
+		if !have[p] {
+			added = append(added, p)
+		}
+	}
+	for p := range have {
+		if !wanted[p] {
+			removed = append(removed, p)
+		}
+	}
+	sortPorts(added)
+	sortPorts(removed)
+	return added, removed
+}
+
+func sortPorts(ps []PublishedPort) {
+	sort.Slice(ps, func(i, j int) bool {
+		if ps[i].HostPort != ps[j].HostPort {
+			return ps[i].HostPort < ps[j].HostPort
+		}
+		if ps[i].Proto != ps[j].Proto {
+			return ps[i].Proto < ps[j].Proto
+		}
+		return ps[i].HostIP < ps[j].HostIP
+	})
+}
+
 // errPipeClosed is returned by AttachWriter when the underlying conn has
 // been closed. Used so callers can distinguish from normal write errors.
 var errPipeClosed = errors.New("attach pipe closed")
@@ -0,0 +1,91 @@
+package docker
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"net/http"
+)
+
+// ExecOptions configures a one-shot exec inside a running container.
+type ExecOptions struct {
+	Cmd        []string
+	User       string
+	WorkingDir string
+	Env        map[string]string
+}
+
+// Exec runs cmd inside the container and streams its output as lines
+// on the returned channel, which closes when the process exits or ctx
+// is cancelled. Call the returned wait func after draining the channel
+// to read the exit code. Output is always demultiplexed (no TTY) so
+// stdout and stderr stay distinguishable.
+func (c *Client) Exec(ctx context.Context, name string, opts ExecOptions) (<-chan LogLine, func() (int, error), error) {
+	env := make([]string, 0, len(opts.Env))
+	for k, v := range opts.Env {
+		env = append(env, k+"="+v)
+	}
+	resp, err := c.doJSON(ctx, http.MethodPost, "/containers/"+name+"/exec", map[string]any{
+		"Cmd":          opts.Cmd,
+		"User":         opts.User,
+		"WorkingDir":   opts.WorkingDir,
+		"Env":          env,
+		"AttachStdout": true,
+		"AttachStderr": true,
+		"Tty":          false,
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	if resp.StatusCode == http.StatusNotFound {
+		resp.Body.Close()
+		return nil, nil, &ContainerNotFoundError{Name: name}
+	}
+	if resp.StatusCode/100 != 2 {
+		return nil, nil, errorFromResponse(resp, "exec create")
+	}
+	var created struct{ Id string }
+	err = json.NewDecoder(resp.Body).Decode(&created)
+	resp.Body.Close()
+	if err != nil {
+		return nil, nil, fmt.Errorf("exec create: %w", err)
+	}
+
+	body, _ := json.Marshal(map[string]any{"Detach": false, "Tty": false})
+	conn, reader, err := c.hijack(ctx, "/exec/"+created.Id+"/start", body, "exec start")
+	if err != nil {
+		return nil, nil, err
+	}
+	out := make(chan LogLine, 32)
+	go func() {
+		defer close(out)
+		defer conn.Close()
+		streamMultiplexedLines(ctx, reader, out)
+	}()
+	wait := func() (int, error) {
+		return c.execExitCode(context.WithoutCancel(ctx), created.Id)
+	}
+	return out, wait, nil
+}
+
+func (c *Client) execExitCode(ctx context.Context, id string) (int, error) {
+	resp, err := c.do(ctx, http.MethodGet, "/exec/"+id+"/json", nil)
+	if err != nil {
+		return -1, err
+	}
+	if resp.StatusCode/100 != 2 {
+		return -1, errorFromResponse(resp, "exec inspect")
+	}
+	defer resp.Body.Close()
+	var raw struct {
+		Running  bool
+		ExitCode int
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&raw); err != nil {
+		return -1, err
+	}
+	if raw.Running {
+		return -1, fmt.Errorf("exec %s still running", id)
+	}
+	return raw.ExitCode, nil
+}
@@ -0,0 +1,104 @@
+package docker
+
+import (
+	"bufio"
+	"context"
+	"encoding/json"
+	"errors"
+	"net/http"
+	"os"
+	"regexp"
+)
+
+// CreateNetwork creates a user-defined bridge network. Idempotent: an
+// existing network with the same name is left as is.
+func (c *Client) CreateNetwork(ctx context.Context, name string) error {
+	resp, err := c.doJSON(ctx, http.MethodPost, "/networks/create", map[string]any{
+		"Name":           name,
+		"Driver":         "bridge",
+		"CheckDuplicate": true,
+	})
+	if err != nil {
+		return err
+	}
+	if resp.StatusCode == http.StatusConflict {
+		resp.Body.Close()
+		return nil
+	}
+	if resp.StatusCode/100 != 2 {
+		return errorFromResponse(resp, "network create")
+	}
+	resp.Body.Close()
+	return nil
+}
+
+// RemoveNetwork deletes a network. Missing networks are not an error.
+func (c *Client) RemoveNetwork(ctx context.Context, name string) error {
+	resp, err := c.do(ctx, http.MethodDelete, "/networks/"+name, nil)
+	if err != nil {
+		return err
+	}
+	if resp.StatusCode == http.StatusNotFound {
+		resp.Body.Close()
+		return nil
+	}
+	if resp.StatusCode/100 != 2 {
+		return errorFromResponse(resp, "network remove")
+	}
+	resp.Body.Close()
+	return nil
+}
+
+// selfContainerRe finds this container's id in the bind mounts Docker
+// gives every container (/etc/hostname, /etc/hosts, ...).
+var selfContainerRe = regexp.MustCompile(`/containers/([0-9a-f]{64})/`)
+
+// HostNetwork reports whether the daemon shares the host's network
+// namespace: it runs outside a container, or in one started with
+// network_mode: host. Binding or probing a host port from the daemon
+// only means anything then; in a bridge-networked container it reaches
+// the container's own namespace instead.
+func (c *Client) HostNetwork(ctx context.Context) (bool, error) {
+	if _, err := os.Stat("/.dockerenv"); os.IsNotExist(err) {
+		return true, nil
+	}
+	id, err := selfContainerID()
+	if err != nil {
+		return false, err
+	}
+	resp, err := c.do(ctx, http.MethodGet, "/containers/"+id+"/json", nil)
+	if err != nil {
+		return false, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		return false, errorFromResponse(resp, "inspect")
+	}
+	var raw struct {
+		HostConfig struct {
+			NetworkMode string
+		}
+	}
+	if err := json.NewDecoder(resp.Body).Decode(&raw); err != nil {
+		return false, err
+	}
+	return raw.HostConfig.NetworkMode == "host", nil
+}
+
+func selfContainerID() (string, error) {
+	f, err := os.Open("/proc/self/mountinfo")
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+	sc := bufio.NewScanner(f)
+	for sc.Scan() {
+		if m := selfContainerRe.FindStringSubmatch(sc.Text()); m != nil {
+			return m[1], nil
+		}
+	}
+	if err := sc.Err(); err != nil {
+		return "", err
+	}
+	return "", errors.New("own container id not found in /proc/self/mountinfo")
+}
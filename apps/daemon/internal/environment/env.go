@@ -6,6 +6,7 @@ package environment
 
 import (
 	"sync"
+	"time"
 
 	"github.com/stellarstack/daemon/internal/docker"
 )
@@ -25,9 +26,25 @@ const (
 // `Value\n` to the container stdin; type "signal" sends the named signal
 // via Docker's kill API; type "" (empty) lets Docker's stop API use the
 // container's configured StopSignal. Mirrors the upstream daemon's StopConfig union.
+//
+// Timeout is how long WaitForStop waits for the container to exit before
+// escalating to SIGKILL; zero means DefaultStopTimeout.
 type StopConfig struct {
-	Type  string // "" | "command" | "signal"
-	Value string // command text without trailing \n, or signal name like "SIGTERM"
+	Type    string        // "" | "command" | "signal"
+	Value   string        // command text without trailing \n, or signal name like "SIGTERM"
+	Timeout time.Duration // grace before SIGKILL
+}
+
+// DefaultStopTimeout is the stop grace period for blueprints that don't
+// declare one.
+const DefaultStopTimeout = 30 * time.Second
+
+// Grace returns the effective stop timeout.
+func (s StopConfig) Grace() time.Duration {
+	if s.Timeout <= 0 {
+		return DefaultStopTimeout
+	}
+	return s.Timeout
 }
 
 // StateListener is invoked on every transition. The Server type registers
@@ -53,9 +53,10 @@ func (e *Environment) IssueStop(ctx context.Context) error {
 		return e.docker.KillContainer(ctx, e.containerName, signal)
 	default:
 		// Empty / native — let Docker use the container's configured
-		// StopSignal. Use a 0-second grace so the signal goes immediately
-		// and we control the wait timeout ourselves.
-		err := e.docker.StopContainer(ctx, e.containerName, 0)
+		// StopSignal, with the blueprint's grace as the stop timeout:
+		// Docker kills the container itself once it runs out, so a zero
+		// here would SIGKILL straight after the signal.
+		err := e.docker.StopContainer(ctx, e.containerName, int(stop.Grace().Seconds()))
 		var nf *docker.ContainerNotFoundError
 		if errors.As(err, &nf) {
 			return nil
@@ -6,6 +6,13 @@
 // its own buffered channel and slow consumers are dropped (we close their
 // handle and let them reconnect) so a wedged browser can't backpressure
 // the daemon.
+//
+// Non-console events (status, job progress, daemon errors, …) can be
+// published sequenced: the bus stamps them with a monotonically
+// increasing seq and keeps the most recent ones in a replay ring, so a
+// client that reconnects with the last seq it saw can catch up on what
+// it missed. Console output and stats are not replayed — the console
+// history buffer and the next stats sample already cover them.
 package events
 
 import (
@@ -24,6 +31,9 @@ type Subscriber struct {
 	ch     chan Frame
 	bus    *Bus
 	closed bool
+
+	// dropped counts frames lost since the client was last told.
+	dropped uint64
 }
 
 func (s *Subscriber) Recv() <-chan Frame { return s.ch }
@@ -31,25 +41,96 @@ func (s *Subscriber) Close() {
 	s.bus.unsubscribe(s)
 }
 
+// replaySize is how many sequenced frames the bus retains per server.
+const replaySize = 128
+
+type sequenced struct {
+	seq   uint64
+	frame Frame
+}
+
+// DefaultBuffer is a subscriber's channel capacity unless SetBuffer
+// says otherwise.
+const DefaultBuffer = 64
+
 // Bus is the per-server fanout. Goroutine-safe.
 type Bus struct {
-	mu   sync.Mutex
-	subs map[*Subscriber]struct{}
+	mu     sync.Mutex
+	subs   map[*Subscriber]struct{}
+	seq    uint64
+	replay []sequenced
+	buffer int
+
+	// dropped is the running total of frames lost to slow subscribers;
+	// dropNotice builds the frame that tells a subscriber about its own.
+	dropped    uint64
+	dropNotice func(n uint64) Frame
 }
 
-func New() *Bus { return &Bus{subs: map[*Subscriber]struct{}{}} }
+func New() *Bus { return &Bus{subs: map[*Subscriber]struct{}{}, buffer: DefaultBuffer} }
+
+// SetDropNotice installs the frame sent to a subscriber that lost
+// frames, once it has room again, so the client can tell the user why
+// lines are missing. Without one drops go unannounced.
+func (b *Bus) SetDropNotice(build func(n uint64) Frame) {
+	b.mu.Lock()
+	b.dropNotice = build
+	b.mu.Unlock()
+}
+
+// Dropped is how many frames the bus has dropped for slow subscribers
+// since it was created.
+func (b *Bus) Dropped() uint64 {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	return b.dropped
+}
+
+// SetBuffer sets the channel capacity of subscribers registered from
+// now on; n <= 0 restores DefaultBuffer.
+func (b *Bus) SetBuffer(n int) {
+	if n <= 0 {
+		n = DefaultBuffer
+	}
+	b.mu.Lock()
+	b.buffer = n
+	b.mu.Unlock()
+}
 
 // Subscribe registers a new subscriber. The returned channel is buffered
-// at 64 frames; if the subscriber falls behind, further Publish calls
-// drop frames (subscriber gets stale state but never blocks the bus).
+// (64 frames unless SetBuffer changed it); if the subscriber falls
+// behind, further Publish calls drop frames (subscriber gets stale
+// state but never blocks the bus).
 func (b *Bus) Subscribe() *Subscriber {
 	b.mu.Lock()
 	defer b.mu.Unlock()
-	s := &Subscriber{ch: make(chan Frame, 64), bus: b}
+	s := &Subscriber{ch: make(chan Frame, b.buffer), bus: b}
 	b.subs[s] = struct{}{}
 	return s
 }
 
+// SubscribeSince registers a subscriber and atomically returns the
+// retained sequenced frames newer than seq, so nothing published in
+// between is either lost or delivered twice. A seq ahead of the bus
+// (the daemon restarted and numbering began again) replays everything
+// retained.
+func (b *Bus) SubscribeSince(seq uint64) (*Subscriber, []Frame) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	if seq > b.seq {
+		seq = 0
+	}
+	var missed []Frame
+	for _, r := range b.replay {
+		if r.seq > seq {
+			missed = append(missed, r.frame)
+		}
+	}
+	s := &Subscriber{ch: make(chan Frame, b.buffer), bus: b}
+	b.subs[s] = struct{}{}
+	return s, missed
+}
+
 func (b *Bus) unsubscribe(s *Subscriber) {
 	b.mu.Lock()
 	defer b.mu.Unlock()
@@ -68,10 +149,39 @@ func (b *Bus) Publish(f Frame) {
 	b.mu.Lock()
 	defer b.mu.Unlock()
 	for s := range b.subs {
-		select {
-		case s.ch <- f:
-		default:
-		}
+		b.deliver(s, f)
+	}
+}
+
+// deliver hands f to s or counts it dropped. A subscriber that dropped
+// frames gets the drop notice ahead of f as soon as both fit. Caller
+// holds b.mu.
+func (b *Bus) deliver(s *Subscriber, f Frame) {
+	if s.dropped > 0 && b.dropNotice != nil && cap(s.ch)-len(s.ch) >= 2 {
+		s.ch <- b.dropNotice(s.dropped)
+		s.dropped = 0
+	}
+	select {
+	case s.ch <- f:
+	default:
+		s.dropped++
+		b.dropped++
+	}
+}
+
+// PublishSequenced assigns the next seq, lets build marshal the frame
+// with it, retains the frame for replay and delivers it like Publish.
+func (b *Bus) PublishSequenced(build func(seq uint64) Frame) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+	b.seq++
+	f := build(b.seq)
+	b.replay = append(b.replay, sequenced{seq: b.seq, frame: f})
+	if len(b.replay) > replaySize {
+		b.replay = b.replay[len(b.replay)-replaySize:]
+	}
+	for s := range b.subs {
+		b.deliver(s, f)
 	}
 }
 
@@ -0,0 +1,53 @@
+package events
+
+import "encoding/json"
+
+// SchemaVersion is the version of the `{v, event, args}` frame schema
+// the daemon emits. Bump it whenever an existing event's args change
+// shape in a way an older consumer would misread; adding a new event
+// or an optional field does not need a bump.
+const SchemaVersion = 1
+
+// SupportedVersions lists every frame schema version this daemon can
+// still speak. A client pinning `?v=` to a version outside this list is
+// refused at the handshake instead of silently getting frames it can't
+// parse. Advertised via /api/system capabilities.
+var SupportedVersions = []int{1}
+
+// Supports reports whether v is one of SupportedVersions.
+func Supports(v int) bool {
+	for _, s := range SupportedVersions {
+		if s == v {
+			return true
+		}
+	}
+	return false
+}
+
+// Envelope is the wire shape of every daemon-originated frame. Seq is
+// only set on frames that went through the replay ring.
+type Envelope struct {
+	V     int    `json:"v"`
+	Event string `json:"event"`
+	Args  []any  `json:"args"`
+	Seq   uint64 `json:"seq,omitempty"`
+}
+
+// Encode marshals `{v, event, args}` at the current SchemaVersion.
+func Encode(event string, args []any) Frame {
+	return encode(Envelope{Event: event, Args: args})
+}
+
+// EncodeSequenced is Encode plus the replay seq.
+func EncodeSequenced(event string, args []any, seq uint64) Frame {
+	return encode(Envelope{Event: event, Args: args, Seq: seq})
+}
+
+func encode(e Envelope) Frame {
+	e.V = SchemaVersion
+	if e.Args == nil {
+		e.Args = []any{}
+	}
+	frame, _ := json.Marshal(e)
+	return frame
+}
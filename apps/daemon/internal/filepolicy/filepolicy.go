@@ -0,0 +1,128 @@
+// Package filepolicy is the node's own blacklist of files no server may
+// be given, whatever its blueprint allows: extensions (".sh", ".so",
+// disk images for nested containers), content types sniffed from the
+// first bytes (ELF executables, shell scripts) and setuid/setgid modes.
+// Blocked extensions and types may still be allowed under chosen
+// directories, e.g. a server's own /scripts. Every way files reach a
+// server checks it: HTTP writes and uploads, pulls, archive extraction,
+// SFTP and scp.
+//
+// A nil *Policy allows everything.
+package filepolicy
+
+import (
+	"bytes"
+	"errors"
+	"fmt"
+	"io/fs"
+	"net/http"
+	"path"
+	"strings"
+)
+
+// ErrBlocked: the node's policy refuses the file. Errors wrapping it
+// say why.
+var ErrBlocked = errors.New("blocked by the node's file policy")
+
+// SniffBytes is how much of a file CheckContent looks at.
+const SniffBytes = 512
+
+// Policy is a compiled blacklist.
+type Policy struct {
+	extensions  []string
+	types       []string
+	allowDirs   []string
+	blockSetuid bool
+}
+
+// New compiles the policy, or returns nil when it blocks nothing.
+// Extensions match the end of the file name, case-insensitively, with
+// or without the leading dot (".tar.gz" works). Types are MIME types as
+// Sniff reports them. allowDirs are server-relative directories where
+// extensions and types aren't checked.
+func New(extensions, types, allowDirs []string, blockSetuid bool) *Policy {
+	p := &Policy{blockSetuid: blockSetuid}
+	for _, e := range extensions {
+		if e = strings.ToLower(strings.TrimSpace(e)); e != "" {
+			p.extensions = append(p.extensions, "."+strings.TrimPrefix(e, "."))
+		}
+	}
+	for _, t := range types {
+		if t = strings.ToLower(strings.TrimSpace(t)); t != "" {
+			p.types = append(p.types, t)
+		}
+	}
+	for _, d := range allowDirs {
+		if d = path.Clean("/" + d); d != "/" {
+			p.allowDirs = append(p.allowDirs, d)
+		}
+	}
+	if len(p.extensions) == 0 && len(p.types) == 0 && !blockSetuid {
+		return nil
+	}
+	return p
+}
+
+// allowed reports whether rel is under one of the allowed directories.
+func (p *Policy) allowed(rel string) bool {
+	rel = path.Clean("/" + rel)
+	for _, d := range p.allowDirs {
+		if strings.HasPrefix(rel, d+"/") {
+			return true
+		}
+	}
+	return false
+}
+
+// CheckName refuses a server-relative path by its extension.
+func (p *Policy) CheckName(rel string) error {
+	if p == nil || len(p.extensions) == 0 || p.allowed(rel) {
+		return nil
+	}
+	name := strings.ToLower(path.Base(rel))
+	for _, e := range p.extensions {
+		if strings.HasSuffix(name, e) {
+			return fmt.Errorf("%w: %s files are not allowed", ErrBlocked, e)
+		}
+	}
+	return nil
+}
+
+// CheckContent refuses rel by the type sniffed from head, its first
+// SniffBytes bytes (or fewer for a short file).
+func (p *Policy) CheckContent(rel string, head []byte) error {
+	if p == nil || len(p.types) == 0 || p.allowed(rel) {
+		return nil
+	}
+	t := Sniff(head)
+	for _, blocked := range p.types {
+		if t == blocked {
+			return fmt.Errorf("%w: %s files are not allowed", ErrBlocked, t)
+		}
+	}
+	return nil
+}
+
+// CheckMode refuses setuid and setgid files when the policy says so.
+func (p *Policy) CheckMode(rel string, mode fs.FileMode) error {
+	if p == nil || !p.blockSetuid || mode&(fs.ModeSetuid|fs.ModeSetgid) == 0 {
+		return nil
+	}
+	return fmt.Errorf("%w: setuid and setgid files are not allowed", ErrBlocked)
+}
+
+// Sniff names the content type of a file from its first bytes. On top
+// of what net/http recognises it knows executables and scripts, which
+// are what a host usually wants to keep out.
+func Sniff(head []byte) string {
+	switch {
+	case bytes.HasPrefix(head, []byte("\x7fELF")):
+		return "application/x-executable"
+	case bytes.HasPrefix(head, []byte("MZ")):
+		return "application/x-msdownload"
+	case bytes.HasPrefix(head, []byte("#!")):
+		return "text/x-shellscript"
+	}
+	t, _, _ := strings.Cut(http.DetectContentType(head), ";")
+	return t
+}
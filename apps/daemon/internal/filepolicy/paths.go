@@ -0,0 +1,65 @@
+package filepolicy
+
+import (
+	"fmt"
+	"path"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/ignore"
+)
+
+// PathRules are one server's denylist and hidden list: the node's
+// defaults merged with what the blueprint and the server itself add.
+// Patterns use package ignore's syntax (".git/", "*.db", "/secrets").
+// A denied path, or anything under a denied directory, is refused by
+// the file manager and SFTP whatever the operation. A hidden one is
+// left out of listings but stays reachable by path, so it only keeps
+// clutter out of view.
+//
+// A nil *PathRules allows and shows everything.
+type PathRules struct {
+	deny   []string
+	hidden []string
+}
+
+// NewPathRules compiles the lists, or returns nil when both are empty.
+func NewPathRules(deny, hidden []string) *PathRules {
+	r := &PathRules{deny: compact(deny), hidden: compact(hidden)}
+	if len(r.deny) == 0 && len(r.hidden) == 0 {
+		return nil
+	}
+	return r
+}
+
+func compact(patterns []string) []string {
+	var out []string
+	for _, p := range patterns {
+		if p = strings.TrimSpace(p); p != "" {
+			out = append(out, p)
+		}
+	}
+	return out
+}
+
+// CheckPath refuses a server-relative path on the denylist, itself or
+// through one of its parent directories.
+func (r *PathRules) CheckPath(rel string, isDir bool) error {
+	if r == nil || len(r.deny) == 0 {
+		return nil
+	}
+	rel = strings.TrimPrefix(path.Clean("/"+rel), "/")
+	for p, dir := rel, isDir; p != "" && p != "."; p, dir = path.Dir(p), true {
+		if ignore.Match(r.deny, p, dir) {
+			return fmt.Errorf("%w: /%s is on the server's denylist", ErrBlocked, rel)
+		}
+	}
+	return nil
+}
+
+// Hidden reports whether a listing should leave rel out.
+func (r *PathRules) Hidden(rel string, isDir bool) bool {
+	if r == nil || len(r.hidden) == 0 {
+		return false
+	}
+	return ignore.Match(r.hidden, strings.TrimPrefix(path.Clean("/"+rel), "/"), isDir)
+}
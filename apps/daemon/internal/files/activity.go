@@ -0,0 +1,201 @@
+package files
+
+import (
+	"sync"
+	"time"
+)
+
+// File activity. Every operation through the Manager is tallied per
+// server in one-minute buckets, so the panel can see what each server's
+// file manager has been doing over the last window and the node can
+// flag a server whose activity looks like abuse or a runaway plugin
+// (mass deletes, gigabytes downloaded, archive after archive). SFTP
+// sessions and the server's own process aren't counted.
+
+// Op is a kind of file operation.
+type Op string
+
+const (
+	OpRead       Op = "read"
+	OpWrite      Op = "write"
+	OpDelete     Op = "delete"
+	OpCompress   Op = "compress"
+	OpDecompress Op = "decompress"
+)
+
+// OpTotals is what one kind of operation amounted to. For deletes
+// Count is entries removed; for the others it is operations, and Bytes
+// is file size for reads and writes, source bytes for compress and
+// archive size for decompress.
+type OpTotals struct {
+	Count int64 `json:"count"`
+	Bytes int64 `json:"bytes"`
+}
+
+// ActivityThresholds flag a server whose activity within Window reaches
+// any of them. Zero disables a threshold; Window defaults to ten
+// minutes.
+type ActivityThresholds struct {
+	Window     time.Duration
+	ReadBytes  int64
+	WriteBytes int64
+	Deletes    int64
+	ArchiveOps int64
+}
+
+// ActivityWarning reports one threshold reached. Metric is "read_bytes",
+// "write_bytes", "deletes" or "archive_ops".
+type ActivityWarning struct {
+	Metric    string
+	Value     int64
+	Threshold int64
+	Window    time.Duration
+}
+
+// ActivityReport is a server's totals over the window.
+type ActivityReport struct {
+	WindowSeconds int64           `json:"windowSeconds"`
+	Ops           map[Op]OpTotals `json:"ops"`
+}
+
+const defaultActivityWindow = 10 * time.Minute
+
+type activity struct {
+	mu         sync.Mutex
+	thresholds ActivityThresholds
+	onWarning  func(serverID string, w ActivityWarning)
+	servers    map[string]*serverActivity
+}
+
+type serverActivity struct {
+	buckets []activityBucket
+	// warned is when each metric last warned; it warns again only once
+	// a whole window has passed.
+	warned map[string]time.Time
+}
+
+type activityBucket struct {
+	minute int64
+	ops    map[Op]OpTotals
+}
+
+// SetActivityThresholds sets the thresholds and the hook called, on its
+// own goroutine, when a server reaches one. A server warns about each
+// metric at most once per window.
+func (m *Manager) SetActivityThresholds(t ActivityThresholds, onWarning func(serverID string, w ActivityWarning)) {
+	if t.Window <= 0 {
+		t.Window = defaultActivityWindow
+	}
+	m.activity.mu.Lock()
+	defer m.activity.mu.Unlock()
+	m.activity.thresholds = t
+	m.activity.onWarning = onWarning
+}
+
+// Activity reports every server with file activity in the window.
+func (m *Manager) Activity() map[string]ActivityReport {
+	a := &m.activity
+	a.mu.Lock()
+	defer a.mu.Unlock()
+	now := time.Now()
+	out := make(map[string]ActivityReport, len(a.servers))
+	for id, s := range a.servers {
+		ops := a.totalsLocked(s, now)
+		if len(ops) == 0 {
+			delete(a.servers, id)
+			continue
+		}
+		out[id] = ActivityReport{WindowSeconds: int64(a.window().Seconds()), Ops: ops}
+	}
+	return out
+}
+
+// record tallies one operation.
+func (m *Manager) record(serverID string, op Op, count, bytes int64) {
+	a := &m.activity
+	now := time.Now()
+	a.mu.Lock()
+	if a.servers == nil {
+		a.servers = map[string]*serverActivity{}
+	}
+	s := a.servers[serverID]
+	if s == nil {
+		s = &serverActivity{warned: map[string]time.Time{}}
+		a.servers[serverID] = s
+	}
+	minute := now.Unix() / 60
+	if n := len(s.buckets); n == 0 || s.buckets[n-1].minute != minute {
+		s.buckets = append(s.buckets, activityBucket{minute: minute, ops: map[Op]OpTotals{}})
+	}
+	b := &s.buckets[len(s.buckets)-1]
+	t := b.ops[op]
+	t.Count += count
+	t.Bytes += bytes
+	b.ops[op] = t
+	warnings := a.checkLocked(s, now)
+	hook := a.onWarning
+	a.mu.Unlock()
+	if hook == nil {
+		return
+	}
+	for _, w := range warnings {
+		go hook(serverID, w)
+	}
+}
+
+func (a *activity) window() time.Duration {
+	if a.thresholds.Window <= 0 {
+		return defaultActivityWindow
+	}
+	return a.thresholds.Window
+}
+
+// totalsLocked drops buckets older than the window and sums the rest.
+func (a *activity) totalsLocked(s *serverActivity, now time.Time) map[Op]OpTotals {
+	oldest := now.Add(-a.window()).Unix() / 60
+	keep := 0
+	for keep < len(s.buckets) && s.buckets[keep].minute < oldest {
+		keep++
+	}
+	s.buckets = s.buckets[keep:]
+	out := map[Op]OpTotals{}
+	for _, b := range s.buckets {
+		for op, t := range b.ops {
+			sum := out[op]
+			sum.Count += t.Count
+			sum.Bytes += t.Bytes
+			out[op] = sum
+		}
+	}
+	return out
+}
+
+// checkLocked returns the thresholds s has newly reached.
+func (a *activity) checkLocked(s *serverActivity, now time.Time) []ActivityWarning {
+	t := a.thresholds
+	if t.ReadBytes == 0 && t.WriteBytes == 0 && t.Deletes == 0 && t.ArchiveOps == 0 {
+		return nil
+	}
+	ops := a.totalsLocked(s, now)
+	metrics := []struct {
+		name             string
+		value, threshold int64
+	}{
+		{"read_bytes", ops[OpRead].Bytes, t.ReadBytes},
+		{"write_bytes", ops[OpWrite].Bytes, t.WriteBytes},
+		{"deletes", ops[OpDelete].Count, t.Deletes},
+		{"archive_ops", ops[OpCompress].Count + ops[OpDecompress].Count, t.ArchiveOps},
+	}
+	var out []ActivityWarning
+	for _, mt := range metrics {
+		if mt.threshold <= 0 || mt.value < mt.threshold {
+			continue
+		}
+		if last, ok := s.warned[mt.name]; ok && now.Sub(last) < a.window() {
+			continue
+		}
+		s.warned[mt.name] = now
+		out = append(out, ActivityWarning{Metric: mt.name, Value: mt.value, Threshold: mt.threshold, Window: a.window()})
+	}
+	return out
+}
@@ -0,0 +1,341 @@
+package files
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"io/fs"
+	"os"
+	"path/filepath"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/fsio"
+)
+
+// ConflictSkip leaves an existing file alone and carries on; only Copy
+// accepts it.
+const ConflictSkip OnConflict = "skip"
+
+// CopyOptions tune Copy. Room is how many more bytes the server may
+// use; negative means no limit. Moves don't grow the server and ignore
+// it.
+type CopyOptions struct {
+	Move       bool
+	OnConflict OnConflict
+	Room       int64
+}
+
+// CopyResult is what a copy did. Paths lists, per source, where it
+// landed (server-relative); it differs from destination/<name> when the
+// source was renamed.
+type CopyResult struct {
+	Paths   []string `json:"paths"`
+	Files   int64    `json:"files"`
+	Skipped int64    `json:"skipped"`
+	Bytes   int64    `json:"bytes"`
+}
+
+// Copy copies each of paths, files or whole directory trees, into
+// destDir under its own name; with opts.Move the sources go away once
+// copied. When the name is taken:
+//
+//   - ConflictRename puts the source under a free "name (n)" instead;
+//   - ConflictOverwrite merges a directory into the existing one and
+//     replaces files (and anything of the other kind) in the way;
+//   - ConflictSkip merges too, but keeps every existing file;
+//   - ConflictFail fails with ErrExists before copying anything.
+//
+// A move with nothing in the way is a rename. Progress counts bytes out
+// of the sources' total. Symlinks and denylisted entries are skipped,
+// as they are by extraction, compression and delete; under a move the
+// skipped files' sources stay put. Cancelling ctx stops between files.
+func (m *Manager) Copy(ctx context.Context, serverID string, paths []string, destDir string, opts CopyOptions, progress ProgressFunc) (CopyResult, error) {
+	res := CopyResult{Paths: []string{}}
+	dst, err := m.resolve(serverID, destDir)
+	if err != nil {
+		return res, err
+	}
+	if err := os.MkdirAll(dst, 0o755); err != nil {
+		return res, err
+	}
+	if st, err := os.Stat(dst); err != nil {
+		return res, err
+	} else if !st.IsDir() {
+		return res, errors.New("destination is not a directory")
+	}
+	root, _ := m.resolve(serverID, "")
+	c := &copier{m: m, ctx: ctx, serverID: serverID, root: root, opts: opts, progress: progress, room: opts.Room, res: res}
+	if opts.Move {
+		c.room = -1
+	}
+	var srcs []string
+	targets := map[string]bool{}
+	for _, p := range paths {
+		src, err := m.resolve(serverID, p)
+		if err != nil {
+			return res, err
+		}
+		if src == root {
+			return res, errors.New("refusing to copy the server root")
+		}
+		if dst == src || strings.HasPrefix(dst, src+string(os.PathSeparator)) {
+			return res, fmt.Errorf("can't copy %s into itself", p)
+		}
+		// "Before copying anything": a taken name further down the
+		// list mustn't leave the sources before it already copied.
+		if target := filepath.Join(dst, filepath.Base(src)); opts.OnConflict == ConflictFail && target != src {
+			if _, err := os.Lstat(target); err == nil || targets[target] {
+				return res, ErrExists
+			}
+			targets[target] = true
+		}
+		srcs = append(srcs, src)
+		c.total += treeBytes(src)
+	}
+	defer func() { m.record(serverID, OpWrite, c.res.Files, c.res.Bytes) }()
+	for _, src := range srcs {
+		target := filepath.Join(dst, filepath.Base(src))
+		if target == src {
+			if opts.Move {
+				c.res.Paths = append(c.res.Paths, c.rel(src))
+				continue
+			}
+			// A copy onto itself can only mean a duplicate.
+			if target, err = freeName(target); err != nil {
+				return c.res, err
+			}
+		} else if _, err := os.Lstat(target); err == nil {
+			switch opts.OnConflict {
+			case ConflictFail:
+				return c.res, ErrExists
+			case ConflictRename:
+				if target, err = freeName(target); err != nil {
+					return c.res, err
+				}
+			}
+		}
+		if err := c.entry(src, target); err != nil {
+			return c.res, err
+		}
+		c.res.Paths = append(c.res.Paths, c.rel(target))
+	}
+	progress.report(c.total, c.total)
+	return c.res, nil
+}
+
+// copier carries one Copy's state across its sources.
+type copier struct {
+	m        *Manager
+	ctx      context.Context
+	serverID string
+	root     string
+	opts     CopyOptions
+	progress ProgressFunc
+	room     int64
+	total    int64
+	done     int64
+	res      CopyResult
+}
+
+func (c *copier) rel(abs string) string {
+	rel, _ := filepath.Rel(c.root, abs)
+	return "/" + filepath.ToSlash(rel)
+}
+
+// entry copies (or moves) the tree at src to target.
+func (c *copier) entry(src, target string) error {
+	if c.opts.Move {
+		if _, err := os.Lstat(target); errors.Is(err, fs.ErrNotExist) {
+			n := treeBytes(src)
+			if err := os.Rename(src, target); err != nil {
+				return err
+			}
+			c.done += n
+			c.res.Bytes += n
+			c.progress.report(c.done, c.total)
+			return nil
+		}
+	}
+	var moved []string
+	err := filepath.WalkDir(src, func(p string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return err
+		}
+		if err := c.ctx.Err(); err != nil {
+			return err
+		}
+		rel, err := filepath.Rel(src, p)
+		if err != nil {
+			return err
+		}
+		to := filepath.Join(target, rel)
+		if !c.allowed(p, d.IsDir()) || !c.allowed(to, d.IsDir()) {
+			c.res.Skipped++
+			if d.IsDir() {
+				return filepath.SkipDir
+			}
+			return nil
+		}
+		switch {
+		case d.IsDir():
+			return c.mkdir(p, to)
+		case d.Type().IsRegular():
+			copied, err := c.file(p, to)
+			if copied {
+				moved = append(moved, p)
+			}
+			return err
+		default:
+			c.res.Skipped++
+			return nil
+		}
+	})
+	if err != nil || !c.opts.Move {
+		return err
+	}
+	for _, p := range moved {
+		if err := os.Remove(p); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+	}
+	removeEmptyDirs(src)
+	return nil
+}
+
+// allowed reports whether abs clears the server's denylist.
+func (c *copier) allowed(abs string, isDir bool) bool {
+	rules := c.m.pathRules(c.serverID)
+	return rules == nil || rules.CheckPath(c.rel(abs), isDir) == nil
+}
+
+// mkdir makes the directory to mirror from, clearing a file in the way
+// under ConflictOverwrite.
+func (c *copier) mkdir(from, to string) error {
+	st, err := os.Lstat(to)
+	if err == nil && !st.IsDir() {
+		if c.opts.OnConflict != ConflictOverwrite {
+			c.res.Skipped++
+			return filepath.SkipDir
+		}
+		if err := os.Remove(to); err != nil {
+			return err
+		}
+	}
+	info, err := os.Stat(from)
+	if err != nil {
+		return err
+	}
+	return os.MkdirAll(to, info.Mode().Perm()|0o700)
+}
+
+// file copies one regular file, reporting whether it did.
+func (c *copier) file(from, to string) (bool, error) {
+	info, err := os.Stat(from)
+	if err != nil {
+		return false, err
+	}
+	room := c.room
+	if st, err := os.Lstat(to); err == nil {
+		if c.opts.OnConflict == ConflictSkip || c.opts.OnConflict == ConflictFail {
+			c.res.Skipped++
+			c.done += info.Size()
+			c.progress.report(c.done, c.total)
+			return false, nil
+		}
+		if st.IsDir() {
+			if err := os.RemoveAll(to); err != nil {
+				return false, err
+			}
+		} else if room >= 0 && st.Mode().IsRegular() {
+			room += st.Size()
+		}
+	}
+	if room >= 0 && info.Size() > room {
+		return false, ErrQuotaExceeded
+	}
+	if err := c.m.policy.CheckName(c.rel(to)); err != nil {
+		return false, c.m.refuse(c.serverID, c.rel(to), "copy", err)
+	}
+	in, err := os.Open(from)
+	if err != nil {
+		return false, err
+	}
+	defer in.Close()
+	tmp, err := os.CreateTemp(filepath.Dir(to), ".copy-*")
+	if err != nil {
+		return false, err
+	}
+	defer os.Remove(tmp.Name())
+	n, err := fsio.Copy(diskio.Writer(c.ctx, diskio.Background, tmp), &countingReader{
+		r:        diskio.Reader(c.ctx, diskio.Background, in),
+		n:        c.done,
+		total:    c.total,
+		progress: c.progress,
+	})
+	if cerr := tmp.Close(); err == nil {
+		err = cerr
+	}
+	if err != nil {
+		return false, err
+	}
+	if err := os.Chmod(tmp.Name(), info.Mode().Perm()); err != nil {
+		return false, err
+	}
+	if err := os.Rename(tmp.Name(), to); err != nil {
+		return false, err
+	}
+	_ = os.Chtimes(to, info.ModTime(), info.ModTime())
+	c.done += n
+	if room >= 0 {
+		c.room = room - n
+	}
+	c.res.Files++
+	c.res.Bytes += n
+	return true, nil
+}
+
+// freeName returns abs, or the first free "name (n).ext" beside it.
+func freeName(abs string) (string, error) {
+	ext := filepath.Ext(abs)
+	stem := strings.TrimSuffix(abs, ext)
+	for i := 1; i <= maxRenameSuffix; i++ {
+		dst := fmt.Sprintf("%s (%d)%s", stem, i, ext)
+		if _, err := os.Lstat(dst); errors.Is(err, fs.ErrNotExist) {
+			return dst, nil
+		}
+	}
+	return "", ErrExists
+}
+
+// treeBytes is the size of the regular files at or under abs.
+func treeBytes(abs string) int64 {
+	var n int64
+	_ = filepath.WalkDir(abs, func(_ string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return nil
+		}
+		if d.Type().IsRegular() {
+			if info, err := d.Info(); err == nil {
+				n += info.Size()
+			}
+		}
+		return nil
+	})
+	return n
+}
+
+// removeEmptyDirs deletes the directories under (and including) abs
+// that a move left empty, deepest first.
+func removeEmptyDirs(abs string) {
+	var dirs []string
+	_ = filepath.WalkDir(abs, func(p string, d fs.DirEntry, err error) error {
+		if err == nil && d.IsDir() {
+			dirs = append(dirs, p)
+		}
+		return nil
+	})
+	for i := len(dirs) - 1; i >= 0; i-- {
+		_ = os.Remove(dirs[i])
+	}
+}
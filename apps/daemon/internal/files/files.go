@@ -8,20 +8,41 @@ import (
 	"archive/tar"
 	"archive/zip"
 	"compress/gzip"
+	"context"
+	"crypto/sha1"
+	"crypto/sha256"
+	"encoding/hex"
 	"errors"
 	"fmt"
+	"hash"
 	"io"
+	"io/fs"
+	"net/http"
 	"os"
+	"path"
 	"path/filepath"
+	"sort"
 	"strings"
 	"time"
+
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/filepolicy"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/pgzip"
+	"github.com/stellarstack/daemon/internal/scan"
 )
 
 // Manager is the per-daemon entry point. The data dir is shared with
 // the docker bind mount; each server's tree lives at
 // `<dataDir>/servers/<uuid>`.
 type Manager struct {
-	dataDir string
+	dataDir   string
+	activity  activity
+	scanner   *scan.Scanner
+	scanOpts  ScanOptions
+	policy    *filepolicy.Policy
+	onBlocked func(serverID string, b Blocked)
+	rules     func(serverID string) *filepolicy.PathRules
 }
 
 func New(dataDir string) *Manager { return &Manager{dataDir: dataDir} }
@@ -37,7 +58,8 @@ type Entry struct {
 }
 
 // resolve returns the absolute filesystem path for a server-relative
-// path, refusing any input that escapes the server root.
+// path, refusing any input that escapes the server root or is on the
+// server's denylist.
 func (m *Manager) resolve(serverID, rel string) (string, error) {
 	if serverID == "" {
 		return "", errors.New("server id required")
@@ -48,38 +70,83 @@ func (m *Manager) resolve(serverID, rel string) (string, error) {
 	if !strings.HasPrefix(abs, root) {
 		return "", errors.New("path escapes server root")
 	}
+	if rules := m.pathRules(serverID); rules != nil && clean != "/" {
+		st, statErr := os.Lstat(abs)
+		if err := rules.CheckPath(filepath.ToSlash(clean), statErr == nil && st.IsDir()); err != nil {
+			return "", err
+		}
+	}
 	return abs, nil
 }
 
 // List returns the entries directly under `path` within the server tree.
-func (m *Manager) List(serverID, path string) ([]Entry, error) {
-	abs, err := m.resolve(serverID, path)
+func (m *Manager) List(ctx context.Context, serverID, path string) ([]Entry, error) {
+	out := []Entry{}
+	err := m.ListEach(ctx, serverID, path, func(e Entry) error {
+		out = append(out, e)
+		return nil
+	})
 	if err != nil {
 		return nil, err
 	}
-	infos, err := os.ReadDir(abs)
+	sort.Slice(out, func(i, j int) bool { return out[i].Name < out[j].Name })
+	return out, nil
+}
+
+// listBatch is how many directory entries ListEach reads per syscall
+// batch; small enough that the first entries reach a streaming client
+// quickly on huge directories.
+const listBatch = 256
+
+// ListEach calls fn for every entry directly under `path` in directory
+// order, as they're read — the whole listing is never held in memory.
+// Entries on the server's hidden list are skipped. A missing directory
+// is empty. fn returning an error, or ctx ending, stops the walk.
+func (m *Manager) ListEach(ctx context.Context, serverID, path string, fn func(Entry) error) error {
+	abs, err := m.resolve(serverID, path)
+	if err != nil {
+		return err
+	}
+	dir, err := os.Open(abs)
 	if err != nil {
 		if os.IsNotExist(err) {
-			return []Entry{}, nil
+			return nil
 		}
-		return nil, err
+		return err
 	}
-	out := make([]Entry, 0, len(infos))
-	for _, info := range infos {
-		fi, err := info.Info()
+	defer dir.Close()
+	rules := m.pathRules(serverID)
+	for {
+		if err := ctx.Err(); err != nil {
+			return err
+		}
+		infos, err := dir.ReadDir(listBatch)
+		for _, info := range infos {
+			if rules.Hidden(filepath.ToSlash(filepath.Join("/", path, info.Name())), info.IsDir()) {
+				continue
+			}
+			fi, err := info.Info()
+			if err != nil {
+				continue
+			}
+			if err := fn(Entry{
+				Name:    info.Name(),
+				Path:    filepath.Join(path, info.Name()),
+				IsDir:   info.IsDir(),
+				Size:    fi.Size(),
+				ModTime: fi.ModTime().UTC().Format(time.RFC3339),
+				Mode:    fi.Mode().String(),
+			}); err != nil {
+				return err
+			}
+		}
+		if errors.Is(err, io.EOF) {
+			return nil
+		}
 		if err != nil {
-			continue
+			return err
 		}
-		out = append(out, Entry{
-			Name:    info.Name(),
-			Path:    filepath.Join(path, info.Name()),
-			IsDir:   info.IsDir(),
-			Size:    fi.Size(),
-			ModTime: fi.ModTime().UTC().Format(time.RFC3339),
-			Mode:    fi.Mode().String(),
-		})
 	}
-	return out, nil
 }
 
 // Read streams a file's contents. Returns ErrTooLarge if the file is
@@ -107,15 +174,44 @@ func (m *Manager) Read(serverID, path string) (io.ReadCloser, int64, error) {
 	if err != nil {
 		return nil, 0, err
 	}
+	m.record(serverID, OpRead, 1, st.Size())
 	return f, st.Size(), nil
 }
 
+// Open returns the file and its metadata with no size cap, for ranged
+// reads and downloads that stream straight to the client.
+func (m *Manager) Open(serverID, path string) (*os.File, os.FileInfo, error) {
+	abs, err := m.resolve(serverID, path)
+	if err != nil {
+		return nil, nil, err
+	}
+	f, err := os.Open(abs)
+	if err != nil {
+		return nil, nil, err
+	}
+	st, err := f.Stat()
+	if err != nil {
+		f.Close()
+		return nil, nil, err
+	}
+	if st.IsDir() {
+		f.Close()
+		return nil, nil, errors.New("is a directory")
+	}
+	m.record(serverID, OpRead, 1, st.Size())
+	return f, st, nil
+}
+
 // Write replaces the file contents.
 func (m *Manager) Write(serverID, path string, body io.Reader) error {
 	abs, err := m.resolve(serverID, path)
 	if err != nil {
 		return err
 	}
+	body, err = m.checkIncoming(serverID, path, "write", body)
+	if err != nil {
+		return err
+	}
 	if err := os.MkdirAll(filepath.Dir(abs), 0o755); err != nil {
 		return err
 	}
@@ -124,10 +220,189 @@ func (m *Manager) Write(serverID, path string, body io.Reader) error {
 		return err
 	}
 	defer f.Close()
-	if _, err := io.Copy(f, body); err != nil {
-		return err
+	n, err := io.Copy(diskio.Writer(context.Background(), diskio.Live, f), body)
+	m.record(serverID, OpWrite, 1, n)
+	return err
+}
+
+// ErrQuotaExceeded: an upload would take the server past its disk
+// limit.
+var ErrQuotaExceeded = errors.New("disk quota exceeded")
+
+// Usage is the size of the server's regular files.
+func (m *Manager) Usage(serverID string) (int64, error) {
+	root, err := m.resolve(serverID, "")
+	if err != nil {
+		return 0, err
 	}
-	return nil
+	var total int64
+	err = filepath.WalkDir(root, func(_ string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return nil
+		}
+		_ = diskio.Wait(context.Background(), diskio.Maintenance, diskio.EntryCost)
+		if d.IsDir() {
+			return nil
+		}
+		if info, err := d.Info(); err == nil && info.Mode().IsRegular() {
+			total += info.Size()
+		}
+		return nil
+	})
+	return total, err
+}
+
+// ErrExists: an upload with OnConflict "fail" found its target taken.
+var ErrExists = errors.New("file exists")
+
+// OnConflict is what an upload does when its target already exists.
+type OnConflict string
+
+const (
+	// ConflictOverwrite replaces the existing file.
+	ConflictOverwrite OnConflict = "overwrite"
+	// ConflictFail leaves the existing file and fails with ErrExists.
+	ConflictFail OnConflict = "fail"
+	// ConflictRename stores the upload as "name (1).ext", "name
+	// (2).ext", … whichever is free first.
+	ConflictRename OnConflict = "rename"
+)
+
+// maxRenameSuffix bounds the search for a free name under
+// ConflictRename.
+const maxRenameSuffix = 1000
+
+// UploadOptions tune Upload. Room is how many more bytes the server may
+// use; negative means no limit.
+type UploadOptions struct {
+	Room       int64
+	OnConflict OnConflict
+}
+
+// UploadResult is where an upload landed and how big it was.
+type UploadResult struct {
+	Path  string
+	Bytes int64
+}
+
+// Upload streams body into path without holding it in memory: it goes
+// to a temp file beside the target, is fsynced, and only then moved
+// into place, so a failed or aborted upload never leaves a torn file.
+// Under ConflictOverwrite the file being replaced gives its own size
+// back to opts.Room. The result's Path is server-relative and differs
+// from path when the upload was renamed. With a scanner set the file is
+// scanned before it is moved into place (ErrInfected when flagged).
+func (m *Manager) Upload(serverID, path string, body io.Reader, opts UploadOptions) (UploadResult, error) {
+	abs, err := m.resolve(serverID, path)
+	if err != nil {
+		return UploadResult{}, err
+	}
+	body, err = m.checkIncoming(serverID, path, "upload", body)
+	if err != nil {
+		return UploadResult{}, err
+	}
+	dir := filepath.Dir(abs)
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return UploadResult{}, err
+	}
+	room := opts.Room
+	if room >= 0 && opts.OnConflict == ConflictOverwrite {
+		if st, err := os.Stat(abs); err == nil && st.Mode().IsRegular() {
+			room += st.Size()
+		}
+	}
+	if opts.OnConflict == ConflictFail {
+		// Checked again when the file is moved into place; this just
+		// spares sending the bytes for nothing.
+		if _, err := os.Lstat(abs); err == nil {
+			return UploadResult{}, ErrExists
+		}
+	}
+	tmp, err := os.CreateTemp(dir, ".upload-*")
+	if err != nil {
+		return UploadResult{}, err
+	}
+	committed := false
+	defer func() {
+		if !committed {
+			tmp.Close()
+			os.Remove(tmp.Name())
+		}
+	}()
+	w := diskio.Writer(context.Background(), diskio.Live, tmp)
+	if room >= 0 {
+		w = &quotaWriter{w: w, room: room}
+	}
+	n, err := io.Copy(w, body)
+	if err != nil {
+		return UploadResult{}, err
+	}
+	if err := tmp.Sync(); err != nil {
+		return UploadResult{}, err
+	}
+	if err := tmp.Close(); err != nil {
+		return UploadResult{}, err
+	}
+	if err := os.Chmod(tmp.Name(), 0o644); err != nil {
+		return UploadResult{}, err
+	}
+	if err := m.scanIncoming(context.Background(), serverID, tmp.Name(), path, "upload"); err != nil {
+		return UploadResult{}, err
+	}
+	dst, err := placeUpload(tmp.Name(), abs, opts.OnConflict)
+	if err != nil {
+		return UploadResult{}, err
+	}
+	committed = true
+	m.record(serverID, OpWrite, 1, n)
+	root, _ := m.resolve(serverID, "")
+	rel, _ := filepath.Rel(root, dst)
+	return UploadResult{Path: "/" + filepath.ToSlash(rel), Bytes: n}, nil
+}
+
+// placeUpload moves the finished temp file to abs, or next to it under
+// ConflictRename. Without overwrite it links rather than renames, which
+// fails instead of replacing a file that appeared meanwhile.
+func placeUpload(tmp, abs string, onConflict OnConflict) (string, error) {
+	if onConflict != ConflictFail && onConflict != ConflictRename {
+		return abs, os.Rename(tmp, abs)
+	}
+	ext := filepath.Ext(abs)
+	stem := strings.TrimSuffix(abs, ext)
+	for i := 0; i <= maxRenameSuffix; i++ {
+		dst := abs
+		if i > 0 {
+			dst = fmt.Sprintf("%s (%d)%s", stem, i, ext)
+		}
+		err := os.Link(tmp, dst)
+		if err == nil {
+			os.Remove(tmp)
+			return dst, nil
+		}
+		if !errors.Is(err, fs.ErrExist) {
+			return "", err
+		}
+		if onConflict == ConflictFail {
+			return "", ErrExists
+		}
+	}
+	return "", ErrExists
+}
+
+// quotaWriter fails with ErrQuotaExceeded once more than room bytes
+// have been written through it.
+type quotaWriter struct {
+	w    io.Writer
+	room int64
+}
+
+func (q *quotaWriter) Write(p []byte) (int, error) {
+	if int64(len(p)) > q.room {
+		return 0, ErrQuotaExceeded
+	}
+	n, err := q.w.Write(p)
+	q.room -= int64(n)
+	return n, err
 }
 
 // Mkdir creates a directory (recursive, idempotent).
@@ -139,8 +414,11 @@ func (m *Manager) Mkdir(serverID, path string) error {
 	return os.MkdirAll(abs, 0o755)
 }
 
-// Delete removes a file or directory tree.
-func (m *Manager) Delete(serverID, path string) error {
+// Delete removes a file or directory tree. Entries are removed deepest
+// first so a cancelled delete leaves the remaining tree consistent, and
+// progress counts removed entries out of the total walked. Entries on
+// the server's denylist stay, with the directories holding them.
+func (m *Manager) Delete(ctx context.Context, serverID, path string, progress ProgressFunc) error {
 	if path == "" || path == "/" {
 		return errors.New("refusing to delete server root")
 	}
@@ -148,7 +426,114 @@ func (m *Manager) Delete(serverID, path string) error {
 	if err != nil {
 		return err
 	}
-	return os.RemoveAll(abs)
+	root, _ := m.resolve(serverID, "")
+	rules := m.pathRules(serverID)
+	var entries []string
+	holding := map[string]bool{}
+	walkErr := filepath.WalkDir(abs, func(p string, d fs.DirEntry, err error) error {
+		if err != nil {
+			return err
+		}
+		if ctx.Err() != nil {
+			return ctx.Err()
+		}
+		if p != abs && denied(rules, root, p, d.IsDir()) {
+			for dir := filepath.Dir(p); !holding[dir]; dir = filepath.Dir(dir) {
+				holding[dir] = true
+				if dir == abs {
+					break
+				}
+			}
+			if d.IsDir() {
+				return filepath.SkipDir
+			}
+			return nil
+		}
+		entries = append(entries, p)
+		return nil
+	})
+	if walkErr != nil {
+		if errors.Is(walkErr, fs.ErrNotExist) {
+			return nil
+		}
+		return walkErr
+	}
+	total := int64(len(entries))
+	var removed int64
+	defer func() { m.record(serverID, OpDelete, removed, 0) }()
+	for i := len(entries) - 1; i >= 0; i-- {
+		if err := ctx.Err(); err != nil {
+			return err
+		}
+		if holding[entries[i]] {
+			continue
+		}
+		if err := os.Remove(entries[i]); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+		removed++
+		progress.report(total-int64(i), total)
+	}
+	return nil
+}
+
+// ValidCleanPattern reports whether p is a pattern Clean accepts: a
+// path.Match glob relative to the server root that doesn't match the
+// root itself.
+func ValidCleanPattern(p string) bool {
+	clean := path.Clean("/" + p)
+	if clean == "/" {
+		return false
+	}
+	_, err := path.Match(clean, "")
+	return err == nil
+}
+
+// Clean deletes everything under the server root matching one of
+// patterns ("mods", "mods/*.jar", "config/*"), except keep and its
+// contents, and returns the server-relative paths it removed. Matches
+// reached through a symlink that leaves the root, and those on the
+// server's denylist, are skipped; Delete keeps what's denied below the
+// rest.
+func (m *Manager) Clean(ctx context.Context, serverID string, patterns []string, keep string) ([]string, error) {
+	root, err := m.resolve(serverID, "/")
+	if err != nil {
+		return nil, err
+	}
+	realRoot, err := filepath.EvalSymlinks(root)
+	if err != nil {
+		return nil, err
+	}
+	keep = path.Clean("/" + keep)
+	rules := m.pathRules(serverID)
+	var removed []string
+	for _, p := range patterns {
+		if !ValidCleanPattern(p) {
+			return removed, fmt.Errorf("invalid clean pattern %q", p)
+		}
+		matches, err := filepath.Glob(filepath.Join(root, path.Clean("/"+p)))
+		if err != nil {
+			return removed, err
+		}
+		for _, abs := range matches {
+			rel := "/" + filepath.ToSlash(strings.TrimPrefix(abs, root+string(os.PathSeparator)))
+			if rel == keep || strings.HasPrefix(rel, keep+"/") || strings.HasPrefix(keep, rel+"/") {
+				continue
+			}
+			parent, err := filepath.EvalSymlinks(filepath.Dir(abs))
+			if err != nil || (parent != realRoot && !strings.HasPrefix(parent, realRoot+string(os.PathSeparator))) {
+				continue
+			}
+			if st, err := os.Lstat(abs); err != nil || denied(rules, root, abs, st.IsDir()) {
+				continue
+			}
+			if err := m.Delete(ctx, serverID, rel, nil); err != nil {
+				return removed, err
+			}
+			removed = append(removed, rel)
+		}
+	}
+	return removed, nil
 }
 
 // Move renames `from` to `to`.
@@ -161,6 +546,9 @@ func (m *Manager) Move(serverID, from, to string) error {
 	if err != nil {
 		return err
 	}
+	if err := m.policy.CheckName(to); err != nil {
+		return m.refuse(serverID, to, "move", err)
+	}
 	if err := os.MkdirAll(filepath.Dir(dst), 0o755); err != nil {
 		return err
 	}
@@ -187,13 +575,40 @@ func (m *Manager) Stat(serverID, path string) (Entry, error) {
 	}, nil
 }
 
+// ProgressFunc receives (done, total) after each unit of work. total
+// is 0 when it can't be known up front. A nil ProgressFunc is valid.
+type ProgressFunc func(done, total int64)
+
+func (p ProgressFunc) report(done, total int64) {
+	if p != nil {
+		p(done, total)
+	}
+}
+
+// countingReader tallies bytes read so archive extraction can report
+// progress against the on-disk archive size.
+type countingReader struct {
+	r        io.Reader
+	n        int64
+	total    int64
+	progress ProgressFunc
+}
+
+func (c *countingReader) Read(b []byte) (int, error) {
+	n, err := c.r.Read(b)
+	c.n += int64(n)
+	c.progress.report(c.n, c.total)
+	return n, err
+}
+
 // Decompress extracts the archive at `archivePath` into `destDir`.
 // Format is sniffed from the filename: `.tar.gz`/`.tgz` → tar+gzip,
 // `.tar` → tar, `.zip` → zip, `.gz` → single-file gzip. Every entry
 // is jail-checked against `destDir` (no `..` or absolute escapes) and
 // symlinks/devices are skipped, mirroring the upstream daemon's jail
-// rules.
-func (m *Manager) Decompress(serverID, archivePath, destDir string) error {
+// rules, as are entries the file policy or the server's denylist
+// refuse. Cancelling ctx stops between entries.
+func (m *Manager) Decompress(ctx context.Context, serverID, archivePath, destDir string, progress ProgressFunc) error {
 	src, err := m.resolve(serverID, archivePath)
 	if err != nil {
 		return err
@@ -207,39 +622,56 @@ func (m *Manager) Decompress(serverID, archivePath, destDir string) error {
 	} else if !st.IsDir() {
 		return errors.New("destination is not a directory")
 	}
+	if st, err := os.Stat(src); err == nil {
+		m.record(serverID, OpDecompress, 1, st.Size())
+	}
+	root, _ := m.resolve(serverID, "")
+	guard := &extractGuard{policy: m.policy, rules: m.pathRules(serverID), root: root}
 	lower := strings.ToLower(archivePath)
 	switch {
 	case strings.HasSuffix(lower, ".tar.gz"), strings.HasSuffix(lower, ".tgz"):
-		return extractTar(src, dst, true)
+		err = extractTar(ctx, src, dst, true, guard, progress)
 	case strings.HasSuffix(lower, ".tar"):
-		return extractTar(src, dst, false)
+		err = extractTar(ctx, src, dst, false, guard, progress)
 	case strings.HasSuffix(lower, ".zip"):
-		return extractZip(src, dst)
+		err = extractZip(ctx, src, dst, guard, progress)
 	case strings.HasSuffix(lower, ".gz"):
 		base := filepath.Base(archivePath)
 		out := strings.TrimSuffix(base, ".gz")
 		if out == "" || out == base {
 			out = base + ".out"
 		}
-		target, err := jailedPath(dst, out)
-		if err != nil {
+		var target string
+		if target, err = jailedPath(dst, out); err != nil {
 			return err
 		}
-		return extractGzip(src, target)
+		err = extractGzip(ctx, src, target, guard, progress)
 	default:
 		return errors.New("unsupported archive format")
 	}
+	if n := len(guard.skipped); n > 0 {
+		reason := fmt.Sprintf("%d blocked entries skipped: %s", n, strings.Join(guard.skipped[:min(n, 5)], ", "))
+		if n > 5 {
+			reason += ", …"
+		}
+		m.refuse(serverID, archivePath, "extract", errors.New(reason))
+	}
+	return err
 }
 
-func extractTar(archivePath, destDir string, gzipped bool) error {
+func extractTar(ctx context.Context, archivePath, destDir string, gzipped bool, guard *extractGuard, progress ProgressFunc) error {
 	f, err := os.Open(archivePath)
 	if err != nil {
 		return err
 	}
 	defer f.Close()
-	var rd io.Reader = f
+	st, err := f.Stat()
+	if err != nil {
+		return err
+	}
+	var rd io.Reader = &countingReader{r: f, total: st.Size(), progress: progress}
 	if gzipped {
-		gz, err := gzip.NewReader(f)
+		gz, err := gzip.NewReader(rd)
 		if err != nil {
 			return err
 		}
@@ -248,6 +680,9 @@ func extractTar(archivePath, destDir string, gzipped bool) error {
 	}
 	tr := tar.NewReader(rd)
 	for {
+		if err := ctx.Err(); err != nil {
+			return err
+		}
 		hdr, err := tr.Next()
 		if errors.Is(err, io.EOF) {
 			return nil
@@ -261,10 +696,17 @@ func extractTar(archivePath, destDir string, gzipped bool) error {
 		}
 		switch hdr.Typeflag {
 		case tar.TypeDir:
+			if !guard.admitDir(target) {
+				continue
+			}
 			if err := os.MkdirAll(target, 0o755); err != nil {
 				return err
 			}
 		case tar.TypeReg, tar.TypeRegA:
+			body, head := sniff(tr)
+			if !guard.admit(target, hdr.FileInfo().Mode(), head) {
+				continue
+			}
 			if err := os.MkdirAll(filepath.Dir(target), 0o755); err != nil {
 				return err
 			}
@@ -272,7 +714,7 @@ func extractTar(archivePath, destDir string, gzipped bool) error {
 			if err != nil {
 				return err
 			}
-			if _, err := io.Copy(out, tr); err != nil {
+			if _, err := fsio.Copy(diskio.Writer(ctx, diskio.Background, out), body); err != nil {
 				out.Close()
 				return err
 			}
@@ -281,36 +723,54 @@ func extractTar(archivePath, destDir string, gzipped bool) error {
 	}
 }
 
-func extractZip(archivePath, destDir string) error {
+func extractZip(ctx context.Context, archivePath, destDir string, guard *extractGuard, progress ProgressFunc) error {
 	rd, err := zip.OpenReader(archivePath)
 	if err != nil {
 		return err
 	}
 	defer rd.Close()
+	var total, done int64
 	for _, f := range rd.File {
+		total += int64(f.CompressedSize64)
+	}
+	for _, f := range rd.File {
+		if err := ctx.Err(); err != nil {
+			return err
+		}
+		done += int64(f.CompressedSize64)
+		progress.report(done, total)
 		target, err := jailedPath(destDir, f.Name)
 		if err != nil {
 			return err
 		}
 		if f.FileInfo().IsDir() {
+			if !guard.admitDir(target) {
+				continue
+			}
 			if err := os.MkdirAll(target, 0o755); err != nil {
 				return err
 			}
 			continue
 		}
-		if err := os.MkdirAll(filepath.Dir(target), 0o755); err != nil {
-			return err
-		}
 		in, err := f.Open()
 		if err != nil {
 			return err
 		}
+		body, head := sniff(in)
+		if !guard.admit(target, f.Mode(), head) {
+			in.Close()
+			continue
+		}
+		if err := os.MkdirAll(filepath.Dir(target), 0o755); err != nil {
+			in.Close()
+			return err
+		}
 		out, err := os.OpenFile(target, os.O_CREATE|os.O_TRUNC|os.O_WRONLY, 0o644)
 		if err != nil {
 			in.Close()
 			return err
 		}
-		if _, err := io.Copy(out, in); err != nil {
+		if _, err := fsio.Copy(diskio.Writer(ctx, diskio.Background, out), body); err != nil {
 			in.Close()
 			out.Close()
 			return err
@@ -321,17 +781,25 @@ func extractZip(archivePath, destDir string) error {
 	return nil
 }
 
-func extractGzip(archivePath, destFile string) error {
+func extractGzip(ctx context.Context, archivePath, destFile string, guard *extractGuard, progress ProgressFunc) error {
 	f, err := os.Open(archivePath)
 	if err != nil {
 		return err
 	}
 	defer f.Close()
-	gz, err := gzip.NewReader(f)
+	st, err := f.Stat()
+	if err != nil {
+		return err
+	}
+	gz, err := gzip.NewReader(&countingReader{r: f, total: st.Size(), progress: progress})
 	if err != nil {
 		return err
 	}
 	defer gz.Close()
+	body, head := sniff(gz)
+	if !guard.admit(destFile, 0o644, head) {
+		return nil
+	}
 	if err := os.MkdirAll(filepath.Dir(destFile), 0o755); err != nil {
 		return err
 	}
@@ -340,10 +808,322 @@ func extractGzip(archivePath, destFile string) error {
 		return err
 	}
 	defer out.Close()
-	_, err = io.Copy(out, gz)
+	_, err = fsio.Copy(diskio.Writer(ctx, diskio.Background, out), &contextReader{ctx: ctx, r: body})
 	return err
 }
 
+// contextReader fails the next Read once ctx is cancelled so copy
+// loops over a single large stream stay cancellable.
+type contextReader struct {
+	ctx context.Context
+	r   io.Reader
+}
+
+func (c *contextReader) Read(b []byte) (int, error) {
+	if err := c.ctx.Err(); err != nil {
+		return 0, err
+	}
+	return c.r.Read(b)
+}
+
+// Compress packs `paths` (server-relative files or directories) into a
+// new tar.gz inside `destDir` and returns the archive's server-relative
+// path. Entries on the server's denylist are left out. Progress counts
+// source bytes written out of the total walked up front. The partial
+// archive is removed on failure or cancellation.
+func (m *Manager) Compress(ctx context.Context, serverID string, paths []string, destDir string, progress ProgressFunc) (string, error) {
+	if len(paths) == 0 {
+		return "", errors.New("nothing to compress")
+	}
+	root, err := m.resolve(serverID, "")
+	if err != nil {
+		return "", err
+	}
+	rules := m.pathRules(serverID)
+	sources := make([]string, 0, len(paths))
+	var total int64
+	for _, p := range paths {
+		abs, err := m.resolve(serverID, p)
+		if err != nil {
+			return "", err
+		}
+		if abs == root {
+			return "", errors.New("refusing to compress server root")
+		}
+		walkErr := filepath.WalkDir(abs, func(p string, d fs.DirEntry, err error) error {
+			if err != nil {
+				return err
+			}
+			if denied(rules, root, p, d.IsDir()) {
+				if d.IsDir() {
+					return filepath.SkipDir
+				}
+				return nil
+			}
+			if d.Type().IsRegular() {
+				if info, err := d.Info(); err == nil {
+					total += info.Size()
+				}
+			}
+			return nil
+		})
+		if walkErr != nil {
+			return "", walkErr
+		}
+		sources = append(sources, abs)
+	}
+
+	name := "archive-" + time.Now().UTC().Format("2006-01-02T150405") + ".tar.gz"
+	relOut := path.Join("/", destDir, name)
+	dst, err := m.resolve(serverID, relOut)
+	if err != nil {
+		return "", err
+	}
+	if err := os.MkdirAll(filepath.Dir(dst), 0o755); err != nil {
+		return "", err
+	}
+	out, err := os.OpenFile(dst, os.O_CREATE|os.O_EXCL|os.O_WRONLY, 0o644)
+	if err != nil {
+		return "", err
+	}
+	gz := pgzip.NewWriter(out)
+	tw := tar.NewWriter(gz)
+	var done int64
+	for _, src := range sources {
+		walkErr := filepath.Walk(src, func(p string, info os.FileInfo, err error) error {
+			if err != nil {
+				return err
+			}
+			if err := ctx.Err(); err != nil {
+				return err
+			}
+			if p == dst {
+				return nil
+			}
+			if denied(rules, root, p, info.IsDir()) {
+				if info.IsDir() {
+					return filepath.SkipDir
+				}
+				return nil
+			}
+			rel, err := filepath.Rel(root, p)
+			if err != nil {
+				return err
+			}
+			hdr, err := tar.FileInfoHeader(info, "")
+			if err != nil {
+				return err
+			}
+			hdr.Name = filepath.ToSlash(rel)
+			if err := tw.WriteHeader(hdr); err != nil {
+				return err
+			}
+			if !info.Mode().IsRegular() {
+				return nil
+			}
+			f, err := os.Open(p)
+			if err != nil {
+				return err
+			}
+			defer f.Close()
+			n, err := fsio.Copy(tw, &contextReader{ctx: ctx, r: diskio.Reader(ctx, diskio.Background, f)})
+			done += n
+			progress.report(done, total)
+			return err
+		})
+		if walkErr != nil {
+			_ = tw.Close()
+			_ = gz.Close()
+			out.Close()
+			os.Remove(dst)
+			return "", walkErr
+		}
+	}
+	if err := tw.Close(); err != nil {
+		out.Close()
+		os.Remove(dst)
+		return "", err
+	}
+	if err := gz.Close(); err != nil {
+		out.Close()
+		os.Remove(dst)
+		return "", err
+	}
+	if err := out.Close(); err != nil {
+		os.Remove(dst)
+		return "", err
+	}
+	m.record(serverID, OpCompress, 1, total)
+	return relOut, nil
+}
+
+// PullOptions extend a pull. Mirrors are tried in order after the main
+// URL when it fails or its download doesn't match Checksum, written
+// "sha256:<hex>" or "sha1:<hex>"; without one any complete download is
+// accepted. Room is how many more bytes the server may use, as for
+// Upload; negative means no limit.
+type PullOptions struct {
+	Mirrors  []string
+	Checksum string
+	Room     int64
+}
+
+// PullResult is where a pull landed and which URL it came from.
+type PullResult struct {
+	Path string `json:"path"`
+	URL  string `json:"url"`
+}
+
+// ErrChecksumMismatch: no URL served a file matching the checksum.
+var ErrChecksumMismatch = errors.New("checksum mismatch")
+
+// Pull downloads `url` into `destDir` under the server root. The file
+// name comes from `filename` when set, otherwise the last URL path
+// segment. Progress is bytes received against Content-Length (0 when
+// the remote doesn't send one), restarting with each mirror. The file
+// is downloaded beside its target and only moved into place once it
+// has verified and passed the malware scan, if any, so a failed pull
+// leaves nothing behind; the error is the last URL's. A flagged file
+// ends the pull with ErrInfected without trying the mirrors. Only
+// public addresses are dialled (ErrForbiddenAddress otherwise), and a
+// download bigger than opts.Room fails with ErrQuotaExceeded.
+func (m *Manager) Pull(ctx context.Context, serverID, url, destDir, filename string, opts PullOptions, progress ProgressFunc) (PullResult, error) {
+	urls := append([]string{url}, opts.Mirrors...)
+	for _, u := range urls {
+		if !strings.HasPrefix(u, "http://") && !strings.HasPrefix(u, "https://") {
+			return PullResult{}, errors.New("pull url must be http(s)")
+		}
+	}
+	newHash, want, err := parseChecksum(opts.Checksum)
+	if err != nil {
+		return PullResult{}, err
+	}
+	if filename == "" {
+		filename = path.Base(strings.SplitN(strings.SplitN(url, "?", 2)[0], "#", 2)[0])
+	}
+	if filename == "" || filename == "/" || filename == "." || strings.ContainsAny(filename, `/\`) {
+		return PullResult{}, errors.New("invalid file name")
+	}
+	relOut := path.Join("/", destDir, filename)
+	dst, err := m.resolve(serverID, relOut)
+	if err != nil {
+		return PullResult{}, err
+	}
+	if err := m.policy.CheckName(relOut); err != nil {
+		return PullResult{}, m.refuse(serverID, relOut, "pull", err)
+	}
+	if err := os.MkdirAll(filepath.Dir(dst), 0o755); err != nil {
+		return PullResult{}, err
+	}
+	for _, u := range urls {
+		var h hash.Hash
+		if newHash != nil {
+			h = newHash()
+		}
+		var tmp string
+		tmp, err = pullTemp(ctx, u, filepath.Dir(dst), h, opts.Room, progress)
+		if err == nil && h != nil && !strings.EqualFold(hex.EncodeToString(h.Sum(nil)), want) {
+			os.Remove(tmp)
+			err = fmt.Errorf("pull %s: %w", u, ErrChecksumMismatch)
+		}
+		if err == nil {
+			if err := m.checkFile(serverID, relOut, "pull", tmp); err != nil {
+				os.Remove(tmp)
+				return PullResult{}, err
+			}
+			if err := m.scanIncoming(ctx, serverID, tmp, relOut, "pull"); err != nil {
+				return PullResult{}, err
+			}
+			if err := os.Rename(tmp, dst); err != nil {
+				os.Remove(tmp)
+				return PullResult{}, err
+			}
+			if st, err := os.Stat(dst); err == nil {
+				m.record(serverID, OpWrite, 1, st.Size())
+			}
+			return PullResult{Path: relOut, URL: u}, nil
+		}
+		if ctx.Err() != nil {
+			return PullResult{}, ctx.Err()
+		}
+		if errors.Is(err, ErrQuotaExceeded) {
+			return PullResult{}, err
+		}
+	}
+	return PullResult{}, err
+}
+
+// pullTemp downloads u to a temp file in dir, hashing it into h when
+// set, and returns the temp file's path. More than room bytes (when
+// not negative) is ErrQuotaExceeded.
+func pullTemp(ctx context.Context, u, dir string, h hash.Hash, room int64, progress ProgressFunc) (string, error) {
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, u, nil)
+	if err != nil {
+		return "", err
+	}
+	resp, err := PullClient.Do(req)
+	if err != nil {
+		return "", err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		return "", fmt.Errorf("pull %s: %s", u, resp.Status)
+	}
+	if room >= 0 && resp.ContentLength > room {
+		return "", ErrQuotaExceeded
+	}
+	out, err := os.CreateTemp(dir, ".pull-*")
+	if err != nil {
+		return "", err
+	}
+	var w io.Writer = out
+	if room >= 0 {
+		w = &quotaWriter{w: w, room: room}
+	}
+	if h != nil {
+		w = io.MultiWriter(w, h)
+	}
+	body := &countingReader{r: resp.Body, total: resp.ContentLength, progress: progress}
+	if _, err := io.Copy(w, body); err != nil {
+		out.Close()
+		os.Remove(out.Name())
+		return "", err
+	}
+	if err := out.Close(); err != nil {
+		os.Remove(out.Name())
+		return "", err
+	}
+	return out.Name(), nil
+}
+
+// ValidChecksum reports whether s is a checksum Pull understands.
+func ValidChecksum(s string) bool {
+	_, _, err := parseChecksum(s)
+	return err == nil
+}
+
+// parseChecksum splits "sha256:<hex>" or "sha1:<hex>" into a hash
+// constructor and the expected hex digest. Empty is no checksum.
+func parseChecksum(s string) (func() hash.Hash, string, error) {
+	if s == "" {
+		return nil, "", nil
+	}
+	algo, digest, _ := strings.Cut(s, ":")
+	var newHash func() hash.Hash
+	switch strings.ToLower(algo) {
+	case "sha256":
+		newHash = sha256.New
+	case "sha1":
+		newHash = sha1.New
+	default:
+		return nil, "", fmt.Errorf("unsupported checksum algorithm %q", algo)
+	}
+	if raw, err := hex.DecodeString(digest); err != nil || len(raw) != newHash().Size() {
+		return nil, "", errors.New("malformed checksum")
+	}
+	return newHash, digest, nil
+}
+
 func jailedPath(root, name string) (string, error) {
 	clean := filepath.Clean("/" + name)
 	abs := filepath.Join(root, clean)
@@ -0,0 +1,222 @@
+package files
+
+import (
+	"context"
+	"io/fs"
+	"os"
+	"path"
+	"path/filepath"
+	"sort"
+	"strings"
+	"time"
+)
+
+// DefaultLogGlobs is what PruneLogs matches when the policy names none.
+var DefaultLogGlobs = []string{"logs/**", "crash-reports/**"}
+
+// logPruneGrace spares files written to this recently: the game most
+// likely still has them open (logs/latest.log), and unlinking an open
+// file frees nothing until it's closed.
+const logPruneGrace = 10 * time.Minute
+
+// LogPruneOptions is a log pruning policy. Globs are slash-separated
+// and relative to the server root; "*" stays within one path segment
+// and "**" spans any number of them. A file matching one is removed
+// when older than MaxAge, then the oldest of the rest go until they
+// total at most MaxTotal bytes. Zero disables either limit.
+type LogPruneOptions struct {
+	Globs    []string
+	MaxAge   time.Duration
+	MaxTotal int64
+	DryRun   bool
+}
+
+// PrunedLog is one file PruneLogs removed (or, dry, would remove).
+type PrunedLog struct {
+	Path     string    `json:"path"`
+	Bytes    int64     `json:"bytes"`
+	Modified time.Time `json:"modified"`
+	Reason   string    `json:"reason"` // "age" or "size"
+}
+
+// LogPruneResult is what PruneLogs did. Kept and KeptBytes count the
+// matching files left in place.
+type LogPruneResult struct {
+	DryRun    bool        `json:"dryRun"`
+	Removed   []PrunedLog `json:"removed"`
+	Bytes     int64       `json:"bytes"`
+	Kept      int64       `json:"kept"`
+	KeptBytes int64       `json:"keptBytes"`
+}
+
+// ValidLogGlob reports whether g is a glob PruneLogs accepts: relative
+// to the server root, well-formed, and under a literal directory.
+// "**", "*.log" or "*/logs/**" would reach files all over the server —
+// its world, its configs — so the first segment can't be a wildcard
+// and there must be more than one.
+func ValidLogGlob(g string) bool {
+	segs := strings.Split(strings.TrimPrefix(path.Clean("/"+g), "/"), "/")
+	if len(segs) < 2 || strings.ContainsAny(segs[0], `*?[\`) {
+		return false
+	}
+	for _, seg := range segs {
+		if _, err := path.Match(seg, ""); err != nil {
+			return false
+		}
+	}
+	return true
+}
+
+// LogGlobBase is the literal directory g matches under, the part before
+// its first wildcard, for locking ("logs/**" → "/logs").
+func LogGlobBase(g string) string {
+	base := "/"
+	for _, seg := range strings.Split(strings.TrimPrefix(path.Clean("/"+g), "/"), "/") {
+		if strings.ContainsAny(seg, `*?[\`) {
+			break
+		}
+		base = path.Join(base, seg)
+	}
+	if base == path.Clean("/"+g) {
+		// No wildcard: the glob names a file, lock its directory.
+		base = path.Dir(base)
+	}
+	return base
+}
+
+// PruneLogs removes the regular files under the server root that opts
+// matches, by age and then by total size, oldest first. Files modified
+// in the last few minutes are never removed. With opts.DryRun nothing
+// is touched and the result lists what would go. Symlinks, and entries
+// on the server's denylist, aren't followed or removed. Cancelling ctx
+// stops between files.
+func (m *Manager) PruneLogs(ctx context.Context, serverID string, opts LogPruneOptions) (LogPruneResult, error) {
+	res := LogPruneResult{DryRun: opts.DryRun, Removed: []PrunedLog{}}
+	globs := opts.Globs
+	if len(globs) == 0 {
+		globs = DefaultLogGlobs
+	}
+	root, err := m.resolve(serverID, "/")
+	if err != nil {
+		return res, err
+	}
+	rules := m.pathRules(serverID)
+	var logs []PrunedLog
+	seen := map[string]bool{}
+	for _, g := range globs {
+		base, err := m.resolve(serverID, LogGlobBase(g))
+		if err != nil {
+			return res, err
+		}
+		pattern := strings.Split(strings.TrimPrefix(path.Clean("/"+g), "/"), "/")
+		err = filepath.WalkDir(base, func(p string, d fs.DirEntry, err error) error {
+			if err != nil {
+				if p == base && os.IsNotExist(err) {
+					return filepath.SkipDir
+				}
+				return err
+			}
+			if err := ctx.Err(); err != nil {
+				return err
+			}
+			if denied(rules, root, p, d.IsDir()) {
+				if d.IsDir() {
+					return filepath.SkipDir
+				}
+				return nil
+			}
+			if !d.Type().IsRegular() || seen[p] {
+				return nil
+			}
+			rel, err := filepath.Rel(root, p)
+			if err != nil {
+				return err
+			}
+			rel = filepath.ToSlash(rel)
+			if !matchSegments(pattern, strings.Split(rel, "/")) {
+				return nil
+			}
+			info, err := d.Info()
+			if err != nil {
+				return nil
+			}
+			seen[p] = true
+			logs = append(logs, PrunedLog{Path: "/" + rel, Bytes: info.Size(), Modified: info.ModTime()})
+			return nil
+		})
+		if err != nil {
+			return res, err
+		}
+	}
+	sort.Slice(logs, func(i, j int) bool { return logs[i].Modified.Before(logs[j].Modified) })
+
+	now := time.Now()
+	var total int64
+	for _, l := range logs {
+		total += l.Bytes
+	}
+	var doomed []PrunedLog
+	for _, l := range logs {
+		if now.Sub(l.Modified) < logPruneGrace {
+			continue
+		}
+		switch {
+		case opts.MaxAge > 0 && now.Sub(l.Modified) > opts.MaxAge:
+			l.Reason = "age"
+		case opts.MaxTotal > 0 && total > opts.MaxTotal:
+			l.Reason = "size"
+		default:
+			continue
+		}
+		total -= l.Bytes
+		doomed = append(doomed, l)
+	}
+
+	var removed int64
+	if !opts.DryRun {
+		defer func() { m.record(serverID, OpDelete, removed, 0) }()
+	}
+	for _, l := range doomed {
+		if err := ctx.Err(); err != nil {
+			return res, err
+		}
+		if !opts.DryRun {
+			abs, err := m.resolve(serverID, l.Path)
+			if err != nil {
+				return res, err
+			}
+			if err := os.Remove(abs); err != nil && !os.IsNotExist(err) {
+				return res, err
+			}
+			removed++
+		}
+		res.Removed = append(res.Removed, l)
+		res.Bytes += l.Bytes
+	}
+	res.Kept = int64(len(logs) - len(doomed))
+	res.KeptBytes = total
+	return res, nil
+}
+
+// matchSegments matches a path against a glob, both split on "/"; "**"
+// matches zero or more segments and anything else one, by path.Match.
+func matchSegments(pattern, segs []string) bool {
+	if len(pattern) == 0 {
+		return len(segs) == 0
+	}
+	if pattern[0] == "**" {
+		for i := 0; i <= len(segs); i++ {
+			if matchSegments(pattern[1:], segs[i:]) {
+				return true
+			}
+		}
+		return false
+	}
+	if len(segs) == 0 {
+		return false
+	}
+	if ok, _ := path.Match(pattern[0], segs[0]); !ok {
+		return false
+	}
+	return matchSegments(pattern[1:], segs[1:])
+}
@@ -0,0 +1,151 @@
+package files
+
+import (
+	"bufio"
+	"errors"
+	"io"
+	"io/fs"
+	"os"
+	"path/filepath"
+
+	"github.com/stellarstack/daemon/internal/filepolicy"
+)
+
+// Blocked describes a file the node's file policy kept off a server.
+// Source is "write", "upload", "pull", "move" or "extract"; for an
+// extraction Path is the archive and Reason lists what was skipped.
+type Blocked struct {
+	Path   string
+	Source string
+	Reason string
+}
+
+// SetPolicy applies the node's file policy to every write, upload,
+// pull, move and extraction. onBlocked, when set, is told about each
+// refusal on its own goroutine.
+func (m *Manager) SetPolicy(p *filepolicy.Policy, onBlocked func(serverID string, b Blocked)) {
+	m.policy = p
+	m.onBlocked = onBlocked
+}
+
+// SetPathRules installs the lookup for each server's denylist and
+// hidden list; see filepolicy.PathRules. Without one nothing is denied
+// or hidden.
+func (m *Manager) SetPathRules(rules func(serverID string) *filepolicy.PathRules) {
+	m.rules = rules
+}
+
+func (m *Manager) pathRules(serverID string) *filepolicy.PathRules {
+	if m.rules == nil {
+		return nil
+	}
+	return m.rules(serverID)
+}
+
+// refuse reports err, a policy refusal of rel, and returns it.
+func (m *Manager) refuse(serverID, rel, source string, err error) error {
+	if hook := m.onBlocked; hook != nil {
+		go hook(serverID, Blocked{Path: rel, Source: source, Reason: err.Error()})
+	}
+	return err
+}
+
+// checkIncoming checks rel's name and the first bytes of body, and
+// returns a reader that still yields all of body.
+func (m *Manager) checkIncoming(serverID, rel, source string, body io.Reader) (io.Reader, error) {
+	if m.policy == nil {
+		return body, nil
+	}
+	if err := m.policy.CheckName(rel); err != nil {
+		return nil, m.refuse(serverID, rel, source, err)
+	}
+	br := bufio.NewReaderSize(body, filepolicy.SniffBytes)
+	head, err := br.Peek(filepolicy.SniffBytes)
+	if err != nil && !errors.Is(err, io.EOF) {
+		return nil, err
+	}
+	if err := m.policy.CheckContent(rel, head); err != nil {
+		return nil, m.refuse(serverID, rel, source, err)
+	}
+	return br, nil
+}
+
+// checkFile is checkIncoming for a file already on disk at abs.
+func (m *Manager) checkFile(serverID, rel, source, abs string) error {
+	if m.policy == nil {
+		return nil
+	}
+	f, err := os.Open(abs)
+	if err != nil {
+		return err
+	}
+	defer f.Close()
+	_, err = m.checkIncoming(serverID, rel, source, f)
+	return err
+}
+
+// denied reports whether abs, a path under the server root, is on the
+// server's denylist. resolve only checks the path an operation names;
+// the walks below it check each entry with this.
+func denied(rules *filepolicy.PathRules, root, abs string, isDir bool) bool {
+	if rules == nil {
+		return false
+	}
+	rel, err := filepath.Rel(root, abs)
+	if err != nil {
+		return true
+	}
+	return rules.CheckPath(filepath.ToSlash(rel), isDir) != nil
+}
+
+// extractGuard decides, entry by entry, what an extraction into a
+// server may write, and remembers what it skipped.
+type extractGuard struct {
+	policy  *filepolicy.Policy
+	rules   *filepolicy.PathRules
+	root    string
+	skipped []string
+}
+
+// admit reports whether the entry bound for target may be written.
+func (g *extractGuard) admit(target string, mode fs.FileMode, head []byte) bool {
+	if g.policy == nil && g.rules == nil {
+		return true
+	}
+	rel, err := filepath.Rel(g.root, target)
+	if err != nil {
+		return false
+	}
+	rel = "/" + filepath.ToSlash(rel)
+	if g.rules.CheckPath(rel, false) != nil {
+		g.skipped = append(g.skipped, rel)
+		return false
+	}
+	if g.policy == nil {
+		return true
+	}
+	if g.policy.CheckName(rel) != nil || g.policy.CheckMode(rel, mode) != nil || g.policy.CheckContent(rel, head) != nil {
+		g.skipped = append(g.skipped, rel)
+		return false
+	}
+	return true
+}
+
+// admitDir is admit for a directory entry, which only the denylist
+// can refuse.
+func (g *extractGuard) admitDir(target string) bool {
+	if !denied(g.rules, g.root, target, true) {
+		return true
+	}
+	if rel, err := filepath.Rel(g.root, target); err == nil {
+		g.skipped = append(g.skipped, "/"+filepath.ToSlash(rel))
+	}
+	return false
+}
+
+// sniff wraps r so its first bytes can be checked and still copied.
+func sniff(r io.Reader) (*bufio.Reader, []byte) {
+	br := bufio.NewReaderSize(r, filepolicy.SniffBytes)
+	head, _ := br.Peek(filepolicy.SniffBytes)
+	return br, head
+}
@@ -0,0 +1,69 @@
+package files
+
+import (
+	"errors"
+	"fmt"
+	"net"
+	"net/http"
+	"net/netip"
+	"syscall"
+	"time"
+)
+
+// ErrForbiddenAddress: a pull URL, or a redirect it answered with,
+// resolved to an address that isn't public (loopback, the node's
+// private networks, link-local and the cloud metadata endpoints).
+var ErrForbiddenAddress = errors.New("pull address not allowed")
+
+// nonPublic are ranges netip's predicates don't cover that a pull must
+// not reach either: "this network", and the shared address space some
+// clouds put their metadata service in (100.100.100.200).
+var nonPublic = []netip.Prefix{
+	netip.MustParsePrefix("0.0.0.0/8"),
+	netip.MustParsePrefix("100.64.0.0/10"),
+}
+
+// PullClient is what pulls, and backup imports from a URL, download
+// with. The dialer checks each address after DNS has resolved it, so
+// neither a hostname pointing inwards nor a redirect to one gets
+// through, and there is no proxy for the check to be made against
+// instead of the target.
+var PullClient = &http.Client{
+	Transport: &http.Transport{
+		DialContext:         (&net.Dialer{Timeout: 30 * time.Second, Control: dialPublic}).DialContext,
+		TLSHandshakeTimeout: 10 * time.Second,
+		ForceAttemptHTTP2:   true,
+	},
+	CheckRedirect: func(req *http.Request, via []*http.Request) error {
+		if len(via) >= 10 {
+			return errors.New("stopped after 10 redirects")
+		}
+		if req.URL.Scheme != "http" && req.URL.Scheme != "https" {
+			return errors.New("pull redirect must be http(s)")
+		}
+		return nil
+	},
+}
+
+// dialPublic is PullClient's net.Dialer Control: it sees the resolved
+// ip:port about to be connected to and refuses non-public ones.
+func dialPublic(_, address string, _ syscall.RawConn) error {
+	host, _, err := net.SplitHostPort(address)
+	if err != nil {
+		return err
+	}
+	ip, err := netip.ParseAddr(host)
+	if err != nil {
+		return err
+	}
+	ip = ip.Unmap()
+	if !ip.IsGlobalUnicast() || ip.IsPrivate() {
+		return fmt.Errorf("%w: %s", ErrForbiddenAddress, ip)
+	}
+	for _, p := range nonPublic {
+		if p.Contains(ip) {
+			return fmt.Errorf("%w: %s", ErrForbiddenAddress, ip)
+		}
+	}
+	return nil
+}
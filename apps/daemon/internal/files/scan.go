@@ -0,0 +1,90 @@
+package files
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"log"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/scan"
+)
+
+// ErrInfected: the malware scan flagged the file and it was
+// quarantined instead of stored. The error text carries the signature.
+var ErrInfected = errors.New("flagged by malware scan")
+
+// ScanOptions configure scanning of incoming files. QuarantineDir holds
+// flagged files, one directory per server. With FailClosed a file the
+// scanner couldn't check is refused; otherwise it is stored and the
+// failure logged. OnQuarantine, when set, is told about every flagged
+// file on its own goroutine.
+type ScanOptions struct {
+	QuarantineDir string
+	FailClosed    bool
+	OnQuarantine  func(serverID string, q Quarantined)
+}
+
+// Quarantined describes a flagged file. Path is where it would have
+// gone on the server, Source "upload", "pull", "sftp" or "scp" and File
+// where it is kept now.
+type Quarantined struct {
+	Path      string
+	Source    string
+	Signature string
+	File      string
+}
+
+// SetScanner scans every upload and pull with s before it is moved into
+// place.
+func (m *Manager) SetScanner(s *scan.Scanner, opts ScanOptions) {
+	m.scanner = s
+	m.scanOpts = opts
+}
+
+// ScanWritten scans abs, a file an SFTP or scp client wrote straight
+// into the server tree and has just closed. Those writes can't be held
+// back until they're checked, so a flagged file is moved out to
+// quarantine after the fact; the error is scanIncoming's.
+func (m *Manager) ScanWritten(ctx context.Context, serverID, abs, rel, source string) error {
+	return m.scanIncoming(ctx, serverID, abs, rel, source)
+}
+
+// scanIncoming checks tmp, a finished download or upload not yet in
+// place, on its way to the server-relative rel. A flagged file is moved
+// to quarantine and ErrInfected returned.
+func (m *Manager) scanIncoming(ctx context.Context, serverID, tmp, rel, source string) error {
+	if m.scanner == nil {
+		return nil
+	}
+	v, err := m.scanner.Scan(ctx, tmp)
+	if err != nil {
+		if m.scanOpts.FailClosed {
+			os.Remove(tmp)
+			return fmt.Errorf("malware scan: %w", err)
+		}
+		log.Printf("files: server %s: %s not scanned: %v", serverID, rel, err)
+		return nil
+	}
+	if !v.Infected {
+		return nil
+	}
+	q := Quarantined{Path: rel, Source: source, Signature: v.Signature}
+	dir := filepath.Join(m.scanOpts.QuarantineDir, serverID)
+	name := time.Now().UTC().Format("20060102T150405") + "-" + strings.ReplaceAll(strings.TrimPrefix(rel, "/"), "/", "_")
+	if err := os.MkdirAll(dir, 0o700); err == nil && os.Rename(tmp, filepath.Join(dir, name)) == nil {
+		q.File = filepath.Join(dir, name)
+		_ = os.Chmod(q.File, 0o600)
+	} else {
+		// Can't keep it; at least make sure it never reaches the server.
+		os.Remove(tmp)
+	}
+	log.Printf("files: server %s: %s %s flagged as %s, quarantined at %q", serverID, source, rel, v.Signature, q.File)
+	if hook := m.scanOpts.OnQuarantine; hook != nil {
+		go hook(serverID, q)
+	}
+	return fmt.Errorf("%w: %s", ErrInfected, v.Signature)
+}
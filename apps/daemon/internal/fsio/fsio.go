@@ -0,0 +1,62 @@
+// Package fsio is the copy path shared by the file manager, backups and
+// transfers. Go's io.Copy already hands *os.File → *os.File copies to
+// the kernel (copy_file_range, falling back to sendfile/splice), so the
+// data never enters user space and reflink-capable filesystems can
+// share extents. Everything else — tar and gzip streams, HTTP bodies —
+// goes through io.Copy's 32 KiB buffer, which on a multi-GB world means
+// hundreds of thousands of read/write syscall pairs. Copy uses pooled
+// 1 MiB buffers for those instead.
+//
+// There is no io_uring path: the stdlib has no ring support and the
+// cgo/third-party options would cost the static build. The gain it
+// offers for sequential streams over large buffers is marginal next to
+// the compression work done on the same bytes.
+package fsio
+
+import (
+	"io"
+	"os"
+	"sync"
+	"sync/atomic"
+)
+
+// BufferSize is the user-space copy buffer for non-file streams.
+const BufferSize = 1 << 20
+
+var (
+	disabled atomic.Bool
+	buffers  = sync.Pool{New: func() any {
+		b := make([]byte, BufferSize)
+		return &b
+	}}
+)
+
+// SetEnabled toggles the optimised path. Disabled, Copy is plain
+// io.Copy — kept as an escape hatch should a filesystem misbehave
+// under copy_file_range.
+func SetEnabled(on bool) {
+	disabled.Store(!on)
+}
+
+// Copy copies src to dst like io.Copy, choosing the cheapest path.
+func Copy(dst io.Writer, src io.Reader) (int64, error) {
+	if disabled.Load() {
+		return io.Copy(dst, src)
+	}
+	if _, ok := dst.(*os.File); ok {
+		if _, ok := src.(*os.File); ok {
+			// os.File.ReadFrom → copy_file_range / sendfile.
+			return io.Copy(dst, src)
+		}
+	}
+	buf := buffers.Get().(*[]byte)
+	defer buffers.Put(buf)
+	// Hide ReaderFrom/WriterTo: both would bypass the buffer and fall
+	// back to their own 32 KiB one (os.File.WriteTo does exactly that
+	// for non-socket destinations).
+	return io.CopyBuffer(writerOnly{dst}, readerOnly{src}, *buf)
+}
+
+type readerOnly struct{ io.Reader }
+
+type writerOnly struct{ io.Writer }
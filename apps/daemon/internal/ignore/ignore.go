@@ -0,0 +1,124 @@
+// Package ignore decides which parts of a server's tree a backup or a
+// transfer leaves out: the patterns sent with the request, plus those
+// in .stellarignore files anywhere in the tree, each of which applies
+// below its own directory the way a .gitignore does.
+//
+// A pattern is matched against the slash-separated path relative to
+// the directory it belongs to, or just the base name when it has no
+// slash; a leading "/" is dropped and a trailing "/" matches
+// directories only. In a .stellarignore, one pattern per line; blank
+// lines and lines starting with "#" are skipped.
+package ignore
+
+import (
+	"os"
+	"path"
+	"path/filepath"
+	"strings"
+)
+
+// FileName is the per-directory ignore file.
+const FileName = ".stellarignore"
+
+// maxFileBytes caps how much of a .stellarignore is read; anything
+// larger isn't a pattern list.
+const maxFileBytes = 64 << 10
+
+// Match reports whether rel matches one of patterns.
+func Match(patterns []string, rel string, isDir bool) bool {
+	for _, p := range patterns {
+		p = strings.TrimPrefix(p, "/")
+		dirOnly := strings.HasSuffix(p, "/")
+		p = strings.TrimSuffix(p, "/")
+		if p == "" || (dirOnly && !isDir) {
+			continue
+		}
+		subject := rel
+		if !strings.Contains(p, "/") {
+			subject = path.Base(rel)
+		}
+		if ok, _ := path.Match(p, subject); ok {
+			return true
+		}
+	}
+	return false
+}
+
+// Walk walks root like filepath.Walk, calling fn for everything that
+// neither patterns nor a .stellarignore leaves out; rel is the entry's
+// slash-separated path relative to root, which itself isn't passed to
+// fn. An ignored directory is skipped whole.
+func Walk(root string, patterns []string, fn func(path, rel string, info os.FileInfo) error) error {
+	// Keyed by the directory's rel ("." for root); only directories
+	// that have a .stellarignore get an entry.
+	nested := map[string][]string{}
+	if r := load(root); len(r) > 0 {
+		nested["."] = r
+	}
+	return filepath.Walk(root, func(p string, info os.FileInfo, err error) error {
+		if err != nil {
+			return err
+		}
+		rel, err := filepath.Rel(root, p)
+		if err != nil {
+			return err
+		}
+		if rel == "." {
+			return nil
+		}
+		rel = filepath.ToSlash(rel)
+		if Match(patterns, rel, info.IsDir()) || matchNested(nested, rel, info.IsDir()) {
+			if info.IsDir() {
+				return filepath.SkipDir
+			}
+			return nil
+		}
+		if info.IsDir() {
+			if r := load(p); len(r) > 0 {
+				nested[rel] = r
+			}
+		}
+		return fn(p, rel, info)
+	})
+}
+
+// matchNested checks rel against the .stellarignore of each directory
+// above it.
+func matchNested(nested map[string][]string, rel string, isDir bool) bool {
+	for dir := path.Dir(rel); ; dir = path.Dir(dir) {
+		if r, ok := nested[dir]; ok {
+			sub := rel
+			if dir != "." {
+				sub = strings.TrimPrefix(rel, dir+"/")
+			}
+			if Match(r, sub, isDir) {
+				return true
+			}
+		}
+		if dir == "." {
+			return false
+		}
+	}
+}
+
+// load reads dir's .stellarignore. A missing, oversized or non-regular
+// one (a symlink out of the tree) is no patterns.
+func load(dir string) []string {
+	name := filepath.Join(dir, FileName)
+	st, err := os.Lstat(name)
+	if err != nil || !st.Mode().IsRegular() || st.Size() > maxFileBytes {
+		return nil
+	}
+	data, err := os.ReadFile(name)
+	if err != nil {
+		return nil
+	}
+	var out []string
+	for _, line := range strings.Split(string(data), "\n") {
+		line = strings.TrimSpace(line)
+		if line != "" && !strings.HasPrefix(line, "#") {
+			out = append(out, line)
+		}
+	}
+	return out
+}
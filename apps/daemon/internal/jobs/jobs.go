@@ -0,0 +1,460 @@
+// Package jobs runs long filesystem operations (compress, decompress,
+// recursive deletes, remote pulls) outside the HTTP request that asked
+// for them. The handler gets a job id back immediately; progress is
+// polled via GET /api/servers/:uuid/jobs/:job or pushed over the
+// per-server WebSocket as `job progress` frames, and a running job can
+// be cancelled through its context.
+//
+// Every job is persisted as `<dataDir>/jobs/<id>.json` so its outcome
+// survives a daemon restart. Work can't be resumed mid-flight (the Func
+// closure is gone), so jobs found non-terminal on boot are marked
+// failed with an explicit reason instead of lingering as "running".
+// Callers may attach an idempotency key; re-submitting the same key
+// returns the existing job rather than doing the work twice.
+package jobs
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"log"
+	"os"
+	"path/filepath"
+	"slices"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/google/uuid"
+)
+
+// Status is the lifecycle of one job. Terminal states are completed,
+// failed and cancelled; nothing transitions out of them.
+type Status string
+
+const (
+	StatusQueued    Status = "queued"
+	StatusRunning   Status = "running"
+	StatusCompleted Status = "completed"
+	StatusFailed    Status = "failed"
+	StatusCancelled Status = "cancelled"
+)
+
+// Terminal reports whether the status is final.
+func (s Status) Terminal() bool {
+	return s == StatusCompleted || s == StatusFailed || s == StatusCancelled
+}
+
+// retention is how long finished jobs stay queryable before the next
+// Start prunes them. Long enough for a panel tab that was backgrounded
+// to come back and read the outcome.
+const retention = time.Hour
+
+// progressInterval throttles listener notifications so a decompress of
+// a million tiny files doesn't emit a million WS frames.
+const progressInterval = 250 * time.Millisecond
+
+// ErrInProgress is returned by Run when a job with the same idempotency
+// key is still running.
+var ErrInProgress = errors.New("job with this idempotency key is already in progress")
+
+// interruptedError is recorded on jobs that were mid-flight when the
+// daemon went down.
+const interruptedError = "interrupted by daemon restart"
+
+// Snapshot is the wire shape of a job, returned by the poll endpoint
+// and carried in `job progress` frames. It is also the on-disk format.
+type Snapshot struct {
+	ID        string          `json:"id"`
+	ServerID  string          `json:"serverId"`
+	Kind      string          `json:"kind"`
+	Key       string          `json:"key,omitempty"`
+	Status    Status          `json:"status"`
+	Result    json.RawMessage `json:"result,omitempty"`
+	Done      int64           `json:"done"`
+	Total     int64           `json:"total"`
+	Message   string          `json:"message,omitempty"`
+	Error     string          `json:"error,omitempty"`
+	CreatedAt time.Time       `json:"createdAt"`
+	UpdatedAt time.Time       `json:"updatedAt"`
+}
+
+// Job is the handle a running Func reports progress through.
+type Job struct {
+	mgr    *Manager
+	cancel context.CancelFunc
+
+	mu         sync.Mutex
+	snap       Snapshot
+	lastNotify time.Time
+}
+
+// Progress records `done` out of `total` units (bytes or entries — the
+// kind decides). total <= 0 means unknown. Notifications are throttled.
+func (j *Job) Progress(done, total int64) {
+	j.mu.Lock()
+	j.snap.Done = done
+	if total > 0 {
+		j.snap.Total = total
+	}
+	j.snap.UpdatedAt = time.Now().UTC()
+	notify := time.Since(j.lastNotify) >= progressInterval || (total > 0 && done >= total)
+	if notify {
+		j.lastNotify = time.Now()
+	}
+	snap := j.snap
+	j.mu.Unlock()
+	if notify {
+		j.mgr.persist(snap)
+		j.mgr.notify(snap)
+	}
+}
+
+// SetMessage attaches a short human-readable note (e.g. the resulting
+// archive name) that surfaces in the snapshot.
+func (j *Job) SetMessage(msg string) {
+	j.mu.Lock()
+	j.snap.Message = msg
+	j.snap.UpdatedAt = time.Now().UTC()
+	j.mu.Unlock()
+}
+
+// SetResult stores the job's JSON-encodable outcome. Replayed verbatim
+// to callers that retry with the same idempotency key.
+func (j *Job) SetResult(v any) {
+	raw, err := json.Marshal(v)
+	if err != nil {
+		return
+	}
+	j.mu.Lock()
+	j.snap.Result = raw
+	j.mu.Unlock()
+}
+
+// Snapshot returns a copy of the current job state.
+func (j *Job) Snapshot() Snapshot {
+	j.mu.Lock()
+	defer j.mu.Unlock()
+	return j.snap
+}
+
+func (j *Job) setStatus(st Status, errMsg string) Snapshot {
+	j.mu.Lock()
+	j.snap.Status = st
+	j.snap.Error = errMsg
+	j.snap.UpdatedAt = time.Now().UTC()
+	snap := j.snap
+	j.mu.Unlock()
+	j.mgr.persist(snap)
+	j.mgr.notify(snap)
+	return snap
+}
+
+// Func is the body of a job. It must honour ctx cancellation and should
+// call j.Progress as it goes.
+type Func func(ctx context.Context, j *Job) error
+
+// Listener is invoked on every status change and (throttled) progress
+// update. The router installs one that republishes onto the server bus.
+type Listener func(Snapshot)
+
+// Manager tracks every job the daemon has run in the retention window.
+// One Manager per daemon process.
+type Manager struct {
+	dir string
+
+	mu       sync.Mutex
+	jobs     map[string]*Job
+	listener Listener
+}
+
+// New loads persisted jobs from `<dataDir>/jobs` and fails any that
+// were still queued or running when the previous process exited.
+func New(dataDir string) *Manager {
+	m := &Manager{
+		dir:  filepath.Join(dataDir, "jobs"),
+		jobs: map[string]*Job{},
+	}
+	if err := os.MkdirAll(m.dir, 0o755); err != nil {
+		log.Printf("jobs: mkdir %s: %v", m.dir, err)
+	}
+	m.load()
+	return m
+}
+
+// load restores every job file. Unreadable files are logged and
+// skipped; a corrupt record must not block daemon boot.
+func (m *Manager) load() {
+	entries, err := os.ReadDir(m.dir)
+	if err != nil {
+		return
+	}
+	now := time.Now().UTC()
+	for _, e := range entries {
+		if e.IsDir() || !strings.HasSuffix(e.Name(), ".json") {
+			continue
+		}
+		raw, err := os.ReadFile(filepath.Join(m.dir, e.Name()))
+		if err != nil {
+			log.Printf("jobs: read %s: %v", e.Name(), err)
+			continue
+		}
+		var snap Snapshot
+		if err := json.Unmarshal(raw, &snap); err != nil || snap.ID == "" {
+			log.Printf("jobs: parse %s: %v", e.Name(), err)
+			continue
+		}
+		if !snap.Status.Terminal() {
+			snap.Status = StatusFailed
+			snap.Error = interruptedError
+			snap.UpdatedAt = now
+			m.persist(snap)
+			log.Printf("jobs: %s (%s, server %s) %s", snap.ID, snap.Kind, snap.ServerID, interruptedError)
+		}
+		m.jobs[snap.ID] = &Job{mgr: m, cancel: func() {}, snap: snap}
+	}
+}
+
+// persist writes the snapshot atomically (temp file + rename) so a
+// crash mid-write leaves the previous record intact.
+func (m *Manager) persist(s Snapshot) {
+	raw, err := json.Marshal(s)
+	if err != nil {
+		return
+	}
+	path := filepath.Join(m.dir, s.ID+".json")
+	tmp := path + ".tmp"
+	if err := os.WriteFile(tmp, raw, 0o644); err != nil {
+		log.Printf("jobs: persist %s: %v", s.ID, err)
+		return
+	}
+	if err := os.Rename(tmp, path); err != nil {
+		log.Printf("jobs: persist %s: %v", s.ID, err)
+	}
+}
+
+// SetListener installs the progress listener. Replaces any prior one.
+func (m *Manager) SetListener(l Listener) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.listener = l
+}
+
+func (m *Manager) notify(s Snapshot) {
+	m.mu.Lock()
+	l := m.listener
+	m.mu.Unlock()
+	if l != nil {
+		l(s)
+	}
+}
+
+// register creates a queued job, or returns the live job already
+// holding (serverID, key). Failed and cancelled jobs don't hold their
+// key, so a panel retry after a failure runs the work again.
+func (m *Manager) register(parent context.Context, serverID, kind, key string) (j *Job, ctx context.Context, existing bool) {
+	now := time.Now().UTC()
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.pruneLocked(now)
+	if key != "" {
+		for _, other := range m.jobs {
+			snap := other.Snapshot()
+			if snap.ServerID != serverID || snap.Key != key {
+				continue
+			}
+			if snap.Status == StatusFailed || snap.Status == StatusCancelled {
+				continue
+			}
+			return other, nil, true
+		}
+	}
+	jobCtx, cancel := context.WithCancel(parent)
+	j = &Job{
+		mgr:    m,
+		cancel: cancel,
+		snap: Snapshot{
+			ID:        uuid.New().String(),
+			ServerID:  serverID,
+			Kind:      kind,
+			Key:       key,
+			Status:    StatusQueued,
+			CreatedAt: now,
+			UpdatedAt: now,
+		},
+	}
+	m.jobs[j.snap.ID] = j
+	m.persist(j.snap)
+	return j, jobCtx, false
+}
+
+// Start registers a job and runs fn in its own goroutine. Returns the
+// initial snapshot so the caller can hand the id back to the client.
+// When key matches a live job for the same server, that job's snapshot
+// is returned and fn is not run.
+func (m *Manager) Start(serverID, kind, key string, fn Func) Snapshot {
+	j, ctx, existing := m.register(context.Background(), serverID, kind, key)
+	if existing {
+		return j.Snapshot()
+	}
+	snap := j.Snapshot()
+	go m.run(ctx, j, fn)
+	return snap
+}
+
+// Run executes fn synchronously under job tracking, for operations
+// whose HTTP contract is still request/response (backups, transfer
+// pushes) but which should be persisted and idempotent. A retry with
+// the key of a completed job returns the stored snapshot (including
+// Result) without re-running; a retry while it runs gets ErrInProgress.
+func (m *Manager) Run(ctx context.Context, serverID, kind, key string, fn Func) (Snapshot, error) {
+	j, jobCtx, existing := m.register(ctx, serverID, kind, key)
+	if existing {
+		snap := j.Snapshot()
+		if snap.Status == StatusCompleted {
+			return snap, nil
+		}
+		return snap, ErrInProgress
+	}
+	err := m.run(jobCtx, j, fn)
+	return j.Snapshot(), err
+}
+
+func (m *Manager) run(ctx context.Context, j *Job, fn Func) error {
+	defer j.cancel()
+	j.setStatus(StatusRunning, "")
+	err := fn(ctx, j)
+	switch {
+	case err == nil:
+		j.setStatus(StatusCompleted, "")
+	case errors.Is(err, context.Canceled) || ctx.Err() != nil:
+		j.setStatus(StatusCancelled, "")
+	default:
+		j.setStatus(StatusFailed, err.Error())
+	}
+	return err
+}
+
+// Get returns the job if it exists and belongs to serverID. Scoping by
+// server stops one server's token from reading another server's jobs.
+func (m *Manager) Get(serverID, id string) (Snapshot, bool) {
+	m.mu.Lock()
+	j, ok := m.jobs[id]
+	m.mu.Unlock()
+	if !ok {
+		return Snapshot{}, false
+	}
+	snap := j.Snapshot()
+	if snap.ServerID != serverID {
+		return Snapshot{}, false
+	}
+	return snap, true
+}
+
+// List returns every retained job for serverID.
+func (m *Manager) List(serverID string) []Snapshot {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	out := make([]Snapshot, 0)
+	for _, j := range m.jobs {
+		snap := j.Snapshot()
+		if snap.ServerID == serverID {
+			out = append(out, snap)
+		}
+	}
+	return out
+}
+
+// Active counts serverID's queued and running jobs whose kind is one of
+// kinds (any kind when none are given).
+func (m *Manager) Active(serverID string, kinds ...string) int {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	n := 0
+	for _, j := range m.jobs {
+		snap := j.Snapshot()
+		if snap.ServerID != serverID || snap.Status.Terminal() {
+			continue
+		}
+		if len(kinds) > 0 && !slices.Contains(kinds, snap.Kind) {
+			continue
+		}
+		n++
+	}
+	return n
+}
+
+// Counts is how many jobs of one kind are waiting and running.
+type Counts struct {
+	Queued  int `json:"queued"`
+	Running int `json:"running"`
+}
+
+// Counts tallies every server's unfinished jobs by kind, for the node's
+// queue report.
+func (m *Manager) Counts() map[string]Counts {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	out := map[string]Counts{}
+	for _, j := range m.jobs {
+		snap := j.Snapshot()
+		c := out[snap.Kind]
+		switch snap.Status {
+		case StatusQueued:
+			c.Queued++
+		case StatusRunning:
+			c.Running++
+		default:
+			continue
+		}
+		out[snap.Kind] = c
+	}
+	return out
+}
+
+// Holds reports whether key belongs to a live or completed job for
+// serverID, i.e. whether Start with that key would return the existing
+// job instead of running new work.
+func (m *Manager) Holds(serverID, key string) bool {
+	if key == "" {
+		return false
+	}
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	for _, j := range m.jobs {
+		snap := j.Snapshot()
+		if snap.ServerID == serverID && snap.Key == key && snap.Status != StatusFailed && snap.Status != StatusCancelled {
+			return true
+		}
+	}
+	return false
+}
+
+// Cancel requests cancellation of a running job. Returns false when the
+// job is unknown, belongs to another server, or has already finished.
+func (m *Manager) Cancel(serverID, id string) bool {
+	m.mu.Lock()
+	j, ok := m.jobs[id]
+	m.mu.Unlock()
+	if !ok {
+		return false
+	}
+	snap := j.Snapshot()
+	if snap.ServerID != serverID || snap.Status.Terminal() {
+		return false
+	}
+	j.cancel()
+	return true
+}
+
+// pruneLocked drops finished jobs older than the retention window.
+// Caller holds m.mu.
+func (m *Manager) pruneLocked(now time.Time) {
+	for id, j := range m.jobs {
+		snap := j.Snapshot()
+		if snap.Status.Terminal() && now.Sub(snap.UpdatedAt) > retention {
+			delete(m.jobs, id)
+			_ = os.Remove(filepath.Join(m.dir, id+".json"))
+		}
+	}
+}
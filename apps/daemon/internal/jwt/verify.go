@@ -25,6 +25,9 @@ type Claims struct {
 	Iat    int64    `json:"iat"`
 	Exp    int64    `json:"exp"`
 	Jti    string   `json:"jti"`
+	// Paths limits an sftp token to these directories of the server
+	// (subusers with restricted SFTP access). Empty means all of it.
+	Paths  []string `json:"paths,omitempty"`
 }
 
 // HasScope reports whether the supplied scope is present on the token.
@@ -37,10 +40,19 @@ func (c *Claims) HasScope(want string) bool {
 	return false
 }
 
+// ErrExpired and ErrNotYetValid are the time checks failing: the token
+// expired more than the leeway ago, or was issued more than the leeway
+// in this node's future, which only a wrong clock explains.
+var (
+	ErrExpired     = errors.New("token expired")
+	ErrNotYetValid = errors.New("token issued in the future; check this node's clock")
+)
+
 // Verifier validates HS256 tokens against the daemon's local signing key.
 // Construct with New(); Verify is goroutine-safe.
 type Verifier struct {
-	key []byte
+	key    []byte
+	leeway int64 // seconds
 }
 
 // New returns a Verifier bound to the supplied hex-encoded HMAC key.
@@ -55,9 +67,21 @@ func New(hexKey string) (*Verifier, error) {
 	return &Verifier{key: key}, nil
 }
 
+// SetLeeway lets tokens through that are up to d past their expiry or
+// before their issue time, for clock drift between node and panel. Call
+// before serving.
+func (v *Verifier) SetLeeway(d time.Duration) {
+	v.leeway = int64(d / time.Second)
+}
+
+// Leeway is the drift SetLeeway allows.
+func (v *Verifier) Leeway() time.Duration {
+	return time.Duration(v.leeway) * time.Second
+}
+
 // Verify parses, validates the signature, and returns the claims. Returns
 // an error if the token is malformed, the signature is wrong, the algorithm
-// is not HS256, or the token is expired.
+// is not HS256, or the token's times are off (ErrExpired, ErrNotYetValid).
 func (v *Verifier) Verify(token string) (*Claims, error) {
 	parts := strings.Split(token, ".")
 	if len(parts) != 3 {
@@ -97,8 +121,12 @@ func (v *Verifier) Verify(token string) (*Claims, error) {
 	if err := json.Unmarshal(claimsBytes, &claims); err != nil {
 		return nil, fmt.Errorf("parse claims: %w", err)
 	}
-	if claims.Exp > 0 && time.Now().Unix() >= claims.Exp {
-		return nil, errors.New("token expired")
+	now := time.Now().Unix()
+	if claims.Exp > 0 && now >= claims.Exp+v.leeway {
+		return nil, ErrExpired
+	}
+	if claims.Iat > now+v.leeway {
+		return nil, ErrNotYetValid
 	}
 	return &claims, nil
 }
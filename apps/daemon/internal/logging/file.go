@@ -0,0 +1,224 @@
+package logging
+
+import (
+	"bytes"
+	"compress/gzip"
+	"errors"
+	"fmt"
+	"io"
+	"log"
+	"os"
+	"path/filepath"
+	"slices"
+	"sort"
+	"strings"
+	"sync"
+	"time"
+)
+
+// fileName is the live log inside the log directory. Rotated files are
+// daemon-<UTC timestamp>.log.gz next to it.
+const fileName = "daemon.log"
+
+// FileOptions bound a File's disk use. Zero values disable that limit.
+type FileOptions struct {
+	MaxBytes int64         // rotate when the live file would grow past this
+	MaxAge   time.Duration // rotate when the live file is older than this
+	Keep     int           // rotated files to keep
+	KeepFor  time.Duration // delete rotated files older than this
+}
+
+// File is an io.Writer over <dir>/daemon.log that rotates by size and
+// age. Rotated files are gzipped and pruned in the background so a
+// write never waits on compression.
+type File struct {
+	dir  string
+	opts FileOptions
+
+	mu     sync.Mutex
+	f      *os.File
+	size   int64
+	opened time.Time
+
+	pruneMu sync.Mutex // one compress/prune pass at a time
+}
+
+// OpenFile opens (appending to) the live log in dir, creating dir.
+func OpenFile(dir string, opts FileOptions) (*File, error) {
+	if err := os.MkdirAll(dir, 0o750); err != nil {
+		return nil, err
+	}
+	lf := &File{dir: dir, opts: opts}
+	if err := lf.open(); err != nil {
+		return nil, err
+	}
+	// Leftovers from a crash mid-compression, or a lowered limit.
+	go lf.compressAndPrune("")
+	return lf, nil
+}
+
+func (lf *File) open() error {
+	f, err := os.OpenFile(filepath.Join(lf.dir, fileName), os.O_CREATE|os.O_WRONLY|os.O_APPEND, 0o640)
+	if err != nil {
+		return err
+	}
+	st, err := f.Stat()
+	if err != nil {
+		f.Close()
+		return err
+	}
+	lf.f, lf.size, lf.opened = f, st.Size(), time.Now()
+	if st.Size() > 0 {
+		// Age an existing file from its last write, not from now, so a
+		// daemon that restarts often still rotates daily.
+		lf.opened = st.ModTime()
+	}
+	return nil
+}
+
+// Write appends p, rotating first if p would cross a limit. Safe for
+// concurrent use.
+func (lf *File) Write(p []byte) (int, error) {
+	lf.mu.Lock()
+	defer lf.mu.Unlock()
+	if lf.size > 0 && lf.due(int64(len(p))) {
+		if err := lf.rotate(); err != nil {
+			// Keep logging into the oversized file rather than losing lines.
+			fmt.Fprintf(os.Stderr, "logging: rotate: %v\n", err)
+		}
+	}
+	n, err := lf.f.Write(p)
+	lf.size += int64(n)
+	return n, err
+}
+
+func (lf *File) due(next int64) bool {
+	if lf.opts.MaxBytes > 0 && lf.size+next > lf.opts.MaxBytes {
+		return true
+	}
+	return lf.opts.MaxAge > 0 && time.Since(lf.opened) > lf.opts.MaxAge
+}
+
+func (lf *File) rotate() error {
+	rotated := filepath.Join(lf.dir, "daemon-"+time.Now().UTC().Format("20060102T150405")+".log")
+	if err := os.Rename(filepath.Join(lf.dir, fileName), rotated); err != nil {
+		return err
+	}
+	old := lf.f
+	if err := lf.open(); err != nil {
+		// Nothing to write to; put the old file back under its name.
+		_ = os.Rename(rotated, filepath.Join(lf.dir, fileName))
+		return err
+	}
+	old.Close()
+	go lf.compressAndPrune(rotated)
+	return nil
+}
+
+// compressAndPrune gzips every rotated-but-uncompressed file (just is
+// the one rotate produced, or "" on startup) and enforces retention.
+func (lf *File) compressAndPrune(just string) {
+	lf.pruneMu.Lock()
+	defer lf.pruneMu.Unlock()
+	plain, _ := filepath.Glob(filepath.Join(lf.dir, "daemon-*.log"))
+	if just != "" && !slices.Contains(plain, just) {
+		plain = append(plain, just)
+	}
+	for _, p := range plain {
+		if err := gzipFile(p); err != nil {
+			log.Printf("logging: compress %s: %v", p, err)
+		}
+	}
+	rotated, _ := filepath.Glob(filepath.Join(lf.dir, "daemon-*.log.gz"))
+	// Timestamped names sort oldest first.
+	sort.Strings(rotated)
+	for i, p := range rotated {
+		tooMany := lf.opts.Keep > 0 && i < len(rotated)-lf.opts.Keep
+		tooOld := false
+		if lf.opts.KeepFor > 0 {
+			if st, err := os.Stat(p); err == nil && time.Since(st.ModTime()) > lf.opts.KeepFor {
+				tooOld = true
+			}
+		}
+		if tooMany || tooOld {
+			_ = os.Remove(p)
+		}
+	}
+}
+
+func gzipFile(path string) error {
+	src, err := os.Open(path)
+	if err != nil {
+		return err
+	}
+	defer src.Close()
+	tmp := path + ".gz.tmp"
+	dst, err := os.OpenFile(tmp, os.O_CREATE|os.O_WRONLY|os.O_TRUNC, 0o640)
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp)
+	gz := gzip.NewWriter(dst)
+	if _, err := io.Copy(gz, src); err != nil {
+		dst.Close()
+		return err
+	}
+	if err := gz.Close(); err != nil {
+		dst.Close()
+		return err
+	}
+	if err := dst.Close(); err != nil {
+		return err
+	}
+	if err := os.Rename(tmp, path+".gz"); err != nil {
+		return err
+	}
+	return os.Remove(path)
+}
+
+// tailChunk is how far back Tail reads per step.
+const tailChunk = 64 << 10
+
+// Tail returns up to n of the last lines of the live log, oldest first.
+func (lf *File) Tail(n int) ([]string, error) {
+	f, err := os.Open(filepath.Join(lf.dir, fileName))
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+	st, err := f.Stat()
+	if err != nil {
+		return nil, err
+	}
+	// Read backwards until the buffer holds n complete lines (n+1
+	// newlines, counting the one ending the last line) or the start.
+	end := st.Size()
+	var buf []byte
+	for end > 0 && bytes.Count(buf, []byte("\n")) <= n {
+		start := max(end-tailChunk, 0)
+		chunk := make([]byte, end-start)
+		if _, err := f.ReadAt(chunk, start); err != nil && !errors.Is(err, io.EOF) {
+			return nil, err
+		}
+		buf = append(chunk, buf...)
+		end = start
+	}
+	lines := strings.Split(strings.TrimSuffix(string(buf), "\n"), "\n")
+	if end > 0 && len(lines) > 0 {
+		lines = lines[1:] // partial first line
+	}
+	if len(lines) > n {
+		lines = lines[len(lines)-n:]
+	}
+	if len(lines) == 1 && lines[0] == "" {
+		return []string{}, nil
+	}
+	return lines, nil
+}
+
+// Close closes the live file.
+func (lf *File) Close() error {
+	lf.mu.Lock()
+	defer lf.mu.Unlock()
+	return lf.f.Close()
+}
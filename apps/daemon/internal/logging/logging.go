@@ -0,0 +1,153 @@
+// Package logging sits under the standard library logger: every
+// log.Printf in the daemon is routed through it, so existing call sites
+// keep working while the output format and filtering become
+// configurable.
+//
+// A line's module is the word it starts with ("server abc: ...",
+// "proxy: ...", "docker.streamRawLines: ..." → server, proxy, docker).
+// Plain log.Printf lines are info; chatty diagnostics go through Debugf
+// with an explicit module and are dropped unless that module (or the
+// default) is at debug. Levels can be changed at runtime, so one
+// subsystem can be debugged on a production node without restarting it
+// or drowning in everyone else's output.
+//
+// Formats: "pretty" is the stdlib layout (`2006/01/02 15:04:05 msg`,
+// with a level tag for non-info lines); "json" is one object per line
+// with time, level, module and msg for Loki/ELK.
+package logging
+
+import (
+	"context"
+	"fmt"
+	"io"
+	"log"
+	"log/slog"
+	"os"
+	"strings"
+	"sync"
+	"time"
+	"unicode"
+)
+
+type settings struct {
+	out     io.Writer
+	json    *slog.Logger // nil in pretty mode
+	def     slog.Level
+	modules map[string]slog.Level
+}
+
+var (
+	mu  sync.RWMutex
+	cur = settings{out: os.Stderr, modules: map[string]slog.Level{}}
+)
+
+// Setup installs the bridge under the stdlib logger. format is "pretty"
+// (or empty) or "json"; level and modules are as in SetLevels.
+func Setup(w io.Writer, format, level string, moduleLevels map[string]string) error {
+	if err := SetLevels(level, moduleLevels); err != nil {
+		return err
+	}
+	var j *slog.Logger
+	switch format {
+	case "", "pretty":
+	case "json":
+		j = slog.New(slog.NewJSONHandler(w, &slog.HandlerOptions{Level: slog.LevelDebug}))
+	default:
+		return fmt.Errorf("log format %q: want pretty or json", format)
+	}
+	mu.Lock()
+	cur.out, cur.json = w, j
+	mu.Unlock()
+	log.SetFlags(0)
+	log.SetOutput(bridge{})
+	return nil
+}
+
+// SetLevels replaces the default level and every per-module override.
+// Levels are debug, info, warn or error; an empty default means info.
+func SetLevels(level string, moduleLevels map[string]string) error {
+	d := slog.LevelInfo
+	if level != "" {
+		if err := d.UnmarshalText([]byte(level)); err != nil {
+			return fmt.Errorf("log level %q: %w", level, err)
+		}
+	}
+	next := make(map[string]slog.Level, len(moduleLevels))
+	for m, l := range moduleLevels {
+		var lv slog.Level
+		if err := lv.UnmarshalText([]byte(l)); err != nil {
+			return fmt.Errorf("log level %q for %s: %w", l, m, err)
+		}
+		next[m] = lv
+	}
+	mu.Lock()
+	cur.def, cur.modules = d, next
+	mu.Unlock()
+	return nil
+}
+
+// Levels reports the current default level and per-module overrides.
+func Levels() (string, map[string]string) {
+	mu.RLock()
+	defer mu.RUnlock()
+	mods := make(map[string]string, len(cur.modules))
+	for m, l := range cur.modules {
+		mods[m] = strings.ToLower(l.String())
+	}
+	return strings.ToLower(cur.def.String()), mods
+}
+
+// Debugf logs a debug line for module when that module is at debug.
+func Debugf(module, format string, args ...any) {
+	if Enabled(module, slog.LevelDebug) {
+		emit(slog.LevelDebug, module, fmt.Sprintf(format, args...))
+	}
+}
+
+// Enabled reports whether module logs at level l. Lets callers skip
+// building expensive debug output.
+func Enabled(module string, l slog.Level) bool {
+	mu.RLock()
+	defer mu.RUnlock()
+	floor, ok := cur.modules[module]
+	if !ok {
+		floor = cur.def
+	}
+	return l >= floor
+}
+
+// bridge is the stdlib logger's writer: one Write per log call.
+type bridge struct{}
+
+func (bridge) Write(p []byte) (int, error) {
+	msg := strings.TrimSuffix(string(p), "\n")
+	module := moduleOf(msg)
+	if Enabled(module, slog.LevelInfo) {
+		emit(slog.LevelInfo, module, msg)
+	}
+	return len(p), nil
+}
+
+func emit(l slog.Level, module, msg string) {
+	mu.RLock()
+	j, w := cur.json, cur.out
+	mu.RUnlock()
+	if j != nil {
+		j.Log(context.Background(), l, msg, "module", module)
+		return
+	}
+	tag := ""
+	if l != slog.LevelInfo {
+		tag = l.String() + " "
+	}
+	_, _ = fmt.Fprintf(w, "%s %s%s\n", time.Now().Format("2006/01/02 15:04:05"), tag, msg)
+}
+
+// moduleOf is the leading run of letters: "server abc: x" → "server".
+func moduleOf(msg string) string {
+	end := strings.IndexFunc(msg, func(r rune) bool { return !unicode.IsLetter(r) })
+	if end < 0 {
+		end = len(msg)
+	}
+	return strings.ToLower(msg[:end])
+}
@@ -0,0 +1,194 @@
+// Package macros stores named console command sequences per server.
+// The panel registers them (e.g. "restart-warning" → say, wait, say,
+// stop) and they are invoked by name from the panel or a schedule task;
+// the router runs each invocation as a cancellable job.
+//
+// Macros live in `<dataDir>/macros/<serverId>.json`, one file per
+// server, written atomically so a crash mid-save keeps the old set.
+package macros
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"regexp"
+	"sort"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Limits keep a macro from turning into a long-running script host.
+const (
+	MaxSteps = 50
+	MaxDelay = 10 * time.Minute
+)
+
+// Step is one console line, sent after waiting DelayMs.
+type Step struct {
+	Command string `json:"command"`
+	DelayMs int64  `json:"delayMs"`
+}
+
+// Delay returns the pre-send wait as a duration.
+func (s Step) Delay() time.Duration { return time.Duration(s.DelayMs) * time.Millisecond }
+
+// Macro is a named sequence of steps.
+type Macro struct {
+	Name  string `json:"name"`
+	Steps []Step `json:"steps"`
+}
+
+// TotalDelay is the sum of every step's delay — the macro's minimum
+// runtime.
+func (m Macro) TotalDelay() time.Duration {
+	var d time.Duration
+	for _, s := range m.Steps {
+		d += s.Delay()
+	}
+	return d
+}
+
+var nameRE = regexp.MustCompile(`^[a-z0-9][a-z0-9_-]{0,63}$`)
+
+// Validate checks the macro against the naming rules and limits.
+func (m Macro) Validate() error {
+	if !nameRE.MatchString(m.Name) {
+		return errors.New("name must be 1-64 lowercase letters, digits, '-' or '_'")
+	}
+	if len(m.Steps) == 0 || len(m.Steps) > MaxSteps {
+		return fmt.Errorf("a macro needs between 1 and %d steps", MaxSteps)
+	}
+	for i, s := range m.Steps {
+		if strings.TrimSpace(s.Command) == "" {
+			return fmt.Errorf("step %d: command is empty", i+1)
+		}
+		if strings.ContainsAny(s.Command, "\r\n") {
+			return fmt.Errorf("step %d: command must be a single line", i+1)
+		}
+		if s.DelayMs < 0 || s.Delay() > MaxDelay {
+			return fmt.Errorf("step %d: delay must be between 0 and %s", i+1, MaxDelay)
+		}
+	}
+	return nil
+}
+
+// ErrNotFound is returned when a macro name isn't registered.
+var ErrNotFound = errors.New("macro not found")
+
+// Store is the per-daemon macro registry. Goroutine-safe.
+type Store struct {
+	dir string
+
+	mu sync.Mutex
+}
+
+func New(dataDir string) *Store {
+	return &Store{dir: filepath.Join(dataDir, "macros")}
+}
+
+func (s *Store) path(serverID string) string {
+	return filepath.Join(s.dir, serverID+".json")
+}
+
+// load reads the server's macro file. A missing file is an empty set.
+// Caller holds s.mu.
+func (s *Store) load(serverID string) (map[string]Macro, error) {
+	raw, err := os.ReadFile(s.path(serverID))
+	if os.IsNotExist(err) {
+		return map[string]Macro{}, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	out := map[string]Macro{}
+	if err := json.Unmarshal(raw, &out); err != nil {
+		return nil, fmt.Errorf("parse macros for %s: %w", serverID, err)
+	}
+	return out, nil
+}
+
+// save writes the set atomically (temp file + rename). Caller holds s.mu.
+func (s *Store) save(serverID string, set map[string]Macro) error {
+	if err := os.MkdirAll(s.dir, 0o755); err != nil {
+		return err
+	}
+	if len(set) == 0 {
+		if err := os.Remove(s.path(serverID)); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+		return nil
+	}
+	raw, err := json.Marshal(set)
+	if err != nil {
+		return err
+	}
+	tmp := s.path(serverID) + ".tmp"
+	if err := os.WriteFile(tmp, raw, 0o644); err != nil {
+		return err
+	}
+	return os.Rename(tmp, s.path(serverID))
+}
+
+// List returns every macro for serverID sorted by name.
+func (s *Store) List(serverID string) ([]Macro, error) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	set, err := s.load(serverID)
+	if err != nil {
+		return nil, err
+	}
+	out := make([]Macro, 0, len(set))
+	for _, m := range set {
+		out = append(out, m)
+	}
+	sort.Slice(out, func(i, j int) bool { return out[i].Name < out[j].Name })
+	return out, nil
+}
+
+// Get returns one macro or ErrNotFound.
+func (s *Store) Get(serverID, name string) (Macro, error) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	set, err := s.load(serverID)
+	if err != nil {
+		return Macro{}, err
+	}
+	m, ok := set[name]
+	if !ok {
+		return Macro{}, ErrNotFound
+	}
+	return m, nil
+}
+
+// Put validates and creates or replaces a macro.
+func (s *Store) Put(serverID string, m Macro) error {
+	if err := m.Validate(); err != nil {
+		return err
+	}
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	set, err := s.load(serverID)
+	if err != nil {
+		return err
+	}
+	set[m.Name] = m
+	return s.save(serverID, set)
+}
+
+// Delete removes a macro. Returns ErrNotFound if it wasn't registered.
+func (s *Store) Delete(serverID, name string) error {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	set, err := s.load(serverID)
+	if err != nil {
+		return err
+	}
+	if _, ok := set[name]; !ok {
+		return ErrNotFound
+	}
+	delete(set, name)
+	return s.save(serverID, set)
+}
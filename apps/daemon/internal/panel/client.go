@@ -13,9 +13,13 @@ import (
 	"encoding/json"
 	"fmt"
 	"io"
+	"math"
 	"net/http"
 	"strings"
 	"time"
+
+	"github.com/stellarstack/daemon/internal/backup"
+	"github.com/stellarstack/daemon/internal/capability"
 )
 
 // Client posts daemon-originated events to the API. Authentication is
@@ -84,7 +88,12 @@ type ServerConfig struct {
 	Stop            StopConfig        `json:"stop"`
 	MemoryLimitMb   int64             `json:"memoryLimitMb"`
 	CPULimitPercent int64             `json:"cpuLimitPercent"`
+	DiskLimitMb     int64             `json:"diskLimitMb"`
+	HostNetwork     bool              `json:"hostNetwork"`
 	Ports           []PortMapping     `json:"ports"`
+	// Blueprint memory overhead policy. nil → container limit equals
+	// MemoryLimitMb.
+	MemoryOverhead *MemoryOverhead `json:"memoryOverhead"`
 	// Console patterns the daemon scans for to detect the application-
 	// level "ready" signal. On match the server flips Starting →
 	// Running. Empty array → fall back to "running once Docker reports
@@ -95,6 +104,76 @@ type ServerConfig struct {
 	// Each entry's `patches` map values can reference {{ENV_VAR}} for
 	// substitution against the resolved environment.
 	ConfigFiles []ConfigFile `json:"configFiles"`
+	// Blueprint sidecar containers started and stopped with the server.
+	Sidecars []Sidecar `json:"sidecars"`
+	// Blueprint services (multi-container stacks), started before the
+	// main container in dependency order.
+	Services []Service `json:"services"`
+	// Blueprint player-administration command templates keyed by
+	// action ("whitelist.add", "kick", …).
+	PlayerCommands map[string]string `json:"playerCommands"`
+	// Idle auto-shutdown policy; nil when the server has none.
+	Idle *IdlePolicy `json:"idle"`
+	// Console sizes; nil or zero fields use the node's defaults.
+	Console *ConsoleConfig `json:"console"`
+	// Extra /etc/hosts entries for the main container, resolved by the
+	// panel (a name pointing at another server's node, say).
+	ExtraHosts []ExtraHost `json:"extraHosts"`
+	// File manager and SFTP path lists, blueprint and server merged;
+	// nil when the server has none.
+	Files *FileRules `json:"files"`
+}
+
+// FileRules are a server's denylist and hidden patterns.
+type FileRules struct {
+	Denylist []string `json:"denylist"`
+	Hidden   []string `json:"hidden"`
+}
+
+// ExtraHost is one /etc/hosts entry. IP may also be Docker's
+// "host-gateway".
+type ExtraHost struct {
+	Hostname string `json:"hostname"`
+	IP       string `json:"ip"`
+}
+
+// ConsoleConfig is the panel's per-server console sizing.
+type ConsoleConfig struct {
+	HistoryLines int `json:"historyLines"`
+	Buffer       int `json:"buffer"`
+}
+
+// IdlePolicy mirrors the panel's per-server idle settings.
+type IdlePolicy struct {
+	Minutes       int      `json:"minutes"`
+	Signals       []string `json:"signals"` // "players", "console", "cpu"
+	CPUPercent    float64  `json:"cpuPercent"`
+	StartOnDemand bool     `json:"startOnDemand"`
+	WakeMessage   string   `json:"wakeMessage"`
+}
+
+type Service struct {
+	Name          string            `json:"name"`
+	Image         string            `json:"image"`
+	Command       []string          `json:"command"`
+	Environment   map[string]string `json:"environment"`
+	MemoryLimitMb int64             `json:"memoryLimitMb"`
+	DependsOn     []string          `json:"dependsOn"`
+	Volumes       []VolumeMount     `json:"volumes"`
+	Console       bool              `json:"console"`
+}
+
+type VolumeMount struct {
+	Name string `json:"name"`
+	Path string `json:"path"`
+}
+
+type Sidecar struct {
+	Name          string            `json:"name"`
+	Image         string            `json:"image"`
+	Command       []string          `json:"command"`
+	Environment   map[string]string `json:"environment"`
+	MemoryLimitMb int64             `json:"memoryLimitMb"`
 }
 
 type DonePattern struct {
@@ -109,17 +188,47 @@ type ConfigFile struct {
 	Patches map[string]string `json:"patches"`
 }
 
+// MemoryOverhead is the blueprint's extra container memory on top of
+// the server limit: `absolute` adds Mb, `percent` adds Percent of it.
+type MemoryOverhead struct {
+	Type    string  `json:"type"`
+	Mb      int64   `json:"mb"`
+	Percent float64 `json:"percent"`
+}
+
+// ResolveMb returns the overhead in MB for a server limited to limitMb.
+// Unknown types and unlimited servers (limitMb <= 0) get none.
+func (o *MemoryOverhead) ResolveMb(limitMb int64) int64 {
+	if o == nil || limitMb <= 0 {
+		return 0
+	}
+	switch o.Type {
+	case "absolute":
+		if o.Mb > 0 {
+			return o.Mb
+		}
+	case "percent":
+		if o.Percent > 0 {
+			return int64(math.Ceil(float64(limitMb) * o.Percent / 100))
+		}
+	}
+	return 0
+}
+
 // StopConfig matches environment.StopConfig on the wire.
 type StopConfig struct {
-	Type  string `json:"type"`
-	Value string `json:"value"`
+	Type      string `json:"type"`
+	Value     string `json:"value"`
+	TimeoutMs int64  `json:"timeoutMs"`
 }
 
 // PortMapping matches docker.PortMapping on the wire.
 type PortMapping struct {
 	HostIP        string `json:"hostIp"`
 	HostPort      int    `json:"hostPort"`
+	HostPortEnd   int    `json:"hostPortEnd"`
 	ContainerPort int    `json:"containerPort"`
+	Protocol      string `json:"protocol"`
 }
 
 // FetchServerConfig pulls the API's authoritative server runtime
@@ -149,9 +258,112 @@ func (c *Client) FetchServerConfig(ctx context.Context, serverUUID string) (*Ser
 
 // Heartbeat tells the API "this node is alive". POSTed by the daemon
 // on startup and on a 30s ticker so the admin nodes page can render an
-// online/offline pill backed by a fresh `connected_at` row column.
-func (c *Client) Heartbeat(ctx context.Context) error {
-	req, err := c.signedRequest(ctx, http.MethodPost, "/api/remote/heartbeat", nil)
+// online/offline pill backed by a fresh `connected_at` row column. The
+// capability report rides along so the panel always gates features on
+// the build that is actually running, including right after an upgrade.
+// Backup storage usage, the transfer policy, the build's version, the
+// host's resources and its health ride along too.
+//
+// skew is the panel's clock minus this node's, read off the answer's
+// Date header (zero without one). It is set even when the panel turns
+// the heartbeat away, which is what a drifting clock looks like.
+func (c *Client) Heartbeat(ctx context.Context, hb HeartbeatReport) (skew time.Duration, err error) {
+	body, err := json.Marshal(hb)
+	if err != nil {
+		return 0, err
+	}
+	req, err := c.signedRequest(ctx, http.MethodPost, "/api/remote/heartbeat", body)
+	if err != nil {
+		return 0, err
+	}
+	sent := time.Now()
+	resp, err := c.http.Do(req)
+	if err != nil {
+		return 0, err
+	}
+	defer resp.Body.Close()
+	skew = clockSkew(resp.Header.Get("Date"), sent, time.Now())
+	if resp.StatusCode/100 != 2 {
+		raw, _ := io.ReadAll(io.LimitReader(resp.Body, 1024))
+		return skew, fmt.Errorf("panel heartbeat %s: %s", resp.Status, string(raw))
+	}
+	return skew, nil
+}
+
+// clockSkew compares date, the panel's clock, with the middle of the
+// round trip. Date has whole seconds, so a second either way is noise.
+func clockSkew(date string, sent, received time.Time) time.Duration {
+	at, err := http.ParseTime(date)
+	if err != nil {
+		return 0
+	}
+	d := at.Sub(sent.Add(received.Sub(sent) / 2))
+	if d.Abs() <= time.Second {
+		return 0
+	}
+	return d.Round(time.Second)
+}
+
+// HeartbeatReport is the heartbeat body. BackupUsage is nil when it
+// couldn't be measured.
+type HeartbeatReport struct {
+	Version      string            `json:"version"`
+	Capabilities capability.Report `json:"capabilities"`
+	BackupUsage  *backup.Usage     `json:"backupUsage,omitempty"`
+	Transfers    TransferPolicy    `json:"transfers"`
+	Resources    Resources         `json:"resources"`
+	Health       Health            `json:"health"`
+}
+
+// Resources are the host's totals as the daemon sees them, for the
+// panel to compare with what the node row was created with. Zero when
+// unknown.
+type Resources struct {
+	CPUs       int   `json:"cpus"`
+	MemoryMb   int64 `json:"memoryMb"`
+	DiskMb     int64 `json:"diskMb"`
+	DiskFreeMb int64 `json:"diskFreeMb"`
+}
+
+// Health is what the daemon needs from the host to run servers. The
+// panel adds the clock skew it measures itself.
+type Health struct {
+	Docker bool `json:"docker"`
+}
+
+// TransferPolicy is the node's transfer throttling, which the panel
+// needs to hold queued transfers until the window opens. OpensAt is set
+// only while the window is closed.
+type TransferPolicy struct {
+	BandwidthMbps int        `json:"bandwidthMbps"`
+	Window        string     `json:"window,omitempty"`
+	WindowOpen    bool       `json:"windowOpen"`
+	OpensAt       *time.Time `json:"opensAt,omitempty"`
+}
+
+// AllocationUsage is the daemon's view of which ports are really bound.
+// Running lists every server whose main container is up, so the panel
+// can tell "assigned but not bound" apart from "server stopped".
+type AllocationUsage struct {
+	Running  []string            `json:"running"`
+	Bindings []AllocationBinding `json:"bindings"`
+}
+
+// AllocationBinding is one host port published by a server container.
+type AllocationBinding struct {
+	ServerID string `json:"serverId"`
+	IP       string `json:"ip"`
+	Port     int    `json:"port"`
+}
+
+// PushAllocationUsage reports the current bindings. The API reconciles
+// them against the node's allocation pool.
+func (c *Client) PushAllocationUsage(ctx context.Context, usage AllocationUsage) error {
+	body, err := json.Marshal(usage)
+	if err != nil {
+		return err
+	}
+	req, err := c.signedRequest(ctx, http.MethodPost, "/api/remote/allocations/usage", body)
 	if err != nil {
 		return err
 	}
@@ -162,7 +374,7 @@ func (c *Client) Heartbeat(ctx context.Context) error {
 	defer resp.Body.Close()
 	if resp.StatusCode/100 != 2 {
 		raw, _ := io.ReadAll(io.LimitReader(resp.Body, 1024))
-		return fmt.Errorf("panel heartbeat %s: %s", resp.Status, string(raw))
+		return fmt.Errorf("panel push allocation usage %s: %s", resp.Status, string(raw))
 	}
 	return nil
 }
@@ -204,6 +416,33 @@ func (c *Client) PushAudit(ctx context.Context, serverUUID, actorID, action stri
 	return nil
 }
 
+// PushNodeAudit posts an audit-log entry about the node itself rather
+// than one of its servers, e.g. Docker going away and coming back.
+func (c *Client) PushNodeAudit(ctx context.Context, action string, metadata map[string]any) error {
+	payload := map[string]any{"action": action}
+	if metadata != nil {
+		payload["metadata"] = metadata
+	}
+	body, err := json.Marshal(payload)
+	if err != nil {
+		return err
+	}
+	req, err := c.signedRequest(ctx, http.MethodPost, "/api/remote/audit", body)
+	if err != nil {
+		return err
+	}
+	resp, err := c.http.Do(req)
+	if err != nil {
+		return err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode/100 != 2 {
+		raw, _ := io.ReadAll(io.LimitReader(resp.Body, 1024))
+		return fmt.Errorf("panel push node audit %s: %s", resp.Status, string(raw))
+	}
+	return nil
+}
+
 func (c *Client) signedRequest(ctx context.Context, method, path string, body []byte) (*http.Request, error) {
 	url := c.baseURL + path
 	var rdr io.Reader
@@ -0,0 +1,188 @@
+// Package pathlock keeps the per-server map of paths that long
+// operations are using, so two of them can't trample one tree: a backup
+// reading the world while an extract writes into it, a delete under a
+// directory being compressed, two writes to one file. A lock covers its
+// path and everything below it. Read locks share; a write lock
+// conflicts with any other lock on an overlapping path, so locking "/"
+// for write (a restore, an inbound transfer) excludes everything else
+// on the server.
+package pathlock
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"path"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Mode is a lock's intent.
+type Mode int
+
+const (
+	Read Mode = iota
+	Write
+)
+
+func (m Mode) String() string {
+	if m == Write {
+		return "write"
+	}
+	return "read"
+}
+
+// ErrLocked matches every *ConflictError.
+var ErrLocked = errors.New("path is locked")
+
+// ConflictError names the lock in the way.
+type ConflictError struct {
+	Path  string
+	Mode  Mode
+	Op    string
+	Since time.Time
+}
+
+func (e *ConflictError) Error() string {
+	return fmt.Sprintf("%s is locked for %s by %s (since %s)", e.Path, e.Mode, e.Op, e.Since.Format(time.RFC3339))
+}
+
+func (e *ConflictError) Is(target error) bool { return target == ErrLocked }
+
+// Want is one path an operation needs, relative to the server root.
+type Want struct {
+	Path string
+	Mode Mode
+}
+
+// R and W build Wants.
+func R(p string) Want { return Want{Path: p, Mode: Read} }
+func W(p string) Want { return Want{Path: p, Mode: Write} }
+
+type held struct {
+	path  string
+	mode  Mode
+	op    string
+	since time.Time
+}
+
+// Map is the daemon's lock table. The zero value is ready to use.
+type Map struct {
+	mu      sync.Mutex
+	servers map[string][]*held
+	changed chan struct{} // closed and replaced on every release
+}
+
+// Lock is a granted set of Wants. Release is idempotent.
+type Lock struct {
+	m        *Map
+	serverID string
+	entries  []*held
+	once     sync.Once
+}
+
+// Check reports the first conflict wants would hit right now without
+// taking anything, for handlers that answer before their work starts.
+func (m *Map) Check(serverID string, wants ...Want) error {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	return m.conflictLocked(serverID, wants)
+}
+
+// Acquire takes every want for op at once, waiting up to wait for the
+// conflicting locks to go. On timeout (or ctx ending) it returns the
+// last *ConflictError seen.
+func (m *Map) Acquire(ctx context.Context, serverID, op string, wait time.Duration, wants ...Want) (*Lock, error) {
+	var deadline <-chan time.Time
+	if wait > 0 {
+		t := time.NewTimer(wait)
+		defer t.Stop()
+		deadline = t.C
+	}
+	for {
+		m.mu.Lock()
+		err := m.conflictLocked(serverID, wants)
+		if err == nil {
+			l := &Lock{m: m, serverID: serverID}
+			now := time.Now()
+			for _, w := range wants {
+				l.entries = append(l.entries, &held{path: clean(w.Path), mode: w.Mode, op: op, since: now})
+			}
+			if m.servers == nil {
+				m.servers = map[string][]*held{}
+			}
+			m.servers[serverID] = append(m.servers[serverID], l.entries...)
+			m.mu.Unlock()
+			return l, nil
+		}
+		if m.changed == nil {
+			m.changed = make(chan struct{})
+		}
+		changed := m.changed
+		m.mu.Unlock()
+		if deadline == nil {
+			return nil, err
+		}
+		select {
+		case <-changed:
+		case <-deadline:
+			return nil, err
+		case <-ctx.Done():
+			return nil, err
+		}
+	}
+}
+
+// Release gives the paths back.
+func (l *Lock) Release() {
+	if l == nil {
+		return
+	}
+	l.once.Do(func() {
+		m := l.m
+		m.mu.Lock()
+		defer m.mu.Unlock()
+		kept := m.servers[l.serverID][:0]
+		for _, h := range m.servers[l.serverID] {
+			mine := false
+			for _, e := range l.entries {
+				if h == e {
+					mine = true
+					break
+				}
+			}
+			if !mine {
+				kept = append(kept, h)
+			}
+		}
+		if len(kept) == 0 {
+			delete(m.servers, l.serverID)
+		} else {
+			m.servers[l.serverID] = kept
+		}
+		if m.changed != nil {
+			close(m.changed)
+			m.changed = nil
+		}
+	})
+}
+
+func (m *Map) conflictLocked(serverID string, wants []Want) error {
+	for _, w := range wants {
+		p := clean(w.Path)
+		for _, h := range m.servers[serverID] {
+			if (w.Mode == Write || h.mode == Write) && overlaps(p, h.path) {
+				return &ConflictError{Path: h.path, Mode: h.mode, Op: h.op, Since: h.since}
+			}
+		}
+	}
+	return nil
+}
+
+func clean(p string) string { return path.Clean("/" + p) }
+
+// overlaps reports whether one of a and b is the other or below it.
+func overlaps(a, b string) bool {
+	return a == b || a == "/" || b == "/" || strings.HasPrefix(a, b+"/") || strings.HasPrefix(b, a+"/")
+}
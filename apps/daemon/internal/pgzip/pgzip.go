@@ -0,0 +1,185 @@
+// Package pgzip is a parallel gzip writer for backups, transfers and
+// archive creation, where single-threaded deflate is the bottleneck on
+// multi-GB trees. Input is cut into fixed-size blocks, each block is
+// compressed on its own goroutine as a complete gzip member, and the
+// members are written out in order. Concatenated members are a valid
+// gzip stream (RFC 1952 §2.2): compress/gzip, gunzip and tar all read
+// them as one file, so nothing on the read side changes.
+//
+// Independent members cost a little ratio (no dictionary carried across
+// block boundaries, ~20 bytes of header per block); at 1 MiB blocks
+// that is well under 1%.
+//
+// Compression goroutines draw from one process-wide thread budget, so
+// three concurrent backups on an 8-core node share 8 threads rather
+// than spawning 24 and starving the game servers.
+package pgzip
+
+import (
+	"bytes"
+	"compress/gzip"
+	"errors"
+	"io"
+	"runtime"
+	"sync"
+)
+
+// BlockSize is the uncompressed size of each gzip member.
+const BlockSize = 1 << 20
+
+var (
+	threadsMu sync.Mutex
+	threads   = make(chan struct{}, runtime.NumCPU())
+)
+
+// SetThreads sizes the shared compression budget. n <= 0 means one per
+// CPU. Call once at startup, before any Writer is created.
+func SetThreads(n int) {
+	if n <= 0 {
+		n = runtime.NumCPU()
+	}
+	threadsMu.Lock()
+	threads = make(chan struct{}, n)
+	threadsMu.Unlock()
+}
+
+func budget() chan struct{} {
+	threadsMu.Lock()
+	defer threadsMu.Unlock()
+	return threads
+}
+
+var errClosed = errors.New("pgzip: write to closed writer")
+
+type block struct {
+	data  []byte
+	out   bytes.Buffer
+	err   error
+	ready chan struct{}
+}
+
+// Writer is an io.WriteCloser producing a multi-member gzip stream.
+// Not safe for concurrent use, like gzip.Writer.
+type Writer struct {
+	w       io.Writer
+	level   int
+	threads chan struct{}
+	cur     []byte
+	queue   chan *block
+	done    chan struct{}
+	emitted bool
+	closed  bool
+
+	mu  sync.Mutex
+	err error // first compression or write failure, set by drain
+}
+
+// NewWriter returns a Writer at the default compression level.
+func NewWriter(w io.Writer) *Writer {
+	z, _ := NewWriterLevel(w, gzip.DefaultCompression)
+	return z
+}
+
+// NewWriterLevel is NewWriter with an explicit gzip level.
+func NewWriterLevel(w io.Writer, level int) (*Writer, error) {
+	if _, err := gzip.NewWriterLevel(io.Discard, level); err != nil {
+		return nil, err
+	}
+	t := budget()
+	z := &Writer{
+		w:       w,
+		level:   level,
+		threads: t,
+		cur:     make([]byte, 0, BlockSize),
+		// Bounded so a slow destination backpressures the caller
+		// instead of buffering the whole input in memory.
+		queue: make(chan *block, 2*cap(t)),
+		done:  make(chan struct{}),
+	}
+	go z.drain()
+	return z, nil
+}
+
+// Write buffers p, handing each full block to a compression goroutine.
+func (z *Writer) Write(p []byte) (int, error) {
+	if z.closed {
+		return 0, errClosed
+	}
+	if err := z.failed(); err != nil {
+		return 0, err
+	}
+	n := 0
+	for len(p) > 0 {
+		take := min(BlockSize-len(z.cur), len(p))
+		z.cur = append(z.cur, p[:take]...)
+		p = p[take:]
+		n += take
+		if len(z.cur) == BlockSize {
+			z.dispatch()
+		}
+	}
+	return n, nil
+}
+
+// Close compresses the final block, waits for every member to be
+// written and returns the first error seen. It does not close the
+// underlying writer.
+func (z *Writer) Close() error {
+	if z.closed {
+		return z.failed()
+	}
+	z.closed = true
+	// An empty input still has to produce one (empty) member to be a
+	// valid gzip file.
+	if len(z.cur) > 0 || !z.emitted {
+		z.dispatch()
+	}
+	z.cur = nil
+	close(z.queue)
+	<-z.done
+	return z.failed()
+}
+
+func (z *Writer) dispatch() {
+	b := &block{data: z.cur, ready: make(chan struct{})}
+	z.cur = make([]byte, 0, BlockSize)
+	z.emitted = true
+	z.threads <- struct{}{}
+	go func() {
+		defer func() { <-z.threads }()
+		defer close(b.ready)
+		gw, _ := gzip.NewWriterLevel(&b.out, z.level)
+		if _, err := gw.Write(b.data); err != nil {
+			b.err = err
+			return
+		}
+		b.err = gw.Close()
+	}()
+	z.queue <- b
+}
+
+// drain writes finished members in submission order.
+func (z *Writer) drain() {
+	defer close(z.done)
+	for b := range z.queue {
+		<-b.ready
+		if z.failed() != nil {
+			continue
+		}
+		err := b.err
+		if err == nil {
+			_, err = z.w.Write(b.out.Bytes())
+		}
+		if err != nil {
+			z.mu.Lock()
+			z.err = err
+			z.mu.Unlock()
+		}
+	}
+}
+
+func (z *Writer) failed() error {
+	z.mu.Lock()
+	defer z.mu.Unlock()
+	return z.err
+}
@@ -0,0 +1,350 @@
+// Package proxy is an optional TCP multiplexer that puts several
+// servers behind one public port. Each connection is routed on the
+// hostname the client asked for — the TLS SNI for TLS clients, or the
+// server address in the Minecraft handshake — to the backend the panel
+// mapped that hostname to. Bytes are spliced untouched after routing,
+// so the backend sees exactly what the client sent.
+//
+// Rules are pushed by the panel, swapped in atomically (connections in
+// flight keep their backend) and persisted to
+// `<dataDir>/proxy/rules.json` so a daemon restart routes immediately.
+package proxy
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"log"
+	"net"
+	"os"
+	"path/filepath"
+	"sort"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"time"
+)
+
+const (
+	// handshakeTimeout bounds how long a client may take to send the
+	// bytes we route on; idle sockets would otherwise pile up.
+	handshakeTimeout = 10 * time.Second
+	dialTimeout      = 5 * time.Second
+)
+
+// Rule maps a hostname to a backend address. Host is matched case-
+// insensitively; a leading "*." matches any subdomain and "*" alone is
+// the fallback for clients that sent no (or an unknown) hostname.
+type Rule struct {
+	Host     string `json:"host"`
+	Backend  string `json:"backend"`
+	ServerID string `json:"serverId,omitempty"`
+}
+
+// BackendStats are the per-backend counters reported to the panel.
+type BackendStats struct {
+	Backend    string `json:"backend"`
+	ServerID   string `json:"serverId,omitempty"`
+	Active     int64  `json:"active"`
+	Total      uint64 `json:"total"`
+	DialFailed uint64 `json:"dialFailed"`
+	BytesIn    uint64 `json:"bytesIn"`
+	BytesOut   uint64 `json:"bytesOut"`
+}
+
+// Stats is the proxy-wide report.
+type Stats struct {
+	Listening string         `json:"listening"`
+	Rules     int            `json:"rules"`
+	Unrouted  uint64         `json:"unrouted"`
+	Backends  []BackendStats `json:"backends"`
+}
+
+type counters struct {
+	serverID   string
+	active     atomic.Int64
+	total      atomic.Uint64
+	dialFailed atomic.Uint64
+	bytesIn    atomic.Uint64
+	bytesOut   atomic.Uint64
+}
+
+type table struct {
+	rules    []Rule
+	exact    map[string]Rule
+	wildcard []Rule // "*.suffix", longest suffix first
+	fallback *Rule
+}
+
+// Proxy is the router plus its rule table. Goroutine-safe.
+type Proxy struct {
+	path string
+
+	table atomic.Pointer[table]
+
+	mu        sync.Mutex
+	listening string
+	backends  map[string]*counters
+
+	unrouted atomic.Uint64
+}
+
+// New loads persisted rules from dataDir. A missing or unreadable file
+// starts with an empty table.
+func New(dataDir string) *Proxy {
+	p := &Proxy{
+		path:     filepath.Join(dataDir, "proxy", "rules.json"),
+		backends: map[string]*counters{},
+	}
+	p.table.Store(buildTable(nil))
+	raw, err := os.ReadFile(p.path)
+	if err != nil {
+		return p
+	}
+	var rules []Rule
+	if err := json.Unmarshal(raw, &rules); err != nil {
+		log.Printf("proxy: ignoring %s: %v", p.path, err)
+		return p
+	}
+	p.install(rules)
+	return p
+}
+
+// Validate checks a rule set before it's installed.
+func Validate(rules []Rule) error {
+	seen := map[string]bool{}
+	for i, r := range rules {
+		host := strings.ToLower(strings.TrimSpace(r.Host))
+		if host == "" {
+			return fmt.Errorf("rule %d: host is required", i+1)
+		}
+		if seen[host] {
+			return fmt.Errorf("rule %d: duplicate host %q", i+1, r.Host)
+		}
+		seen[host] = true
+		if _, _, err := net.SplitHostPort(r.Backend); err != nil {
+			return fmt.Errorf("rule %d: backend must be host:port", i+1)
+		}
+	}
+	return nil
+}
+
+// SetRules validates, persists and installs a new rule set.
+func (p *Proxy) SetRules(rules []Rule) error {
+	if err := Validate(rules); err != nil {
+		return err
+	}
+	if err := p.save(rules); err != nil {
+		return err
+	}
+	p.install(rules)
+	return nil
+}
+
+// Rules returns the installed rule set.
+func (p *Proxy) Rules() []Rule {
+	return append([]Rule{}, p.table.Load().rules...)
+}
+
+func (p *Proxy) install(rules []Rule) {
+	t := buildTable(rules)
+	p.mu.Lock()
+	live := map[string]bool{}
+	for _, r := range t.rules {
+		live[r.Backend] = true
+		if c, ok := p.backends[r.Backend]; ok {
+			c.serverID = r.ServerID
+		} else {
+			p.backends[r.Backend] = &counters{serverID: r.ServerID}
+		}
+	}
+	// Drop counters for backends no rule points at any more, unless
+	// connections are still open to them.
+	for b, c := range p.backends {
+		if !live[b] && c.active.Load() == 0 {
+			delete(p.backends, b)
+		}
+	}
+	p.mu.Unlock()
+	p.table.Store(t)
+}
+
+func (p *Proxy) save(rules []Rule) error {
+	if err := os.MkdirAll(filepath.Dir(p.path), 0o755); err != nil {
+		return err
+	}
+	raw, err := json.Marshal(rules)
+	if err != nil {
+		return err
+	}
+	tmp := p.path + ".tmp"
+	if err := os.WriteFile(tmp, raw, 0o644); err != nil {
+		return err
+	}
+	return os.Rename(tmp, p.path)
+}
+
+func buildTable(rules []Rule) *table {
+	t := &table{rules: rules, exact: map[string]Rule{}}
+	for _, r := range rules {
+		host := strings.ToLower(strings.TrimSpace(r.Host))
+		switch {
+		case host == "*":
+			rc := r
+			t.fallback = &rc
+		case strings.HasPrefix(host, "*."):
+			r.Host = host[1:] // keep the dot: ".example.com"
+			t.wildcard = append(t.wildcard, r)
+		default:
+			t.exact[host] = r
+		}
+	}
+	sort.Slice(t.wildcard, func(i, j int) bool { return len(t.wildcard[i].Host) > len(t.wildcard[j].Host) })
+	return t
+}
+
+func (t *table) match(host string) (Rule, bool) {
+	if r, ok := t.exact[host]; ok {
+		return r, true
+	}
+	for _, r := range t.wildcard {
+		if strings.HasSuffix(host, r.Host) {
+			return r, true
+		}
+	}
+	if t.fallback != nil {
+		return *t.fallback, true
+	}
+	return Rule{}, false
+}
+
+// Stats snapshots the counters.
+func (p *Proxy) Stats() Stats {
+	p.mu.Lock()
+	defer p.mu.Unlock()
+	out := Stats{
+		Listening: p.listening,
+		Rules:     len(p.table.Load().rules),
+		Unrouted:  p.unrouted.Load(),
+		Backends:  make([]BackendStats, 0, len(p.backends)),
+	}
+	for b, c := range p.backends {
+		out.Backends = append(out.Backends, BackendStats{
+			Backend:    b,
+			ServerID:   c.serverID,
+			Active:     c.active.Load(),
+			Total:      c.total.Load(),
+			DialFailed: c.dialFailed.Load(),
+			BytesIn:    c.bytesIn.Load(),
+			BytesOut:   c.bytesOut.Load(),
+		})
+	}
+	sort.Slice(out.Backends, func(i, j int) bool { return out.Backends[i].Backend < out.Backends[j].Backend })
+	return out
+}
+
+func (p *Proxy) countersFor(r Rule) *counters {
+	p.mu.Lock()
+	defer p.mu.Unlock()
+	c, ok := p.backends[r.Backend]
+	if !ok {
+		c = &counters{serverID: r.ServerID}
+		p.backends[r.Backend] = c
+	}
+	return c
+}
+
+// ListenAndServe accepts on addr until the listener fails.
+func (p *Proxy) ListenAndServe(addr string) error {
+	l, err := net.Listen("tcp", addr)
+	if err != nil {
+		return err
+	}
+	p.mu.Lock()
+	p.listening = l.Addr().String()
+	p.mu.Unlock()
+	log.Printf("proxy: listening on %s", addr)
+	for {
+		conn, err := l.Accept()
+		if err != nil {
+			if errors.Is(err, net.ErrClosed) {
+				return nil
+			}
+			return err
+		}
+		go p.handle(conn)
+	}
+}
+
+func (p *Proxy) handle(client net.Conn) {
+	defer client.Close()
+	_ = client.SetReadDeadline(time.Now().Add(handshakeTimeout))
+	rec := &recorder{r: client}
+	host, err := sniffHost(rec)
+	if err != nil {
+		p.unrouted.Add(1)
+		return
+	}
+	rule, ok := p.table.Load().match(host)
+	if !ok {
+		p.unrouted.Add(1)
+		return
+	}
+	_ = client.SetReadDeadline(time.Time{})
+
+	c := p.countersFor(rule)
+	c.total.Add(1)
+	backend, err := net.DialTimeout("tcp", rule.Backend, dialTimeout)
+	if err != nil {
+		c.dialFailed.Add(1)
+		return
+	}
+	defer backend.Close()
+	c.active.Add(1)
+	defer c.active.Add(-1)
+
+	// Replay what we consumed while routing, then splice.
+	if _, err := backend.Write(rec.buf); err != nil {
+		return
+	}
+	c.bytesIn.Add(uint64(len(rec.buf)))
+	done := make(chan struct{}, 2)
+	go func() {
+		n, _ := io.Copy(backend, client)
+		c.bytesIn.Add(uint64(n))
+		closeWrite(backend)
+		done <- struct{}{}
+	}()
+	go func() {
+		n, _ := io.Copy(client, backend)
+		c.bytesOut.Add(uint64(n))
+		closeWrite(client)
+		done <- struct{}{}
+	}()
+	<-done
+	<-done
+}
+
+// closeWrite half-closes TCP connections so the peer sees EOF while
+// the other direction drains.
+func closeWrite(c net.Conn) {
+	if tc, ok := c.(*net.TCPConn); ok {
+		_ = tc.CloseWrite()
+		return
+	}
+	_ = c.Close()
+}
+
+// recorder keeps every byte read through it so the routing prelude can
+// be replayed to the backend.
+type recorder struct {
+	r   io.Reader
+	buf []byte
+}
+
+func (r *recorder) Read(p []byte) (int, error) {
+	n, err := r.r.Read(p)
+	r.buf = append(r.buf, p[:n]...)
+	return n, err
+}
@@ -0,0 +1,223 @@
+package proxy
+
+import (
+	"bytes"
+	"encoding/binary"
+	"errors"
+	"io"
+	"strings"
+)
+
+const (
+	tlsRecordHandshake  = 0x16
+	tlsClientHello      = 0x01
+	tlsExtServerName    = 0x0000
+	maxTLSRecord        = 16384
+	maxMinecraftPacket  = 2048
+	minecraftHandshake  = 0x00
+	minecraftLegacyPing = 0xFE
+)
+
+var errUnroutable = errors.New("no routable hostname")
+
+// sniffHost reads just enough of the connection to learn the hostname
+// the client asked for. TLS handshakes start with a 0x16 record whose
+// version major is 3; anything else is read as the VarInt length of a
+// Minecraft handshake packet (whose length byte may itself be 0x16, so
+// the second byte disambiguates: a handshake's packet id is 0x00).
+func sniffHost(r io.Reader) (string, error) {
+	var head [2]byte
+	if _, err := io.ReadFull(r, head[:]); err != nil {
+		return "", err
+	}
+	first := head[0]
+	r = io.MultiReader(bytes.NewReader(head[1:]), r)
+	switch {
+	case first == tlsRecordHandshake && head[1] == 0x03:
+		return sniffTLS(r)
+	case first == minecraftLegacyPing:
+		// Pre-1.7 server list ping carries no hostname we can use.
+		return "", errUnroutable
+	default:
+		return sniffMinecraft(r, first)
+	}
+}
+
+// sniffTLS parses the SNI extension out of a ClientHello. The first
+// byte of the record header has already been consumed.
+func sniffTLS(r io.Reader) (string, error) {
+	var hdr [4]byte // version(2) length(2)
+	if _, err := io.ReadFull(r, hdr[:]); err != nil {
+		return "", err
+	}
+	n := int(binary.BigEndian.Uint16(hdr[2:]))
+	if n == 0 || n > maxTLSRecord {
+		return "", errUnroutable
+	}
+	rec := make([]byte, n)
+	if _, err := io.ReadFull(r, rec); err != nil {
+		return "", err
+	}
+	b := bytesReader(rec)
+	if t, ok := b.u8(); !ok || t != tlsClientHello {
+		return "", errUnroutable
+	}
+	// length(3) version(2) random(32)
+	if !b.skip(3 + 2 + 32) {
+		return "", errUnroutable
+	}
+	if l, ok := b.u8(); !ok || !b.skip(int(l)) { // session id
+		return "", errUnroutable
+	}
+	if l, ok := b.u16(); !ok || !b.skip(int(l)) { // cipher suites
+		return "", errUnroutable
+	}
+	if l, ok := b.u8(); !ok || !b.skip(int(l)) { // compression methods
+		return "", errUnroutable
+	}
+	extLen, ok := b.u16()
+	if !ok {
+		return "", errUnroutable
+	}
+	exts, ok := b.take(int(extLen))
+	if !ok {
+		return "", errUnroutable
+	}
+	e := bytesReader(exts)
+	for len(e) > 0 {
+		typ, ok1 := e.u16()
+		l, ok2 := e.u16()
+		data, ok3 := e.take(int(l))
+		if !ok1 || !ok2 || !ok3 {
+			return "", errUnroutable
+		}
+		if typ != tlsExtServerName {
+			continue
+		}
+		s := bytesReader(data)
+		if _, ok := s.u16(); !ok { // server name list length
+			return "", errUnroutable
+		}
+		for len(s) > 0 {
+			nameType, ok1 := s.u8()
+			nl, ok2 := s.u16()
+			name, ok3 := s.take(int(nl))
+			if !ok1 || !ok2 || !ok3 {
+				return "", errUnroutable
+			}
+			if nameType == 0 {
+				return normaliseHost(string(name))
+			}
+		}
+	}
+	return "", errUnroutable
+}
+
+// sniffMinecraft parses the handshake packet: VarInt length, VarInt
+// packet id (0x00), VarInt protocol version, String server address,
+// u16 port, VarInt next state. first is the length's first byte.
+func sniffMinecraft(r io.Reader, first byte) (string, error) {
+	n, err := readVarInt(r, first)
+	if err != nil || n <= 0 || n > maxMinecraftPacket {
+		return "", errUnroutable
+	}
+	pkt := make([]byte, n)
+	if _, err := io.ReadFull(r, pkt); err != nil {
+		return "", err
+	}
+	b := bytesReader(pkt)
+	if id, ok := b.varint(); !ok || id != minecraftHandshake {
+		return "", errUnroutable
+	}
+	if _, ok := b.varint(); !ok { // protocol version
+		return "", errUnroutable
+	}
+	l, ok := b.varint()
+	if !ok || l < 0 {
+		return "", errUnroutable
+	}
+	addr, ok := b.take(int(l))
+	if !ok {
+		return "", errUnroutable
+	}
+	// Forge appends "\x00FML\x00" (or FML2/FML3) markers; BungeeCord
+	// IP forwarding appends "\x00<ip>\x00<uuid>". The hostname is
+	// always the part before the first NUL.
+	host, _, _ := strings.Cut(string(addr), "\x00")
+	return normaliseHost(host)
+}
+
+func normaliseHost(h string) (string, error) {
+	h = strings.TrimSuffix(strings.ToLower(strings.TrimSpace(h)), ".")
+	if h == "" {
+		return "", errUnroutable
+	}
+	return h, nil
+}
+
+func readVarInt(r io.Reader, first byte) (int32, error) {
+	var v uint32
+	b := first
+	for i := 0; ; i++ {
+		v |= uint32(b&0x7F) << (7 * i)
+		if b&0x80 == 0 {
+			return int32(v), nil
+		}
+		if i == 4 {
+			return 0, errUnroutable
+		}
+		var next [1]byte
+		if _, err := io.ReadFull(r, next[:]); err != nil {
+			return 0, err
+		}
+		b = next[0]
+	}
+}
+
+// bytesReader is a tiny bounds-checked cursor over a parsed record.
+type bytesReader []byte
+
+func (b *bytesReader) take(n int) ([]byte, bool) {
+	if n < 0 || len(*b) < n {
+		return nil, false
+	}
+	out := (*b)[:n]
+	*b = (*b)[n:]
+	return out, true
+}
+
+func (b *bytesReader) skip(n int) bool {
+	_, ok := b.take(n)
+	return ok
+}
+
+func (b *bytesReader) u8() (byte, bool) {
+	v, ok := b.take(1)
+	if !ok {
+		return 0, false
+	}
+	return v[0], true
+}
+
+func (b *bytesReader) u16() (uint16, bool) {
+	v, ok := b.take(2)
+	if !ok {
+		return 0, false
+	}
+	return binary.BigEndian.Uint16(v), true
+}
+
+func (b *bytesReader) varint() (int32, bool) {
+	var v uint32
+	for i := 0; i < 5; i++ {
+		c, ok := b.u8()
+		if !ok {
+			return 0, false
+		}
+		v |= uint32(c&0x7F) << (7 * i)
+		if c&0x80 == 0 {
+			return int32(v), true
+		}
+	}
+	return 0, false
+}
@@ -0,0 +1,83 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"log"
+	"net/http"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// handleAllocationSync serves POST /api/servers/:uuid/allocations, sent
+// by the panel after a server's allocations change. It pulls the panel
+// config and compares the new ports with what the container publishes.
+// An offline server only moves its wake listeners; the next start
+// creates the container with the new bindings. A running one answers
+// with the difference and, when the body asks to restart, queues the
+// restart that recreates its container on the new ports (the user
+// token then needs control.restart, otherwise stats.read).
+// HMAC-authenticated.
+func (r *Router) handleAllocationSync(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	var body apitypes.AllocationSyncRequest
+	if err := decodeJSON(req, &body); err != nil {
+		writeJSONError(w, http.StatusBadRequest, "allocations.bad_request")
+		return
+	}
+	scope := "stats.read"
+	if body.Restart {
+		scope = powerScope("restart")
+	}
+	if !r.authorizeActingUser(w, req, serverID, scope) {
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if err := syncServerConfig(req.Context(), srv); err != nil {
+		log.Printf("allocation sync %s: fetch config: %v", serverID, err)
+		writeJSONError(w, http.StatusBadGateway, "config.sync_failed")
+		return
+	}
+	srv.RearmWake()
+	added, removed, err := srv.PortDiff(req.Context())
+	if err != nil {
+		writeJSONErrorMessage(w, http.StatusBadGateway, "allocations.inspect_failed", err.Error())
+		return
+	}
+	result := apitypes.AllocationSyncResult{
+		State:           string(srv.Environment().State()),
+		Added:           portBindings(added),
+		Removed:         portBindings(removed),
+		RestartRequired: len(added) > 0 || len(removed) > 0,
+	}
+	if result.RestartRequired && body.Restart {
+		ticket, err := srv.EnqueuePower(server.PowerRestart, func(ctx context.Context) error {
+			return syncServerConfig(ctx, srv)
+		})
+		if errors.Is(err, server.ErrPowerQueueFull) {
+			writeJSONError(w, http.StatusTooManyRequests, "power.queue_full")
+			return
+		}
+		// ErrPowerInProgress hands back the restart already waiting,
+		// which pulls the new config when it runs.
+		result.Ticket = ticket.ID
+	}
+	writeJSON(w, result)
+}
+
+func portBindings(ports []docker.PublishedPort) []apitypes.PortBinding {
+	out := make([]apitypes.PortBinding, 0, len(ports))
+	for _, p := range ports {
+		out = append(out, apitypes.PortBinding{IP: p.HostIP, Port: p.HostPort, Protocol: p.Proto})
+	}
+	return out
+}
@@ -1,14 +1,35 @@
 package router
 
 import (
+	"context"
 	"encoding/json"
+	"errors"
 	"fmt"
+	"io"
 	"net/http"
+	"net/url"
+	"os"
+	"strconv"
+	"strings"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/backup"
+	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/jobs"
+	"github.com/stellarstack/daemon/internal/pathlock"
 )
 
 // handleBackups is invoked by the API (HMAC-authenticated, not browser
-// JWT) for create / restore / delete. The browser never hits the daemon
+// JWT) for create / restore / delete / manifest / list / import / usage / prune. The browser never hits the daemon
 // directly for backup ops — the API mediates so we can persist DB state.
+// The user token needs backup.read for manifest, list and usage and
+// backup.write for the rest; scheduled creates come from the panel
+// itself.
+//
+// Create and restore are tracked as synchronous jobs: persisted across
+// daemon restarts and deduplicated by the `Idempotency-Key` header, so
+// a panel retry after a dropped response gets the original result
+// instead of a second tarball.
 func (r *Router) handleBackups(w http.ResponseWriter, req *http.Request, serverID string) {
 	if r.backups == nil {
 		http.Error(w, "backups disabled", http.StatusServiceUnavailable)
@@ -19,44 +40,212 @@ func (r *Router) handleBackups(w http.ResponseWriter, req *http.Request, serverI
 		return
 	}
 	op := req.URL.Query().Get("op")
+	scope := "backup.write"
+	if op == "manifest" || op == "list" || op == "usage" {
+		scope = "backup.read"
+	}
+	// Schedules create backups on the panel's own account.
+	authorize := r.authorizeActingUser
+	if op == "create" {
+		authorize = r.authorizePanelOrUser
+	}
+	if !authorize(w, req, serverID, scope) {
+		return
+	}
 	srv := r.manager.Get(serverID)
 	switch op {
 	case "create":
-		var body struct{ Name string }
+		var body apitypes.CreateBackupRequest
 		if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 			writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
 			return
 		}
-		srv.PublishDaemon("Creating backup '" + body.Name + "', this can take a while...")
-		res, err := r.backups.Create(serverID, body.Name)
+		job, err := r.jobs.Run(req.Context(), serverID, "backup.create", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "backup", pathlock.R("/"))
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			srv.PublishDaemon("Creating backup '" + body.Name + "', this can take a while...")
+			res, err := r.backups.Create(serverID, body.Name, backup.CreateOptions{
+				Source:   body.Source,
+				Ignore:   body.Ignore,
+				Manifest: body.Manifest,
+				Quota:    backup.Quota(body.Quota),
+				Base:     body.Base,
+				// Frames on the server's socket drive the panel's progress
+				// bar; the job snapshot carries the same counts for polling.
+				Progress: func(p backup.Progress) {
+					j.Progress(p.BytesProcessed, p.TotalBytes)
+					srv.PublishEvent("backup progress", p)
+				},
+			})
+			if err != nil {
+				srv.PublishDaemon("Backup '" + body.Name + "' failed: " + err.Error())
+				return err
+			}
+			srv.PublishDaemon(fmt.Sprintf("Backup '%s' complete (%.2f MB)", body.Name, float64(res.Bytes)/1024/1024))
+			if len(res.Rotated) > 0 {
+				srv.PublishDaemon(fmt.Sprintf("Backup quota reached; removed %d older backup(s)", len(res.Rotated)))
+			}
+			j.SetResult(res)
+			return nil
+		})
+		if errors.Is(err, jobs.ErrInProgress) {
+			writeJSONError(w, http.StatusConflict, "backups.in_progress")
+			return
+		}
+		if errors.Is(err, pathlock.ErrLocked) {
+			writeLocked(w, err)
+			return
+		}
+		if errors.Is(err, backup.ErrQuotaExceeded) {
+			writeJSONError(w, http.StatusInsufficientStorage, "backups.quota_exceeded")
+			return
+		}
+		if errors.Is(err, backup.ErrBaseMissing) {
+			writeJSONError(w, http.StatusConflict, "backups.base_missing")
+			return
+		}
 		if err != nil {
-			srv.PublishDaemon("Backup '" + body.Name + "' failed: " + err.Error())
 			writeJSONError(w, http.StatusInternalServerError, "backups.create_failed")
 			return
 		}
-		srv.PublishDaemon(fmt.Sprintf("Backup '%s' complete (%.2f MB)", body.Name, float64(res.Bytes)/1024/1024))
-		writeJSON(w, res)
+		w.Header().Set("Content-Type", "application/json")
+		_, _ = w.Write(job.Result)
 	case "restore":
-		var body struct{ Name string }
+		var body apitypes.RestoreBackupRequest
 		if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 			writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
 			return
 		}
-		srv.PublishDaemon("Restoring backup '" + body.Name + "'...")
-		if err := r.backups.Restore(serverID, body.Name); err != nil {
-			srv.PublishDaemon("Restore failed: " + err.Error())
+		_, err := r.jobs.Run(req.Context(), serverID, "backup.restore", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "restore", pathlock.W("/"))
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			srv.PublishDaemon("Restoring backup '" + body.Name + "'...")
+			if err := r.backups.Restore(serverID, body.Name); err != nil {
+				srv.PublishDaemon("Restore failed: " + err.Error())
+				return err
+			}
+			srv.PublishDaemon("Restore of '" + body.Name + "' complete")
+			return nil
+		})
+		if errors.Is(err, jobs.ErrInProgress) {
+			writeJSONError(w, http.StatusConflict, "backups.in_progress")
+			return
+		}
+		if errors.Is(err, pathlock.ErrLocked) {
+			writeLocked(w, err)
+			return
+		}
+		if errors.Is(err, backup.ErrBaseMissing) {
+			writeJSONErrorMessage(w, http.StatusConflict, "backups.base_missing", err.Error())
+			return
+		}
+		if err != nil {
 			writeJSONError(w, http.StatusInternalServerError, "backups.restore_failed")
 			return
 		}
-		srv.PublishDaemon("Restore of '" + body.Name + "' complete")
 		writeJSON(w, map[string]any{"ok": true})
+	case "manifest":
+		mf, err := r.backups.Manifest(serverID, req.URL.Query().Get("name"))
+		if errors.Is(err, os.ErrNotExist) {
+			writeJSONError(w, http.StatusNotFound, "backups.no_manifest")
+			return
+		}
+		if err != nil {
+			writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
+			return
+		}
+		writeJSON(w, mf)
+	case "list":
+		// Everything on disk, with each backup's metadata sidecar.
+		list, err := r.backups.List(serverID)
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "backups.list_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"backups": list})
+	case "usage":
+		used, err := r.backups.ServerUsage(serverID)
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "backups.list_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"bytes": used})
+	case "prune":
+		// Frees dedup repository chunks no backup uses; deletes already
+		// do this, so it only matters after a failed backup.
+		res, err := r.backups.Prune(serverID)
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "backups.delete_failed")
+			return
+		}
+		writeJSON(w, res)
+	case "import":
+		// The archive is either the raw request body (?name=) or, for a
+		// JSON body, pulled from {url}. The quota always comes from the
+		// query, since the raw form has no other place for it.
+		name, url := req.URL.Query().Get("name"), ""
+		quota := quotaFromQuery(req.URL.Query())
+		if strings.HasPrefix(req.Header.Get("Content-Type"), "application/json") {
+			var body struct{ Name, URL string }
+			if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
+				writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
+				return
+			}
+			name, url = body.Name, body.URL
+			if !strings.HasPrefix(url, "http://") && !strings.HasPrefix(url, "https://") {
+				writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
+				return
+			}
+		}
+		job, err := r.jobs.Run(req.Context(), serverID, "backup.import", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			var src io.Reader = req.Body
+			if url != "" {
+				body, err := openURL(ctx, url)
+				if err != nil {
+					return err
+				}
+				defer body.Close()
+				src = body
+			}
+			res, err := r.backups.Import(serverID, name, "import", quota, src)
+			if err != nil {
+				return err
+			}
+			srv.PublishDaemon(fmt.Sprintf("Imported backup '%s' (%.2f MB)", name, float64(res.Bytes)/1024/1024))
+			j.SetResult(res)
+			return nil
+		})
+		switch {
+		case errors.Is(err, jobs.ErrInProgress):
+			writeJSONError(w, http.StatusConflict, "backups.in_progress")
+		case errors.Is(err, backup.ErrExists):
+			writeJSONError(w, http.StatusConflict, "backups.exists")
+		case errors.Is(err, backup.ErrInvalidArchive):
+			writeJSONErrorMessage(w, http.StatusUnprocessableEntity, "backups.invalid_archive", err.Error())
+		case errors.Is(err, backup.ErrQuotaExceeded):
+			writeJSONError(w, http.StatusInsufficientStorage, "backups.quota_exceeded")
+		case err != nil:
+			writeJSONErrorMessage(w, http.StatusInternalServerError, "backups.import_failed", err.Error())
+		default:
+			w.Header().Set("Content-Type", "application/json")
+			_, _ = w.Write(job.Result)
+		}
 	case "delete":
-		var body struct{ Name string }
+		var body apitypes.DeleteBackupRequest
 		if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 			writeJSONError(w, http.StatusBadRequest, "backups.bad_request")
 			return
 		}
-		if err := r.backups.Delete(serverID, body.Name); err != nil {
+		if err := r.backups.Delete(serverID, body.Name); errors.Is(err, backup.ErrHasDependents) {
+			writeJSONErrorMessage(w, http.StatusConflict, "backups.has_dependents", err.Error())
+			return
+		} else if err != nil {
 			writeJSONError(w, http.StatusInternalServerError, "backups.delete_failed")
 			return
 		}
@@ -65,3 +254,32 @@ func (r *Router) handleBackups(w http.ResponseWriter, req *http.Request, serverI
 		http.NotFound(w, req)
 	}
 }
+
+// quotaFromQuery reads ?quotaBytes=, ?quotaRotate=1 and repeated
+// ?protected= into a backup quota.
+func quotaFromQuery(q url.Values) backup.Quota {
+	bytes, _ := strconv.ParseInt(q.Get("quotaBytes"), 10, 64)
+	return backup.Quota{
+		Bytes:     bytes,
+		Rotate:    q.Get("quotaRotate") == "1",
+		Protected: q["protected"],
+	}
+}
+
+// openURL starts a GET of url and returns the body for a 2xx response.
+// Like a file pull, it only reaches public addresses.
+func openURL(ctx context.Context, url string) (io.ReadCloser, error) {
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, url, nil)
+	if err != nil {
+		return nil, err
+	}
+	resp, err := files.PullClient.Do(req)
+	if err != nil {
+		return nil, err
+	}
+	if resp.StatusCode/100 != 2 {
+		resp.Body.Close()
+		return nil, fmt.Errorf("fetch %s: %s", url, resp.Status)
+	}
+	return resp.Body, nil
+}
@@ -0,0 +1,40 @@
+package router
+
+import (
+	"errors"
+	"net/http"
+)
+
+// limitBody caps req.Body at maxMb MiB (0 leaves it unbounded), so a
+// single request can't fill memory or disk. A body that declares more
+// up front is refused with 413 before anything is read; one that runs
+// over while streaming fails its next read, which bodyTooLarge turns
+// into the same 413. Reports whether the handler may go on.
+func limitBody(w http.ResponseWriter, req *http.Request, maxMb int, code string) bool {
+	if maxMb <= 0 {
+		return true
+	}
+	limit := int64(maxMb) << 20
+	if req.ContentLength > limit {
+		writeTooLarge(w, code, limit)
+		return false
+	}
+	req.Body = http.MaxBytesReader(w, req.Body, limit)
+	return true
+}
+
+// bodyTooLarge writes the 413 if err is a limitBody cap being hit, and
+// reports whether it was.
+func bodyTooLarge(w http.ResponseWriter, err error, code string) bool {
+	var mbe *http.MaxBytesError
+	if !errors.As(err, &mbe) {
+		return false
+	}
+	writeTooLarge(w, code, mbe.Limit)
+	return true
+}
+
+func writeTooLarge(w http.ResponseWriter, code string, limit int64) {
+	writeJSONErrorMessage(w, http.StatusRequestEntityTooLarge, code,
+		"request body is over the "+itoa(limit)+" byte limit")
+}
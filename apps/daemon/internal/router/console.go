@@ -0,0 +1,63 @@
+package router
+
+import (
+	"net/http"
+
+	"github.com/stellarstack/daemon/internal/events"
+)
+
+// handleConsole streams a server's console for `stellar-daemon ctl
+// console`: the history buffer, then with ?follow=1 every frame the bus
+// publishes until the client hangs up. Frames are the WebSocket's
+// `{v, event, args}` envelopes, one per line (NDJSON), so a reader picks
+// out "console output" the same way the browser does.
+// HMAC-authenticated, i.e. the panel or a local socket peer.
+func (r *Router) handleConsole(w http.ResponseWriter, req *http.Request, serverID string) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	srv := r.manager.Get(serverID)
+	var sub *events.Subscriber
+	if req.URL.Query().Get("follow") == "1" {
+		// Subscribe before the snapshot so no line falls in between.
+		sub = srv.Bus().Subscribe()
+		defer sub.Close()
+	}
+	w.Header().Set("Content-Type", "application/x-ndjson")
+	w.Header().Set("X-Content-Type-Options", "nosniff")
+	for _, line := range srv.History().Snapshot() {
+		if !writeLine(w, events.Encode("console output", []any{line})) {
+			return
+		}
+	}
+	flusher, _ := w.(http.Flusher)
+	if flusher != nil {
+		flusher.Flush()
+	}
+	if sub == nil {
+		return
+	}
+	for {
+		select {
+		case <-req.Context().Done():
+			return
+		case frame, ok := <-sub.Recv():
+			if !ok || !writeLine(w, frame) {
+				return
+			}
+			if flusher != nil {
+				flusher.Flush()
+			}
+		}
+	}
+}
+
+func writeLine(w http.ResponseWriter, frame events.Frame) bool {
+	_, err := w.Write(append(frame, '\n'))
+	return err == nil
+}
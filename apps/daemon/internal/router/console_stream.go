@@ -0,0 +1,127 @@
+package router
+
+import (
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"strconv"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/events"
+)
+
+// consoleStreamKeepAlive is how often an idle stream gets a comment
+// line, so proxies that time out quiet responses leave it open.
+const consoleStreamKeepAlive = 20 * time.Second
+
+// handleConsoleStream serves GET /api/servers/:uuid/console/stream, the
+// read-only Server-Sent Events fallback for browsers (or proxies) that
+// can't hold the console WebSocket. It takes the same ?token=, ?v= and
+// ?since= as the socket and sends the same frames in the same order:
+// auth success, status, the history snapshot, missed frames, then
+// everything the bus publishes. Each frame is one `data:` event
+// carrying the `{v, event, args}` envelope; sequenced frames also set
+// the event id, so EventSource's Last-Event-ID works in place of
+// ?since= when it reconnects.
+//
+// There's no way to re-auth a one-way stream: `token expiring` goes out
+// a minute before exp as on the socket, and at exp `token expired` ends
+// the stream. The client reconnects with a fresh token.
+func (r *Router) handleConsoleStream(w http.ResponseWriter, req *http.Request, serverUUID string) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	claims, ok := r.consoleClaims(w, req, serverUUID)
+	if !ok {
+		return
+	}
+	flusher, ok := w.(http.Flusher)
+	if !ok {
+		http.Error(w, "streaming unsupported", http.StatusInternalServerError)
+		return
+	}
+	ctx := req.Context()
+
+	srv := r.manager.Get(serverUUID)
+	since := req.URL.Query().Get("since")
+	if id := req.Header.Get("Last-Event-ID"); id != "" {
+		since = id
+	}
+	var sub *events.Subscriber
+	var missed []events.Frame
+	if seq, err := strconv.ParseUint(since, 10, 64); err == nil {
+		sub, missed = srv.Bus().SubscribeSince(seq)
+	} else {
+		sub = srv.Bus().Subscribe()
+	}
+	defer sub.Close()
+
+	w.Header().Set("Content-Type", "text/event-stream")
+	w.Header().Set("Cache-Control", "no-cache")
+	// nginx buffers proxied responses unless told otherwise.
+	w.Header().Set("X-Accel-Buffering", "no")
+	w.WriteHeader(http.StatusOK)
+
+	_ = writeEvent(w, events.Encode("auth success", nil))
+	currentState := srv.Environment().State()
+	_ = writeEvent(w, events.Encode("status", []any{string(currentState)}))
+	if currentState == "offline" {
+		_ = writeEvent(w, events.Encode("console output",
+			[]any{"stellarstack@" + serverUUID[:8] + "~ Server marked as offline..."}))
+	} else {
+		for _, line := range srv.History().Snapshot() {
+			_ = writeEvent(w, events.Encode("console output", []any{line}))
+		}
+	}
+	for _, frame := range missed {
+		_ = writeEvent(w, frame)
+	}
+	flusher.Flush()
+
+	keepAlive := time.NewTicker(consoleStreamKeepAlive)
+	defer keepAlive.Stop()
+	var expiring, expired <-chan time.Time
+	if claims.Exp > 0 {
+		until := time.Until(time.Unix(claims.Exp, 0))
+		expiring = time.After(max(0, until-time.Minute))
+		expired = time.After(until)
+	}
+	for {
+		select {
+		case <-ctx.Done():
+			return
+		case frame, ok := <-sub.Recv():
+			if !ok || writeEvent(w, frame) != nil {
+				return
+			}
+		case <-keepAlive.C:
+			if _, err := fmt.Fprint(w, ": keep-alive\n\n"); err != nil {
+				return
+			}
+		case <-expiring:
+			_ = writeEvent(w, events.Encode("token expiring", nil))
+		case <-expired:
+			_ = writeEvent(w, events.Encode("token expired", nil))
+			flusher.Flush()
+			return
+		}
+		flusher.Flush()
+	}
+}
+
+// writeEvent writes frame as one SSE event, with its replay seq as the
+// event id when it has one. Frames are single-line JSON, so one `data:`
+// line carries each.
+func writeEvent(w http.ResponseWriter, frame events.Frame) error {
+	var head struct {
+		Seq uint64 `json:"seq"`
+	}
+	if json.Unmarshal(frame, &head) == nil && head.Seq > 0 {
+		if _, err := fmt.Fprintf(w, "id: %d\n", head.Seq); err != nil {
+			return err
+		}
+	}
+	_, err := fmt.Fprintf(w, "data: %s\n\n", frame)
+	return err
+}
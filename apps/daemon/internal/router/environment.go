@@ -0,0 +1,73 @@
+package router
+
+import (
+	"errors"
+	"log"
+	"net/http"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// handleEnvironment serves /api/servers/:uuid/environment. GET pulls
+// the panel config and reports the installed environment; PUT first
+// stores the body's variables as overrides (see server.SetEnvOverrides)
+// so they hold for the next start and across a daemon restart, even if
+// the panel's config hasn't caught up. Either answers with the
+// variables a running server only picks up on restart. Rule checks are
+// the panel's; the daemon only refuses names and values Docker can't
+// carry. HMAC-authenticated; the user token needs control.start.
+func (r *Router) handleEnvironment(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if !r.authorizeActingUser(w, req, serverID, "control.start") {
+		return
+	}
+	switch req.Method {
+	case http.MethodGet:
+	case http.MethodPut:
+		var body apitypes.EnvironmentRequest
+		if err := decodeJSON(req, &body); err != nil {
+			writeJSONError(w, http.StatusBadRequest, "environment.bad_request")
+			return
+		}
+		if err := srv.SetEnvOverrides(body.Environment); err != nil {
+			if errors.Is(err, server.ErrBadEnvironment) {
+				writeJSONErrorMessage(w, http.StatusUnprocessableEntity, "environment.invalid", err.Error())
+				return
+			}
+			log.Printf("environment %s: save overrides: %v", serverID, err)
+			writeJSONError(w, http.StatusInternalServerError, "environment.save_failed")
+			return
+		}
+	default:
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	synced := true
+	if err := syncServerConfig(req.Context(), srv); err != nil {
+		log.Printf("environment %s: fetch config: %v", serverID, err)
+		synced = false
+	}
+	pending, err := srv.PendingEnv(req.Context())
+	if err != nil {
+		log.Printf("environment %s: inspect: %v", serverID, err)
+	}
+	if pending == nil {
+		pending = []string{}
+	}
+	env := srv.Config().Environment
+	if env == nil {
+		env = map[string]string{}
+	}
+	writeJSON(w, apitypes.EnvironmentResult{
+		State:          string(srv.Environment().State()),
+		Synced:         synced,
+		Environment:    env,
+		Overrides:      srv.EnvOverrides(),
+		PendingRestart: pending,
+	})
+}
@@ -0,0 +1,103 @@
+package router
+
+import (
+	"context"
+	"log"
+	"net/http"
+	"slices"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// execTimeout caps a single debug exec. Anything longer belongs in an
+// SSH session on the node, not a panel request.
+const execTimeout = 2 * time.Minute
+
+// handleExec runs a one-shot command inside the server's running
+// container for support staff. Browser-authenticated with a JWT that
+// carries the `admin.exec` scope, which the API only mints for panel
+// admins. argv[0] must be on the daemon's exec allowlist and every
+// invocation is pushed to the panel audit log before it runs.
+//
+// Body: { command: ["ls", "-la"] }. The response streams the same
+// JSON-Lines shape as install: `{stream, line}` frames followed by a
+// terminal `{exitCode}`.
+func (r *Router) handleExec(w http.ResponseWriter, req *http.Request, serverID string) {
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	claims, ok := r.authorizeServerToken(w, req, serverID)
+	if !ok {
+		return
+	}
+	if !claims.HasScope("admin.exec") {
+		http.Error(w, "missing scope admin.exec", http.StatusForbidden)
+		return
+	}
+	var body struct {
+		Command []string `json:"command"`
+	}
+	if err := decodeJSON(req, &body); err != nil || len(body.Command) == 0 {
+		writeJSONError(w, http.StatusBadRequest, "exec.bad_request")
+		return
+	}
+	if !slices.Contains(r.cfg.ExecAllowlist, body.Command[0]) {
+		writeJSONErrorMessage(w, http.StatusForbidden, "exec.not_allowed",
+			body.Command[0]+" is not on this node's exec allowlist")
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if srv.Environment().State() == environment.StateOffline {
+		writeJSONError(w, http.StatusConflict, "exec.not_running")
+		return
+	}
+
+	// Audit first and synchronously: a command that can't be recorded,
+	// or with no panel to record it, doesn't run.
+	p := srv.Panel()
+	if p == nil {
+		log.Printf("exec %s: no panel client to audit to", serverID)
+		writeJSONError(w, http.StatusBadGateway, "exec.audit_failed")
+		return
+	}
+	auditCtx, auditCancel := context.WithTimeout(req.Context(), 5*time.Second)
+	err := p.PushAudit(auditCtx, serverID, claims.Sub, "servers.exec", map[string]any{
+		"command": strings.Join(body.Command, " "),
+	})
+	auditCancel()
+	if err != nil {
+		log.Printf("exec %s: audit push: %v", serverID, err)
+		writeJSONError(w, http.StatusBadGateway, "exec.audit_failed")
+		return
+	}
+	log.Printf("exec %s: %q by %s", serverID, body.Command, claims.Sub)
+
+	ctx, cancel := context.WithTimeout(req.Context(), execTimeout)
+	defer cancel()
+	env := srv.Environment()
+	lines, wait, err := env.Docker().Exec(ctx, env.ContainerName(), docker.ExecOptions{
+		Cmd:        body.Command,
+		WorkingDir: "/home/container",
+	})
+	if err != nil {
+		writeJSONError(w, http.StatusBadGateway, "exec.failed")
+		return
+	}
+
+	w.Header().Set("Content-Type", "application/x-ndjson")
+	w.Header().Set("Cache-Control", "no-cache")
+	w.WriteHeader(http.StatusOK)
+	flusher, _ := w.(http.Flusher)
+	for line := range lines {
+		emit(w, flusher, line.Stream, line.Line)
+	}
+	exitCode, err := wait()
+	if err != nil {
+		emit(w, flusher, "stderr", "exec: "+err.Error())
+	}
+	finalize(w, flusher, exitCode)
+}
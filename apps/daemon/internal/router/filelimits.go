@@ -0,0 +1,110 @@
+package router
+
+import (
+	"fmt"
+	"net/http"
+	"strconv"
+	"sync"
+	"time"
+)
+
+// fileJobKinds are the file operations that run as jobs and chew through
+// disk and CPU for as long as they run; they share a per-server budget.
+var fileJobKinds = []string{"compress", "decompress", "pull", "copy"}
+
+// admitFileJob enforces files.max_concurrent_jobs before a compress,
+// decompress, pull or copy starts. A retry carrying the idempotency key of a
+// job that already exists is always admitted — it doesn't start work.
+// Writes a 429 and returns false when the server is at its budget.
+//
+// Admission reserves a slot until release, which the caller defers past
+// jobs.Start: two requests racing can't both see the last free slot,
+// and the job counts itself once registered.
+func (r *Router) admitFileJob(w http.ResponseWriter, req *http.Request, serverID string) (release func(), ok bool) {
+	if r.jobs.Holds(serverID, idempotencyKey(req)) {
+		return func() {}, true
+	}
+	limit := r.cfg.Files.MaxConcurrentJobs
+	var active int
+	release, ok = r.fileJobs.reserve(serverID, limit, func() int {
+		active = r.jobs.Active(serverID, fileJobKinds...)
+		return active
+	})
+	if ok {
+		return release, true
+	}
+	w.Header().Set("Retry-After", "5")
+	writeJSONErrorMessage(w, http.StatusTooManyRequests, "files.busy",
+		fmt.Sprintf("%d file operations are already running for this server (limit %d); wait for one to finish or cancel it", active, limit))
+	return nil, false
+}
+
+// slotLimiter hands out at most `limit` concurrent slots per server.
+type slotLimiter struct {
+	mu    sync.Mutex
+	inUse map[string]int
+}
+
+func (l *slotLimiter) acquire(serverID string, limit int) (release func(), ok bool) {
+	return l.reserve(serverID, limit, nil)
+}
+
+// reserve is acquire with running(), read under the limiter's lock,
+// counted against the limit too: work under way that holds no slot.
+func (l *slotLimiter) reserve(serverID string, limit int, running func() int) (release func(), ok bool) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	if l.inUse == nil {
+		l.inUse = map[string]int{}
+	}
+	busy := l.inUse[serverID]
+	if running != nil {
+		busy += running()
+	}
+	if busy >= limit {
+		return nil, false
+	}
+	l.inUse[serverID]++
+	var once sync.Once
+	return func() {
+		once.Do(func() {
+			l.mu.Lock()
+			defer l.mu.Unlock()
+			if l.inUse[serverID]--; l.inUse[serverID] <= 0 {
+				delete(l.inUse, serverID)
+			}
+		})
+	}, true
+}
+
+// admitFileList takes one of the server's files.max_concurrent_lists
+// slots. Listing a directory with hundreds of thousands of entries
+// stats every one of them, so a client re-requesting in a loop would
+// otherwise keep the disk busy for everyone.
+func (r *Router) admitFileList(w http.ResponseWriter, serverID string) (release func(), ok bool) {
+	limit := r.cfg.Files.MaxConcurrentLists
+	release, ok = r.fileLists.acquire(serverID, limit)
+	if !ok {
+		w.Header().Set("Retry-After", "1")
+		writeJSONErrorMessage(w, http.StatusTooManyRequests, "files.busy",
+			"too many directory listings in flight for this server (limit "+strconv.Itoa(limit)+"); retry shortly")
+	}
+	return release, ok
+}
+
+// withFileTimeout runs h under files.request_timeout_seconds. A handler
+// still running at the deadline gets a 503 in its place; its writes are
+// discarded, and its request context is done. Only for short
+// request/response calls: the timeout handler buffers the body, so
+// streams and transfers must not go through it.
+func (r *Router) withFileTimeout(op string, h http.HandlerFunc) http.Handler {
+	d := time.Duration(r.cfg.Files.RequestTimeoutSeconds) * time.Second
+	body := fmt.Sprintf(`{"error":{"code":"files.timeout","message":"%s did not finish within %s; the directory may be too large or the disk too busy"}}`, op, d)
+	th := http.TimeoutHandler(h, d, body)
+	return http.HandlerFunc(func(w http.ResponseWriter, req *http.Request) {
+		// The 503 goes out with whatever headers w already has; h's own
+		// replace these when it finishes in time.
+		w.Header().Set("Content-Type", "application/json")
+		th.ServeHTTP(w, req)
+	})
+}
@@ -1,14 +1,19 @@
 package router
 
 import (
+	"context"
 	"encoding/json"
 	"errors"
-	"io"
+	"mime"
 	"net/http"
 	"strings"
 
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/filepolicy"
 	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/jobs"
 	"github.com/stellarstack/daemon/internal/jwt"
+	"github.com/stellarstack/daemon/internal/pathlock"
 )
 
 // FilesEnabled wires the file manager handlers into the router. Called
@@ -21,26 +26,24 @@ type filesRouter struct {
 
 // HandleFiles is the entry point for /api/servers/:uuid/files/* requests.
 // Authentication is via JWT in the `?token=` query param. Scope check:
-//   - GET / list-dir / stat: files.read
-//   - PUT / mkdir / move:    files.write
-//   - DELETE:                files.delete
+//   - GET / list-dir / stat / download / structured:   files.read
+//   - PUT / upload / mkdir / move / copy / (de)compress / pull: files.write
+//   - DELETE:                                          files.delete
+//
+// Delete, copy, decompress, compress and pull run as jobs: the response is
+// 202 with the job snapshot and the work continues in the background.
+// Writes, uploads, moves, deletes, decompress and compress lock their
+// paths first (423 files.locked on conflict; see pathlocks.go).
+// Decompress, compress and pull share a per-server job budget and
+// listings a per-server slot budget (429 when exhausted); short calls
+// run under the files request timeout (503). See config.FilesConfig.
 func (r *Router) handleFiles(w http.ResponseWriter, req *http.Request, serverID string) {
 	if r.files == nil {
 		http.Error(w, "files disabled", http.StatusServiceUnavailable)
 		return
 	}
-	token := req.URL.Query().Get("token")
-	if token == "" {
-		http.Error(w, "missing token", http.StatusUnauthorized)
-		return
-	}
-	claims, err := r.verifier.Verify(token)
-	if err != nil {
-		http.Error(w, "invalid token", http.StatusUnauthorized)
-		return
-	}
-	if claims.Server != serverID || claims.Node != r.cfg.NodeID {
-		http.Error(w, "token scope mismatch", http.StatusUnauthorized)
+	claims, ok := r.authorizeServerToken(w, req, serverID)
+	if !ok {
 		return
 	}
 
@@ -52,109 +55,379 @@ func (r *Router) handleFiles(w http.ResponseWriter, req *http.Request, serverID
 			http.Error(w, "missing files.read", http.StatusForbidden)
 			return
 		}
-		entries, err := r.files.List(serverID, relPath)
-		if err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.list_failed")
+		release, ok := r.admitFileList(w, serverID)
+		if !ok {
+			return
+		}
+		if strings.Contains(req.Header.Get("Accept"), "application/x-ndjson") {
+			defer release()
+			r.streamFileList(w, req, serverID, relPath)
 			return
 		}
-		writeJSON(w, map[string]any{"entries": entries})
-	case "read":
+		// The slot goes when the walk does, not when the timeout answers
+		// for it: List stops at the deadline, but only between batches.
+		r.withFileTimeout("listing", func(w http.ResponseWriter, req *http.Request) {
+			defer release()
+			entries, err := r.files.List(req.Context(), serverID, relPath)
+			if errors.Is(err, filepolicy.ErrBlocked) {
+				writeJSONErrorMessage(w, http.StatusForbidden, "files.blocked", err.Error())
+				return
+			}
+			if err != nil {
+				writeJSONError(w, http.StatusBadRequest, "files.list_failed")
+				return
+			}
+			writeJSON(w, map[string]any{"entries": entries})
+		}).ServeHTTP(w, req)
+	case "read", "download":
 		if !claims.HasScope("files.read") {
 			http.Error(w, "missing files.read", http.StatusForbidden)
 			return
 		}
-		rd, size, err := r.files.Read(serverID, relPath)
+		f, st, err := r.files.Open(serverID, relPath)
+		if errors.Is(err, filepolicy.ErrBlocked) {
+			writeJSONErrorMessage(w, http.StatusForbidden, "files.blocked", err.Error())
+			return
+		}
 		if err != nil {
-			if errors.Is(err, files.ErrTooLarge) {
-				writeJSONError(w, http.StatusRequestEntityTooLarge, "files.too_large")
-				return
-			}
 			writeJSONError(w, http.StatusBadRequest, "files.read_failed")
 			return
 		}
-		defer rd.Close()
+		defer f.Close()
+		// Inline reads are for the editor; whole large files must go
+		// through download. Ranged reads of any size are fine — the
+		// client bounds them.
+		if op == "read" && req.Header.Get("Range") == "" && st.Size() > files.MaxReadBytes {
+			writeJSONErrorMessage(w, http.StatusRequestEntityTooLarge, "files.too_large",
+				"file is "+itoa(st.Size())+" bytes, over the "+itoa(files.MaxReadBytes)+
+					" byte inline limit; use GET /files/download or a Range request")
+			return
+		}
+		// Server files are untrusted: never let a browser render one
+		// (an uploaded .html or .svg) on the daemon's origin. With the
+		// type set, ServeContent neither guesses nor sniffs it and only
+		// handles Range/If-Range/If-Modified-Since.
 		w.Header().Set("Content-Type", "application/octet-stream")
-		w.Header().Set("Content-Length", itoa(size))
-		_, _ = io.Copy(w, rd)
+		w.Header().Set("X-Content-Type-Options", "nosniff")
+		if op == "download" {
+			w.Header().Set("Content-Disposition", mime.FormatMediaType("attachment", map[string]string{"filename": st.Name()}))
+		}
+		http.ServeContent(w, req, st.Name(), st.ModTime(), f)
 	case "write":
 		if !claims.HasScope("files.write") {
 			http.Error(w, "missing files.write", http.StatusForbidden)
 			return
 		}
-		body := http.MaxBytesReader(w, req.Body, 50*1024*1024)
-		defer body.Close()
-		if err := r.files.Write(serverID, relPath, body); err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.write_failed")
+		if !limitBody(w, req, r.cfg.Files.MaxWriteMb, "files.too_large") {
+			return
+		}
+		defer req.Body.Close()
+		lock, ok := r.lockPaths(w, req, serverID, "write", pathlock.W(relPath))
+		if !ok {
+			return
+		}
+		defer lock.Release()
+		if err := r.files.Write(serverID, relPath, req.Body); err != nil {
+			switch {
+			case errors.Is(err, filepolicy.ErrBlocked):
+				writeJSONErrorMessage(w, http.StatusForbidden, "files.blocked", err.Error())
+			case !bodyTooLarge(w, err, "files.too_large"):
+				writeJSONError(w, http.StatusBadRequest, "files.write_failed")
+			}
 			return
 		}
 		writeJSON(w, map[string]any{"ok": true})
-	case "mkdir":
+	case "upload":
 		if !claims.HasScope("files.write") {
 			http.Error(w, "missing files.write", http.StatusForbidden)
 			return
 		}
-		if err := r.files.Mkdir(serverID, relPath); err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.mkdir_failed")
+		lock, ok := r.lockPaths(w, req, serverID, "upload", pathlock.W(relPath))
+		if !ok {
 			return
 		}
-		writeJSON(w, map[string]any{"ok": true})
+		defer lock.Release()
+		r.uploadFiles(w, req, serverID, relPath)
+	case "mkdir":
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
+			return
+		}
+		r.withFileTimeout("mkdir", func(w http.ResponseWriter, _ *http.Request) {
+			if err := r.files.Mkdir(serverID, relPath); err != nil {
+				writeJSONError(w, http.StatusBadRequest, "files.mkdir_failed")
+				return
+			}
+			writeJSON(w, map[string]any{"ok": true})
+		}).ServeHTTP(w, req)
 	case "delete":
 		if !claims.HasScope("files.delete") {
 			http.Error(w, "missing files.delete", http.StatusForbidden)
 			return
 		}
-		if err := r.files.Delete(serverID, relPath); err != nil {
+		if relPath == "" || relPath == "/" {
 			writeJSONError(w, http.StatusBadRequest, "files.delete_failed")
 			return
 		}
-		writeJSON(w, map[string]any{"ok": true})
+		if !r.checkPaths(w, req, serverID, pathlock.W(relPath)) {
+			return
+		}
+		job := r.jobs.Start(serverID, "delete", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "delete", pathlock.W(relPath))
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			return r.files.Delete(ctx, serverID, relPath, j.Progress)
+		})
+		writeJobAccepted(w, job)
 	case "move":
 		if !claims.HasScope("files.write") {
 			http.Error(w, "missing files.write", http.StatusForbidden)
 			return
 		}
-		var body struct{ From, To string }
+		var body apitypes.MoveRequest
 		if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
 			return
 		}
-		if err := r.files.Move(serverID, body.From, body.To); err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.move_failed")
+		lock, ok := r.lockPaths(w, req, serverID, "move", pathlock.W(body.From), pathlock.W(body.To))
+		if !ok {
 			return
 		}
-		writeJSON(w, map[string]any{"ok": true})
+		defer lock.Release()
+		r.withFileTimeout("move", func(w http.ResponseWriter, _ *http.Request) {
+			if err := r.files.Move(serverID, body.From, body.To); err != nil {
+				if errors.Is(err, filepolicy.ErrBlocked) {
+					writeJSONErrorMessage(w, http.StatusForbidden, "files.blocked", err.Error())
+				} else {
+					writeJSONError(w, http.StatusBadRequest, "files.move_failed")
+				}
+				return
+			}
+			writeJSON(w, map[string]any{"ok": true})
+		}).ServeHTTP(w, req)
+	case "copy":
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
+			return
+		}
+		var body apitypes.CopyRequest
+		if err := json.NewDecoder(req.Body).Decode(&body); err != nil || len(body.Paths) == 0 {
+			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+			return
+		}
+		onConflict := files.OnConflict(body.OnConflict)
+		switch onConflict {
+		case "":
+			onConflict = files.ConflictRename
+		case files.ConflictRename, files.ConflictOverwrite, files.ConflictSkip, files.ConflictFail:
+		default:
+			writeJSONErrorMessage(w, http.StatusBadRequest, "files.bad_request",
+				"onConflict must be rename, overwrite, skip or fail")
+			return
+		}
+		admitted, ok := r.admitFileJob(w, req, serverID)
+		if !ok {
+			return
+		}
+		defer admitted()
+		wants := []pathlock.Want{pathlock.W(body.Destination)}
+		for _, p := range body.Paths {
+			if body.Move {
+				wants = append(wants, pathlock.W(p))
+			} else {
+				wants = append(wants, pathlock.R(p))
+			}
+		}
+		if !r.checkPaths(w, req, serverID, wants...) {
+			return
+		}
+		job := r.jobs.Start(serverID, "copy", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "copy", wants...)
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			room := int64(-1)
+			if !body.Move {
+				if room, err = r.diskRoom(serverID); err != nil {
+					return err
+				}
+			}
+			opts := files.CopyOptions{Move: body.Move, OnConflict: onConflict, Room: room}
+			res, err := r.files.Copy(ctx, serverID, body.Paths, body.Destination, opts, j.Progress)
+			if err != nil {
+				return err
+			}
+			j.SetResult(res)
+			return nil
+		})
+		writeJobAccepted(w, job)
 	case "decompress":
 		if !claims.HasScope("files.write") {
 			http.Error(w, "missing files.write", http.StatusForbidden)
 			return
 		}
-		var body struct{ Path, Destination string }
+		var body apitypes.DecompressRequest
 		if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
 			return
 		}
-		if err := r.files.Decompress(serverID, body.Path, body.Destination); err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.decompress_failed")
+		admitted, ok := r.admitFileJob(w, req, serverID)
+		if !ok {
 			return
 		}
-		writeJSON(w, map[string]any{"ok": true})
-	case "stat":
+		defer admitted()
+		wants := []pathlock.Want{pathlock.R(body.Path), pathlock.W(body.Destination)}
+		if !r.checkPaths(w, req, serverID, wants...) {
+			return
+		}
+		job := r.jobs.Start(serverID, "decompress", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "decompress", wants...)
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			return r.files.Decompress(ctx, serverID, body.Path, body.Destination, j.Progress)
+		})
+		writeJobAccepted(w, job)
+	case "compress":
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
+			return
+		}
+		var body apitypes.CompressRequest
+		if err := json.NewDecoder(req.Body).Decode(&body); err != nil || len(body.Paths) == 0 {
+			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+			return
+		}
+		admitted, ok := r.admitFileJob(w, req, serverID)
+		if !ok {
+			return
+		}
+		defer admitted()
+		// The archive is a new file, so only the sources are locked.
+		var wants []pathlock.Want
+		for _, p := range body.Paths {
+			wants = append(wants, pathlock.R(p))
+		}
+		if !r.checkPaths(w, req, serverID, wants...) {
+			return
+		}
+		job := r.jobs.Start(serverID, "compress", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			lock, err := r.lockJob(ctx, serverID, "compress", wants...)
+			if err != nil {
+				return err
+			}
+			defer lock.Release()
+			out, err := r.files.Compress(ctx, serverID, body.Paths, body.Destination, j.Progress)
+			if err != nil {
+				return err
+			}
+			j.SetMessage(out)
+			return nil
+		})
+		writeJobAccepted(w, job)
+	case "pull":
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
+			return
+		}
+		var body apitypes.PullRequest
+		if err := json.NewDecoder(req.Body).Decode(&body); err != nil || body.URL == "" || !files.ValidChecksum(body.Checksum) {
+			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+			return
+		}
+		admitted, ok := r.admitFileJob(w, req, serverID)
+		if !ok {
+			return
+		}
+		defer admitted()
+		job := r.jobs.Start(serverID, "pull", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+			room, err := r.diskRoom(serverID)
+			if err != nil {
+				return err
+			}
+			opts := files.PullOptions{Mirrors: body.Mirrors, Checksum: body.Checksum, Room: room}
+			res, err := r.files.Pull(ctx, serverID, body.URL, body.Directory, body.Filename, opts, j.Progress)
+			if err != nil {
+				return err
+			}
+			j.SetMessage(res.Path)
+			j.SetResult(res)
+			return nil
+		})
+		writeJobAccepted(w, job)
+	case "structured":
 		if !claims.HasScope("files.read") {
 			http.Error(w, "missing files.read", http.StatusForbidden)
 			return
 		}
-		entry, err := r.files.Stat(serverID, relPath)
-		if err != nil {
-			writeJSONError(w, http.StatusBadRequest, "files.stat_failed")
+		r.handleStructuredFile(w, req, serverID, relPath, false)
+	case "structured-write":
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
 			return
 		}
-		writeJSON(w, map[string]any{"entry": entry})
+		r.handleStructuredFile(w, req, serverID, relPath, true)
+	case "stat":
+		if !claims.HasScope("files.read") {
+			http.Error(w, "missing files.read", http.StatusForbidden)
+			return
+		}
+		r.withFileTimeout("stat", func(w http.ResponseWriter, _ *http.Request) {
+			entry, err := r.files.Stat(serverID, relPath)
+			if err != nil {
+				writeJSONError(w, http.StatusBadRequest, "files.stat_failed")
+				return
+			}
+			writeJSON(w, map[string]any{"entry": entry})
+		}).ServeHTTP(w, req)
 	default:
 		http.NotFound(w, req)
 	}
 	_ = filesRouter{} // keep type referenced
 }
 
+// streamFileList writes one JSON entry per line as the directory is
+// read, flushing every batch so the panel can render huge directories
+// progressively. Errors before the first entry get the usual JSON
+// error; once the 200 is out the status can't change, so a failure
+// mid-walk is sent as a final `{"error":...}` line instead.
+func (r *Router) streamFileList(w http.ResponseWriter, req *http.Request, serverID, relPath string) {
+	flusher, _ := w.(http.Flusher)
+	enc := json.NewEncoder(w)
+	n := 0
+	err := r.files.ListEach(req.Context(), serverID, relPath, func(e files.Entry) error {
+		if n == 0 {
+			w.Header().Set("Content-Type", "application/x-ndjson")
+			w.Header().Set("X-Content-Type-Options", "nosniff")
+		}
+		if err := enc.Encode(e); err != nil {
+			return err
+		}
+		n++
+		if flusher != nil && n%ndjsonFlushEvery == 0 {
+			flusher.Flush()
+		}
+		return nil
+	})
+	switch {
+	case err != nil && n == 0:
+		writeJSONError(w, http.StatusBadRequest, "files.list_failed")
+	case err != nil:
+		_ = enc.Encode(map[string]string{"error": "files.list_failed"})
+	case n == 0:
+		w.Header().Set("Content-Type", "application/x-ndjson")
+		w.WriteHeader(http.StatusOK)
+	}
+}
+
+// ndjsonFlushEvery is how many listing lines are buffered between
+// flushes: per-line flushing costs a syscall per entry on huge dirs.
+const ndjsonFlushEvery = 64
+
 // writeJSON marshals + writes with the standard headers.
 func writeJSON(w http.ResponseWriter, body any) {
 	w.Header().Set("Content-Type", "application/json")
@@ -189,11 +462,16 @@ func itoa(n int64) string {
 // mapping that matches the existing useFiles hook contract:
 //
 //	GET  /files            → list
-//	GET  /files/content    → read
+//	GET  /files/content    → read (inline, size-capped unless ranged)
+//	GET  /files/download   → download (attachment, any size)
 //	PUT  /files/content    → write
+//	POST /files/upload     → upload (multipart, into ?path=)
 //	DELETE /files          → delete
 //	POST /files/mkdir      → mkdir
 //	POST /files/move       → move
+//	POST /files/decompress → decompress (job)
+//	POST /files/compress   → compress (job)
+//	POST /files/pull       → pull (job)
 //	GET  /files/stat       → stat
 func resolveFilesOp(req *http.Request) string {
 	if explicit := req.URL.Query().Get("op"); explicit != "" {
@@ -212,12 +490,19 @@ func resolveFilesOp(req *http.Request) string {
 			return "list"
 		case "content":
 			return "read"
+		case "download":
+			return "download"
 		case "stat":
 			return "stat"
+		case "structured":
+			return "structured"
 		}
 	case http.MethodPut:
-		if tail == "content" {
+		switch tail {
+		case "content":
 			return "write"
+		case "structured":
+			return "structured-write"
 		}
 	case http.MethodDelete:
 		if tail == "" {
@@ -225,12 +510,20 @@ func resolveFilesOp(req *http.Request) string {
 		}
 	case http.MethodPost:
 		switch tail {
+		case "upload":
+			return "upload"
 		case "mkdir":
 			return "mkdir"
 		case "move":
 			return "move"
+		case "copy":
+			return "copy"
 		case "decompress":
 			return "decompress"
+		case "compress":
+			return "compress"
+		case "pull":
+			return "pull"
 		}
 	}
 	return ""
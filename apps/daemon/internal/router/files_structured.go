@@ -0,0 +1,103 @@
+package router
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"io"
+	"io/fs"
+	"net/http"
+
+	"github.com/stellarstack/daemon/internal/configfile"
+	"github.com/stellarstack/daemon/internal/filepolicy"
+	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/pathlock"
+)
+
+// handleStructuredFile serves /files/structured?path= (or ?file=), a
+// config file as keys so the panel can build a form for it without
+// parsing the format in the browser. The format comes from the
+// extension: .properties, .json, .toml and .yml/.yaml (see configfile).
+//
+//	GET                       → {format, entries: [{key, value}]}
+//	PUT {set: {key: value}}   → the same, after the change
+//
+// A PUT only changes the keys it names. The new file is parsed back
+// before it is written, so a value that would break the file is
+// refused with 422 files.structured_invalid and the file is left as it
+// was. A missing file reads as empty and is created by a PUT.
+func (r *Router) handleStructuredFile(w http.ResponseWriter, req *http.Request, serverID, relPath string, write bool) {
+	if relPath == "" {
+		relPath = req.URL.Query().Get("file")
+	}
+	format, ok := configfile.Detect(relPath)
+	if !ok {
+		writeJSONError(w, http.StatusUnsupportedMediaType, "files.structured_unsupported")
+		return
+	}
+	if write {
+		// Held from the read to the write so the patch can't lose a
+		// concurrent edit.
+		lock, ok := r.lockPaths(w, req, serverID, "write", pathlock.W(relPath))
+		if !ok {
+			return
+		}
+		defer lock.Release()
+	}
+	body, err := r.readConfigFile(serverID, relPath)
+	switch {
+	case errors.Is(err, files.ErrTooLarge):
+		writeJSONError(w, http.StatusRequestEntityTooLarge, "files.too_large")
+		return
+	case err != nil:
+		writeJSONError(w, http.StatusBadRequest, "files.read_failed")
+		return
+	}
+
+	if write {
+		var patch struct {
+			Set map[string]any `json:"set"`
+		}
+		dec := json.NewDecoder(io.LimitReader(req.Body, 256*1024))
+		dec.UseNumber()
+		if err := dec.Decode(&patch); err != nil || len(patch.Set) == 0 {
+			writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+			return
+		}
+		out, err := configfile.Patch(format, body, patch.Set)
+		if err != nil {
+			writeJSONErrorMessage(w, http.StatusUnprocessableEntity, "files.structured_invalid", err.Error())
+			return
+		}
+		if err := r.files.Write(serverID, relPath, bytes.NewReader(out)); err != nil {
+			if errors.Is(err, filepolicy.ErrBlocked) {
+				writeJSONErrorMessage(w, http.StatusForbidden, "files.blocked", err.Error())
+				return
+			}
+			writeJSONError(w, http.StatusBadRequest, "files.write_failed")
+			return
+		}
+		body = out
+	}
+
+	entries, err := configfile.Read(format, body)
+	if err != nil {
+		writeJSONErrorMessage(w, http.StatusUnprocessableEntity, "files.structured_invalid", err.Error())
+		return
+	}
+	writeJSON(w, map[string]any{"format": format, "entries": entries})
+}
+
+// readConfigFile is the file's contents, or nothing when it doesn't
+// exist yet.
+func (r *Router) readConfigFile(serverID, relPath string) ([]byte, error) {
+	rc, _, err := r.files.Read(serverID, relPath)
+	if errors.Is(err, fs.ErrNotExist) {
+		return nil, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	defer rc.Close()
+	return io.ReadAll(rc)
+}
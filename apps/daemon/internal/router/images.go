@@ -0,0 +1,99 @@
+package router
+
+import (
+	"encoding/json"
+	"log"
+	"net/http"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// imagePullProgressEvery spaces out byte-count frames; status changes
+// always go out.
+const imagePullProgressEvery = 250 * time.Millisecond
+
+// SetDocker enables POST /api/system/images/pull.
+func (r *Router) SetDocker(dc *docker.Client) {
+	r.docker = dc
+}
+
+// handleImagePull serves POST /api/system/images/pull {image, force}:
+// pull an image ahead of the servers that will need it (a migration, a
+// blueprint rollout) so their first start doesn't wait on the
+// registry. An image already present answers at once unless force
+// asks for a fresh pull of its tag. At most image_pull_concurrency
+// pulls run together; the rest wait their turn.
+//
+// The response is JSON-Lines like an install: `{status:"queued"}` while
+// waiting for a slot, the Engine's progress messages `{status, id,
+// current, total}`, then `{done:true}` or `{error}`. Closing the
+// request cancels the pull.
+func (r *Router) handleImagePull(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	var body struct {
+		Image string `json:"image"`
+		Force bool   `json:"force"`
+	}
+	if err := decodeJSON(req, &body); err != nil || body.Image == "" {
+		writeJSONError(w, http.StatusBadRequest, "images.bad_request")
+		return
+	}
+	if r.docker == nil {
+		writeJSONError(w, http.StatusServiceUnavailable, "images.unavailable")
+		return
+	}
+	ctx := req.Context()
+
+	w.Header().Set("Content-Type", "application/x-ndjson")
+	w.Header().Set("Cache-Control", "no-cache")
+	w.WriteHeader(http.StatusOK)
+	flusher, _ := w.(http.Flusher)
+	send := func(frame any) {
+		buf, _ := json.Marshal(frame)
+		_, _ = w.Write(append(buf, '\n'))
+		if flusher != nil {
+			flusher.Flush()
+		}
+	}
+
+	if !body.Force && r.docker.ImagePresent(ctx, body.Image) {
+		send(map[string]any{"done": true, "present": true})
+		return
+	}
+	select {
+	case r.imagePulls <- struct{}{}:
+	default:
+		send(map[string]any{"status": "queued"})
+		select {
+		case r.imagePulls <- struct{}{}:
+		case <-ctx.Done():
+			return
+		}
+	}
+	defer func() { <-r.imagePulls }()
+
+	started := time.Now()
+	var last time.Time
+	err := r.docker.PullImage(ctx, body.Image, func(p docker.PullProgress) {
+		if p.Current > 0 && time.Since(last) < imagePullProgressEvery {
+			return
+		}
+		last = time.Now()
+		send(p)
+	})
+	if err != nil {
+		log.Printf("images: prewarm %s failed: %v", body.Image, err)
+		send(map[string]any{"error": err.Error()})
+		return
+	}
+	log.Printf("images: prewarmed %s in %s", body.Image, time.Since(started).Round(time.Second))
+	send(map[string]any{"done": true})
+}
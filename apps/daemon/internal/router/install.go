@@ -7,8 +7,11 @@ import (
 	"fmt"
 	"log"
 	"net/http"
+	"net/url"
 	"os"
+	"path"
 	"path/filepath"
+	"slices"
 	"strings"
 	"time"
 
@@ -16,15 +19,50 @@ import (
 
 	"github.com/stellarstack/daemon/internal/docker"
 	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/files"
 	"github.com/stellarstack/daemon/internal/server"
 )
 
+// installStaging is where an archive install's download waits under
+// the server root. Removed when the install ends.
+const installStaging = "/.install"
+
+// installScriptDir is where the install container finds its script:
+// mounted read-only from outside the server's tree, so the script
+// can't rewrite itself and never lands among the server's files.
+const installScriptDir = "/mnt/install"
+
+// installInterpreters maps the interpreters a blueprint may declare to
+// the command run in the install image.
+var installInterpreters = map[string]string{
+	"bash":   "bash",
+	"sh":     "sh",
+	"ash":    "ash",
+	"python": "python3",
+}
+
 // installRequest is the body the API sends to /api/servers/:id/install.
+// With Archive set the server is installed from it and the script
+// fields are ignored.
 type installRequest struct {
 	Image       string            `json:"image"`
 	Entrypoint  string            `json:"entrypoint"`
 	Script      string            `json:"script"`
 	Environment map[string]string `json:"environment"`
+	// Interpreter runs the script ("bash", "sh", "ash", "python") in
+	// place of Entrypoint. Empty execs the script with Entrypoint -c,
+	// so it needs a shebang the image can honour.
+	Interpreter string          `json:"interpreter,omitempty"`
+	Archive     *installArchive `json:"archive,omitempty"`
+}
+
+// installArchive is a template to install from: downloaded like a pull
+// (mirrors tried in order, the checksum required) and extracted over
+// the server root. The URL's file name tells its format.
+type installArchive struct {
+	URL      string   `json:"url"`
+	Checksum string   `json:"checksum"`
+	Mirrors  []string `json:"mirrors,omitempty"`
 }
 
 // handleInstall runs the blueprint's install script inside a one-shot
@@ -34,19 +72,36 @@ type installRequest struct {
 //
 // Each line emitted on the response body is `{stream:"stdout"|"stderr",
 // line:"<text>"}` followed by a newline. The terminal frame is
-// `{exitCode:N}`.
+// `{exitCode:N}`. An archive install (installFromArchive) streams the
+// same frames.
 func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverUUID string) {
 	var body installRequest
 	if err := json.NewDecoder(req.Body).Decode(&body); err != nil {
 		writeJSONError(w, http.StatusBadRequest, "install.bad_request")
 		return
 	}
-	if body.Image == "" {
+	if a := body.Archive; a != nil {
+		if a.URL == "" || a.Checksum == "" || !files.ValidChecksum(a.Checksum) {
+			writeJSONError(w, http.StatusBadRequest, "install.bad_request")
+			return
+		}
+	} else if body.Image == "" {
 		writeJSONError(w, http.StatusBadRequest, "install.missing_image")
 		return
+	} else if body.Interpreter != "" {
+		if _, known := installInterpreters[body.Interpreter]; !known {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "install.bad_request", "unknown interpreter "+body.Interpreter)
+			return
+		}
+		if !slices.Contains(r.cfg.Install.Interpreters, body.Interpreter) {
+			writeJSONErrorMessage(w, http.StatusUnprocessableEntity, "install.interpreter_not_allowed", body.Interpreter+" is not allowed on this node")
+			return
+		}
 	}
 
 	srv := r.manager.Get(serverUUID)
+	srv.SetInstalling(true)
+	defer srv.SetInstalling(false)
 	dc := srv.Environment().Docker()
 	containerName := "stellar-install-" + serverUUID + "-" + uuid.New().String()[:8]
 
@@ -62,7 +117,9 @@ func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverU
 	if srv.Environment().State() != environment.StateOffline {
 		emit(w, flusher, "stdout", "[StellarStack Daemon]: Stopping server before installation...")
 		srv.PublishDaemon("Stopping server before installation...")
-		stopCtx, stopCancel := context.WithTimeout(req.Context(), 30*time.Second)
+		// Give the blueprint's full stop grace plus slack for the kill.
+		grace := srv.Environment().Stop().Grace() + 10*time.Second
+		stopCtx, stopCancel := context.WithTimeout(req.Context(), grace)
 		err := srv.HandlePower(stopCtx, server.PowerStop)
 		stopCancel()
 		if err != nil {
@@ -73,20 +130,27 @@ func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverU
 		}
 	}
 
+	if body.Archive != nil {
+		finalize(w, flusher, r.installFromArchive(req.Context(), w, flusher, serverUUID, *body.Archive))
+		return
+	}
+
 	// The install container mounts the server's bind dir at /home/container
 	// so anything the script writes there persists into the running
-	// server's data tree. We stage the install script inside the same
-	// bind dir under .install/, then remove it at the end so it doesn't
-	// leak into the runtime container.
+	// server's data tree. The script itself is staged outside it, one
+	// directory per server, and mounted read-only at installScriptDir.
 	serverDir := filepath.Join(r.cfg.DataDir, "servers", serverUUID)
-	stageDir := filepath.Join(serverDir, ".install")
-	if err := os.MkdirAll(stageDir, 0o755); err != nil {
+	if err := os.MkdirAll(serverDir, 0o755); err != nil {
 		emit(w, flusher, "stderr", "mkdir server dir: "+err.Error())
 		return
 	}
+	stageDir := filepath.Join(r.cfg.DataDir, "tmp", "install", serverUUID)
+	if err := os.MkdirAll(stageDir, 0o755); err != nil {
+		emit(w, flusher, "stderr", "mkdir install dir: "+err.Error())
+		return
+	}
 	defer os.RemoveAll(stageDir)
-	scriptPath := filepath.Join(stageDir, "install.sh")
-	if err := os.WriteFile(scriptPath, []byte(body.Script), 0o755); err != nil {
+	if err := os.WriteFile(filepath.Join(stageDir, "install.sh"), []byte(body.Script), 0o755); err != nil {
 		emit(w, flusher, "stderr", "write script: "+err.Error())
 		return
 	}
@@ -99,12 +163,18 @@ func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverU
 		return
 	}
 
+	script := installScriptDir + "/install.sh"
 	entrypoint := body.Entrypoint
 	if entrypoint == "" {
 		entrypoint = "/bin/ash"
 	}
-	// Inside the container the script lives at /home/container/.install/install.sh
-	cmd := []string{"-c", "/home/container/.install/install.sh"}
+	cmd := []string{"-c", script}
+	if body.Interpreter != "" {
+		// Read, not exec'd: no shebang or exec bit needed, and an image
+		// without bash can still run a POSIX sh script.
+		entrypoint = installInterpreters[body.Interpreter]
+		cmd = []string{script}
+	}
 
 	id, err := dc.CreateContainer(ctx, docker.CreateContainerOptions{
 		Name:       containerName,
@@ -113,6 +183,7 @@ func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverU
 		Entrypoint: []string{entrypoint},
 		Cmd:        cmd,
 		BindMount:  serverDir,
+		Volumes:    []string{stageDir + ":" + installScriptDir + ":ro"},
 		WorkingDir: "/home/container",
 		AutoRemove: true,
 	})
@@ -147,6 +218,41 @@ func (r *Router) handleInstall(w http.ResponseWriter, req *http.Request, serverU
 	finalize(w, flusher, exitCode)
 }
 
+// installFromArchive installs a template-based server without an
+// install container: the archive is downloaded and verified, then
+// extracted over the server's files. The output goes out as install
+// log lines like a script's; the result is the exit code to report.
+func (r *Router) installFromArchive(ctx context.Context, w http.ResponseWriter, flusher http.Flusher, serverID string, a installArchive) int {
+	ctx, cancel := context.WithTimeout(ctx, 30*time.Minute)
+	defer cancel()
+	u, err := url.Parse(a.URL)
+	if err != nil {
+		emit(w, flusher, "stderr", "archive url: "+err.Error())
+		return 1
+	}
+	emit(w, flusher, "stdout", "[StellarStack Daemon]: Downloading server archive from "+u.Host+"...")
+	defer func() { _ = r.files.Delete(context.Background(), serverID, installStaging, nil) }()
+	room, err := r.diskRoom(serverID)
+	if err != nil {
+		emit(w, flusher, "stderr", "download archive: "+err.Error())
+		return 1
+	}
+	opts := files.PullOptions{Mirrors: a.Mirrors, Checksum: a.Checksum, Room: room}
+	// The name only tells Decompress the archive's format; keep the URL's.
+	pulled, err := r.files.Pull(ctx, serverID, a.URL, installStaging, path.Base(u.Path), opts, nil)
+	if err != nil {
+		emit(w, flusher, "stderr", "download archive: "+err.Error())
+		return 1
+	}
+	emit(w, flusher, "stdout", "[StellarStack Daemon]: Checksum verified, extracting...")
+	if err := r.files.Decompress(ctx, serverID, pulled.Path, "/", nil); err != nil {
+		emit(w, flusher, "stderr", "extract archive: "+err.Error())
+		return 1
+	}
+	emit(w, flusher, "stdout", "[StellarStack Daemon]: Installed from "+pulled.URL)
+	return 0
+}
+
 func emit(w http.ResponseWriter, flusher http.Flusher, stream, line string) {
 	frame := map[string]any{"stream": stream, "line": strings.TrimRight(line, "\n")}
 	buf, _ := json.Marshal(frame)
@@ -0,0 +1,70 @@
+package router
+
+import (
+	"net/http"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/jobs"
+)
+
+// handleJobs serves /api/servers/:uuid/jobs[/:job]. Browser JWT auth,
+// same token the file manager already holds:
+//
+//	GET    /jobs       → list retained jobs   (files.read)
+//	GET    /jobs/:job  → one job snapshot     (files.read)
+//	DELETE /jobs/:job  → cancel a running job (files.write)
+func (r *Router) handleJobs(w http.ResponseWriter, req *http.Request, serverID string, rest []string) {
+	claims, ok := r.authorizeServerToken(w, req, serverID)
+	if !ok {
+		return
+	}
+	switch {
+	case len(rest) == 0 && req.Method == http.MethodGet:
+		if !claims.HasScope("files.read") {
+			http.Error(w, "missing files.read", http.StatusForbidden)
+			return
+		}
+		writeJSON(w, map[string]any{"jobs": r.jobs.List(serverID)})
+	case len(rest) == 1 && req.Method == http.MethodGet:
+		if !claims.HasScope("files.read") {
+			http.Error(w, "missing files.read", http.StatusForbidden)
+			return
+		}
+		snap, found := r.jobs.Get(serverID, rest[0])
+		if !found {
+			writeJSONError(w, http.StatusNotFound, "jobs.not_found")
+			return
+		}
+		writeJSON(w, map[string]any{"job": snap})
+	case len(rest) == 1 && req.Method == http.MethodDelete:
+		if !claims.HasScope("files.write") {
+			http.Error(w, "missing files.write", http.StatusForbidden)
+			return
+		}
+		if !r.jobs.Cancel(serverID, rest[0]) {
+			writeJSONError(w, http.StatusConflict, "jobs.not_cancellable")
+			return
+		}
+		writeJSON(w, map[string]any{"ok": true})
+	default:
+		http.NotFound(w, req)
+	}
+}
+
+// idempotencyKey returns the caller-supplied `Idempotency-Key` header.
+// A client that retries a mutation after a dropped response sends the
+// same key again to attach to the original job instead of starting
+// another. The panel doesn't send one today; a request without it
+// always starts a new job.
+func idempotencyKey(req *http.Request) string {
+	return strings.TrimSpace(req.Header.Get("Idempotency-Key"))
+}
+
+// writeJobAccepted answers a job-backed request with 202 and the
+// initial snapshot. `ok` stays in the body so callers that only check
+// it keep working.
+func writeJobAccepted(w http.ResponseWriter, job jobs.Snapshot) {
+	w.Header().Set("Content-Type", "application/json")
+	w.WriteHeader(http.StatusAccepted)
+	writeJSON(w, map[string]any{"ok": true, "job": job})
+}
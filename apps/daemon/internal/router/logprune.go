@@ -0,0 +1,82 @@
+package router
+
+import (
+	"fmt"
+	"net/http"
+	"time"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/pathlock"
+)
+
+// handleLogPrune serves POST /api/servers/:uuid/logs/prune: delete old
+// log files by the server's pruning policy, which the panel resolves
+// from the blueprint and the server and sends along. A dry run only
+// lists what would go, for the panel's preview.
+//
+// HMAC-authenticated. Synchronous: pruning is a walk of a few log
+// directories, and the schedule waits for the result.
+func (r *Router) handleLogPrune(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if r.files == nil {
+		http.Error(w, "files disabled", http.StatusServiceUnavailable)
+		return
+	}
+	var body apitypes.LogPruneRequest
+	if err := decodeJSON(req, &body); err != nil || body.MaxAgeDays < 0 || body.MaxTotalMb < 0 {
+		writeJSONError(w, http.StatusBadRequest, "log_prune.bad_request")
+		return
+	}
+	for _, g := range body.Globs {
+		if !files.ValidLogGlob(g) {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "log_prune.bad_request", "invalid glob "+g)
+			return
+		}
+	}
+	scope := "files.delete"
+	if body.DryRun {
+		scope = "files.read"
+	}
+	if !r.authorizePanelOrUser(w, req, serverID, scope) {
+		return
+	}
+	globs := body.Globs
+	if len(globs) == 0 {
+		globs = files.DefaultLogGlobs
+	}
+	var wants []pathlock.Want
+	for _, g := range globs {
+		if body.DryRun {
+			wants = append(wants, pathlock.R(files.LogGlobBase(g)))
+		} else {
+			wants = append(wants, pathlock.W(files.LogGlobBase(g)))
+		}
+	}
+	lock, ok := r.lockPaths(w, req, serverID, "log prune", wants...)
+	if !ok {
+		return
+	}
+	defer lock.Release()
+	res, err := r.files.PruneLogs(req.Context(), serverID, files.LogPruneOptions{
+		Globs:    globs,
+		MaxAge:   time.Duration(body.MaxAgeDays) * 24 * time.Hour,
+		MaxTotal: body.MaxTotalMb << 20,
+		DryRun:   body.DryRun,
+	})
+	if err != nil {
+		writeJSONErrorMessage(w, http.StatusInternalServerError, "log_prune.failed", err.Error())
+		return
+	}
+	if !body.DryRun && len(res.Removed) > 0 {
+		r.manager.Get(serverID).PublishDaemon(fmt.Sprintf("Pruned %d log file(s), freeing %.2f MB", len(res.Removed), float64(res.Bytes)/1024/1024))
+	}
+	writeJSON(w, res)
+}
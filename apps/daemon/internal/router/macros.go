@@ -0,0 +1,129 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"net/http"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/jobs"
+	"github.com/stellarstack/daemon/internal/macros"
+)
+
+// handleCommandMacros serves /api/servers/:uuid/commands/*. HMAC-
+// authenticated like handleCommand: the panel manages macros for users
+// (the user token needs console.read to list, console.write for the
+// rest) and the schedule executor runs them on its own account.
+//
+//	GET    /commands/macros        → list
+//	PUT    /commands/macros/:name  → create / replace ({steps})
+//	DELETE /commands/macros/:name  → remove
+//	POST   /commands/macro/:name   → run as a job (202 + snapshot)
+func (r *Router) handleCommandMacros(w http.ResponseWriter, req *http.Request, serverID string, rest []string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	switch {
+	case len(rest) == 1 && rest[0] == "macros" && req.Method == http.MethodGet:
+		if !r.authorizeActingUser(w, req, serverID, "console.read") {
+			return
+		}
+		list, err := r.macros.List(serverID)
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "macros.list_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"macros": list})
+	case len(rest) == 2 && rest[0] == "macros" && req.Method == http.MethodPut:
+		if !r.authorizeActingUser(w, req, serverID, "console.write") {
+			return
+		}
+		var body struct {
+			Steps []macros.Step `json:"steps"`
+		}
+		if err := decodeJSON(req, &body); err != nil {
+			writeJSONError(w, http.StatusBadRequest, "macros.bad_request")
+			return
+		}
+		m := macros.Macro{Name: rest[1], Steps: body.Steps}
+		if err := m.Validate(); err != nil {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "macros.invalid", err.Error())
+			return
+		}
+		if err := r.macros.Put(serverID, m); err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "macros.save_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"macro": m})
+	case len(rest) == 2 && rest[0] == "macros" && req.Method == http.MethodDelete:
+		if !r.authorizeActingUser(w, req, serverID, "console.write") {
+			return
+		}
+		err := r.macros.Delete(serverID, rest[1])
+		if errors.Is(err, macros.ErrNotFound) {
+			writeJSONError(w, http.StatusNotFound, "macros.not_found")
+			return
+		}
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "macros.delete_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"ok": true})
+	case len(rest) == 2 && rest[0] == "macro" && req.Method == http.MethodPost:
+		if !r.authorizePanelOrUser(w, req, serverID, "console.write") {
+			return
+		}
+		r.runCommandMacro(w, req, serverID, rest[1])
+	default:
+		http.NotFound(w, req)
+	}
+}
+
+// runCommandMacro sends each step's command to the container after its
+// delay. The server must be running when the macro starts; a step that
+// finds it stopped (e.g. the macro's own `stop`) ends the job early
+// without failing it, so "warn, wait, stop" macros complete cleanly.
+func (r *Router) runCommandMacro(w http.ResponseWriter, req *http.Request, serverID, name string) {
+	m, err := r.macros.Get(serverID, name)
+	if errors.Is(err, macros.ErrNotFound) {
+		writeJSONError(w, http.StatusNotFound, "macros.not_found")
+		return
+	}
+	if err != nil {
+		writeJSONError(w, http.StatusInternalServerError, "macros.load_failed")
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if srv.Environment().State() == environment.StateOffline {
+		writeJSONError(w, http.StatusConflict, "macros.not_running")
+		return
+	}
+	job := r.jobs.Start(serverID, "macro", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+		j.SetMessage(m.Name)
+		total := int64(len(m.Steps))
+		for i, step := range m.Steps {
+			if d := step.Delay(); d > 0 {
+				select {
+				case <-ctx.Done():
+					return ctx.Err()
+				case <-time.After(d):
+				}
+			}
+			if srv.Environment().State() == environment.StateOffline {
+				return nil
+			}
+			cmdCtx, cancel := context.WithTimeout(ctx, 5*time.Second)
+			err := srv.Environment().SendCommand(cmdCtx, step.Command)
+			cancel()
+			if err != nil {
+				return fmt.Errorf("step %d: %w", i+1, err)
+			}
+			j.Progress(int64(i+1), total)
+		}
+		return nil
+	})
+	writeJobAccepted(w, job)
+}
@@ -0,0 +1,59 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"net/http"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/pathlock"
+)
+
+// lockWait is how long an operation waits for a conflicting one to
+// release its paths before giving up with 423 (files.lock_wait_seconds).
+func (r *Router) lockWait() time.Duration {
+	return time.Duration(r.cfg.Files.LockWaitSeconds) * time.Second
+}
+
+// lockPaths takes wants for a call that does its work before answering.
+// Writes a 423 naming the conflicting operation and returns false when
+// the wait runs out.
+func (r *Router) lockPaths(w http.ResponseWriter, req *http.Request, serverID, op string, wants ...pathlock.Want) (*pathlock.Lock, bool) {
+	lock, err := r.locks.Acquire(req.Context(), serverID, op, r.lockWait(), wants...)
+	if err != nil {
+		writeLocked(w, err)
+		return nil, false
+	}
+	return lock, true
+}
+
+// checkPaths is the pre-flight for work that runs as a job: answer 423
+// now if wants conflict, before handing back a job id. The job then
+// takes the locks itself with lockJob. A retry of an existing job is
+// let through, since that job holds the paths.
+func (r *Router) checkPaths(w http.ResponseWriter, req *http.Request, serverID string, wants ...pathlock.Want) bool {
+	if r.jobs.Holds(serverID, idempotencyKey(req)) {
+		return true
+	}
+	if err := r.locks.Check(serverID, wants...); err != nil {
+		writeLocked(w, err)
+		return false
+	}
+	return true
+}
+
+// lockJob takes wants from inside a job, waiting as lockPaths does. A
+// conflict that appeared since checkPaths fails the job.
+func (r *Router) lockJob(ctx context.Context, serverID, op string, wants ...pathlock.Want) (*pathlock.Lock, error) {
+	return r.locks.Acquire(ctx, serverID, op, r.lockWait(), wants...)
+}
+
+// writeLocked answers a pathlock conflict with 423; anything else is a
+// cancelled request and gets the usual 503.
+func writeLocked(w http.ResponseWriter, err error) {
+	if errors.Is(err, pathlock.ErrLocked) {
+		writeJSONErrorMessage(w, http.StatusLocked, "files.locked", err.Error())
+		return
+	}
+	writeJSONError(w, http.StatusServiceUnavailable, "files.timeout")
+}
@@ -0,0 +1,104 @@
+package router
+
+import (
+	"context"
+	"log"
+	"net/http"
+	"regexp"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// playerActions are the actions a blueprint may template. Anything
+// else is a 404 rather than a free-form command channel.
+var playerActions = map[string]bool{
+	"whitelist.add":    true,
+	"whitelist.remove": true,
+	"op":               true,
+	"deop":             true,
+	"kick":             true,
+	"ban":              true,
+	"pardon":           true,
+}
+
+// playerNameRE accepts Java/Bedrock usernames (Floodgate prefixes
+// Bedrock players with '.') and rejects anything that could smuggle a
+// second command out of the template.
+var playerNameRE = regexp.MustCompile(`^[A-Za-z0-9_.\-]{1,32}$`)
+
+const maxPlayerReason = 256
+
+// handlePlayers serves POST /api/servers/:uuid/players/:action with
+// body {player, reason?}. HMAC-authenticated: the panel forwards
+// requests from users and plugins. The action is rendered through the
+// blueprint's playerCommands template and written to the console, so
+// callers never need to know the game's command syntax.
+func (r *Router) handlePlayers(w http.ResponseWriter, req *http.Request, serverID, action string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !playerActions[action] {
+		writeJSONError(w, http.StatusNotFound, "players.unknown_action")
+		return
+	}
+	if !r.authorizeActingUser(w, req, serverID, "console.write") {
+		return
+	}
+	var body struct {
+		Player string `json:"player"`
+		Reason string `json:"reason"`
+	}
+	if err := decodeJSON(req, &body); err != nil {
+		writeJSONError(w, http.StatusBadRequest, "players.bad_request")
+		return
+	}
+	if !playerNameRE.MatchString(body.Player) {
+		writeJSONError(w, http.StatusBadRequest, "players.invalid_player")
+		return
+	}
+	reason := strings.TrimSpace(body.Reason)
+	if len(reason) > maxPlayerReason || strings.ContainsAny(reason, "\r\n") {
+		writeJSONError(w, http.StatusBadRequest, "players.invalid_reason")
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if srv.Environment().State() == environment.StateOffline {
+		writeJSONError(w, http.StatusConflict, "players.not_running")
+		return
+	}
+	ctx, cancel := context.WithTimeout(req.Context(), 10*time.Second)
+	defer cancel()
+	// A daemon restart leaves the running server without its config
+	// until the next power action; pull it so the templates are known.
+	if srv.Config().PlayerCommands == nil {
+		if err := syncServerConfig(ctx, srv); err != nil {
+			log.Printf("players %s: fetch config: %v", action, err)
+		}
+	}
+	tmpl := srv.Config().PlayerCommands[action]
+	if tmpl == "" {
+		writeJSONError(w, http.StatusNotFound, "players.unsupported_action")
+		return
+	}
+	line := renderPlayerCommand(tmpl, body.Player, reason)
+	if err := srv.Environment().SendCommand(ctx, line); err != nil {
+		writeJSONError(w, http.StatusBadRequest, "command.send_failed")
+		return
+	}
+	writeJSON(w, map[string]any{"ok": true, "command": line})
+}
+
+// renderPlayerCommand substitutes {{player}} and {{reason}}. An empty
+// reason collapses the whitespace it leaves behind so `kick {{player}}
+// {{reason}}` doesn't send a trailing space.
+func renderPlayerCommand(tmpl, player, reason string) string {
+	out := strings.NewReplacer("{{player}}", player, "{{reason}}", reason).Replace(tmpl)
+	return strings.Join(strings.Fields(out), " ")
+}
@@ -2,9 +2,18 @@ package router
 
 import (
 	"context"
+	"encoding/json"
+	"errors"
 	"net/http"
+	"regexp"
 	"time"
 
+	"github.com/google/uuid"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/events"
+	"github.com/stellarstack/daemon/internal/logging"
 	"github.com/stellarstack/daemon/internal/server"
 )
 
@@ -12,47 +21,121 @@ import (
 // `set state` WS event but reachable from server-side processes that
 // don't hold a browser session — currently the schedule executor.
 //
-// HMAC-authenticated, body: { action: "start" | "stop" | "restart" | "kill" }.
-// Returns once the daemon has the action queued; status is observable via
+//	POST {action: "start" | "stop" | "restart" | "kill"} → queue it
+//	POST {action: "pause" | "unpause"}                   → freeze / thaw it
+//	POST {action: "restart", countdown: {seconds, message?, warnings?}}
+//	GET                                                 → {running, pending}
+//	DELETE                                              → cancel a countdown
+//
+// A countdown restart warns players in the console (see
+// server.Countdown) and holds its place in the queue until it reaches
+// zero; stopping the server or cancelling skips the restart.
+//
+// HMAC-authenticated. The user token needs control.<action> (kill and
+// pause are control.stop, unpause control.start), or the queue view
+// stats.read; schedules post on the panel's own account (see
+// authorizePanelOrUser). A POST returns once the action is queued,
+// with its ticket; 409 power.in_progress (and that request's ticket)
+// when the same action is already running or queued, 429
+// power.queue_full when too many are waiting. Status is observable via
 // the usual /api/remote/servers/:id/container/status callback.
 func (r *Router) handlePower(w http.ResponseWriter, req *http.Request, serverID string) {
 	if !r.verifyDaemonHMAC(req) {
 		http.Error(w, "unauthorized", http.StatusUnauthorized)
 		return
 	}
-	var body struct {
-		Action string `json:"action"`
+	srv := r.manager.Get(serverID)
+	switch req.Method {
+	case http.MethodGet:
+		if !r.authorizeActingUser(w, req, serverID, "stats.read") {
+			return
+		}
+		writeJSON(w, srv.PowerQueue())
+		return
+	case http.MethodDelete:
+		if !r.authorizeActingUser(w, req, serverID, "control.restart") {
+			return
+		}
+		if !srv.CancelCountdown() {
+			writeJSONError(w, http.StatusNotFound, "power.no_countdown")
+			return
+		}
+		writeJSON(w, map[string]any{"ok": true})
+		return
+	case http.MethodPost:
+	default:
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
 	}
+	var body apitypes.PowerRequest
 	if err := decodeJSON(req, &body); err != nil {
 		writeJSONError(w, http.StatusBadRequest, "power.bad_request")
 		return
 	}
 	switch body.Action {
-	case "start", "stop", "restart", "kill":
+	case "start", "stop", "restart", "kill", "pause", "unpause":
 	default:
 		writeJSONError(w, http.StatusBadRequest, "power.unknown_action")
 		return
 	}
-	srv := r.manager.Get(serverID)
-	go func(action string) {
-		ctx, cancel := context.WithTimeout(context.Background(), 15*time.Minute)
-		defer cancel()
-		_ = srv.HandlePower(ctx, server.PowerAction(action))
-	}(body.Action)
-	writeJSON(w, map[string]any{"ok": true})
+	var countdown server.Countdown
+	if body.Countdown != nil {
+		if body.Action != "restart" {
+			writeJSONError(w, http.StatusBadRequest, "power.bad_request")
+			return
+		}
+		var err error
+		if countdown, err = server.Countdown(*body.Countdown).Normalize(); err != nil {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "power.bad_countdown", err.Error())
+			return
+		}
+	}
+	if !r.authorizePanelOrUser(w, req, serverID, powerScope(body.Action)) {
+		return
+	}
+	ticket, err := srv.EnqueuePower(server.PowerAction(body.Action), func(ctx context.Context) error {
+		// A stopped server has no one to warn; restart it straight away.
+		if countdown.Seconds > 0 && srv.Environment().State() == environment.StateRunning {
+			if err := srv.RunCountdown(ctx, countdown); err != nil {
+				return err
+			}
+		}
+		// Same fresh config pull as the WS path — schedules firing after
+		// a daemon restart would otherwise start with an empty config.
+		return syncServerConfig(ctx, srv)
+	})
+	switch {
+	case errors.Is(err, server.ErrPowerInProgress):
+		w.Header().Set("Content-Type", "application/json")
+		w.WriteHeader(http.StatusConflict)
+		_ = json.NewEncoder(w).Encode(map[string]any{
+			"error":  map[string]string{"code": "power.in_progress", "message": body.Action + " already in progress"},
+			"ticket": ticket,
+		})
+	case errors.Is(err, server.ErrPowerQueueFull):
+		writeJSONError(w, http.StatusTooManyRequests, "power.queue_full")
+	default:
+		writeJSON(w, map[string]any{"ok": true, "ticket": ticket})
+	}
 }
 
+// Limits on a captured command: how long the daemon holds the request
+// open and how many lines it keeps (the latest ones).
+const (
+	commandCaptureMaxWindow = 30 * time.Second
+	commandCaptureMaxLines  = 500
+)
+
 // handleCommand writes a single line to the container's stdin via a
 // fresh attach. HMAC-authenticated; used by schedule tasks of type
-// "command".
+// "command" and the panel's command runner, which sets capture to get
+// the command's output back (see captureCommand).
 func (r *Router) handleCommand(w http.ResponseWriter, req *http.Request, serverID string) {
 	if !r.verifyDaemonHMAC(req) {
 		http.Error(w, "unauthorized", http.StatusUnauthorized)
 		return
 	}
-	var body struct {
-		Line string `json:"line"`
-	}
+	var body apitypes.CommandRequest
 	if err := decodeJSON(req, &body); err != nil {
 		writeJSONError(w, http.StatusBadRequest, "command.bad_request")
 		return
@@ -61,7 +144,14 @@ func (r *Router) handleCommand(w http.ResponseWriter, req *http.Request, serverI
 		writeJSONError(w, http.StatusBadRequest, "command.empty")
 		return
 	}
+	if !r.authorizePanelOrUser(w, req, serverID, "console.write") {
+		return
+	}
 	srv := r.manager.Get(serverID)
+	if body.Capture != nil {
+		captureCommand(w, req, srv, body.Line, *body.Capture)
+		return
+	}
 	ctx, cancel := context.WithTimeout(req.Context(), 5*time.Second)
 	defer cancel()
 	if err := srv.Environment().SendCommand(ctx, body.Line); err != nil {
@@ -70,3 +160,130 @@ func (r *Router) handleCommand(w http.ResponseWriter, req *http.Request, serverI
 	}
 	writeJSON(w, map[string]any{"ok": true})
 }
+
+// captureCommand sends line and answers with the console output that
+// follows it: every line for the capture window, or up to and including
+// the first line matching capture.Until. The console has no notion of
+// which command printed what, so anything else the server logs in the
+// window is captured too; a tight Until keeps that down.
+func captureCommand(w http.ResponseWriter, req *http.Request, srv *server.Server, line string, capture apitypes.CommandCapture) {
+	window := time.Duration(capture.WindowMs) * time.Millisecond
+	if window <= 0 || window > commandCaptureMaxWindow {
+		writeJSONError(w, http.StatusBadRequest, "command.bad_capture")
+		return
+	}
+	var until *regexp.Regexp
+	if capture.Until != "" {
+		re, err := regexp.Compile(capture.Until)
+		if err != nil {
+			writeJSONError(w, http.StatusBadRequest, "command.bad_capture")
+			return
+		}
+		until = re
+	}
+
+	// Subscribe before sending so the first line of output can't slip
+	// past.
+	sub := srv.Bus().Subscribe()
+	defer sub.Close()
+	ctx, cancel := context.WithTimeout(req.Context(), 5*time.Second)
+	err := srv.Environment().SendCommand(ctx, line)
+	cancel()
+	if err != nil {
+		writeJSONError(w, http.StatusBadRequest, "command.send_failed")
+		return
+	}
+	result := apitypes.CommandResult{OK: true, ID: uuid.NewString(), Lines: []string{}}
+	logging.Debugf("server", "server %s: command %s sent, capturing output for up to %s", srv.UUID(), result.ID, window)
+
+	deadline := time.NewTimer(window)
+	defer deadline.Stop()
+capture:
+	for {
+		select {
+		case <-req.Context().Done():
+			return
+		case <-deadline.C:
+			break capture
+		case frame, ok := <-sub.Recv():
+			if !ok {
+				break capture
+			}
+			out, ok := consoleLine(frame)
+			if !ok {
+				continue
+			}
+			result.Lines = append(result.Lines, out)
+			if len(result.Lines) > commandCaptureMaxLines {
+				result.Lines = result.Lines[1:]
+				result.Truncated = true
+			}
+			if until != nil && until.MatchString(out) {
+				result.Matched = true
+				break capture
+			}
+		}
+	}
+	logging.Debugf("server", "server %s: command %s captured %d lines (matched=%t)", srv.UUID(), result.ID, len(result.Lines), result.Matched)
+	writeJSON(w, result)
+}
+
+// consoleLine returns the text of a "console output" frame.
+func consoleLine(frame events.Frame) (string, bool) {
+	var env struct {
+		Event string            `json:"event"`
+		Args  []json.RawMessage `json:"args"`
+	}
+	if json.Unmarshal(frame, &env) != nil || env.Event != "console output" || len(env.Args) == 0 {
+		return "", false
+	}
+	var line string
+	if json.Unmarshal(env.Args[0], &line) != nil {
+		return "", false
+	}
+	return line, true
+}
+
+// handlePowerValidate serves POST /api/servers/:uuid/power/validate: a
+// dry run of start. Pulls the panel config exactly as a start would,
+// then runs the server's pre-flight checks and returns the checklist.
+// ok is false when any check failed; nothing is started or pulled.
+func (r *Router) handlePowerValidate(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.authorizeActingUser(w, req, serverID, "control.start") {
+		return
+	}
+	srv := r.manager.Get(serverID)
+	ctx, cancel := context.WithTimeout(req.Context(), 30*time.Second)
+	defer cancel()
+	checks := []server.Check{{ID: "config", Status: server.CheckPass, Message: "fetched the panel's current config"}}
+	if err := syncServerConfig(ctx, srv); err != nil {
+		checks[0] = server.Check{ID: "config", Status: server.CheckFail, Message: "couldn't fetch config from the panel: " + err.Error()}
+	}
+	checks = append(checks, srv.Preflight(ctx)...)
+	ok := true
+	for _, c := range checks {
+		if c.Status == server.CheckFail {
+			ok = false
+		}
+	}
+	writeJSON(w, map[string]any{"ok": ok, "checks": checks})
+}
+
+// powerScope is the user token scope a power action needs.
+func powerScope(action string) string {
+	switch action {
+	case "kill", "pause":
+		return "control.stop"
+	case "unpause":
+		return "control.start"
+	}
+	return "control." + action
+}
@@ -0,0 +1,53 @@
+package router
+
+import (
+	"encoding/json"
+	"io"
+	"net/http"
+
+	"github.com/stellarstack/daemon/internal/proxy"
+)
+
+// handleProxy serves the shared-port proxy control surface:
+//
+//	GET /api/proxy/rules  → installed rules
+//	PUT /api/proxy/rules  → replace rules ({rules}); live, no restart
+//	GET /api/proxy/stats  → per-backend connection and byte counters
+//
+// 404 proxy.disabled when the node doesn't run the proxy.
+func (r *Router) handleProxy(w http.ResponseWriter, req *http.Request) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if r.proxy == nil {
+		writeJSONError(w, http.StatusNotFound, "proxy.disabled")
+		return
+	}
+	switch {
+	case req.URL.Path == "/api/proxy/rules" && req.Method == http.MethodGet:
+		writeJSON(w, map[string]any{"rules": r.proxy.Rules()})
+	case req.URL.Path == "/api/proxy/rules" && req.Method == http.MethodPut:
+		var body struct {
+			Rules []proxy.Rule `json:"rules"`
+		}
+		// A rule set can outgrow decodeJSON's 16KB cap on busy nodes.
+		if err := json.NewDecoder(io.LimitReader(req.Body, 1<<20)).Decode(&body); err != nil {
+			writeJSONError(w, http.StatusBadRequest, "proxy.bad_request")
+			return
+		}
+		if err := proxy.Validate(body.Rules); err != nil {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "proxy.invalid_rules", err.Error())
+			return
+		}
+		if err := r.proxy.SetRules(body.Rules); err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "proxy.save_failed")
+			return
+		}
+		writeJSON(w, map[string]any{"rules": r.proxy.Rules()})
+	case req.URL.Path == "/api/proxy/stats" && req.Method == http.MethodGet:
+		writeJSON(w, r.proxy.Stats())
+	default:
+		http.NotFound(w, req)
+	}
+}
@@ -11,6 +11,7 @@ import (
 	"crypto/hmac"
 	"crypto/sha256"
 	"encoding/hex"
+	"encoding/json"
 	"errors"
 	"fmt"
 	"log"
@@ -20,10 +21,19 @@ import (
 	"time"
 
 	"github.com/stellarstack/daemon/internal/backup"
+	"github.com/stellarstack/daemon/internal/clock"
 	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/docker"
 	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/jobs"
 	"github.com/stellarstack/daemon/internal/jwt"
+	"github.com/stellarstack/daemon/internal/logging"
+	"github.com/stellarstack/daemon/internal/macros"
+	"github.com/stellarstack/daemon/internal/pathlock"
+	"github.com/stellarstack/daemon/internal/proxy"
+	"github.com/stellarstack/daemon/internal/selfupdate"
 	"github.com/stellarstack/daemon/internal/server"
+	"github.com/stellarstack/daemon/internal/sftp"
 )
 
 // Router wires the WS and remote handlers against the shared dependencies.
@@ -33,13 +43,41 @@ type Router struct {
 	manager  *server.Manager
 	files    *files.Manager
 	backups  *backup.Manager
+	jobs     *jobs.Manager
+	macros   *macros.Store
+	proxy    *proxy.Proxy        // nil when the shared-port proxy is disabled
+	updater  *selfupdate.Updater // nil when self-update is not configured
+	restart  func()
+	logFile  *logging.File // nil when logging to stderr
+	sftp     *sftp.Server  // nil when the SFTP listener failed to start
+	docker   *docker.Client
+
+	fileLists    slotLimiter
+	fileJobs     slotLimiter
+	locks        pathlock.Map
+	inbound      inboundTransfers
+	transferRate *transferRate // nil when transfer bandwidth is unlimited
+	feed         *stateFeed
+	imagePulls   chan struct{} // one slot per image_pull_concurrency
 }
 
-func New(cfg *config.Config, v *jwt.Verifier, m *server.Manager, f *files.Manager, b *backup.Manager) *Router {
+func New(cfg *config.Config, v *jwt.Verifier, m *server.Manager, f *files.Manager, b *backup.Manager, j *jobs.Manager, mc *macros.Store, px *proxy.Proxy) *Router {
 	// Inform the WS handler where bind mounts live so it can compute
 	// per-server paths without threading config in.
 	serverDirRoot = cfg.DataDir
-	return &Router{cfg: cfg, verifier: v, manager: m, files: f, backups: b}
+	// Job progress rides the same per-server bus as console output so
+	// any open console socket sees `job progress` frames for free. Only
+	// the final frame is replayed; a reconnecting client reads the rest
+	// off GET /jobs.
+	j.SetListener(func(snap jobs.Snapshot) {
+		if snap.Status.Terminal() {
+			m.Get(snap.ServerID).PublishEvent("job progress", snap)
+		} else {
+			m.Get(snap.ServerID).PublishTransient("job progress", snap)
+		}
+	})
+	m.SetConfigSync(syncServerConfig)
+	return &Router{cfg: cfg, verifier: v, manager: m, files: f, backups: b, jobs: j, macros: mc, proxy: px, transferRate: newTransferRate(cfg.Transfer.BandwidthMbps), feed: newStateFeed(), imagePulls: make(chan struct{}, cfg.ImagePullConcurrency)}
 }
 
 // Handler returns the http.Handler the daemon should serve.
@@ -47,11 +85,30 @@ func (r *Router) Handler() http.Handler {
 	mux := http.NewServeMux()
 	// Browser-facing WS. Path: /api/servers/:uuid/ws
 	mux.HandleFunc("/api/servers/", r.routeServerSubpath)
+	// Every server's state at once for the dashboard. API → daemon,
+	// HMAC-authenticated.
+	mux.HandleFunc("/api/servers/states", r.handleServerStates)
+	mux.HandleFunc("/api/servers/states/changes", r.handleServerStateChanges)
 	// Remote (API → daemon) control. Path: /api/remote/...
 	mux.HandleFunc("/api/remote/", r.routeRemote)
-	// Health probe.
+	// Node capacity/commitment report. API → daemon, HMAC-authenticated.
+	mux.HandleFunc("/api/system", r.handleSystem)
+	// Self-update, rollback, log levels, log tail and queue state. API →
+	// daemon, HMAC-authenticated.
+	mux.HandleFunc("/api/system/", r.routeSystem)
+	// Shared-port proxy rules + stats. API → daemon, HMAC-authenticated.
+	mux.HandleFunc("/api/proxy/", r.handleProxy)
+	// Health probe, with the clock check: a node whose clock is off by
+	// more than the token leeway refuses its users.
 	mux.HandleFunc("/healthz", func(w http.ResponseWriter, _ *http.Request) {
-		_, _ = w.Write([]byte(`{"ok":true}`))
+		type clockHealth struct {
+			OK bool `json:"ok"`
+			clock.Report
+		}
+		writeJSON(w, map[string]any{
+			"ok":    true,
+			"clock": clockHealth{OK: clock.Offset().Abs() <= r.verifier.Leeway(), Report: clock.Current()},
+		})
 	})
 	return cors(mux)
 }
@@ -73,7 +130,7 @@ func cors(next http.Handler) http.Handler {
 		)
 		w.Header().Set(
 			"Access-Control-Allow-Headers",
-			"Authorization, Content-Type, X-Stellar-Node-Id, X-Stellar-Timestamp, X-Stellar-Transfer-Token, X-Stellar-Transfer-Timestamp",
+			"Authorization, Content-Type, Idempotency-Key, X-Stellar-Node-Id, X-Stellar-Timestamp, X-Stellar-Transfer-Token, X-Stellar-Transfer-Timestamp, X-Stellar-Transfer-Urgent, X-Stellar-Transfer-Encryption",
 		)
 		w.Header().Set("Access-Control-Max-Age", "600")
 		if req.Method == http.MethodOptions {
@@ -98,16 +155,46 @@ func (r *Router) routeServerSubpath(w http.ResponseWriter, req *http.Request) {
 		r.handleWS(w, req, uuid)
 	case len(parts) >= 4 && parts[3] == "files":
 		r.handleFiles(w, req, uuid)
+	case len(parts) >= 4 && parts[3] == "jobs":
+		r.handleJobs(w, req, uuid, parts[4:])
 	case len(parts) >= 4 && parts[3] == "backups":
 		r.handleBackups(w, req, uuid)
 	case len(parts) == 5 && parts[3] == "transfer" && parts[4] == "ingest":
 		r.handleTransferIngest(w, req, uuid)
 	case len(parts) == 5 && parts[3] == "transfer" && parts[4] == "push":
 		r.handleTransferPush(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "transfer" && parts[4] == "status":
+		r.handleTransferStatus(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "transfer" && parts[4] == "manifest":
+		r.handleTransferManifest(w, req, uuid)
 	case len(parts) == 4 && parts[3] == "power":
 		r.handlePower(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "power" && parts[4] == "validate":
+		r.handlePowerValidate(w, req, uuid)
+	case len(parts) == 4 && parts[3] == "console":
+		r.handleConsole(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "console" && parts[4] == "stream":
+		r.handleConsoleStream(w, req, uuid)
+	case len(parts) == 4 && parts[3] == "update":
+		r.handleServerUpdate(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "logs" && parts[4] == "prune":
+		r.handleLogPrune(w, req, uuid)
 	case len(parts) == 4 && parts[3] == "command":
 		r.handleCommand(w, req, uuid)
+	case len(parts) >= 5 && parts[3] == "commands":
+		r.handleCommandMacros(w, req, uuid, parts[4:])
+	case len(parts) == 5 && parts[3] == "players":
+		r.handlePlayers(w, req, uuid, parts[4])
+	case len(parts) == 5 && parts[3] == "config" && parts[4] == "effective":
+		r.handleEffectiveConfig(w, req, uuid)
+	case len(parts) == 5 && parts[3] == "config" && parts[4] == "apply":
+		r.handleApplyConfig(w, req, uuid)
+	case len(parts) == 4 && parts[3] == "allocations":
+		r.handleAllocationSync(w, req, uuid)
+	case len(parts) == 4 && parts[3] == "environment":
+		r.handleEnvironment(w, req, uuid)
+	case len(parts) == 4 && parts[3] == "exec":
+		r.handleExec(w, req, uuid)
 	case len(parts) >= 4 && parts[3] == "install":
 		// API-initiated install. Verified with daemon HMAC, not browser
 		// JWT, so route through the remote auth middleware.
@@ -130,8 +217,12 @@ func (r *Router) routeRemote(w http.ResponseWriter, req *http.Request) {
 
 // verifyDaemonHMAC checks that the request was signed by the API using
 // the same per-node key the daemon holds. Mirrors the panel client's
-// signing scheme: HMAC-SHA256 over `<nodeId>|<unix-seconds>`.
+// signing scheme: HMAC-SHA256 over `<nodeId>|<unix-seconds>`. Requests
+// from an admitted peer on the local socket (see socket.go) need none.
 func (r *Router) verifyDaemonHMAC(req *http.Request) bool {
+	if r.localPeer(req) {
+		return true
+	}
 	nodeID := req.Header.Get("X-Stellar-Node-Id")
 	ts := req.Header.Get("X-Stellar-Timestamp")
 	auth := req.Header.Get("Authorization")
@@ -162,6 +253,105 @@ func (r *Router) verifyDaemonHMAC(req *http.Request) bool {
 	return hmac.Equal(expected, provided)
 }
 
+// authorizeServerToken verifies the browser JWT carried in `?token=`
+// and checks it is bound to serverID on this node. Writes the 401 and
+// returns ok=false on failure so callers can just return.
+func (r *Router) authorizeServerToken(w http.ResponseWriter, req *http.Request, serverID string) (*jwt.Claims, bool) {
+	token := req.URL.Query().Get("token")
+	if token == "" {
+		http.Error(w, "missing token", http.StatusUnauthorized)
+		return nil, false
+	}
+	claims, err := r.verifier.Verify(token)
+	if err != nil {
+		r.refuseToken(w, err)
+		return nil, false
+	}
+	if claims.Server != serverID || claims.Node != r.cfg.NodeID {
+		http.Error(w, "token scope mismatch", http.StatusUnauthorized)
+		return nil, false
+	}
+	return claims, true
+}
+
+// actingHeader marks a signed request the panel makes for itself (a
+// schedule task, a scheduled backup) rather than for a user:
+// "X-Stellar-Acting: panel". Only routes that use authorizePanelOrUser
+// accept it.
+const actingHeader = "X-Stellar-Acting"
+
+// authorizeActingUser checks the user a daemon-signed request is made
+// on behalf of. The panel passes them as X-Stellar-User-Token: a JWT
+// like the browser's, minted for that user with their permissions on
+// this server. The request needs `scope` in it as well as the node
+// signature, so a bug or bypass in the panel's own checks can't turn
+// the node key into a user's missing permission. The token is required:
+// leaving it out is refused, not taken as the panel acting for itself,
+// except from an admitted local-socket peer, which is root on the node.
+// Writes the 401/403 and returns false on failure.
+func (r *Router) authorizeActingUser(w http.ResponseWriter, req *http.Request, serverID, scope string) bool {
+	return r.authorizeActor(w, req, serverID, scope, false)
+}
+
+// authorizePanelOrUser is authorizeActingUser for the routes the panel
+// also calls on its own account: power, commands, macros, updates, log
+// pruning and backup creation, which schedules run. Those requests say
+// so with actingHeader instead of carrying a user token.
+func (r *Router) authorizePanelOrUser(w http.ResponseWriter, req *http.Request, serverID, scope string) bool {
+	return r.authorizeActor(w, req, serverID, scope, true)
+}
+
+func (r *Router) authorizeActor(w http.ResponseWriter, req *http.Request, serverID, scope string, panelOK bool) bool {
+	token := req.Header.Get("X-Stellar-User-Token")
+	if token == "" {
+		if r.localPeer(req) || (panelOK && req.Header.Get(actingHeader) == "panel") {
+			return true
+		}
+		writeJSONErrorMessage(w, http.StatusUnauthorized, "auth.user_token_invalid", "missing X-Stellar-User-Token")
+		return false
+	}
+	claims, err := r.verifier.Verify(token)
+	if err != nil && r.clockSkewed(err) {
+		r.refuseToken(w, err)
+		return false
+	}
+	if err != nil || claims.Server != serverID || claims.Node != r.cfg.NodeID {
+		writeJSONError(w, http.StatusUnauthorized, "auth.user_token_invalid")
+		return false
+	}
+	if !claims.HasScope(scope) {
+		writeJSONErrorMessage(w, http.StatusForbidden, "permissions.denied", "missing "+scope)
+		return false
+	}
+	return true
+}
+
+// clockSkewed reports whether err, a token refusal, is down to this
+// node's clock rather than the token: it was issued in the node's
+// future, or it expired while the clock is known to be off by more
+// than the leeway.
+func (r *Router) clockSkewed(err error) bool {
+	switch {
+	case errors.Is(err, jwt.ErrNotYetValid):
+		return true
+	case errors.Is(err, jwt.ErrExpired):
+		return clock.Offset().Abs() > r.verifier.Leeway()
+	}
+	return false
+}
+
+// refuseToken answers a token Verify refused with a 401: code
+// auth.clock_skew when the node's clock is to blame, so the panel can
+// say so instead of sending the user to log in again.
+func (r *Router) refuseToken(w http.ResponseWriter, err error) {
+	if !r.clockSkewed(err) {
+		http.Error(w, "invalid token: "+err.Error(), http.StatusUnauthorized)
+		return
+	}
+	writeJSONErrorMessage(w, http.StatusUnauthorized, "auth.clock_skew",
+		fmt.Sprintf("this node's clock is %s off; fix its time sync", clock.Offset().Round(time.Second)))
+}
+
 func abs(x int64) int64 {
 	if x < 0 {
 		return -x
@@ -184,4 +374,15 @@ func writeJSONError(w http.ResponseWriter, status int, code string) {
 	_, _ = fmt.Fprintf(w, `{"error":{"code":%q}}`, code)
 }
 
+// writeJSONErrorMessage is writeJSONError plus a human-readable message
+// for errors the panel surfaces verbatim (e.g. admission refusals).
+func writeJSONErrorMessage(w http.ResponseWriter, status int, code, message string) {
+	w.Header().Set("Content-Type", "application/json")
+	w.WriteHeader(status)
+	buf, _ := json.Marshal(map[string]any{
+		"error": map[string]string{"code": code, "message": message},
+	})
+	_, _ = w.Write(buf)
+}
+
 func init() { _ = log.Println }
@@ -0,0 +1,236 @@
+package router
+
+import (
+	"context"
+	"log"
+	"net"
+	"net/http"
+	"regexp"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/panel"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// syncServerConfig pulls the panel's authoritative runtime config and
+// installs it on srv. Called before every power action so a panel-side
+// blueprint/variable/memory change lands on the next start. No-op when
+// the daemon runs without a panel client.
+func syncServerConfig(ctx context.Context, srv *server.Server) error {
+	p := srv.Panel()
+	if p == nil {
+		return nil
+	}
+	cfgCtx, cfgCancel := context.WithTimeout(ctx, 10*time.Second)
+	cfg, err := p.FetchServerConfig(cfgCtx, srv.UUID())
+	cfgCancel()
+	if err != nil {
+		return err
+	}
+	ports := make([]docker.PortMapping, 0, len(cfg.Ports))
+	for _, p := range cfg.Ports {
+		pm := docker.PortMapping{
+			HostIP:        p.HostIP,
+			HostPort:      p.HostPort,
+			HostPortEnd:   p.HostPortEnd,
+			ContainerPort: p.ContainerPort,
+			Protocol:      p.Protocol,
+		}
+		if pm.Span() > docker.MaxPortSpan {
+			log.Printf("server %s: skipping port block %d-%d (over %d ports)", srv.UUID(), pm.HostPort, pm.HostPortEnd, docker.MaxPortSpan)
+			continue
+		}
+		ports = append(ports, pm)
+	}
+	done := make([]*regexp.Regexp, 0, len(cfg.StartupDone))
+	for _, p := range cfg.StartupDone {
+		re, ok := compileDonePattern(p)
+		if !ok {
+			continue
+		}
+		done = append(done, re)
+	}
+	patches := make([]server.ConfigFilePatch, 0, len(cfg.ConfigFiles))
+	for _, f := range cfg.ConfigFiles {
+		patches = append(patches, server.ConfigFilePatch{
+			Path:    f.Path,
+			Parser:  f.Parser,
+			Patches: f.Patches,
+		})
+	}
+	sidecars := make([]server.Sidecar, 0, len(cfg.Sidecars))
+	for _, sc := range cfg.Sidecars {
+		sidecars = append(sidecars, server.Sidecar{
+			Name:        sc.Name,
+			Image:       sc.Image,
+			Cmd:         sc.Command,
+			Environment: sc.Environment,
+			Memory:      sc.MemoryLimitMb,
+		})
+	}
+	services := make([]server.Service, 0, len(cfg.Services))
+	for _, svc := range cfg.Services {
+		volumes := make([]server.VolumeMount, 0, len(svc.Volumes))
+		for _, v := range svc.Volumes {
+			volumes = append(volumes, server.VolumeMount{Name: v.Name, Path: v.Path})
+		}
+		services = append(services, server.Service{
+			Name:        svc.Name,
+			Image:       svc.Image,
+			Cmd:         svc.Command,
+			Environment: svc.Environment,
+			Memory:      svc.MemoryLimitMb,
+			DependsOn:   svc.DependsOn,
+			Volumes:     volumes,
+			Console:     svc.Console,
+		})
+	}
+	var console panel.ConsoleConfig
+	if cfg.Console != nil {
+		console = *cfg.Console
+	}
+	var fileRules panel.FileRules
+	if cfg.Files != nil {
+		fileRules = *cfg.Files
+	}
+	extraHosts := make([]string, 0, len(cfg.ExtraHosts))
+	for _, h := range cfg.ExtraHosts {
+		if !validExtraHost(h) {
+			log.Printf("server %s: skipping extra host %q -> %q", srv.UUID(), h.Hostname, h.IP)
+			continue
+		}
+		extraHosts = append(extraHosts, h.Hostname+":"+h.IP)
+	}
+	srv.SetConfig(server.Config{
+		DockerImage:    cfg.DockerImage,
+		StartupCommand: cfg.StartupCommand,
+		Environment:    srv.MergeEnvOverrides(cfg.Environment),
+		Stop: environment.StopConfig{
+			Type:    cfg.Stop.Type,
+			Value:   cfg.Stop.Value,
+			Timeout: time.Duration(cfg.Stop.TimeoutMs) * time.Millisecond,
+		},
+		Memory:         cfg.MemoryLimitMb,
+		MemoryOverhead: cfg.MemoryOverhead.ResolveMb(cfg.MemoryLimitMb),
+		CPUPercent:     cfg.CPULimitPercent,
+		Disk:           cfg.DiskLimitMb,
+		PortMappings:   ports,
+		HostNetwork:    cfg.HostNetwork,
+		BindMount:      filepathServerDir(srv.UUID()),
+		StartupDone:    done,
+		ConfigFiles:    patches,
+		Sidecars:       sidecars,
+		Services:       services,
+		PlayerCommands: cfg.PlayerCommands,
+		Idle:           idlePolicy(cfg.Idle),
+
+		ConsoleHistoryLines: console.HistoryLines,
+		ConsoleBuffer:       console.Buffer,
+		ExtraHosts:          extraHosts,
+		FileDenylist:        fileRules.Denylist,
+		FileHidden:          fileRules.Hidden,
+	})
+	return nil
+}
+
+// extraHostnameRE is a DNS name: labels of letters, digits and hyphens.
+var extraHostnameRE = regexp.MustCompile(`^[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?(\.[A-Za-z0-9]([A-Za-z0-9-]{0,61}[A-Za-z0-9])?)*$`)
+
+// validExtraHost refuses entries Docker would reject at create, which
+// would otherwise fail the start.
+func validExtraHost(h panel.ExtraHost) bool {
+	if !extraHostnameRE.MatchString(h.Hostname) {
+		return false
+	}
+	return h.IP == "host-gateway" || net.ParseIP(h.IP) != nil
+}
+
+// idlePolicy converts the panel's signal list into server.IdlePolicy.
+// Unknown signals are dropped; the cpu threshold only counts when the
+// cpu signal is listed.
+func idlePolicy(p *panel.IdlePolicy) server.IdlePolicy {
+	if p == nil {
+		return server.IdlePolicy{}
+	}
+	out := server.IdlePolicy{Minutes: p.Minutes, StartOnDemand: p.StartOnDemand, WakeMessage: p.WakeMessage}
+	for _, sig := range p.Signals {
+		switch sig {
+		case "players":
+			out.Players = true
+		case "console":
+			out.Console = true
+		case "cpu":
+			out.CPUPercent = p.CPUPercent
+		}
+	}
+	return out
+}
+
+// handleEffectiveConfig serves GET /api/servers/:uuid/config/effective:
+// the fully resolved container spec the next start will use. Pulls the
+// panel config first, as a start would; if the panel can't be reached
+// the installed (possibly stale) config is returned with synced=false
+// so the report still helps when the panel link is what's broken.
+func (r *Router) handleEffectiveConfig(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	// The environment in it is as sensitive as the environment route's.
+	if !r.authorizeActingUser(w, req, serverID, "control.start") {
+		return
+	}
+	srv := r.manager.Get(serverID)
+	synced := true
+	if err := syncServerConfig(req.Context(), srv); err != nil {
+		log.Printf("effective config %s: fetch config: %v", serverID, err)
+		synced = false
+	}
+	writeJSON(w, map[string]any{
+		"synced":    synced,
+		"state":     srv.Environment().State(),
+		"effective": srv.Effective(),
+	})
+}
+
+// handleApplyConfig serves POST /api/servers/:uuid/config/apply: pull
+// the panel config, then patch the blueprint's config files with the
+// current variables right away instead of at the next start. Answers
+// with each file's changed keys (old and new values) or its error, so
+// the panel can show what changed and offer the game's reload command.
+// A running server isn't told; picking the change up is up to the game.
+// HMAC-authenticated; the user token needs files.write.
+func (r *Router) handleApplyConfig(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.authorizeActingUser(w, req, serverID, "files.write") {
+		return
+	}
+	srv := r.manager.Get(serverID)
+	if err := syncServerConfig(req.Context(), srv); err != nil {
+		log.Printf("apply config %s: fetch config: %v", serverID, err)
+		writeJSONError(w, http.StatusBadGateway, "config.sync_failed")
+		return
+	}
+	results, err := srv.ApplyConfigFiles()
+	if err != nil {
+		writeJSONErrorMessage(w, http.StatusConflict, "config.apply_failed", err.Error())
+		return
+	}
+	writeJSON(w, map[string]any{
+		"state": srv.Environment().State(),
+		"files": results,
+	})
+}
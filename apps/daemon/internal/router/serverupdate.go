@@ -0,0 +1,163 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"net/http"
+	"net/url"
+	"path"
+	"time"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/files"
+	"github.com/stellarstack/daemon/internal/jobs"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// updateStaging is where the archive waits, under the server root,
+// between download and extraction. Clean never touches it.
+const updateStaging = "/.stellar-update"
+
+// handleServerUpdate serves POST /api/servers/:uuid/update, the
+// "update from URL" used to roll out modpacks and the like: download
+// and verify the archive, then (with restart, stopping a running server
+// first) clear the clean globs, extract over the server and start it
+// again. The download happens while the server is still up, and a
+// failed or mismatched download leaves the server as it was.
+//
+// HMAC-authenticated; run as a synchronous job like backups, so a panel
+// retry with the same Idempotency-Key gets the original result.
+func (r *Router) handleServerUpdate(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	var body apitypes.UpdateFromURLRequest
+	if err := decodeJSON(req, &body); err != nil || body.URL == "" || !files.ValidChecksum(body.Checksum) {
+		writeJSONError(w, http.StatusBadRequest, "server_update.bad_request")
+		return
+	}
+	for _, p := range body.Clean {
+		if !files.ValidCleanPattern(p) {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "server_update.bad_request", "invalid clean pattern "+p)
+			return
+		}
+	}
+	if !r.authorizePanelOrUser(w, req, serverID, "files.write") {
+		return
+	}
+	if body.Restart && !r.authorizePanelOrUser(w, req, serverID, "control.restart") {
+		return
+	}
+	if !r.jobs.Holds(serverID, idempotencyKey(req)) && r.jobs.Active(serverID, "server.update") > 0 {
+		writeJSONError(w, http.StatusConflict, "server_update.in_progress")
+		return
+	}
+	srv := r.manager.Get(serverID)
+	job, err := r.jobs.Run(req.Context(), serverID, "server.update", idempotencyKey(req), func(ctx context.Context, j *jobs.Job) error {
+		res, err := r.updateFromURL(ctx, srv, body, j)
+		if err != nil {
+			srv.PublishDaemon("Update failed: " + err.Error())
+			return err
+		}
+		j.SetResult(res)
+		return nil
+	})
+	if errors.Is(err, jobs.ErrInProgress) {
+		writeJSONError(w, http.StatusConflict, "server_update.in_progress")
+		return
+	}
+	if errors.Is(err, files.ErrChecksumMismatch) {
+		writeJSONError(w, http.StatusUnprocessableEntity, "server_update.checksum_mismatch")
+		return
+	}
+	if err != nil {
+		writeJSONErrorMessage(w, http.StatusInternalServerError, "server_update.failed", err.Error())
+		return
+	}
+	w.Header().Set("Content-Type", "application/json")
+	_, _ = w.Write(job.Result)
+}
+
+func (r *Router) updateFromURL(ctx context.Context, srv *server.Server, body apitypes.UpdateFromURLRequest, j *jobs.Job) (apitypes.UpdateFromURLResult, error) {
+	serverID := srv.UUID()
+	u, err := url.Parse(body.URL)
+	if err != nil {
+		return apitypes.UpdateFromURLResult{}, err
+	}
+	srv.PublishDaemon("Downloading update from " + u.Host + "...")
+	j.SetMessage("downloading")
+	// The name only tells Decompress the archive's format; keep the URL's.
+	name := path.Base(u.Path)
+	defer func() { _ = r.files.Delete(context.Background(), serverID, updateStaging, nil) }()
+	room, err := r.diskRoom(serverID)
+	if err != nil {
+		return apitypes.UpdateFromURLResult{}, err
+	}
+	opts := files.PullOptions{Mirrors: body.Mirrors, Checksum: body.Checksum, Room: room}
+	pulled, err := r.files.Pull(ctx, serverID, body.URL, updateStaging, name, opts, j.Progress)
+	if err != nil {
+		return apitypes.UpdateFromURLResult{}, err
+	}
+
+	res := apitypes.UpdateFromURLResult{URL: pulled.URL, Removed: []string{}}
+	res.Restarted = body.Restart && srv.Environment().State() != environment.StateOffline
+	if res.Restarted {
+		srv.PublishDaemon("Stopping server to apply the update...")
+		j.SetMessage("stopping")
+		// Same stop as a reinstall: the blueprint's grace, then a kill.
+		stopCtx, stopCancel := context.WithTimeout(ctx, srv.Environment().Stop().Grace()+10*time.Second)
+		err := srv.HandlePower(stopCtx, server.PowerStop)
+		stopCancel()
+		if err != nil {
+			killCtx, killCancel := context.WithTimeout(ctx, 10*time.Second)
+			_ = srv.HandlePower(killCtx, server.PowerKill)
+			killCancel()
+		}
+	}
+
+	// From here on a failure leaves the server stopped: half-replaced
+	// files are worse than downtime.
+	j.SetMessage("cleaning")
+	removed, err := r.files.Clean(ctx, serverID, body.Clean, updateStaging)
+	if err != nil {
+		return res, err
+	}
+	res.Removed = append(res.Removed, removed...)
+	if len(removed) > 0 {
+		srv.PublishDaemon(fmt.Sprintf("Removed %d path(s) before extracting", len(removed)))
+	}
+
+	srv.PublishDaemon("Extracting update...")
+	j.SetMessage("extracting")
+	dest := body.Directory
+	if dest == "" {
+		dest = "/"
+	}
+	if _, err := r.files.Stat(serverID, dest); err != nil {
+		if err := r.files.Mkdir(serverID, dest); err != nil {
+			return res, err
+		}
+	}
+	if err := r.files.Decompress(ctx, serverID, pulled.Path, dest, j.Progress); err != nil {
+		return res, err
+	}
+
+	if res.Restarted {
+		_, err := srv.EnqueuePower(server.PowerStart, func(ctx context.Context) error {
+			return syncServerConfig(ctx, srv)
+		})
+		if err != nil && !errors.Is(err, server.ErrPowerInProgress) {
+			return res, fmt.Errorf("start after update: %w", err)
+		}
+	}
+	srv.PublishDaemon("Update applied")
+	j.SetMessage(pulled.URL)
+	return res, nil
+}
@@ -0,0 +1,53 @@
+package router
+
+import (
+	"net/http"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/sftp"
+)
+
+// SetSFTP enables the SFTP session admin endpoints.
+func (r *Router) SetSFTP(s *sftp.Server) {
+	r.sftp = s
+}
+
+// handleSFTPAdmin serves the admin view of the SFTP listener:
+//
+//	GET    /api/system/sftp/sessions      → {sessions, bans}
+//	DELETE /api/system/sftp/sessions/<id> → disconnect that session
+//	DELETE /api/system/sftp/bans/<ip>     → lift a login-failure ban
+//
+// 404 sftp.disabled when the listener isn't running.
+func (r *Router) handleSFTPAdmin(w http.ResponseWriter, req *http.Request) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if r.sftp == nil {
+		writeJSONError(w, http.StatusNotFound, "sftp.disabled")
+		return
+	}
+	rest := strings.TrimPrefix(req.URL.Path, "/api/system/sftp/")
+	switch {
+	case rest == "sessions" && req.Method == http.MethodGet:
+		writeJSON(w, map[string]any{
+			"sessions": r.sftp.Sessions(),
+			"bans":     r.sftp.Bans(),
+		})
+	case strings.HasPrefix(rest, "sessions/") && req.Method == http.MethodDelete:
+		if !r.sftp.Kill(strings.TrimPrefix(rest, "sessions/")) {
+			writeJSONError(w, http.StatusNotFound, "sftp.session_not_found")
+			return
+		}
+		writeJSON(w, map[string]any{"ok": true})
+	case strings.HasPrefix(rest, "bans/") && req.Method == http.MethodDelete:
+		if !r.sftp.Unban(strings.TrimPrefix(rest, "bans/")) {
+			writeJSONError(w, http.StatusNotFound, "sftp.ban_not_found")
+			return
+		}
+		writeJSON(w, map[string]any{"ok": true})
+	default:
+		http.NotFound(w, req)
+	}
+}
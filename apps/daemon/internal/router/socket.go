@@ -0,0 +1,102 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"log"
+	"net"
+	"net/http"
+	"os"
+	"slices"
+	"syscall"
+	"time"
+)
+
+// Local socket. With [socket] path set the API is served a second time
+// on a Unix socket, for tooling on the node itself. The peer's
+// credentials stand in for the panel's HMAC signature: a request from an
+// admitted peer passes verifyDaemonHMAC, everyone else is turned away
+// before routing. Browser-token endpoints still want their token.
+
+// peerCredKey carries the connecting process's credentials, read once
+// per connection, in the request context.
+type peerCredKey struct{}
+
+// SocketServer binds the local socket and returns the server to run on
+// it; the caller serves and shuts it down alongside the TCP listener. A
+// socket file left behind by an earlier run is replaced.
+func (r *Router) SocketServer() (*http.Server, net.Listener, error) {
+	path := r.cfg.Socket.Path
+	if err := os.Remove(path); err != nil && !errors.Is(err, os.ErrNotExist) {
+		return nil, nil, err
+	}
+	ln, err := net.Listen("unix", path)
+	if err != nil {
+		return nil, nil, err
+	}
+	if err := os.Chmod(path, os.FileMode(r.cfg.Socket.Mode)); err != nil {
+		_ = ln.Close()
+		return nil, nil, err
+	}
+	srv := &http.Server{
+		Handler:           r.admitPeer(r.Handler()),
+		ReadHeaderTimeout: 15 * time.Second,
+		ConnContext: func(ctx context.Context, c net.Conn) context.Context {
+			if cred, err := peerCred(c); err == nil {
+				return context.WithValue(ctx, peerCredKey{}, cred)
+			}
+			return ctx
+		},
+	}
+	return srv, ln, nil
+}
+
+// peerCred asks the kernel who is on the other end of a Unix socket.
+func peerCred(c net.Conn) (*syscall.Ucred, error) {
+	uc, ok := c.(*net.UnixConn)
+	if !ok {
+		return nil, errors.New("socket: not a unix connection")
+	}
+	raw, err := uc.SyscallConn()
+	if err != nil {
+		return nil, err
+	}
+	var cred *syscall.Ucred
+	var credErr error
+	if err := raw.Control(func(fd uintptr) {
+		cred, credErr = syscall.GetsockoptUcred(int(fd), syscall.SOL_SOCKET, syscall.SO_PEERCRED)
+	}); err != nil {
+		return nil, err
+	}
+	return cred, credErr
+}
+
+// admitPeer turns away socket connections from users the config
+// doesn't admit.
+func (r *Router) admitPeer(next http.Handler) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, req *http.Request) {
+		cred, ok := req.Context().Value(peerCredKey{}).(*syscall.Ucred)
+		if !ok || !r.peerAdmitted(cred) {
+			if ok {
+				log.Printf("socket: refused uid %d (pid %d)", cred.Uid, cred.Pid)
+			}
+			writeJSONError(w, http.StatusForbidden, "socket.peer_denied")
+			return
+		}
+		next.ServeHTTP(w, req)
+	})
+}
+
+func (r *Router) peerAdmitted(cred *syscall.Ucred) bool {
+	uid, gid := int(cred.Uid), int(cred.Gid)
+	return uid == 0 || uid == os.Getuid() ||
+		slices.Contains(r.cfg.Socket.AllowUIDs, uid) ||
+		slices.Contains(r.cfg.Socket.AllowGIDs, gid)
+}
+
+// localPeer reports whether req came over the local socket from an
+// admitted peer.
+func (r *Router) localPeer(req *http.Request) bool {
+	cred, ok := req.Context().Value(peerCredKey{}).(*syscall.Ucred)
+	return ok && r.peerAdmitted(cred)
+}
@@ -0,0 +1,153 @@
+package router
+
+import (
+	"context"
+	"net/http"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// State change feed. The panel long-polls
+// GET /api/servers/states/changes?since=<cursor> and gets back, as soon
+// as any server's process state or flags change, the current State of
+// the servers that changed and the cursor for its next poll. A missing
+// cursor, or one from an earlier daemon run, gets every server with
+// reset set. CPU and memory ride along but never wake a poll on their
+// own; /api/servers/states is there for those.
+
+const (
+	// stateFeedFallback resweeps without a change signal, in case a
+	// flag flips somewhere that doesn't raise one.
+	stateFeedFallback = 5 * time.Second
+	// stateFeedWait is how long a poll is held open by default, and
+	// stateFeedMaxWait the most a caller may ask for.
+	stateFeedWait    = 25 * time.Second
+	stateFeedMaxWait = 60 * time.Second
+)
+
+// stateFeed versions the servers' process state and flags. Every sweep
+// that finds a change bumps the version and stamps the changed servers
+// with it; wake is closed and replaced at the same time.
+type stateFeed struct {
+	mu      sync.Mutex
+	epoch   string
+	version uint64
+	seen    map[string]stateMark
+	wake    chan struct{}
+}
+
+type stateMark struct {
+	key     string
+	version uint64
+}
+
+func newStateFeed() *stateFeed {
+	return &stateFeed{
+		epoch: strconv.FormatInt(time.Now().UnixNano(), 36),
+		seen:  map[string]stateMark{},
+		wake:  make(chan struct{}),
+	}
+}
+
+func (f *stateFeed) sweep(states map[string]server.State) {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	next := f.version + 1
+	changed := false
+	for id, st := range states {
+		key := string(st.ProcessState) + "|" + strings.Join(st.Flags, ",")
+		if m, ok := f.seen[id]; ok && m.key == key {
+			continue
+		}
+		f.seen[id] = stateMark{key: key, version: next}
+		changed = true
+	}
+	if changed {
+		f.version = next
+		close(f.wake)
+		f.wake = make(chan struct{})
+	}
+}
+
+// changedSince lists the servers stamped after cursor (every server
+// when reset), the cursor to hand back, and a channel closed on the
+// next change.
+func (f *stateFeed) changedSince(cursor string) (ids []string, reset bool, next string, wake <-chan struct{}) {
+	f.mu.Lock()
+	defer f.mu.Unlock()
+	epoch, v, found := strings.Cut(cursor, ":")
+	version, err := strconv.ParseUint(v, 10, 64)
+	reset = !found || err != nil || epoch != f.epoch || version > f.version
+	for id, m := range f.seen {
+		if reset || m.version > version {
+			ids = append(ids, id)
+		}
+	}
+	return ids, reset, f.epoch + ":" + strconv.FormatUint(f.version, 10), f.wake
+}
+
+// serverStates is every server's state, as served by
+// /api/servers/states.
+func (r *Router) serverStates() map[string]server.State {
+	out := map[string]server.State{}
+	for _, srv := range r.manager.All() {
+		out[srv.UUID()] = r.serverState(srv)
+	}
+	return out
+}
+
+// RunStateFeed keeps the change feed current until ctx ends: it
+// resweeps on every manager change signal, and on a slow tick.
+func (r *Router) RunStateFeed(ctx context.Context) {
+	tick := time.NewTicker(stateFeedFallback)
+	defer tick.Stop()
+	for {
+		r.feed.sweep(r.serverStates())
+		select {
+		case <-ctx.Done():
+			return
+		case <-r.manager.StateChanged():
+		case <-tick.C:
+		}
+	}
+}
+
+// handleServerStateChanges is the long poll described above. ?wait=
+// (seconds) shortens or lengthens the hold, up to stateFeedMaxWait; a
+// poll that times out answers with no servers and the same cursor.
+func (r *Router) handleServerStateChanges(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	wait := stateFeedWait
+	if n, err := strconv.Atoi(req.URL.Query().Get("wait")); err == nil && n >= 0 {
+		wait = min(time.Duration(n)*time.Second, stateFeedMaxWait)
+	}
+	since := req.URL.Query().Get("since")
+	ids, reset, next, wake := r.feed.changedSince(since)
+	if len(ids) == 0 && !reset {
+		timer := time.NewTimer(wait)
+		defer timer.Stop()
+		select {
+		case <-wake:
+			ids, reset, next, _ = r.feed.changedSince(since)
+		case <-timer.C:
+		case <-req.Context().Done():
+			return
+		}
+	}
+	out := make(map[string]server.State, len(ids))
+	for _, id := range ids {
+		out[id] = r.serverState(r.manager.Get(id))
+	}
+	writeJSON(w, map[string]any{"cursor": next, "reset": reset, "servers": out})
+}
@@ -0,0 +1,39 @@
+package router
+
+import (
+	"net/http"
+
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// handleServerStates is GET /api/servers/states: a map of server uuid
+// to its process state, busy flags, uptime and latest CPU/memory, for
+// every server on the node, so the panel dashboard refreshes with one
+// call per node instead of one per server. Changes after that come
+// from the long poll in handleServerStateChanges.
+func (r *Router) handleServerStates(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	writeJSON(w, r.serverStates())
+}
+
+// serverState is srv.State with the router's own flags added.
+func (r *Router) serverState(srv *server.Server) server.State {
+	st := srv.State()
+	if r.transferring(srv.UUID()) {
+		st.Flags = append(st.Flags, "transferring")
+	}
+	return st
+}
+
+// transferring reports whether the server is being pushed from or
+// ingested into this node.
+func (r *Router) transferring(serverID string) bool {
+	return r.inbound.get(serverID) != nil || r.jobs.Active(serverID, "transfer.push") > 0
+}
@@ -0,0 +1,224 @@
+package router
+
+import (
+	"net/http"
+	"runtime"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/backup"
+	"github.com/stellarstack/daemon/internal/capability"
+	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/jobs"
+	"github.com/stellarstack/daemon/internal/logging"
+	"github.com/stellarstack/daemon/internal/server"
+)
+
+// handleSystem reports node-level facts the API shows on the admin
+// nodes page: host basics plus the admission-control commitment
+// (sum of configured limits of non-offline servers vs capacity), backup
+// storage usage, and the capabilities the panel negotiates against.
+func (r *Router) handleSystem(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	writeJSON(w, struct {
+		NodeID       string                 `json:"nodeId"`
+		Version      string                 `json:"version"`
+		OS           string                 `json:"os"`
+		Arch         string                 `json:"arch"`
+		CPUs         int                    `json:"cpus"`
+		Admission    server.AdmissionReport `json:"admission"`
+		BackupUsage  *backup.Usage          `json:"backupUsage,omitempty"`
+		Capabilities capability.Report      `json:"capabilities"`
+	}{
+		NodeID:       r.cfg.NodeID,
+		Version:      config.Version,
+		OS:           runtime.GOOS,
+		Arch:         runtime.GOARCH,
+		CPUs:         runtime.NumCPU(),
+		Admission:    r.manager.AdmissionReport(),
+		BackupUsage:  r.backupUsage(),
+		Capabilities: capability.Current(capability.Options{
+			ProxyEnabled:      r.proxy != nil,
+			SelfUpdateEnabled: r.updater != nil,
+			LogFileEnabled:    r.logFile != nil,
+			SFTPEnabled:       r.sftp != nil,
+			ScanEnabled:       r.cfg.Scan.Enabled(),
+			HostNetwork:       r.manager.DaemonHostNetwork(),
+		}),
+	})
+}
+
+// backupUsage is nil when backups are disabled or the directory can't
+// be read.
+func (r *Router) backupUsage() *backup.Usage {
+	if r.backups == nil {
+		return nil
+	}
+	u, err := r.backups.Usage()
+	if err != nil {
+		return nil
+	}
+	return &u
+}
+
+// routeSystem dispatches the node-level control endpoints under
+// /api/system/.
+func (r *Router) routeSystem(w http.ResponseWriter, req *http.Request) {
+	switch req.URL.Path {
+	case "/api/system/update", "/api/system/rollback":
+		r.handleUpdate(w, req)
+	case "/api/system/log-levels":
+		r.handleLogLevels(w, req)
+	case "/api/system/logs":
+		r.handleLogTail(w, req)
+	case "/api/system/file-activity":
+		r.handleFileActivity(w, req)
+	case "/api/system/queues":
+		r.handleQueues(w, req)
+	case "/api/system/images/pull":
+		r.handleImagePull(w, req)
+	default:
+		if strings.HasPrefix(req.URL.Path, "/api/system/sftp/") {
+			r.handleSFTPAdmin(w, req)
+			return
+		}
+		http.NotFound(w, req)
+	}
+}
+
+// handleFileActivity reports each server's file manager activity over
+// the files.activity_window_minutes window: operations and bytes per
+// kind, for servers with any.
+func (r *Router) handleFileActivity(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	writeJSON(w, map[string]any{"servers": r.files.Activity()})
+}
+
+// handleQueues reports what the node is busy with, so the panel can
+// show why a backup or transfer hasn't finished yet: installs, backups
+// (create, restore, import), transfer pushes and ingests, and every job
+// by kind, each as queued and running counts, plus the limits they run
+// under. The per-server file job and listing limits are the only
+// concurrency caps; transfers are bounded by bandwidth and the window.
+func (r *Router) handleQueues(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	byKind := r.jobs.Counts()
+	var installs, backups jobs.Counts
+	for _, s := range r.manager.All() {
+		if s.Installing() {
+			installs.Running++
+		}
+	}
+	for _, kind := range []string{"backup.create", "backup.restore", "backup.import"} {
+		backups.Queued += byKind[kind].Queued
+		backups.Running += byKind[kind].Running
+	}
+	windowOpen, _ := r.cfg.Transfer.WindowOpen(time.Now())
+	writeJSON(w, map[string]any{
+		"installs": installs,
+		"backups":  backups,
+		"transfers": map[string]any{
+			"pushing":    byKind["transfer.push"],
+			"ingesting":  jobs.Counts{Running: r.inbound.count()},
+			"windowOpen": windowOpen,
+		},
+		"jobs": byKind,
+		"limits": map[string]int{
+			"fileJobsPerServer":     r.cfg.Files.MaxConcurrentJobs,
+			"fileListsPerServer":    r.cfg.Files.MaxConcurrentLists,
+			"transferBandwidthMbps": r.cfg.Transfer.BandwidthMbps,
+		},
+	})
+}
+
+// handleLogLevels reads (GET) or replaces (PUT {level, modules}) the
+// daemon's log levels at runtime. A PUT replaces every override, so
+// send the full set; the config file values come back on restart.
+func (r *Router) handleLogLevels(w http.ResponseWriter, req *http.Request) {
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	switch req.Method {
+	case http.MethodGet:
+	case http.MethodPut:
+		var body struct {
+			Level   string            `json:"level"`
+			Modules map[string]string `json:"modules"`
+		}
+		if err := decodeJSON(req, &body); err != nil {
+			writeJSONError(w, http.StatusBadRequest, "logs.bad_request")
+			return
+		}
+		if err := logging.SetLevels(body.Level, body.Modules); err != nil {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "logs.bad_level", err.Error())
+			return
+		}
+	default:
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	level, modules := logging.Levels()
+	writeJSON(w, map[string]any{"level": level, "modules": modules})
+}
+
+// maxLogTailLines caps GET /api/system/logs?lines=.
+const maxLogTailLines = 5000
+
+// SetLogFile enables GET /api/system/logs over the daemon's log file.
+func (r *Router) SetLogFile(f *logging.File) {
+	r.logFile = f
+}
+
+// handleLogTail returns the last `lines` (default 200) lines of the
+// daemon's own log. 404 logs.disabled when the daemon logs to stderr,
+// where journald or Docker already keeps them.
+func (r *Router) handleLogTail(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodGet {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if r.logFile == nil {
+		writeJSONError(w, http.StatusNotFound, "logs.disabled")
+		return
+	}
+	n := 200
+	if v := req.URL.Query().Get("lines"); v != "" {
+		n = int(parseInt64(v))
+		if n <= 0 {
+			writeJSONError(w, http.StatusBadRequest, "logs.bad_request")
+			return
+		}
+	}
+	lines, err := r.logFile.Tail(min(n, maxLogTailLines))
+	if err != nil {
+		writeJSONError(w, http.StatusInternalServerError, "logs.read_failed")
+		return
+	}
+	writeJSON(w, map[string]any{"lines": lines})
+}
@@ -8,6 +8,7 @@ import (
 	"crypto/sha256"
 	"encoding/hex"
 	"encoding/json"
+	"errors"
 	"fmt"
 	"io"
 	"net/http"
@@ -15,8 +16,23 @@ import (
 	"path/filepath"
 	"strings"
 	"time"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/ignore"
+	"github.com/stellarstack/daemon/internal/jobs"
+	"github.com/stellarstack/daemon/internal/logging"
+	"github.com/stellarstack/daemon/internal/pathlock"
+	"github.com/stellarstack/daemon/internal/pgzip"
 )
 
+// archiveDigestTrailer carries the hex sha256 of the pushed archive as
+// an HTTP trailer: the source only knows it once the last byte is out,
+// and the target hashes what it reads as it extracts, so the archive is
+// verified without either side buffering or re-reading it.
+const archiveDigestTrailer = "X-Stellar-Archive-Sha256"
+
 // transferTokenWindow is how far apart the source's signed token can be
 // from the target's clock before we reject. 5-minute skew tolerance.
 const transferTokenWindow = 5 * time.Minute
@@ -25,21 +41,109 @@ const transferTokenWindow = 5 * time.Minute
 // pushes a tarball into. Authenticated via a one-time token signed with
 // the per-node HMAC the API minted at transfer-start time.
 //
-// Body is the same .tar.gz format the backup module emits; the daemon
-// extracts into the bind mount and replies 200 on success.
+// Body is the same .tar.gz format the backup module emits, encrypted
+// with the per-transfer key (see transferseal.go); a plaintext body from
+// a source that predates encryption is only accepted over HTTPS. The
+// daemon extracts it into a staging directory under the transfer tmp
+// directory, moves that over the bind mount once the archive has
+// verified, and replies 200 with its receipt; a source that dies
+// halfway leaves only the staging tree, kept for a while for a delta
+// retry and then purged by the janitor. One ingest per server runs at a
+// time. In a delta push, files this node already has arrive as empty
+// entries naming their hash and are taken from the live tree or that
+// kept one; see transferdelta.go.
 func (r *Router) handleTransferIngest(w http.ResponseWriter, req *http.Request, serverID string) {
 	if !verifyTransferToken(req, r.cfg.SigningKeyHex) {
 		http.Error(w, "unauthorized", http.StatusUnauthorized)
 		return
 	}
-	dst := filepath.Join(r.cfg.DataDir, "servers", serverID)
+	token := req.Header.Get("X-Stellar-Transfer-Token")
+	key, ok := r.inboundKey(w, req)
+	if !ok {
+		return
+	}
+	if !r.checkTransferWindow(w, req.Header.Get("X-Stellar-Transfer-Urgent") == "1") {
+		return
+	}
+	if !limitBody(w, req, r.cfg.Transfer.MaxIngestMb, "transfer.too_large") {
+		return
+	}
+	lim := r.transferLimits()
+	start := time.Now()
+	in := &inboundTransfer{
+		token:   token,
+		staging: filepath.Join(r.cfg.Transfer.TmpDirectory, fmt.Sprintf("%s.%d", serverID, start.Unix())),
+	}
+	in.last.Store(start.UnixNano())
+	release, ok := r.inbound.acquire(serverID, in)
+	if !ok {
+		writeJSONError(w, http.StatusConflict, "transfer.in_progress")
+		return
+	}
+	r.manager.NotifyStateChanged()
+	defer func() {
+		release()
+		r.manager.NotifyStateChanged()
+	}()
+	lock, ok := r.lockPaths(w, req, serverID, "transfer", pathlock.W("/"))
+	if !ok {
+		return
+	}
+	defer lock.Release()
+	// Refuse before any bytes land if hosting this server would
+	// oversubscribe the node. The panel still homes the server on the
+	// source (it only switches it over once this push has landed), so
+	// its limits are read from the panel's record of it and reserved
+	// here until the tree is committed, or for as long as a failed
+	// ingest's partial tree waits for the retry.
+	srv := r.manager.Get(serverID)
+	if err := syncServerConfig(req.Context(), srv); err != nil {
+		writeJSONError(w, http.StatusBadGateway, "transfer.config_failed")
+		return
+	}
+	cfg := srv.Config()
+	if err := r.manager.Reserve(serverID, cfg.TotalMemory(), cfg.CPUPercent); err != nil {
+		writeJSONErrorMessage(w, http.StatusConflict, "transfer.node_oversubscribed", err.Error())
+		return
+	}
+	dst := in.staging
+	live := filepath.Join(r.cfg.DataDir, "servers", serverID)
+	partial := r.partialPath(serverID)
+	committed := false
+	defer func() {
+		if committed || !keepPartial(dst, partial) {
+			r.manager.Unreserve(serverID)
+		}
+	}()
 	if err := os.MkdirAll(dst, 0o755); err != nil {
 		writeJSONError(w, http.StatusInternalServerError, "transfer.mkdir_failed")
 		return
 	}
-	gz, err := gzip.NewReader(req.Body)
+	logging.Debugf("transfer", "transfer: ingest %s from %s started", serverID, req.RemoteAddr)
+	hasher := sha256.New()
+	var raw io.Reader = &ingestReader{
+		ctx:   req.Context(),
+		r:     req.Body,
+		rc:    http.NewResponseController(w),
+		in:    in,
+		rate:  lim.rate,
+		stall: lim.stall,
+		end:   start.Add(lim.timeout),
+	}
+	if key != nil {
+		opened, err := newOpenReader(raw, key)
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "transfer.key_failed")
+			return
+		}
+		raw = opened
+	}
+	body := io.TeeReader(raw, hasher)
+	gz, err := gzip.NewReader(body)
 	if err != nil {
-		writeJSONError(w, http.StatusBadRequest, "transfer.bad_archive")
+		if !bodyTooLarge(w, err, "transfer.too_large") {
+			writeJSONError(w, http.StatusBadRequest, "transfer.bad_archive")
+		}
 		return
 	}
 	defer gz.Close()
@@ -50,7 +154,9 @@ func (r *Router) handleTransferIngest(w http.ResponseWriter, req *http.Request,
 			break
 		}
 		if err != nil {
-			writeJSONError(w, http.StatusBadRequest, "transfer.read_failed")
+			if !bodyTooLarge(w, err, "transfer.too_large") {
+				writeJSONError(w, http.StatusBadRequest, "transfer.read_failed")
+			}
 			return
 		}
 		clean := filepath.Clean("/" + hdr.Name)
@@ -70,39 +176,140 @@ func (r *Router) handleTransferIngest(w http.ResponseWriter, req *http.Request,
 				writeJSONError(w, http.StatusInternalServerError, "transfer.mkdir_failed")
 				return
 			}
+			if sum := hdr.PAXRecords[deltaKeepRecord]; sum != "" {
+				if err := keepFile([]string{partial, live}, clean, target, os.FileMode(hdr.Mode), sum); err != nil {
+					writeJSONErrorMessage(w, http.StatusConflict, "transfer.delta_stale", err.Error())
+					return
+				}
+				continue
+			}
 			f, err := os.OpenFile(target, os.O_CREATE|os.O_WRONLY|os.O_TRUNC, os.FileMode(hdr.Mode))
 			if err != nil {
 				writeJSONError(w, http.StatusInternalServerError, "transfer.write_failed")
 				return
 			}
-			if _, err := io.Copy(f, tr); err != nil {
+			if _, err := fsio.Copy(diskio.Writer(req.Context(), diskio.Background, f), tr); err != nil {
 				f.Close()
-				writeJSONError(w, http.StatusInternalServerError, "transfer.write_failed")
+				if !bodyTooLarge(w, err, "transfer.too_large") {
+					writeJSONError(w, http.StatusInternalServerError, "transfer.write_failed")
+				}
 				return
 			}
 			f.Close()
 		}
 	}
-	writeJSON(w, map[string]any{"ok": true})
+	// The tar reader stops at the end-of-archive marker; read the rest
+	// so the hash covers every byte and the trailer has arrived.
+	if _, err := io.Copy(io.Discard, body); err != nil {
+		if !bodyTooLarge(w, err, "transfer.too_large") {
+			writeJSONError(w, http.StatusBadRequest, "transfer.read_failed")
+		}
+		return
+	}
+	got := hex.EncodeToString(hasher.Sum(nil))
+	// Only sources that predate the trailer may leave it out: one that
+	// declared it up front, or sealed the push, must send it, so it
+	// can't be dropped on the way to skip the check.
+	want := req.Trailer.Get(archiveDigestTrailer)
+	_, declared := req.Trailer[archiveDigestTrailer]
+	if want == "" && (declared || key != nil) {
+		writeJSONError(w, http.StatusBadRequest, "transfer.checksum_missing")
+		return
+	}
+	if want != "" && !strings.EqualFold(got, want) {
+		writeJSONErrorMessage(w, http.StatusBadRequest, "transfer.checksum_mismatch",
+			"archive sha256 "+got+" does not match the source's "+want)
+		return
+	}
+	// An encrypted push must sign what it sent.
+	if key != nil {
+		sig := req.Trailer.Get(archiveSignatureTrailer)
+		if !hmac.Equal([]byte(sig), []byte(transferMAC(key, archiveManifest(serverID, got)))) {
+			writeJSONError(w, http.StatusBadRequest, "transfer.signature_invalid")
+			return
+		}
+	}
+	if err := commitInbound(dst, live); err != nil {
+		writeJSONErrorMessage(w, http.StatusInternalServerError, "transfer.commit_failed", err.Error())
+		return
+	}
+	committed = true
+	_ = os.RemoveAll(partial)
+	logging.Debugf("transfer", "transfer: ingest %s done (sha256 %s, trailer %t, encrypted %t)", serverID, got, want != "", key != nil)
+	resp := map[string]any{"ok": true}
+	if key != nil {
+		resp["receipt"] = transferReceipt(key, serverID, got)
+	}
+	writeJSON(w, resp)
+}
+
+// inboundKey is the per-transfer key for an encrypted push (or its
+// manifest request), nil for a plaintext one over HTTPS. Anything else
+// is answered here and ok is false.
+func (r *Router) inboundKey(w http.ResponseWriter, req *http.Request) (key []byte, ok bool) {
+	switch enc := req.Header.Get("X-Stellar-Transfer-Encryption"); {
+	case enc == transferCipher:
+		k, err := transferKey(r.cfg.SigningKeyHex, req.Header.Get("X-Stellar-Transfer-Token"))
+		if err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "transfer.key_failed")
+			return nil, false
+		}
+		return k, true
+	case enc != "":
+		writeJSONErrorMessage(w, http.StatusBadRequest, "transfer.unsupported_encryption",
+			"unsupported transfer encryption "+enc)
+		return nil, false
+	case req.TLS == nil && req.Header.Get("X-Forwarded-Proto") != "https":
+		writeJSONErrorMessage(w, http.StatusForbidden, "transfer.insecure",
+			"unencrypted transfers are only accepted over HTTPS")
+		return nil, false
+	}
+	return nil, true
 }
 
 // handleTransferPush is the source-side endpoint the API hits to start
 // the byte transfer. Authenticated by daemon HMAC. Body specifies the
-// target node URL + the one-time token to present.
+// target node URL + the one-time token to present, and the sealed
+// per-transfer key; without one (a target that predates encryption)
+// the target URL must be HTTPS. Tracked as a synchronous job so retries
+// with the same `Idempotency-Key` don't start a second push while the
+// first is still streaming. The panel may ask for a shorter timeout
+// than the node's own, and pass patterns for paths to leave behind on
+// top of the server's .stellarignore files.
 func (r *Router) handleTransferPush(w http.ResponseWriter, req *http.Request, serverID string) {
 	if !r.verifyDaemonHMAC(req) {
 		http.Error(w, "unauthorized", http.StatusUnauthorized)
 		return
 	}
-	var body struct {
-		TargetURL  string `json:"targetUrl"`
-		Token      string `json:"token"`
-		Timestamp  int64  `json:"timestamp"`
-	}
+	var body apitypes.TransferPushRequest
 	if err := decodeJSON(req, &body); err != nil {
 		writeJSONError(w, http.StatusBadRequest, "transfer.bad_request")
 		return
 	}
+	push := transferPush{
+		serverID:  serverID,
+		targetURL: body.TargetURL,
+		token:     body.Token,
+		timestamp: body.Timestamp,
+		urgent:    body.Urgent,
+		ignore:    body.Ignore,
+		delta:     body.Delta,
+	}
+	if body.SealedKey != "" {
+		key, err := unsealTransferKey(r.cfg.SigningKeyHex, body.Token, body.SealedKey)
+		if err != nil {
+			writeJSONError(w, http.StatusBadRequest, "transfer.bad_key")
+			return
+		}
+		push.key = key
+	} else if !strings.HasPrefix(body.TargetURL, "https://") {
+		writeJSONErrorMessage(w, http.StatusBadRequest, "transfer.insecure_target",
+			"an unencrypted transfer needs an HTTPS target")
+		return
+	}
+	if !r.checkTransferWindow(w, body.Urgent) {
+		return
+	}
 
 	src := filepath.Join(r.cfg.DataDir, "servers", serverID)
 	if _, err := os.Stat(src); err != nil {
@@ -110,33 +317,129 @@ func (r *Router) handleTransferPush(w http.ResponseWriter, req *http.Request, se
 		return
 	}
 
+	lim := r.transferLimits()
+	if t := time.Duration(body.TimeoutSeconds) * time.Second; t > 0 && t < lim.timeout {
+		lim.timeout = t
+	}
+	logging.Debugf("transfer", "transfer: push %s to %s started", serverID, body.TargetURL)
+	_, err := r.jobs.Run(req.Context(), serverID, "transfer.push", idempotencyKey(req), func(ctx context.Context, _ *jobs.Job) error {
+		lock, err := r.lockJob(ctx, serverID, "transfer", pathlock.R("/"))
+		if err != nil {
+			return err
+		}
+		defer lock.Release()
+		r.manager.NotifyStateChanged()
+		return pushTransferArchive(ctx, src, push, lim)
+	})
+	r.manager.NotifyStateChanged()
+	logging.Debugf("transfer", "transfer: push %s finished: %v", serverID, err)
+	var te *transferError
+	switch {
+	case err == nil:
+		writeJSON(w, map[string]any{"ok": true})
+	case errors.Is(err, jobs.ErrInProgress):
+		writeJSONError(w, http.StatusConflict, "transfer.in_progress")
+	case errors.Is(err, pathlock.ErrLocked):
+		writeLocked(w, err)
+	case errors.As(err, &te):
+		writeJSONError(w, te.status, te.code)
+	default:
+		writeJSONError(w, http.StatusBadGateway, "transfer.push_failed")
+	}
+}
+
+// transferError carries the HTTP status + error code a failed push
+// should surface, so the job body can stay free of ResponseWriter.
+type transferError struct {
+	status int
+	code   string
+	err    error
+}
+
+func (e *transferError) Error() string {
+	if e.err != nil {
+		return e.code + ": " + e.err.Error()
+	}
+	return e.code
+}
+
+func (e *transferError) Unwrap() error { return e.err }
+
+// transferPush is where and how a push goes. key is the per-transfer
+// key, nil for an unencrypted push to an HTTPS target. ignore lists
+// patterns for paths to leave out; see package ignore. With delta the
+// target is first asked what it already has.
+type transferPush struct {
+	serverID  string
+	targetURL string
+	token     string
+	timestamp int64
+	urgent    bool
+	key       []byte
+	ignore    []string
+	delta     bool
+}
+
+// pushTransferArchive streams the server tree as a tar.gz straight into
+// the target daemon's ingest endpoint, under watchTransferPush. With a
+// key the archive goes out encrypted and signed, and the push only
+// succeeds once the target's receipt checks out.
+func pushTransferArchive(ctx context.Context, src string, push transferPush, lim transferLimits) error {
+	var have deltaManifest
+	if push.delta {
+		m, err := fetchTargetManifest(ctx, push, lim.timeout)
+		if err != nil {
+			// The target predates delta transfers or couldn't list its
+			// tree; a full push still works.
+			logging.Debugf("transfer", "transfer: push %s: no manifest from target, sending everything: %v", push.serverID, err)
+		}
+		have = m
+	}
 	pr, pw := io.Pipe()
+	// Filled in by the writer goroutine before it closes the pipe; the
+	// client only reads trailers once the body hits EOF.
+	trailer := http.Header{archiveDigestTrailer: nil}
+	if push.key != nil {
+		trailer[archiveSignatureTrailer] = nil
+	}
+	// The writer hands over the archive digest once it's all out, for
+	// checking the target's receipt.
+	digests := make(chan string, 1)
 	// Stream the tarball directly into the HTTP request body so we don't
 	// need to stage a multi-GB archive on disk first.
 	go func() {
 		defer pw.Close()
-		gz := gzip.NewWriter(pw)
-		tw := tar.NewWriter(gz)
-		walkErr := filepath.Walk(src, func(path string, info os.FileInfo, err error) error {
-			if err != nil {
-				return err
-			}
-			rel, err := filepath.Rel(src, path)
+		var out io.Writer = pw
+		var sealer *sealWriter
+		if push.key != nil {
+			s, err := newSealWriter(pw, push.key)
 			if err != nil {
-				return err
-			}
-			if rel == "." {
-				return nil
+				pw.CloseWithError(err)
+				return
 			}
+			out, sealer = s, s
+		}
+		hasher := sha256.New()
+		gz := pgzip.NewWriter(io.MultiWriter(out, hasher))
+		// Idempotent; on the error paths this stops the compressor.
+		defer gz.Close()
+		tw := tar.NewWriter(gz)
+		walkErr := ignore.Walk(src, push.ignore, func(path, rel string, info os.FileInfo) error {
 			hdr, err := tar.FileInfoHeader(info, "")
 			if err != nil {
 				return err
 			}
 			hdr.Name = rel
+			if info.Mode().IsRegular() {
+				if sum := have.unchanged(rel, path, info); sum != "" {
+					hdr.Size = 0
+					hdr.PAXRecords = map[string]string{deltaKeepRecord: sum}
+				}
+			}
 			if err := tw.WriteHeader(hdr); err != nil {
 				return err
 			}
-			if !info.Mode().IsRegular() {
+			if !info.Mode().IsRegular() || hdr.PAXRecords[deltaKeepRecord] != "" {
 				return nil
 			}
 			f, err := os.Open(path)
@@ -144,7 +447,7 @@ func (r *Router) handleTransferPush(w http.ResponseWriter, req *http.Request, se
 				return err
 			}
 			defer f.Close()
-			_, err = io.Copy(tw, f)
+			_, err = fsio.Copy(tw, diskio.Reader(ctx, diskio.Background, f))
 			return err
 		})
 		if walkErr != nil {
@@ -155,32 +458,93 @@ func (r *Router) handleTransferPush(w http.ResponseWriter, req *http.Request, se
 			pw.CloseWithError(err)
 			return
 		}
-		_ = gz.Close()
+		if err := gz.Close(); err != nil {
+			pw.CloseWithError(err)
+			return
+		}
+		if sealer != nil {
+			if err := sealer.Close(); err != nil {
+				pw.CloseWithError(err)
+				return
+			}
+		}
+		digest := hex.EncodeToString(hasher.Sum(nil))
+		digests <- digest
+		trailer.Set(archiveDigestTrailer, digest)
+		if push.key != nil {
+			trailer.Set(archiveSignatureTrailer, transferMAC(push.key, archiveManifest(push.serverID, digest)))
+		}
 	}()
 
-	ctx, cancel := context.WithTimeout(req.Context(), 30*time.Minute)
+	ctx, cancel := context.WithTimeout(ctx, lim.timeout)
 	defer cancel()
-	pushReq, err := http.NewRequestWithContext(ctx, http.MethodPost, body.TargetURL, pr)
+	ctx, abort := context.WithCancelCause(ctx)
+	defer abort(nil)
+	body := &pushReader{ctx: ctx, pr: pr, rate: lim.rate}
+	body.last.Store(time.Now().UnixNano())
+	go watchTransferPush(ctx, abort, body, transferStatusURL(push.targetURL), push.token, lim.stall)
+	pushReq, err := http.NewRequestWithContext(ctx, http.MethodPost, push.targetURL, body)
 	if err != nil {
-		writeJSONError(w, http.StatusBadRequest, "transfer.bad_target")
-		return
+		pr.CloseWithError(err)
+		return &transferError{status: http.StatusBadRequest, code: "transfer.bad_target", err: err}
 	}
+	pushReq.Trailer = trailer
 	pushReq.Header.Set("Content-Type", "application/x-gtar")
-	pushReq.Header.Set("X-Stellar-Transfer-Token", body.Token)
+	pushReq.Header.Set("X-Stellar-Transfer-Token", push.token)
 	pushReq.Header.Set("X-Stellar-Transfer-Timestamp",
-		fmt.Sprintf("%d", body.Timestamp))
+		fmt.Sprintf("%d", push.timestamp))
+	if push.urgent {
+		pushReq.Header.Set("X-Stellar-Transfer-Urgent", "1")
+	}
+	if push.key != nil {
+		pushReq.Header.Set("X-Stellar-Transfer-Encryption", transferCipher)
+	}
 
 	pushResp, err := http.DefaultClient.Do(pushReq)
 	if err != nil {
-		writeJSONError(w, http.StatusBadGateway, "transfer.push_failed")
-		return
+		var te *transferError
+		switch cause := context.Cause(ctx); {
+		case errors.As(cause, &te):
+			return te
+		case errors.Is(cause, context.DeadlineExceeded):
+			return &transferError{status: http.StatusGatewayTimeout, code: "transfer.timeout", err: err}
+		}
+		return &transferError{status: http.StatusBadGateway, code: "transfer.push_failed", err: err}
 	}
 	defer pushResp.Body.Close()
 	if pushResp.StatusCode/100 != 2 {
-		writeJSONError(w, http.StatusBadGateway, "transfer.target_rejected")
-		return
+		var rejected struct {
+			Error struct{ Code string }
+		}
+		_ = json.NewDecoder(io.LimitReader(pushResp.Body, 4096)).Decode(&rejected)
+		switch rejected.Error.Code {
+		case "transfer.outside_window":
+			return &transferError{status: http.StatusConflict, code: "transfer.target_outside_window"}
+		case "transfer.too_large":
+			return &transferError{status: http.StatusRequestEntityTooLarge, code: "transfer.target_too_large"}
+		}
+		return &transferError{status: http.StatusBadGateway, code: "transfer.target_rejected", err: errors.New(pushResp.Status)}
+	}
+	if push.key == nil {
+		return nil
+	}
+	// The target only accepts a complete archive, so the writer has
+	// finished by now. Only the node holding the target's signing key
+	// can produce the receipt.
+	var digest string
+	select {
+	case digest = <-digests:
+	case <-ctx.Done():
+		return &transferError{status: http.StatusGatewayTimeout, code: "transfer.timeout", err: ctx.Err()}
+	}
+	var accepted struct {
+		Receipt string `json:"receipt"`
+	}
+	_ = json.NewDecoder(io.LimitReader(pushResp.Body, 4096)).Decode(&accepted)
+	if !hmac.Equal([]byte(accepted.Receipt), []byte(transferReceipt(push.key, push.serverID, digest))) {
+		return &transferError{status: http.StatusBadGateway, code: "transfer.target_unverified"}
 	}
-	writeJSON(w, map[string]any{"ok": true})
+	return nil
 }
 
 // verifyTransferToken checks the source-supplied token: the source
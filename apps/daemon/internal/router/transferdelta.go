@@ -0,0 +1,276 @@
+package router
+
+import (
+	"context"
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"net/http"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/diskio"
+	"github.com/stellarstack/daemon/internal/fsio"
+	"github.com/stellarstack/daemon/internal/logging"
+)
+
+// Delta transfers. Before a delta push the source asks the target for
+// a manifest of what it already holds for the server: its live tree
+// (an earlier transfer, a mirror kept in sync) and what a failed ingest
+// left behind. Every regular file whose size and sha256 match goes out
+// as an empty tar entry carrying deltaKeepRecord, and the target links
+// its own copy into the staging tree instead, after hashing it again.
+// The rest of the push — digest, signature, receipt, commit — is
+// unchanged, so a delta archive is verified like a full one.
+
+// deltaKeepRecord is the PAX record on a kept entry; its value is the
+// file's hex sha256.
+const deltaKeepRecord = "STELLAR.keep"
+
+// partialKeep is how long a failed ingest's tree is kept for the retry
+// to pick up.
+const partialKeep = 24 * time.Hour
+
+// maxManifestBytes caps the manifest the source reads.
+const maxManifestBytes = 256 << 20
+
+// deltaManifest maps slash-separated paths relative to the server root
+// to what the target has there.
+type deltaManifest map[string]deltaFile
+
+type deltaFile struct {
+	Size   int64  `json:"size"`
+	SHA256 string `json:"sha256"`
+}
+
+// unchanged returns the sha256 of the regular file at path when the
+// target has the same bytes at rel, or "".
+func (m deltaManifest) unchanged(rel, path string, info os.FileInfo) string {
+	want, ok := m[rel]
+	if !ok || want.Size != info.Size() {
+		return ""
+	}
+	sum, err := hashFile(path)
+	if err != nil || sum != want.SHA256 {
+		return ""
+	}
+	return sum
+}
+
+// partialPath is where a failed ingest of serverID is kept.
+func (r *Router) partialPath(serverID string) string {
+	return filepath.Join(r.cfg.Transfer.TmpDirectory, serverID+".partial")
+}
+
+// keepPartial replaces the kept tree of an earlier failed ingest with
+// staging, unless staging is empty and the older one more useful. It
+// reports whether a kept tree is left.
+func keepPartial(staging, partial string) bool {
+	if entries, err := os.ReadDir(staging); err != nil || len(entries) == 0 {
+		os.RemoveAll(staging)
+		_, err := os.Stat(partial)
+		return err == nil
+	}
+	_ = os.RemoveAll(partial)
+	if err := os.Rename(staging, partial); err != nil {
+		os.RemoveAll(staging)
+		return false
+	}
+	// The janitor ages it by its own mtime, which a rename keeps.
+	now := time.Now()
+	_ = os.Chtimes(partial, now, now)
+	return true
+}
+
+// handleTransferManifest answers a delta push's first request with the
+// manifest of what this (target) node holds for the server. Same token
+// as the ingest; sealed under a key derived from the transfer key when
+// the push is encrypted, or only served over HTTPS otherwise, like the
+// ingest itself.
+func (r *Router) handleTransferManifest(w http.ResponseWriter, req *http.Request, serverID string) {
+	if !verifyTransferToken(req, r.cfg.SigningKeyHex) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	key, ok := r.inboundKey(w, req)
+	if !ok {
+		return
+	}
+	m := deltaManifest{}
+	// Partial files win: they're newer than the live tree.
+	for _, root := range []string{filepath.Join(r.cfg.DataDir, "servers", serverID), r.partialPath(serverID)} {
+		if err := addToManifest(m, root); err != nil {
+			writeJSONError(w, http.StatusInternalServerError, "transfer.manifest_failed")
+			return
+		}
+	}
+	body, err := json.Marshal(map[string]any{"files": m})
+	if err != nil {
+		writeJSONError(w, http.StatusInternalServerError, "transfer.manifest_failed")
+		return
+	}
+	if key == nil {
+		w.Header().Set("Content-Type", "application/json")
+		_, _ = w.Write(body)
+		return
+	}
+	w.Header().Set("Content-Type", "application/octet-stream")
+	s, err := newSealWriter(w, manifestKey(key))
+	if err != nil {
+		writeJSONError(w, http.StatusInternalServerError, "transfer.key_failed")
+		return
+	}
+	if _, err := s.Write(body); err == nil {
+		_ = s.Close()
+	}
+}
+
+// addToManifest hashes every regular file under root into m. A root
+// that doesn't exist adds nothing.
+func addToManifest(m deltaManifest, root string) error {
+	err := filepath.Walk(root, func(path string, info os.FileInfo, err error) error {
+		if err != nil {
+			return err
+		}
+		if !info.Mode().IsRegular() {
+			return nil
+		}
+		rel, err := filepath.Rel(root, path)
+		if err != nil {
+			return err
+		}
+		sum, err := hashFile(path)
+		if err != nil {
+			return err
+		}
+		m[filepath.ToSlash(rel)] = deltaFile{Size: info.Size(), SHA256: sum}
+		return nil
+	})
+	if os.IsNotExist(err) {
+		return nil
+	}
+	return err
+}
+
+// fetchTargetManifest asks the target for its manifest. Any failure,
+// including a target that predates delta transfers (404), is an error
+// and the caller pushes everything.
+func fetchTargetManifest(ctx context.Context, push transferPush, timeout time.Duration) (deltaManifest, error) {
+	base, ok := strings.CutSuffix(push.targetURL, "/ingest")
+	if !ok {
+		return nil, errors.New("target URL is not an ingest endpoint")
+	}
+	ctx, cancel := context.WithTimeout(ctx, timeout)
+	defer cancel()
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, base+"/manifest", nil)
+	if err != nil {
+		return nil, err
+	}
+	req.Header.Set("X-Stellar-Transfer-Token", push.token)
+	req.Header.Set("X-Stellar-Transfer-Timestamp", fmt.Sprintf("%d", push.timestamp))
+	if push.key != nil {
+		req.Header.Set("X-Stellar-Transfer-Encryption", transferCipher)
+	}
+	resp, err := http.DefaultClient.Do(req)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return nil, errors.New(resp.Status)
+	}
+	var body io.Reader = io.LimitReader(resp.Body, maxManifestBytes)
+	if push.key != nil {
+		opened, err := newOpenReader(body, manifestKey(push.key))
+		if err != nil {
+			return nil, err
+		}
+		body = opened
+	}
+	var out struct {
+		Files deltaManifest `json:"files"`
+	}
+	if err := json.NewDecoder(body).Decode(&out); err != nil {
+		return nil, err
+	}
+	return out.Files, nil
+}
+
+// manifestKey seals the manifest. Frame nonces are counters, so it
+// must not be the archive's key.
+func manifestKey(key []byte) []byte {
+	mac := hmac.New(sha256.New, key)
+	mac.Write([]byte("manifest-key"))
+	return mac.Sum(nil)
+}
+
+// keepFile puts this node's copy of a kept entry at target: from the
+// failed ingest's tree when it has the right bytes, else from the live
+// tree. Hard links where possible; the trees share the tmp directory's
+// filesystem with the staging tree, which commitInbound already relies
+// on.
+func keepFile(candidates []string, clean, target string, mode os.FileMode, sum string) error {
+	for _, root := range candidates {
+		src := filepath.Join(root, clean)
+		st, err := os.Lstat(src)
+		if err != nil || !st.Mode().IsRegular() {
+			continue
+		}
+		// The live tree is the server's own; a symlinked directory on
+		// the way must not reach outside it.
+		realRoot, err := filepath.EvalSymlinks(root)
+		if err != nil {
+			continue
+		}
+		if real, err := filepath.EvalSymlinks(src); err != nil || !strings.HasPrefix(real, realRoot+string(filepath.Separator)) {
+			continue
+		}
+		if got, err := hashFile(src); err != nil || got != sum {
+			continue
+		}
+		if err := os.Link(src, target); err != nil {
+			if err := copyFile(src, target, mode); err != nil {
+				return err
+			}
+		}
+		return os.Chmod(target, mode)
+	}
+	logging.Debugf("transfer", "transfer: kept entry %s no longer matches", clean)
+	return fmt.Errorf("%s changed since the manifest was sent", clean)
+}
+
+func copyFile(src, dst string, mode os.FileMode) error {
+	in, err := os.Open(src)
+	if err != nil {
+		return err
+	}
+	defer in.Close()
+	out, err := os.OpenFile(dst, os.O_CREATE|os.O_WRONLY|os.O_TRUNC, mode)
+	if err != nil {
+		return err
+	}
+	if _, err := fsio.Copy(diskio.Writer(context.Background(), diskio.Background, out), in); err != nil {
+		out.Close()
+		return err
+	}
+	return out.Close()
+}
+
+func hashFile(path string) (string, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+	h := sha256.New()
+	if _, err := fsio.Copy(h, diskio.Reader(context.Background(), diskio.Maintenance, f)); err != nil {
+		return "", err
+	}
+	return hex.EncodeToString(h.Sum(nil)), nil
+}
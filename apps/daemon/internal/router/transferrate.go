@@ -0,0 +1,51 @@
+package router
+
+import (
+	"context"
+	"sync"
+	"time"
+)
+
+// transferRate is the node-wide transfer bandwidth cap, shared by every
+// push and ingest so concurrent transfers split it between them. A nil
+// *transferRate is unlimited.
+type transferRate struct {
+	mu          sync.Mutex
+	bytesPerSec float64
+	next        time.Time // when the bytes granted so far have drained
+}
+
+func newTransferRate(mbps int) *transferRate {
+	if mbps <= 0 {
+		return nil
+	}
+	return &transferRate{bytesPerSec: float64(mbps) * 1e6 / 8}
+}
+
+// wait accounts for n bytes just moved, blocking until the bytes
+// granted before them have drained at the configured rate.
+func (t *transferRate) wait(ctx context.Context, n int) error {
+	if t == nil || n <= 0 {
+		return nil
+	}
+	t.mu.Lock()
+	now := time.Now()
+	start := t.next
+	if start.Before(now) {
+		start = now
+	}
+	t.next = start.Add(time.Duration(float64(n) / t.bytesPerSec * float64(time.Second)))
+	t.mu.Unlock()
+	delay := start.Sub(now)
+	if delay <= 0 {
+		return nil
+	}
+	timer := time.NewTimer(delay)
+	defer timer.Stop()
+	select {
+	case <-ctx.Done():
+		return ctx.Err()
+	case <-timer.C:
+		return nil
+	}
+}
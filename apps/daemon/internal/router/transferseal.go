@@ -0,0 +1,231 @@
+package router
+
+import (
+	"bufio"
+	"crypto/aes"
+	"crypto/cipher"
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/binary"
+	"encoding/hex"
+	"errors"
+	"io"
+)
+
+// Transfer encryption. The panel derives a per-transfer key from the
+// target's signing key and the transfer token, and hands it to the
+// source sealed under the source's own key, so it never crosses the
+// wire in the clear and only the two nodes can recover it. The source
+// encrypts the archive with it, signs the archive digest, and trusts
+// the target only once it answers with a receipt made with the same
+// key: a node that can't derive the key can neither read the archive
+// nor pass for the target.
+
+// transferCipher is the transfer mode (and X-Stellar-Transfer-Encryption
+// value) for an encrypted push.
+const transferCipher = "aes-256-gcm"
+
+// archiveSignatureTrailer carries HMAC(key, archive manifest) on an
+// encrypted push, next to the digest trailer.
+const archiveSignatureTrailer = "X-Stellar-Archive-Signature"
+
+// sealFrameSize is how much plaintext goes into one sealed frame.
+const sealFrameSize = 64 * 1024
+
+// sealFinal marks the last frame in the frame header, so a stream cut
+// at a frame boundary is still caught.
+const sealFinal = 1 << 31
+
+var errSealTruncated = errors.New("transfer: encrypted stream truncated")
+
+// transferMAC is the hex HMAC-SHA256 of msg under key.
+func transferMAC(key []byte, msg string) string {
+	mac := hmac.New(sha256.New, key)
+	mac.Write([]byte(msg))
+	return hex.EncodeToString(mac.Sum(nil))
+}
+
+// transferKey is the target's derivation of the per-transfer key.
+func transferKey(signingKeyHex, token string) ([]byte, error) {
+	key, err := hex.DecodeString(signingKeyHex)
+	if err != nil {
+		return nil, err
+	}
+	mac := hmac.New(sha256.New, key)
+	mac.Write([]byte("transfer-key|" + token))
+	return mac.Sum(nil), nil
+}
+
+// unsealTransferKey recovers the per-transfer key the panel sealed for
+// this (source) node: the sealed value is the key XORed with a pad only
+// this node's signing key produces, unique to the transfer's token.
+func unsealTransferKey(signingKeyHex, token, sealedHex string) ([]byte, error) {
+	sealed, err := hex.DecodeString(sealedHex)
+	if err != nil || len(sealed) != sha256.Size {
+		return nil, errors.New("transfer: malformed sealed key")
+	}
+	key, err := hex.DecodeString(signingKeyHex)
+	if err != nil {
+		return nil, err
+	}
+	mac := hmac.New(sha256.New, key)
+	mac.Write([]byte("transfer-key-seal|" + token))
+	pad := mac.Sum(nil)
+	for i := range sealed {
+		sealed[i] ^= pad[i]
+	}
+	return sealed, nil
+}
+
+// archiveManifest is what the source signs: the server and the sha256
+// of the plaintext archive.
+func archiveManifest(serverID, digest string) string {
+	return "manifest|" + serverID + "|" + digest
+}
+
+// transferReceipt is the target's proof it received and verified the
+// archive.
+func transferReceipt(key []byte, serverID, digest string) string {
+	return transferMAC(key, "receipt|"+serverID+"|"+digest)
+}
+
+func transferAEAD(key []byte) (cipher.AEAD, error) {
+	block, err := aes.NewCipher(key)
+	if err != nil {
+		return nil, err
+	}
+	return cipher.NewGCM(block)
+}
+
+// sealNonce is the frame counter; each key seals exactly one stream, so
+// counters never repeat under a key.
+func sealNonce(aead cipher.AEAD, seq uint64) []byte {
+	nonce := make([]byte, aead.NonceSize())
+	binary.BigEndian.PutUint64(nonce[len(nonce)-8:], seq)
+	return nonce
+}
+
+// sealWriter encrypts what is written to it into length-prefixed GCM
+// frames. The 4-byte header (ciphertext length, high bit set on the
+// last frame) is the frame's associated data. Close writes the final
+// frame and doesn't close w.
+type sealWriter struct {
+	w    io.Writer
+	aead cipher.AEAD
+	buf  []byte
+	seq  uint64
+}
+
+func newSealWriter(w io.Writer, key []byte) (*sealWriter, error) {
+	aead, err := transferAEAD(key)
+	if err != nil {
+		return nil, err
+	}
+	return &sealWriter{w: w, aead: aead, buf: make([]byte, 0, sealFrameSize)}, nil
+}
+
+func (s *sealWriter) Write(p []byte) (int, error) {
+	n := 0
+	for len(p) > 0 {
+		take := min(sealFrameSize-len(s.buf), len(p))
+		s.buf = append(s.buf, p[:take]...)
+		p = p[take:]
+		n += take
+		if len(s.buf) == sealFrameSize {
+			if err := s.flush(false); err != nil {
+				return n, err
+			}
+		}
+	}
+	return n, nil
+}
+
+func (s *sealWriter) Close() error { return s.flush(true) }
+
+func (s *sealWriter) flush(final bool) error {
+	var hdr [4]byte
+	size := uint32(len(s.buf) + s.aead.Overhead())
+	if final {
+		size |= sealFinal
+	}
+	binary.BigEndian.PutUint32(hdr[:], size)
+	frame := s.aead.Seal(hdr[:], sealNonce(s.aead, s.seq), s.buf, hdr[:])
+	s.seq++
+	s.buf = s.buf[:0]
+	_, err := s.w.Write(frame)
+	return err
+}
+
+// openReader decrypts a sealWriter stream. It fails on any frame that
+// doesn't authenticate, and on a stream that ends before the final
+// frame or carries bytes after it.
+type openReader struct {
+	r     *bufio.Reader
+	aead  cipher.AEAD
+	plain []byte
+	frame []byte
+	seq   uint64
+	final bool
+}
+
+func newOpenReader(r io.Reader, key []byte) (*openReader, error) {
+	aead, err := transferAEAD(key)
+	if err != nil {
+		return nil, err
+	}
+	return &openReader{r: bufio.NewReader(r), aead: aead}, nil
+}
+
+func (o *openReader) Read(p []byte) (int, error) {
+	for len(o.plain) == 0 {
+		if o.final {
+			// Read on to EOF so the caller sees the request trailers.
+			if _, err := o.r.ReadByte(); err != io.EOF {
+				if err == nil {
+					err = errors.New("transfer: data after the final frame")
+				}
+				return 0, err
+			}
+			return 0, io.EOF
+		}
+		if err := o.next(); err != nil {
+			return 0, err
+		}
+	}
+	n := copy(p, o.plain)
+	o.plain = o.plain[n:]
+	return n, nil
+}
+
+func (o *openReader) next() error {
+	var hdr [4]byte
+	if _, err := io.ReadFull(o.r, hdr[:]); err != nil {
+		if err == io.EOF {
+			return errSealTruncated
+		}
+		return err
+	}
+	size := binary.BigEndian.Uint32(hdr[:])
+	o.final = size&sealFinal != 0
+	size &^= sealFinal
+	if size < uint32(o.aead.Overhead()) || size > sealFrameSize+uint32(o.aead.Overhead()) {
+		return errors.New("transfer: malformed encrypted frame")
+	}
+	if cap(o.frame) < int(size) {
+		o.frame = make([]byte, size)
+	}
+	o.frame = o.frame[:size]
+	if _, err := io.ReadFull(o.r, o.frame); err != nil {
+		if err == io.EOF {
+			return errSealTruncated
+		}
+		return err
+	}
+	plain, err := o.aead.Open(o.frame[:0], sealNonce(o.aead, o.seq), o.frame, hdr[:])
+	if err != nil {
+		return errors.New("transfer: encrypted frame failed to authenticate")
+	}
+	o.seq++
+	o.plain = plain
+	return nil
+}
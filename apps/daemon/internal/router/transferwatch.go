@@ -0,0 +1,370 @@
+package router
+
+import (
+	"context"
+	"crypto/hmac"
+	"io"
+	"log"
+	"net/http"
+	"os"
+	"path/filepath"
+	"strings"
+	"sync"
+	"sync/atomic"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/logging"
+)
+
+// Stale-transfer handling. Either partner can die mid-transfer, so
+// neither side waits on the other unbounded: the target fails the
+// ingest when the source goes quiet, the source fails the push when
+// bytes stop moving or the target stops answering its heartbeat, and
+// both give up at the overall timeout. Locks on both sides (the push
+// job, the inbound entry below) go with the handler however it ends,
+// and the janitor removes whatever a crashed daemon left in the tmp
+// directory.
+
+// transferJanitorInterval is how often the tmp directory is swept.
+const transferJanitorInterval = 5 * time.Minute
+
+// transferLimits are the deadlines and bandwidth cap a push runs
+// under; see config.TransferConfig.
+type transferLimits struct {
+	timeout time.Duration
+	stall   time.Duration
+	rate    *transferRate
+}
+
+func (r *Router) transferLimits() transferLimits {
+	return transferLimits{
+		timeout: time.Duration(r.cfg.Transfer.TimeoutMinutes) * time.Minute,
+		stall:   time.Duration(r.cfg.Transfer.StallSeconds) * time.Second,
+		rate:    r.transferRate,
+	}
+}
+
+// checkTransferWindow refuses a non-urgent transfer outside the node's
+// transfer window, telling the caller when it opens. Reports whether
+// the transfer may go ahead.
+func (r *Router) checkTransferWindow(w http.ResponseWriter, urgent bool) bool {
+	if urgent {
+		return true
+	}
+	open, opens := r.cfg.Transfer.WindowOpen(time.Now())
+	if !open {
+		writeJSONErrorMessage(w, http.StatusConflict, "transfer.outside_window",
+			"transfers run "+r.cfg.Transfer.Window+"; the window opens "+opens.Format(time.RFC3339))
+	}
+	return open
+}
+
+// inboundTransfer is an ingest running on this (target) node.
+type inboundTransfer struct {
+	token   string
+	staging string
+	bytes   atomic.Int64
+	last    atomic.Int64 // unix nanos of the last byte received
+}
+
+func (in *inboundTransfer) received(n int) {
+	in.bytes.Add(int64(n))
+	in.last.Store(time.Now().UnixNano())
+}
+
+// inboundTransfers is the target-side transfer lock: one ingest per
+// server at a time.
+type inboundTransfers struct {
+	mu     sync.Mutex
+	active map[string]*inboundTransfer
+}
+
+func (t *inboundTransfers) acquire(serverID string, in *inboundTransfer) (release func(), ok bool) {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	if t.active == nil {
+		t.active = map[string]*inboundTransfer{}
+	}
+	if t.active[serverID] != nil {
+		return nil, false
+	}
+	t.active[serverID] = in
+	var once sync.Once
+	return func() {
+		once.Do(func() {
+			t.mu.Lock()
+			delete(t.active, serverID)
+			t.mu.Unlock()
+		})
+	}, true
+}
+
+func (t *inboundTransfers) count() int {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	return len(t.active)
+}
+
+func (t *inboundTransfers) get(serverID string) *inboundTransfer {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	return t.active[serverID]
+}
+
+// owned is the set of staging paths running ingests are writing to.
+func (t *inboundTransfers) owned() map[string]bool {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	out := make(map[string]bool, len(t.active))
+	for _, in := range t.active {
+		out[in.staging] = true
+	}
+	return out
+}
+
+// ingestReader feeds an ingest from the request body. Each read pushes
+// the connection's read deadline out by stall (capped at the transfer's
+// end), so a source that goes quiet fails the read instead of holding
+// the handler and its lock.
+type ingestReader struct {
+	ctx      context.Context
+	r        io.Reader
+	rc       *http.ResponseController
+	in       *inboundTransfer
+	rate     *transferRate
+	stall    time.Duration
+	end      time.Time
+	extended time.Time
+}
+
+func (ir *ingestReader) Read(b []byte) (int, error) {
+	// Once a second is plenty; the deadline is a timer reset, not free.
+	if now := time.Now(); now.Sub(ir.extended) >= time.Second {
+		deadline := now.Add(ir.stall)
+		if deadline.After(ir.end) {
+			deadline = ir.end
+		}
+		_ = ir.rc.SetReadDeadline(deadline)
+		ir.extended = now
+	}
+	n, err := ir.r.Read(b)
+	if n > 0 {
+		ir.in.received(n)
+		if werr := ir.rate.wait(ir.ctx, n); werr != nil {
+			return n, werr
+		}
+	}
+	return n, err
+}
+
+// commitInbound moves a verified staging tree into place as the
+// server's root, replacing whatever was there before.
+func commitInbound(staging, dst string) error {
+	old := staging + ".old"
+	if err := os.Rename(dst, old); err != nil && !os.IsNotExist(err) {
+		return err
+	}
+	if err := os.Rename(staging, dst); err != nil {
+		_ = os.Rename(old, dst)
+		return err
+	}
+	return os.RemoveAll(old)
+}
+
+// handleTransferStatus answers the source's heartbeat while an ingest
+// runs. The token is compared with the one the ingest was started with
+// rather than checked against the clock: a long transfer outlives the
+// token's freshness window. 410 means no such transfer is running.
+func (r *Router) handleTransferStatus(w http.ResponseWriter, req *http.Request, serverID string) {
+	in := r.inbound.get(serverID)
+	tok := req.Header.Get("X-Stellar-Transfer-Token")
+	if in == nil || tok == "" || !hmac.Equal([]byte(tok), []byte(in.token)) {
+		writeJSONError(w, http.StatusGone, "transfer.not_active")
+		return
+	}
+	writeJSON(w, map[string]any{
+		"bytes":      in.bytes.Load(),
+		"lastByteAt": time.Unix(0, in.last.Load()).UTC(),
+	})
+}
+
+// pushReader is the push body. It records when bytes last moved and
+// whether the whole archive has gone out, for watchTransferPush, and
+// holds the push to the node's bandwidth cap.
+type pushReader struct {
+	ctx  context.Context
+	pr   *io.PipeReader
+	rate *transferRate
+	last atomic.Int64
+	done atomic.Bool
+}
+
+func (p *pushReader) Read(b []byte) (int, error) {
+	n, err := p.pr.Read(b)
+	if n > 0 {
+		p.last.Store(time.Now().UnixNano())
+		if werr := p.rate.wait(p.ctx, n); werr != nil {
+			return n, werr
+		}
+	}
+	if err == io.EOF {
+		p.done.Store(true)
+	}
+	return n, err
+}
+
+// Close is called by the HTTP client however the request ends; it
+// unblocks the archive writer.
+func (p *pushReader) Close() error { return p.pr.Close() }
+
+// watchTransferPush aborts the push when no bytes have moved for stall,
+// or the target hasn't answered a heartbeat for as long. A target that
+// predates heartbeats (404) is left to the stall check. Once the body
+// is fully sent the target is only verifying and moving the tree into
+// place, so the watch ends and the overall timeout covers the rest.
+func watchTransferPush(ctx context.Context, abort context.CancelCauseFunc, body *pushReader, statusURL, token string, stall time.Duration) {
+	interval := stall / 4
+	tick := time.NewTicker(interval)
+	defer tick.Stop()
+	heartbeats := statusURL != ""
+	lastBeat := time.Now()
+	for {
+		select {
+		case <-ctx.Done():
+			return
+		case <-tick.C:
+		}
+		if body.done.Load() {
+			return
+		}
+		now := time.Now()
+		if now.Sub(time.Unix(0, body.last.Load())) > stall {
+			abort(&transferError{status: http.StatusGatewayTimeout, code: "transfer.stalled"})
+			return
+		}
+		if !heartbeats {
+			continue
+		}
+		switch status := transferHeartbeat(ctx, statusURL, token, interval); {
+		case status == http.StatusNotFound:
+			heartbeats = false
+		case status/100 == 2:
+			lastBeat = now
+		}
+		if now.Sub(lastBeat) > stall {
+			abort(&transferError{status: http.StatusBadGateway, code: "transfer.partner_lost"})
+			return
+		}
+	}
+}
+
+// transferHeartbeat asks the target how the ingest is going and returns
+// the response status, or 0 if the target couldn't be reached.
+func transferHeartbeat(ctx context.Context, statusURL, token string, timeout time.Duration) int {
+	ctx, cancel := context.WithTimeout(ctx, timeout)
+	defer cancel()
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, statusURL, nil)
+	if err != nil {
+		return 0
+	}
+	req.Header.Set("X-Stellar-Transfer-Token", token)
+	resp, err := http.DefaultClient.Do(req)
+	if err != nil {
+		return 0
+	}
+	_, _ = io.Copy(io.Discard, io.LimitReader(resp.Body, 4096))
+	resp.Body.Close()
+	return resp.StatusCode
+}
+
+// transferStatusURL derives the target's heartbeat endpoint from the
+// ingest URL the panel handed the source; "" if it doesn't look like
+// one.
+func transferStatusURL(targetURL string) string {
+	base, ok := strings.CutSuffix(targetURL, "/ingest")
+	if !ok {
+		return ""
+	}
+	return base + "/status"
+}
+
+// RunTransferJanitor purges what dead transfers left in the tmp
+// directory, at startup and then every few minutes, until ctx ends.
+// First it restores the reservations of the partial trees a restart
+// left behind.
+func (r *Router) RunTransferJanitor(ctx context.Context) {
+	r.restoreIngestReservations(ctx)
+	r.purgeStaleTransfers()
+	tick := time.NewTicker(transferJanitorInterval)
+	defer tick.Stop()
+	for {
+		select {
+		case <-ctx.Done():
+			return
+		case <-tick.C:
+			r.purgeStaleTransfers()
+		}
+	}
+}
+
+// restoreIngestReservations holds the limits of every server with a
+// failed ingest's tree kept for the retry, as the ingest did before the
+// daemon restarted; the janitor drops each when it purges the tree.
+// The limits are the panel's, as at ingest time.
+func (r *Router) restoreIngestReservations(ctx context.Context) {
+	entries, err := os.ReadDir(r.cfg.Transfer.TmpDirectory)
+	if err != nil {
+		return
+	}
+	for _, e := range entries {
+		serverID, ok := strings.CutSuffix(e.Name(), ".partial")
+		if !ok || !e.IsDir() {
+			continue
+		}
+		srv := r.manager.Get(serverID)
+		if err := syncServerConfig(ctx, srv); err != nil {
+			log.Printf("transfer: janitor: config for %s: %v", serverID, err)
+			continue
+		}
+		cfg := srv.Config()
+		r.manager.Hold(serverID, cfg.TotalMemory(), cfg.CPUPercent)
+	}
+}
+
+// purgeStaleTransfers removes every entry in the tmp directory that no
+// running ingest owns, except failed ingests younger than partialKeep.
+// Ingests register before creating their staging directory, so listing
+// first and snapshotting the owners second never catches one
+// mid-creation.
+func (r *Router) purgeStaleTransfers() {
+	dir := r.cfg.Transfer.TmpDirectory
+	entries, err := os.ReadDir(dir)
+	if err != nil {
+		if !os.IsNotExist(err) {
+			log.Printf("transfer: janitor: %v", err)
+		}
+		return
+	}
+	owned := r.inbound.owned()
+	for _, e := range entries {
+		p := filepath.Join(dir, e.Name())
+		if owned[p] || owned[strings.TrimSuffix(p, ".old")] {
+			continue
+		}
+		// A failed ingest's tree waits a while for the retry.
+		if strings.HasSuffix(e.Name(), ".partial") {
+			if info, err := e.Info(); err == nil && time.Since(info.ModTime()) < partialKeep {
+				continue
+			}
+		}
+		if err := os.RemoveAll(p); err != nil {
+			log.Printf("transfer: janitor: remove %s: %v", p, err)
+			continue
+		}
+		// A running ingest for the server holds its own reservation.
+		if serverID, ok := strings.CutSuffix(e.Name(), ".partial"); ok && r.inbound.get(serverID) == nil {
+			r.manager.Unreserve(serverID)
+		}
+		logging.Debugf("transfer", "transfer: janitor: purged stale %s", e.Name())
+	}
+}
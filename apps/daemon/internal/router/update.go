@@ -0,0 +1,84 @@
+package router
+
+import (
+	"context"
+	"errors"
+	"net/http"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/config"
+	"github.com/stellarstack/daemon/internal/selfupdate"
+)
+
+// SetUpdater enables the self-update endpoints. restart is called once
+// the new binary is in place, after the response has been written; it
+// is expected to drain the HTTP server and exec the new binary.
+func (r *Router) SetUpdater(u *selfupdate.Updater, restart func()) {
+	r.updater = u
+	r.restart = restart
+}
+
+// handleUpdate serves the panel-triggered self-update:
+//
+//	POST /api/system/update   {version} → install that release, restart
+//	POST /api/system/rollback           → restore the previous binary, restart
+//
+// 404 update.disabled when the node has no [update] config. Running
+// servers are not touched; the restarted daemon re-adopts them.
+func (r *Router) handleUpdate(w http.ResponseWriter, req *http.Request) {
+	if req.Method != http.MethodPost {
+		http.Error(w, "method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+	if !r.verifyDaemonHMAC(req) {
+		http.Error(w, "unauthorized", http.StatusUnauthorized)
+		return
+	}
+	if r.updater == nil {
+		writeJSONError(w, http.StatusNotFound, "update.disabled")
+		return
+	}
+	var err error
+	version := ""
+	switch req.URL.Path {
+	case "/api/system/update":
+		var body struct {
+			Version string `json:"version"`
+		}
+		if err := decodeJSON(req, &body); err != nil {
+			writeJSONError(w, http.StatusBadRequest, "update.bad_request")
+			return
+		}
+		version = body.Version
+		// Detached from the request: a panel timeout mid-download
+		// shouldn't leave a half-written binary behind.
+		ctx, cancel := context.WithTimeout(context.Background(), 15*time.Minute)
+		defer cancel()
+		err = r.updater.Install(ctx, version)
+	default:
+		err = r.updater.Rollback()
+	}
+	switch {
+	case err == nil:
+	case errors.Is(err, selfupdate.ErrBadVersion):
+		writeJSONError(w, http.StatusBadRequest, "update.bad_version")
+		return
+	case errors.Is(err, selfupdate.ErrInProgress):
+		writeJSONError(w, http.StatusConflict, "update.in_progress")
+		return
+	case errors.Is(err, selfupdate.ErrSignature):
+		writeJSONError(w, http.StatusUnprocessableEntity, "update.bad_signature")
+		return
+	case errors.Is(err, selfupdate.ErrNoPrevious):
+		writeJSONError(w, http.StatusNotFound, "update.no_previous")
+		return
+	default:
+		writeJSONErrorMessage(w, http.StatusBadGateway, "update.failed", err.Error())
+		return
+	}
+	writeJSON(w, map[string]any{"ok": true, "from": config.Version, "version": version, "restarting": true})
+	if f, ok := w.(http.Flusher); ok {
+		f.Flush()
+	}
+	go r.restart()
+}
@@ -0,0 +1,130 @@
+package router
+
+import (
+	"errors"
+	"io"
+	"mime"
+	"net/http"
+	"path"
+
+	"github.com/stellarstack/daemon/apitypes"
+	"github.com/stellarstack/daemon/internal/filepolicy"
+	"github.com/stellarstack/daemon/internal/files"
+)
+
+// uploadFiles stores the "file" parts of a multipart upload under dir.
+// Parts are streamed to disk one at a time (files.Upload), never parsed
+// into memory, so the upload limit can be large: the body is capped at
+// files.max_upload_mb and, when the server has a disk limit, each part
+// is checked against what is left of it as it is written. A part's
+// filename may carry subdirectories (folder uploads send the relative
+// path). Parts written before a failure are kept.
+//
+// ?overwrite= says what happens to a file that already exists:
+// "overwrite" (the default) replaces it, "rename" stores the upload
+// under a free "name (n).ext", and "fail" skips that file. The response
+// lists every file with where it went and its status: "written",
+// "renamed", "exists", "blocked" when the node's file policy refused it
+// or, when the malware scan flagged it, "quarantined".
+func (r *Router) uploadFiles(w http.ResponseWriter, req *http.Request, serverID, dir string) {
+	onConflict := files.OnConflict(req.URL.Query().Get("overwrite"))
+	switch onConflict {
+	case "":
+		onConflict = files.ConflictOverwrite
+	case files.ConflictOverwrite, files.ConflictFail, files.ConflictRename:
+	default:
+		writeJSONErrorMessage(w, http.StatusBadRequest, "files.bad_request",
+			"overwrite must be overwrite, rename or fail")
+		return
+	}
+	if !limitBody(w, req, r.cfg.Files.MaxUploadMb, "files.too_large") {
+		return
+	}
+	mr, err := req.MultipartReader()
+	if err != nil {
+		writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+		return
+	}
+	room, err := r.diskRoom(serverID)
+	if err != nil {
+		writeJSONError(w, http.StatusInternalServerError, "files.upload_failed")
+		return
+	}
+	results := []apitypes.UploadedFile{}
+	count := 0
+	for {
+		part, err := mr.NextPart()
+		if errors.Is(err, io.EOF) {
+			break
+		}
+		if err != nil {
+			if !bodyTooLarge(w, err, "files.too_large") {
+				writeJSONError(w, http.StatusBadRequest, "files.bad_request")
+			}
+			return
+		}
+		name := partFilename(part.Header.Get("Content-Disposition"))
+		if part.FormName() != "file" || name == "" {
+			part.Close()
+			continue
+		}
+		target := path.Join(dir, name)
+		res, err := r.files.Upload(serverID, target, part, files.UploadOptions{Room: room, OnConflict: onConflict})
+		part.Close()
+		switch {
+		case err == nil:
+		case errors.Is(err, files.ErrExists):
+			results = append(results, apitypes.UploadedFile{Name: name, Path: path.Join("/", target), Status: "exists"})
+			continue
+		case errors.Is(err, files.ErrInfected):
+			results = append(results, apitypes.UploadedFile{Name: name, Path: path.Join("/", target), Status: "quarantined"})
+			continue
+		case errors.Is(err, filepolicy.ErrBlocked):
+			results = append(results, apitypes.UploadedFile{Name: name, Path: path.Join("/", target), Status: "blocked"})
+			continue
+		case bodyTooLarge(w, err, "files.too_large"):
+			return
+		case errors.Is(err, files.ErrQuotaExceeded):
+			writeJSONErrorMessage(w, http.StatusInsufficientStorage, "files.quota_exceeded",
+				name+" doesn't fit in the server's disk limit")
+			return
+		default:
+			writeJSONError(w, http.StatusBadRequest, "files.upload_failed")
+			return
+		}
+		if room >= 0 {
+			room = max(room-res.Bytes, 0)
+		}
+		status := "written"
+		if res.Path != path.Join("/", target) {
+			status = "renamed"
+		}
+		results = append(results, apitypes.UploadedFile{Name: name, Path: res.Path, Bytes: res.Bytes, Status: status})
+		count++
+	}
+	writeJSON(w, apitypes.UploadResponse{OK: true, Count: count, Files: results})
+}
+
+// partFilename is the part's filename as sent. multipart.Part.FileName
+// strips it to its base name, which would flatten a folder upload.
+func partFilename(disposition string) string {
+	_, params, err := mime.ParseMediaType(disposition)
+	if err != nil {
+		return ""
+	}
+	return params["filename"]
+}
+
+// diskRoom is how many more bytes the server may write under its disk
+// limit, or -1 when it has none.
+func (r *Router) diskRoom(serverID string) (int64, error) {
+	limit := r.manager.Get(serverID).Config().Disk
+	if limit <= 0 {
+		return -1, nil
+	}
+	used, err := r.files.Usage(serverID)
+	if err != nil {
+		return 0, err
+	}
+	return max(limit<<20-used, 0), nil
+}
@@ -4,18 +4,19 @@ import (
 	"context"
 	"encoding/json"
 	"errors"
+	"fmt"
 	"log"
 	"net/http"
 	"path/filepath"
 	"regexp"
+	"strconv"
 	"strings"
 	"sync"
 	"time"
 
 	"github.com/coder/websocket"
 
-	"github.com/stellarstack/daemon/internal/docker"
-	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/events"
 	"github.com/stellarstack/daemon/internal/jwt"
 	"github.com/stellarstack/daemon/internal/panel"
 	"github.com/stellarstack/daemon/internal/server"
@@ -59,7 +60,10 @@ var serverDirRoot = "/var/lib/stellarstack"
 
 // envelope is the wire shape every frame on the per-server WS uses.
 // `event` is the discriminator; `args` carries event-specific payloads.
+// `v` is the frame schema version; browsers that predate versioning
+// omit it and are treated as the current version.
 type envelope struct {
+	V     int               `json:"v"`
 	Event string            `json:"event"`
 	Args  []json.RawMessage `json:"args"`
 }
@@ -71,6 +75,8 @@ var setStateScope = map[string]string{
 	"stop":    "control.stop",
 	"restart": "control.restart",
 	"kill":    "control.stop",
+	"pause":   "control.stop",
+	"unpause": "control.start",
 }
 
 // handleWS runs the per-server browser-facing WebSocket. One read pump,
@@ -82,27 +88,18 @@ var setStateScope = map[string]string{
 //   - As the token approaches expiry, daemon emits `token expiring` once
 //     (60s before exp) and `token expired` at exp. The browser is
 //     expected to send a fresh `auth` frame on the same socket.
+//
+// Replay: non-console frames carry a `seq`. A client reconnecting with
+// `?since=<last seq>` gets the retained frames it missed (status, job
+// progress, daemon errors, …) right after the initial snapshot.
+//
+// Versioning: every frame carries `v` (events.SchemaVersion). A client
+// may pin `?v=<n>`; a version this daemon doesn't support is refused
+// with 400 before the upgrade rather than streaming frames it would
+// misparse.
 func (r *Router) handleWS(w http.ResponseWriter, req *http.Request, serverUUID string) {
-	token := req.URL.Query().Get("token")
-	if token == "" {
-		http.Error(w, "missing token", http.StatusUnauthorized)
-		return
-	}
-	claims, err := r.verifier.Verify(token)
-	if err != nil {
-		http.Error(w, "invalid token: "+err.Error(), http.StatusUnauthorized)
-		return
-	}
-	if claims.Server != serverUUID {
-		http.Error(w, "token scoped to different server", http.StatusUnauthorized)
-		return
-	}
-	if claims.Node != r.cfg.NodeID {
-		http.Error(w, "token scoped to different node", http.StatusUnauthorized)
-		return
-	}
-	if !claims.HasScope("console.read") {
-		http.Error(w, "token missing console.read scope", http.StatusForbidden)
+	claims, ok := r.consoleClaims(w, req, serverUUID)
+	if !ok {
 		return
 	}
 
@@ -120,7 +117,13 @@ func (r *Router) handleWS(w http.ResponseWriter, req *http.Request, serverUUID s
 	defer cancel()
 
 	srv := r.manager.Get(serverUUID)
-	sub := srv.Bus().Subscribe()
+	var sub *events.Subscriber
+	var missed []events.Frame
+	if since, err := strconv.ParseUint(req.URL.Query().Get("since"), 10, 64); err == nil {
+		sub, missed = srv.Bus().SubscribeSince(since)
+	} else {
+		sub = srv.Bus().Subscribe()
+	}
 	defer sub.Close()
 
 	state := &wsSession{
@@ -146,6 +149,9 @@ func (r *Router) handleWS(w http.ResponseWriter, req *http.Request, serverUUID s
 			_ = writeFrame(ctx, conn, "console output", []any{line})
 		}
 	}
+	for _, frame := range missed {
+		_ = conn.Write(ctx, websocket.MessageText, frame)
+	}
 
 	// Pump bus → ws.
 	go func() {
@@ -173,12 +179,52 @@ func (r *Router) handleWS(w http.ResponseWriter, req *http.Request, serverUUID s
 			_ = writeFrame(ctx, conn, "daemon error", []any{"malformed frame"})
 			continue
 		}
+		if env.V != 0 && !events.Supports(env.V) {
+			_ = writeFrame(ctx, conn, "daemon error", []any{"unsupported schema version"})
+			continue
+		}
 		if err := r.dispatch(ctx, conn, srv, state, &env); err != nil {
 			_ = writeFrame(ctx, conn, "daemon error", []any{err.Error()})
 		}
 	}
 }
 
+// consoleClaims checks a console client's ?token= (and ?v= pin) for
+// serverUUID, answering the request itself when they don't pass. Shared
+// by the WebSocket and its Server-Sent Events fallback.
+func (r *Router) consoleClaims(w http.ResponseWriter, req *http.Request, serverUUID string) (*jwt.Claims, bool) {
+	token := req.URL.Query().Get("token")
+	if token == "" {
+		http.Error(w, "missing token", http.StatusUnauthorized)
+		return nil, false
+	}
+	claims, err := r.verifier.Verify(token)
+	if err != nil {
+		r.refuseToken(w, err)
+		return nil, false
+	}
+	if claims.Server != serverUUID {
+		http.Error(w, "token scoped to different server", http.StatusUnauthorized)
+		return nil, false
+	}
+	if claims.Node != r.cfg.NodeID {
+		http.Error(w, "token scoped to different node", http.StatusUnauthorized)
+		return nil, false
+	}
+	if !claims.HasScope("console.read") {
+		http.Error(w, "token missing console.read scope", http.StatusForbidden)
+		return nil, false
+	}
+	if raw := req.URL.Query().Get("v"); raw != "" {
+		if v, err := strconv.Atoi(raw); err != nil || !events.Supports(v) {
+			writeJSONErrorMessage(w, http.StatusBadRequest, "ws.unsupported_version",
+				"unsupported message schema version "+raw)
+			return nil, false
+		}
+	}
+	return claims, true
+}
+
 // wsSession is the per-connection mutable state: the active claims (for
 // re-auth) and a guard against double-firing token-expiry warnings.
 type wsSession struct {
@@ -254,64 +300,15 @@ func (r *Router) handleSetState(ctx context.Context, conn *websocket.Conn, srv *
 	if !claims.HasScope(scope) {
 		return errors.New("set state: missing scope " + scope)
 	}
-	go func() {
-		runCtx, cancel := context.WithTimeout(context.Background(), 15*time.Minute)
-		defer cancel()
-		// Fresh config pull on every power action so a panel-side
-		// blueprint/variable/memory change lands on the next start.
-		// Stop/kill don't strictly need it but the call is cheap.
-		if p := srv.Panel(); p != nil {
-			cfgCtx, cfgCancel := context.WithTimeout(runCtx, 10*time.Second)
-			cfg, err := p.FetchServerConfig(cfgCtx, srv.UUID())
-			cfgCancel()
-			if err != nil {
-				log.Printf("set state %s: fetch config: %v", action, err)
-				return
-			}
-			ports := make([]docker.PortMapping, 0, len(cfg.Ports))
-			for _, p := range cfg.Ports {
-				ports = append(ports, docker.PortMapping{
-					HostIP:        p.HostIP,
-					HostPort:      p.HostPort,
-					ContainerPort: p.ContainerPort,
-				})
-			}
-			done := make([]*regexp.Regexp, 0, len(cfg.StartupDone))
-			for _, p := range cfg.StartupDone {
-				re, ok := compileDonePattern(p)
-				if !ok {
-					continue
-				}
-				done = append(done, re)
-			}
-			patches := make([]server.ConfigFilePatch, 0, len(cfg.ConfigFiles))
-			for _, f := range cfg.ConfigFiles {
-				patches = append(patches, server.ConfigFilePatch{
-					Path:    f.Path,
-					Parser:  f.Parser,
-					Patches: f.Patches,
-				})
-			}
-			srv.SetConfig(server.Config{
-				DockerImage:    cfg.DockerImage,
-				StartupCommand: cfg.StartupCommand,
-				Environment:    cfg.Environment,
-				Stop: environment.StopConfig{
-					Type:  cfg.Stop.Type,
-					Value: cfg.Stop.Value,
-				},
-				Memory:       cfg.MemoryLimitMb,
-				CPUPercent:   cfg.CPULimitPercent,
-				PortMappings: ports,
-				BindMount:    filepathServerDir(srv.UUID()),
-				StartupDone:  done,
-				ConfigFiles:  patches,
-			})
-		}
-		if err := srv.HandlePower(runCtx, server.PowerAction(action)); err != nil {
-			log.Printf("set state %s: %v", action, err)
-		}
-	}()
+	// Fresh config pull on every power action so a panel-side
+	// blueprint/variable/memory change lands on the next start.
+	// Stop/kill don't strictly need it but the call is cheap.
+	_, err := srv.EnqueuePower(server.PowerAction(action), func(ctx context.Context) error {
+		return syncServerConfig(ctx, srv)
+	})
+	if err != nil {
+		return fmt.Errorf("set state %s: %w", action, err)
+	}
 	// Best-effort audit push so the activity tab learns who triggered the
 	// power action; happens out of band and does not block the response.
 	if p := srv.Panel(); p != nil {
@@ -385,12 +382,5 @@ func (r *Router) watchTokenExpiry(ctx context.Context, conn *websocket.Conn, ses
 }
 
 func writeFrame(ctx context.Context, conn *websocket.Conn, event string, args []any) error {
-	frame, err := json.Marshal(map[string]any{
-		"event": event,
-		"args":  args,
-	})
-	if err != nil {
-		return err
-	}
-	return conn.Write(ctx, websocket.MessageText, frame)
+	return conn.Write(ctx, websocket.MessageText, events.Encode(event, args))
 }
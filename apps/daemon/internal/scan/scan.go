@@ -0,0 +1,168 @@
+// Package scan checks files arriving on a server from outside (uploads
+// and pulls) for malware before they are put in place, for hosts that
+// must answer abuse reports. Two backends:
+//
+//   - clamd, spoken to over its socket with INSTREAM, so clamd needs no
+//     access to the server's files. An address starting with "/" is a
+//     Unix socket, anything else host:port.
+//   - an external command, given the file's path as its last argument
+//     or wherever "{path}" appears. It follows clamscan's convention:
+//     exit 0 clean, 1 infected (the first line of output names the
+//     signature), anything else a failed scan.
+//
+// With both configured clamd is asked first and the command only when
+// clamd can't be reached.
+package scan
+
+import (
+	"bufio"
+	"bytes"
+	"context"
+	"encoding/binary"
+	"errors"
+	"fmt"
+	"io"
+	"net"
+	"os"
+	"os/exec"
+	"strings"
+	"time"
+)
+
+// ErrDisabled: neither backend is configured.
+var ErrDisabled = errors.New("malware scanning is not configured")
+
+// chunkSize is how much of the file goes in each INSTREAM chunk.
+const chunkSize = 64 << 10
+
+// Verdict is a scan's outcome. Signature names what was found.
+type Verdict struct {
+	Infected  bool
+	Signature string
+}
+
+// Scanner scans files with clamd, an external command or both.
+type Scanner struct {
+	clamd   string
+	command []string
+	timeout time.Duration
+}
+
+// New returns a scanner, or ErrDisabled when clamd and command are both
+// empty. timeout bounds one scan; 0 means a minute.
+func New(clamd string, command []string, timeout time.Duration) (*Scanner, error) {
+	if clamd == "" && len(command) == 0 {
+		return nil, ErrDisabled
+	}
+	if timeout <= 0 {
+		timeout = time.Minute
+	}
+	return &Scanner{clamd: clamd, command: command, timeout: timeout}, nil
+}
+
+// Scan checks the file at path.
+func (s *Scanner) Scan(ctx context.Context, path string) (Verdict, error) {
+	ctx, cancel := context.WithTimeout(ctx, s.timeout)
+	defer cancel()
+	if s.clamd != "" {
+		v, err := s.clamdScan(ctx, path)
+		if err == nil || len(s.command) == 0 {
+			return v, err
+		}
+	}
+	return s.commandScan(ctx, path)
+}
+
+func (s *Scanner) clamdScan(ctx context.Context, path string) (Verdict, error) {
+	network := "tcp"
+	if strings.HasPrefix(s.clamd, "/") {
+		network = "unix"
+	}
+	conn, err := (&net.Dialer{}).DialContext(ctx, network, s.clamd)
+	if err != nil {
+		return Verdict{}, fmt.Errorf("clamd: %w", err)
+	}
+	defer conn.Close()
+	if dl, ok := ctx.Deadline(); ok {
+		_ = conn.SetDeadline(dl)
+	}
+	f, err := os.Open(path)
+	if err != nil {
+		return Verdict{}, err
+	}
+	defer f.Close()
+	if _, err := conn.Write([]byte("zINSTREAM\x00")); err != nil {
+		return Verdict{}, fmt.Errorf("clamd: %w", err)
+	}
+	buf := make([]byte, 4+chunkSize)
+	for {
+		n, err := f.Read(buf[4:])
+		if n > 0 {
+			binary.BigEndian.PutUint32(buf[:4], uint32(n))
+			if _, err := conn.Write(buf[:4+n]); err != nil {
+				return Verdict{}, fmt.Errorf("clamd: %w", err)
+			}
+		}
+		if errors.Is(err, io.EOF) {
+			break
+		}
+		if err != nil {
+			return Verdict{}, err
+		}
+	}
+	if _, err := conn.Write([]byte{0, 0, 0, 0}); err != nil {
+		return Verdict{}, fmt.Errorf("clamd: %w", err)
+	}
+	reply, err := bufio.NewReader(conn).ReadString(0)
+	if err != nil && !errors.Is(err, io.EOF) {
+		return Verdict{}, fmt.Errorf("clamd: %w", err)
+	}
+	return parseClamdReply(strings.TrimRight(reply, "\x00\n"))
+}
+
+// parseClamdReply reads "stream: OK", "stream: <sig> FOUND" or
+// "<reason> ERROR".
+func parseClamdReply(reply string) (Verdict, error) {
+	result := strings.TrimSpace(strings.TrimPrefix(reply, "stream:"))
+	switch {
+	case result == "OK":
+		return Verdict{}, nil
+	case strings.HasSuffix(result, " FOUND"):
+		return Verdict{Infected: true, Signature: strings.TrimSuffix(result, " FOUND")}, nil
+	default:
+		return Verdict{}, fmt.Errorf("clamd: %s", result)
+	}
+}
+
+func (s *Scanner) commandScan(ctx context.Context, path string) (Verdict, error) {
+	args := make([]string, 0, len(s.command)+1)
+	placed := false
+	for _, a := range s.command[1:] {
+		if strings.Contains(a, "{path}") {
+			a = strings.ReplaceAll(a, "{path}", path)
+			placed = true
+		}
+		args = append(args, a)
+	}
+	if !placed {
+		args = append(args, path)
+	}
+	cmd := exec.CommandContext(ctx, s.command[0], args...)
+	var out bytes.Buffer
+	cmd.Stdout = &out
+	cmd.Stderr = &out
+	err := cmd.Run()
+	var exit *exec.ExitError
+	switch {
+	case err == nil:
+		return Verdict{}, nil
+	case errors.As(err, &exit) && exit.ExitCode() == 1:
+		sig, _, _ := strings.Cut(strings.TrimSpace(out.String()), "\n")
+		if sig == "" {
+			sig = "unknown"
+		}
+		return Verdict{Infected: true, Signature: sig}, nil
+	default:
+		return Verdict{}, fmt.Errorf("scan command: %w: %s", err, strings.TrimSpace(out.String()))
+	}
+}
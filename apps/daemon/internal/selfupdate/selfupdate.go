@@ -0,0 +1,228 @@
+// Package selfupdate replaces the running daemon binary with a signed
+// release and hands the process over to it.
+//
+// Releases live under `<release_url>/<version>/`:
+//
+//	stellar-daemon-<goos>-<goarch>      the binary
+//	stellar-daemon-<goos>-<goarch>.sig  base64 Ed25519 signature
+//
+// The signature is over "<version>\n<sha256 hex of the binary>", so a
+// genuine binary can't be served under another version (an old,
+// vulnerable one as the latest), and the binary is hashed as it
+// streams to disk instead of being held in memory. The new binary is
+// written next to the current one, checked with `<new> version` — which
+// must report the version asked for — and renamed over it; the old one
+// is hard-linked to `<exe>.previous` first so Rollback is a single
+// rename as well.
+//
+// Game containers belong to Docker, not the daemon, so they keep running
+// across the handoff; the new process re-adopts them in Reconcile.
+package selfupdate
+
+import (
+	"context"
+	"crypto/ed25519"
+	"crypto/sha256"
+	"encoding/base64"
+	"encoding/hex"
+	"errors"
+	"fmt"
+	"io"
+	"net/http"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"regexp"
+	"runtime"
+	"strings"
+	"sync"
+	"syscall"
+	"time"
+)
+
+// maxBinaryBytes bounds the download; the daemon is ~20 MB.
+const maxBinaryBytes = 256 << 20
+
+var (
+	ErrDisabled   = errors.New("self-update is not configured")
+	ErrBadVersion = errors.New("invalid version")
+	ErrInProgress = errors.New("an update is already in progress")
+	ErrSignature  = errors.New("signature does not verify")
+	ErrNoPrevious = errors.New("no previous binary to roll back to")
+)
+
+var versionRE = regexp.MustCompile(`^v?[0-9A-Za-z][0-9A-Za-z.+-]{0,63}$`)
+
+// Updater downloads, verifies and installs daemon releases.
+type Updater struct {
+	releaseURL string
+	publicKey  ed25519.PublicKey
+	exe        string
+	http       *http.Client
+	mu         sync.Mutex
+}
+
+// New returns an Updater for releases under releaseURL signed by the
+// hex Ed25519 publicKeyHex. Either empty → ErrDisabled.
+func New(releaseURL, publicKeyHex string) (*Updater, error) {
+	if releaseURL == "" || publicKeyHex == "" {
+		return nil, ErrDisabled
+	}
+	key, err := hex.DecodeString(publicKeyHex)
+	if err != nil || len(key) != ed25519.PublicKeySize {
+		return nil, fmt.Errorf("public_key must be %d hex-encoded bytes", ed25519.PublicKeySize)
+	}
+	exe, err := os.Executable()
+	if err != nil {
+		return nil, fmt.Errorf("locate executable: %w", err)
+	}
+	if exe, err = filepath.EvalSymlinks(exe); err != nil {
+		return nil, fmt.Errorf("locate executable: %w", err)
+	}
+	return &Updater{
+		releaseURL: strings.TrimRight(releaseURL, "/"),
+		publicKey:  ed25519.PublicKey(key),
+		exe:        exe,
+		http:       &http.Client{Timeout: 10 * time.Minute},
+	}, nil
+}
+
+// Install downloads and verifies version, then swaps it in for the
+// running binary. The running process is untouched; call Exec (or
+// restart the service) to switch over.
+func (u *Updater) Install(ctx context.Context, version string) error {
+	if !versionRE.MatchString(version) {
+		return ErrBadVersion
+	}
+	if !u.mu.TryLock() {
+		return ErrInProgress
+	}
+	defer u.mu.Unlock()
+
+	asset := fmt.Sprintf("%s/%s/stellar-daemon-%s-%s", u.releaseURL, version, runtime.GOOS, runtime.GOARCH)
+	sig, err := u.fetchSignature(ctx, asset+".sig")
+	if err != nil {
+		return err
+	}
+	tmp := u.exe + ".new"
+	defer os.Remove(tmp)
+	digest, err := u.download(ctx, asset, tmp)
+	if err != nil {
+		return err
+	}
+	if !ed25519.Verify(u.publicKey, SignedMessage(version, digest), sig) {
+		return ErrSignature
+	}
+	// A binary for the wrong libc or a truncated release fails here
+	// rather than after we've exec'd into it.
+	checkCtx, cancel := context.WithTimeout(ctx, 10*time.Second)
+	defer cancel()
+	out, err := exec.CommandContext(checkCtx, tmp, "version").CombinedOutput()
+	if err != nil {
+		return fmt.Errorf("new binary doesn't run: %v: %s", err, strings.TrimSpace(string(out)))
+	}
+	if got := strings.TrimSpace(string(out)); got != version {
+		return fmt.Errorf("new binary reports version %q, not %s", got, version)
+	}
+
+	prev := u.exe + ".previous"
+	if err := os.Remove(prev); err != nil && !errors.Is(err, os.ErrNotExist) {
+		return err
+	}
+	if err := os.Link(u.exe, prev); err != nil {
+		return fmt.Errorf("keep previous binary: %w", err)
+	}
+	if err := os.Rename(tmp, u.exe); err != nil {
+		return fmt.Errorf("swap binary: %w", err)
+	}
+	return nil
+}
+
+// Rollback restores the binary replaced by the last Install.
+func (u *Updater) Rollback() error {
+	if !u.mu.TryLock() {
+		return ErrInProgress
+	}
+	defer u.mu.Unlock()
+	if err := os.Rename(u.exe+".previous", u.exe); err != nil {
+		if errors.Is(err, os.ErrNotExist) {
+			return ErrNoPrevious
+		}
+		return err
+	}
+	return nil
+}
+
+// SignedMessage is what a release's .sig signs: the version, a
+// newline, and the hex SHA-256 of the binary (as in its .sha256 file).
+func SignedMessage(version string, sha256sum []byte) []byte {
+	return []byte(version + "\n" + hex.EncodeToString(sha256sum))
+}
+
+func (u *Updater) fetchSignature(ctx context.Context, url string) ([]byte, error) {
+	body, err := u.get(ctx, url)
+	if err != nil {
+		return nil, err
+	}
+	defer body.Close()
+	raw, err := io.ReadAll(io.LimitReader(body, 1024))
+	if err != nil {
+		return nil, err
+	}
+	sig, err := base64.StdEncoding.DecodeString(strings.TrimSpace(string(raw)))
+	if err != nil || len(sig) != ed25519.SignatureSize {
+		return nil, ErrSignature
+	}
+	return sig, nil
+}
+
+// download streams url into path (mode 0755, fsynced) and returns the
+// SHA-256 of what it wrote.
+func (u *Updater) download(ctx context.Context, url, path string) ([]byte, error) {
+	body, err := u.get(ctx, url)
+	if err != nil {
+		return nil, err
+	}
+	defer body.Close()
+	f, err := os.OpenFile(path, os.O_CREATE|os.O_WRONLY|os.O_TRUNC, 0o755)
+	if err != nil {
+		return nil, err
+	}
+	defer f.Close()
+	h := sha256.New()
+	n, err := io.Copy(io.MultiWriter(f, h), io.LimitReader(body, maxBinaryBytes+1))
+	if err != nil {
+		return nil, fmt.Errorf("download %s: %w", url, err)
+	}
+	if n > maxBinaryBytes {
+		return nil, fmt.Errorf("download %s: larger than %d bytes", url, maxBinaryBytes)
+	}
+	if err := f.Sync(); err != nil {
+		return nil, err
+	}
+	return h.Sum(nil), f.Close()
+}
+
+func (u *Updater) get(ctx context.Context, url string) (io.ReadCloser, error) {
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, url, nil)
+	if err != nil {
+		return nil, err
+	}
+	resp, err := u.http.Do(req)
+	if err != nil {
+		return nil, fmt.Errorf("download %s: %w", url, err)
+	}
+	if resp.StatusCode != http.StatusOK {
+		resp.Body.Close()
+		return nil, fmt.Errorf("download %s: %s", url, resp.Status)
+	}
+	return resp.Body, nil
+}
+
+// Exec replaces the current process with the binary now at the
+// executable path, keeping the PID (so systemd sees no restart), the
+// arguments and the environment. Listeners must be closed first: the
+// new process binds them again. Only returns on failure.
+func (u *Updater) Exec() error {
+	return syscall.Exec(u.exe, os.Args, os.Environ())
+}
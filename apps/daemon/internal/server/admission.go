@@ -0,0 +1,211 @@
+package server
+
+import (
+	"bufio"
+	"fmt"
+	"os"
+	"runtime"
+	"strconv"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// Capacity is what the node may hand out to servers. Limits are the
+// physical figures scaled by the overcommit ratios.
+type Capacity struct {
+	MemoryMb         int64
+	CPUPercent       int64
+	MemoryOvercommit float64
+	CPUOvercommit    float64
+}
+
+func (c Capacity) memoryLimitMb() int64 {
+	return int64(float64(c.MemoryMb) * c.MemoryOvercommit)
+}
+
+func (c Capacity) cpuLimitPercent() int64 {
+	return int64(float64(c.CPUPercent) * c.CPUOvercommit)
+}
+
+// DetectCapacity fills in zero fields from the host: MemTotal from
+// /proc/meminfo and 100% per logical CPU.
+func DetectCapacity(c Capacity) Capacity {
+	if c.MemoryMb <= 0 {
+		c.MemoryMb = hostMemoryMb()
+	}
+	if c.CPUPercent <= 0 {
+		c.CPUPercent = int64(runtime.NumCPU()) * 100
+	}
+	return c
+}
+
+func hostMemoryMb() int64 {
+	f, err := os.Open("/proc/meminfo")
+	if err != nil {
+		return 0
+	}
+	defer f.Close()
+	sc := bufio.NewScanner(f)
+	for sc.Scan() {
+		fields := strings.Fields(sc.Text())
+		if len(fields) >= 2 && fields[0] == "MemTotal:" {
+			kb, err := strconv.ParseInt(fields[1], 10, 64)
+			if err != nil {
+				return 0
+			}
+			return kb / 1024
+		}
+	}
+	return 0
+}
+
+// AdmissionError is returned when admitting a server would push the
+// node past its (overcommitted) capacity for one resource.
+type AdmissionError struct {
+	Resource  string
+	Unit      string
+	Committed int64
+	Requested int64
+	Limit     int64
+}
+
+func (e *AdmissionError) Error() string {
+	return fmt.Sprintf(
+		"node would be oversubscribed on %s: %d %s committed + %d %s requested exceeds the %d %s limit",
+		e.Resource, e.Committed, e.Unit, e.Requested, e.Unit, e.Limit, e.Unit,
+	)
+}
+
+// Commitment is the sum of configured limits across servers that are
+// not offline, and those reserved for servers on their way here.
+// Servers without a limit (0) don't contribute.
+type Commitment struct {
+	Servers    int   `json:"servers"`
+	MemoryMb   int64 `json:"memoryMb"`
+	CPUPercent int64 `json:"cpuPercent"`
+}
+
+// AdmissionReport is the /api/system view of node commitment.
+type AdmissionReport struct {
+	Enabled   bool       `json:"enabled"`
+	Committed Commitment `json:"committed"`
+	Memory    struct {
+		CapacityMb int64   `json:"capacityMb"`
+		Overcommit float64 `json:"overcommit"`
+		LimitMb    int64   `json:"limitMb"`
+	} `json:"memory"`
+	CPU struct {
+		CapacityPercent int64   `json:"capacityPercent"`
+		Overcommit      float64 `json:"overcommit"`
+		LimitPercent    int64   `json:"limitPercent"`
+	} `json:"cpu"`
+}
+
+// SetAdmission enables admission control with the supplied capacity.
+// Pass nil to disable.
+func (m *Manager) SetAdmission(c *Capacity) {
+	m.admitMu.Lock()
+	defer m.admitMu.Unlock()
+	m.capacity = c
+}
+
+// Commitment sums the limits of every non-offline server and every
+// reservation other than `exclude`'s.
+func (m *Manager) Commitment(exclude string) Commitment {
+	var out Commitment
+	counted := map[string]bool{}
+	for _, s := range m.All() {
+		if s.uuid == exclude || s.env.State() == environment.StateOffline {
+			continue
+		}
+		cfg := s.Config()
+		counted[s.uuid] = true
+		out.Servers++
+		out.MemoryMb += cfg.TotalMemory()
+		out.CPUPercent += cfg.CPUPercent
+	}
+	m.reserveMu.Lock()
+	defer m.reserveMu.Unlock()
+	for uuid, r := range m.reserved {
+		if uuid == exclude || counted[uuid] {
+			continue
+		}
+		out.Servers++
+		out.MemoryMb += r.MemoryMb
+		out.CPUPercent += r.CPUPercent
+	}
+	return out
+}
+
+// Admit checks that committing (memoryMb, cpuPercent) for uuid keeps
+// the node inside its limits. Returns nil when admission control is
+// disabled. Serialised so two concurrent starts can't both squeeze
+// into the last slot.
+func (m *Manager) Admit(uuid string, memoryMb, cpuPercent int64) error {
+	m.admitMu.Lock()
+	defer m.admitMu.Unlock()
+	return m.admit(uuid, memoryMb, cpuPercent)
+}
+
+// admit is Admit with admitMu held.
+func (m *Manager) admit(uuid string, memoryMb, cpuPercent int64) error {
+	if m.capacity == nil {
+		return nil
+	}
+	c := m.Commitment(uuid)
+	if limit := m.capacity.memoryLimitMb(); limit > 0 && memoryMb > 0 && c.MemoryMb+memoryMb > limit {
+		return &AdmissionError{Resource: "memory", Unit: "MB", Committed: c.MemoryMb, Requested: memoryMb, Limit: limit}
+	}
+	if limit := m.capacity.cpuLimitPercent(); limit > 0 && cpuPercent > 0 && c.CPUPercent+cpuPercent > limit {
+		return &AdmissionError{Resource: "cpu", Unit: "%", Committed: c.CPUPercent, Requested: cpuPercent, Limit: limit}
+	}
+	return nil
+}
+
+// Reserve admits (memoryMb, cpuPercent) for uuid and keeps counting
+// it until Unreserve, whatever the server's state: an inbound transfer
+// holds its server's limits from admission until the staged tree is
+// committed or dropped, so two ingests can't both take the last slot.
+// A second Reserve for uuid replaces the first.
+func (m *Manager) Reserve(uuid string, memoryMb, cpuPercent int64) error {
+	m.admitMu.Lock()
+	defer m.admitMu.Unlock()
+	if err := m.admit(uuid, memoryMb, cpuPercent); err != nil {
+		return err
+	}
+	m.Hold(uuid, memoryMb, cpuPercent)
+	return nil
+}
+
+// Hold records a reservation without admitting it, for one the node
+// already made before a restart.
+func (m *Manager) Hold(uuid string, memoryMb, cpuPercent int64) {
+	m.reserveMu.Lock()
+	defer m.reserveMu.Unlock()
+	m.reserved[uuid] = Commitment{MemoryMb: memoryMb, CPUPercent: cpuPercent}
+}
+
+// Unreserve drops uuid's reservation, if it has one.
+func (m *Manager) Unreserve(uuid string) {
+	m.reserveMu.Lock()
+	defer m.reserveMu.Unlock()
+	delete(m.reserved, uuid)
+}
+
+// AdmissionReport returns current commitment against capacity.
+func (m *Manager) AdmissionReport() AdmissionReport {
+	m.admitMu.Lock()
+	capacity := m.capacity
+	m.admitMu.Unlock()
+	r := AdmissionReport{Enabled: capacity != nil, Committed: m.Commitment("")}
+	if capacity != nil {
+		r.Memory.CapacityMb = capacity.MemoryMb
+		r.Memory.Overcommit = capacity.MemoryOvercommit
+		r.Memory.LimitMb = capacity.memoryLimitMb()
+		r.CPU.CapacityPercent = capacity.CPUPercent
+		r.CPU.Overcommit = capacity.CPUOvercommit
+		r.CPU.LimitPercent = capacity.cpuLimitPercent()
+	}
+	return r
+}
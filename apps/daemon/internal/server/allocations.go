@@ -0,0 +1,84 @@
+package server
+
+import (
+	"context"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/panel"
+)
+
+// AllocationUsage collects the host ports bound by every running
+// server container. Docker reports a wildcard binding once per address
+// family; those collapse to one entry per (ip, port).
+func (m *Manager) AllocationUsage(ctx context.Context) (panel.AllocationUsage, error) {
+	usage := panel.AllocationUsage{Running: []string{}, Bindings: []panel.AllocationBinding{}}
+	published, err := m.docker.PublishedPorts(ctx)
+	if err != nil {
+		return usage, err
+	}
+	m.mu.RLock()
+	known := make(map[string]*Server, len(m.servers))
+	for id, s := range m.servers {
+		known[id] = s
+	}
+	m.mu.RUnlock()
+	for id, s := range known {
+		if s.env.State() != environment.StateOffline {
+			usage.Running = append(usage.Running, id)
+		}
+	}
+	seen := map[panel.AllocationBinding]bool{}
+	for _, p := range published {
+		id, ok := strings.CutPrefix(p.Container, "stellar-")
+		if !ok || known[id] == nil {
+			continue
+		}
+		ip := p.HostIP
+		if ip == "::" {
+			ip = "0.0.0.0"
+		}
+		b := panel.AllocationBinding{ServerID: id, IP: ip, Port: p.HostPort}
+		if seen[b] {
+			continue
+		}
+		seen[b] = true
+		usage.Bindings = append(usage.Bindings, b)
+	}
+	// Host-network containers publish nothing through Docker; report
+	// their configured ports as bound while they run. The daemon can't
+	// tell whether the game actually listens on them.
+	for id, s := range known {
+		cfg := s.Config()
+		if !cfg.HostNetwork || s.env.State() == environment.StateOffline {
+			continue
+		}
+		for _, p := range cfg.PortMappings {
+			ip := p.HostIP
+			if ip == "" {
+				ip = "0.0.0.0"
+			}
+			for i := 0; i < p.Span(); i++ {
+				b := panel.AllocationBinding{ServerID: id, IP: ip, Port: p.HostPort + i}
+				if !seen[b] {
+					seen[b] = true
+					usage.Bindings = append(usage.Bindings, b)
+				}
+			}
+		}
+	}
+	return usage, nil
+}
+
+// ReportAllocations pushes AllocationUsage to the panel. No-op without
+// a panel client.
+func (m *Manager) ReportAllocations(ctx context.Context) error {
+	if m.panel == nil {
+		return nil
+	}
+	usage, err := m.AllocationUsage(ctx)
+	if err != nil {
+		return err
+	}
+	return m.panel.PushAllocationUsage(ctx, usage)
+}
@@ -1,12 +1,17 @@
 package server
 
 import (
+	"encoding/json"
+	"errors"
 	"fmt"
 	"log"
 	"os"
 	"path/filepath"
 	"regexp"
-	"strings"
+	"sort"
+	"strconv"
+
+	"github.com/stellarstack/daemon/internal/configfile"
 )
 
 // applyConfigFiles walks the blueprint's configFiles and patches each
@@ -14,27 +19,80 @@ import (
 // daemon's pre-start "config files" step: supports {{ENV_VAR}}
 // substitution and per-parser key paths.
 //
-// Today only the `properties` parser is implemented (covers Minecraft
-// server.properties — the most common case). Other parsers no-op
-// with a warning so a missing patcher doesn't block a start.
-func (s *Server) applyConfigFiles(bindMount string, env map[string]string) {
+// The properties, json, toml and yaml parsers are implemented (see
+// configfile). The others (ini, xml) no-op with a warning so a missing
+// patcher doesn't block a start.
+//
+// Writes are two-phase (see writeConfigFile): the patched file is
+// rendered next to the original, parsed back, and only swapped in when
+// it reads as intended. A file that fails is left as it was and the
+// reason goes to the console; the server starts on its old config. A
+// file whose keys already hold their values isn't rewritten.
+func (s *Server) applyConfigFiles(bindMount string, env map[string]string) []ConfigFileResult {
 	cfg := s.Config()
+	results := make([]ConfigFileResult, 0, len(cfg.ConfigFiles))
 	for _, f := range cfg.ConfigFiles {
 		abs := filepath.Join(bindMount, f.Path)
 		patched := substituteVars(f.Patches, env)
-		switch f.Parser {
-		case "properties":
-			if err := patchPropertiesFile(abs, patched); err != nil {
+		res := ConfigFileResult{Path: f.Path, Changes: []ConfigChange{}}
+		switch {
+		case patchable(f.Parser):
+			changes, err := patchConfigFile(abs, f.Parser, patched)
+			if err != nil {
+				res.Error = err.Error()
 				s.publishDaemon(
 					fmt.Sprintf("Couldn't patch %s: %v", f.Path, err),
 				)
 			} else {
-				log.Printf("server %s: patched %s (%d keys)", s.uuid, f.Path, len(patched))
+				res.Changes = changes
+				log.Printf("server %s: patched %s (%d keys, %d changed)", s.uuid, f.Path, len(patched), len(changes))
 			}
 		default:
+			res.Skipped = true
 			log.Printf("server %s: configFiles parser %q not implemented yet, skipping %s", s.uuid, f.Parser, f.Path)
 		}
+		results = append(results, res)
+	}
+	return results
+}
+
+// patchable reports whether applyConfigFiles patches files of parser.
+func patchable(parser string) bool {
+	switch parser {
+	case configfile.Properties, configfile.JSON, configfile.TOML, configfile.YAML:
+		return true
 	}
+	return false
+}
+
+// ConfigFileResult is what patching one config file did: the keys it
+// changed, or why it couldn't. Skipped files have a parser the daemon
+// doesn't implement.
+type ConfigFileResult struct {
+	Path    string         `json:"path"`
+	Changes []ConfigChange `json:"changes"`
+	Skipped bool           `json:"skipped,omitempty"`
+	Error   string         `json:"error,omitempty"`
+}
+
+// ConfigChange is one key a patch changed. Old is empty for a key the
+// file didn't have.
+type ConfigChange struct {
+	Key string `json:"key"`
+	Old string `json:"old"`
+	New string `json:"new"`
+}
+
+// ApplyConfigFiles re-applies the variable substitutions to the
+// server's config files now, outside a start, so a variable changed in
+// the panel reaches a running server that can reload its config. Call
+// SetConfig with the panel's current config first.
+func (s *Server) ApplyConfigFiles() ([]ConfigFileResult, error) {
+	cfg := s.Config()
+	if cfg.BindMount == "" {
+		return nil, errors.New("server has no data directory configured")
+	}
+	return s.applyConfigFiles(cfg.BindMount, cfg.Environment), nil
 }
 
 var varRE = regexp.MustCompile(`\{\{\s*([A-Z0-9_]+)\s*\}\}`)
@@ -53,43 +111,118 @@ func substituteVars(in map[string]string, env map[string]string) map[string]stri
 	return out
 }
 
-func patchPropertiesFile(path string, patches map[string]string) error {
+// patchConfigFile sets each key of patches in the format file at path.
+// Substituted values are strings; over a number or boolean that reads
+// as one they keep the key's type (see patchValue), and keys the file
+// doesn't have yet are written as strings.
+func patchConfigFile(path, format string, patches map[string]string) ([]ConfigChange, error) {
 	if len(patches) == 0 {
-		return nil
+		return []ConfigChange{}, nil
 	}
 	if err := os.MkdirAll(filepath.Dir(path), 0o755); err != nil {
-		return err
+		return nil, err
 	}
 	body, err := os.ReadFile(path)
 	if err != nil && !os.IsNotExist(err) {
-		return err
+		return nil, err
 	}
-	lines := strings.Split(string(body), "\n")
-	seen := map[string]bool{}
-	for i, raw := range lines {
-		trimmed := strings.TrimSpace(raw)
-		if trimmed == "" || strings.HasPrefix(trimmed, "#") {
-			continue
-		}
-		eq := strings.IndexAny(raw, "=:")
-		if eq < 0 {
+	entries, err := configfile.Read(format, body)
+	if err != nil {
+		return nil, err
+	}
+	current := make(map[string]any, len(entries))
+	for _, e := range entries {
+		current[e.Key] = e.Value
+	}
+	changes := []ConfigChange{}
+	set := map[string]any{}
+	for k, v := range patches {
+		old, ok := current[k]
+		if ok && fmt.Sprint(old) == v {
 			continue
 		}
-		key := strings.TrimSpace(raw[:eq])
-		if val, ok := patches[key]; ok {
-			lines[i] = key + "=" + val
-			seen[key] = true
+		change := ConfigChange{Key: k, New: v}
+		if ok {
+			change.Old = fmt.Sprint(old)
 		}
+		changes = append(changes, change)
+		set[k] = patchValue(old, v)
 	}
-	for k, v := range patches {
-		if seen[k] {
-			continue
+	if len(changes) == 0 {
+		return changes, nil
+	}
+	sort.Slice(changes, func(i, j int) bool { return changes[i].Key < changes[j].Key })
+	rendered, err := configfile.Patch(format, body, set)
+	if err != nil {
+		return nil, fmt.Errorf("patched file is invalid, kept the current one: %w", err)
+	}
+	err = writeConfigFile(path, body, rendered, func(written []byte) error {
+		return configfile.Check(format, written, patches)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return changes, nil
+}
+
+// patchValue is v typed like old, the value it replaces: a number over
+// a number, a boolean over a boolean, when v reads as one. Anything
+// else, properties values included, stays a string.
+func patchValue(old any, v string) any {
+	switch old.(type) {
+	case json.Number, int, int64, uint64, float64:
+		if _, err := strconv.ParseFloat(v, 64); err == nil {
+			return json.Number(v)
+		}
+	case bool:
+		if v == "true" || v == "false" {
+			return v == "true"
 		}
-		lines = append(lines, k+"="+v)
 	}
-	out := strings.Join(lines, "\n")
-	if !strings.HasSuffix(out, "\n") {
-		out += "\n"
+	return v
+}
+
+// writeConfigFile replaces path with rendered in two phases: write it to
+// a temp file in the same directory, run validate over what was
+// written, and only then rename it over path. The previous contents
+// (prev, nil when the file is new) are kept as path+".prev" so a bad
+// patch can be undone by hand. On a validation failure the temp file is
+// removed and path is untouched.
+func writeConfigFile(path string, prev, rendered []byte, validate func([]byte) error) error {
+	mode := os.FileMode(0o644)
+	if info, err := os.Stat(path); err == nil {
+		mode = info.Mode().Perm()
+	}
+	tmp, err := os.CreateTemp(filepath.Dir(path), "."+filepath.Base(path)+".*.tmp")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+	if _, err := tmp.Write(rendered); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Sync(); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	written, err := os.ReadFile(tmp.Name())
+	if err != nil {
+		return err
+	}
+	if err := validate(written); err != nil {
+		return fmt.Errorf("patched file is invalid, kept the current one: %w", err)
+	}
+	if err := os.Chmod(tmp.Name(), mode); err != nil {
+		return err
+	}
+	if prev != nil {
+		if err := os.WriteFile(path+".prev", prev, mode); err != nil {
+			return err
+		}
 	}
-	return os.WriteFile(path, []byte(out), 0o644)
+	return os.Rename(tmp.Name(), path)
 }
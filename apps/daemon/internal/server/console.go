@@ -2,7 +2,6 @@ package server
 
 import (
 	"context"
-	"encoding/json"
 	"io"
 	"log"
 	"regexp"
@@ -10,6 +9,8 @@ import (
 	"sync"
 
 	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/events"
+	"github.com/stellarstack/daemon/internal/logging"
 )
 
 // consoleHistory is the bounded ring buffer of recent stdout/stderr
@@ -28,6 +29,50 @@ func newConsoleHistory(max int) *consoleHistory {
 	return &consoleHistory{max: max}
 }
 
+// ConsoleDefaults are the node's console sizes (config history_lines
+// and console_buffer), used where the panel sets none for a server.
+type ConsoleDefaults struct {
+	HistoryLines int
+	Buffer       int
+}
+
+// applyConsoleConfig resizes the history ring and subscriber buffer to
+// c's values, or back to the node defaults where c has none. Clients
+// already connected keep the buffer they subscribed with.
+func (s *Server) applyConsoleConfig(c Config) {
+	lines, buffer := c.ConsoleHistoryLines, c.ConsoleBuffer
+	if lines <= 0 {
+		lines = s.console.HistoryLines
+	}
+	if buffer <= 0 {
+		buffer = s.console.Buffer
+	}
+	s.history.resize(lines)
+	s.bus.SetBuffer(min(buffer, maxConsoleBuffer))
+}
+
+// maxConsoleBuffer caps a panel-set subscriber buffer.
+const maxConsoleBuffer = 4096
+
+// maxHistoryLines caps a panel-set history depth; the ring is held in
+// memory for every server on the node.
+const maxHistoryLines = 20000
+
+// resize changes the ring's depth, dropping the oldest lines if it
+// shrinks. max <= 0 leaves it alone.
+func (h *consoleHistory) resize(max int) {
+	if max <= 0 {
+		return
+	}
+	max = min(max, maxHistoryLines)
+	h.mu.Lock()
+	defer h.mu.Unlock()
+	h.max = max
+	if over := len(h.lines) - max; over > 0 {
+		h.lines = h.lines[over:]
+	}
+}
+
 func (h *consoleHistory) push(line string) {
 	h.mu.Lock()
 	defer h.mu.Unlock()
@@ -127,7 +172,7 @@ func (s *Server) runAttachStream(ctx context.Context, reader io.Reader, tty bool
 		}
 	}()
 	defer closer()
-	log.Printf("server %s: attach stream start (tty=%v)", s.uuid, tty)
+	logging.Debugf("server", "server %s: attach stream start (tty=%v)", s.uuid, tty)
 	out := make(chan docker.LogLine, 64)
 	go func() {
 		defer close(out)
@@ -144,15 +189,12 @@ func (s *Server) runAttachStream(ctx context.Context, reader io.Reader, tty bool
 			continue
 		}
 		count++
+		s.noteConsoleOutput()
 		s.history.push(cleaned)
-		frame, _ := json.Marshal(map[string]any{
-			"event": "console output",
-			"args":  []any{cleaned},
-		})
-		s.bus.Publish(frame)
+		s.bus.Publish(events.Encode("console output", []any{cleaned}))
 		s.scanLineForErrors(cleaned)
 	}
-	log.Printf("server %s: attach stream end (lines=%d ctx=%v)", s.uuid, count, ctx.Err())
+	logging.Debugf("server", "server %s: attach stream end (lines=%d ctx=%v)", s.uuid, count, ctx.Err())
 }
 
 func (s *Server) stopAttachPump() {
@@ -201,11 +243,7 @@ func (s *Server) SnapshotLogs(ctx context.Context, tail int) {
 			continue
 		}
 		s.history.push(cleaned)
-		frame, _ := json.Marshal(map[string]any{
-			"event": "console output",
-			"args":  []any{cleaned},
-		})
-		s.bus.Publish(frame)
+		s.bus.Publish(events.Encode("console output", []any{cleaned}))
 		s.scanLineForErrors(cleaned)
 	}
 }
@@ -218,8 +256,8 @@ func (s *Server) runAttachPump(ctx context.Context) {
 	}()
 	dc := s.env.Docker()
 	containerName := s.env.ContainerName()
-	log.Printf("server %s: attach pump start", s.uuid)
-	defer log.Printf("server %s: attach pump exit (ctx err=%v)", s.uuid, ctx.Err())
+	logging.Debugf("server", "server %s: attach pump start", s.uuid)
+	defer logging.Debugf("server", "server %s: attach pump exit (ctx err=%v)", s.uuid, ctx.Err())
 	lineCount := 0
 	for {
 		if ctx.Err() != nil {
@@ -239,15 +277,12 @@ func (s *Server) runAttachPump(ctx context.Context) {
 				continue
 			}
 			lineCount++
+			s.noteConsoleOutput()
 			s.history.push(cleaned)
-			frame, _ := json.Marshal(map[string]any{
-				"event": "console output",
-				"args":  []any{cleaned},
-			})
-			s.bus.Publish(frame)
+			s.bus.Publish(events.Encode("console output", []any{cleaned}))
 			s.scanLineForErrors(cleaned)
 		}
-		log.Printf("server %s: log stream closed (read %d lines)", s.uuid, lineCount)
+		logging.Debugf("server", "server %s: log stream closed (read %d lines)", s.uuid, lineCount)
 		if !sleepOrDone(ctx, 1) {
 			return
 		}
@@ -0,0 +1,139 @@
+package server
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"slices"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// Countdown warns players before a restart: Message is sent to the
+// console at each of Warnings (seconds before the restart) within the
+// total Seconds, with {{time}} replaced by "5 minutes", "10 seconds"
+// and so on.
+type Countdown struct {
+	Seconds  int    `json:"seconds"`
+	Message  string `json:"message"`
+	Warnings []int  `json:"warnings"`
+}
+
+// Countdown limits and defaults.
+const (
+	maxCountdownSeconds   = 3600
+	defaultCountdownMsg   = "say Server restarting in {{time}}"
+	maxCountdownMsgLength = 256
+)
+
+var defaultCountdownWarnings = []int{1800, 900, 600, 300, 120, 60, 30, 10, 5, 4, 3, 2, 1}
+
+// ErrCountdownAborted: the server stopped, or the countdown was
+// cancelled, before it reached zero. The restart is skipped.
+var ErrCountdownAborted = errors.New("restart countdown aborted")
+
+// Normalize validates c and fills in the default message and warnings.
+func (c Countdown) Normalize() (Countdown, error) {
+	if c.Seconds <= 0 || c.Seconds > maxCountdownSeconds {
+		return c, fmt.Errorf("countdown seconds must be 1–%d", maxCountdownSeconds)
+	}
+	if c.Message == "" {
+		c.Message = defaultCountdownMsg
+	}
+	if len(c.Message) > maxCountdownMsgLength || strings.ContainsAny(c.Message, "\r\n") {
+		return c, errors.New("countdown message must be a single line of at most 256 characters")
+	}
+	warnings := c.Warnings
+	if len(warnings) == 0 {
+		warnings = defaultCountdownWarnings
+	}
+	// Always announce the full duration first.
+	out := []int{c.Seconds}
+	for _, w := range warnings {
+		if w > 0 && w < c.Seconds && !slices.Contains(out, w) {
+			out = append(out, w)
+		}
+	}
+	slices.Sort(out)
+	slices.Reverse(out)
+	c.Warnings = out
+	return c, nil
+}
+
+// RunCountdown sends the countdown's warnings and returns when it
+// reaches zero. It aborts with ErrCountdownAborted if the server stops
+// running or CancelCountdown is called. c must be normalized.
+func (s *Server) RunCountdown(ctx context.Context, c Countdown) error {
+	ctx, cancel := context.WithCancel(ctx)
+	s.countdownMu.Lock()
+	s.countdownCancel = cancel
+	s.countdownMu.Unlock()
+	defer func() {
+		s.countdownMu.Lock()
+		s.countdownCancel = nil
+		s.countdownMu.Unlock()
+		cancel()
+	}()
+
+	s.publishDaemon(fmt.Sprintf("Restarting in %s.", humanDuration(c.Seconds)))
+	end := time.Now().Add(time.Duration(c.Seconds) * time.Second)
+	for _, w := range c.Warnings {
+		at := end.Add(-time.Duration(w) * time.Second)
+		if err := s.waitRunning(ctx, time.Until(at)); err != nil {
+			return err
+		}
+		line := strings.ReplaceAll(c.Message, "{{time}}", humanDuration(w))
+		sendCtx, sendCancel := context.WithTimeout(ctx, 5*time.Second)
+		_ = s.env.SendCommand(sendCtx, line)
+		sendCancel()
+	}
+	return s.waitRunning(ctx, time.Until(end))
+}
+
+// CancelCountdown stops a countdown in progress. False if none is.
+func (s *Server) CancelCountdown() bool {
+	s.countdownMu.Lock()
+	defer s.countdownMu.Unlock()
+	if s.countdownCancel == nil {
+		return false
+	}
+	s.countdownCancel()
+	s.publishDaemon("Restart cancelled.")
+	return true
+}
+
+// waitRunning sleeps for d, failing early if the server leaves the
+// running state or ctx ends.
+func (s *Server) waitRunning(ctx context.Context, d time.Duration) error {
+	timer := time.NewTimer(max(d, 0))
+	defer timer.Stop()
+	tick := time.NewTicker(time.Second)
+	defer tick.Stop()
+	for {
+		if s.env.State() != environment.StateRunning {
+			return ErrCountdownAborted
+		}
+		select {
+		case <-ctx.Done():
+			return ErrCountdownAborted
+		case <-timer.C:
+			return nil
+		case <-tick.C:
+		}
+	}
+}
+
+// humanDuration renders whole seconds as "2 minutes", "90 seconds" or
+// "1 second" — minutes only when they're exact.
+func humanDuration(sec int) string {
+	unit, n := "second", sec
+	if sec >= 60 && sec%60 == 0 {
+		unit, n = "minute", sec/60
+	}
+	if n != 1 {
+		unit += "s"
+	}
+	return fmt.Sprintf("%d %s", n, unit)
+}
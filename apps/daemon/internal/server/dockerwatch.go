@@ -0,0 +1,148 @@
+package server
+
+import (
+	"context"
+	"errors"
+	"log"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// dockerRetry is how often WatchDocker tries to reopen the event
+// stream while Docker is down.
+const dockerRetry = 2 * time.Second
+
+// WatchDocker follows Docker's event stream until ctx ends, applying
+// container events as handleDockerEvent describes, and treats the
+// stream breaking as Docker going away. While it's gone every
+// server that isn't offline is flagged "unknown" in State and told so
+// on its console. Once the stream reopens the manager reconciles
+// against the container list again, re-arms the log, stats and exit
+// watchers Docker's restart cut off, and clears the flags. onChange
+// hears each transition (false when lost, true when back), so the
+// node can report it. A server that was still starting when Docker
+// went away comes back as running if its container is up.
+func (m *Manager) WatchDocker(ctx context.Context, onChange func(up bool)) {
+	up := true
+	for ctx.Err() == nil {
+		streamCtx, cancel := context.WithCancel(ctx)
+		stream, err := m.docker.Events(streamCtx)
+		if err == nil && !up {
+			// Opening the stream again is the first sign of Docker
+			// coming back; it isn't back until the reconcile works.
+			if err = m.dockerBack(ctx); err == nil {
+				up = true
+				onChange(true)
+			}
+		}
+		if err == nil {
+			for ev := range stream {
+				m.handleDockerEvent(ev)
+			}
+		}
+		cancel()
+		if ctx.Err() != nil {
+			return
+		}
+		if up {
+			up = false
+			if err == nil {
+				err = errors.New("event stream closed")
+			}
+			log.Printf("manager: lost connection to docker: %v", err)
+			m.dockerLost()
+			onChange(false)
+		}
+		select {
+		case <-ctx.Done():
+			return
+		case <-time.After(dockerRetry):
+		}
+	}
+}
+
+func (m *Manager) dockerLost() {
+	for _, s := range m.All() {
+		if s.env.State() == environment.StateOffline {
+			continue
+		}
+		s.dockerLost.Store(true)
+		s.publishDaemon("Lost connection to Docker; server state unknown until it's back.")
+	}
+	m.NotifyStateChanged()
+}
+
+func (m *Manager) dockerBack(ctx context.Context) error {
+	log.Printf("manager: docker is back; reconciling")
+	seen, err := m.reconcile(ctx)
+	if err != nil {
+		return err
+	}
+	for _, s := range m.All() {
+		if !s.dockerLost.Swap(false) {
+			continue
+		}
+		if !seen[s.uuid] {
+			// Removed while Docker was down.
+			s.env.MarkOffline()
+		}
+		if s.env.State() == environment.StateRunning {
+			// Reconcile's running transition restarted the stats pump
+			// and any attach pump that had ended; the exit watcher
+			// needs re-arming by hand.
+			if s.exitWatchers.Load() == 0 {
+				go s.watchExit()
+			}
+		}
+		s.publishDaemon("Reconnected to Docker.")
+	}
+	m.NotifyStateChanged()
+	return nil
+}
+
+// handleDockerEvent applies a server container's lifecycle event, so
+// the state stays right when someone runs docker start, stop, kill or
+// pause behind the daemon's back. The daemon's own starts and stops
+// pass through starting and stopping, which the events leave alone;
+// only a change the daemon didn't make moves the state. Stops and kills
+// arrive as die, which goes through the same exit handling as
+// watchExit; pause only sets the "paused" flag.
+func (m *Manager) handleDockerEvent(ev docker.Event) {
+	if ev.Type != "container" {
+		return
+	}
+	uuid, ok := strings.CutPrefix(ev.Name, "stellar-")
+	if !ok || uuid == "" || strings.HasPrefix(uuid, "install-") || strings.HasPrefix(uuid, "sidecar-") || strings.HasPrefix(uuid, "service-") {
+		return
+	}
+	m.mu.RLock()
+	s := m.servers[uuid]
+	m.mu.RUnlock()
+	if s == nil {
+		return
+	}
+	switch ev.Action {
+	case "start":
+		if s.env.State() != environment.StateOffline {
+			return
+		}
+		log.Printf("server %s: container started outside the daemon", s.uuid)
+		s.publishDaemon("Container was started outside the panel.")
+		s.env.ForceState(environment.StateRunning)
+		if s.exitWatchers.Load() == 0 {
+			go s.watchExit()
+		}
+	case "die":
+		s.setPaused(false)
+		go s.containerExited()
+	case "oom":
+		s.publishDaemon("Container ran out of memory.")
+	case "pause":
+		s.setPaused(true)
+	case "unpause":
+		s.setPaused(false)
+	}
+}
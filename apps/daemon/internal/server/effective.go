@@ -0,0 +1,182 @@
+package server
+
+import (
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// containerPidsLimit is the PIDs cgroup limit of every main container.
+const containerPidsLimit = 256
+
+// EffectiveConfig is what the next start hands Docker for this server,
+// resolved the same way doStart resolves it: memory overhead applied,
+// environment flattened, {{VAR}} placeholders substituted, network mode
+// and port bindings decided. It answers "the panel says X but the
+// container does Y" without reading the code.
+type EffectiveConfig struct {
+	Container   string               `json:"container"`
+	Image       string               `json:"image"`
+	Startup     string               `json:"startup"`
+	Environment map[string]string    `json:"environment"`
+	Limits      EffectiveLimits      `json:"limits"`
+	NetworkMode string               `json:"networkMode"`
+	Ports       []docker.PortMapping `json:"ports"`
+	Mounts      []EffectiveMount     `json:"mounts"`
+	Stop        EffectiveStop        `json:"stop"`
+	StartupDone []string             `json:"startupDone"`
+	ConfigFiles []ConfigFilePatch    `json:"configFiles"`
+	Sidecars    []EffectiveExtra     `json:"sidecars"`
+	Services    []EffectiveExtra     `json:"services"`
+	ExtraHosts  []string             `json:"extraHosts"`
+	// Problems are the refusals doStart would hit before Docker is
+	// asked for anything (host network not allowlisted, no image).
+	Problems []string `json:"problems"`
+}
+
+// EffectiveLimits are the cgroup limits of the main container.
+type EffectiveLimits struct {
+	MemoryMb          int64 `json:"memoryMb"`
+	MemoryOverheadMb  int64 `json:"memoryOverheadMb"`
+	ContainerMemoryMb int64 `json:"containerMemoryMb"`
+	TotalMemoryMb     int64 `json:"totalMemoryMb"`
+	CPUPercent        int64 `json:"cpuPercent"`
+	PidsLimit         int64 `json:"pidsLimit"`
+}
+
+// EffectiveMount is one bind or named-volume mount.
+type EffectiveMount struct {
+	Source string `json:"source"`
+	Target string `json:"target"`
+	Volume bool   `json:"volume,omitempty"`
+}
+
+// EffectiveStop is the stop sequence with its defaults applied.
+type EffectiveStop struct {
+	Type           string `json:"type"`
+	Value          string `json:"value"`
+	StopSignal     string `json:"stopSignal,omitempty"`
+	TimeoutSeconds int64  `json:"timeoutSeconds"`
+}
+
+// EffectiveExtra summarises a sidecar or service container.
+type EffectiveExtra struct {
+	Name        string            `json:"name"`
+	Container   string            `json:"container"`
+	Image       string            `json:"image"`
+	Cmd         []string          `json:"cmd,omitempty"`
+	Environment map[string]string `json:"environment"`
+	MemoryMb    int64             `json:"memoryMb"`
+	DependsOn   []string          `json:"dependsOn,omitempty"`
+	Mounts      []EffectiveMount  `json:"mounts,omitempty"`
+}
+
+// containerNetwork decides the main container's network mode, aliases
+// and published ports. Shared with doStart so the effective view can't
+// drift from what actually runs.
+func (s *Server) containerNetwork(cfg Config) (mode string, aliases []string, ports []docker.PortMapping) {
+	ports = cfg.PortMappings
+	if len(cfg.Services) > 0 {
+		mode = s.networkName()
+		aliases = []string{mainServiceAlias}
+	}
+	if cfg.HostNetwork {
+		// The game binds its allocation ports on the host directly.
+		mode = "host"
+		ports = nil
+	}
+	return mode, aliases, ports
+}
+
+// stopSignal is the container's StopSignal: set only for blueprints
+// that stop by signal rather than console command.
+func stopSignal(cfg Config) string {
+	if cfg.Stop.Type == "signal" {
+		return cfg.Stop.Value
+	}
+	return ""
+}
+
+// Effective resolves the currently installed config.
+func (s *Server) Effective() EffectiveConfig {
+	cfg := s.Config()
+	env := flattenEnv(cfg.Environment, cfg.StartupCommand, cfg.Memory)
+	mode, _, ports := s.containerNetwork(cfg)
+	if mode == "" {
+		mode = "bridge"
+	}
+	if ports == nil {
+		ports = []docker.PortMapping{}
+	}
+	out := EffectiveConfig{
+		Container:   s.env.ContainerName(),
+		Image:       cfg.DockerImage,
+		Startup:     substituteVars(map[string]string{"": cfg.StartupCommand}, env)[""],
+		Environment: env,
+		Limits: EffectiveLimits{
+			MemoryMb:          cfg.Memory,
+			MemoryOverheadMb:  cfg.MemoryOverhead,
+			ContainerMemoryMb: cfg.ContainerMemory(),
+			TotalMemoryMb:     cfg.TotalMemory(),
+			CPUPercent:        cfg.CPUPercent,
+			PidsLimit:         containerPidsLimit,
+		},
+		NetworkMode: mode,
+		Ports:       ports,
+		Mounts:      []EffectiveMount{},
+		Stop: EffectiveStop{
+			Type:           cfg.Stop.Type,
+			Value:          cfg.Stop.Value,
+			StopSignal:     stopSignal(cfg),
+			TimeoutSeconds: int64(cfg.Stop.Grace().Seconds()),
+		},
+		StartupDone: make([]string, 0, len(cfg.StartupDone)),
+		ConfigFiles: make([]ConfigFilePatch, 0, len(cfg.ConfigFiles)),
+		Sidecars:    make([]EffectiveExtra, 0, len(cfg.Sidecars)),
+		Services:    make([]EffectiveExtra, 0, len(cfg.Services)),
+		ExtraHosts:  append([]string{}, cfg.ExtraHosts...),
+		Problems:    []string{},
+	}
+	if cfg.BindMount != "" {
+		out.Mounts = append(out.Mounts, EffectiveMount{Source: cfg.BindMount, Target: "/home/container"})
+	}
+	for _, re := range cfg.StartupDone {
+		out.StartupDone = append(out.StartupDone, re.String())
+	}
+	for _, f := range cfg.ConfigFiles {
+		out.ConfigFiles = append(out.ConfigFiles, ConfigFilePatch{Path: f.Path, Parser: f.Parser, Patches: substituteVars(f.Patches, cfg.Environment)})
+	}
+	for _, sc := range cfg.Sidecars {
+		out.Sidecars = append(out.Sidecars, EffectiveExtra{
+			Name:        sc.Name,
+			Container:   s.sidecarPrefix() + sc.Name,
+			Image:       sc.Image,
+			Cmd:         sc.Cmd,
+			Environment: sc.Environment,
+			MemoryMb:    sc.Memory,
+		})
+	}
+	for _, svc := range cfg.Services {
+		mounts := make([]EffectiveMount, 0, len(svc.Volumes))
+		for _, v := range svc.Volumes {
+			mounts = append(mounts, EffectiveMount{Source: s.volumeName(v.Name), Target: v.Path, Volume: true})
+		}
+		out.Services = append(out.Services, EffectiveExtra{
+			Name:        svc.Name,
+			Container:   s.servicePrefix() + svc.Name,
+			Image:       svc.Image,
+			Cmd:         svc.Cmd,
+			Environment: svc.Environment,
+			MemoryMb:    svc.Memory,
+			DependsOn:   svc.DependsOn,
+			Mounts:      mounts,
+		})
+	}
+	if cfg.DockerImage == "" {
+		out.Problems = append(out.Problems, "no docker image configured")
+	}
+	if cfg.HostNetwork {
+		if err := s.checkHostNetwork(cfg); err != nil {
+			out.Problems = append(out.Problems, err.Error())
+		}
+	}
+	return out
+}
@@ -0,0 +1,205 @@
+package server
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"regexp"
+	"sort"
+	"strings"
+
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// Environment overrides are variable values the panel pushed to the
+// daemon directly (the environment API) rather than through the config
+// pull. They live in `<dataDir>/environment/<serverId>.json` and are
+// laid over every pulled config, so a value the user just saved is
+// used by the next start even when the panel's config still lags, and
+// survives a daemon restart. An override retires once the pulled config
+// carries the same value.
+
+// envKeyRE is a POSIX environment variable name.
+var envKeyRE = regexp.MustCompile(`^[A-Za-z_][A-Za-z0-9_]{0,127}$`)
+
+// maxEnvValue caps one override's value.
+const maxEnvValue = 4096
+
+// ErrBadEnvironment is returned for an override Docker can't carry.
+var ErrBadEnvironment = errors.New("invalid environment variable")
+
+// SetEnvOverrideDir installs the directory overrides persist in. Call
+// before servers load; without one overrides last until the daemon
+// restarts.
+func (m *Manager) SetEnvOverrideDir(dir string) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.envDir = dir
+}
+
+func (m *Manager) envOverridePath(uuid string) string {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	if m.envDir == "" {
+		return ""
+	}
+	return filepath.Join(m.envDir, uuid+".json")
+}
+
+// EnvOverrides returns the server's overrides.
+func (s *Server) EnvOverrides() map[string]string {
+	s.envMu.Lock()
+	defer s.envMu.Unlock()
+	cur := s.loadEnvOverrides()
+	out := make(map[string]string, len(cur))
+	for k, v := range cur {
+		out[k] = v
+	}
+	return out
+}
+
+// SetEnvOverrides validates set, persists it over the existing
+// overrides and applies it to the installed config, so it holds even
+// if the config pull that follows fails.
+func (s *Server) SetEnvOverrides(set map[string]string) error {
+	for k, v := range set {
+		if !envKeyRE.MatchString(k) {
+			return fmt.Errorf("%w: name %q", ErrBadEnvironment, k)
+		}
+		if len(v) > maxEnvValue || strings.ContainsRune(v, 0) {
+			return fmt.Errorf("%w: value of %s", ErrBadEnvironment, k)
+		}
+	}
+	s.envMu.Lock()
+	defer s.envMu.Unlock()
+	merged := s.loadEnvOverrides()
+	for k, v := range set {
+		merged[k] = v
+	}
+	if err := s.saveEnvOverrides(merged); err != nil {
+		return err
+	}
+	s.cfgMu.Lock()
+	env := make(map[string]string, len(s.cfg.Environment)+len(set))
+	for k, v := range s.cfg.Environment {
+		env[k] = v
+	}
+	for k, v := range set {
+		env[k] = v
+	}
+	s.cfg.Environment = env
+	s.cfgMu.Unlock()
+	return nil
+}
+
+// MergeEnvOverrides lays the overrides over env, a freshly pulled
+// config's environment, and retires those env already agrees with.
+func (s *Server) MergeEnvOverrides(env map[string]string) map[string]string {
+	s.envMu.Lock()
+	defer s.envMu.Unlock()
+	overrides := s.loadEnvOverrides()
+	if len(overrides) == 0 {
+		return env
+	}
+	out := make(map[string]string, len(env)+len(overrides))
+	for k, v := range env {
+		out[k] = v
+	}
+	kept := map[string]string{}
+	for k, v := range overrides {
+		if cur, ok := env[k]; ok && cur == v {
+			continue
+		}
+		kept[k] = v
+		out[k] = v
+	}
+	if len(kept) != len(overrides) {
+		if err := s.saveEnvOverrides(kept); err != nil {
+			s.publishDaemon("Failed to save environment overrides: " + err.Error())
+		}
+	}
+	return out
+}
+
+// PendingEnv lists the installed variables the running container was
+// created with a different value of, sorted; they take effect on the
+// next restart. Nil when the server isn't running.
+func (s *Server) PendingEnv(ctx context.Context) ([]string, error) {
+	st, err := s.env.Docker().Inspect(ctx, s.env.ContainerName())
+	var missing *docker.ContainerNotFoundError
+	if errors.As(err, &missing) {
+		return nil, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	if !st.Running {
+		return nil, nil
+	}
+	pending := []string{}
+	for k, v := range s.Config().Environment {
+		if cur, ok := st.Env[k]; !ok || cur != v {
+			pending = append(pending, k)
+		}
+	}
+	sort.Strings(pending)
+	return pending, nil
+}
+
+// loadEnvOverrides returns the cached overrides, reading the file on
+// first use. Caller holds s.envMu.
+func (s *Server) loadEnvOverrides() map[string]string {
+	if s.envOverrides != nil {
+		return s.envOverrides
+	}
+	s.envOverrides = map[string]string{}
+	if s.manager == nil {
+		return s.envOverrides
+	}
+	path := s.manager.envOverridePath(s.uuid)
+	if path == "" {
+		return s.envOverrides
+	}
+	raw, err := os.ReadFile(path)
+	if err != nil {
+		return s.envOverrides
+	}
+	if err := json.Unmarshal(raw, &s.envOverrides); err != nil {
+		s.envOverrides = map[string]string{}
+	}
+	return s.envOverrides
+}
+
+// saveEnvOverrides writes set atomically (temp file + rename) and
+// caches it; an empty set removes the file. Caller holds s.envMu.
+func (s *Server) saveEnvOverrides(set map[string]string) error {
+	s.envOverrides = set
+	if s.manager == nil {
+		return nil
+	}
+	path := s.manager.envOverridePath(s.uuid)
+	if path == "" {
+		return nil
+	}
+	if len(set) == 0 {
+		if err := os.Remove(path); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+		return nil
+	}
+	if err := os.MkdirAll(filepath.Dir(path), 0o700); err != nil {
+		return err
+	}
+	raw, err := json.Marshal(set)
+	if err != nil {
+		return err
+	}
+	tmp := path + ".tmp"
+	if err := os.WriteFile(tmp, raw, 0o600); err != nil {
+		return err
+	}
+	return os.Rename(tmp, path)
+}
@@ -0,0 +1,256 @@
+package server
+
+import (
+	"bufio"
+	"context"
+	"errors"
+	"fmt"
+	"log"
+	"os"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+)
+
+// IdlePolicy stops a running server nobody is using. Each enabled
+// signal tracks when it last saw activity; once every one of them has
+// been quiet for Minutes the daemon queues a stop and tells the panel.
+// A policy with no signals, or Minutes <= 0, is off.
+type IdlePolicy struct {
+	Minutes int
+	// Players: no established TCP connections on the server's
+	// allocation ports.
+	Players bool
+	// Console: no output from the container.
+	Console bool
+	// CPUPercent: cpu_absolute stays below this; 0 disables the signal.
+	CPUPercent float64
+
+	// StartOnDemand lets a connection to a stopped server start it
+	// again; see wake.go.
+	StartOnDemand bool
+	// WakeMessage, when set, is the MOTD Minecraft clients see while
+	// the server sleeps.
+	WakeMessage string
+}
+
+func (p IdlePolicy) enabled() bool {
+	return p.Minutes > 0 && (p.Players || p.Console || p.CPUPercent > 0)
+}
+
+// signals names the enabled signals for the audit entry.
+func (p IdlePolicy) signals() []string {
+	var out []string
+	if p.Players {
+		out = append(out, "players")
+	}
+	if p.Console {
+		out = append(out, "console")
+	}
+	if p.CPUPercent > 0 {
+		out = append(out, "cpu")
+	}
+	return out
+}
+
+// idleCheckInterval is how often the monitor samples players and
+// compares the signals against the policy.
+const idleCheckInterval = 30 * time.Second
+
+// idleTracker holds the last activity seen per signal. Console and CPU
+// are touched from the attach and stats pumps; players from the
+// monitor itself.
+type idleTracker struct {
+	mu      sync.Mutex
+	console time.Time
+	cpu     time.Time
+	players time.Time
+}
+
+func (t *idleTracker) reset(now time.Time) {
+	t.mu.Lock()
+	t.console, t.cpu, t.players = now, now, now
+	t.mu.Unlock()
+}
+
+func (t *idleTracker) touch(field *time.Time) {
+	t.mu.Lock()
+	*field = time.Now()
+	t.mu.Unlock()
+}
+
+// lastActive is the most recent activity across p's signals.
+func (t *idleTracker) lastActive(p IdlePolicy) time.Time {
+	t.mu.Lock()
+	defer t.mu.Unlock()
+	var last time.Time
+	consider := func(on bool, at time.Time) {
+		if on && at.After(last) {
+			last = at
+		}
+	}
+	consider(p.Players, t.players)
+	consider(p.Console, t.console)
+	consider(p.CPUPercent > 0, t.cpu)
+	return last
+}
+
+// noteConsoleOutput records container output for the console signal.
+func (s *Server) noteConsoleOutput() { s.idle.touch(&s.idle.console) }
+
+// noteCPU records a stats sample for the CPU signal.
+func (s *Server) noteCPU(cpuAbsolute float64) {
+	if threshold := s.Config().Idle.CPUPercent; threshold > 0 && cpuAbsolute >= threshold {
+		s.idle.touch(&s.idle.cpu)
+	}
+}
+
+// startIdleMonitor runs the idle check while the server is running.
+// Idempotent; a policy that's off just leaves the monitor sampling
+// nothing until the next config sync turns it on.
+func (s *Server) startIdleMonitor() {
+	s.idleMu.Lock()
+	defer s.idleMu.Unlock()
+	if s.idleCancel != nil {
+		s.idleCancel()
+	}
+	ctx, cancel := context.WithCancel(context.Background())
+	s.idleCancel = cancel
+	s.idle.reset(time.Now())
+	go s.runIdleMonitor(ctx)
+}
+
+func (s *Server) stopIdleMonitor() {
+	s.idleMu.Lock()
+	defer s.idleMu.Unlock()
+	if s.idleCancel != nil {
+		s.idleCancel()
+		s.idleCancel = nil
+	}
+}
+
+func (s *Server) runIdleMonitor(ctx context.Context) {
+	defer func() {
+		if r := recover(); r != nil {
+			log.Printf("server %s: idle monitor panic: %v", s.uuid, r)
+		}
+	}()
+	tick := time.NewTicker(idleCheckInterval)
+	defer tick.Stop()
+	for {
+		select {
+		case <-ctx.Done():
+			return
+		case <-tick.C:
+		}
+		p := s.Config().Idle
+		if !p.enabled() {
+			// Restart the clock so turning the policy on mid-run
+			// doesn't stop a server that was merely unwatched.
+			s.idle.reset(time.Now())
+			continue
+		}
+		if p.Players {
+			// Unreadable counts as busy: a server whose connections
+			// can't be seen is never taken for empty.
+			if n, err := s.playerConnections(ctx); err != nil || n > 0 {
+				if err != nil {
+					log.Printf("server %s: idle: count connections: %v", s.uuid, err)
+				}
+				s.idle.touch(&s.idle.players)
+			}
+		}
+		quiet := time.Since(s.idle.lastActive(p))
+		if quiet < time.Duration(p.Minutes)*time.Minute {
+			continue
+		}
+		s.idleShutdown(p)
+		return
+	}
+}
+
+// idleShutdown queues the stop and records it on the panel.
+func (s *Server) idleShutdown(p IdlePolicy) {
+	_, err := s.EnqueuePower(PowerStop, nil)
+	if err != nil && !errors.Is(err, ErrPowerInProgress) {
+		log.Printf("server %s: idle: queue stop: %v", s.uuid, err)
+		return
+	}
+	s.publishDaemon(fmt.Sprintf("Server idle for %s; stopping.", humanDuration(p.Minutes*60)))
+	log.Printf("server %s: idle for %d minute(s) (%s); stopping", s.uuid, p.Minutes, strings.Join(p.signals(), ", "))
+	if s.panel == nil {
+		return
+	}
+	go func() {
+		ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		defer cancel()
+		_ = s.panel.PushAudit(ctx, s.uuid, "", "servers.lifecycle.idle_shutdown", map[string]any{
+			"minutes":       p.Minutes,
+			"signals":       p.signals(),
+			"startOnDemand": p.StartOnDemand,
+		})
+	}()
+}
+
+// playerConnections counts ESTABLISHED TCP connections to the server's
+// container ports, read from the container's network namespace via
+// /proc/<pid>/net/tcp{,6}. With host networking that's the host's
+// table, which still only matches the server's own ports.
+func (s *Server) playerConnections(ctx context.Context) (int, error) {
+	ports := map[int]bool{}
+	for _, pm := range s.Config().PortMappings {
+		if pm.Protocol == "udp" {
+			continue
+		}
+		for i := 0; i < pm.Span(); i++ {
+			ports[pm.ContainerPort+i] = true
+		}
+	}
+	if len(ports) == 0 {
+		return 0, nil
+	}
+	st, err := s.env.Docker().Inspect(ctx, s.env.ContainerName())
+	if err != nil {
+		return 0, err
+	}
+	if st.Pid == 0 {
+		return 0, errors.New("container has no process")
+	}
+	n := 0
+	for _, table := range []string{"tcp", "tcp6"} {
+		c, err := establishedOn(fmt.Sprintf("/proc/%d/net/%s", st.Pid, table), ports)
+		if err != nil && !errors.Is(err, os.ErrNotExist) {
+			return 0, err
+		}
+		n += c
+	}
+	return n, nil
+}
+
+// establishedOn counts the sockets in a /proc/net/tcp-format table
+// that are ESTABLISHED (01) with a local port in ports.
+func establishedOn(table string, ports map[int]bool) (int, error) {
+	f, err := os.Open(table)
+	if err != nil {
+		return 0, err
+	}
+	defer f.Close()
+	n := 0
+	sc := bufio.NewScanner(f)
+	sc.Scan() // header
+	for sc.Scan() {
+		fields := strings.Fields(sc.Text())
+		if len(fields) < 4 || fields[3] != "01" {
+			continue
+		}
+		_, hexPort, ok := strings.Cut(fields[1], ":")
+		if !ok {
+			continue
+		}
+		if p, err := strconv.ParseInt(hexPort, 16, 32); err == nil && ports[int(p)] {
+			n++
+		}
+	}
+	return n, sc.Err()
+}
@@ -15,20 +15,166 @@ import (
 // reconcile-on-startup pass that aligns them with actual Docker state.
 // One Manager per daemon process.
 type Manager struct {
-	docker        *docker.Client
-	panel         *panel.Client
-	historyLines  int
+	docker  *docker.Client
+	panel   *panel.Client
+	console ConsoleDefaults
 
 	mu      sync.RWMutex
 	servers map[string]*Server
+
+	// admitMu guards capacity and serialises Admit calls.
+	admitMu  sync.Mutex
+	capacity *Capacity
+
+	// reserved holds the limits of servers not running here yet that
+	// already count against capacity; see Reserve.
+	reserveMu sync.Mutex
+	reserved  map[string]Commitment
+
+	statsPolicy StatsPolicy
+
+	hostNetwork map[string]bool
+
+	// isolatedNet is set when the daemon itself isn't in the host's
+	// network namespace; see SetDaemonHostNetwork.
+	isolatedNet bool
+
+	// envDir holds each server's environment overrides.
+	envDir string
+
+	// syncConfig pulls a server's config from the panel; wake-on-connect
+	// starts go through it like every other start.
+	syncConfig func(context.Context, *Server) error
+
+	// changed is signalled whenever a server's State may have changed
+	// its process state or flags; see StateChanged.
+	changed chan struct{}
+}
+
+// SetConfigSync installs the panel config pull used before starts the
+// daemon initiates itself.
+func (m *Manager) SetConfigSync(fn func(context.Context, *Server) error) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.syncConfig = fn
+}
+
+func (m *Manager) configSync() func(context.Context, *Server) error {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	return m.syncConfig
+}
+
+// ArmWake pulls the config of every offline server and opens its wake
+// listeners if it starts on demand. Run once after Reconcile: servers
+// come up without config after a daemon restart, so nothing would be
+// listening until their next start.
+func (m *Manager) ArmWake(ctx context.Context) {
+	syncConfig := m.configSync()
+	if syncConfig == nil {
+		return
+	}
+	for _, s := range m.All() {
+		if s.env.State() != environment.StateOffline {
+			continue
+		}
+		if err := syncConfig(ctx, s); err != nil {
+			log.Printf("manager: wake: fetch config %s: %v", s.uuid, err)
+			continue
+		}
+		s.armWake()
+	}
+}
+
+// releaseWakePorts closes the wake listeners of any other server
+// holding one of mappings' host ports, so a server that has since been
+// given the allocation can start.
+func (m *Manager) releaseWakePorts(owner *Server, mappings []docker.PortMapping) {
+	for _, s := range m.All() {
+		if s == owner {
+			continue
+		}
+		for _, pm := range mappings {
+			if s.holdsWakePort(pm.HostPort) {
+				log.Printf("server %s: wake: releasing port %d for server %s", s.uuid, pm.HostPort, owner.uuid)
+				s.disarmWake()
+				break
+			}
+		}
+	}
+}
+
+// SetHostNetworkAllowlist installs the server UUIDs allowed to run with
+// host networking ("*" = all).
+func (m *Manager) SetHostNetworkAllowlist(ids []string) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.hostNetwork = make(map[string]bool, len(ids))
+	for _, id := range ids {
+		m.hostNetwork[id] = true
+	}
 }
 
-func NewManager(d *docker.Client, p *panel.Client, historyLines int) *Manager {
+// HostNetworkAllowed reports whether uuid may use host networking.
+func (m *Manager) HostNetworkAllowed(uuid string) bool {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	return m.hostNetwork["*"] || m.hostNetwork[uuid]
+}
+
+// SetDaemonHostNetwork records whether the daemon shares the host's
+// network namespace (docker.Client.HostNetwork). Without it the start
+// port check can't bind-probe host ports and wake-on-connect can't
+// listen on them. Assumed true until set.
+func (m *Manager) SetDaemonHostNetwork(onHost bool) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.isolatedNet = !onHost
+}
+
+// DaemonHostNetwork reports what SetDaemonHostNetwork recorded.
+func (m *Manager) DaemonHostNetwork() bool {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	return !m.isolatedNet
+}
+
+// SetStatsPolicy installs the stats suppression thresholds. Call before
+// servers start; running pumps keep the policy they started with.
+func (m *Manager) SetStatsPolicy(p StatsPolicy) {
+	m.mu.Lock()
+	defer m.mu.Unlock()
+	m.statsPolicy = p
+}
+
+func (m *Manager) currentStatsPolicy() StatsPolicy {
+	m.mu.RLock()
+	defer m.mu.RUnlock()
+	return m.statsPolicy
+}
+
+func NewManager(d *docker.Client, p *panel.Client, console ConsoleDefaults) *Manager {
 	return &Manager{
-		docker:       d,
-		panel:        p,
-		historyLines: historyLines,
-		servers:      map[string]*Server{},
+		docker:  d,
+		panel:   p,
+		console: console,
+		servers:  map[string]*Server{},
+		reserved: map[string]Commitment{},
+		changed:  make(chan struct{}, 1),
+	}
+}
+
+// StateChanged fires after a server's process state or flags change.
+// Signals coalesce, so the single consumer should re-read every
+// server's State rather than count them.
+func (m *Manager) StateChanged() <-chan struct{} { return m.changed }
+
+// NotifyStateChanged signals StateChanged; for flags tracked outside
+// the manager (transfers).
+func (m *Manager) NotifyStateChanged() {
+	select {
+	case m.changed <- struct{}{}:
+	default:
 	}
 }
 
@@ -48,7 +194,8 @@ func (m *Manager) Get(uuid string) *Server {
 	if s, ok := m.servers[uuid]; ok {
 		return s
 	}
-	s := New(uuid, m.docker, m.panel, m.historyLines)
+	s := New(uuid, m.docker, m.panel, m.console)
+	s.manager = m
 	m.servers[uuid] = s
 	return s
 }
@@ -64,22 +211,30 @@ func (m *Manager) All() []*Server {
 	return out
 }
 
-// Reconcile runs once at startup. It lists every "stellar-*" container
-// on the host, registers a Server for each, sets the state from Docker
+// Reconcile runs at startup. It lists every "stellar-*" container on
+// the host, registers a Server for each, sets the state from Docker
 // reality, and force-pushes the result to the API so the panel/DB
-// converge after a daemon restart. No periodic reconcile loop — this
-// fires once per process lifetime.
+// converge after a daemon restart. No periodic reconcile loop — besides
+// startup it only runs again when Docker comes back; see WatchDocker.
 func (m *Manager) Reconcile(ctx context.Context) {
+	if _, err := m.reconcile(ctx); err != nil {
+		log.Printf("manager: reconcile list: %v", err)
+	}
+}
+
+// reconcile is Reconcile, returning the UUIDs it found a container for.
+func (m *Manager) reconcile(ctx context.Context) (map[string]bool, error) {
 	containers, err := m.docker.ListContainersFiltered(ctx, "stellar-")
 	if err != nil {
-		log.Printf("manager: reconcile list: %v", err)
-		return
+		return nil, err
 	}
+	seen := make(map[string]bool, len(containers))
 	for _, c := range containers {
 		uuid := strings.TrimPrefix(c.Name, "stellar-")
-		if uuid == "" || strings.HasPrefix(uuid, "install-") {
+		if uuid == "" || strings.HasPrefix(uuid, "install-") || strings.HasPrefix(uuid, "sidecar-") || strings.HasPrefix(uuid, "service-") {
 			continue
 		}
+		seen[uuid] = true
 		s := m.Get(uuid)
 		next := environment.StateOffline
 		if c.Running {
@@ -91,4 +246,5 @@ func (m *Manager) Reconcile(ctx context.Context) {
 		s.env.ForceState(next)
 		log.Printf("manager: reconcile %s -> %s", uuid, next)
 	}
+	return seen, nil
 }
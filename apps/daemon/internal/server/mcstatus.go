@@ -0,0 +1,133 @@
+package server
+
+import (
+	"bufio"
+	"encoding/binary"
+	"encoding/json"
+	"errors"
+	"io"
+)
+
+// Just enough of the Minecraft protocol for a sleeping server to answer
+// the server list and turn joins away politely: the handshake, the
+// status exchange, and a login-state disconnect.
+
+const (
+	mcMaxPacket   = 2048
+	mcStateStatus = 1
+	mcLegacyPing  = 0xFE
+)
+
+var errNotMinecraft = errors.New("not a minecraft handshake")
+
+type mcHandshake struct {
+	Protocol  int32
+	NextState int32
+}
+
+// readMCHandshake parses the handshake packet: VarInt length, VarInt
+// packet id (0x00), VarInt protocol version, String server address,
+// u16 port, VarInt next state.
+func readMCHandshake(r *bufio.Reader) (mcHandshake, error) {
+	id, pkt, err := readMCPacket(r)
+	if err != nil {
+		return mcHandshake{}, err
+	}
+	if id != 0x00 {
+		return mcHandshake{}, errNotMinecraft
+	}
+	var hs mcHandshake
+	var ok bool
+	if hs.Protocol, pkt, ok = mcVarInt(pkt); !ok {
+		return hs, errNotMinecraft
+	}
+	l, pkt, ok := mcVarInt(pkt)
+	if !ok || l < 0 || int(l)+2 > len(pkt) {
+		return hs, errNotMinecraft
+	}
+	pkt = pkt[l+2:] // address, port
+	if hs.NextState, _, ok = mcVarInt(pkt); !ok {
+		return hs, errNotMinecraft
+	}
+	return hs, nil
+}
+
+// serveMCStatus answers the status request with motd as the
+// description and echoes the ping that follows, so the server list
+// shows the message and a latency. The client's own protocol version is
+// reported back so it doesn't flag the server as incompatible.
+func serveMCStatus(rw *bufio.ReadWriter, protocol int32, motd string) error {
+	if id, _, err := readMCPacket(rw.Reader); err != nil || id != 0x00 {
+		return errNotMinecraft
+	}
+	status, _ := json.Marshal(map[string]any{
+		"version":     map[string]any{"name": "Sleeping", "protocol": protocol},
+		"players":     map[string]any{"max": 0, "online": 0},
+		"description": map[string]any{"text": motd},
+	})
+	if err := writeMCPacket(rw.Writer, 0x00, mcString(string(status))); err != nil {
+		return err
+	}
+	if err := rw.Flush(); err != nil {
+		return err
+	}
+	id, payload, err := readMCPacket(rw.Reader)
+	if err != nil || id != 0x01 {
+		return nil // the client may skip the ping
+	}
+	if err := writeMCPacket(rw.Writer, 0x01, payload); err != nil {
+		return err
+	}
+	return rw.Flush()
+}
+
+// writeMCDisconnect sends a login-state disconnect with text as the
+// reason.
+func writeMCDisconnect(w *bufio.Writer, text string) error {
+	reason, _ := json.Marshal(map[string]string{"text": text})
+	if err := writeMCPacket(w, 0x00, mcString(string(reason))); err != nil {
+		return err
+	}
+	return w.Flush()
+}
+
+func readMCPacket(r *bufio.Reader) (int32, []byte, error) {
+	n, err := binary.ReadUvarint(r)
+	if err != nil {
+		return 0, nil, err
+	}
+	if n == 0 || n > mcMaxPacket {
+		return 0, nil, errNotMinecraft
+	}
+	pkt := make([]byte, n)
+	if _, err := io.ReadFull(r, pkt); err != nil {
+		return 0, nil, err
+	}
+	id, rest, ok := mcVarInt(pkt)
+	if !ok {
+		return 0, nil, errNotMinecraft
+	}
+	return id, rest, nil
+}
+
+func writeMCPacket(w io.Writer, id int32, payload []byte) error {
+	body := binary.AppendUvarint(nil, uint64(uint32(id)))
+	body = append(body, payload...)
+	frame := binary.AppendUvarint(nil, uint64(len(body)))
+	_, err := w.Write(append(frame, body...))
+	return err
+}
+
+// mcVarInt decodes a protocol VarInt (at most 5 bytes, two's complement
+// int32) from the front of b.
+func mcVarInt(b []byte) (int32, []byte, bool) {
+	v, n := binary.Uvarint(b)
+	if n <= 0 || n > 5 {
+		return 0, b, false
+	}
+	return int32(uint32(v)), b[n:], true
+}
+
+func mcString(s string) []byte {
+	return append(binary.AppendUvarint(nil, uint64(len(s))), s...)
+}
@@ -0,0 +1,61 @@
+package server
+
+import (
+	"context"
+	"errors"
+	"log"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// doPause freezes a running server's container in place: processes
+// stop being scheduled but keep their memory, so unpausing carries on
+// where it left off. Meant for hosts that need an abusive server to
+// stop now. The process state stays running with the "paused" flag
+// set, and the idle monitor is held off so a frozen server isn't
+// mistaken for an empty one.
+func (s *Server) doPause(ctx context.Context) error {
+	if s.env.State() != environment.StateRunning {
+		return errors.New("not running")
+	}
+	if s.paused.Load() {
+		return nil
+	}
+	if err := s.env.Docker().PauseContainer(ctx, s.env.ContainerName()); err != nil {
+		return err
+	}
+	s.setPaused(true)
+	s.stopIdleMonitor()
+	s.publishDaemon("Server paused.")
+	return nil
+}
+
+// doUnpause thaws a paused server.
+func (s *Server) doUnpause(ctx context.Context) error {
+	if !s.paused.Load() {
+		return nil
+	}
+	if err := s.env.Docker().UnpauseContainer(ctx, s.env.ContainerName()); err != nil {
+		return err
+	}
+	s.setPaused(false)
+	if s.env.State() == environment.StateRunning {
+		s.startIdleMonitor()
+	}
+	s.publishDaemon("Server unpaused.")
+	return nil
+}
+
+// thawForStop unpauses a paused server before a stop, which otherwise
+// sends its stop command to a process that can't read it and waits out
+// the whole grace period.
+func (s *Server) thawForStop(ctx context.Context) {
+	if !s.paused.Load() {
+		return
+	}
+	if err := s.env.Docker().UnpauseContainer(ctx, s.env.ContainerName()); err != nil {
+		log.Printf("server %s: unpause before stop: %v", s.uuid, err)
+		return
+	}
+	s.setPaused(false)
+}
@@ -0,0 +1,205 @@
+package server
+
+import (
+	"bufio"
+	"context"
+	"errors"
+	"fmt"
+	"net"
+	"os"
+	"path/filepath"
+	"strconv"
+	"strings"
+	"syscall"
+
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// PortConflictError is returned when an allocation port is already
+// bound on the host. Holder names the owner as precisely as we can:
+// another server, a foreign container, or a host process.
+type PortConflictError struct {
+	HostIP   string
+	HostPort int
+	Protocol string
+	Holder   string
+}
+
+func (e *PortConflictError) Error() string {
+	return fmt.Sprintf("port %s/%s is already in use by %s", net.JoinHostPort(e.HostIP, strconv.Itoa(e.HostPort)), e.Protocol, e.Holder)
+}
+
+// checkPorts returns a PortConflictError for the first host port in
+// mappings that's taken. Docker would otherwise fail mid-start with
+// "driver failed programming external connectivity", after the image
+// pull and with services already up. Ports other containers publish
+// come from Docker; anything else holding one (a host process) is found
+// by bind-probing, which needs the daemon in the host's network
+// namespace. A daemon in a bridge-networked container would probe its
+// own namespace and find every port free, so there the probe is
+// skipped and those conflicts are left for Docker to report. Probe
+// errors other than EADDRINUSE (e.g. a privileged port when the daemon
+// isn't root) are not conflicts either.
+func (s *Server) checkPorts(ctx context.Context, mappings []docker.PortMapping) error {
+	published, _ := s.env.Docker().PublishedPorts(ctx)
+	probe := s.probesHostPorts()
+	for _, p := range mappings {
+		host := p.HostIP
+		if host == "" {
+			host = "0.0.0.0"
+		}
+		for _, proto := range p.Protocols() {
+			for i := 0; i < p.Span(); i++ {
+				port := p.HostPort + i
+				if holder := s.publishedHolder(published, host, proto, port); holder != "" {
+					return &PortConflictError{HostIP: host, HostPort: port, Protocol: proto, Holder: holder}
+				}
+				if !probe || !portInUse(proto, net.JoinHostPort(host, strconv.Itoa(port))) {
+					continue
+				}
+				return &PortConflictError{HostIP: host, HostPort: port, Protocol: proto, Holder: s.portHolder(proto, port)}
+			}
+		}
+	}
+	return nil
+}
+
+// probesHostPorts reports whether the daemon's own sockets are on the
+// host's network, so binding a port there says anything about it.
+func (s *Server) probesHostPorts() bool {
+	return s.manager == nil || s.manager.DaemonHostNetwork()
+}
+
+// publishedHolder names the container other than this server's that
+// publishes port, or "" when none does.
+func (s *Server) publishedHolder(published []docker.PublishedPort, hostIP, proto string, port int) string {
+	for _, pp := range published {
+		if pp.HostPort != port || pp.Proto != proto || !overlaps(hostIP, pp.HostIP) {
+			continue
+		}
+		if pp.Container == s.env.ContainerName() {
+			continue
+		}
+		if uuid, ok := strings.CutPrefix(pp.Container, "stellar-"); ok {
+			return "server " + uuid
+		}
+		return "container " + pp.Container
+	}
+	return ""
+}
+
+// overlaps reports whether bindings on host IPs a and b collide: they
+// do when either is a wildcard or both are the same address.
+func overlaps(a, b string) bool {
+	wild := func(ip string) bool { return ip == "" || ip == "0.0.0.0" || ip == "::" }
+	return wild(a) || wild(b) || a == b
+}
+
+func portInUse(proto, addr string) bool {
+	var err error
+	if proto == "udp" {
+		var pc net.PacketConn
+		if pc, err = net.ListenPacket("udp", addr); err == nil {
+			_ = pc.Close()
+		}
+	} else {
+		var l net.Listener
+		if l, err = net.Listen("tcp", addr); err == nil {
+			_ = l.Close()
+		}
+	}
+	return errors.Is(err, syscall.EADDRINUSE)
+}
+
+// portHolder names the host process listening on port, from /proc,
+// when a bind probe found it taken.
+func (s *Server) portHolder(proto string, port int) string {
+	if proc := listeningProcess(proto, port); proc != "" {
+		return proc
+	}
+	return "another process"
+}
+
+// listeningProcess resolves the socket bound to port to "process
+// <comm> (pid N)" via /proc/net/{tcp,udp}{,6} inodes and
+// /proc/<pid>/fd. Returns "" when the socket isn't visible (the daemon
+// runs in its own PID namespace or without permission to read other
+// processes' fds).
+func listeningProcess(proto string, port int) string {
+	// TCP listeners are in LISTEN (0A); bound UDP sockets report CLOSE (07).
+	state := "0A"
+	if proto == "udp" {
+		state = "07"
+	}
+	inodes := map[string]bool{}
+	for _, table := range []string{"/proc/net/" + proto, "/proc/net/" + proto + "6"} {
+		for _, ino := range boundInodes(table, state, port) {
+			inodes[ino] = true
+		}
+	}
+	if len(inodes) == 0 {
+		return ""
+	}
+	pids, _ := filepath.Glob("/proc/[0-9]*")
+	for _, dir := range pids {
+		fds, err := os.ReadDir(filepath.Join(dir, "fd"))
+		if err != nil {
+			continue
+		}
+		for _, fd := range fds {
+			target, err := os.Readlink(filepath.Join(dir, "fd", fd.Name()))
+			if err != nil {
+				continue
+			}
+			ino, ok := strings.CutPrefix(target, "socket:[")
+			if !ok || !inodes[strings.TrimSuffix(ino, "]")] {
+				continue
+			}
+			comm, _ := os.ReadFile(filepath.Join(dir, "comm"))
+			return fmt.Sprintf("process %s (pid %s)", strings.TrimSpace(string(comm)), filepath.Base(dir))
+		}
+	}
+	return ""
+}
+
+// boundInodes returns the socket inodes in a /proc/net/tcp-format
+// table that are in state on port.
+func boundInodes(table, state string, port int) []string {
+	f, err := os.Open(table)
+	if err != nil {
+		return nil
+	}
+	defer f.Close()
+	var out []string
+	sc := bufio.NewScanner(f)
+	sc.Scan() // header
+	for sc.Scan() {
+		fields := strings.Fields(sc.Text())
+		// sl local_address rem_address st ... inode at index 9.
+		if len(fields) < 10 || fields[3] != state {
+			continue
+		}
+		_, hexPort, ok := strings.Cut(fields[1], ":")
+		if !ok {
+			continue
+		}
+		if p, err := strconv.ParseInt(hexPort, 16, 32); err != nil || int(p) != port {
+			continue
+		}
+		out = append(out, fields[9])
+	}
+	return out
+}
+
+// checkHostNetwork refuses host networking unless the node allowlists
+// the server. Multi-container stacks need their own bridge network for
+// service discovery, so they can't combine with it either.
+func (s *Server) checkHostNetwork(cfg Config) error {
+	if s.manager == nil || !s.manager.HostNetworkAllowed(s.uuid) {
+		return errors.New("host networking is not allowed for this server on this node")
+	}
+	if len(cfg.Services) > 0 {
+		return errors.New("host networking cannot be combined with blueprint services")
+	}
+	return nil
+}
@@ -0,0 +1,41 @@
+package server
+
+import (
+	"context"
+
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// PortDiff compares the ports the running container publishes with the
+// ones the installed config asks for. Docker can't rebind a container
+// in place, so a non-empty diff means the server runs on its old ports
+// until it restarts (which recreates the container). Offline and
+// host-network servers have nothing bound through Docker and always
+// diff empty: their next start binds whatever the config says.
+func (s *Server) PortDiff(ctx context.Context) (added, removed []docker.PublishedPort, err error) {
+	if s.env.State() == environment.StateOffline {
+		return nil, nil, nil
+	}
+	cfg := s.Config()
+	if cfg.HostNetwork {
+		return nil, nil, nil
+	}
+	current, err := s.env.Docker().ContainerPorts(ctx, s.env.ContainerName())
+	if err != nil {
+		return nil, nil, err
+	}
+	_, _, ports := s.containerNetwork(cfg)
+	added, removed = docker.DiffPorts(current, ports)
+	return added, removed, nil
+}
+
+// RearmWake moves an offline server's wake listeners to the ports in
+// its installed config.
+func (s *Server) RearmWake() {
+	if s.env.State() != environment.StateOffline {
+		return
+	}
+	s.disarmWake()
+	s.armWake()
+}
@@ -0,0 +1,155 @@
+package server
+
+import (
+	"context"
+	"errors"
+	"log"
+	"time"
+)
+
+// Power requests from the console, the panel and schedules go through a
+// per-server queue instead of racing for the power lock: they run one
+// at a time in arrival order, a request for an action that is already
+// running or waiting is collapsed into it, and the queue is short so a
+// burst of clicks can't line up minutes of restarts. Kill skips the
+// queue, drops whatever is waiting, and runs at once, beside the
+// action it interrupts if there is one. It takes over as the running
+// action, so it's reported like any other and whatever is queued after
+// it waits for it to finish.
+
+// maxPendingPower bounds the requests waiting behind the running one.
+const maxPendingPower = 3
+
+// powerTimeout bounds one queued action.
+const powerTimeout = 15 * time.Minute
+
+// prepareTimeout bounds a request's prepare step: the config pull and,
+// for a countdown restart, the countdown itself.
+const prepareTimeout = maxCountdownSeconds*time.Second + time.Minute
+
+var (
+	// ErrPowerInProgress: the same action is already running or queued.
+	// The returned ticket is that request.
+	ErrPowerInProgress = errors.New("power action already in progress")
+	// ErrPowerQueueFull: too many requests are waiting.
+	ErrPowerQueueFull = errors.New("too many power actions queued")
+)
+
+// PowerTicket is one queued power request.
+type PowerTicket struct {
+	ID        uint64      `json:"id"`
+	Action    PowerAction `json:"action"`
+	QueuedAt  time.Time   `json:"queuedAt"`
+	StartedAt *time.Time  `json:"startedAt,omitempty"`
+
+	prepare func(context.Context) error
+}
+
+// PowerQueueState is the queue as reported on the power endpoint.
+type PowerQueueState struct {
+	Running *PowerTicket  `json:"running"`
+	Pending []PowerTicket `json:"pending"`
+}
+
+// EnqueuePower queues action and returns its ticket. prepare, when set,
+// runs right before the action (the router pulls fresh config there)
+// and a failure skips the action. Returns the existing ticket with
+// ErrPowerInProgress for a duplicate.
+func (s *Server) EnqueuePower(action PowerAction, prepare func(context.Context) error) (PowerTicket, error) {
+	s.powerMu.Lock()
+	defer s.powerMu.Unlock()
+	if r := s.powerRunning; r != nil && r.Action == action {
+		return *r, ErrPowerInProgress
+	}
+	for _, p := range s.powerPending {
+		if p.Action == action {
+			return *p, ErrPowerInProgress
+		}
+	}
+	s.powerSeq++
+	t := &PowerTicket{ID: s.powerSeq, Action: action, QueuedAt: time.Now(), prepare: prepare}
+	if action == PowerKill {
+		if n := len(s.powerPending); n > 0 {
+			log.Printf("server %s: kill dropped %d queued power action(s)", s.uuid, n)
+		}
+		s.powerPending = nil
+		now := time.Now()
+		t.StartedAt = &now
+		s.powerRunning = t
+		go s.drainPower(t)
+		return *t, nil
+	}
+	if len(s.powerPending) >= maxPendingPower {
+		return PowerTicket{}, ErrPowerQueueFull
+	}
+	s.powerPending = append(s.powerPending, t)
+	if s.powerRunning == nil {
+		s.powerRunning = s.popPowerLocked()
+		go s.drainPower(s.powerRunning)
+	}
+	return *t, nil
+}
+
+// PowerQueue reports the running and waiting requests.
+func (s *Server) PowerQueue() PowerQueueState {
+	s.powerMu.Lock()
+	defer s.powerMu.Unlock()
+	st := PowerQueueState{Pending: make([]PowerTicket, 0, len(s.powerPending))}
+	if s.powerRunning != nil {
+		r := *s.powerRunning
+		st.Running = &r
+	}
+	for _, p := range s.powerPending {
+		st.Pending = append(st.Pending, *p)
+	}
+	return st
+}
+
+// popPowerLocked moves the head of the queue to running.
+func (s *Server) popPowerLocked() *PowerTicket {
+	t := s.powerPending[0]
+	s.powerPending = s.powerPending[1:]
+	now := time.Now()
+	t.StartedAt = &now
+	return t
+}
+
+// drainPower runs t, the running request, then queued requests until
+// the queue is empty. A kill that takes over while one runs starts its
+// own drain, and this one stops once its request returns.
+func (s *Server) drainPower(t *PowerTicket) {
+	for {
+		s.runPower(t)
+
+		s.powerMu.Lock()
+		if s.powerRunning != t {
+			s.powerMu.Unlock()
+			return
+		}
+		if len(s.powerPending) == 0 {
+			s.powerRunning = nil
+			s.powerMu.Unlock()
+			return
+		}
+		t = s.popPowerLocked()
+		s.powerRunning = t
+		s.powerMu.Unlock()
+	}
+}
+
+func (s *Server) runPower(t *PowerTicket) {
+	if t.prepare != nil {
+		ctx, cancel := context.WithTimeout(context.Background(), prepareTimeout)
+		err := t.prepare(ctx)
+		cancel()
+		if err != nil {
+			log.Printf("server %s: power %s: prepare: %v", s.uuid, t.Action, err)
+			return
+		}
+	}
+	ctx, cancel := context.WithTimeout(context.Background(), powerTimeout)
+	defer cancel()
+	if err := s.HandlePower(ctx, t.Action); err != nil {
+		log.Printf("server %s: power %s: %v", s.uuid, t.Action, err)
+	}
+}
@@ -0,0 +1,168 @@
+package server
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"path/filepath"
+	"sort"
+	"strings"
+	"syscall"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// CheckStatus is the outcome of one pre-flight check. Only fail blocks
+// a start; warn is something the operator should know about.
+type CheckStatus string
+
+const (
+	CheckPass CheckStatus = "pass"
+	CheckWarn CheckStatus = "warn"
+	CheckFail CheckStatus = "fail"
+	CheckSkip CheckStatus = "skip"
+)
+
+// Check is one line of the pre-flight checklist.
+type Check struct {
+	ID      string      `json:"id"`
+	Status  CheckStatus `json:"status"`
+	Message string      `json:"message"`
+}
+
+const (
+	// minFreeDiskBytes fails pre-flight: the game can't even write its
+	// world/session lock below this.
+	minFreeDiskBytes = 100 << 20
+	// lowFreeDiskBytes warns: enough to start, not to save for long.
+	lowFreeDiskBytes = 1 << 30
+)
+
+// Preflight runs doStart's checks against the installed config without
+// touching the container: state, image, admission, host networking,
+// ports, free disk, config-file parsers and startup placeholders. The
+// image is looked up in the registry, never pulled.
+func (s *Server) Preflight(ctx context.Context) []Check {
+	cfg := s.Config()
+	var out []Check
+	add := func(id string, st CheckStatus, msg string) {
+		out = append(out, Check{ID: id, Status: st, Message: msg})
+	}
+
+	offline := s.env.State() == environment.StateOffline
+	if offline {
+		add("state", CheckPass, "server is offline")
+	} else {
+		add("state", CheckFail, fmt.Sprintf("server is %s; stop it first", s.env.State()))
+	}
+
+	dc := s.env.Docker()
+	switch {
+	case cfg.DockerImage == "":
+		add("image", CheckFail, "no docker image configured")
+	case dc.ImagePresent(ctx, cfg.DockerImage):
+		add("image", CheckPass, cfg.DockerImage+" is present locally")
+	default:
+		if err := dc.ImagePullable(ctx, cfg.DockerImage); err != nil {
+			add("image", CheckFail, cfg.DockerImage+" is not present locally and can't be pulled: "+err.Error())
+		} else {
+			add("image", CheckPass, cfg.DockerImage+" will be pulled from its registry")
+		}
+	}
+
+	if s.manager != nil {
+		if err := s.manager.Admit(s.uuid, cfg.TotalMemory(), cfg.CPUPercent); err != nil {
+			add("admission", CheckFail, err.Error())
+		} else {
+			add("admission", CheckPass, "node has capacity for this server")
+		}
+	}
+
+	if cfg.HostNetwork {
+		if err := s.checkHostNetwork(cfg); err != nil {
+			add("host_network", CheckFail, err.Error())
+		} else {
+			add("host_network", CheckPass, "host networking is allowed")
+		}
+	}
+
+	_, _, ports := s.containerNetwork(cfg)
+	switch {
+	case !offline:
+		add("ports", CheckSkip, "server is running; its own ports are bound")
+	case len(ports) == 0:
+		add("ports", CheckPass, "no ports to publish")
+	default:
+		if err := s.checkPorts(ctx, ports); err != nil {
+			add("ports", CheckFail, err.Error())
+		} else {
+			msg := fmt.Sprintf("%d port mapping(s) free", len(ports))
+			if !s.probesHostPorts() {
+				msg += " of other containers; host processes weren't checked (the daemon isn't on the host network)"
+			}
+			add("ports", CheckPass, msg)
+		}
+	}
+
+	out = append(out, diskCheck(cfg.BindMount))
+
+	for _, f := range cfg.ConfigFiles {
+		if !patchable(f.Parser) {
+			add("config_files", CheckWarn, fmt.Sprintf("%s: parser %q is not supported and will be skipped", f.Path, f.Parser))
+		}
+	}
+
+	env := flattenEnv(cfg.Environment, cfg.StartupCommand, cfg.Memory)
+	switch startup := substituteVars(map[string]string{"": cfg.StartupCommand}, env)[""]; {
+	case strings.TrimSpace(cfg.StartupCommand) == "":
+		add("startup", CheckWarn, "no startup command; the image's default entrypoint runs")
+	case varRE.MatchString(startup):
+		add("startup", CheckWarn, "startup command has variables the daemon can't resolve (the image may): "+strings.Join(unresolvedVars(startup), ", "))
+	default:
+		add("startup", CheckPass, "startup command resolves")
+	}
+	return out
+}
+
+// diskCheck reports free space on the filesystem holding the server's
+// files (or the nearest existing parent before the first install).
+func diskCheck(bindMount string) Check {
+	if bindMount == "" {
+		return Check{ID: "disk", Status: CheckSkip, Message: "no server directory configured"}
+	}
+	dir := bindMount
+	var st syscall.Statfs_t
+	for {
+		err := syscall.Statfs(dir, &st)
+		if err == nil {
+			break
+		}
+		parent := filepath.Dir(dir)
+		if !errors.Is(err, syscall.ENOENT) || parent == dir {
+			return Check{ID: "disk", Status: CheckWarn, Message: "couldn't read free space: " + err.Error()}
+		}
+		dir = parent
+	}
+	free := int64(st.Bavail) * int64(st.Bsize)
+	msg := fmt.Sprintf("%.1f GB free", float64(free)/(1<<30))
+	switch {
+	case free < minFreeDiskBytes:
+		return Check{ID: "disk", Status: CheckFail, Message: msg + "; the server can't write its files"}
+	case free < lowFreeDiskBytes:
+		return Check{ID: "disk", Status: CheckWarn, Message: msg + "; saves may fail soon"}
+	}
+	return Check{ID: "disk", Status: CheckPass, Message: msg}
+}
+
+func unresolvedVars(s string) []string {
+	seen := map[string]bool{}
+	for _, m := range varRE.FindAllStringSubmatch(s, -1) {
+		seen[m[1]] = true
+	}
+	out := make([]string, 0, len(seen))
+	for name := range seen {
+		out = append(out, name)
+	}
+	sort.Strings(out)
+	return out
+}
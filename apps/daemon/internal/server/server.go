@@ -5,13 +5,14 @@ package server
 
 import (
 	"context"
-	"encoding/json"
 	"errors"
 	"fmt"
 	"log"
+	"net"
 	"regexp"
 	"runtime"
 	"sync"
+	"sync/atomic"
 	"time"
 
 	"github.com/stellarstack/daemon/internal/docker"
@@ -27,9 +28,30 @@ type Server struct {
 	bus     *events.Bus
 	history *consoleHistory
 	panel   *panel.Client
+	manager *Manager
+	console ConsoleDefaults
 
 	powerLock chan struct{}
 
+	// Power request queue; see powerqueue.go.
+	powerMu      sync.Mutex
+	powerRunning *PowerTicket
+	powerPending []*PowerTicket
+	powerSeq     uint64
+
+	countdownMu     sync.Mutex
+	countdownCancel context.CancelFunc // set while a restart countdown runs
+
+	// Idle auto-shutdown; see idle.go.
+	idle       idleTracker
+	idleMu     sync.Mutex
+	idleCancel context.CancelFunc
+
+	// Wake-on-connect listeners while stopped; see wake.go.
+	wakeMu        sync.Mutex
+	wakeGen       uint64
+	wakeListeners []net.Listener
+
 	cfgMu sync.RWMutex
 	cfg   Config
 
@@ -38,10 +60,33 @@ type Server struct {
 	attachMu     sync.Mutex
 	attachCancel context.CancelFunc
 
-	// statsCancel + uptime tracking for the WS stats event.
+	// statsCancel + uptime tracking for the WS stats event. lastStats
+	// is the most recent sample, for State; zero with no stream running.
 	statsMu     sync.Mutex
 	statsCancel context.CancelFunc
 	startedAt   time.Time
+	lastStats   docker.StatsSnapshot
+
+	// installing is set while the panel's install script runs.
+	installing atomic.Bool
+
+	// dockerLost is set while the Docker daemon is unreachable and the
+	// container's real state can't be known; see Manager.WatchDocker.
+	dockerLost atomic.Bool
+
+	// exitWatchers counts running watchExit calls, so re-arming after a
+	// Docker reconnect can tell whether the old one survived. exitMu
+	// serialises containerExited.
+	exitWatchers atomic.Int32
+	exitMu       sync.Mutex
+
+	// paused is set while the container is paused in Docker.
+	paused atomic.Bool
+
+	// envOverrides caches the persisted environment overrides; nil
+	// until first read. See envoverride.go.
+	envMu        sync.Mutex
+	envOverrides map[string]string
 
 	// errorOnce gates one-shot daemon error events (eula-required, …).
 	// Reset on each start so a subsequent run can re-emit. Prevents
@@ -59,32 +104,78 @@ type Config struct {
 	Environment    map[string]string
 	Stop           environment.StopConfig
 	Memory         int64
+	MemoryOverhead int64
 	CPUPercent     int64
+	Disk           int64 // MB, 0 = unlimited; SFTP statvfs reports it as the disk size
 	PortMappings   []docker.PortMapping
+	HostNetwork    bool
 	BindMount      string
 	StartupDone    []*regexp.Regexp
 	ConfigFiles    []ConfigFilePatch
+	Sidecars       []Sidecar
+	Services       []Service
+	PlayerCommands map[string]string
+	Idle           IdlePolicy
+	// Console history depth and subscriber buffer; 0 = node default.
+	ConsoleHistoryLines int
+	ConsoleBuffer       int
+	// ExtraHosts are "hostname:ip" entries added to the main
+	// container's /etc/hosts; a change lands at the next start.
+	ExtraHosts []string
+	// FileDenylist and FileHidden are the panel's path lists for the
+	// file manager and SFTP, on top of the node's defaults.
+	FileDenylist []string
+	FileHidden   []string
+}
+
+// ContainerMemory is the Docker memory limit in MB: the panel limit plus
+// the blueprint overhead. SERVER_MEMORY stays at Memory so a JVM sized
+// from it (-Xmx) has headroom for off-heap allocations. 0 = unlimited.
+func (c Config) ContainerMemory() int64 {
+	if c.Memory <= 0 {
+		return 0
+	}
+	return c.Memory + c.MemoryOverhead
+}
+
+// TotalMemory is ContainerMemory plus every sidecar and service limit —
+// what the server commits on the node. Unlimited extras don't count.
+func (c Config) TotalMemory() int64 {
+	total := c.ContainerMemory()
+	if total <= 0 {
+		return 0
+	}
+	for _, sc := range c.Sidecars {
+		total += sc.Memory
+	}
+	for _, svc := range c.Services {
+		total += svc.Memory
+	}
+	return total
 }
 
 type ConfigFilePatch struct {
-	Path    string
-	Parser  string
-	Patches map[string]string
+	Path    string            `json:"path"`
+	Parser  string            `json:"parser"`
+	Patches map[string]string `json:"patches"`
 }
 
 // New constructs a Server for the supplied uuid. Container name follows
 // the "stellar-<uuid>" convention so reconcile can find it.
-func New(uuid string, dc *docker.Client, panelClient *panel.Client, historyLines int) *Server {
+func New(uuid string, dc *docker.Client, panelClient *panel.Client, console ConsoleDefaults) *Server {
 	containerName := "stellar-" + uuid
 	env := environment.New(dc, containerName)
 	bus := events.New()
-	hist := newConsoleHistory(historyLines)
+	bus.SetBuffer(console.Buffer)
+	bus.SetDropNotice(dropNotice)
+	hist := newConsoleHistory(console.HistoryLines)
 	s := &Server{
 		uuid:      uuid,
 		env:       env,
 		bus:       bus,
 		history:   hist,
 		panel:     panelClient,
+		console:   console,
 		powerLock: make(chan struct{}, 1),
 	}
 	env.SetListener(s.onStateChange)
@@ -104,6 +195,7 @@ func (s *Server) SetConfig(c Config) {
 	s.cfg = c
 	s.cfgMu.Unlock()
 	s.env.SetStop(c.Stop)
+	s.applyConsoleConfig(c)
 }
 
 func (s *Server) Config() Config {
@@ -117,19 +209,56 @@ func (s *Server) Config() Config {
 // daemon-prefixed status messages into the same console stream.
 func (s *Server) PublishDaemon(msg string) { s.publishDaemon(msg) }
 
+// PublishEvent marshals `{v, event, args}` onto the server bus. Used by
+// subsystems outside this package (jobs, …) that emit their own
+// structured frames alongside console output. Everything except
+// console output is sequenced for replay.
+func (s *Server) PublishEvent(event string, args ...any) {
+	if args == nil {
+		args = []any{}
+	}
+	if event == "console output" {
+		s.bus.Publish(events.Encode(event, args))
+		return
+	}
+	s.publishSequenced(event, args)
+}
+
+// PublishTransient is PublishEvent without the replay sequence, for
+// frames a later one supersedes (job progress): kept out of the replay
+// ring, a flood of them can't push out the frames a reconnecting client
+// actually needs.
+func (s *Server) PublishTransient(event string, args ...any) {
+	if args == nil {
+		args = []any{}
+	}
+	s.bus.Publish(events.Encode(event, args))
+}
+
+// publishSequenced emits `{v, event, args, seq}` through the bus replay
+// ring so reconnecting clients can ask for it again.
+func (s *Server) publishSequenced(event string, args []any) {
+	s.bus.PublishSequenced(func(seq uint64) events.Frame {
+		return events.EncodeSequenced(event, args, seq)
+	})
+}
+
 // publishDaemon emits a standard "[StellarStack Daemon]: <msg>"
 // console line so the panel surfaces what the daemon is doing during a
 // power action — pulling images, running config patches, marking
 // state, etc. Output goes through the same bus + history pipeline as
 // real container stdout so the user sees a continuous stream.
+// dropNotice is the console line a client gets after the bus dropped
+// frames for it. Not kept in history: only that client missed them.
+func dropNotice(n uint64) events.Frame {
+	line := fmt.Sprintf("[StellarStack Daemon]: Output truncated due to a slow connection (%d messages dropped).", n)
+	return events.Encode("console output", []any{line})
+}
+
 func (s *Server) publishDaemon(msg string) {
 	line := "[StellarStack Daemon]: " + msg
 	s.history.push(line)
-	frame, _ := json.Marshal(map[string]any{
-		"event": "console output",
-		"args":  []any{line},
-	})
-	s.bus.Publish(frame)
+	s.bus.Publish(events.Encode("console output", []any{line}))
 }
 
 // publishDaemonError emits a one-shot `{event:"daemon error", args:[code]}`
@@ -148,11 +277,7 @@ func (s *Server) publishDaemonError(code string) {
 	}
 	s.errorEmitted[code] = true
 	s.errorMu.Unlock()
-	frame, _ := json.Marshal(map[string]any{
-		"event": "daemon error",
-		"args":  []any{code},
-	})
-	s.bus.Publish(frame)
+	s.publishSequenced("daemon error", []any{code})
 }
 
 // resetErrors clears the per-run daemon-error gate. Called at the start
@@ -210,11 +335,7 @@ func (s *Server) scanLineForReady(line string) {
 func (s *Server) publishHeader(msg string) {
 	line := "stellarstack@" + s.uuid[:8] + "~ " + msg
 	s.history.push(line)
-	frame, _ := json.Marshal(map[string]any{
-		"event": "console output",
-		"args":  []any{line},
-	})
-	s.bus.Publish(frame)
+	s.bus.Publish(events.Encode("console output", []any{line}))
 }
 
 // onStateChange is invoked by the Environment listener for every state
@@ -227,11 +348,10 @@ func (s *Server) onStateChange(prev, next environment.State) {
 	caller := runtime.FuncForPC(pc).Name()
 	log.Printf("server %s: state %s -> %s (caller=%s)", s.uuid, prev, next, caller)
 	s.publishHeader("Server marked as " + string(next) + "...")
-	frame, _ := json.Marshal(map[string]any{
-		"event": "status",
-		"args":  []any{string(next)},
-	})
-	s.bus.Publish(frame)
+	s.publishSequenced("status", []any{string(next)})
+	if s.manager != nil {
+		s.manager.NotifyStateChanged()
+	}
 
 	if s.panel != nil {
 		go func() {
@@ -248,9 +368,14 @@ func (s *Server) onStateChange(prev, next environment.State) {
 	case environment.StateRunning:
 		s.startAttachPump()
 		s.startStatsPump()
+		s.startIdleMonitor()
+	case environment.StateStarting:
+		s.disarmWake()
 	case environment.StateOffline:
 		s.stopAttachPump()
 		s.stopStatsPump()
+		s.stopIdleMonitor()
+		go s.armWake()
 		// Clear the history ring so a future browser (re)connect on an
 		// offline server doesn't dump the previous session's log. The
 		// frontend's offline-transition path also clears its in-memory
@@ -267,6 +392,8 @@ const (
 	PowerStop    PowerAction = "stop"
 	PowerRestart PowerAction = "restart"
 	PowerKill    PowerAction = "kill"
+	PowerPause   PowerAction = "pause"
+	PowerUnpause PowerAction = "unpause"
 )
 
 // HandlePower runs the supplied action under the per-server power lock.
@@ -300,6 +427,10 @@ func (s *Server) HandlePower(ctx context.Context, action PowerAction) error {
 		return s.doStop(ctx)
 	case PowerRestart:
 		return s.doRestart(ctx)
+	case PowerPause:
+		return s.doPause(ctx)
+	case PowerUnpause:
+		return s.doUnpause(ctx)
 	default:
 		return fmt.Errorf("unknown power action %q", action)
 	}
@@ -318,6 +449,15 @@ func (s *Server) doStart(ctx context.Context) error {
 
 	s.resetErrors()
 	s.env.MarkStarting()
+	// Admission runs after MarkStarting so a concurrent start of another
+	// server already counts this one as committed.
+	if s.manager != nil {
+		if err := s.manager.Admit(s.uuid, cfg.TotalMemory(), cfg.CPUPercent); err != nil {
+			s.publishDaemon("Refusing to start: " + err.Error())
+			s.env.MarkOffline()
+			return err
+		}
+	}
 	s.publishDaemon("Updating process configuration files...")
 	if cfg.BindMount != "" {
 		s.applyConfigFiles(cfg.BindMount, cfg.Environment)
@@ -331,6 +471,29 @@ func (s *Server) doStart(ctx context.Context) error {
 	if err := dc.RemoveContainer(ctx, containerName, true); err != nil {
 		log.Printf("server %s: pre-start remove: %v", s.uuid, err)
 	}
+	s.removeSidecars(ctx)
+	s.removeServices(ctx)
+
+	if cfg.HostNetwork {
+		if err := s.checkHostNetwork(cfg); err != nil {
+			s.publishDaemon("Refusing to start: " + err.Error())
+			s.env.MarkOffline()
+			return err
+		}
+	}
+
+	// Probe after the old container is gone so its own bindings don't
+	// count, and before the pull so a conflict fails fast. A stopped
+	// server still waiting for connections on a port this one now owns
+	// gives it up first.
+	if s.manager != nil {
+		s.manager.releaseWakePorts(s, cfg.PortMappings)
+	}
+	if err := s.checkPorts(ctx, cfg.PortMappings); err != nil {
+		s.publishDaemon("Refusing to start: " + err.Error())
+		s.env.MarkOffline()
+		return err
+	}
 
 	s.publishDaemon("Pulling Docker container image, this could take a few minutes to complete...")
 	if err := dc.EnsureImage(ctx, cfg.DockerImage); err != nil {
@@ -340,24 +503,33 @@ func (s *Server) doStart(ctx context.Context) error {
 	}
 	s.publishDaemon("Finished pulling Docker container image")
 
-	stopSignal := ""
-	if cfg.Stop.Type == "signal" {
-		stopSignal = cfg.Stop.Value
+	// Services come up first: the main container depends on all of them
+	// and joins their network.
+	if err := s.startServices(ctx, cfg); err != nil {
+		s.publishDaemon("Failed to start services: " + err.Error())
+		s.removeServices(context.Background())
+		s.env.MarkOffline()
+		return fmt.Errorf("start services: %w", err)
 	}
+	networkMode, aliases, ports := s.containerNetwork(cfg)
 	if _, err := dc.CreateContainer(ctx, docker.CreateContainerOptions{
 		Name:             containerName,
 		Image:            cfg.DockerImage,
 		Env:              flattenEnv(cfg.Environment, cfg.StartupCommand, cfg.Memory),
-		StopSignal:       stopSignal,
+		StopSignal:       stopSignal(cfg),
 		BindMount:        cfg.BindMount,
-		MemoryLimitBytes: cfg.Memory * 1024 * 1024,
+		MemoryLimitBytes: cfg.ContainerMemory() * 1024 * 1024,
 		CPULimitPercent:  cfg.CPUPercent,
-		PidsLimit:        256,
-		Ports:            cfg.PortMappings,
+		PidsLimit:        containerPidsLimit,
+		Ports:            ports,
+		NetworkMode:      networkMode,
+		NetworkAliases:   aliases,
+		ExtraHosts:       cfg.ExtraHosts,
 		OpenStdin:        true,
 		Tty:              true,
 	}); err != nil {
 		s.publishDaemon("Failed to create container: " + err.Error())
+		s.removeServices(context.Background())
 		s.env.MarkOffline()
 		return fmt.Errorf("create container: %w", err)
 	}
@@ -374,6 +546,7 @@ func (s *Server) doStart(ctx context.Context) error {
 	})
 	if attachErr != nil {
 		s.publishDaemon("Failed to attach to container: " + attachErr.Error())
+		s.removeServices(context.Background())
 		s.env.MarkOffline()
 		return fmt.Errorf("pre-start attach: %w", attachErr)
 	}
@@ -385,9 +558,11 @@ func (s *Server) doStart(ctx context.Context) error {
 
 	if err := dc.StartContainer(ctx, containerName); err != nil {
 		_ = attachConn.Close()
+		s.removeServices(context.Background())
 		s.env.MarkOffline()
 		return fmt.Errorf("start container: %w", err)
 	}
+	s.startSidecars(ctx, cfg)
 
 	// Capture started-at for uptime in stats frames. We don't fail the
 	// start if inspect fails; the field is optional in the wire schema.
@@ -428,14 +603,20 @@ func (s *Server) doStop(ctx context.Context) error {
 	if s.env.State() == environment.StateOffline {
 		return nil
 	}
-	return s.env.WaitForStop(ctx, 30*time.Second, true)
+	s.thawForStop(ctx)
+	err := s.env.WaitForStop(ctx, s.env.Stop().Grace(), true)
+	s.removeSidecars(context.Background())
+	s.stopServices(context.Background(), s.Config())
+	return err
 }
 
 func (s *Server) doRestart(ctx context.Context) error {
 	if s.env.State() != environment.StateOffline {
-		if err := s.env.WaitForStop(ctx, 30*time.Second, true); err != nil {
+		s.thawForStop(ctx)
+		if err := s.env.WaitForStop(ctx, s.env.Stop().Grace(), true); err != nil {
 			return fmt.Errorf("restart-stop: %w", err)
 		}
+		s.stopServices(context.Background(), s.Config())
 	}
 	return s.doStart(ctx)
 }
@@ -447,6 +628,8 @@ func (s *Server) doKill(ctx context.Context) error {
 	final, cancel := context.WithTimeout(context.Background(), 5*time.Second)
 	defer cancel()
 	_ = s.env.Docker().WaitNotRunning(final, s.env.ContainerName())
+	s.removeSidecars(final)
+	s.removeServices(final)
 	s.env.MarkOffline()
 	return nil
 }
@@ -454,6 +637,8 @@ func (s *Server) doKill(ctx context.Context) error {
 // watchExit blocks on Docker container wait. Used to detect a container
 // that exited without anyone asking it to (crash) so the UI flips off.
 func (s *Server) watchExit() {
+	s.exitWatchers.Add(1)
+	defer s.exitWatchers.Add(-1)
 	ctx, cancel := context.WithCancel(context.Background())
 	defer cancel()
 	// next-exit fires only on the NEXT exit, not on the current state.
@@ -464,6 +649,15 @@ func (s *Server) watchExit() {
 	if !exited {
 		return
 	}
+	s.containerExited()
+}
+
+// containerExited takes a running server whose container went away on
+// its own to offline. Both watchExit and the Docker event stream call
+// it for the same exit; exitMu makes the second a no-op.
+func (s *Server) containerExited() {
+	s.exitMu.Lock()
+	defer s.exitMu.Unlock()
 	// If state is already stopping/offline, the stop path will set it.
 	// We only intervene when we're in running.
 	if s.env.State() != environment.StateRunning {
@@ -498,6 +692,8 @@ func (s *Server) watchExit() {
 	drainCtx, drainCancel := context.WithTimeout(context.Background(), 5*time.Second)
 	s.SnapshotLogs(drainCtx, 200)
 	drainCancel()
+	s.removeSidecars(context.Background())
+	s.stopServices(context.Background(), s.Config())
 	s.env.MarkOffline()
 }
 
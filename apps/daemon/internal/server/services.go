@@ -0,0 +1,222 @@
+package server
+
+import (
+	"context"
+	"fmt"
+	"log"
+
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// Service is one extra container in a multi-container server (e.g. the
+// database of a game+database stack). Unlike a sidecar it gets its own
+// network namespace on a per-server bridge network, reachable from the
+// main container and other services by its name. Services start before
+// the main container, in DependsOn order, and stop after it.
+type Service struct {
+	Name        string
+	Image       string
+	Cmd         []string
+	Environment map[string]string
+	Memory      int64 // MB; 0 = unlimited
+	DependsOn   []string
+	Volumes     []VolumeMount
+	// Console mirrors the service's output into the server console,
+	// each line prefixed with `[name]`.
+	Console bool
+}
+
+// VolumeMount attaches a per-server named volume at Path. Services
+// naming the same volume share it; volumes outlive restarts.
+type VolumeMount struct {
+	Name string
+	Path string
+}
+
+// mainServiceAlias is the DNS name the main container answers to on
+// the service network.
+const mainServiceAlias = "server"
+
+func (s *Server) servicePrefix() string {
+	return "stellar-service-" + s.uuid + "-"
+}
+
+func (s *Server) networkName() string {
+	return "stellar-net-" + s.uuid
+}
+
+func (s *Server) volumeName(name string) string {
+	return "stellar-vol-" + s.uuid + "-" + name
+}
+
+// orderServices sorts services so each comes after everything it
+// depends on, keeping declaration order among independent ones.
+// Unknown dependencies and cycles are errors.
+func orderServices(services []Service) ([]Service, error) {
+	byName := make(map[string]Service, len(services))
+	for _, svc := range services {
+		byName[svc.Name] = svc
+	}
+	for _, svc := range services {
+		for _, dep := range svc.DependsOn {
+			if _, ok := byName[dep]; !ok {
+				return nil, fmt.Errorf("service %s depends on unknown service %s", svc.Name, dep)
+			}
+		}
+	}
+	const (
+		unvisited = iota
+		visiting
+		done
+	)
+	state := make(map[string]int, len(services))
+	out := make([]Service, 0, len(services))
+	var visit func(svc Service) error
+	visit = func(svc Service) error {
+		switch state[svc.Name] {
+		case done:
+			return nil
+		case visiting:
+			return fmt.Errorf("service dependency cycle through %s", svc.Name)
+		}
+		state[svc.Name] = visiting
+		for _, dep := range svc.DependsOn {
+			if err := visit(byName[dep]); err != nil {
+				return err
+			}
+		}
+		state[svc.Name] = done
+		out = append(out, svc)
+		return nil
+	}
+	for _, svc := range services {
+		if err := visit(svc); err != nil {
+			return nil, err
+		}
+	}
+	return out, nil
+}
+
+// startServices brings up the service network and every service in
+// dependency order. Unlike sidecars a failing service fails the server
+// start — the main container usually can't run without its database.
+func (s *Server) startServices(ctx context.Context, cfg Config) error {
+	if len(cfg.Services) == 0 {
+		return nil
+	}
+	ordered, err := orderServices(cfg.Services)
+	if err != nil {
+		return err
+	}
+	dc := s.env.Docker()
+	if err := dc.CreateNetwork(ctx, s.networkName()); err != nil {
+		return fmt.Errorf("create service network: %w", err)
+	}
+	for _, svc := range ordered {
+		s.publishDaemon("Starting service " + svc.Name + "...")
+		if err := dc.EnsureImage(ctx, svc.Image); err != nil {
+			return fmt.Errorf("service %s: pull image: %w", svc.Name, err)
+		}
+		env := make(map[string]string, len(cfg.Environment)+len(svc.Environment))
+		for k, v := range cfg.Environment {
+			env[k] = v
+		}
+		for k, v := range svc.Environment {
+			env[k] = v
+		}
+		volumes := make([]string, 0, len(svc.Volumes))
+		for _, v := range svc.Volumes {
+			volumes = append(volumes, s.volumeName(v.Name)+":"+v.Path)
+		}
+		name := s.servicePrefix() + svc.Name
+		if _, err := dc.CreateContainer(ctx, docker.CreateContainerOptions{
+			Name:             name,
+			Image:            svc.Image,
+			Env:              env,
+			Cmd:              svc.Cmd,
+			Volumes:          volumes,
+			MemoryLimitBytes: svc.Memory * 1024 * 1024,
+			PidsLimit:        256,
+			NetworkMode:      s.networkName(),
+			NetworkAliases:   []string{svc.Name},
+		}); err != nil {
+			return fmt.Errorf("service %s: create: %w", svc.Name, err)
+		}
+		if err := dc.StartContainer(ctx, name); err != nil {
+			return fmt.Errorf("service %s: start: %w", svc.Name, err)
+		}
+		if svc.Console {
+			go s.pumpServiceLogs(svc.Name)
+		}
+		log.Printf("server %s: service %s started", s.uuid, svc.Name)
+	}
+	return nil
+}
+
+// stopServices stops services in reverse dependency order with a short
+// grace so e.g. a database flushes before its dependants are torn
+// down, then removes them.
+func (s *Server) stopServices(ctx context.Context, cfg Config) {
+	dc := s.env.Docker()
+	if ordered, err := orderServices(cfg.Services); err == nil {
+		for i := len(ordered) - 1; i >= 0; i-- {
+			name := s.servicePrefix() + ordered[i].Name
+			if err := dc.StopContainer(ctx, name, 10); err != nil {
+				log.Printf("server %s: stop service %s: %v", s.uuid, ordered[i].Name, err)
+			}
+		}
+	}
+	s.removeServices(ctx)
+}
+
+// removeServices force-removes every service container and the service
+// network. Named volumes are kept — they hold the stack's data.
+func (s *Server) removeServices(ctx context.Context) {
+	dc := s.env.Docker()
+	containers, err := dc.ListContainersFiltered(ctx, s.servicePrefix())
+	if err != nil {
+		log.Printf("server %s: list services: %v", s.uuid, err)
+	}
+	for _, c := range containers {
+		if err := dc.RemoveContainer(ctx, c.Name, true); err != nil {
+			log.Printf("server %s: remove service %s: %v", s.uuid, c.Name, err)
+		}
+	}
+	if err := dc.RemoveNetwork(ctx, s.networkName()); err != nil {
+		log.Printf("server %s: remove service network: %v", s.uuid, err)
+	}
+}
+
+// pumpServiceLogs mirrors a service's output into the console tagged
+// with its name. Ends when the container goes away. Lines don't feed
+// the ready/error scanners — those belong to the main container.
+func (s *Server) pumpServiceLogs(service string) {
+	stream, err := s.env.Docker().FollowLogs(context.Background(), s.servicePrefix()+service)
+	if err != nil {
+		log.Printf("server %s: service %s logs: %v", s.uuid, service, err)
+		return
+	}
+	for line := range stream {
+		cleaned := cleanLine(line.Line)
+		if cleaned == "" {
+			continue
+		}
+		tagged := "[" + service + "] " + cleaned
+		s.history.push(tagged)
+		s.PublishEvent("console output", tagged)
+	}
+}
+
+// statsTargets lists the sidecar and service containers whose usage
+// is folded into the server's stats. Sidecars share the main netns, so
+// only services contribute network counters.
+func (s *Server) statsTargets(cfg Config) []statsTarget {
+	targets := make([]statsTarget, 0, len(cfg.Sidecars)+len(cfg.Services))
+	for _, sc := range cfg.Sidecars {
+		targets = append(targets, statsTarget{name: s.sidecarPrefix() + sc.Name})
+	}
+	for _, svc := range cfg.Services {
+		targets = append(targets, statsTarget{name: s.servicePrefix() + svc.Name, ownNetwork: true})
+	}
+	return targets
+}
@@ -0,0 +1,85 @@
+package server
+
+import (
+	"context"
+	"log"
+
+	"github.com/stellarstack/daemon/internal/docker"
+)
+
+// Sidecar is a helper container (metrics exporter, proxy, …) declared by
+// the blueprint that lives and dies with the server. It joins the main
+// container's network namespace, so it reaches the game on localhost
+// and needs no port mappings, and mounts the same /home/container.
+type Sidecar struct {
+	Name        string
+	Image       string
+	Cmd         []string
+	Environment map[string]string
+	Memory      int64 // MB; 0 = unlimited
+}
+
+// sidecarPrefix is the container name prefix for this server's
+// sidecars. Distinct from "stellar-<uuid>" so reconcile doesn't treat a
+// sidecar as a server of its own.
+func (s *Server) sidecarPrefix() string {
+	return "stellar-sidecar-" + s.uuid + "-"
+}
+
+// startSidecars creates and starts every configured sidecar. Must be
+// called after the main container is running — `container:` network
+// mode needs a live namespace to join. A sidecar that fails to come up
+// is reported to the console but doesn't fail the server start: the
+// game shouldn't be held hostage by its exporter.
+func (s *Server) startSidecars(ctx context.Context, cfg Config) {
+	dc := s.env.Docker()
+	for _, sc := range cfg.Sidecars {
+		name := s.sidecarPrefix() + sc.Name
+		if err := dc.EnsureImage(ctx, sc.Image); err != nil {
+			s.publishDaemon("Failed to pull sidecar " + sc.Name + " image: " + err.Error())
+			continue
+		}
+		env := make(map[string]string, len(cfg.Environment)+len(sc.Environment))
+		for k, v := range cfg.Environment {
+			env[k] = v
+		}
+		for k, v := range sc.Environment {
+			env[k] = v
+		}
+		if _, err := dc.CreateContainer(ctx, docker.CreateContainerOptions{
+			Name:             name,
+			Image:            sc.Image,
+			Env:              env,
+			Cmd:              sc.Cmd,
+			BindMount:        cfg.BindMount,
+			MemoryLimitBytes: sc.Memory * 1024 * 1024,
+			PidsLimit:        64,
+			NetworkMode:      "container:" + s.env.ContainerName(),
+		}); err != nil {
+			s.publishDaemon("Failed to create sidecar " + sc.Name + ": " + err.Error())
+			continue
+		}
+		if err := dc.StartContainer(ctx, name); err != nil {
+			s.publishDaemon("Failed to start sidecar " + sc.Name + ": " + err.Error())
+			continue
+		}
+		log.Printf("server %s: sidecar %s started", s.uuid, sc.Name)
+	}
+}
+
+// removeSidecars force-removes every container carrying this server's
+// sidecar prefix, including ones from a previous config that has since
+// dropped or renamed them.
+func (s *Server) removeSidecars(ctx context.Context) {
+	dc := s.env.Docker()
+	containers, err := dc.ListContainersFiltered(ctx, s.sidecarPrefix())
+	if err != nil {
+		log.Printf("server %s: list sidecars: %v", s.uuid, err)
+		return
+	}
+	for _, c := range containers {
+		if err := dc.RemoveContainer(ctx, c.Name, true); err != nil {
+			log.Printf("server %s: remove sidecar %s: %v", s.uuid, c.Name, err)
+		}
+	}
+}
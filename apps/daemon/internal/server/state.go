@@ -0,0 +1,74 @@
+package server
+
+import (
+	"time"
+
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// State is the compact view of a server the panel dashboard polls for
+// every server on the node at once: process state, uptime and the most
+// recent stats sample. Flags marks work that keeps the server busy:
+// "installing" here, "transferring" added by the router, which tracks
+// transfers. "paused" means the container is paused in Docker, and
+// "unknown" that Docker is unreachable and ProcessState is the last one
+// seen. Suspension is the panel's own and never reported.
+// ConsoleDropped counts the console frames slow clients have missed
+// since the server was loaded.
+type State struct {
+	ProcessState     environment.State `json:"process_state"`
+	Flags            []string          `json:"flags"`
+	UptimeMs         int64             `json:"uptime_ms"`
+	CPUAbsolute      float64           `json:"cpu_absolute"`
+	MemoryBytes      int64             `json:"memory_bytes"`
+	MemoryLimitBytes int64             `json:"memory_limit_bytes"`
+	ConsoleDropped   uint64            `json:"console_dropped"`
+}
+
+// State reports the server's process state and, unless it is offline,
+// its uptime and last stats sample.
+func (s *Server) State() State {
+	st := State{
+		ProcessState:   s.env.State(),
+		Flags:          []string{},
+		ConsoleDropped: s.bus.Dropped(),
+	}
+	if s.Installing() {
+		st.Flags = append(st.Flags, "installing")
+	}
+	if s.paused.Load() {
+		st.Flags = append(st.Flags, "paused")
+	}
+	if s.dockerLost.Load() {
+		st.Flags = append(st.Flags, "unknown")
+	}
+	if st.ProcessState == environment.StateOffline {
+		return st
+	}
+	s.statsMu.Lock()
+	snap, started := s.lastStats, s.startedAt
+	s.statsMu.Unlock()
+	if !started.IsZero() {
+		st.UptimeMs = time.Since(started).Milliseconds()
+	}
+	st.CPUAbsolute = snap.CPUAbsolute
+	st.MemoryBytes = snap.MemoryBytes
+	st.MemoryLimitBytes = snap.MemoryLimitBytes
+	return st
+}
+
+// SetInstalling marks the server as running its install script.
+func (s *Server) SetInstalling(v bool) {
+	if s.installing.Swap(v) != v && s.manager != nil {
+		s.manager.NotifyStateChanged()
+	}
+}
+
+// Installing reports whether the install script is running.
+func (s *Server) Installing() bool { return s.installing.Load() }
+
+func (s *Server) setPaused(v bool) {
+	if s.paused.Swap(v) != v && s.manager != nil {
+		s.manager.NotifyStateChanged()
+	}
+}
@@ -2,11 +2,14 @@ package server
 
 import (
 	"context"
-	"encoding/json"
 	"log"
+	"math"
+	"sync"
 	"time"
 
+	"github.com/stellarstack/daemon/internal/docker"
 	"github.com/stellarstack/daemon/internal/environment"
+	"github.com/stellarstack/daemon/internal/events"
 )
 
 // startStatsPump opens a Docker stats stream and republishes each frame
@@ -29,6 +32,7 @@ func (s *Server) stopStatsPump() {
 		s.statsCancel()
 		s.statsCancel = nil
 	}
+	s.lastStats = docker.StatsSnapshot{}
 }
 
 func (s *Server) runStatsPump(ctx context.Context) {
@@ -44,7 +48,22 @@ func (s *Server) runStatsPump(ctx context.Context) {
 		log.Printf("server %s: stats stream: %v", s.uuid, err)
 		return
 	}
+	var extra extraStats
+	extra.watch(ctx, s, s.statsTargets(s.Config()))
+	var filter statsFilter
+	if s.manager != nil {
+		filter.policy = s.manager.currentStatsPolicy()
+	}
 	for snap := range stream {
+		snap = extra.addTo(snap)
+		s.noteCPU(snap.CPUAbsolute)
+		s.statsMu.Lock()
+		s.lastStats = snap
+		s.statsMu.Unlock()
+		state := s.env.State()
+		if !filter.publish(snap, state, time.Now()) {
+			continue
+		}
 		s.statsMu.Lock()
 		started := s.startedAt
 		s.statsMu.Unlock()
@@ -52,27 +71,140 @@ func (s *Server) runStatsPump(ctx context.Context) {
 		if !started.IsZero() {
 			uptime = time.Since(started).Milliseconds()
 		}
-		frame, _ := json.Marshal(map[string]any{
-			"event": "stats",
-			"args": []any{
-				map[string]any{
-					"memory_bytes":       snap.MemoryBytes,
-					"memory_limit_bytes": snap.MemoryLimitBytes,
-					"cpu_absolute":       snap.CPUAbsolute,
-					"network": map[string]any{
-						"rx_bytes": snap.NetworkRxBytes,
-						"tx_bytes": snap.NetworkTxBytes,
-					},
-					"disk_bytes":       int64(0), // populated by a separate path; 0 is acceptable
-					"disk_read_bytes":  snap.DiskReadBytes,
-					"disk_write_bytes": snap.DiskWriteBytes,
-					"uptime_ms":        uptime,
-					"state":            string(s.env.State()),
+		s.bus.Publish(events.Encode("stats", []any{
+			map[string]any{
+				"memory_bytes":       snap.MemoryBytes,
+				"memory_limit_bytes": snap.MemoryLimitBytes,
+				"cpu_absolute":       snap.CPUAbsolute,
+				"network": map[string]any{
+					"rx_bytes": snap.NetworkRxBytes,
+					"tx_bytes": snap.NetworkTxBytes,
 				},
+				"disk_bytes":       int64(0), // populated by a separate path; 0 is acceptable
+				"disk_read_bytes":  snap.DiskReadBytes,
+				"disk_write_bytes": snap.DiskWriteBytes,
+				"uptime_ms":        uptime,
+				"state":            string(state),
+				"console_dropped":  s.bus.Dropped(),
 			},
-		})
-		s.bus.Publish(frame)
+		}))
+	}
+}
+
+// StatsPolicy is the change threshold below which a stats sample is
+// not republished. Zero deltas disable suppression.
+type StatsPolicy struct {
+	MemoryDeltaBytes  int64
+	CPUDelta          float64 // percentage points of cpu_absolute
+	NetworkDeltaBytes int64
+	DiskDeltaBytes    int64
+	// FullInterval forces a frame at least this often so a client that
+	// connected mid-stream never waits long for its first sample.
+	FullInterval time.Duration
+}
+
+func (p StatsPolicy) enabled() bool {
+	return p.MemoryDeltaBytes > 0 || p.CPUDelta > 0 || p.NetworkDeltaBytes > 0 || p.DiskDeltaBytes > 0
+}
+
+// statsFilter decides per sample whether it's worth a frame. Compares
+// against the last *published* sample so slow drift still crosses the
+// threshold eventually.
+type statsFilter struct {
+	policy    StatsPolicy
+	last      docker.StatsSnapshot
+	lastState environment.State
+	lastAt    time.Time
+}
+
+func (f *statsFilter) publish(snap docker.StatsSnapshot, state environment.State, now time.Time) bool {
+	p := f.policy
+	changed := !p.enabled() ||
+		f.lastAt.IsZero() ||
+		state != f.lastState ||
+		(p.FullInterval > 0 && now.Sub(f.lastAt) >= p.FullInterval) ||
+		exceeds(snap.MemoryBytes-f.last.MemoryBytes, p.MemoryDeltaBytes) ||
+		(p.CPUDelta > 0 && math.Abs(snap.CPUAbsolute-f.last.CPUAbsolute) >= p.CPUDelta) ||
+		exceeds(snap.NetworkRxBytes-f.last.NetworkRxBytes, p.NetworkDeltaBytes) ||
+		exceeds(snap.NetworkTxBytes-f.last.NetworkTxBytes, p.NetworkDeltaBytes) ||
+		exceeds(snap.DiskReadBytes-f.last.DiskReadBytes, p.DiskDeltaBytes) ||
+		exceeds(snap.DiskWriteBytes-f.last.DiskWriteBytes, p.DiskDeltaBytes)
+	if changed {
+		f.last = snap
+		f.lastState = state
+		f.lastAt = now
+	}
+	return changed
+}
+
+// exceeds reports |delta| >= threshold for an enabled (positive) threshold.
+func exceeds(delta, threshold int64) bool {
+	if threshold <= 0 {
+		return false
+	}
+	if delta < 0 {
+		delta = -delta
+	}
+	return delta >= threshold
+}
+
+// statsTarget is an extra container (sidecar or service) whose usage
+// counts towards the server's stats.
+type statsTarget struct {
+	name       string
+	ownNetwork bool
+}
+
+// extraStats keeps the latest sample of each extra container so the
+// main stats pump can fold them into the server's totals.
+type extraStats struct {
+	mu     sync.Mutex
+	latest map[string]docker.StatsSnapshot
+	netOwn map[string]bool
+}
+
+// watch streams stats for every target until ctx ends.
+func (st *extraStats) watch(ctx context.Context, s *Server, targets []statsTarget) {
+	st.latest = map[string]docker.StatsSnapshot{}
+	st.netOwn = map[string]bool{}
+	dc := s.env.Docker()
+	for _, t := range targets {
+		stream, err := dc.StatsStream(ctx, t.name)
+		if err != nil {
+			log.Printf("server %s: stats %s: %v", s.uuid, t.name, err)
+			continue
+		}
+		st.netOwn[t.name] = t.ownNetwork
+		go func() {
+			for snap := range stream {
+				st.mu.Lock()
+				st.latest[t.name] = snap
+				st.mu.Unlock()
+			}
+			st.mu.Lock()
+			delete(st.latest, t.name)
+			st.mu.Unlock()
+		}()
+	}
+}
+
+// addTo folds extra-container usage into the main container's sample.
+// Network counters are only added for containers with their own netns;
+// a sidecar reports the main container's interface totals.
+func (st *extraStats) addTo(snap docker.StatsSnapshot) docker.StatsSnapshot {
+	st.mu.Lock()
+	defer st.mu.Unlock()
+	for name, x := range st.latest {
+		snap.MemoryBytes += x.MemoryBytes
+		snap.CPUAbsolute += x.CPUAbsolute
+		snap.DiskReadBytes += x.DiskReadBytes
+		snap.DiskWriteBytes += x.DiskWriteBytes
+		if st.netOwn[name] {
+			snap.NetworkRxBytes += x.NetworkRxBytes
+			snap.NetworkTxBytes += x.NetworkTxBytes
+		}
 	}
+	return snap
 }
 
 // timeAfter is a thin wrapper so tests can stub time.After. Used by the
@@ -0,0 +1,229 @@
+package server
+
+import (
+	"bufio"
+	"context"
+	"errors"
+	"log"
+	"net"
+	"os"
+	"slices"
+	"strconv"
+	"syscall"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/docker"
+	"github.com/stellarstack/daemon/internal/environment"
+)
+
+// Wake-on-connect: a stopped server whose idle policy has StartOnDemand
+// keeps its allocation's TCP ports open in the daemon, and the first
+// real connection queues a start. The listeners close on the move to
+// starting, before the port check, so Docker takes the ports over.
+//
+// With WakeMessage set, Minecraft clients get an answer while the
+// server sleeps: a server list ping shows the message without waking
+// anything, and a join attempt starts the server and is disconnected
+// with a note to reconnect. A connection that closes without sending a
+// byte (a port check) never wakes the server.
+
+const (
+	// wakeReadTimeout bounds the wait for a client's first bytes. A
+	// client that stays silent this long speaks second; it wakes the
+	// server.
+	wakeReadTimeout = 5 * time.Second
+	// wakeBindAttempts covers docker-proxy letting go of the ports
+	// just after the container stops.
+	wakeBindAttempts = 5
+	wakeBindBackoff  = 2 * time.Second
+
+	wakeDisconnect = "The server is starting, please reconnect in a moment."
+)
+
+// armWake opens the wake listeners if the policy asks for them and the
+// server is offline. Idempotent. The listeners need the daemon on the
+// host's network: in a bridge-networked container they'd open in its
+// own namespace where no player reaches them, so there the feature is
+// off (the node doesn't advertise it) and the console says why.
+func (s *Server) armWake() {
+	cfg := s.Config()
+	if !cfg.Idle.StartOnDemand || s.env.State() != environment.StateOffline {
+		return
+	}
+	addrs := wakeAddrs(cfg.PortMappings)
+	if len(addrs) == 0 {
+		return
+	}
+	if !s.probesHostPorts() {
+		s.publishDaemon("Wake-on-connect is unavailable: the daemon isn't on the host network.")
+		return
+	}
+	s.wakeMu.Lock()
+	if len(s.wakeListeners) > 0 {
+		s.wakeMu.Unlock()
+		return
+	}
+	s.wakeGen++
+	gen := s.wakeGen
+	s.wakeMu.Unlock()
+
+	var bound []net.Listener
+	for _, addr := range addrs {
+		ln, err := s.listenWake(addr, gen)
+		if err != nil {
+			log.Printf("server %s: wake: listen %s: %v", s.uuid, addr, err)
+			if !errors.Is(err, errWakeSuperseded) {
+				s.publishDaemon("Wake-on-connect couldn't listen on " + addr + ": " + err.Error())
+			}
+			continue
+		}
+		bound = append(bound, ln)
+	}
+	s.wakeMu.Lock()
+	defer s.wakeMu.Unlock()
+	// A start (or another arm) got in while we were binding.
+	if s.wakeGen != gen || s.env.State() != environment.StateOffline {
+		for _, ln := range bound {
+			_ = ln.Close()
+		}
+		return
+	}
+	s.wakeListeners = bound
+	for _, ln := range bound {
+		go s.serveWake(ln, cfg.Idle)
+	}
+	if len(bound) > 0 {
+		log.Printf("server %s: wake: listening on %d port(s)", s.uuid, len(bound))
+	}
+}
+
+// errWakeSuperseded: a disarm or newer arm took over while listenWake
+// was waiting for a port.
+var errWakeSuperseded = errors.New("superseded")
+
+// listenWake binds addr, retrying while the port is still in use
+// unless a disarm supersedes gen.
+func (s *Server) listenWake(addr string, gen uint64) (net.Listener, error) {
+	for attempt := 1; ; attempt++ {
+		ln, err := net.Listen("tcp", addr)
+		if err == nil || !errors.Is(err, syscall.EADDRINUSE) || attempt == wakeBindAttempts {
+			return ln, err
+		}
+		time.Sleep(wakeBindBackoff)
+		s.wakeMu.Lock()
+		stale := s.wakeGen != gen
+		s.wakeMu.Unlock()
+		if stale {
+			return nil, errWakeSuperseded
+		}
+	}
+}
+
+// disarmWake closes the wake listeners. Safe to call when none are
+// open.
+func (s *Server) disarmWake() {
+	s.wakeMu.Lock()
+	defer s.wakeMu.Unlock()
+	s.wakeGen++
+	for _, ln := range s.wakeListeners {
+		_ = ln.Close()
+	}
+	s.wakeListeners = nil
+}
+
+// holdsWakePort reports whether a wake listener is bound to host port.
+func (s *Server) holdsWakePort(port int) bool {
+	s.wakeMu.Lock()
+	defer s.wakeMu.Unlock()
+	for _, ln := range s.wakeListeners {
+		if a, ok := ln.Addr().(*net.TCPAddr); ok && a.Port == port {
+			return true
+		}
+	}
+	return false
+}
+
+func (s *Server) serveWake(ln net.Listener, p IdlePolicy) {
+	for {
+		conn, err := ln.Accept()
+		if err != nil {
+			return // closed by disarmWake
+		}
+		go s.handleWakeConn(conn, p)
+	}
+}
+
+func (s *Server) handleWakeConn(conn net.Conn, p IdlePolicy) {
+	defer conn.Close()
+	_ = conn.SetDeadline(time.Now().Add(wakeReadTimeout))
+	rw := bufio.NewReadWriter(bufio.NewReader(conn), bufio.NewWriter(conn))
+	first, err := rw.Peek(1)
+	if err != nil {
+		if errors.Is(err, os.ErrDeadlineExceeded) {
+			s.wake(conn.RemoteAddr())
+		}
+		return
+	}
+	if p.WakeMessage == "" {
+		s.wake(conn.RemoteAddr())
+		return
+	}
+	if first[0] == mcLegacyPing {
+		return // pre-1.7 server list ping
+	}
+	hs, err := readMCHandshake(rw.Reader)
+	if err != nil {
+		s.wake(conn.RemoteAddr())
+		return
+	}
+	if hs.NextState == mcStateStatus {
+		_ = serveMCStatus(rw, hs.Protocol, p.WakeMessage)
+		return
+	}
+	s.wake(conn.RemoteAddr())
+	_ = writeMCDisconnect(rw.Writer, wakeDisconnect)
+}
+
+// wake queues a start on behalf of a connection from remote.
+func (s *Server) wake(remote net.Addr) {
+	var prepare func(context.Context) error
+	if s.manager != nil {
+		if syncConfig := s.manager.configSync(); syncConfig != nil {
+			prepare = func(ctx context.Context) error { return syncConfig(ctx, s) }
+		}
+	}
+	_, err := s.EnqueuePower(PowerStart, prepare)
+	if errors.Is(err, ErrPowerInProgress) {
+		return
+	}
+	if err != nil {
+		log.Printf("server %s: wake: queue start: %v", s.uuid, err)
+		return
+	}
+	host, _, _ := net.SplitHostPort(remote.String())
+	s.publishDaemon("Connection from " + host + ", starting the server.")
+	log.Printf("server %s: wake: connection from %s; starting", s.uuid, host)
+	if s.panel == nil {
+		return
+	}
+	go func() {
+		ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+		defer cancel()
+		_ = s.panel.PushAudit(ctx, s.uuid, "", "servers.lifecycle.woken", map[string]any{
+			"remoteIp": host,
+		})
+	}()
+}
+
+// wakeAddrs is one listen address per TCP mapping: the first port of a
+// block is enough to catch a client.
+func wakeAddrs(mappings []docker.PortMapping) []string {
+	var out []string
+	for _, pm := range mappings {
+		if !slices.Contains(pm.Protocols(), "tcp") {
+			continue
+		}
+		out = append(out, net.JoinHostPort(pm.HostIP, strconv.Itoa(pm.HostPort)))
+	}
+	return out
+}
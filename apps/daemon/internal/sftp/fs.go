@@ -4,19 +4,31 @@ import (
 	"errors"
 	"io"
 	"os"
+	"path"
 	"path/filepath"
 	"time"
 
 	pkgsftp "github.com/pkg/sftp"
+
+	"github.com/stellarstack/daemon/internal/filepolicy"
 )
 
 // chrootFS implements pkg/sftp's Handlers contract against a confined
 // directory tree. Every supplied path is resolved through `resolve`
 // before any os.* call so the SFTP client cannot escape `root` via
-// `..` or absolute paths.
+// `..` or absolute paths, nor leave the session's path scope.
 type chrootFS struct {
-	root    string
-	resolve func(string) (string, error)
+	root      string
+	resolve   func(string) (string, error) // jail + scope
+	jail      func(string) (string, error) // jail only
+	scope     pathScope
+	writes    WriteOptions
+	diskLimit func() int64 // bytes; nil or 0 = none
+	usage     *usageCache
+	policy    *filepolicy.Policy
+	rules     *filepolicy.PathRules
+	onBlocked func(path, reason string) // nil = unreported
+	scan      func(abs, rel, source string) error
 }
 
 func (f *chrootFS) Fileread(req *pkgsftp.Request) (io.ReaderAt, error) {
@@ -32,10 +44,24 @@ func (f *chrootFS) Filewrite(req *pkgsftp.Request) (io.WriterAt, error) {
 	if err != nil {
 		return nil, err
 	}
+	if err := f.checkName(req.Filepath); err != nil {
+		return nil, err
+	}
 	if err := os.MkdirAll(filepath.Dir(abs), 0o755); err != nil {
 		return nil, err
 	}
-	return os.OpenFile(abs, os.O_WRONLY|os.O_CREATE|os.O_TRUNC, 0o644)
+	file, err := os.OpenFile(abs, os.O_WRONLY|os.O_CREATE|os.O_TRUNC, 0o644)
+	if err != nil {
+		return nil, err
+	}
+	var w io.WriterAt = file
+	if f.writes.BufferBytes > 0 {
+		w = newBufferedWriterAt(file, f.writes.BufferBytes, f.writes.WriteBehind)
+	}
+	if f.policy != nil || f.scan != nil {
+		w = &policedWriterAt{WriterAt: w, fs: f, rel: path.Clean("/" + req.Filepath), abs: abs}
+	}
+	return w, nil
 }
 
 func (f *chrootFS) Filecmd(req *pkgsftp.Request) error {
@@ -50,14 +76,7 @@ func (f *chrootFS) Filecmd(req *pkgsftp.Request) error {
 		// is rarely meaningful and gives a tidy default for clients.
 		return nil
 	case "Rename":
-		target, err := f.resolve(req.Target)
-		if err != nil {
-			return err
-		}
-		if err := os.MkdirAll(filepath.Dir(target), 0o755); err != nil {
-			return err
-		}
-		return os.Rename(abs, target)
+		return f.rename(abs, req.Target)
 	case "Rmdir":
 		return os.Remove(abs)
 	case "Mkdir":
@@ -74,19 +93,85 @@ func (f *chrootFS) Filecmd(req *pkgsftp.Request) error {
 	return errors.New("unsupported method: " + req.Method)
 }
 
+// PosixRename answers posix-rename@openssh.com: rename(2) semantics,
+// atomically replacing an existing target. Clients use it to upload to
+// a temp name and swap the file in, so a reader never sees it half
+// written.
+func (f *chrootFS) PosixRename(req *pkgsftp.Request) error {
+	abs, err := f.resolve(req.Filepath)
+	if err != nil {
+		return err
+	}
+	return f.rename(abs, req.Target)
+}
+
+// rename moves abs to the jailed target. os.Rename already replaces
+// atomically, so plain SFTP rename gets the same semantics.
+func (f *chrootFS) rename(abs, target string) error {
+	dst, err := f.resolve(target)
+	if err != nil {
+		return err
+	}
+	if err := f.checkName(target); err != nil {
+		return err
+	}
+	if err := os.MkdirAll(filepath.Dir(dst), 0o755); err != nil {
+		return err
+	}
+	return os.Rename(abs, dst)
+}
+
+// browse resolves a path for List and Stat, which a scoped session may
+// also use on the directories above its prefixes. above is true for
+// those, and their listings must be filtered through the scope.
+func (f *chrootFS) browse(p string) (abs string, above bool, err error) {
+	clean := path.Clean("/" + p)
+	if !f.scope.allows(clean) && f.scope.leadsTo(clean) {
+		abs, err = f.jail(p)
+		return abs, true, err
+	}
+	abs, err = f.resolve(p)
+	return abs, false, err
+}
+
 func (f *chrootFS) Filelist(req *pkgsftp.Request) (pkgsftp.ListerAt, error) {
+	if req.Method == "List" || req.Method == "Stat" {
+		return f.browseList(req)
+	}
+	if req.Method != "Readlink" {
+		return nil, errors.New("unsupported list method: " + req.Method)
+	}
 	abs, err := f.resolve(req.Filepath)
 	if err != nil {
 		return nil, err
 	}
+	target, err := os.Readlink(abs)
+	if err != nil {
+		return nil, err
+	}
+	return listerAt([]os.FileInfo{readlinkInfo(target)}), nil
+}
+
+func (f *chrootFS) browseList(req *pkgsftp.Request) (pkgsftp.ListerAt, error) {
+	abs, above, err := f.browse(req.Filepath)
+	if err != nil {
+		return nil, err
+	}
 	switch req.Method {
 	case "List":
 		entries, err := os.ReadDir(abs)
 		if err != nil {
 			return nil, err
 		}
+		dir := path.Clean("/" + req.Filepath)
 		infos := make([]os.FileInfo, 0, len(entries))
 		for _, e := range entries {
+			if above && !f.scope.visible(dir, e.Name()) {
+				continue
+			}
+			if f.rules.Hidden(path.Join(dir, e.Name()), e.IsDir()) {
+				continue
+			}
 			info, err := e.Info()
 			if err != nil {
 				continue
@@ -100,12 +185,6 @@ func (f *chrootFS) Filelist(req *pkgsftp.Request) (pkgsftp.ListerAt, error) {
 			return nil, err
 		}
 		return listerAt([]os.FileInfo{info}), nil
-	case "Readlink":
-		target, err := os.Readlink(abs)
-		if err != nil {
-			return nil, err
-		}
-		return listerAt([]os.FileInfo{readlinkInfo(target)}), nil
 	}
 	return nil, errors.New("unsupported list method: " + req.Method)
 }
@@ -0,0 +1,245 @@
+package sftp
+
+import (
+	"crypto/rand"
+	"encoding/hex"
+	"net"
+	"sort"
+	"sync"
+	"time"
+
+	"golang.org/x/crypto/ssh"
+)
+
+// Limits protect a listener exposed to the internet. Zero values
+// disable that protection.
+type Limits struct {
+	MaxConnsPerIP int           // concurrent connections from one address, authenticated or not
+	MaxFailures   int           // failed logins within FailWindow before a ban
+	FailWindow    time.Duration // how long a failed login counts against an address
+	BanFor        time.Duration // how long a banned address is refused
+}
+
+// guard enforces Limits and tracks live sessions. Everything is in
+// memory: bans don't survive a restart, which is fine for stopping
+// password sprayers and keeps a mistyped token from locking anyone out
+// for long.
+type guard struct {
+	limits Limits
+
+	mu       sync.Mutex
+	conns    map[string]int         // ip → open connections
+	failures map[string][]time.Time // ip → failed logins in the window
+	bans     map[string]time.Time   // ip → banned until
+	sessions map[string]*session
+}
+
+// session is one authenticated SSH connection.
+type session struct {
+	id       string
+	serverID string
+	userID   string
+	started  time.Time
+	conn     ssh.Conn
+}
+
+// SessionInfo is the admin view of a live session.
+type SessionInfo struct {
+	ID         string    `json:"id"`
+	ServerID   string    `json:"serverId"`
+	UserID     string    `json:"userId"`
+	RemoteAddr string    `json:"remoteAddr"`
+	StartedAt  time.Time `json:"startedAt"`
+}
+
+// BanInfo is the admin view of a banned address.
+type BanInfo struct {
+	IP    string    `json:"ip"`
+	Until time.Time `json:"until"`
+}
+
+func newGuard(limits Limits) *guard {
+	return &guard{
+		limits:   limits,
+		conns:    map[string]int{},
+		failures: map[string][]time.Time{},
+		bans:     map[string]time.Time{},
+		sessions: map[string]*session{},
+	}
+}
+
+// hostOf strips the port from a remote address.
+func hostOf(addr net.Addr) string {
+	host, _, err := net.SplitHostPort(addr.String())
+	if err != nil {
+		return addr.String()
+	}
+	return host
+}
+
+// admit counts a new connection from ip, or refuses it when the address
+// is banned or already at its connection limit. Every admitted
+// connection must be released.
+func (g *guard) admit(ip string) bool {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	if until, ok := g.bans[ip]; ok {
+		if time.Now().Before(until) {
+			return false
+		}
+		delete(g.bans, ip)
+	}
+	if g.limits.MaxConnsPerIP > 0 && g.conns[ip] >= g.limits.MaxConnsPerIP {
+		return false
+	}
+	g.conns[ip]++
+	return true
+}
+
+func (g *guard) release(ip string) {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	if g.conns[ip] <= 1 {
+		delete(g.conns, ip)
+		return
+	}
+	g.conns[ip]--
+}
+
+// fail records a failed login from ip and returns how long to stall the
+// reply: growing with each recent failure, so a client guessing tokens
+// gets slower long before it's banned.
+func (g *guard) fail(ip string) time.Duration {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	now := time.Now()
+	recent := g.recentLocked(ip, now)
+	recent = append(recent, now)
+	g.failures[ip] = recent
+	if g.limits.MaxFailures > 0 && len(recent) >= g.limits.MaxFailures && g.limits.BanFor > 0 {
+		g.bans[ip] = now.Add(g.limits.BanFor)
+		delete(g.failures, ip)
+	}
+	return min(time.Duration(len(recent))*250*time.Millisecond, 3*time.Second)
+}
+
+// succeed clears ip's failure count.
+func (g *guard) succeed(ip string) {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	delete(g.failures, ip)
+}
+
+// recentLocked is ip's failures still inside the window.
+func (g *guard) recentLocked(ip string, now time.Time) []time.Time {
+	all := g.failures[ip]
+	i := 0
+	for i < len(all) && g.limits.FailWindow > 0 && now.Sub(all[i]) > g.limits.FailWindow {
+		i++
+	}
+	return all[i:]
+}
+
+// prune drops expired bans and failures so addresses that stop
+// connecting don't accumulate.
+func (g *guard) prune() {
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	now := time.Now()
+	for ip, until := range g.bans {
+		if now.After(until) {
+			delete(g.bans, ip)
+		}
+	}
+	for ip := range g.failures {
+		if recent := g.recentLocked(ip, now); len(recent) == 0 {
+			delete(g.failures, ip)
+		} else {
+			g.failures[ip] = recent
+		}
+	}
+}
+
+// track registers an authenticated connection and returns the function
+// that removes it.
+func (g *guard) track(conn ssh.Conn, serverID, userID string) func() {
+	var b [8]byte
+	_, _ = rand.Read(b[:])
+	s := &session{
+		id:       hex.EncodeToString(b[:]),
+		serverID: serverID,
+		userID:   userID,
+		started:  time.Now(),
+		conn:     conn,
+	}
+	g.mu.Lock()
+	g.sessions[s.id] = s
+	g.mu.Unlock()
+	return func() {
+		g.mu.Lock()
+		delete(g.sessions, s.id)
+		g.mu.Unlock()
+	}
+}
+
+// Sessions lists live sessions, oldest first.
+func (s *Server) Sessions() []SessionInfo {
+	g := s.guard
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	out := make([]SessionInfo, 0, len(g.sessions))
+	for _, sess := range g.sessions {
+		out = append(out, SessionInfo{
+			ID:         sess.id,
+			ServerID:   sess.serverID,
+			UserID:     sess.userID,
+			RemoteAddr: sess.conn.RemoteAddr().String(),
+			StartedAt:  sess.started,
+		})
+	}
+	sort.Slice(out, func(i, j int) bool { return out[i].StartedAt.Before(out[j].StartedAt) })
+	return out
+}
+
+// Bans lists addresses currently refused.
+func (s *Server) Bans() []BanInfo {
+	g := s.guard
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	now := time.Now()
+	out := make([]BanInfo, 0, len(g.bans))
+	for ip, until := range g.bans {
+		if now.Before(until) {
+			out = append(out, BanInfo{IP: ip, Until: until})
+		}
+	}
+	sort.Slice(out, func(i, j int) bool { return out[i].IP < out[j].IP })
+	return out
+}
+
+// Kill closes a session. False if no such session is live.
+func (s *Server) Kill(id string) bool {
+	g := s.guard
+	g.mu.Lock()
+	sess, ok := g.sessions[id]
+	g.mu.Unlock()
+	if !ok {
+		return false
+	}
+	// handleConn's deferred cleanup removes it from the registry.
+	_ = sess.conn.Close()
+	return true
+}
+
+// Unban lifts a ban early. False if ip wasn't banned.
+func (s *Server) Unban(ip string) bool {
+	g := s.guard
+	g.mu.Lock()
+	defer g.mu.Unlock()
+	if _, ok := g.bans[ip]; !ok {
+		return false
+	}
+	delete(g.bans, ip)
+	delete(g.failures, ip)
+	return true
+}
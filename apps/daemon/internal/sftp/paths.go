@@ -0,0 +1,57 @@
+package sftp
+
+import (
+	"path"
+	"strings"
+)
+
+// pathScope narrows a session to some directories of the server, for
+// subusers the panel has limited to e.g. /plugins/MyPlugin. Entries are
+// clean absolute virtual paths; an empty scope is the whole server.
+//
+// Inside a prefix everything is allowed (subject to the token's other
+// checks). The directories above a prefix can be listed and stat'ed so
+// clients can navigate down to it, but listings only show the entries
+// on the way, and nothing in them can be read, written or renamed.
+type pathScope []string
+
+// parseScope reads the newline-separated prefixes carried in the SSH
+// permissions extensions.
+func parseScope(s string) pathScope {
+	var out pathScope
+	for _, p := range strings.Split(s, "\n") {
+		if p = strings.TrimSpace(p); p != "" {
+			out = append(out, path.Clean("/"+p))
+		}
+	}
+	return out
+}
+
+// allows reports whether clean is a prefix or inside one.
+func (s pathScope) allows(clean string) bool {
+	if len(s) == 0 {
+		return true
+	}
+	for _, p := range s {
+		if p == "/" || clean == p || strings.HasPrefix(clean, p+"/") {
+			return true
+		}
+	}
+	return false
+}
+
+// leadsTo reports whether clean is a directory above some prefix.
+func (s pathScope) leadsTo(clean string) bool {
+	for _, p := range s {
+		if clean == "/" || strings.HasPrefix(p, clean+"/") {
+			return true
+		}
+	}
+	return false
+}
+
+// visible reports whether a listing of dir should include name.
+func (s pathScope) visible(dir, name string) bool {
+	child := path.Join(dir, name)
+	return s.allows(child) || s.leadsTo(child)
+}
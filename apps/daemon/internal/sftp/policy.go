@@ -0,0 +1,68 @@
+package sftp
+
+import (
+	"io"
+	"os"
+	"path"
+	"sync"
+
+	"github.com/stellarstack/daemon/internal/filepolicy"
+)
+
+// refuse reports err, the node's file policy refusing the session-
+// relative p, and returns it.
+func (f *chrootFS) refuse(p string, err error) error {
+	if f.onBlocked != nil {
+		go f.onBlocked(path.Clean("/"+p), err.Error())
+	}
+	return err
+}
+
+// checkName is the policy's name check for p.
+func (f *chrootFS) checkName(p string) error {
+	if err := f.policy.CheckName(path.Clean("/" + p)); err != nil {
+		return f.refuse(p, err)
+	}
+	return nil
+}
+
+// policedWriterAt keeps a copy of the first bytes written to a file and
+// checks them against the policy when the client closes the handle,
+// then has the finished file scanned. A refused file is deleted (or, if
+// flagged, quarantined) and the error becomes the close status, which
+// is what the client reports as the upload's outcome.
+type policedWriterAt struct {
+	io.WriterAt
+	fs  *chrootFS
+	rel string
+	abs string
+
+	mu   sync.Mutex // guards head and n
+	head [filepolicy.SniffBytes]byte
+	n    int
+}
+
+func (w *policedWriterAt) WriteAt(p []byte, off int64) (int, error) {
+	if off < int64(len(w.head)) {
+		w.mu.Lock()
+		n := copy(w.head[off:], p)
+		w.n = max(w.n, int(off)+n)
+		w.mu.Unlock()
+	}
+	return w.WriterAt.WriteAt(p, off)
+}
+
+func (w *policedWriterAt) Close() error {
+	var err error
+	if c, ok := w.WriterAt.(io.Closer); ok {
+		err = c.Close()
+	}
+	if perr := w.fs.policy.CheckContent(w.rel, w.head[:w.n]); perr != nil {
+		os.Remove(w.abs)
+		return w.fs.refuse(w.rel, perr)
+	}
+	if err == nil && w.fs.scan != nil {
+		err = w.fs.scan(w.abs, w.rel, "sftp")
+	}
+	return err
+}
@@ -0,0 +1,469 @@
+package sftp
+
+import (
+	"bufio"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"path"
+	"path/filepath"
+	"strconv"
+	"strings"
+	"time"
+
+	"github.com/stellarstack/daemon/internal/filepolicy"
+)
+
+// SCP over an exec channel: the legacy protocol `scp -O` speaks, and
+// that every OpenSSH before 9.0 uses by default (newer clients already
+// go through the SFTP subsystem). Only the two server-side modes are
+// accepted — `scp -t` (sink: client uploads) and `scp -f` (source:
+// client downloads) — and every path goes through the session's jail,
+// so scp sees exactly what SFTP does. Nothing is run through a shell.
+//
+// Protocol, per line from the sending side, each acked with a 0 byte:
+//
+//	C<mode> <size> <name>\n  then <size> bytes and a 0 byte
+//	D<mode> 0 <name>\n       enter a directory (recursive copies)
+//	E\n                      leave it
+//	T<mtime> 0 <atime> 0\n   times for the next C/D (-p)
+//
+// Errors are a 1 (warning) or 2 (fatal) byte followed by a message line.
+
+// scpCommand is a parsed `scp` exec request.
+type scpCommand struct {
+	sink      bool // -t
+	recursive bool // -r
+	targetDir bool // -d
+	preserve  bool // -p
+	paths     []string
+}
+
+// parseSCP parses an exec command line. ok is false for anything that
+// isn't a server-side scp invocation.
+func parseSCP(command string) (scpCommand, bool) {
+	args := splitWords(command)
+	if len(args) < 2 || path.Base(args[0]) != "scp" {
+		return scpCommand{}, false
+	}
+	var c scpCommand
+	mode := false
+	for i, a := range args[1:] {
+		if !strings.HasPrefix(a, "-") || a == "-" {
+			c.paths = args[1+i:]
+			break
+		}
+		if a == "--" {
+			c.paths = args[2+i:]
+			break
+		}
+		for _, f := range a[1:] {
+			switch f {
+			case 't':
+				c.sink, mode = true, true
+			case 'f':
+				mode = true
+			case 'r':
+				c.recursive = true
+			case 'd':
+				c.targetDir = true
+			case 'p':
+				c.preserve = true
+			case 'v', 'q':
+			default:
+				return scpCommand{}, false
+			}
+		}
+	}
+	if !mode || len(c.paths) == 0 {
+		return scpCommand{}, false
+	}
+	return c, true
+}
+
+// splitWords splits like a POSIX shell would for the quoting scp
+// clients emit (single quotes, double quotes, backslashes), with no
+// expansion of any kind.
+func splitWords(s string) []string {
+	var (
+		out   []string
+		cur   strings.Builder
+		in    bool
+		quote rune
+		esc   bool
+	)
+	for _, r := range s {
+		switch {
+		case esc:
+			cur.WriteRune(r)
+			esc = false
+		case r == '\\' && quote != '\'':
+			esc, in = true, true
+		case quote != 0 && r == quote:
+			quote = 0
+		case quote != 0:
+			cur.WriteRune(r)
+		case r == '\'' || r == '"':
+			quote, in = r, true
+		case r == ' ' || r == '\t' || r == '\n':
+			if in {
+				out = append(out, cur.String())
+				cur.Reset()
+				in = false
+			}
+		default:
+			cur.WriteRune(r)
+			in = true
+		}
+	}
+	if in {
+		out = append(out, cur.String())
+	}
+	return out
+}
+
+// scpSession runs one scp transfer against the jail.
+type scpSession struct {
+	fs  *chrootFS
+	rw  io.ReadWriter
+	in  *bufio.Reader
+	cmd scpCommand
+}
+
+// serveSCP runs the transfer and returns the exit status for the client.
+func serveSCP(rw io.ReadWriter, stderr io.Writer, fs *chrootFS, cmd scpCommand) int {
+	s := &scpSession{fs: fs, rw: rw, in: bufio.NewReader(rw), cmd: cmd}
+	var err error
+	if cmd.sink {
+		err = s.sink()
+	} else {
+		err = s.source()
+	}
+	if err != nil && !errors.Is(err, io.EOF) {
+		fmt.Fprintf(stderr, "scp: %v\n", err)
+		return 1
+	}
+	return 0
+}
+
+// sink receives uploads into the single target: files land inside it
+// when it's a directory, or are written as it otherwise.
+func (s *scpSession) sink() error {
+	if len(s.cmd.paths) != 1 {
+		return s.fatal("ambiguous target")
+	}
+	target := s.cmd.paths[0]
+	abs, err := s.fs.resolve(target)
+	if err != nil {
+		return s.fatal(err.Error())
+	}
+	isDir := false
+	if st, err := os.Stat(abs); err == nil && st.IsDir() {
+		isDir = true
+	} else if s.cmd.targetDir {
+		return s.fatal(target + ": not a directory")
+	}
+	if err := s.ack(); err != nil {
+		return err
+	}
+
+	// dirs is the stack of virtual directories entered with D.
+	dirs := []string{}
+	if isDir {
+		dirs = append(dirs, target)
+	}
+	var times *[2]time.Time
+	for {
+		line, err := s.in.ReadString('\n')
+		if err != nil {
+			return err
+		}
+		line = strings.TrimSuffix(line, "\n")
+		if line == "" {
+			return s.fatal("protocol error: empty line")
+		}
+		switch line[0] {
+		case 'T':
+			t, err := parseSCPTimes(line[1:])
+			if err != nil {
+				return s.fatal(err.Error())
+			}
+			times = &t
+			if err := s.ack(); err != nil {
+				return err
+			}
+		case 'D':
+			if !s.cmd.recursive {
+				return s.fatal("received directory without -r")
+			}
+			_, _, name, err := parseSCPHeader(line[1:])
+			if err != nil {
+				return s.fatal(err.Error())
+			}
+			dir := s.childPath(dirs, target, name)
+			dirAbs, err := s.fs.resolve(dir)
+			if err != nil {
+				return s.fatal(err.Error())
+			}
+			if err := os.MkdirAll(dirAbs, 0o755); err != nil {
+				return s.fatal(err.Error())
+			}
+			s.applyTimes(dirAbs, times)
+			times = nil
+			dirs = append(dirs, dir)
+			if err := s.ack(); err != nil {
+				return err
+			}
+		case 'E':
+			if len(dirs) == 0 || (isDir && len(dirs) == 1) {
+				return s.fatal("protocol error: unbalanced E")
+			}
+			dirs = dirs[:len(dirs)-1]
+			if err := s.ack(); err != nil {
+				return err
+			}
+		case 'C':
+			mode, size, name, err := parseSCPHeader(line[1:])
+			if err != nil {
+				return s.fatal(err.Error())
+			}
+			if err := s.receiveFile(s.childPath(dirs, target, name), mode, size, times); err != nil {
+				return err
+			}
+			times = nil
+		case 1, 2:
+			// The client reporting its own error; a fatal one ends it.
+			if line[0] == 2 {
+				return io.EOF
+			}
+		default:
+			return s.fatal("protocol error: unexpected " + strconv.Quote(line[:1]))
+		}
+	}
+}
+
+// childPath is where an incoming entry lands: inside the current D (or
+// the target directory), or at the target itself for a single file.
+func (s *scpSession) childPath(dirs []string, target, name string) string {
+	if len(dirs) == 0 {
+		return target
+	}
+	return path.Join(dirs[len(dirs)-1], name)
+}
+
+func (s *scpSession) receiveFile(virtual string, mode os.FileMode, size int64, times *[2]time.Time) error {
+	abs, err := s.fs.resolve(virtual)
+	if err != nil {
+		return s.fatal(err.Error())
+	}
+	if err := s.fs.checkName(virtual); err != nil {
+		return s.fatal(err.Error())
+	}
+	if err := os.MkdirAll(filepath.Dir(abs), 0o755); err != nil {
+		return s.fatal(err.Error())
+	}
+	f, err := os.OpenFile(abs, os.O_WRONLY|os.O_CREATE|os.O_TRUNC, mode|0o600)
+	if err != nil {
+		return s.fatal(err.Error())
+	}
+	if err := s.ack(); err != nil {
+		f.Close()
+		return err
+	}
+	// The body's first bytes are still in s.in's buffer; check them
+	// before anything is written.
+	head, _ := s.in.Peek(int(min(size, filepolicy.SniffBytes)))
+	if err := s.fs.policy.CheckContent(path.Clean("/"+virtual), head); err != nil {
+		f.Close()
+		os.Remove(abs)
+		if _, err := io.CopyN(io.Discard, s.in, size); err != nil {
+			return err
+		}
+		_ = s.readAck()
+		return s.fatal(s.fs.refuse(virtual, err).Error())
+	}
+	_, copyErr := io.CopyN(f, s.in, size)
+	closeErr := f.Close()
+	if copyErr != nil {
+		return copyErr
+	}
+	if err := s.readAck(); err != nil {
+		return err
+	}
+	if closeErr != nil {
+		return s.fatal(closeErr.Error())
+	}
+	if s.fs.scan != nil {
+		if err := s.fs.scan(abs, path.Clean("/"+virtual), "scp"); err != nil {
+			return s.fatal(err.Error())
+		}
+	}
+	s.applyTimes(abs, times)
+	return s.ack()
+}
+
+func (s *scpSession) applyTimes(abs string, times *[2]time.Time) {
+	if times != nil && s.cmd.preserve {
+		_ = os.Chtimes(abs, times[1], times[0])
+	}
+}
+
+func (s *scpSession) source() error {
+	if err := s.readAck(); err != nil {
+		return err
+	}
+	var failed error
+	for _, p := range s.cmd.paths {
+		virtual := path.Clean("/" + p)
+		abs, err := s.fs.resolve(virtual)
+		if err != nil {
+			failed = s.warn(err.Error())
+			continue
+		}
+		st, err := os.Lstat(abs)
+		if err != nil {
+			failed = s.warn(p + ": no such file or directory")
+			continue
+		}
+		if err := s.send(virtual, abs, st); err != nil {
+			return err
+		}
+	}
+	return failed
+}
+
+// send sends one file, or with -r a directory tree. virtual is abs as
+// the session sees it. Symlinks are never followed.
+func (s *scpSession) send(virtual, abs string, st os.FileInfo) error {
+	if s.cmd.preserve {
+		mt := st.ModTime().Unix()
+		if err := s.header(fmt.Sprintf("T%d 0 %d 0\n", mt, mt)); err != nil {
+			return err
+		}
+	}
+	if st.IsDir() {
+		if !s.cmd.recursive {
+			return s.warn(st.Name() + ": is a directory")
+		}
+		if err := s.header(fmt.Sprintf("D%04o 0 %s\n", st.Mode().Perm(), st.Name())); err != nil {
+			return err
+		}
+		entries, err := os.ReadDir(abs)
+		if err != nil {
+			return s.warn(err.Error())
+		}
+		for _, e := range entries {
+			// Each child goes through the jail like a path the client
+			// named: hidden and denied entries and symlinks are left out.
+			child := path.Join(virtual, e.Name())
+			if s.fs.rules.Hidden(child, e.IsDir()) {
+				continue
+			}
+			childAbs, err := s.fs.resolve(child)
+			if err != nil {
+				continue
+			}
+			info, err := os.Lstat(childAbs)
+			if err != nil || info.Mode()&os.ModeSymlink != 0 {
+				continue
+			}
+			if err := s.send(child, childAbs, info); err != nil {
+				return err
+			}
+		}
+		return s.header("E\n")
+	}
+	if !st.Mode().IsRegular() {
+		return s.warn(st.Name() + ": not a regular file")
+	}
+	f, err := os.Open(abs)
+	if err != nil {
+		return s.warn(err.Error())
+	}
+	defer f.Close()
+	if err := s.header(fmt.Sprintf("C%04o %d %s\n", st.Mode().Perm(), st.Size(), st.Name())); err != nil {
+		return err
+	}
+	if _, err := io.CopyN(s.rw, f, st.Size()); err != nil {
+		return err
+	}
+	if err := s.ack(); err != nil {
+		return err
+	}
+	return s.readAck()
+}
+
+// header sends one protocol line and waits for the client's ack.
+func (s *scpSession) header(line string) error {
+	if _, err := io.WriteString(s.rw, line); err != nil {
+		return err
+	}
+	return s.readAck()
+}
+
+func (s *scpSession) ack() error {
+	_, err := s.rw.Write([]byte{0})
+	return err
+}
+
+// readAck reads the peer's 0 byte, or its error line.
+func (s *scpSession) readAck() error {
+	b, err := s.in.ReadByte()
+	if err != nil {
+		return err
+	}
+	if b == 0 {
+		return nil
+	}
+	msg, _ := s.in.ReadString('\n')
+	return fmt.Errorf("client: %s", strings.TrimSpace(msg))
+}
+
+// warn reports a non-fatal error to the client and returns it so the
+// exit status reflects it.
+func (s *scpSession) warn(msg string) error {
+	_, _ = fmt.Fprintf(s.rw, "\x01scp: %s\n", msg)
+	return errors.New(msg)
+}
+
+// fatal reports an error that ends the transfer.
+func (s *scpSession) fatal(msg string) error {
+	_, _ = fmt.Fprintf(s.rw, "\x02scp: %s\n", msg)
+	return errors.New(msg)
+}
+
+// parseSCPHeader parses "<mode> <size> <name>" from a C or D line.
+func parseSCPHeader(s string) (os.FileMode, int64, string, error) {
+	parts := strings.SplitN(s, " ", 3)
+	if len(parts) != 3 {
+		return 0, 0, "", errors.New("protocol error: bad header")
+	}
+	mode, err := strconv.ParseUint(parts[0], 8, 32)
+	if err != nil {
+		return 0, 0, "", errors.New("protocol error: bad mode")
+	}
+	size, err := strconv.ParseInt(parts[1], 10, 64)
+	if err != nil || size < 0 {
+		return 0, 0, "", errors.New("protocol error: bad size")
+	}
+	name := parts[2]
+	if name == "" || name == "." || name == ".." || strings.Contains(name, "/") {
+		return 0, 0, "", errors.New("protocol error: bad file name " + strconv.Quote(name))
+	}
+	return os.FileMode(mode).Perm(), size, name, nil
+}
+
+// parseSCPTimes parses "<mtime> 0 <atime> 0" into {mtime, atime}.
+func parseSCPTimes(s string) ([2]time.Time, error) {
+	f := strings.Fields(s)
+	if len(f) != 4 {
+		return [2]time.Time{}, errors.New("protocol error: bad times")
+	}
+	mt, err1 := strconv.ParseInt(f[0], 10, 64)
+	at, err2 := strconv.ParseInt(f[2], 10, 64)
+	if err1 != nil || err2 != nil {
+		return [2]time.Time{}, errors.New("protocol error: bad times")
+	}
+	return [2]time.Time{time.Unix(mt, 0), time.Unix(at, 0)}, nil
+}
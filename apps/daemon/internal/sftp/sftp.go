@@ -3,7 +3,8 @@
 // the JWT in the password field and `<userId>.<serverId>` in the
 // username. The daemon verifies the JWT against the node's signing key,
 // requires the `sftp` scope, and chroots into the per-server bind
-// directory.
+// directory. Legacy scp (an exec of `scp -t`/`scp -f`) is served over
+// the same jail; see scp.go.
 package sftp
 
 import (
@@ -17,14 +18,17 @@ import (
 	"log"
 	"net"
 	"os"
+	"path"
 	"path/filepath"
 	"strings"
+	"time"
 
 	"crypto/x509"
 
 	pkgsftp "github.com/pkg/sftp"
 	"golang.org/x/crypto/ssh"
 
+	"github.com/stellarstack/daemon/internal/filepolicy"
 	stellarjwt "github.com/stellarstack/daemon/internal/jwt"
 )
 
@@ -36,6 +40,22 @@ type Server struct {
 	verifier  *stellarjwt.Verifier
 	dataDir   string
 	nodeID    string
+	writes    WriteOptions
+	diskLimit func(serverID string) int64
+	usage     usageCache
+	guard     *guard
+	policy    *filepolicy.Policy
+	pathRules func(serverID string) *filepolicy.PathRules
+	onBlocked func(serverID, path, reason string)
+	scan      func(serverID, abs, rel, source string) error
+}
+
+// WriteOptions tune upload handling. BufferBytes coalesces sequential
+// WRITE packets per open file (0 writes each packet straight through);
+// WriteBehind flushes full buffers in the background.
+type WriteOptions struct {
+	BufferBytes int
+	WriteBehind bool
 }
 
 // New configures the SFTP server. If `hostKeyPath` doesn't exist a
@@ -47,17 +67,40 @@ func New(params struct {
 	Verifier    *stellarjwt.Verifier
 	DataDir     string
 	NodeID      string
+	Writes      WriteOptions
+	// DiskLimit returns a server's disk limit in bytes (0 = none),
+	// reported to clients through statvfs. Optional.
+	DiskLimit func(serverID string) int64
+	Limits    Limits
+	// Policy is the node's file policy, applied to every write and
+	// rename; OnBlocked is told about each refusal. Both optional.
+	Policy    *filepolicy.Policy
+	OnBlocked func(serverID, path, reason string)
+	// PathRules returns a server's denylist and hidden list, read
+	// once per session. Optional.
+	PathRules func(serverID string) *filepolicy.PathRules
+	// Scan checks a file once the client has finished writing it
+	// ("sftp" or "scp"); an error fails the upload, and the file is
+	// no longer on the server by then. Optional.
+	Scan func(serverID, abs, rel, source string) error
 }) (*Server, error) {
 	signer, err := loadOrCreateHostKey(params.HostKeyPath)
 	if err != nil {
 		return nil, fmt.Errorf("host key: %w", err)
 	}
 	return &Server{
-		listen:   params.Listen,
-		hostKey:  signer,
-		verifier: params.Verifier,
-		dataDir:  params.DataDir,
-		nodeID:   params.NodeID,
+		listen:    params.Listen,
+		hostKey:   signer,
+		verifier:  params.Verifier,
+		dataDir:   params.DataDir,
+		nodeID:    params.NodeID,
+		writes:    params.Writes,
+		diskLimit: params.DiskLimit,
+		guard:     newGuard(params.Limits),
+		policy:    params.Policy,
+		pathRules: params.PathRules,
+		onBlocked: params.OnBlocked,
+		scan:      params.Scan,
 	}, nil
 }
 
@@ -73,19 +116,47 @@ func (s *Server) ListenAndServe() error {
 		return fmt.Errorf("listen: %w", err)
 	}
 	log.Printf("sftp: listening on %s", s.listen)
+	go func() {
+		for range time.Tick(time.Minute) {
+			s.guard.prune()
+		}
+	}()
 	for {
 		conn, err := ln.Accept()
 		if err != nil {
 			return err
 		}
-		go s.handleConn(conn, cfg)
+		ip := hostOf(conn.RemoteAddr())
+		if !s.guard.admit(ip) {
+			// Banned or over the per-address limit: drop before the
+			// handshake so it costs no crypto.
+			conn.Close()
+			continue
+		}
+		go func() {
+			defer s.guard.release(ip)
+			s.handleConn(conn, cfg)
+		}()
 	}
 }
 
-// passwordCallback verifies the JWT presented as the password. Returns
-// the parsed claims via Permissions so the session handler doesn't have
-// to reparse them.
+// passwordCallback verifies the JWT presented as the password. Failures
+// are stalled and counted towards a ban of the client's address.
 func (s *Server) passwordCallback(c ssh.ConnMetadata, password []byte) (*ssh.Permissions, error) {
+	ip := hostOf(c.RemoteAddr())
+	perms, err := s.authenticate(c, password)
+	if err != nil {
+		time.Sleep(s.guard.fail(ip))
+		return nil, err
+	}
+	s.guard.succeed(ip)
+	return perms, nil
+}
+
+// authenticate checks the token against the username. Returns the
+// parsed claims via Permissions so the session handler doesn't have to
+// reparse them.
+func (s *Server) authenticate(c ssh.ConnMetadata, password []byte) (*ssh.Permissions, error) {
 	parts := strings.SplitN(c.User(), ".", 2)
 	if len(parts) != 2 {
 		return nil, errors.New("invalid username (expected <userId>.<serverId>)")
@@ -98,26 +169,40 @@ func (s *Server) passwordCallback(c ssh.ConnMetadata, password []byte) (*ssh.Per
 	if claims.Server != serverID || claims.Node != s.nodeID {
 		return nil, errors.New("token scoped to different server/node")
 	}
+	if claims.Sub != parts[0] {
+		return nil, errors.New("token issued to a different user")
+	}
 	if !claims.HasScope("sftp") {
 		return nil, errors.New("token missing sftp scope")
 	}
 	return &ssh.Permissions{
 		Extensions: map[string]string{
 			"server-id": serverID,
+			"user-id":   claims.Sub,
+			"paths":     strings.Join(claims.Paths, "\n"),
 		},
 	}, nil
 }
 
+// handshakeTimeout bounds key exchange plus authentication.
+const handshakeTimeout = 30 * time.Second
+
 func (s *Server) handleConn(c net.Conn, cfg *ssh.ServerConfig) {
 	defer c.Close()
+	// An unauthenticated connection holds one of its address's slots;
+	// don't let it sit in the handshake forever.
+	_ = c.SetDeadline(time.Now().Add(handshakeTimeout))
 	sshConn, chans, reqs, err := ssh.NewServerConn(c, cfg)
 	if err != nil {
 		return
 	}
+	_ = c.SetDeadline(time.Time{})
 	defer sshConn.Close()
 	go ssh.DiscardRequests(reqs)
 
 	serverID := sshConn.Permissions.Extensions["server-id"]
+	scope := parseScope(sshConn.Permissions.Extensions["paths"])
+	defer s.guard.track(sshConn, serverID, sshConn.Permissions.Extensions["user-id"])()
 	root := filepath.Join(s.dataDir, "servers", serverID)
 	if _, err := os.Stat(root); err != nil {
 		log.Printf("sftp: server root missing: %s", root)
@@ -135,34 +220,67 @@ func (s *Server) handleConn(c net.Conn, cfg *ssh.ServerConfig) {
 		}
 		go func() {
 			defer ch.Close()
-			// Wait for the SFTP subsystem request before serving.
+			// Wait for the SFTP subsystem (or an scp exec) before serving.
 			for req := range channelReqs {
 				if req.Type == "subsystem" && len(req.Payload) >= 4 &&
 					string(req.Payload[4:]) == "sftp" {
 					_ = req.Reply(true, nil)
-					if err := serveSFTP(ch, root); err != nil && err != io.EOF {
+					if err := serveSFTP(ch, s.chrootFS(root, serverID, scope)); err != nil && err != io.EOF {
 						log.Printf("sftp: serve: %v", err)
 					}
 					return
 				}
+				if req.Type == "exec" {
+					s.handleExec(ch, req, s.chrootFS(root, serverID, scope))
+					return
+				}
 				_ = req.Reply(false, nil)
 			}
 		}()
 	}
 }
 
+// handleExec serves an exec request. Only server-side scp is run;
+// anything else (a shell command, rsync) gets a message on stderr and a
+// non-zero exit rather than a bare "exec request failed".
+func (s *Server) handleExec(ch ssh.Channel, req *ssh.Request, fs *chrootFS) {
+	var payload struct{ Command string }
+	if err := ssh.Unmarshal(req.Payload, &payload); err != nil {
+		_ = req.Reply(false, nil)
+		return
+	}
+	_ = req.Reply(true, nil)
+	status := 1
+	cmd, ok := parseSCP(payload.Command)
+	switch {
+	case ok:
+		status = serveSCP(ch, ch.Stderr(), fs, cmd)
+	case strings.HasPrefix(payload.Command, "rsync"):
+		fmt.Fprintln(ch.Stderr(), "rsync is not supported on this server; use sftp or scp")
+	default:
+		fmt.Fprintln(ch.Stderr(), "only sftp and scp are available on this server")
+	}
+	_, _ = ch.SendRequest("exit-status", false, ssh.Marshal(struct{ Status uint32 }{uint32(status)}))
+}
+
 // serveSFTP runs pkg/sftp against a Channel, with all paths confined to
 // `root`. The chroot is implemented via a custom Handlers struct so the
 // SFTP layer can never see anything above `root`.
-func serveSFTP(ch ssh.Channel, root string) error {
-	handlers := chrootHandlers(root)
-	srv := pkgsftp.NewRequestServer(ch, handlers)
+func serveSFTP(ch ssh.Channel, fs *chrootFS) error {
+	srv := pkgsftp.NewRequestServer(ch, chrootHandlers(fs))
 	return srv.Serve()
 }
 
-func chrootHandlers(root string) pkgsftp.Handlers {
+// chrootFS builds the handler set for one session confined to root and,
+// when scope is non-empty, to those directories within it. Paths on the
+// server's denylist are refused as if outside the scope.
+func (s *Server) chrootFS(root, serverID string, scope pathScope) *chrootFS {
+	var rules *filepolicy.PathRules
+	if s.pathRules != nil {
+		rules = s.pathRules(serverID)
+	}
 	root = filepath.Clean(root)
-	resolve := func(p string) (string, error) {
+	jail := func(p string) (string, error) {
 		clean := filepath.Clean("/" + p)
 		abs := filepath.Join(root, clean)
 		if !strings.HasPrefix(abs, root) {
@@ -170,7 +288,34 @@ func chrootHandlers(root string) pkgsftp.Handlers {
 		}
 		return abs, nil
 	}
-	fs := &chrootFS{root: root, resolve: resolve}
+	resolve := func(p string) (string, error) {
+		if !scope.allows(path.Clean("/" + p)) {
+			return "", os.ErrPermission
+		}
+		abs, err := jail(p)
+		if err != nil || rules == nil {
+			return abs, err
+		}
+		st, statErr := os.Lstat(abs)
+		if rules.CheckPath(p, statErr == nil && st.IsDir()) != nil {
+			return "", os.ErrPermission
+		}
+		return abs, nil
+	}
+	fs := &chrootFS{root: root, resolve: resolve, jail: jail, scope: scope, writes: s.writes, usage: &s.usage, policy: s.policy, rules: rules}
+	if s.diskLimit != nil {
+		fs.diskLimit = func() int64 { return s.diskLimit(serverID) }
+	}
+	if s.onBlocked != nil {
+		fs.onBlocked = func(p, reason string) { s.onBlocked(serverID, p, reason) }
+	}
+	if s.scan != nil {
+		fs.scan = func(abs, rel, source string) error { return s.scan(serverID, abs, rel, source) }
+	}
+	return fs
+}
+
+func chrootHandlers(fs *chrootFS) pkgsftp.Handlers {
 	return pkgsftp.Handlers{
 		FileGet:  fs,
 		FilePut:  fs,
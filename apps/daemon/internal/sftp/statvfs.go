@@ -0,0 +1,93 @@
+package sftp
+
+import (
+	"io/fs"
+	"path/filepath"
+	"sync"
+	"syscall"
+	"time"
+
+	pkgsftp "github.com/pkg/sftp"
+)
+
+// usageTTL is how long a measured directory size is reused. Clients
+// call statvfs on every directory change; walking a 50k-file modpack
+// each time would cost more than the upload.
+const usageTTL = 30 * time.Second
+
+// usageCache remembers per-server directory sizes for statvfs.
+type usageCache struct {
+	mu      sync.Mutex
+	entries map[string]usageEntry
+}
+
+type usageEntry struct {
+	bytes int64
+	at    time.Time
+}
+
+func (c *usageCache) get(root string) int64 {
+	c.mu.Lock()
+	e, ok := c.entries[root]
+	c.mu.Unlock()
+	if ok && time.Since(e.at) < usageTTL {
+		return e.bytes
+	}
+	var total int64
+	_ = filepath.WalkDir(root, func(_ string, d fs.DirEntry, err error) error {
+		if err != nil || d.IsDir() {
+			return nil
+		}
+		if info, err := d.Info(); err == nil && info.Mode().IsRegular() {
+			total += info.Size()
+		}
+		return nil
+	})
+	c.mu.Lock()
+	if c.entries == nil {
+		c.entries = map[string]usageEntry{}
+	}
+	c.entries[root] = usageEntry{bytes: total, at: time.Now()}
+	c.mu.Unlock()
+	return total
+}
+
+// StatVFS answers statvfs@openssh.com (`df` in sftp, the free-space
+// display in WinSCP and FileZilla). With a disk limit the server's
+// quota is reported as the filesystem: its size is the limit and its
+// free space what's left of it, capped by what the real filesystem
+// still has. Without one the real filesystem is reported.
+func (f *chrootFS) StatVFS(req *pkgsftp.Request) (*pkgsftp.StatVFS, error) {
+	abs, err := f.resolve(req.Filepath)
+	if err != nil {
+		return nil, err
+	}
+	var st syscall.Statfs_t
+	if err := syscall.Statfs(abs, &st); err != nil {
+		return nil, err
+	}
+	bsize := uint64(st.Bsize)
+	out := &pkgsftp.StatVFS{
+		Bsize:   bsize,
+		Frsize:  bsize,
+		Blocks:  st.Blocks,
+		Bfree:   st.Bfree,
+		Bavail:  st.Bavail,
+		Files:   st.Files,
+		Ffree:   st.Ffree,
+		Favail:  st.Ffree,
+		Namemax: uint64(st.Namelen),
+	}
+	limit := int64(0)
+	if f.diskLimit != nil {
+		limit = f.diskLimit()
+	}
+	if limit > 0 && bsize > 0 {
+		used := f.usage.get(f.root)
+		free := uint64(max(limit-used, 0)) / bsize
+		out.Blocks = uint64(limit) / bsize
+		out.Bfree = min(free, st.Bfree)
+		out.Bavail = min(free, st.Bavail)
+	}
+	return out, nil
+}
@@ -0,0 +1,135 @@
+package sftp
+
+import (
+	"os"
+	"sync"
+)
+
+// bufferedWriterAt turns the SFTP client's stream of small WRITE
+// packets (32 KiB each for OpenSSH, often less) into large sequential
+// writes. Uploads almost always arrive in offset order, so consecutive
+// writes are coalesced in memory and hit the disk once per buffer; an
+// out-of-order write flushes and goes straight through.
+//
+// Each handle has its own lock, so concurrent uploads of different
+// files never wait on each other.
+//
+// With write-behind on, a full buffer is handed to a per-handle flusher
+// goroutine and the next WRITE is acknowledged without waiting for the
+// disk. A flush error is reported on the following WriteAt or on Close,
+// which is what the client checks before it considers the file done.
+type bufferedWriterAt struct {
+	f    *os.File
+	size int
+
+	mu   sync.Mutex // serialises WriteAt/Close on this handle
+	buf  []byte
+	off  int64 // file offset of buf[0]
+	work chan pending
+	busy sync.WaitGroup // queued buffers not yet written
+
+	errMu sync.Mutex
+	err   error // first write failure; sticky
+}
+
+type pending struct {
+	b   []byte
+	off int64
+}
+
+func newBufferedWriterAt(f *os.File, size int, writeBehind bool) *bufferedWriterAt {
+	w := &bufferedWriterAt{f: f, size: size, buf: make([]byte, 0, size)}
+	if writeBehind {
+		// Two buffers in flight bounds memory at ~3x size per handle.
+		w.work = make(chan pending, 2)
+		go w.flusher()
+	}
+	return w
+}
+
+func (w *bufferedWriterAt) WriteAt(p []byte, off int64) (int, error) {
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	if err := w.failed(); err != nil {
+		return 0, err
+	}
+	if len(w.buf) > 0 && off != w.off+int64(len(w.buf)) {
+		w.flushLocked()
+	}
+	if len(p) >= w.size {
+		// Larger than the buffer: nothing to coalesce with. Wait for
+		// queued buffers so an older one can't overwrite this range.
+		w.flushLocked()
+		w.busy.Wait()
+		if err := w.failed(); err != nil {
+			return 0, err
+		}
+		return w.f.WriteAt(p, off)
+	}
+	if len(w.buf)+len(p) > w.size {
+		w.flushLocked()
+	}
+	if len(w.buf) == 0 {
+		w.off = off
+	}
+	w.buf = append(w.buf, p...)
+	return len(p), nil
+}
+
+// flushLocked writes (or queues) the buffer and starts a fresh one.
+func (w *bufferedWriterAt) flushLocked() {
+	if len(w.buf) == 0 {
+		return
+	}
+	if w.work != nil {
+		w.busy.Add(1)
+		w.work <- pending{b: w.buf, off: w.off}
+		w.buf = make([]byte, 0, w.size)
+		return
+	}
+	if _, err := w.f.WriteAt(w.buf, w.off); err != nil {
+		w.fail(err)
+	}
+	w.buf = w.buf[:0]
+}
+
+func (w *bufferedWriterAt) flusher() {
+	for p := range w.work {
+		if _, err := w.f.WriteAt(p.b, p.off); err != nil {
+			w.fail(err)
+		}
+		w.busy.Done()
+	}
+}
+
+func (w *bufferedWriterAt) fail(err error) {
+	w.errMu.Lock()
+	if w.err == nil {
+		w.err = err
+	}
+	w.errMu.Unlock()
+}
+
+func (w *bufferedWriterAt) failed() error {
+	w.errMu.Lock()
+	defer w.errMu.Unlock()
+	return w.err
+}
+
+// Close flushes everything and closes the file. pkg/sftp calls it when
+// the client closes the handle.
+func (w *bufferedWriterAt) Close() error {
+	w.mu.Lock()
+	defer w.mu.Unlock()
+	w.flushLocked()
+	if w.work != nil {
+		close(w.work)
+		w.work = nil
+		w.busy.Wait()
+	}
+	err := w.failed()
+	if cerr := w.f.Close(); err == nil {
+		err = cerr
+	}
+	return err
+}
@@ -75,6 +75,7 @@ const blueprintRowToBlueprint = (row: BlueprintListRow): Blueprint => ({
     image: row.installImage,
     entrypoint: row.installEntrypoint,
     script: row.installScript,
+    ...(row.installInterpreter !== null ? { interpreter: row.installInterpreter } : {}),
   },
   lifecycle: row.lifecycle,
   features: row.features,
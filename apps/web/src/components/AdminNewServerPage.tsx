@@ -56,6 +56,8 @@ export const AdminNewServerPage = () => {
   const [cpu, setCpu] = useState(100)
   const [disk, setDisk] = useState(5000)
   const [variables, setVariables] = useState<Record<string, string>>({})
+  const [archiveUrl, setArchiveUrl] = useState("")
+  const [archiveChecksum, setArchiveChecksum] = useState("")
   const [error, setError] = useState<string | null>(null)
 
   const blueprints = blueprintsData?.blueprints ?? []
@@ -119,6 +121,9 @@ export const AdminNewServerPage = () => {
         cpuLimitPercent: cpu,
         diskLimitMb: disk,
         variables,
+        ...(archiveUrl !== ""
+          ? { installArchive: { url: archiveUrl, checksum: archiveChecksum } }
+          : {}),
       })
       void navigate({
         to: "/admin/servers/$serverId",
@@ -222,6 +227,23 @@ export const AdminNewServerPage = () => {
                 </SelectContent>
               </Select>
             </Field>
+            <Field label={t("admin_servers.field.install_archive")}>
+              <Input
+                value={archiveUrl}
+                onChange={(e) => setArchiveUrl(e.target.value)}
+                placeholder="https://example.com/template.tar.gz"
+              />
+              <p className="text-muted-foreground text-xs">{t("admin_servers.field.install_archive_hint")}</p>
+            </Field>
+            {archiveUrl !== "" && (
+              <Field label={t("admin_servers.field.install_checksum")}>
+                <Input
+                  value={archiveChecksum}
+                  onChange={(e) => setArchiveChecksum(e.target.value)}
+                  placeholder="sha256:…"
+                />
+              </Field>
+            )}
             </CardInner>
         </Card>
 
@@ -294,7 +316,7 @@ export const AdminNewServerPage = () => {
         <div className="flex justify-end">
           <Button
             type="submit"
-            disabled={create.isPending || !name || !ownerId || !blueprintId || !dockerImage || !nodeId}
+            disabled={create.isPending || !name || !ownerId || !blueprintId || !dockerImage || !nodeId || (archiveUrl !== "" && !archiveChecksum)}
           >
             {create.isPending ? t("admin_servers.creating") : t("admin_servers.create_submit")}
           </Button>
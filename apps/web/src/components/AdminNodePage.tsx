@@ -34,14 +34,23 @@ import {
   useUpdateNode,
   useDeleteNode,
   useMintPairingToken,
+  useNodeQueues,
 } from "@/hooks/useNodes"
 import {
   useAllocations,
   useCreateAllocations,
   useDeleteAllocations,
 } from "@/hooks/useAllocations"
-import type { NodeListRow, UpdateNodeRequest } from "@/hooks/useNodes.types"
-import type { AllocationRow, CreateAllocationsRequest } from "@/hooks/useAllocations.types"
+import type {
+  NodeListRow,
+  QueueCounts,
+  UpdateNodeRequest,
+} from "@/hooks/useNodes.types"
+import type {
+  AllocationProtocol,
+  AllocationRow,
+  CreateAllocationsRequest,
+} from "@/hooks/useAllocations.types"
 
 const Field = ({ label, children }: { label: string; children: React.ReactNode }) => (
   <div className="space-y-1.5">
@@ -190,7 +199,75 @@ const PairingTokenCard = ({ nodeId }: { nodeId: string }) => {
   )
 }
 
-const initialAllocationForm = { ip: "0.0.0.0", startPort: 25565, endPort: 25569, alias: "" }
+const QueueRow = ({ label, counts }: { label: string; counts: QueueCounts }) => {
+  const { t } = useTranslation()
+  return (
+    <div className="flex items-center justify-between text-xs">
+      <span>{label}</span>
+      <span className="text-muted-foreground font-mono">
+        {t("nodes.queues.counts", { running: counts.running, queued: counts.queued })}
+      </span>
+    </div>
+  )
+}
+
+const QueuesCard = ({ nodeId }: { nodeId: string }) => {
+  const { t } = useTranslation()
+  const queues = useNodeQueues(nodeId)
+  const data = queues.data
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle>{t("nodes.queues.heading")}</CardTitle>
+      </CardHeader>
+      <CardInner className="p-3 flex flex-col gap-2">
+        {queues.error instanceof ApiFetchError ? (
+          <p className="text-muted-foreground text-xs">{translateApiError(t, queues.error.body.error)}</p>
+        ) : data === undefined ? (
+          <p className="text-muted-foreground text-xs">{t("nodes.queues.loading")}</p>
+        ) : (
+          <>
+            <QueueRow label={t("nodes.queues.installs")} counts={data.installs} />
+            <QueueRow label={t("nodes.queues.backups")} counts={data.backups} />
+            <QueueRow label={t("nodes.queues.transfers_out")} counts={data.transfers.pushing} />
+            <QueueRow label={t("nodes.queues.transfers_in")} counts={data.transfers.ingesting} />
+            <QueueRow
+              label={t("nodes.queues.transfers_waiting")}
+              counts={{ running: 0, queued: data.transfersQueued }}
+            />
+            <p className="text-muted-foreground text-xs">
+              {data.transfers.windowOpen ? t("nodes.queues.window_open") : t("nodes.queues.window_closed")}
+              {" · "}
+              {t("nodes.queues.limits", {
+                jobs: data.limits.fileJobsPerServer,
+                lists: data.limits.fileListsPerServer,
+                bandwidth:
+                  data.limits.transferBandwidthMbps > 0
+                    ? `${data.limits.transferBandwidthMbps} Mbps`
+                    : t("nodes.queues.unlimited"),
+              })}
+            </p>
+          </>
+        )}
+      </CardInner>
+    </Card>
+  )
+}
+
+const initialAllocationForm = {
+  ip: "0.0.0.0",
+  startPort: 25565,
+  endPort: 25569,
+  alias: "",
+  protocol: "tcp" as AllocationProtocol,
+  block: false,
+}
+
+const formatAllocation = (row: AllocationRow): string => {
+  const ports = row.portEnd !== null ? `${row.port}-${row.portEnd}` : String(row.port)
+  return row.protocol === "tcp" ? `${row.ip}:${ports}` : `${row.ip}:${ports}/${row.protocol}`
+}
 
 const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
   const { t } = useTranslation()
@@ -212,6 +289,8 @@ const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
     const body: CreateAllocationsRequest = {
       ip: form.ip.trim(),
       portRange: { start: form.startPort, end: form.endPort },
+      protocol: form.protocol,
+      block: form.block,
       ...(form.alias.trim().length > 0 ? { alias: form.alias.trim() } : {}),
     }
     try {
@@ -228,7 +307,7 @@ const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
 
   const handleDelete = async (row: AllocationRow) => {
     if (row.serverId !== null) return
-    if (!window.confirm(t("nodes.allocations.confirm_delete", { ip: row.ip, port: row.port }))) return
+    if (!window.confirm(t("nodes.allocations.confirm_delete", { ip: row.ip, port: row.portEnd !== null ? `${row.port}-${row.portEnd}` : row.port }))) return
     setBusyDelete(row.id)
     try {
       await remove.mutateAsync([row.id])
@@ -267,7 +346,7 @@ const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
                   title={inUse ? t("nodes.allocations.bound_to", { serverId: row.serverId }) : t("nodes.allocations.free")}
                 >
                   <span className={`size-1 rounded-full ${inUse ? "bg-chart-2" : "bg-chart-1"}`} />
-                  <span>{row.ip}:{row.port}</span>
+                  <span>{formatAllocation(row)}</span>
                   {!inUse && (
                     <button
                       type="button"
@@ -284,7 +363,7 @@ const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
             })}
           </ul>
         )}
-        <form className="grid grid-cols-2 gap-2 sm:grid-cols-5" onSubmit={(e) => void handleAdd(e)}>
+        <form className="grid grid-cols-2 gap-2 sm:grid-cols-7" onSubmit={(e) => void handleAdd(e)}>
           <div className="flex flex-col gap-1">
             <Label className="text-xs">{t("nodes.allocations.field.ip")}</Label>
             <Input value={form.ip} onChange={(e) => setForm({ ...form, ip: e.target.value })} required className="h-7 text-xs" />
@@ -301,6 +380,21 @@ const AllocationsCard = ({ nodeId }: { nodeId: string }) => {
             <Label className="text-xs">{t("nodes.allocations.field.alias")}</Label>
             <Input value={form.alias} onChange={(e) => setForm({ ...form, alias: e.target.value })} className="h-7 text-xs" />
           </div>
+          <div className="flex flex-col gap-1">
+            <Label className="text-xs">{t("nodes.allocations.field.protocol")}</Label>
+            <Select value={form.protocol} onValueChange={(v) => setForm({ ...form, protocol: v as AllocationProtocol })}>
+              <SelectTrigger className="h-7 text-xs"><SelectValue /></SelectTrigger>
+              <SelectContent>
+                <SelectItem value="tcp">TCP</SelectItem>
+                <SelectItem value="udp">UDP</SelectItem>
+                <SelectItem value="both">TCP + UDP</SelectItem>
+              </SelectContent>
+            </Select>
+          </div>
+          <label className="flex items-end gap-1 pb-1.5 text-xs">
+            <input type="checkbox" checked={form.block} onChange={(e) => setForm({ ...form, block: e.target.checked })} />
+            {t("nodes.allocations.field.block")}
+          </label>
           <div className="flex items-end">
             <Button type="submit" size="sm" disabled={create.isPending} className="w-full">
               {create.isPending ? t("nodes.allocations.add_pending") : t("nodes.allocations.add_button")}
@@ -363,6 +457,30 @@ const DangerCard = ({ node }: { node: NodeListRow }) => {
   )
 }
 
+// Beyond this the daemon and panel start rejecting each other's
+// signatures and users' tokens.
+const CLOCK_SKEW_WARN_SECONDS = 5
+
+const NodeHealthWarnings = ({ node }: { node: NodeListRow }) => {
+  const { t } = useTranslation()
+  if (node.connectedAt === null || node.health === null) return null
+  const skew = node.health.clockSkewSeconds
+  const warnings = [
+    ...(node.health.docker ? [] : [t("nodes.health.docker_down")]),
+    ...(Math.abs(skew) >= CLOCK_SKEW_WARN_SECONDS
+      ? [t("nodes.health.clock_skew", { seconds: Math.abs(skew) })]
+      : []),
+  ]
+  if (warnings.length === 0) return null
+  return (
+    <div className="border-destructive/40 text-destructive rounded border p-3 text-xs" role="alert">
+      {warnings.map((w) => (
+        <p key={w}>{w}</p>
+      ))}
+    </div>
+  )
+}
+
 const NodeNotFound = () => {
   const { t } = useTranslation()
   return (
@@ -399,15 +517,20 @@ export const AdminNodePage = () => {
       <header className="flex items-start justify-between gap-4">
         <div>
           <h1 className="text-base font-semibold">{node.name}</h1>
-          <p className="text-muted-foreground font-mono text-xs">{node.fqdn}</p>
+          <p className="text-muted-foreground font-mono text-xs">
+            {node.fqdn}
+            {node.daemonVersion !== null && ` · ${node.daemonVersion}`}
+          </p>
         </div>
         <Button variant="outline" size="sm" asChild>
           <Link to="/admin/nodes">{t("actions.cancel")}</Link>
         </Button>
       </header>
 
+      <NodeHealthWarnings node={node} />
       <SettingsCard node={node} />
       <PairingTokenCard nodeId={node.id} />
+      {node.connectedAt !== null && <QueuesCard nodeId={node.id} />}
       <AllocationsCard nodeId={node.id} />
       <DangerCard node={node} />
     </div>
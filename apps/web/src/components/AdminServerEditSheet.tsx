@@ -98,6 +98,7 @@ const BuildSection = ({ server }: Props) => {
 
   const [blueprintId, setBlueprintId] = useState(server.blueprintId)
   const [dockerImage, setDockerImage] = useState(server.dockerImage)
+  const [hostNetwork, setHostNetwork] = useState(server.hostNetwork)
 
   const blueprints = blueprintsData?.blueprints ?? []
   const selectedBlueprint = blueprints.find((b) => b.id === blueprintId)
@@ -151,11 +152,25 @@ const BuildSection = ({ server }: Props) => {
             </SelectContent>
           </Select>
         </div>
+        <label className="flex items-start gap-2 text-xs">
+          <input
+            type="checkbox"
+            className="mt-0.5"
+            checked={hostNetwork}
+            onChange={(e) => setHostNetwork(e.target.checked)}
+          />
+          <span>
+            {t("admin_servers.field.host_network")}
+            <span className="text-muted-foreground block">
+              {t("admin_servers.field.host_network_hint")}
+            </span>
+          </span>
+        </label>
         <div className="flex justify-end">
           <Button
             size="sm"
             disabled={update.isPending || !blueprintId || !dockerImage}
-            onClick={() => update.mutate({ blueprintId, dockerImage })}
+            onClick={() => update.mutate({ blueprintId, dockerImage, hostNetwork })}
           >
             {update.isPending ? t("settings.saving") : t("admin_servers.save_build")}
           </Button>
@@ -69,6 +69,8 @@ const TransferDialog = ({ server }: TransferDialogProps) => {
   const [open, setOpen] = useState(false)
   const [targetNodeId, setTargetNodeId] = useState("")
   const [targetAllocationId, setTargetAllocationId] = useState("")
+  const [urgent, setUrgent] = useState(false)
+  const [ignore, setIgnore] = useState("")
 
   const { data: nodesData } = useNodes()
   const { data: allocationsData } = useAllocations(targetNodeId || null)
@@ -81,13 +83,24 @@ const TransferDialog = ({ server }: TransferDialogProps) => {
 
   const handleSubmit = () => {
     if (!targetNodeId || !targetAllocationId) return
+    const patterns = ignore
+      .split(",")
+      .map((p) => p.trim())
+      .filter((p) => p !== "")
     createTransfer.mutate(
-      { targetNodeId, targetAllocationId },
+      {
+        targetNodeId,
+        targetAllocationId,
+        urgent,
+        ignore: patterns.length > 0 ? patterns : undefined,
+      },
       {
         onSuccess: () => {
           setOpen(false)
           setTargetNodeId("")
           setTargetAllocationId("")
+          setUrgent(false)
+          setIgnore("")
         },
       }
     )
@@ -151,6 +164,26 @@ const TransferDialog = ({ server }: TransferDialogProps) => {
               </SelectContent>
             </Select>
           </div>
+          <div className="space-y-1.5">
+            <Label htmlFor="transfer-ignore">{t("transfers.dialog.ignore_label")}</Label>
+            <Input
+              id="transfer-ignore"
+              value={ignore}
+              onChange={(e) => setIgnore(e.target.value)}
+              placeholder="backups/, cache/, logs/"
+            />
+            <p className="text-xs text-muted-foreground">
+              {t("transfers.dialog.ignore_hint")}
+            </p>
+          </div>
+          <div className="flex items-center gap-2">
+            <Checkbox
+              id="transfer-urgent"
+              checked={urgent}
+              onCheckedChange={(v) => setUrgent(v === true)}
+            />
+            <Label htmlFor="transfer-urgent">{t("transfers.dialog.urgent")}</Label>
+          </div>
         </div>
         <DialogFooter>
           <Button variant="ghost" onClick={() => setOpen(false)}>
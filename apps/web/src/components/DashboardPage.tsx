@@ -1,11 +1,12 @@
 import { useTranslation } from "react-i18next"
 
 import { ServerList } from "@/components/ServerList"
-import { useServers } from "@/hooks/useServers"
+import { useServerStateFeed, useServers } from "@/hooks/useServers"
 
 export const DashboardPage = () => {
   const { t } = useTranslation()
   const serversQuery = useServers()
+  useServerStateFeed()
 
   return (
     <div className="flex flex-col gap-4">
@@ -470,15 +470,33 @@ export const FileManager = ({ serverId }: FileManagerProps) => {
       const result = await uploadFiles.mutateAsync({
         targetDir: path,
         files: uploads,
+        overwrite: "rename",
         onProgress: (loaded, _total, bytesPerSec) => {
           const pct = Math.round((loaded / _total) * 100)
           notify.update(id, { description: `${pct}% · ${formatSpeed(bytesPerSec)}` })
         },
       })
+      const renamed = (result.files ?? []).filter(
+        (f) => f.status === "renamed"
+      ).length
+      const quarantined = (result.files ?? []).filter(
+        (f) => f.status === "quarantined"
+      )
+      const blocked = (result.files ?? []).filter(
+        (f) => f.status === "blocked"
+      )
       notify.update(id, {
-        kind: "success",
+        kind:
+          quarantined.length > 0 || blocked.length > 0 ? "error" : "success",
         title: `Uploaded ${result.count} file${result.count === 1 ? "" : "s"}`,
-        description: undefined,
+        description:
+          quarantined.length > 0
+            ? `Blocked by the malware scan: ${quarantined.map((f) => f.name).join(", ")}`
+            : blocked.length > 0
+              ? `Not allowed on this node: ${blocked.map((f) => f.name).join(", ")}`
+              : renamed > 0
+                ? `${renamed} renamed to keep existing file${renamed === 1 ? "" : "s"}`
+                : undefined,
       })
     } catch (err) {
       notify.update(id, {
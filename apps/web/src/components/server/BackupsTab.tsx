@@ -24,6 +24,7 @@ import {
   TableHeader,
   TableRow,
 } from "@workspace/ui/components/table"
+import type { BackupProgressPayload } from "@workspace/shared/events.types"
 
 import { ApiFetchError } from "@/lib/ApiFetch"
 import { translateApiError } from "@/lib/TranslateError"
@@ -48,6 +49,40 @@ const formatBytes = (bytes: number): string => {
   return `${(bytes / 1024 / 1024 / 1024).toFixed(1)} GB`
 }
 
+const formatEta = (seconds: number): string => {
+  if (seconds < 60) return `${seconds}s`
+  if (seconds < 3600) return `${Math.floor(seconds / 60)}m ${seconds % 60}s`
+  return `${Math.floor(seconds / 3600)}h ${Math.floor((seconds % 3600) / 60)}m`
+}
+
+/**
+ * Size cell of a pending backup: a bar fed by the daemon's `backup
+ * progress` frames, or the plain "archiving…" label until the first one
+ * arrives (or on a node too old to send them).
+ */
+const PendingProgress = ({
+  progress,
+}: {
+  progress: BackupProgressPayload | undefined
+}) => {
+  const { t } = useTranslation()
+  if (progress === undefined) return <>{t("backups.state.archiving")}</>
+  const percent = Math.floor(progress.progress)
+  return (
+    <span className="flex items-center gap-2">
+      <span className="bg-muted h-1 w-16 overflow-hidden rounded-full">
+        <span
+          className="bg-chart-2 block h-full transition-[width]"
+          style={{ width: `${percent}%` }}
+        />
+      </span>
+      {progress.etaSeconds >= 0
+        ? t("backups.state.progress", { percent, eta: formatEta(progress.etaSeconds) })
+        : t("backups.state.progress_no_eta", { percent })}
+    </span>
+  )
+}
+
 const stateBadgeClass: Record<BackupRow["state"], string> = {
   pending: "bg-chart-2 animate-pulse",
   ready: "bg-chart-1",
@@ -56,7 +91,7 @@ const stateBadgeClass: Record<BackupRow["state"], string> = {
 
 export const BackupsTab = () => {
   const { t } = useTranslation()
-  const { server } = useServerLayout()
+  const { server, console } = useServerLayout()
   const backups = useBackups(server.id)
   const createBackup = useCreateBackup(server.id)
   const restoreBackup = useRestoreBackup(server.id)
@@ -123,7 +158,7 @@ export const BackupsTab = () => {
             {row.original.state === "ready"
               ? formatBytes(row.original.bytes)
               : row.original.state === "pending"
-                ? t("backups.state.archiving")
+                ? <PendingProgress progress={console.backupProgress[row.original.name]} />
                 : t("backups.state.failed", { code: row.original.failureCode ?? "unknown" })}
           </span>
         ),
@@ -189,7 +224,7 @@ export const BackupsTab = () => {
         ),
       },
     ],
-    [t, lockBackup, downloadBackup, restoreBackup.isPending, deleteBackup.isPending, server.status]
+    [t, lockBackup, downloadBackup, restoreBackup.isPending, deleteBackup.isPending, server.status, console.backupProgress]
   )
 
   const table = useReactTable({
@@ -530,7 +530,10 @@ const ScheduleEditor = (props: {
                     <SelectContent>
                       <SelectItem value="power">{t("schedules.editor.action_power")}</SelectItem>
                       <SelectItem value="command">{t("schedules.editor.action_command")}</SelectItem>
+                      <SelectItem value="macro">{t("schedules.editor.action_macro")}</SelectItem>
                       <SelectItem value="backup">{t("schedules.editor.action_backup")}</SelectItem>
+                      <SelectItem value="update">{t("schedules.editor.action_update")}</SelectItem>
+                      <SelectItem value="prune_logs">{t("schedules.editor.action_prune_logs")}</SelectItem>
                     </SelectContent>
                   </Select>
                 </div>
@@ -555,7 +558,13 @@ const ScheduleEditor = (props: {
                         ? '{"action":"restart"}'
                         : task.action === "command"
                           ? '{"line":"say hello"}'
-                          : '{"name":"daily"}'
+                          : task.action === "macro"
+                            ? '{"name":"restart-warning"}'
+                            : task.action === "update"
+                              ? '{"url":"https://…/pack.zip","checksum":"sha256:…","clean":"mods/*"}'
+                              : task.action === "prune_logs"
+                                ? "{}"
+                                : '{"name":"daily"}'
                     }
                     className="font-mono"
                   />
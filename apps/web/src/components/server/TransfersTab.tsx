@@ -15,6 +15,7 @@ import type { TransferRow } from "@/hooks/useTransfers.types"
 const statusBadge = (status: TransferRow["status"]) => {
   const map: Record<TransferRow["status"], string> = {
     pending: "bg-yellow-500/15 text-yellow-600",
+    queued: "bg-yellow-500/15 text-yellow-600",
     running: "bg-blue-500/15 text-blue-600",
     completed: "bg-green-500/15 text-green-700",
     failed: "bg-red-500/15 text-red-600",
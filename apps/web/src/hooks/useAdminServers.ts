@@ -13,6 +13,8 @@ export type AdminCreateServerRequest = {
   cpuLimitPercent: number
   diskLimitMb: number
   variables: Record<string, string>
+  /** Install from this archive instead of the blueprint's script. */
+  installArchive?: { url: string; checksum: string }
 }
 
 const LIST_KEY = ["admin", "servers"] as const
@@ -41,6 +43,7 @@ export const useAdminUpdateServer = (serverId: string) => {
       blueprintId?: string
       dockerImage?: string
       ownerId?: string
+      hostNetwork?: boolean
     }) =>
       apiFetch<{ ok: boolean }>(`/admin/servers/${serverId}`, {
         method: "PATCH",
@@ -15,6 +15,7 @@ export type AdminServerRow = {
   diskLimitMb: number
   dockerImage: string
   blueprintId: string
+  hostNetwork: boolean
   createdAt: string
   ownerId: string
   nodeId: string
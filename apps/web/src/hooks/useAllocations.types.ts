@@ -1,3 +1,5 @@
+export type AllocationProtocol = "tcp" | "udp" | "both"
+
 /**
  * Single allocation row returned by `GET /admin/nodes/:id/allocations`.
  */
@@ -6,6 +8,8 @@ export type AllocationRow = {
   nodeId: string
   ip: string
   port: number
+  portEnd: number | null
+  protocol: AllocationProtocol
   alias: string | null
   serverId: string | null
   createdAt: string
@@ -13,11 +17,14 @@ export type AllocationRow = {
 
 /**
  * Body of `POST /admin/nodes/:id/allocations`. Either `ports` or
- * `portRange` is required; `alias` is optional.
+ * `portRange` is required; `alias` is optional. `block` reserves the
+ * range as a single allocation.
  */
 export type CreateAllocationsRequest = {
   ip: string
   ports?: number[]
   portRange?: { start: number; end: number }
   alias?: string
+  protocol?: AllocationProtocol
+  block?: boolean
 }
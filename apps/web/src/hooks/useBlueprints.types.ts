@@ -1,4 +1,7 @@
-import type { Blueprint } from "@workspace/shared/blueprint.types"
+import type {
+  Blueprint,
+  BlueprintInstallInterpreter,
+} from "@workspace/shared/blueprint.types"
 
 /**
  * Wire shape returned by `GET /admin/blueprints`. The persisted columns mirror
@@ -10,6 +13,7 @@ export type BlueprintListRow = Omit<Blueprint, "schemaVersion" | "install"> & {
   installImage: string
   installEntrypoint: string
   installScript: string
+  installInterpreter: BlueprintInstallInterpreter | null
   createdAt: string
   updatedAt: string
 }
@@ -2,7 +2,14 @@ import { useCallback, useEffect, useRef, useState } from "react"
 import { useQueryClient } from "@tanstack/react-query"
 import { z } from "zod"
 
-import type { ServerLifecycleState } from "@workspace/shared/events.types"
+import {
+  WS_SCHEMA_VERSION,
+  backupProgressPayloadSchema,
+} from "@workspace/shared/events"
+import type {
+  BackupProgressPayload,
+  ServerLifecycleState,
+} from "@workspace/shared/events.types"
 
 import { apiFetch } from "@/lib/ApiFetch"
 import type {
@@ -68,12 +75,19 @@ const statsPayloadSchema = z.object({
   disk_read_bytes: z.number().nonnegative(),
   disk_write_bytes: z.number().nonnegative(),
   uptime_ms: z.number().nonnegative().optional(),
+  console_dropped: z.number().int().nonnegative().optional(),
   state: stateSchema,
 })
 
 const envelopeSchema = z.object({
+  // Frame schema version. Frames newer than we understand are dropped
+  // instead of being half-parsed into the UI.
+  v: z.number().int().positive().optional(),
   event: z.string(),
   args: z.array(z.unknown()).default([]),
+  // Present on replayable (non-console) frames; echoed back as
+  // `?since=` on reconnect so the daemon replays what we missed.
+  seq: z.number().int().nonnegative().optional(),
 })
 
 /**
@@ -92,6 +106,9 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
   const [statsHistory, setStatsHistory] = useState<StatsSample[]>([])
   const [daemonError, setDaemonError] = useState<string | null>(null)
   const clearDaemonError = useCallback(() => setDaemonError(null), [])
+  const [backupProgress, setBackupProgress] = useState<
+    Record<string, BackupProgressPayload>
+  >({})
 
   // Whenever the daemon WS reports a fresh status, also write it
   // through to the React Query cache for the server detail row so any
@@ -153,11 +170,12 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
   const reconnectTimerRef = useRef<number | null>(null)
   const refreshTimerRef = useRef<number | null>(null)
   const expiresAtRef = useRef<number>(0)
+  const lastSeqRef = useRef<number | null>(null)
 
   const send = useCallback((envelope: { event: string; args: unknown[] }) => {
     const ws = socketRef.current
     if (ws === null || ws.readyState !== 1) return
-    ws.send(JSON.stringify(envelope))
+    ws.send(JSON.stringify({ v: WS_SCHEMA_VERSION, ...envelope }))
   }, [])
 
   const sendCommand = useCallback(
@@ -200,6 +218,10 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
 
         const url = new URL(parsed.data.wsUrl)
         url.searchParams.set("token", parsed.data.token)
+        url.searchParams.set("v", String(WS_SCHEMA_VERSION))
+        if (lastSeqRef.current !== null) {
+          url.searchParams.set("since", String(lastSeqRef.current))
+        }
         const ws = new WebSocket(url.toString())
         socketRef.current = ws
 
@@ -230,11 +252,14 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
           }
           const env = envelopeSchema.safeParse(payload)
           if (!env.success) return
+          if (env.data.v !== undefined && env.data.v > WS_SCHEMA_VERSION) return
+          if (env.data.seq !== undefined) lastSeqRef.current = env.data.seq
           dispatchFrame(env.data.event, env.data.args, {
             setStatus,
             setLines,
             setStatsHistory,
             setDaemonError,
+            setBackupProgress,
             counterRef,
           })
           if (env.data.event === "token expiring") {
@@ -279,6 +304,7 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
     void connect(ac.signal)
     return () => {
       ac.abort()
+      lastSeqRef.current = null
       setLines([])
       setStatsHistory([])
       setStatus(null)
@@ -305,6 +331,7 @@ export const useConsole = (serverId: string, enabled: boolean): UseConsoleResult
     stats: { latest: latestStats, history: statsHistory },
     daemonError,
     clearDaemonError,
+    backupProgress,
     sendCommand,
     setState: setStateAction,
   }
@@ -315,6 +342,9 @@ type FrameSetters = {
   setLines: React.Dispatch<React.SetStateAction<ConsoleLine[]>>
   setStatsHistory: React.Dispatch<React.SetStateAction<StatsSample[]>>
   setDaemonError: React.Dispatch<React.SetStateAction<string | null>>
+  setBackupProgress: React.Dispatch<
+    React.SetStateAction<Record<string, BackupProgressPayload>>
+  >
   counterRef: React.MutableRefObject<number>
 }
 
@@ -369,6 +399,7 @@ const dispatchFrame = (
           p.uptime_ms !== undefined
             ? new Date(Date.now() - p.uptime_ms).toISOString()
             : undefined,
+        consoleDropped: p.console_dropped,
       }
       setters.setStatsHistory((prev) => [...prev, sample].slice(-MAX_STATS))
       // Status piggybacks on stats so a missed `status` frame is recovered.
@@ -380,6 +411,12 @@ const dispatchFrame = (
       if (code !== null) setters.setDaemonError(code)
       return
     }
+    case "backup progress": {
+      const v = backupProgressPayloadSchema.safeParse(args[0])
+      if (!v.success) return
+      setters.setBackupProgress((prev) => ({ ...prev, [v.data.name]: v.data }))
+      return
+    }
     case "auth success":
     case "token expired":
     default:
@@ -1,4 +1,7 @@
-import type { ServerLifecycleState } from "@workspace/shared/events.types"
+import type {
+  BackupProgressPayload,
+  ServerLifecycleState,
+} from "@workspace/shared/events.types"
 
 import type { StatsSample } from "@/hooks/useServerStats.types"
 
@@ -32,7 +35,13 @@ export type ConsoleConnectionState =
   | "closed"
 
 /** Power actions the browser can ask the daemon to perform. */
-export type ConsolePowerAction = "start" | "stop" | "restart" | "kill"
+export type ConsolePowerAction =
+  | "start"
+  | "stop"
+  | "restart"
+  | "kill"
+  | "pause"
+  | "unpause"
 
 /**
  * Result returned from `useConsole`. The hook owns the daemon WebSocket
@@ -52,6 +61,12 @@ export type UseConsoleResult = {
   daemonError: string | null
   /** Clear `daemonError` (used by modals after the user dismisses). */
   clearDaemonError: () => void
+  /**
+   * Latest `backup progress` frame, keyed by backup name, so the backups
+   * tab can draw a progress bar on the pending row. A finished backup's
+   * entry stays at 100% until the row turns ready.
+   */
+  backupProgress: Record<string, BackupProgressPayload>
   /** Send a console command (writes `<line>\n` to the container stdin). */
   sendCommand: (line: string) => void
   /** Dispatch a power action over the same WS. */
@@ -3,10 +3,14 @@ import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query"
 
 import { apiFetch } from "@/lib/ApiFetch"
 import type {
+  CopyConflict,
   FileCredentials,
   FileEntry,
+  FileJob,
   SftpCredentials,
   UploadFileEntry,
+  UploadOverwrite,
+  UploadResult,
 } from "@/hooks/useFiles.types"
 
 const REFRESH_BEFORE_EXPIRY_MS = 30_000
@@ -103,21 +107,93 @@ const useDaemonFetch = (serverId: string) => {
   )
 }
 
+const JOB_POLL_MS = 1_000
+
+/**
+ * Waits out the job a long file operation answered 202 with, polling
+ * `GET /jobs/:id`, so the mutation settles (and the listing is
+ * refetched) once the files have actually changed rather than when the
+ * work was accepted. A failed or cancelled job rejects with its error.
+ */
+const awaitJob = async (
+  daemonFetch: ReturnType<typeof useDaemonFetch>,
+  accepted: { job: FileJob }
+): Promise<FileJob> => {
+  let job = accepted.job
+  while (job.status === "queued" || job.status === "running") {
+    await new Promise((resolve) => window.setTimeout(resolve, JOB_POLL_MS))
+    job = (await daemonFetch<{ job: FileJob }>("GET", `/jobs/${job.id}`)).job
+  }
+  if (job.status !== "completed") {
+    throw new Error(job.error ?? `job ${job.status}`)
+  }
+  return job
+}
+
 const listKey = (serverId: string, path: string) =>
   ["servers", serverId, "files", path] as const
 const contentKey = (serverId: string, path: string) =>
   ["servers", serverId, "files", "content", path] as const
 
+const byName = (a: FileEntry, b: FileEntry) =>
+  a.name < b.name ? -1 : a.name > b.name ? 1 : 0
+
 /**
  * List files at `path` for a server. Calls the daemon directly using the
- * minted file credentials.
+ * minted file credentials, asking for the NDJSON stream so entries of a
+ * huge directory show up in the cache as they're read instead of after
+ * the whole listing.
  */
 export const useFileList = (serverId: string, path: string) => {
-  const daemonFetch = useDaemonFetch(serverId)
+  const credentials = useFileCredentials(serverId)
+  const queryClient = useQueryClient()
   return useQuery({
     queryKey: listKey(serverId, path),
-    queryFn: () =>
-      daemonFetch<{ entries: FileEntry[] }>("GET", "/files", { path }),
+    queryFn: async ({ signal }) => {
+      const cred = await credentials.get()
+      const url = new URL(cred.baseUrl + "/files")
+      url.searchParams.set("token", cred.token)
+      url.searchParams.set("path", path)
+      const response = await fetch(url, {
+        headers: {
+          ...buildHeaders(cred.token),
+          Accept: "application/x-ndjson",
+        },
+        signal,
+      })
+      if (!response.ok) {
+        const text = await response.text()
+        throw new Error(text || `daemon error ${response.status}`)
+      }
+      if (
+        response.body === null ||
+        response.headers.get("Content-Type")?.includes("ndjson") !== true
+      ) {
+        return (await response.json()) as { entries: FileEntry[] }
+      }
+      const entries: FileEntry[] = []
+      const reader = response.body
+        .pipeThrough(new TextDecoderStream())
+        .getReader()
+      let buffered = ""
+      for (;;) {
+        const { done, value } = await reader.read()
+        if (done) break
+        buffered += value
+        const lines = buffered.split("\n")
+        buffered = lines.pop() ?? ""
+        for (const line of lines) {
+          if (line === "") continue
+          const parsed = JSON.parse(line) as FileEntry | { error: string }
+          if ("error" in parsed) throw new Error(parsed.error)
+          entries.push(parsed)
+        }
+        queryClient.setQueryData(listKey(serverId, path), {
+          entries: [...entries].sort(byName),
+        })
+      }
+      return { entries: entries.sort(byName) }
+    },
   })
 }
 
@@ -164,14 +240,18 @@ export const useWriteFile = (serverId: string) => {
 }
 
 /**
- * Delete a file or directory recursively.
+ * Delete a file or directory recursively. Settles when the daemon's
+ * delete job has finished.
  */
 export const useDeleteFile = (serverId: string) => {
   const daemonFetch = useDaemonFetch(serverId)
   const queryClient = useQueryClient()
   return useMutation({
-    mutationFn: (path: string) =>
-      daemonFetch<{ ok: boolean }>("DELETE", "/files", { path }),
+    mutationFn: async (path: string) =>
+      awaitJob(
+        daemonFetch,
+        await daemonFetch<{ job: FileJob }>("DELETE", "/files", { path })
+      ),
     onSuccess: () => {
       void queryClient.invalidateQueries({
         queryKey: ["servers", serverId, "files"],
@@ -222,17 +302,21 @@ export const useUploadFiles = (serverId: string) => {
     mutationFn: async (params: {
       targetDir: string
       files: UploadFileEntry[]
+      overwrite?: UploadOverwrite
       onProgress?: (loaded: number, total: number, bytesPerSec: number) => void
     }) => {
       const cred = await credentials.get()
       const url = new URL(cred.baseUrl + "/files/upload")
       url.searchParams.set("token", cred.token)
       url.searchParams.set("path", params.targetDir)
+      if (params.overwrite !== undefined) {
+        url.searchParams.set("overwrite", params.overwrite)
+      }
       const formData = new FormData()
       for (const entry of params.files) {
         formData.append("file", entry.file, entry.relativePath)
       }
-      return new Promise<{ ok: boolean; count: number }>((resolve, reject) => {
+      return new Promise<UploadResult>((resolve, reject) => {
         const xhr = new XMLHttpRequest()
         const startTime = Date.now()
         xhr.upload.addEventListener("progress", (e) => {
@@ -246,7 +330,7 @@ export const useUploadFiles = (serverId: string) => {
             reject(new Error(xhr.responseText || `daemon error ${xhr.status}`))
             return
           }
-          resolve(JSON.parse(xhr.responseText) as { ok: boolean; count: number })
+          resolve(JSON.parse(xhr.responseText) as UploadResult)
         })
         xhr.addEventListener("error", () => reject(new Error("Upload failed")))
         xhr.addEventListener("abort", () => reject(new Error("Upload cancelled")))
@@ -311,12 +395,54 @@ export const useCompressFiles = (serverId: string) => {
   const daemonFetch = useDaemonFetch(serverId)
   const queryClient = useQueryClient()
   return useMutation({
-    mutationFn: (params: { paths: string[]; destination: string }) =>
-      daemonFetch<{ ok: boolean }>(
-        "POST",
-        "/files/compress",
-        {},
-        JSON.stringify({ paths: params.paths, destination: params.destination })
+    mutationFn: async (params: { paths: string[]; destination: string }) =>
+      awaitJob(
+        daemonFetch,
+        await daemonFetch<{ job: FileJob }>(
+          "POST",
+          "/files/compress",
+          {},
+          JSON.stringify({
+            paths: params.paths,
+            destination: params.destination,
+          })
+        )
+      ),
+    onSuccess: () => {
+      void queryClient.invalidateQueries({ queryKey: ["servers", serverId, "files"] })
+    },
+  })
+}
+
+/**
+ * Copies (or with `move`, moves) files and whole directories into
+ * `destination`. The daemon runs it as a job, reporting progress as
+ * `job progress` frames on the server socket; the mutation settles with
+ * the finished job.
+ */
+export const useCopyFiles = (serverId: string) => {
+  const daemonFetch = useDaemonFetch(serverId)
+  const queryClient = useQueryClient()
+  return useMutation({
+    mutationFn: async (params: {
+      paths: string[]
+      destination: string
+      move?: boolean
+      onConflict?: CopyConflict
+    }) =>
+      awaitJob(
+        daemonFetch,
+        await daemonFetch<{ job: FileJob }>(
+          "POST",
+          "/files/copy",
+          {},
+          JSON.stringify({
+            paths: params.paths,
+            destination: params.destination,
+            move: params.move ?? false,
+            onConflict: params.onConflict ?? "rename",
+          })
+        )
       ),
     onSuccess: () => {
       void queryClient.invalidateQueries({ queryKey: ["servers", serverId, "files"] })
@@ -347,7 +473,8 @@ export const useMediaBlobUrl = (serverId: string, path: string | null) => {
       .get()
       .then(async (cred) => {
         if (activeRef.current !== path) return
-        const url = new URL(cred.baseUrl + "/files/content")
+        // Download, not content: media is often over the inline cap.
+        const url = new URL(cred.baseUrl + "/files/download")
         url.searchParams.set("token", cred.token)
         url.searchParams.set("path", path)
         const response = await fetch(url, {
@@ -378,12 +505,18 @@ export const useDecompressFile = (serverId: string) => {
   const daemonFetch = useDaemonFetch(serverId)
   const queryClient = useQueryClient()
   return useMutation({
-    mutationFn: (params: { path: string; destination: string }) =>
-      daemonFetch<{ ok: boolean; count: number }>(
-        "POST",
-        "/files/decompress",
-        {},
-        JSON.stringify({ path: params.path, destination: params.destination })
+    mutationFn: async (params: { path: string; destination: string }) =>
+      awaitJob(
+        daemonFetch,
+        await daemonFetch<{ job: FileJob }>(
+          "POST",
+          "/files/decompress",
+          {},
+          JSON.stringify({
+            path: params.path,
+            destination: params.destination,
+          })
+        )
       ),
     onSuccess: () => {
       void queryClient.invalidateQueries({ queryKey: ["servers", serverId, "files"] })
@@ -35,3 +35,58 @@ export type UploadFileEntry = {
   file: File
   relativePath: string
 }
+
+/**
+ * What an upload does with a file that already exists: replace it,
+ * store the upload as "name (1).ext", or skip it.
+ */
+export type UploadOverwrite = "overwrite" | "rename" | "fail"
+
+/**
+ * What a copy or move does when a source's name is taken in the
+ * destination: put it under "name (1)", merge and replace, merge and
+ * keep existing files, or refuse.
+ */
+export type CopyConflict = "rename" | "overwrite" | "skip" | "fail"
+
+/**
+ * A daemon job, as a long file operation (delete, copy, compress,
+ * decompress) answers with and `GET /jobs/:id` reports. A completed
+ * copy's `result` is a {@link CopyResult}.
+ */
+export type FileJob = {
+  id: string
+  status: "queued" | "running" | "completed" | "failed" | "cancelled"
+  result?: unknown
+  error?: string
+}
+
+/** A finished copy: where each source landed and what was skipped. */
+export type CopyResult = {
+  paths: string[]
+  files: number
+  skipped: number
+  bytes: number
+}
+
+/** One file's outcome in an upload. */
+export type UploadedFile = {
+  name: string
+  path: string
+  bytes: number
+  /**
+   * `blocked`: the node's file policy refused it. `quarantined`: the
+   * node's malware scan flagged it. Neither is stored.
+   */
+  status: "written" | "renamed" | "exists" | "blocked" | "quarantined"
+}
+
+/**
+ * Daemon answer to an upload. `files` is absent on daemons that predate
+ * per-file results.
+ */
+export type UploadResult = {
+  ok: boolean
+  count: number
+  files?: UploadedFile[]
+}
@@ -4,6 +4,7 @@ import { apiFetch } from "@/lib/ApiFetch"
 import type {
   CreateNodeRequest,
   NodeListRow,
+  NodeQueuesResponse,
   PairingTokenResponse,
   UpdateNodeRequest,
 } from "@/hooks/useNodes.types"
@@ -23,6 +24,19 @@ export const useNodes = () =>
     refetchInterval: 10_000,
   })
 
+/**
+ * What the node is busy with right now. Polled while the node page is
+ * open; fails with nodes.unsupported_feature on older daemons.
+ */
+export const useNodeQueues = (nodeId: string) =>
+  useQuery({
+    queryKey: [...QUERY_KEY, nodeId, "queues"],
+    queryFn: () =>
+      apiFetch<NodeQueuesResponse>(`/admin/nodes/${nodeId}/queues`),
+    refetchInterval: 5_000,
+    retry: false,
+  })
+
 /**
  * Mutation: create a new node row. Invalidates the list on success.
  */
@@ -13,6 +13,15 @@ export type NodeListRow = {
   memoryTotalMb: number
   diskTotalMb: number
   connectedAt: string | null
+  /** Set by the heartbeat of daemons that report them. */
+  daemonVersion: string | null
+  resources: {
+    cpus: number
+    memoryMb: number
+    diskMb: number
+    diskFreeMb: number
+  } | null
+  health: { docker: boolean; clockSkewSeconds: number } | null
   createdAt: string
 }
 
@@ -39,3 +48,30 @@ export type PairingTokenResponse = {
   token: string
   expiresAt: string
 }
+
+/** Queued and running counts for one kind of work on a node. */
+export type QueueCounts = {
+  queued: number
+  running: number
+}
+
+/**
+ * Response of `GET /admin/nodes/:id/queues`: what the node's daemon is
+ * busy with, and transfers the panel is holding for its window.
+ */
+export type NodeQueuesResponse = {
+  installs: QueueCounts
+  backups: QueueCounts
+  transfers: {
+    pushing: QueueCounts
+    ingesting: QueueCounts
+    windowOpen: boolean
+  }
+  jobs: Record<string, QueueCounts>
+  limits: {
+    fileJobsPerServer: number
+    fileListsPerServer: number
+    transferBandwidthMbps: number
+  }
+  transfersQueued: number
+}
@@ -7,7 +7,13 @@ export type ScheduleTaskRow = {
   id: string
   scheduleId: string
   sortOrder: number
-  action: "power" | "command" | "backup"
+  action:
+    | "power"
+    | "command"
+    | "macro"
+    | "backup"
+    | "update"
+    | "prune_logs"
   delaySeconds: number
   payload: Record<string, string | number | boolean> | null
   createdAt: string
@@ -37,7 +43,7 @@ export type ScheduleInput = {
   onlyWhenOnline: boolean
   tasks: Array<{
     sortOrder: number
-    action: "power" | "command" | "backup"
+    action: ScheduleTaskRow["action"]
     delaySeconds: number
     payload: Record<string, string | number | boolean> | null
   }>
@@ -14,6 +14,8 @@ export type StatsSample = {
   diskWriteBytes: number
   /** ISO string of when the container started (from Docker inspect via daemon). */
   startedAt?: string
+  /** Console frames the daemon dropped for slow clients of this server. */
+  consoleDropped?: number
 }
 
 /**
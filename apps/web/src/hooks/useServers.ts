@@ -1,3 +1,4 @@
+import { useEffect } from "react"
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query"
 
 import { apiFetch } from "@/lib/ApiFetch"
@@ -5,6 +6,7 @@ import type {
   CreateServerRequest,
   ServerDetailRow,
   ServerListRow,
+  ServerStateChanges,
 } from "@/hooks/useServers.types"
 
 const LIST_KEY = ["servers"] as const
@@ -22,6 +24,50 @@ export const useServers = () =>
     refetchInterval: 10_000,
   })
 
+/**
+ * Keep the server list's statuses live: long-polls
+ * `/servers/states/changes` for as long as the component is mounted
+ * and patches each changed server's status into the cached list, so
+ * the dashboard updates within a moment of a server changing state.
+ * Errors back off briefly; the list's own polling still covers gaps.
+ */
+export const useServerStateFeed = () => {
+  const queryClient = useQueryClient()
+  useEffect(() => {
+    const controller = new AbortController()
+    const run = async () => {
+      let cursor = ""
+      while (!controller.signal.aborted) {
+        try {
+          const changes = await apiFetch<ServerStateChanges>(
+            `/servers/states/changes?cursor=${encodeURIComponent(cursor)}`,
+            { signal: controller.signal }
+          )
+          cursor = changes.cursor
+          queryClient.setQueryData<{ servers: ServerListRow[] }>(
+            LIST_KEY,
+            (existing) => {
+              if (existing === undefined) return existing
+              return {
+                servers: existing.servers.map((s) => {
+                  const state = changes.states[s.id]
+                  if (state === undefined) return s
+                  return { ...s, status: state.process_state }
+                }),
+              }
+            }
+          )
+        } catch {
+          if (controller.signal.aborted) return
+          await new Promise((resolve) => setTimeout(resolve, 5_000))
+        }
+      }
+    }
+    void run()
+    return () => controller.abort()
+  }, [queryClient])
+}
+
 /**
  * Subscribe to a single server.
  */
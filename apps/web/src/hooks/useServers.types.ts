@@ -27,6 +27,31 @@ export type ServerListRow = {
   updatedAt: string
 }
 
+/**
+ * One server's live state as reported by its node. `flags` holds any of
+ * "installing", "transferring", "paused" and "suspended", plus "unknown"
+ * while the node has lost Docker and `process_state` is the last one it
+ * saw.
+ */
+export type ServerLiveState = {
+  process_state: ServerLifecycleState
+  flags: string[]
+  uptime_ms: number
+  cpu_absolute: number
+  memory_bytes: number
+  memory_limit_bytes: number
+  console_dropped?: number
+}
+
+/**
+ * Wire shape returned by `GET /servers/states/changes`: the servers
+ * whose state changed since `cursor` was issued, and the next cursor.
+ */
+export type ServerStateChanges = {
+  cursor: string
+  states: Record<string, ServerLiveState>
+}
+
 /**
  * Wire shape returned by `GET /servers/:id`. Extends the list row with
  * resolved fields that require extra joins.
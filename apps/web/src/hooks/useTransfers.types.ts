@@ -8,9 +8,14 @@ export type TransferRow = {
   sourceNodeId: string
   targetNodeId: string
   targetAllocationId: string
-  status: "pending" | "running" | "completed" | "failed"
+  status: "pending" | "queued" | "running" | "completed" | "failed"
+  /** Runs outside the nodes' transfer windows instead of queueing. */
+  urgent: boolean
+  /** Paths the source left behind; null when nothing was excluded. */
+  ignore: string[] | null
   error: string | null
   createdAt: string
+  startedAt: string | null
   completedAt: string | null
 }
 
@@ -20,4 +25,6 @@ export type TransferRow = {
 export type TransferInput = {
   targetNodeId: string
   targetAllocationId: string
+  urgent?: boolean
+  ignore?: string[]
 }
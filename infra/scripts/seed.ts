@@ -101,6 +101,7 @@ const minecraftBlueprint: Blueprint = {
       ],
     },
   },
+  memoryOverhead: { type: "percent", percent: 15 },
   features: { eula: [], java_version_picker: [] },
 }
 
@@ -128,6 +129,12 @@ const upsertBlueprint = async (blueprint: Blueprint): Promise<string> => {
     installEntrypoint: validated.install.entrypoint,
     installScript: validated.install.script,
     lifecycle: validated.lifecycle,
+    memoryOverhead: validated.memoryOverhead ?? null,
+    sidecars: validated.sidecars ?? null,
+    services: validated.services ?? null,
+    playerCommands: validated.playerCommands ?? null,
+    files: validated.files ?? null,
+    logPruning: validated.logPruning ?? null,
     features: validated.features ?? null,
     updatedAt: new Date(),
   }
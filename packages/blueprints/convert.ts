@@ -22,6 +22,7 @@ import { blueprintSchema } from "@workspace/shared/blueprint"
 import type {
   Blueprint,
   BlueprintConfigFile,
+  BlueprintInstallInterpreter,
   BlueprintVariable,
 } from "@workspace/shared/blueprint.types"
 
@@ -160,6 +161,18 @@ const mapFeature = (f: string): string => {
   return MAP[f] ?? f
 }
 
+/**
+ * The interpreter an egg's install entrypoint names ("bash", "/bin/ash",
+ * "python3"), so the script runs without relying on its shebang.
+ * Anything else is left to the entrypoint.
+ */
+const eggInterpreter = (entrypoint: string): BlueprintInstallInterpreter | undefined => {
+  const name = path.posix.basename(entrypoint.trim())
+  if (name === "bash" || name === "sh" || name === "ash") return name
+  if (name === "python" || name === "python3") return "python"
+  return undefined
+}
+
 /**
  * Convert a single StellarStack PTDL_v2 egg object to a StellarStack blueprint.
  */
@@ -200,13 +213,16 @@ const convertEgg = (egg: Egg): Blueprint => {
       : { strategy: "console" as const, match: { type: "substring" as const, value: "done" } }
 
   const install = egg.scripts?.installation
+  const interpreter = eggInterpreter(install?.entrypoint ?? "ash")
   const installBlock = {
     image: install?.container ?? "ghcr.io/stellarstackoss/planets:installers_alpine",
     entrypoint: install?.entrypoint ?? "ash",
     script: (install?.script ?? "#!/bin/ash\necho 'No install script'").replace(/\r\n/g, "\n"),
+    ...(interpreter !== undefined ? { interpreter } : {}),
   }
 
   const features = (egg.features ?? []).map(mapFeature)
+  const denylist = (egg.file_denylist ?? []).filter((p) => p.trim() !== "")
 
   const blueprint: Blueprint = {
     schemaVersion: 1,
@@ -240,6 +256,7 @@ const convertEgg = (egg: Egg): Blueprint => {
         ],
       },
     },
+    ...(denylist.length > 0 ? { files: { denylist } } : {}),
     ...(features.length > 0 ? { features } : {}),
   }
 
@@ -0,0 +1 @@
+ALTER TABLE "blueprints" ADD COLUMN IF NOT EXISTS "services" jsonb;
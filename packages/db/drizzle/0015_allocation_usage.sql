@@ -0,0 +1,3 @@
+ALTER TABLE "node_allocations"
+    ADD COLUMN IF NOT EXISTS "bound_server_id" uuid,
+    ADD COLUMN IF NOT EXISTS "usage_reported_at" timestamp with time zone;
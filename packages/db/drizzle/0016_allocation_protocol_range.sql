@@ -0,0 +1,3 @@
+ALTER TABLE "node_allocations"
+    ADD COLUMN IF NOT EXISTS "port_end" integer,
+    ADD COLUMN IF NOT EXISTS "protocol" text NOT NULL DEFAULT 'tcp';
@@ -0,0 +1 @@
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "host_network" boolean NOT NULL DEFAULT false;
@@ -0,0 +1 @@
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "proxy_rules" jsonb;
@@ -0,0 +1 @@
+ALTER TABLE "server_subusers" ADD COLUMN IF NOT EXISTS "sftp_paths" jsonb;
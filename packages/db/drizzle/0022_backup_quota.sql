@@ -0,0 +1,3 @@
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "backup_usage" jsonb;--> statement-breakpoint
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "backup_quota_mb" bigint;--> statement-breakpoint
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "backup_quota_policy" text DEFAULT 'reject' NOT NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "transfer_policy" jsonb;--> statement-breakpoint
+ALTER TABLE "server_transfers" ADD COLUMN IF NOT EXISTS "urgent" boolean DEFAULT false NOT NULL;--> statement-breakpoint
+ALTER TABLE "server_transfers" ADD COLUMN IF NOT EXISTS "started_at" timestamp with time zone;--> statement-breakpoint
+UPDATE "server_transfers" SET "started_at" = "created_at" WHERE "started_at" IS NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "console_history_lines" integer;--> statement-breakpoint
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "console_buffer" integer;
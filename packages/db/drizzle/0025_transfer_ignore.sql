@@ -0,0 +1 @@
+ALTER TABLE "server_transfers" ADD COLUMN IF NOT EXISTS "ignore" jsonb;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS "server_replications" (
+    "server_id" uuid PRIMARY KEY REFERENCES "servers"("id") ON DELETE CASCADE,
+    "standby_node_id" uuid NOT NULL REFERENCES "nodes"("id") ON DELETE RESTRICT,
+    "standby_allocation_id" uuid NOT NULL,
+    "interval_minutes" integer NOT NULL DEFAULT 60,
+    "last_synced_at" timestamptz,
+    "last_attempt_at" timestamptz,
+    "last_error" text,
+    "created_at" timestamptz NOT NULL DEFAULT now()
+);
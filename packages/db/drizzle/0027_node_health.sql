@@ -0,0 +1,3 @@
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "daemon_version" text;--> statement-breakpoint
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "resources" jsonb;--> statement-breakpoint
+ALTER TABLE "nodes" ADD COLUMN IF NOT EXISTS "health" jsonb;
@@ -0,0 +1 @@
+ALTER TABLE "backups" ADD COLUMN IF NOT EXISTS "base" text;
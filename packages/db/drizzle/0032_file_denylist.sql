@@ -0,0 +1,2 @@
+ALTER TABLE "blueprints" ADD COLUMN IF NOT EXISTS "files" jsonb;--> statement-breakpoint
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "file_denylist" jsonb;
@@ -0,0 +1,2 @@
+ALTER TABLE "blueprints" ADD COLUMN IF NOT EXISTS "log_pruning" jsonb;--> statement-breakpoint
+ALTER TABLE "servers" ADD COLUMN IF NOT EXISTS "log_pruning" jsonb;
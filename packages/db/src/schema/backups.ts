@@ -44,7 +44,8 @@ export const backupDestinationsTable = pgTable("backup_destinations", {
  * Backup record. `storage` indicates where the bytes live; `s3ObjectKey` is
  * populated only for S3-backed backups. `state` mirrors the lifecycle
  * (pending → ready / failed) so partially-completed jobs are visible in the
- * panel.
+ * panel. `base` names the backup an incremental one builds on (null for a
+ * full backup); restoring it needs every backup down that chain.
  */
 export const backupsTable = pgTable(
   "backups",
@@ -67,6 +68,7 @@ export const backupsTable = pgTable(
     failureCode: text("failure_code"),
     s3ObjectKey: text("s3_object_key"),
     locked: boolean("locked").notNull().default(false),
+    base: text("base"),
     completedAt: timestamp("completed_at", { withTimezone: true }),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
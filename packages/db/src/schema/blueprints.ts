@@ -4,8 +4,14 @@ import { jsonb, pgTable, text, timestamp, uuid } from "drizzle-orm/pg-core"
 import type {
   Blueprint,
   BlueprintConfigFile,
+  BlueprintFiles,
+  BlueprintInstallInterpreter,
   BlueprintLifecycle,
   BlueprintLocalizableText,
+  BlueprintLogPruning,
+  BlueprintMemoryOverhead,
+  BlueprintService,
+  BlueprintSidecar,
   BlueprintVariable,
 } from "@workspace/shared/blueprint.types"
 
@@ -31,7 +37,16 @@ export const blueprintsTable = pgTable("blueprints", {
   installImage: text("install_image").notNull(),
   installEntrypoint: text("install_entrypoint").notNull(),
   installScript: text("install_script").notNull(),
+  installInterpreter:
+    text("install_interpreter").$type<BlueprintInstallInterpreter>(),
   lifecycle: jsonb("lifecycle").$type<BlueprintLifecycle>().notNull(),
+  memoryOverhead: jsonb("memory_overhead").$type<BlueprintMemoryOverhead>(),
+  sidecars: jsonb("sidecars").$type<BlueprintSidecar[]>(),
+  services: jsonb("services").$type<BlueprintService[]>(),
+  playerCommands:
+    jsonb("player_commands").$type<Blueprint["playerCommands"]>(),
+  files: jsonb("files").$type<BlueprintFiles>(),
+  logPruning: jsonb("log_pruning").$type<BlueprintLogPruning>(),
   features: jsonb("features").$type<Record<string, string[]>>(),
   createdAt: timestamp("created_at", { withTimezone: true })
     .notNull()
@@ -3,6 +3,7 @@ import {
   bigint,
   index,
   integer,
+  jsonb,
   pgTable,
   text,
   timestamp,
@@ -10,6 +11,66 @@ import {
   uuid,
 } from "drizzle-orm/pg-core"
 
+export type NodeProxyRule = {
+  host: string
+  backend: string
+  serverId?: string
+}
+
+/**
+ * What the node's daemon build reports it can do, refreshed on every
+ * heartbeat. `features` gates endpoints added after the baseline; see
+ * the daemon's `capability` package for the strings.
+ */
+export type NodeCapabilities = {
+  messages: { current: number; supported: number[] }
+  backupDrivers: string[]
+  transferModes: string[]
+  scheduleTasks: string[]
+  features: string[]
+}
+
+/**
+ * Bytes of backup storage on the node, in total and per server id, as
+ * measured by the daemon on its last heartbeat.
+ */
+export type NodeBackupUsage = {
+  totalBytes: number
+  servers: Record<string, number>
+}
+
+/**
+ * The node's transfer throttling as of its last heartbeat. `opensAt`
+ * is set only while the window is closed.
+ */
+export type NodeTransferPolicy = {
+  bandwidthMbps: number
+  window?: string
+  windowOpen: boolean
+  opensAt?: string
+}
+
+/**
+ * The host's totals as the daemon measured them on its last heartbeat;
+ * zero when it couldn't tell.
+ */
+export type NodeResources = {
+  cpus: number
+  memoryMb: number
+  diskMb: number
+  diskFreeMb: number
+}
+
+/**
+ * The node's health as of its last heartbeat. `clockSkewSeconds` is the
+ * panel's clock minus the node's; signed requests and user tokens fail
+ * once it drifts too far.
+ */
+export type NodeHealth = {
+  docker: boolean
+  clockSkewSeconds: number
+}
+
 /**
  * A node is a host machine running `stellar-daemon`. Capacity columns are
  * advisory limits enforced when scheduling new servers — actual use is
@@ -30,6 +91,25 @@ export const nodesTable = pgTable(
     memoryTotalMb: bigint("memory_total_mb", { mode: "number" }).notNull(),
     diskTotalMb: bigint("disk_total_mb", { mode: "number" }).notNull(),
     connectedAt: timestamp("connected_at", { withTimezone: true }),
+    /**
+     * Hostname → backend routes for the daemon's shared-port proxy.
+     * Pushed to the daemon whenever they change; the daemon persists
+     * its own copy so it routes across restarts.
+     */
+    proxyRules: jsonb("proxy_rules").$type<NodeProxyRule[]>(),
+    /**
+     * Null until the daemon's first heartbeat, and for daemons that
+     * predate capability reporting; treated as "baseline only".
+     */
+    capabilities: jsonb("capabilities").$type<NodeCapabilities>(),
+    /** Null until a daemon that reports backup usage heartbeats. */
+    backupUsage: jsonb("backup_usage").$type<NodeBackupUsage>(),
+    /** Null for daemons without transfer windows: always open. */
+    transferPolicy: jsonb("transfer_policy").$type<NodeTransferPolicy>(),
+    /** Null until a daemon that reports them heartbeats. */
+    daemonVersion: text("daemon_version"),
+    resources: jsonb("resources").$type<NodeResources>(),
+    health: jsonb("health").$type<NodeHealth>(),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
       .defaultNow(),
@@ -52,8 +132,23 @@ export const nodeAllocationsTable = pgTable(
       .references(() => nodesTable.id, { onDelete: "cascade" }),
     ip: text("ip").notNull(),
     port: integer("port").notNull(),
+    /**
+     * Inclusive last port when the row reserves a contiguous block
+     * (`port`..`portEnd`) as one allocation; null for a single port.
+     */
+    portEnd: integer("port_end"),
+    protocol: text("protocol", { enum: ["tcp", "udp", "both"] })
+      .notNull()
+      .default("tcp"),
     alias: text("alias"),
     serverId: uuid("server_id"),
+    /**
+     * Server whose container the daemon last saw publishing this port
+     * (null when unbound), and when the node last reported. A running
+     * server whose allocation isn't bound by it is stale.
+     */
+    boundServerId: uuid("bound_server_id"),
+    usageReportedAt: timestamp("usage_reported_at", { withTimezone: true }),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
       .defaultNow(),
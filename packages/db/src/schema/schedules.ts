@@ -55,7 +55,7 @@ export const scheduleTasksTable = pgTable(
       .references(() => schedulesTable.id, { onDelete: "cascade" }),
     sortOrder: integer("sort_order").notNull(),
     action: text("action", {
-      enum: ["power", "command", "backup"],
+      enum: ["power", "command", "macro", "backup", "update", "prune_logs"],
     }).notNull(),
     payload: jsonb("payload").$type<Record<string, string | number | boolean>>(),
     delaySeconds: integer("delay_seconds").notNull().default(0),
@@ -12,6 +12,7 @@ import {
   uuid,
 } from "drizzle-orm/pg-core"
 
+import type { BlueprintLogPruning } from "@workspace/shared/blueprint.types"
 import type { ServerLifecycleState } from "@workspace/shared/events.types"
 
 import { usersTable } from "@workspace/db/schema/auth"
@@ -21,6 +22,51 @@ import {
   nodesTable,
 } from "@workspace/db/schema/nodes"
 
+/**
+ * Idle auto-shutdown settings. The daemon stops the server once every
+ * listed signal has been quiet for `minutes`: no connections on its
+ * ports (`players`), no console output (`console`), or CPU below
+ * `cpuPercent` (`cpu`). `startOnDemand` lets a connection to the
+ * stopped server start it again; `wakeMessage`, when set, is what
+ * Minecraft clients see in the server list meanwhile.
+ */
+export type ServerIdlePolicy = {
+  minutes: number
+  signals: Array<"players" | "console" | "cpu">
+  cpuPercent: number
+  startOnDemand: boolean
+  wakeMessage?: string | null
+}
+
+/**
+ * A template to install the server from instead of running the
+ * blueprint's install script: the daemon downloads `url` (then each of
+ * `mirrors` until one matches), checks it against `checksum`
+ * ("sha256:<hex>" or "sha1:<hex>") and extracts it over the server's
+ * files.
+ */
+export type ServerInstallArchive = {
+  url: string
+  checksum: string
+  mirrors?: string[]
+}
+
+/**
+ * An /etc/hosts entry for the server's container: `hostname` resolves
+ * to `ip`, or to wherever the server `serverId` is reachable from this
+ * one (see lib/ExtraHosts in the API).
+ */
+export type ServerExtraHost =
+  | { hostname: string; ip: string }
+  | { hostname: string; serverId: string }
+
+/**
+ * The server's log pruning policy: its fields override the blueprint's,
+ * and `enabled: false` turns pruning off whatever the blueprint says.
+ * With no blueprint policy the server's is used on its own.
+ */
+export type ServerLogPruning = BlueprintLogPruning & { enabled?: boolean }
+
 /**
  * A managed Docker container instance. Status mirrors the lifecycle state
  * machine in `@workspace/shared/events.types`.
@@ -67,6 +113,16 @@ export const serversTable = pgTable(
      * rejects creates past it. Mirrors the upstream daemon's per-server backup cap.
      */
     backupLimit: integer("backup_limit").notNull().default(5),
+    /**
+     * Disk the server's backups may use on the node, in MB; null is
+     * unlimited. Past it a new backup is rejected, or with "rotate" the
+     * oldest unlocked backups make room for it. Enforced by the daemon.
+     */
+    backupQuotaMb: bigint("backup_quota_mb", { mode: "number" }),
+    backupQuotaPolicy: text("backup_quota_policy")
+      .$type<"reject" | "rotate">()
+      .notNull()
+      .default("reject"),
     status: text("status").$type<ServerLifecycleState>().notNull().default("offline"),
     /**
      * Install lifecycle is tracked separately from the live container
@@ -77,7 +133,35 @@ export const serversTable = pgTable(
       .$type<"pending" | "running" | "succeeded" | "failed">()
       .notNull()
       .default("pending"),
+    /** Null installs with the blueprint's script. */
+    installArchive: jsonb("install_archive").$type<ServerInstallArchive>(),
     suspended: boolean("suspended").notNull().default(false),
+    /**
+     * Run in the host's network namespace instead of behind Docker port
+     * bindings. Only honoured on nodes whose daemon allowlists the
+     * server; the game must bind its allocation ports itself.
+     */
+    hostNetwork: boolean("host_network").notNull().default(false),
+    /** Null means the server never idles out. */
+    idlePolicy: jsonb("idle_policy").$type<ServerIdlePolicy>(),
+    /**
+     * Console replay depth in lines and per-client frame buffer on the
+     * daemon; null uses the node's configured defaults.
+     */
+    consoleHistoryLines: integer("console_history_lines"),
+    consoleBuffer: integer("console_buffer"),
+    /**
+     * Extra /etc/hosts entries; applied when the container is next
+     * created, i.e. on restart.
+     */
+    extraHosts: jsonb("extra_hosts").$type<ServerExtraHost[]>(),
+    /**
+     * Path patterns the file manager and SFTP refuse for this server, on
+     * top of the blueprint's and the node's.
+     */
+    fileDenylist: jsonb("file_denylist").$type<string[]>(),
+    /** Overrides the blueprint's log pruning; see ServerLogPruning. */
+    logPruning: jsonb("log_pruning").$type<ServerLogPruning>(),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
       .defaultNow(),
@@ -131,7 +215,9 @@ export const serverVariablesTable = pgTable(
 
 /**
  * Subuser permissions for a server. `permissions` holds the better-auth
- * statement strings (`console.read`, `files.write`, …).
+ * statement strings (`console.read`, `files.write`, …). `sftpPaths`
+ * limits the subuser's SFTP access to those directories (null = the
+ * whole server).
  */
 export const serverSubusersTable = pgTable(
   "server_subusers",
@@ -146,6 +232,7 @@ export const serverSubusersTable = pgTable(
       .notNull()
       .references(() => usersTable.id, { onDelete: "cascade" }),
     permissions: jsonb("permissions").$type<string[]>().notNull(),
+    sftpPaths: jsonb("sftp_paths").$type<string[]>(),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
       .defaultNow(),
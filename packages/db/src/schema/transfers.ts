@@ -1,6 +1,9 @@
 import { sql } from "drizzle-orm"
 import {
+  boolean,
   index,
+  integer,
+  jsonb,
   pgTable,
   text,
   timestamp,
@@ -34,14 +37,26 @@ export const serverTransfersTable = pgTable(
     targetAllocationId: uuid("target_allocation_id").notNull(),
     /** One-time HMAC token the source daemon presents when pushing to the target. */
     token: text("token").notNull(),
+    /**
+     * `queued` holds a non-urgent transfer until both nodes' transfer
+     * windows are open.
+     */
     status: text("status")
-      .$type<"pending" | "running" | "completed" | "failed">()
+      .$type<"pending" | "queued" | "running" | "completed" | "failed">()
       .notNull()
       .default("pending"),
+    urgent: boolean("urgent").notNull().default(false),
+    /**
+     * Patterns for paths the source leaves behind, on top of the
+     * server's `.stellarignore` files. Null sends everything.
+     */
+    ignore: jsonb("ignore").$type<string[]>(),
     error: text("error"),
     createdAt: timestamp("created_at", { withTimezone: true })
       .notNull()
       .defaultNow(),
+    /** When the push last started; the timeout runs from here. */
+    startedAt: timestamp("started_at", { withTimezone: true }),
     completedAt: timestamp("completed_at", { withTimezone: true }),
   },
   (table) => [
@@ -52,3 +67,30 @@ export const serverTransfersTable = pgTable(
 
 export type ServerTransferRow = typeof serverTransfersTable.$inferSelect
 export type ServerTransferInsert = typeof serverTransfersTable.$inferInsert
+
+/**
+ * A server's warm standby: the ReplicationQueue pushes the server's
+ * files to the standby node every `intervalMinutes` with the transfer
+ * machinery (delta pushes, so only changed files move), and promoting
+ * the standby switches the server over to it like a finished transfer.
+ * The standby allocation is held for the server meanwhile.
+ */
+export const serverReplicationsTable = pgTable("server_replications", {
+  serverId: uuid("server_id")
+    .primaryKey()
+    .references(() => serversTable.id, { onDelete: "cascade" }),
+  standbyNodeId: uuid("standby_node_id")
+    .notNull()
+    .references(() => nodesTable.id, { onDelete: "restrict" }),
+  standbyAllocationId: uuid("standby_allocation_id").notNull(),
+  intervalMinutes: integer("interval_minutes").notNull().default(60),
+  /** When the standby last received a complete copy. */
+  lastSyncedAt: timestamp("last_synced_at", { withTimezone: true }),
+  lastAttemptAt: timestamp("last_attempt_at", { withTimezone: true }),
+  lastError: text("last_error"),
+  createdAt: timestamp("created_at", { withTimezone: true })
+    .notNull()
+    .defaultNow(),
+})
+
+export type ServerReplicationRow = typeof serverReplicationsTable.$inferSelect
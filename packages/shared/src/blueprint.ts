@@ -111,10 +111,81 @@ export const blueprintLifecycleSchema = z.object({
   crashDetection: crashLifecycleSchema,
 })
 
+const memoryOverheadSchema = z.discriminatedUnion("type", [
+  z.object({ type: z.literal("absolute"), mb: z.number().int().min(0) }),
+  z.object({
+    type: z.literal("percent"),
+    percent: z.number().min(0).max(100),
+  }),
+])
+
+const sidecarSchema = z.object({
+  name: z
+    .string()
+    .min(1)
+    .max(32)
+    .regex(/^[a-z0-9][a-z0-9_.-]*$/, {
+      message: "Sidecar names must be lowercase alphanumeric",
+    }),
+  image: z.string().min(1),
+  command: z.array(z.string()).optional(),
+  environment: z.record(z.string(), z.string()).optional(),
+  memoryLimitMb: z.number().int().min(0).optional(),
+})
+
+const serviceSchema = sidecarSchema.extend({
+  dependsOn: z.array(z.string()).optional(),
+  volumes: z
+    .array(
+      z.object({
+        name: z.string().regex(/^[a-z0-9][a-z0-9_.-]*$/),
+        path: z.string().startsWith("/"),
+      })
+    )
+    .optional(),
+  console: z.boolean().optional(),
+})
+
 const installSchema = z.object({
   image: z.string().min(1),
   entrypoint: z.string().min(1),
   script: z.string().min(1),
+  interpreter: z.enum(["bash", "sh", "ash", "python"]).optional(),
+})
+
+export const playerActionSchema = z.enum([
+  "whitelist.add",
+  "whitelist.remove",
+  "op",
+  "deop",
+  "kick",
+  "ban",
+  "pardon",
+])
+
+/** One denylist or hidden-list path pattern; see `files` below. */
+export const filePatternSchema = z.string().trim().min(1).max(256)
+
+/**
+ * A log pruning glob: under a literal directory (`logs/**`, not `**` or
+ * `*.log`), so a policy can never reach the whole server.
+ */
+const logGlobSchema = filePatternSchema.refine(
+  (g) => {
+    const segs = g.split("/").filter((s) => s !== "" && s !== ".")
+    return segs.length >= 2 && segs[0] !== ".." && !/[*?[\\]/.test(segs[0]!)
+  },
+  { message: "Log globs must start with a directory name, like logs/**" }
+)
+
+/**
+ * A log pruning policy; see `logPruning` below. Globs are relative to
+ * the server root, `**` spanning directories.
+ */
+export const logPruningSchema = z.object({
+  globs: z.array(logGlobSchema).min(1).max(32).optional(),
+  maxAgeDays: z.number().int().min(1).max(3650).optional(),
+  maxTotalMb: z.number().int().min(1).optional(),
 })
 
 /**
@@ -138,6 +209,81 @@ export const blueprintSchema = z.object({
   variables: z.array(variableSchema),
   install: installSchema,
   lifecycle: blueprintLifecycleSchema,
+  /**
+   * Container memory granted on top of the server's memory limit. JVM
+   * servers size the heap from SERVER_MEMORY, so the container needs
+   * headroom for metaspace, thread stacks and direct buffers or the
+   * kernel OOM-kills it at the limit.
+   */
+  memoryOverhead: memoryOverheadSchema.optional(),
+  /**
+   * Helper containers started and stopped with the server. They share
+   * its network namespace and data mount, and their usage is folded
+   * into the server's stats.
+   */
+  sidecars: z
+    .array(sidecarSchema)
+    .refine((s) => new Set(s.map((c) => c.name)).size === s.length, {
+      message: "Sidecar names must be unique",
+    })
+    .optional(),
+  /**
+   * Multi-container stack: named services started before the main
+   * container in `dependsOn` order on a per-server network (the main
+   * container is reachable as `server`), with named volumes shared by
+   * name and optional console output tagged `[name]`.
+   */
+  services: z
+    .array(serviceSchema)
+    .superRefine((services, ctx) => {
+      const names = new Set(services.map((s) => s.name))
+      if (names.size !== services.length) {
+        ctx.addIssue({ code: "custom", message: "Service names must be unique" })
+      }
+      if (names.has("server")) {
+        ctx.addIssue({
+          code: "custom",
+          message: "`server` is reserved for the main container",
+        })
+      }
+      for (const s of services) {
+        for (const dep of s.dependsOn ?? []) {
+          if (!names.has(dep)) {
+            ctx.addIssue({
+              code: "custom",
+              message: `Service ${s.name} depends on unknown service ${dep}`,
+            })
+          }
+        }
+      }
+    })
+    .optional(),
+  /**
+   * Console command templates for structured player administration
+   * (`POST /players/:action`). `{{player}}` and `{{reason}}` are
+   * substituted by the daemon; actions without a template are
+   * reported as unsupported for this game.
+   */
+  playerCommands: z
+    .partialRecord(playerActionSchema, z.string().min(1).max(256))
+    .optional(),
+  /**
+   * Path patterns the file manager and SFTP refuse (`denylist`) or
+   * leave out of listings (`hidden`), e.g. `.git/` or `*.db`. Merged
+   * with the node's defaults and the server's own denylist.
+   */
+  files: z
+    .object({
+      denylist: z.array(filePatternSchema).max(128).optional(),
+      hidden: z.array(filePatternSchema).max(128).optional(),
+    })
+    .optional(),
+  /**
+   * Log pruning for this blueprint's servers, e.g. `{ "maxAgeDays": 14 }`.
+   * Servers can override or disable it; `prune_logs` schedule tasks
+   * run it.
+   */
+  logPruning: logPruningSchema.optional(),
   /**
    * Feature flags. Accepted as either a flat string list (legacy
    * standard) or a record mapping feature name → console patterns
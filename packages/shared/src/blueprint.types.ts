@@ -40,8 +40,16 @@ export type BlueprintInstall = {
   image: string
   entrypoint: string
   script: string
+  /**
+   * Runs the script with this interpreter instead of exec'ing it through
+   * `entrypoint`, so it needs no shebang the image can't honour. Nodes
+   * may allow only some of them.
+   */
+  interpreter?: BlueprintInstallInterpreter
 }
 
+export type BlueprintInstallInterpreter = "bash" | "sh" | "ash" | "python"
+
 /**
  * A regex/substring match descriptor used by `console` lifecycle probes.
  */
@@ -125,6 +133,76 @@ export type BlueprintLifecycle = {
   crashDetection: BlueprintCrashLifecycle
 }
 
+/**
+ * Extra container memory on top of the server's memory limit, either a
+ * fixed amount or a percentage of the limit. SERVER_MEMORY (and so the
+ * JVM heap) stays at the limit; only the Docker cgroup limit grows.
+ */
+export type BlueprintMemoryOverhead =
+  | { type: "absolute"; mb: number }
+  | { type: "percent"; percent: number }
+
+/**
+ * Helper container run alongside the server (metrics exporter, proxy,
+ * …). Joins the server's network namespace and mounts its data
+ * directory; inherits the server environment, overlaid by `environment`.
+ */
+export type BlueprintSidecar = {
+  name: string
+  image: string
+  command?: string[]
+  environment?: Record<string, string>
+  memoryLimitMb?: number
+}
+
+/**
+ * One container of a multi-container stack. Started before the main
+ * container, after every service in `dependsOn`, on a per-server network
+ * where it resolves as `name` (and the main container as `server`).
+ * Volumes with the same `name` are shared between services.
+ */
+export type BlueprintService = BlueprintSidecar & {
+  dependsOn?: string[]
+  volumes?: { name: string; path: string }[]
+  console?: boolean
+}
+
+/**
+ * Game administration actions the daemon can translate to console
+ * commands. Keyed by action; values are command templates with
+ * `{{player}}` and `{{reason}}` placeholders.
+ */
+export type BlueprintPlayerAction =
+  | "whitelist.add"
+  | "whitelist.remove"
+  | "op"
+  | "deop"
+  | "kick"
+  | "ban"
+  | "pardon"
+
+/**
+ * Path patterns (`.git/`, `*.db`, `/secrets`) for the file manager and
+ * SFTP, merged with the node's defaults. `denylist` paths are refused
+ * outright; `hidden` ones are only left out of listings.
+ */
+export type BlueprintFiles = {
+  denylist?: string[]
+  hidden?: string[]
+}
+
+/**
+ * Log pruning for servers of this blueprint, run by `prune_logs`
+ * schedule tasks: files matching `globs` (default `logs/**` and
+ * `crash-reports/**`) older than `maxAgeDays` go, then the oldest
+ * until they fit in `maxTotalMb`. Omitted limits don't apply.
+ */
+export type BlueprintLogPruning = {
+  globs?: string[]
+  maxAgeDays?: number
+  maxTotalMb?: number
+}
+
 /**
  * A blueprint is an admin-authored JSON document describing how to provision
  * and run one class of server (a Minecraft server, an FTP daemon, etc.).
@@ -141,5 +219,11 @@ export type Blueprint = {
   variables: BlueprintVariable[]
   install: BlueprintInstall
   lifecycle: BlueprintLifecycle
+  memoryOverhead?: BlueprintMemoryOverhead
+  sidecars?: BlueprintSidecar[]
+  services?: BlueprintService[]
+  playerCommands?: Partial<Record<BlueprintPlayerAction, string>>
+  files?: BlueprintFiles
+  logPruning?: BlueprintLogPruning
   features?: Record<string, string[]>
 }
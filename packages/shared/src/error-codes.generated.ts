@@ -2,14 +2,22 @@
 // Run `pnpm --filter @workspace/shared codegen:errors` to regenerate.
 
 export type ErrorCode =
+  | "allocations.overlap"
+  | "auth.clock_skew"
   | "auth.login.email_unverified"
   | "auth.login.invalid_credentials"
   | "auth.session.expired"
   | "auth.session.invalid"
   | "auth.signup.disabled"
   | "auth.signup.email_taken"
+  | "backups.base_missing"
+  | "backups.exists"
+  | "backups.has_dependents"
+  | "backups.invalid_archive"
   | "backups.locked"
+  | "backups.no_manifest"
   | "backups.not_found"
+  | "backups.quota_exceeded"
   | "backups.s3_credentials_missing"
   | "backups.upload_failed"
   | "blueprints.invalid_image"
@@ -17,11 +25,17 @@ export type ErrorCode =
   | "blueprints.parse.invalid_json"
   | "blueprints.parse.schema_version_unsupported"
   | "blueprints.parse.unknown_field"
+  | "files.blocked"
+  | "files.busy"
   | "files.decompress_failed"
+  | "files.locked"
   | "files.not_found"
   | "files.path_outside_jail"
+  | "files.quota_exceeded"
   | "files.read_only"
+  | "files.timeout"
   | "files.too_large"
+  | "files.upload_failed"
   | "instances.nested_not_allowed"
   | "instances.pool_exhausted"
   | "internal.unexpected"
@@ -30,9 +44,21 @@ export type ErrorCode =
   | "nodes.pair.token_already_claimed"
   | "nodes.pair.token_expired"
   | "nodes.pair.token_invalid"
+  | "nodes.sftp.ban_not_found"
+  | "nodes.sftp.session_not_found"
   | "nodes.unreachable"
+  | "nodes.unsupported_feature"
+  | "nodes.update.bad_signature"
+  | "nodes.update.bad_version"
+  | "nodes.update.failed"
+  | "nodes.update.in_progress"
+  | "nodes.update.no_previous"
   | "permissions.denied"
   | "rate_limit.exceeded"
+  | "replication.busy"
+  | "replication.not_configured"
+  | "replication.not_synced"
+  | "replication.same_node"
   | "schedules.cron_invalid"
   | "schedules.not_found"
   | "servers.action.already_running"
@@ -40,6 +66,8 @@ export type ErrorCode =
   | "servers.action.suspended"
   | "servers.allocations.limit_reached"
   | "servers.cannot_remove_primary_allocation"
+  | "servers.command.bad_pattern"
+  | "servers.command.send_failed"
   | "servers.create.allocation_unavailable"
   | "servers.create.no_free_allocation"
   | "servers.create.node_at_capacity"
@@ -50,9 +78,16 @@ export type ErrorCode =
   | "servers.lifecycle.crashed.console_match"
   | "servers.lifecycle.crashed.container_exit"
   | "servers.lifecycle.start_timeout"
+  | "servers.log_pruning_off"
   | "servers.not_found"
+  | "servers.power.in_progress"
+  | "servers.power.no_countdown"
+  | "servers.power.queue_full"
+  | "servers.rollout_not_found"
   | "servers.startup.invalid_docker_image"
   | "transfers.allocation_unavailable"
+  | "transfers.in_progress"
+  | "transfers.insecure"
   | "transfers.not_found"
   | "transfers.push_failed"
   | "transfers.same_node"
@@ -72,16 +107,27 @@ export type ErrorCode =
   | "validation.string.regex"
   | "validation.string.url"
   | "validation.string.uuid"
+  | "validation.variable.not_editable"
+  | "validation.variable.rule"
+  | "validation.variable.unknown"
 
 export const errorCodes = [
+  "allocations.overlap",
+  "auth.clock_skew",
   "auth.login.email_unverified",
   "auth.login.invalid_credentials",
   "auth.session.expired",
   "auth.session.invalid",
   "auth.signup.disabled",
   "auth.signup.email_taken",
+  "backups.base_missing",
+  "backups.exists",
+  "backups.has_dependents",
+  "backups.invalid_archive",
   "backups.locked",
+  "backups.no_manifest",
   "backups.not_found",
+  "backups.quota_exceeded",
   "backups.s3_credentials_missing",
   "backups.upload_failed",
   "blueprints.invalid_image",
@@ -89,11 +135,17 @@ export const errorCodes = [
   "blueprints.parse.invalid_json",
   "blueprints.parse.schema_version_unsupported",
   "blueprints.parse.unknown_field",
+  "files.blocked",
+  "files.busy",
   "files.decompress_failed",
+  "files.locked",
   "files.not_found",
   "files.path_outside_jail",
+  "files.quota_exceeded",
   "files.read_only",
+  "files.timeout",
   "files.too_large",
+  "files.upload_failed",
   "instances.nested_not_allowed",
   "instances.pool_exhausted",
   "internal.unexpected",
@@ -102,9 +154,21 @@ export const errorCodes = [
   "nodes.pair.token_already_claimed",
   "nodes.pair.token_expired",
   "nodes.pair.token_invalid",
+  "nodes.sftp.ban_not_found",
+  "nodes.sftp.session_not_found",
   "nodes.unreachable",
+  "nodes.unsupported_feature",
+  "nodes.update.bad_signature",
+  "nodes.update.bad_version",
+  "nodes.update.failed",
+  "nodes.update.in_progress",
+  "nodes.update.no_previous",
   "permissions.denied",
   "rate_limit.exceeded",
+  "replication.busy",
+  "replication.not_configured",
+  "replication.not_synced",
+  "replication.same_node",
   "schedules.cron_invalid",
   "schedules.not_found",
   "servers.action.already_running",
@@ -112,6 +176,8 @@ export const errorCodes = [
   "servers.action.suspended",
   "servers.allocations.limit_reached",
   "servers.cannot_remove_primary_allocation",
+  "servers.command.bad_pattern",
+  "servers.command.send_failed",
   "servers.create.allocation_unavailable",
   "servers.create.no_free_allocation",
   "servers.create.node_at_capacity",
@@ -122,9 +188,16 @@ export const errorCodes = [
   "servers.lifecycle.crashed.console_match",
   "servers.lifecycle.crashed.container_exit",
   "servers.lifecycle.start_timeout",
+  "servers.log_pruning_off",
   "servers.not_found",
+  "servers.power.in_progress",
+  "servers.power.no_countdown",
+  "servers.power.queue_full",
+  "servers.rollout_not_found",
   "servers.startup.invalid_docker_image",
   "transfers.allocation_unavailable",
+  "transfers.in_progress",
+  "transfers.insecure",
   "transfers.not_found",
   "transfers.push_failed",
   "transfers.same_node",
@@ -144,4 +217,7 @@ export const errorCodes = [
   "validation.string.regex",
   "validation.string.url",
   "validation.string.uuid",
+  "validation.variable.not_editable",
+  "validation.variable.rule",
+  "validation.variable.unknown",
 ] as const
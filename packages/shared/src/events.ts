@@ -56,14 +56,37 @@ export const statsPayloadSchema = z.object({
   disk_read_bytes: z.number().nonnegative(),
   disk_write_bytes: z.number().nonnegative(),
   uptime_ms: z.number().nonnegative().optional(),
+  console_dropped: z.number().int().nonnegative().optional(),
   state: lifecycleStateSchema,
 })
 
+/**
+ * Payload broadcast over the daemon WS as
+ * `{event:"backup progress", args:[…]}` while a backup runs.
+ */
+export const backupProgressPayloadSchema = z.object({
+  name: z.string(),
+  progress: z.number().min(0).max(100),
+  bytesProcessed: z.number().nonnegative(),
+  totalBytes: z.number().nonnegative(),
+  etaSeconds: z.number().int(),
+})
+
+/**
+ * Frame schema version this codebase understands. The daemon stamps
+ * every frame with `v`; bump both sides together when an existing
+ * event's args change shape.
+ */
+export const WS_SCHEMA_VERSION = 1
+
 /**
  * Envelope schema for every frame on the per-server daemon WebSocket.
- * Note: `{event, args}` discriminated by `event`.
+ * Note: `{v, event, args}` discriminated by `event`. `v` is absent on
+ * frames from daemons that predate versioning.
  */
 export const wsEnvelopeSchema = z.object({
+  v: z.number().int().positive().optional(),
   event: z.string(),
   args: z.array(z.unknown()).default([]),
+  seq: z.number().int().nonnegative().optional(),
 })
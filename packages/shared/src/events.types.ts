@@ -32,16 +32,34 @@ export type ServerStatsPayload = {
   disk_read_bytes: number
   disk_write_bytes: number
   uptime_ms?: number
+  /** Console frames dropped for slow clients since the server loaded. */
+  console_dropped?: number
   state: ServerLifecycleState
 }
 
+/**
+ * Payload of the `backup progress` event the daemon pushes about once a
+ * second while a backup is being written. `progress` is a percentage;
+ * `etaSeconds` is -1 until the daemon has a rate to extrapolate.
+ */
+export type BackupProgressPayload = {
+  name: string
+  progress: number
+  bytesProcessed: number
+  totalBytes: number
+  etaSeconds: number
+}
+
 /**
  * Per-server WebSocket envelope. Every frame the daemon sends or the
- * browser sends fits this shape; `event` is the discriminator.
+ * browser sends fits this shape; `event` is the discriminator and `v`
+ * the frame schema version.
  */
 export type WsEnvelope<E extends string = string, A = unknown[]> = {
+  v?: number
   event: E
   args: A
+  seq?: number
 }
 
 /**
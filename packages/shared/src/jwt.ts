@@ -15,6 +15,7 @@ const daemonJwtScopeSchema = z.enum([
   "backup.write",
   "transfer.source",
   "transfer.target",
+  "admin.exec",
 ])
 
 /**
@@ -17,6 +17,7 @@ export type DaemonJwtScope =
   | "backup.write"
   | "transfer.source"
   | "transfer.target"
+  | "admin.exec"
 
 /**
  * Claims carried by every JWT minted by the API for direct daemon access.
@@ -38,6 +39,11 @@ export type DaemonJwtClaims = {
   exp: number
   /** Token id — single-use guard for SFTP password tokens. */
   jti: string
+  /**
+   * SFTP only: directories the session is limited to, for subusers with
+   * restricted SFTP access. Absent means the whole server.
+   */
+  paths?: string[]
 }
 
 /**